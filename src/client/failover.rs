@@ -0,0 +1,385 @@
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use hyper::body::Incoming;
+use log::debug;
+
+#[cfg(feature = "grpc")]
+use std::sync::Arc;
+
+#[cfg(feature = "grpc")]
+use crate::client::grpc::interceptor::NetGrpcInterceptor;
+use crate::{
+    client::native::{
+        GrpcStreamHandle, HttpDownloadSink, IClient, IGrpcClient, IHttpClient, IStreamClient,
+    },
+    types::{
+        DartCallback,
+        config::NetConfig,
+        error::{NetError, NetResultStatus},
+        native::request::{NetHttpHeaderRef, NetHttpRetryConfig, NetRequestHttp},
+        response::{
+            NetGrpcConnectivity, NetResponseEndpoint, NetResponseGrpcUnary, NetResponseHttp,
+            NetResponseKind,
+        },
+    },
+    utils::{Utils, buffer::StreamEncoding, subscribers::NetSubscription},
+};
+
+/// How long a transport stays on a fallback before trying the primary url again
+const RECOVERY_AFTER: Duration = Duration::from_secs(30);
+
+/// gRPC `UNAVAILABLE`; any other server status means the endpoint is up
+const GRPC_UNAVAILABLE: i32 = 14;
+
+/// Ordered endpoints of one transport, one client per url. A call moves on to the next
+/// endpoint when one is unreachable, which for HTTP can also mean the connection broke
+/// after the request was written; so only idempotent requests, or those with an
+/// `Idempotency-Key` header, fail over, the rest get the error of the first endpoint tried
+struct Endpoints<C: ?Sized> {
+    clients: Vec<Box<C>>,
+    active: AtomicUsize,
+    left_primary: Mutex<Option<Instant>>,
+    callback: DartCallback,
+}
+
+impl<C: IClient + ?Sized> Endpoints<C> {
+    fn new(
+        primary: Box<C>,
+        fallbacks: Vec<NetConfig>,
+        create: fn(NetConfig) -> Result<Box<C>, NetError>,
+        callback: DartCallback,
+    ) -> Result<Self, NetError> {
+        let mut clients = vec![primary];
+        for config in fallbacks {
+            clients.push(create(config)?);
+        }
+        Ok(Self {
+            clients,
+            active: AtomicUsize::new(0),
+            left_primary: Mutex::new(None),
+            callback,
+        })
+    }
+
+    fn active(&self) -> &C {
+        &self.clients[self.active.load(Ordering::Acquire)]
+    }
+
+    /// Active endpoint first, then the rest in configured order. once `RECOVERY_AFTER` has
+    /// passed on a fallback the primary is probed first.
+    fn order(&self) -> Vec<usize> {
+        let active = self.active.load(Ordering::Acquire);
+        let recover = active != 0
+            && self
+                .left_primary
+                .lock()
+                .map(|left| left.is_some_and(|at| at.elapsed() >= RECOVERY_AFTER))
+                .unwrap_or(false);
+        let first = if recover { 0 } else { active };
+        std::iter::once(first)
+            .chain((0..self.clients.len()).filter(|index| *index != first))
+            .collect()
+    }
+
+    fn succeeded(&self, index: usize) {
+        let previous = self.active.swap(index, Ordering::AcqRel);
+        if let Ok(mut left) = self.left_primary.lock() {
+            *left = match index {
+                0 => None,
+                _ if previous == index => *left,
+                _ => Some(Instant::now()),
+            };
+        }
+        if previous == index {
+            return;
+        }
+        let url = self.clients[index].get_config().addr.url.clone();
+        debug!("failover: endpoint {} -> {} ({})", previous, index, url);
+        (self.callback)(NetResponseKind::EndpointChanged(NetResponseEndpoint::new(
+            index as u32,
+            url,
+        )));
+    }
+}
+
+/// Only errors that say the endpoint itself is unreachable move to the next one
+fn fails_over(error: &NetError) -> bool {
+    let unreachable = matches!(
+        error.status(),
+        NetResultStatus::ConnectionError
+            | NetResultStatus::SocketError
+            | NetResultStatus::TorNetError
+            | NetResultStatus::Http2ConctionFailed
+    );
+    unreachable
+        && error
+            .grpc_status()
+            .is_none_or(|status| status.code() == GRPC_UNAVAILABLE)
+}
+
+/// Runs `$call` against each endpoint in `Endpoints::order` until one is reachable, or only
+/// against the first when `$replay` is false
+macro_rules! with_failover {
+    ($endpoints:expr, |$index:ident, $client:ident| $call:expr) => {
+        with_failover!($endpoints, true, |$index, $client| $call)
+    };
+    ($endpoints:expr, $replay:expr, |$index:ident, $client:ident| $call:expr) => {{
+        let endpoints = &$endpoints;
+        let mut last_error = None;
+        let mut result = None;
+        for $index in endpoints.order() {
+            let $client = &endpoints.clients[$index];
+            match $call {
+                Ok(value) => {
+                    endpoints.succeeded($index);
+                    result = Some(Ok(value));
+                    break;
+                }
+                Err(e) if $replay && fails_over(&e) => last_error = Some(e),
+                Err(e) => {
+                    result = Some(Err(e));
+                    break;
+                }
+            }
+        }
+        result.unwrap_or_else(|| {
+            Err(last_error.unwrap_or_else(|| NetError::new(NetResultStatus::InternalError)))
+        })
+    }};
+}
+
+pub struct FailoverStreamClient {
+    endpoints: Endpoints<dyn IStreamClient>,
+}
+
+impl FailoverStreamClient {
+    pub fn new(
+        primary: Box<dyn IStreamClient>,
+        fallbacks: Vec<NetConfig>,
+        create: fn(NetConfig) -> Result<Box<dyn IStreamClient>, NetError>,
+        callback: DartCallback,
+    ) -> Result<Self, NetError> {
+        Ok(Self {
+            endpoints: Endpoints::new(primary, fallbacks, create, callback)?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl IClient for FailoverStreamClient {
+    async fn connect(&self) -> Result<(), NetError> {
+        with_failover!(self.endpoints, |_index, client| client.connect().await)
+    }
+
+    /// Config of the primary url
+    fn get_config(&self) -> &NetConfig {
+        self.endpoints.clients[0].get_config()
+    }
+}
+
+#[async_trait::async_trait]
+impl IStreamClient for FailoverStreamClient {
+    async fn send<'a>(&self, data: &'a [u8]) -> Result<(), NetError> {
+        self.endpoints.active().send(data).await
+    }
+
+    async fn subscribe(
+        &self,
+    ) -> Result<NetSubscription<Result<Option<Vec<u8>>, NetError>>, NetError> {
+        with_failover!(self.endpoints, |_index, client| client.subscribe().await)
+    }
+
+    async fn close(&self) {
+        for client in &self.endpoints.clients {
+            client.close().await;
+        }
+    }
+}
+
+pub struct FailoverGrpcClient {
+    endpoints: Endpoints<dyn IGrpcClient>,
+}
+
+impl FailoverGrpcClient {
+    pub fn new(
+        primary: Box<dyn IGrpcClient>,
+        fallbacks: Vec<NetConfig>,
+        create: fn(NetConfig) -> Result<Box<dyn IGrpcClient>, NetError>,
+        callback: DartCallback,
+    ) -> Result<Self, NetError> {
+        Ok(Self {
+            endpoints: Endpoints::new(primary, fallbacks, create, callback)?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl IClient for FailoverGrpcClient {
+    async fn connect(&self) -> Result<(), NetError> {
+        with_failover!(self.endpoints, |_index, client| client.connect().await)
+    }
+
+    /// Config of the primary url
+    fn get_config(&self) -> &NetConfig {
+        self.endpoints.clients[0].get_config()
+    }
+}
+
+#[async_trait::async_trait]
+impl IGrpcClient for FailoverGrpcClient {
+    async fn unary<'a>(
+        &self,
+        buffer: Bytes,
+        method_name: &'a str,
+        metadata: &[NetHttpHeaderRef<'a>],
+        deadline: Option<Duration>,
+        authority: Option<&'a str>,
+    ) -> Result<NetResponseGrpcUnary, NetError> {
+        with_failover!(self.endpoints, |_index, client| client
+            .unary(buffer.clone(), method_name, metadata, deadline, authority)
+            .await)
+    }
+
+    async fn stream<'a>(
+        &self,
+        buffer: Bytes,
+        method_name: &'a str,
+        metadata: &[NetHttpHeaderRef<'a>],
+        deadline: Option<Duration>,
+        authority: Option<&'a str>,
+    ) -> Result<GrpcStreamHandle, NetError> {
+        with_failover!(self.endpoints, |_index, client| client
+            .stream(buffer.clone(), method_name, metadata, deadline, authority)
+            .await)
+    }
+
+    /// Added to every endpoint, so calls keep them wherever they fail over to
+    #[cfg(feature = "grpc")]
+    fn add_interceptor(&self, interceptor: Arc<dyn NetGrpcInterceptor>) {
+        for client in &self.endpoints.clients {
+            client.add_interceptor(Arc::clone(&interceptor));
+        }
+    }
+
+    /// Of the endpoint calls go to
+    fn connectivity(&self) -> NetGrpcConnectivity {
+        self.endpoints.active().connectivity()
+    }
+
+    async fn close(&self) {
+        for client in &self.endpoints.clients {
+            client.close().await;
+        }
+    }
+}
+
+/// Requests on the primary url's origin are moved to the origin of the endpoint in use
+pub struct FailoverHttpClient {
+    endpoints: Endpoints<dyn IHttpClient>,
+}
+
+impl FailoverHttpClient {
+    pub fn new(
+        primary: Box<dyn IHttpClient>,
+        fallbacks: Vec<NetConfig>,
+        create: fn(NetConfig) -> Result<Box<dyn IHttpClient>, NetError>,
+        callback: DartCallback,
+    ) -> Result<Self, NetError> {
+        Ok(Self {
+            endpoints: Endpoints::new(primary, fallbacks, create, callback)?,
+        })
+    }
+
+    fn endpoint_url(&self, index: usize, url: &str) -> Result<String, NetError> {
+        if index == 0 {
+            return Ok(url.to_string());
+        }
+        Utils::rebase_url(
+            url,
+            &self.endpoints.clients[0].get_config().addr.url,
+            &self.endpoints.clients[index].get_config().addr.url,
+        )
+        .map_err(|e| NetError::new(e).with_url(url))
+    }
+}
+
+#[async_trait::async_trait]
+impl IClient for FailoverHttpClient {
+    async fn connect(&self) -> Result<(), NetError> {
+        with_failover!(self.endpoints, |_index, client| client.connect().await)
+    }
+
+    /// Config of the primary url
+    fn get_config(&self) -> &NetConfig {
+        self.endpoints.clients[0].get_config()
+    }
+}
+
+#[async_trait::async_trait]
+impl IHttpClient for FailoverHttpClient {
+    async fn send<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        encoding: StreamEncoding,
+        retry_config: &NetHttpRetryConfig<'a>,
+    ) -> Result<NetResponseHttp, NetError> {
+        let replay = NetRequestHttp::is_idempotent(method, headers);
+        with_failover!(self.endpoints, replay, |index, client| {
+            let url = self.endpoint_url(index, url)?;
+            client
+                .send(&url, method, body.clone(), headers, encoding, retry_config)
+                .await
+        })
+    }
+
+    async fn events<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        last_event_id: Option<&'a str>,
+    ) -> Result<Incoming, NetError> {
+        let replay = NetRequestHttp::is_idempotent(method, headers);
+        with_failover!(self.endpoints, replay, |index, client| {
+            let url = self.endpoint_url(index, url)?;
+            client
+                .events(&url, method, body.clone(), headers, last_event_id)
+                .await
+        })
+    }
+
+    async fn download<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        retry_config: &NetHttpRetryConfig<'a>,
+        sink: &HttpDownloadSink<'a>,
+    ) -> Result<NetResponseHttp, NetError> {
+        let replay = NetRequestHttp::is_idempotent(method, headers);
+        with_failover!(self.endpoints, replay, |index, client| {
+            let url = self.endpoint_url(index, url)?;
+            client
+                .download(&url, method, body.clone(), headers, retry_config, sink)
+                .await
+        })
+    }
+
+    async fn close(&self) {
+        for client in &self.endpoints.clients {
+            client.close().await;
+        }
+    }
+}
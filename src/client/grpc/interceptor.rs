@@ -0,0 +1,100 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tonic::metadata::MetadataMap;
+
+use crate::types::error::NetError;
+
+/// Hooks around every unary and stream call of a gRPC client, e.g. to refresh an auth
+/// token or to time calls; added with `IGrpcClient::add_interceptor`
+pub trait NetGrpcInterceptor: Send + Sync {
+    /// Before the call goes out, in the order the interceptors were added. `metadata` holds
+    /// what the call sends so far; an error fails the call without sending it
+    fn on_request(&self, _method: &str, _metadata: &mut MetadataMap) -> Result<(), NetError> {
+        Ok(())
+    }
+
+    /// Once the call is done, in reverse order: a unary call with its response, a stream when
+    /// it ends, cancelled by this end included. A failed call carries its `grpc_status` when
+    /// the server answered; `elapsed` counts from before `on_request`
+    fn on_response(&self, _method: &str, _result: Result<(), &NetError>, _elapsed: Duration) {}
+}
+
+/// Interceptors of one client; calls started take the ones added by then
+#[derive(Clone, Default)]
+pub(crate) struct GrpcInterceptors(Arc<RwLock<Vec<Arc<dyn NetGrpcInterceptor>>>>);
+
+impl GrpcInterceptors {
+    pub fn add(&self, interceptor: Arc<dyn NetGrpcInterceptor>) {
+        self.0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(interceptor);
+    }
+
+    /// A call of `method`, timed from now
+    pub fn call(&self, method: &str) -> GrpcInterceptedCall {
+        GrpcInterceptedCall {
+            interceptors: self.0.read().unwrap_or_else(|e| e.into_inner()).clone(),
+            method: method.to_string(),
+            clock: CallClock::start(),
+        }
+    }
+}
+
+/// One call through the interceptors, `finish` reports it done
+pub(crate) struct GrpcInterceptedCall {
+    interceptors: Vec<Arc<dyn NetGrpcInterceptor>>,
+    method: String,
+    clock: CallClock,
+}
+
+impl GrpcInterceptedCall {
+    pub fn intercept(&self, metadata: &mut MetadataMap) -> Result<(), NetError> {
+        for interceptor in &self.interceptors {
+            interceptor.on_request(&self.method, metadata)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self, result: Result<(), &NetError>) {
+        if self.interceptors.is_empty() {
+            return;
+        }
+        let elapsed = self.clock.elapsed();
+        for interceptor in self.interceptors.iter().rev() {
+            interceptor.on_response(&self.method, result, elapsed);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct CallClock(std::time::Instant);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl CallClock {
+    fn start() -> Self {
+        CallClock(std::time::Instant::now())
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// `Instant` is not there on wasm, the js clock in milliseconds stands in
+#[cfg(target_arch = "wasm32")]
+struct CallClock(f64);
+
+#[cfg(target_arch = "wasm32")]
+impl CallClock {
+    fn start() -> Self {
+        CallClock(js_sys::Date::now())
+    }
+
+    fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64(((js_sys::Date::now() - self.0) / 1000.0).max(0.0))
+    }
+}
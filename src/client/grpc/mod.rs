@@ -1,6 +1,54 @@
 #[cfg(not(target_arch = "wasm32"))]
 pub mod native;
 
+pub mod interceptor;
 pub mod raw_codec;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
+
+use tonic::metadata::{
+    AsciiMetadataValue, BinaryMetadataValue, KeyAndValueRef, MetadataKey, MetadataMap,
+};
+
+use crate::types::config::{NetHttpHeader, NetHttpHeaders};
+
+/// Adds the call `metadata` to `request`, repeated keys included. A key ending in `-bin`
+/// sends its value as binary metadata, base64 on the wire; the key of an entry gRPC
+/// metadata can not hold is the error
+pub(crate) fn append_metadata<'m, R>(
+    request: &mut tonic::Request<R>,
+    metadata: impl IntoIterator<Item = (&'m str, &'m str)>,
+) -> Result<(), &'m str> {
+    let map = request.metadata_mut();
+    for (key, value) in metadata {
+        if key.to_ascii_lowercase().ends_with("-bin") {
+            let name = MetadataKey::from_bytes(key.as_bytes()).map_err(|_| key)?;
+            map.append_bin(name, BinaryMetadataValue::from_bytes(value.as_bytes()));
+        } else {
+            let name = MetadataKey::from_bytes(key.as_bytes()).map_err(|_| key)?;
+            let value = AsciiMetadataValue::try_from(value).map_err(|_| key)?;
+            map.append(name, value);
+        }
+    }
+    Ok(())
+}
+
+/// Metadata the server sent, but the `grpc-` entries the call status is read from. Binary
+/// values are kept base64 as they came
+pub(crate) fn metadata_headers(map: &MetadataMap) -> NetHttpHeaders {
+    let mut headers = NetHttpHeaders::new();
+    for entry in map.iter() {
+        let (key, value) = match entry {
+            KeyAndValueRef::Ascii(key, value) => (key.as_str(), value.as_encoded_bytes()),
+            KeyAndValueRef::Binary(key, value) => (key.as_str(), value.as_encoded_bytes()),
+        };
+        if key.starts_with("grpc-") {
+            continue;
+        }
+        headers.push(NetHttpHeader::new(
+            key.to_string(),
+            String::from_utf8_lossy(value).into_owned(),
+        ));
+    }
+    headers
+}
@@ -1,170 +1,509 @@
+use bytes::Bytes;
 use futures::stream;
+use http::{
+    Uri,
+    uri::{Authority, PathAndQuery},
+};
 use log::debug;
-use std::{marker::PhantomData, sync::Arc};
-use tokio::sync::{Mutex, broadcast, oneshot};
-use tokio_tungstenite::tungstenite::http::uri::PathAndQuery;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU8, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{Mutex, oneshot};
 use tonic::{Code, client::Grpc, transport::Channel};
 
+#[cfg(feature = "otel")]
+use crate::utils::otel::OtelSpan;
 use crate::{
     client::{
-        grpc::raw_codec::BufferCodec,
+        grpc::{
+            append_metadata,
+            interceptor::{GrpcInterceptedCall, GrpcInterceptors, NetGrpcInterceptor},
+            metadata_headers,
+            raw_codec::BufferCodec,
+        },
         native::{GrpcStreamHandle, IClient, IGrpcClient},
     },
-    stream::{ConnectStream, grpc::GrpcConnector},
-    types::{config::NetConfig, error::NetResultStatus},
+    stream::{ConnectStream, NetworkWatcher, grpc::GrpcConnector},
+    types::{
+        config::NetConfig,
+        error::{NetError, NetResultStatus},
+        grpc_status::NetGrpcStatus,
+        native::request::NetHttpHeaderRef,
+        response::{NetGrpcConnectivity, NetResponseGrpcUnary},
+    },
+    utils::subscribers::StreamSubscribers,
 };
 
+/// Calls in flight on the channels of a client and when the last one started or ended, for
+/// `NetConfig::grpc_idle_timeout`
+struct GrpcChannelUse {
+    in_flight: AtomicUsize,
+    last_used: std::sync::Mutex<Instant>,
+}
+
+impl GrpcChannelUse {
+    fn new() -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            last_used: std::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    /// A call from now until the returned guard is dropped
+    fn start(self: &Arc<Self>) -> GrpcChannelCall {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        self.touch();
+        GrpcChannelCall(Arc::clone(self))
+    }
+
+    fn touch(&self) {
+        *self.last_used.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// How long the channels went without a call, `None` while one is in flight
+    fn idle_for(&self) -> Option<Duration> {
+        (self.in_flight.load(Ordering::Acquire) == 0).then(|| {
+            self.last_used
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .elapsed()
+        })
+    }
+}
+
+/// Keeps the channels from going idle while the call holding it runs
+struct GrpcChannelCall(Arc<GrpcChannelUse>);
+
+impl Drop for GrpcChannelCall {
+    fn drop(&mut self) {
+        self.0.touch();
+        self.0.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
 pub struct GrpcClient<T> {
     client: Arc<Mutex<Option<Grpc<Channel>>>>,
+    /// Channels of the calls overriding the `:authority`, by it; a channel sets one for every
+    /// call it carries, so each takes a connection of its own. Beyond `NetHttpPool::max_hosts`
+    /// of them one is let go, closing once its calls are done
+    authorities: Arc<Mutex<HashMap<String, Grpc<Channel>>>>,
+    /// `NetworkWatcher::generation` when `client` was connected
+    network_generation: AtomicU64,
+    /// `NetGrpcConnectivity` of the last connect, read without waiting for one in progress
+    connectivity: Arc<AtomicU8>,
+    usage: Arc<GrpcChannelUse>,
+    /// Whether the task closing idle channels runs, started by the first connect
+    idle_watched: AtomicBool,
     config: NetConfig,
+    interceptors: GrpcInterceptors,
     _marker: PhantomData<T>,
 }
 impl<T> GrpcClient<T>
 where
     T: ConnectStream,
 {
-    pub fn default(config: NetConfig) -> Result<Self, NetResultStatus> {
+    pub fn default(config: NetConfig) -> Result<Self, NetError> {
         Ok(Self {
             client: Arc::new(Mutex::new(None)),
+            authorities: Arc::new(Mutex::new(HashMap::new())),
+            network_generation: AtomicU64::new(NetworkWatcher::generation()),
+            connectivity: Arc::new(AtomicU8::new(NetGrpcConnectivity::Idle as u8)),
+            usage: Arc::new(GrpcChannelUse::new()),
+            idle_watched: AtomicBool::new(false),
             config: config,
+            interceptors: GrpcInterceptors::default(),
             _marker: PhantomData,
         })
     }
-}
-#[async_trait::async_trait]
-impl<T> IClient for GrpcClient<T>
-where
-    T: ConnectStream,
-{
-    async fn connect(&self) -> Result<(), NetResultStatus> {
-        let mut guard = self.client.lock().await;
 
-        let reconnect_needed = match guard.as_mut() {
-            Some(client) => client.ready().await.is_err(),
-            None => true,
+    fn set_connectivity(&self, state: NetGrpcConnectivity) {
+        self.connectivity.store(state as u8, Ordering::Release);
+    }
+
+    /// Closes the channels once no call was in flight for `timeout`, the next call dialing
+    /// again; ends with the client. A stream keeps them open however quiet it is
+    fn watch_idle(&self, timeout: Duration) {
+        if self.idle_watched.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let client = Arc::downgrade(&self.client);
+        let authorities = Arc::clone(&self.authorities);
+        let connectivity = Arc::clone(&self.connectivity);
+        let usage = Arc::clone(&self.usage);
+        tokio::spawn(async move {
+            let mut wait = timeout;
+            loop {
+                tokio::time::sleep(wait).await;
+                let Some(client) = client.upgrade() else {
+                    return;
+                };
+                // under the lock, so a call starting now either is counted or dials again
+                let mut guard = client.lock().await;
+                wait = match usage.idle_for() {
+                    Some(idle) if idle >= timeout => {
+                        if guard.take().is_some() {
+                            authorities.lock().await.clear();
+                            connectivity.store(NetGrpcConnectivity::Idle as u8, Ordering::Release);
+                            debug!("Grpc channel idle for {:?}, closed.", idle);
+                        }
+                        timeout
+                    }
+                    Some(idle) => timeout - idle,
+                    None => timeout,
+                };
+            }
+        });
+    }
+
+    /// Dials the server for a new channel, of `authority` or the url's host and port
+    async fn open_channel(&self, authority: Option<&str>) -> Result<Channel, NetError> {
+        let mut endpoint = tonic::transport::Endpoint::from_shared(self.config.addr.url.clone())
+            .map_err(|e| {
+                debug!(
+                    "Create Grpc channel error: {:#?}, {:#?} ",
+                    e, self.config.addr.url
+                );
+                NetError::new(NetResultStatus::InvalidRequestParameters)
+                    .with_message("invalid grpc endpoint")
+                    .with_url(self.config.addr.url.clone())
+                    .with_source(e)
+            })?
+            // a dead channel fails `ready` in `connect` instead of taking the next call
+            .http2_keep_alive_interval(self.config.http.http2_settings.ping_interval())
+            .keep_alive_timeout(self.config.http.http2_settings.ping_timeout())
+            .keep_alive_while_idle(self.config.http.http2_settings.ping_while_idle());
+        if let Some(authority) = authority {
+            endpoint = endpoint.origin(self.origin(authority)?);
+        }
+        let connector = GrpcConnector::<T>::default(&self.config);
+        endpoint
+            .connect_with_connector(connector)
+            .await
+            .map_err(|e| {
+                debug!("Grpc client error: {:#?}, {:#?} ", e, self.config.addr.url);
+                NetError::new(NetResultStatus::ConnectionError)
+                    .with_message("grpc connect failed")
+                    .with_url(self.config.addr.url.clone())
+                    .with_source(e)
+            })
+    }
+
+    /// Scheme of the url with `authority`, which the channel puts on its calls
+    fn origin(&self, authority: &str) -> Result<Uri, NetError> {
+        let invalid = |e: http::Error| {
+            NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message(format!("invalid grpc authority: {}", authority))
+                .with_source(e)
         };
-        if reconnect_needed {
-            // Create new channel
-            let endpoint = tonic::transport::Endpoint::from_shared(self.config.addr.url.clone())
-                .map_err(|e| {
-                    debug!(
-                        "Create Grpc channel error: {:#?}, {:#?} ",
-                        e, self.config.addr.url
-                    );
-                    NetResultStatus::InvalidRequestParameters
-                })?;
-            let connector = GrpcConnector::<T>::default(&self.config);
-            let channel = endpoint
-                .connect_with_connector(connector)
+        let authority = Authority::try_from(authority).map_err(|e| invalid(e.into()))?;
+        let scheme = match self.config.addr.is_tls {
+            true => "https",
+            false => "http",
+        };
+        Uri::builder()
+            .scheme(scheme)
+            .authority(authority)
+            .path_and_query("/")
+            .build()
+            .map_err(invalid)
+    }
+
+    /// The connected channel for one call, of its own `authority` when it overrides the
+    /// config's. Clones share its connection, so calls made at once are multiplexed on it
+    /// instead of waiting for each other
+    async fn grpc(&self, authority: Option<&str>) -> Result<Grpc<Channel>, NetError> {
+        let authority =
+            authority.filter(|authority| Some(*authority) != self.config.grpc_authority.as_deref());
+        let Some(authority) = authority else {
+            // should exist after connect()
+            return self
+                .client
+                .lock()
                 .await
-                .map_err(|e| {
-                    debug!("Grpc client error: {:#?}, {:#?} ", e, self.config.addr.url);
-                    NetResultStatus::ConnectionError
-                })?;
-            *guard = Some(Grpc::new(channel));
+                .clone()
+                .ok_or(NetResultStatus::InternalError.into());
+        };
+        let mut authorities = self.authorities.lock().await;
+        if let Some(client) = authorities.get(authority) {
+            return Ok(client.clone());
         }
+        let client = Grpc::new(self.open_channel(Some(authority)).await?);
+        if authorities.len() >= self.config.http.pool.max_hosts()
+            && let Some(evicted) = authorities.keys().next().cloned()
+        {
+            authorities.remove(&evicted);
+        }
+        authorities.insert(authority.to_string(), client.clone());
+        Ok(client)
+    }
 
-        Ok(())
+    /// A request of `message` carrying the call `metadata`
+    fn request<R>(
+        message: R,
+        metadata: &[NetHttpHeaderRef<'_>],
+        deadline: Option<Duration>,
+    ) -> Result<tonic::Request<R>, NetError> {
+        let mut request = tonic::Request::new(message);
+        if let Some(deadline) = deadline {
+            request.set_timeout(deadline);
+        }
+        append_metadata(&mut request, metadata.iter().map(|m| (m.key, m.value))).map_err(
+            |key| {
+                NetError::new(NetResultStatus::InvalidRequestParameters)
+                    .with_message(format!("invalid grpc metadata: {}", key))
+            },
+        )?;
+        Ok(request)
     }
 
-    fn get_config(&self) -> &NetConfig {
-        return &self.config;
+    /// Sends the span in scope along as `traceparent` metadata
+    #[cfg(feature = "otel")]
+    fn trace<R>(request: &mut tonic::Request<R>) {
+        if let Some(traceparent) = OtelSpan::traceparent()
+            && let Ok(value) = traceparent.parse()
+        {
+            request.metadata_mut().insert("traceparent", value);
+        }
     }
-}
-#[async_trait::async_trait]
-impl<T> IGrpcClient for GrpcClient<T>
-where
-    T: ConnectStream,
-{
-    async fn unary<'a>(
+
+    /// `IGrpcClient::unary` of `call`
+    async fn send_unary<'a>(
         &self,
-        buffer: &'a [u8],
+        call: &GrpcInterceptedCall,
+        buffer: Bytes,
         method_name: &'a str,
-    ) -> Result<Vec<u8>, NetResultStatus> {
+        metadata: &[NetHttpHeaderRef<'a>],
+        deadline: Option<Duration>,
+        authority: Option<&'a str>,
+    ) -> Result<NetResponseGrpcUnary, NetError> {
         self.connect().await?;
-        let mut guard = self.client.lock().await;
-        let client = guard.as_mut().ok_or(NetResultStatus::InternalError)?; // should exist after connect()
+        let mut client = self.grpc(authority).await?;
         let path = PathAndQuery::try_from(method_name.to_string()).map_err(|e| {
             debug!("Config grpc query path error: {:#?}", e);
-            NetResultStatus::InvalidRequestParameters
+            NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message(format!("invalid grpc method: {}", method_name))
+                .with_source(e)
         })?;
-        let req = tonic::Request::new(Vec::from(buffer));
+        let mut req = GrpcClient::<T>::request(buffer, metadata, deadline)?;
+        #[cfg(feature = "otel")]
+        GrpcClient::<T>::trace(&mut req);
+        call.intercept(req.metadata_mut())?;
         let codec = BufferCodec::default();
 
         client.ready().await.map_err(|e| {
             debug!("Grpc client error: {:#?}", e);
-            NetResultStatus::ConnectionError
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_url(self.config.addr.url.clone())
+                .with_source(e)
         })?;
-        let resp = client.unary(req, path, codec).await.map_err(|e| {
+        let call_error = |e: tonic::Status| {
             debug!("Grpc unary requeset error: {:#?}", e);
-            NetResultStatus::ConnectionError
-        })?;
-        Ok(resp.into_inner())
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("grpc unary call {} failed", method_name))
+                .with_url(self.config.addr.url.clone())
+                .with_grpc_status(NetGrpcStatus::from(&e))
+                .with_source(e)
+        };
+        // a unary call of one message, read as a stream to keep the trailers apart
+        let resp = client
+            .server_streaming(req, path, codec)
+            .await
+            .map_err(call_error)?;
+        let (metadata, mut body, _) = resp.into_parts();
+        let message = body
+            .message()
+            .await
+            .and_then(|message| {
+                message.ok_or_else(|| tonic::Status::internal("Missing response message."))
+            })
+            .map_err(call_error)?;
+        let trailers = body.trailers().await.map_err(call_error)?;
+        Ok(NetResponseGrpcUnary::new(message.into()).with_metadata(
+            metadata_headers(&metadata),
+            trailers.as_ref().map(metadata_headers).unwrap_or_default(),
+        ))
     }
 
-    async fn stream<'a>(
+    /// The server stream of `IGrpcClient::stream`, answering `call`
+    async fn open_stream<'a>(
         &self,
-        buffer: &'a [u8],
+        call: &GrpcInterceptedCall,
+        buffer: Bytes,
         method_name: &'a str,
-    ) -> Result<GrpcStreamHandle, NetResultStatus> {
+        metadata: &[NetHttpHeaderRef<'a>],
+        deadline: Option<Duration>,
+        authority: Option<&'a str>,
+    ) -> Result<tonic::Streaming<Vec<u8>>, NetError> {
         self.connect().await?;
-        let (tx, rx) = broadcast::channel(128);
-        let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
-        // Lock mutex to get client
-        let mut guard = self.client.lock().await;
-        let client = guard.as_mut().ok_or(NetResultStatus::ConnectionError)?; // should exist after connect()
+        let mut client = self.grpc(authority).await?;
 
         let path = PathAndQuery::try_from(method_name.to_string()).map_err(|e| {
             debug!("Grpc stream config query path error: {:#?}", e);
-            NetResultStatus::InvalidRequestParameters
+            NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message(format!("invalid grpc method: {}", method_name))
+                .with_source(e)
         })?;
         let codec = BufferCodec::default();
-        let buffer = Vec::from(buffer);
         let req_stream = stream::once(async { buffer });
-        let req = tonic::Request::new(req_stream);
+        let mut req = GrpcClient::<T>::request(req_stream, metadata, deadline)?;
+        #[cfg(feature = "otel")]
+        GrpcClient::<T>::trace(&mut req);
+        call.intercept(req.metadata_mut())?;
 
         client.ready().await.map_err(|e| {
             debug!("Grpc client error: {:#?}", e);
-            NetResultStatus::ConnectionError
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_url(self.config.addr.url.clone())
+                .with_source(e)
         })?;
         let stream = client.streaming(req, path, codec).await.map_err(|e| {
             debug!("Grpc streaming request error: {:#?}", e);
-            NetResultStatus::ConnectionError
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("grpc streaming call {} failed", method_name))
+                .with_url(self.config.addr.url.clone())
+                .with_grpc_status(NetGrpcStatus::from(&e))
+                .with_source(e)
         })?;
-        let mut stream: tonic::Streaming<Vec<u8>> = stream.into_inner();
+        Ok(stream.into_inner())
+    }
+}
+#[async_trait::async_trait]
+impl<T> IClient for GrpcClient<T>
+where
+    T: ConnectStream,
+{
+    async fn connect(&self) -> Result<(), NetError> {
+        let mut guard = self.client.lock().await;
+        let generation = NetworkWatcher::generation();
+
+        let reconnect_needed = match guard.as_mut() {
+            // after a network change the channel may still look ready on a dead connection
+            Some(_) if self.network_generation.load(Ordering::Acquire) != generation => true,
+            Some(client) => client.ready().await.is_err(),
+            None => true,
+        };
+        if reconnect_needed {
+            self.set_connectivity(NetGrpcConnectivity::Connecting);
+            let channel = match self
+                .open_channel(self.config.grpc_authority.as_deref())
+                .await
+            {
+                Ok(channel) => channel,
+                Err(e) => {
+                    self.set_connectivity(NetGrpcConnectivity::Failed);
+                    return Err(e);
+                }
+            };
+            *guard = Some(Grpc::new(channel));
+            // those of the overrides went down with it, or are on the old route
+            self.authorities.lock().await.clear();
+            self.network_generation.store(generation, Ordering::Release);
+            if self.config.grpc_idle_timeout != 0 {
+                self.watch_idle(Duration::from_secs(self.config.grpc_idle_timeout.into()));
+            }
+        }
+        self.set_connectivity(NetGrpcConnectivity::Ready);
+
+        Ok(())
+    }
+
+    fn get_config(&self) -> &NetConfig {
+        &self.config
+    }
+}
+#[async_trait::async_trait]
+impl<T> IGrpcClient for GrpcClient<T>
+where
+    T: ConnectStream,
+{
+    async fn unary<'a>(
+        &self,
+        buffer: Bytes,
+        method_name: &'a str,
+        metadata: &[NetHttpHeaderRef<'a>],
+        deadline: Option<Duration>,
+        authority: Option<&'a str>,
+    ) -> Result<NetResponseGrpcUnary, NetError> {
+        let _held = self.usage.start();
+        let call = self.interceptors.call(method_name);
+        let response = self
+            .send_unary(&call, buffer, method_name, metadata, deadline, authority)
+            .await;
+        call.finish(response.as_ref().map(|_| ()));
+        response
+    }
+
+    async fn stream<'a>(
+        &self,
+        buffer: Bytes,
+        method_name: &'a str,
+        metadata: &[NetHttpHeaderRef<'a>],
+        deadline: Option<Duration>,
+        authority: Option<&'a str>,
+    ) -> Result<GrpcStreamHandle, NetError> {
+        let held = self.usage.start();
+        let call = self.interceptors.call(method_name);
+        let mut stream = match self
+            .open_stream(&call, buffer, method_name, metadata, deadline, authority)
+            .await
+        {
+            Ok(stream) => stream,
+            Err(e) => {
+                call.finish(Err(&e));
+                return Err(e);
+            }
+        };
+        let incoming = StreamSubscribers::new(&self.config.stream_queue);
+        let rx = incoming.subscribe();
+        let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
 
-        let tx_clone: broadcast::Sender<Result<Option<Vec<u8>>, NetResultStatus>> = tx.clone();
         tokio::spawn(async move {
-            loop {
+            let result = loop {
                 tokio::select! {
                     _ = &mut cancel_rx => {
                         // cancel requested
-                        break;
+                        break Ok(());
                     }
                     msg = stream.message() => {
                         match msg {
                             Ok(Some(msg)) => {
-                                let _ = tx_clone.send(Ok(Some(msg.to_vec())));
+                                // a full queue must not keep the cancel from the server
+                                tokio::select! {
+                                    _ = incoming.send(Ok(Some(msg.to_vec()))) => {}
+                                    _ = &mut cancel_rx => break Ok(()),
+                                }
                             },
                             Ok(None) => {
-                                let _ = tx_clone.send(Ok(None));
-                                break;
+                                incoming.send(Ok(None)).await;
+                                break Ok(());
                             }
                             Err(err) => {
                                     debug!("Grpc streaming on message error: {:#?}", err);
                                      if err.code()==Code::Ok{
-                                           let _ = tx_clone.send(Ok(None));
-                                     }else{
-                                         let _ = tx_clone.send(Err(NetResultStatus::SocketError));
+                                           incoming.send(Ok(None)).await;
+                                           break Ok(());
                                      }
-                                    break;
-
+                                     let error = NetError::new(NetResultStatus::SocketError)
+                                         .with_grpc_status(NetGrpcStatus::from(&err))
+                                         .with_source(err);
+                                     incoming.send(Err(error.clone())).await;
+                                     break Err(error);
                             }
 
                         }
                     }
                 }
-            }
+            };
+            // dropping the call resets it, which tells the server to stop sending
+            drop(stream);
+            drop(held);
+            call.finish(result.as_ref().map(|_| ()));
         });
 
         Ok(GrpcStreamHandle {
@@ -173,9 +512,19 @@ where
         })
     }
 
+    fn add_interceptor(&self, interceptor: Arc<dyn NetGrpcInterceptor>) {
+        self.interceptors.add(interceptor);
+    }
+
+    fn connectivity(&self) -> NetGrpcConnectivity {
+        NetGrpcConnectivity::from_u8(self.connectivity.load(Ordering::Acquire))
+    }
+
     async fn close(&self) {
         let mut client = self.client.lock().await;
         *client = None;
+        self.authorities.lock().await.clear();
+        self.set_connectivity(NetGrpcConnectivity::Idle);
         debug!("Grpc client close.");
     }
 }
@@ -7,7 +7,8 @@ use tonic::{
 pub struct BufferCodec;
 impl Codec for BufferCodec {
     type Decode = Vec<u8>;
-    type Encode = Vec<u8>;
+    /// Written into the frame as is, no conversion on the way
+    type Encode = Bytes;
 
     type Encoder = BufferRawBytesEncoder;
     type Decoder = BufferRawBytesDecoder;
@@ -24,11 +25,11 @@ impl Codec for BufferCodec {
 pub struct BufferRawBytesEncoder;
 
 impl Encoder for BufferRawBytesEncoder {
-    type Item = Vec<u8>;
+    type Item = Bytes;
     type Error = Status;
 
-    fn encode(&mut self, item: Vec<u8>, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
-        dst.put(Bytes::from(item));
+    fn encode(&mut self, item: Bytes, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        dst.put(item);
         Ok(())
     }
 
@@ -43,8 +44,12 @@ impl Decoder for BufferRawBytesDecoder {
     type Item = Vec<u8>;
     type Error = Status;
 
+    /// `src` is one whole message: tonic strips its length prefix and holds it back until
+    /// every frame it spans arrived, so messages merged in a frame are decoded one by one
     fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
-        Ok(Some(src.copy_to_bytes(src.remaining()).to_vec()))
+        let mut message = vec![0; src.remaining()];
+        src.copy_to_slice(&mut message);
+        Ok(Some(message))
     }
 
     fn buffer_settings(&self) -> BufferSettings {
@@ -1,18 +1,41 @@
 use crate::client::wasm::{GrpcStreamHandle, IClient, IGrpcClient};
 use crate::{
-    client::grpc::raw_codec::BufferCodec,
-    types::{config::NetConfig, error::NetResultStatus},
+    client::grpc::{
+        append_metadata,
+        interceptor::{GrpcInterceptedCall, GrpcInterceptors, NetGrpcInterceptor},
+        metadata_headers,
+        raw_codec::BufferCodec,
+    },
+    types::{
+        config::{NetConfig, NetHttpHeader},
+        error::{NetError, NetResultStatus},
+        grpc_status::NetGrpcStatus,
+        response::{NetGrpcConnectivity, NetResponseGrpcUnary},
+    },
+    utils::subscribers::StreamSubscribers,
 };
+use bytes::Bytes;
 use futures::stream;
 use http::uri::PathAndQuery;
-use std::{marker::PhantomData, sync::Arc};
-use tokio::sync::{Mutex, broadcast, oneshot};
+use std::{
+    marker::PhantomData,
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+    time::Duration,
+};
+use tokio::sync::{Mutex, oneshot};
 use tonic::{Code, client::Grpc};
 use tonic_web_wasm_client::Client;
 use wasm_bindgen_futures::spawn_local;
 pub struct GrpcClient {
     client: Arc<Mutex<Option<Grpc<Client>>>>,
+    /// `NetGrpcConnectivity` of the client; the browser dials for each call on its own, so
+    /// it is never seen connecting or failed here
+    connectivity: AtomicU8,
     config: NetConfig,
+    interceptors: GrpcInterceptors,
     _marker: PhantomData<()>,
 }
 
@@ -20,10 +43,115 @@ impl GrpcClient {
     pub fn default(config: NetConfig) -> Result<Self, NetResultStatus> {
         Ok(Self {
             client: Arc::new(Mutex::new(None)),
+            connectivity: AtomicU8::new(NetGrpcConnectivity::Idle as u8),
             config,
+            interceptors: GrpcInterceptors::default(),
             _marker: PhantomData,
         })
     }
+
+    /// The client for one call; clones send their calls side by side
+    async fn grpc(&self) -> Option<Grpc<Client>> {
+        self.client.lock().await.clone()
+    }
+
+    /// A request of `message` carrying the call `metadata`
+    fn request<R>(
+        message: R,
+        metadata: &[NetHttpHeader],
+        deadline: Option<Duration>,
+    ) -> Result<tonic::Request<R>, NetResultStatus> {
+        let mut request = tonic::Request::new(message);
+        if let Some(deadline) = deadline {
+            request.set_timeout(deadline);
+        }
+        append_metadata(
+            &mut request,
+            metadata.iter().map(|m| (m.key_ref(), m.value_ref())),
+        )
+        .map_err(|_| NetResultStatus::InvalidRequestParameters)?;
+        Ok(request)
+    }
+
+    /// Error of a failed call, keeping the status the server answered with
+    fn call_error(&self, method_name: &str, status: tonic::Status) -> NetError {
+        NetError::new(NetResultStatus::ConnectionError)
+            .with_message(format!("grpc call {} failed", method_name))
+            .with_url(self.config.addr.url.clone())
+            .with_grpc_status(NetGrpcStatus::from(&status))
+            .with_source(status)
+    }
+
+    /// `IGrpcClient::unary` of `call`
+    async fn send_unary(
+        &self,
+        call: &GrpcInterceptedCall,
+        buffer: &[u8],
+        method_name: &str,
+        metadata: &[NetHttpHeader],
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseGrpcUnary, NetError> {
+        self.connect().await?;
+        let mut client = self.grpc().await.ok_or(NetResultStatus::ConnectionError)?; // should exist after connect()
+
+        let path = PathAndQuery::try_from(method_name.to_string())
+            .map_err(|_| NetResultStatus::InvalidRequestParameters)?;
+        let mut req = GrpcClient::request(Bytes::copy_from_slice(buffer), metadata, deadline)?;
+        call.intercept(req.metadata_mut())?;
+        let codec = BufferCodec::default();
+        // a unary call of one message, read as a stream to keep the trailers apart
+        let resp = client
+            .server_streaming(req, path, codec)
+            .await
+            .map_err(|e| self.call_error(method_name, e))?;
+        let (metadata, mut body, _) = resp.into_parts();
+        let message = body
+            .message()
+            .await
+            .and_then(|message| {
+                message.ok_or_else(|| tonic::Status::internal("Missing response message."))
+            })
+            .map_err(|e| self.call_error(method_name, e))?;
+        let trailers = body
+            .trailers()
+            .await
+            .map_err(|e| self.call_error(method_name, e))?;
+        Ok(NetResponseGrpcUnary::new(message.into()).with_metadata(
+            metadata_headers(&metadata),
+            trailers.as_ref().map(metadata_headers).unwrap_or_default(),
+        ))
+    }
+
+    /// The server stream of `IGrpcClient::stream`, answering `call`
+    async fn open_stream(
+        &self,
+        call: &GrpcInterceptedCall,
+        buffer: &[u8],
+        method_name: &str,
+        metadata: &[NetHttpHeader],
+        deadline: Option<Duration>,
+    ) -> Result<tonic::Streaming<Vec<u8>>, NetError> {
+        self.connect().await?;
+        let mut client = self.grpc().await.ok_or(NetResultStatus::ConnectionError)?; // should exist after connect()
+
+        let path = PathAndQuery::try_from(method_name.to_string())
+            .map_err(|_| NetResultStatus::InvalidRequestParameters)?;
+        let codec = BufferCodec::default();
+        let buffer = Bytes::copy_from_slice(buffer);
+        let req_stream = stream::once(async { buffer });
+        let mut req = GrpcClient::request(req_stream, metadata, deadline)?;
+        call.intercept(req.metadata_mut())?;
+
+        client
+            .ready()
+            .await
+            .map_err(|_| NetResultStatus::ConnectionError)?;
+        let stream = client
+            .streaming(req, path, codec)
+            .await
+            .map_err(|e| self.call_error(method_name, e))?;
+        Ok(stream.into_inner())
+    }
 }
 
 #[async_trait::async_trait(?Send)]
@@ -39,6 +167,8 @@ impl IClient for GrpcClient {
             let grpc = Grpc::new(wasm_client);
             *guard = Some(grpc);
         }
+        self.connectivity
+            .store(NetGrpcConnectivity::Ready as u8, Ordering::Release);
 
         Ok(())
     }
@@ -50,82 +180,83 @@ impl IClient for GrpcClient {
 
 #[async_trait::async_trait(?Send)]
 impl IGrpcClient for GrpcClient {
-    async fn unary(&self, buffer: &[u8], method_name: &str) -> Result<Vec<u8>, NetResultStatus> {
-        self.connect().await?;
-        let mut guard = self.client.lock().await;
-        let client = guard.as_mut().ok_or(NetResultStatus::ConnectionError)?; // should exist after connect()
-
-        let path = PathAndQuery::try_from(method_name.to_string())
-            .map_err(|_| NetResultStatus::InvalidRequestParameters)?;
-        let req = tonic::Request::new(Vec::from(buffer));
-        let codec = BufferCodec::default();
-        let resp = client
-            .unary(req, path, codec)
-            .await
-            .map_err(|_| NetResultStatus::ConnectionError)?;
-        Ok(resp.into_inner())
+    async fn unary(
+        &self,
+        buffer: &[u8],
+        method_name: &str,
+        metadata: &[NetHttpHeader],
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseGrpcUnary, NetError> {
+        let call = self.interceptors.call(method_name);
+        let response = self
+            .send_unary(&call, buffer, method_name, metadata, deadline)
+            .await;
+        call.finish(response.as_ref().map(|_| ()));
+        response
     }
 
     async fn stream(
         &self,
         buffer: &[u8],
         method_name: &str,
-    ) -> Result<GrpcStreamHandle, NetResultStatus> {
-        self.connect().await?;
-        let (tx, rx) = broadcast::channel(128);
-        let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
-        // Lock mutex to get client
-        let mut guard = self.client.lock().await;
-        let client = guard.as_mut().ok_or(NetResultStatus::ConnectionError)?; // should exist after connect()
-
-        let path = PathAndQuery::try_from(method_name.to_string())
-            .map_err(|_| NetResultStatus::InvalidRequestParameters)?;
-        let codec = BufferCodec::default();
-        let buffer = Vec::from(buffer);
-        let req_stream = stream::once(async { buffer });
-        let req = tonic::Request::new(req_stream);
-
-        client
-            .ready()
+        metadata: &[NetHttpHeader],
+        deadline: Option<Duration>,
+    ) -> Result<GrpcStreamHandle, NetError> {
+        let call = self.interceptors.call(method_name);
+        let mut stream = match self
+            .open_stream(&call, buffer, method_name, metadata, deadline)
             .await
-            .map_err(|_| NetResultStatus::ConnectionError)?;
-        let stream = client
-            .streaming(req, path, codec)
-            .await
-            .map_err(|_| NetResultStatus::ConnectionError)?;
-        let mut stream: tonic::Streaming<Vec<u8>> = stream.into_inner();
+        {
+            Ok(stream) => stream,
+            Err(e) => {
+                call.finish(Err(&e));
+                return Err(e);
+            }
+        };
+        let incoming = StreamSubscribers::new(&self.config.stream_queue);
+        let rx = incoming.subscribe();
+        let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
 
-        let tx_clone: broadcast::Sender<Result<Option<Vec<u8>>, NetResultStatus>> = tx.clone();
         spawn_local(async move {
-            loop {
+            let result = loop {
                 tokio::select! {
                     _ = &mut cancel_rx => {
                         // cancel requested
-                        break;
+                        break Ok(());
                     }
                     msg = stream.message() => {
                         match msg {
                             Ok(Some(msg)) => {
-                                let _ = tx_clone.send(Ok(Some(msg.to_vec())));
+                                // a full queue must not keep the cancel from the server
+                                tokio::select! {
+                                    _ = incoming.send(Ok(Some(msg.to_vec()))) => {}
+                                    _ = &mut cancel_rx => break Ok(()),
+                                }
                             },
                             Ok(None) => {
-                                let _ = tx_clone.send(Ok(None));
-                                break;
+                                incoming.send(Ok(None)).await;
+                                break Ok(());
                             }
                             Err(err) => {
                                      if err.code()==Code::Ok{
-                                           let _ = tx_clone.send(Ok(None));
-                                     }else{
-                                         let _ = tx_clone.send(Err(NetResultStatus::SocketError));
+                                           incoming.send(Ok(None)).await;
+                                           break Ok(());
                                      }
-                                    break;
-
+                                     let error = NetError::new(NetResultStatus::SocketError)
+                                         .with_grpc_status(NetGrpcStatus::from(&err))
+                                         .with_source(err);
+                                     incoming.send(Err(error.clone())).await;
+                                     break Err(error);
                             }
 
                         }
                     }
                 }
-            }
+            };
+            // the response body holds the fetch's abort guard, so dropping the call aborts the
+            // fetch and the browser resets the stream, which tells the server to stop sending
+            drop(stream);
+            call.finish(result.as_ref().map(|_| ()));
         });
 
         Ok(GrpcStreamHandle {
@@ -134,8 +265,18 @@ impl IGrpcClient for GrpcClient {
         })
     }
 
+    fn add_interceptor(&self, interceptor: Arc<dyn NetGrpcInterceptor>) {
+        self.interceptors.add(interceptor);
+    }
+
+    fn connectivity(&self) -> NetGrpcConnectivity {
+        NetGrpcConnectivity::from_u8(self.connectivity.load(Ordering::Acquire))
+    }
+
     async fn close(&self) {
         let mut guard = self.client.lock().await;
         *guard = None;
+        self.connectivity
+            .store(NetGrpcConnectivity::Idle as u8, Ordering::Release);
     }
 }
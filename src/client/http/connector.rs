@@ -0,0 +1,158 @@
+use std::{
+    io,
+    marker::PhantomData,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper::{
+    Uri,
+    rt::{Read, ReadBufCursor, Write},
+};
+use hyper_util::{
+    client::legacy::connect::{Connected, Connection},
+    rt::TokioIo,
+};
+use log::debug;
+use tower_service::Service;
+
+use crate::{
+    stream::ConnectStream,
+    types::{
+        config::{NetConfig, NetHttpProtocol},
+        error::{NetError, NetResultStatus},
+    },
+    utils::Utils,
+};
+
+/// Opens the connections of the `HttpClient` pool with the transport's stream type, so Tor,
+/// custom streams and the TLS settings apply to each of them.
+pub struct HttpConnector<T> {
+    config: Arc<NetConfig>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> HttpConnector<T> {
+    pub fn new(config: &NetConfig) -> Self {
+        Self {
+            config: Arc::new(config.clone()),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for HttpConnector<T> {
+    fn clone(&self) -> Self {
+        Self {
+            config: Arc::clone(&self.config),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Service<Uri> for HttpConnector<T>
+where
+    T: ConnectStream,
+{
+    type Response = HttpConnection<T>;
+    type Error = NetError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Uri) -> Self::Future {
+        let base = Arc::clone(&self.config);
+        Box::pin(async move {
+            let addr = Utils::parse_http_url(&req.to_string())?;
+            let config = NetConfig {
+                addr,
+                ..NetConfig::clone(&base)
+            };
+            let stream = T::connect(&config).await?;
+            // a pooled connection may sit idle long enough for a middlebox to drop it
+            if let Err(e) = stream.set_keep_alive(config.http.pool.keep_alive()) {
+                debug!("tcp keep-alive not set: {:?}", e);
+            }
+            let h2 = stream.alpn_protocol() == Some(b"h2")
+                || config.http.protocol == Some(NetHttpProtocol::Http2PriorKnowledge);
+            if config.http.protocol == Some(NetHttpProtocol::Http2) && !h2 {
+                return Err(NetError::new(NetResultStatus::Http2ConctionFailed)
+                    .with_message("server did not negotiate h2 via ALPN")
+                    .with_url(config.addr.host.clone()));
+            }
+            Ok(HttpConnection {
+                remote_addr: stream.remote_addr(),
+                io: TokioIo::new(stream),
+                h2,
+            })
+        })
+    }
+}
+
+/// Pooled connection; tells the pool whether ALPN picked h2, so it is multiplexed
+pub struct HttpConnection<T> {
+    io: TokioIo<T>,
+    h2: bool,
+    remote_addr: Option<SocketAddr>,
+}
+
+/// Peer of the connection a response came over, in the response extensions
+#[derive(Clone, Copy, Debug)]
+pub struct HttpRemoteAddr(pub SocketAddr);
+
+impl<T: ConnectStream> Connection for HttpConnection<T> {
+    fn connected(&self) -> Connected {
+        let connected = match self.h2 {
+            true => Connected::new().negotiated_h2(),
+            false => Connected::new(),
+        };
+        match self.remote_addr {
+            Some(addr) => connected.extra(HttpRemoteAddr(addr)),
+            None => connected,
+        }
+    }
+}
+
+impl<T: ConnectStream> Read for HttpConnection<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<T: ConnectStream> Write for HttpConnection<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
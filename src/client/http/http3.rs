@@ -0,0 +1,445 @@
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use bytes::{Buf, Bytes};
+use http_body_util::{BodyExt, Full};
+use hyper::{Request, Response, body::Incoming};
+use log::debug;
+use quinn::crypto::rustls::QuicClientConfig;
+use tokio::{net::TcpStream, time::sleep};
+use tokio_rustls::client::TlsStream;
+
+use crate::{
+    client::{
+        http::{connector::HttpRemoteAddr, native::HttpClient},
+        native::{HttpDownloadSink, IClient, IHttpClient},
+    },
+    stream::{NetworkWatcher, StreamUtils},
+    types::{
+        config::{NetConfig, NetHttpHeaders},
+        error::{NetError, NetResultStatus},
+        native::request::{NetHttpHeaderRef, NetHttpRetryConfig},
+        response::NetResponseHttp,
+    },
+    utils::buffer::StreamEncoding,
+};
+
+/// The TCP path HTTP/3 falls back to
+type FallbackClient = HttpClient<TlsStream<TcpStream>>;
+
+type H3Sender = h3::client::SendRequest<h3_quinn::OpenStreams, Bytes>;
+
+/// Longest the QUIC handshakes to the addresses of the host may take together before it
+/// counts as unreachable over UDP, unless the config's connect timeout is shorter
+const QUIC_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long requests stay on the fallback after QUIC failed before it is dialed again
+const QUIC_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// `ma` of an `Alt-Svc` that names none
+const ALT_SVC_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// An open QUIC connection and the HTTP/3 requests it carries
+struct Http3Connection {
+    sender: H3Sender,
+    quic: quinn::Connection,
+    /// `NetworkWatcher::generation` when it was dialed
+    network_generation: u64,
+}
+
+/// What an `Alt-Svc` header says about HTTP/3 on the host of the response
+enum AltSvc {
+    Clear,
+    Port(u16, Duration),
+}
+
+/// Requests of `NetHttpProtocol::Http3`: HTTP/3 over one QUIC connection to the host, which
+/// carries them side by side. While QUIC can not be dialed they go through an HTTP/2 or
+/// HTTP/1.1 client of the same config, and so do event streams and downloads.
+pub struct Http3Client {
+    config: NetConfig,
+    fallback: FallbackClient,
+    /// Held across a dial, so concurrent requests wait for one connection
+    connection: tokio::sync::Mutex<Option<Http3Connection>>,
+    /// Until when QUIC is left alone after it failed
+    paused_until: Mutex<Option<Instant>>,
+    /// Port an `Alt-Svc` moved HTTP/3 to, and until when
+    alt_port: Mutex<Option<(u16, Instant)>>,
+}
+
+impl Http3Client {
+    pub fn new(config: NetConfig) -> Result<Self, NetError> {
+        let mut fallback = config.clone();
+        fallback.http.protocol = None;
+        Ok(Self {
+            config,
+            fallback: FallbackClient::default(fallback)?,
+            connection: tokio::sync::Mutex::new(None),
+            paused_until: Mutex::new(None),
+            alt_port: Mutex::new(None),
+        })
+    }
+
+    /// A sender on the HTTP/3 connection and the server it goes to, dialed when there is
+    /// none; `None` while QUIC is paused or could not be dialed
+    async fn sender(&self) -> Option<(H3Sender, SocketAddr)> {
+        let mut connection = self.connection.lock().await;
+        let generation = NetworkWatcher::generation();
+        if let Some(open) = connection.as_ref()
+            && open.network_generation == generation
+            && open.quic.close_reason().is_none()
+        {
+            return Some((open.sender.clone(), open.quic.remote_address()));
+        }
+        *connection = None;
+        let paused_until = *self.paused_until.lock().unwrap_or_else(|e| e.into_inner());
+        if paused_until.is_some_and(|until| Instant::now() < until) {
+            return None;
+        }
+        match self.dial(generation).await {
+            Ok(open) => {
+                let sender = (open.sender.clone(), open.quic.remote_address());
+                *connection = Some(open);
+                Some(sender)
+            }
+            Err(e) => {
+                debug!(
+                    "HTTP/3 to {} failed, falling back for {:?}: {:?}",
+                    self.config.addr.host, QUIC_RETRY_AFTER, e
+                );
+                *self.paused_until.lock().unwrap_or_else(|e| e.into_inner()) =
+                    Some(Instant::now() + QUIC_RETRY_AFTER);
+                None
+            }
+        }
+    }
+
+    /// Opens a QUIC connection offering `h3` to the first address of the host that answers,
+    /// on the port of an `Alt-Svc` when one is known
+    async fn dial(&self, network_generation: u64) -> Result<Http3Connection, NetError> {
+        let config = &self.config;
+        let addr = &config.addr;
+        config.host_policy.check_address(addr)?;
+        let port = match *self.alt_port.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some((port, until)) if Instant::now() < until => port,
+            _ => addr.port,
+        };
+        let socket_addrs = match StreamUtils::scoped_socket_addr(addr)? {
+            Some(socket_addr) => vec![socket_addr],
+            None => StreamUtils::socket_addrs(addr, &config.host_policy, &config.resolver).await?,
+        };
+        let tls = StreamUtils::tls_client_config(
+            Some(vec![b"h3".to_vec()]),
+            &config.tls_mode,
+            &config.mode,
+            config.tls_client_config.as_ref(),
+        )?;
+        let crypto = QuicClientConfig::try_from(tls).map_err(|e| {
+            NetError::new(NetResultStatus::TlsError)
+                .with_message("tls config can not be used for QUIC")
+                .with_source(e)
+        })?;
+        let client_config = quinn::ClientConfig::new(Arc::new(crypto));
+        let limit = config
+            .timeouts
+            .connect()
+            .map_or(QUIC_HANDSHAKE_TIMEOUT, |limit| {
+                limit.min(QUIC_HANDSHAKE_TIMEOUT)
+            });
+        let connect_error = |e: &dyn std::fmt::Debug| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("quic connect failed: {:?}", e))
+                .with_url(addr.authority())
+        };
+        let deadline = tokio::time::Instant::now() + limit;
+        let mut last_error = None;
+        for mut socket_addr in socket_addrs {
+            socket_addr.set_port(port);
+            let bind = match socket_addr {
+                SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+                SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+            };
+            let mut endpoint = quinn::Endpoint::client(bind).map_err(|e| connect_error(&e))?;
+            endpoint.set_default_client_config(client_config.clone());
+            let connecting = endpoint
+                .connect(socket_addr, &addr.host)
+                .map_err(|e| connect_error(&e))?;
+            let quic = match tokio::time::timeout_at(deadline, connecting).await {
+                Ok(Ok(quic)) => quic,
+                Ok(Err(e)) => {
+                    last_error = Some(connect_error(&e));
+                    continue;
+                }
+                Err(_) => {
+                    last_error = Some(
+                        NetError::new(NetResultStatus::ConnectionError)
+                            .with_message(format!("quic handshake timed out after {:?}", limit))
+                            .with_url(addr.authority()),
+                    );
+                    continue;
+                }
+            };
+            let (mut driver, sender) = h3::client::new(h3_quinn::Connection::new(quic.clone()))
+                .await
+                .map_err(|e| {
+                    NetError::new(NetResultStatus::ConnectionError)
+                        .with_message("http/3 handshake failed")
+                        .with_url(addr.authority())
+                        .with_source(e)
+                })?;
+            tokio::spawn(async move {
+                let e = driver.wait_idle().await;
+                debug!("HTTP/3 connection closed: {:?}", e);
+            });
+            return Ok(Http3Connection {
+                sender,
+                quic,
+                network_generation,
+            });
+        }
+        Err(last_error.unwrap_or_else(|| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("host has no address")
+                .with_url(addr.authority())
+        }))
+    }
+
+    /// Sends `req` on the HTTP/3 connection of `sender` to `remote_addr` and reads the whole
+    /// answer, each step within `read_timeout`
+    async fn exchange(
+        mut sender: H3Sender,
+        remote_addr: SocketAddr,
+        req: Request<Full<Bytes>>,
+        read_timeout: Option<Duration>,
+    ) -> Result<(Response<()>, Vec<u8>, NetHttpHeaders), NetError> {
+        let stream_error = |e: h3::error::StreamError| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("http/3 request failed")
+                .with_source(e)
+        };
+        let timed_out = FallbackClient::read_timeout;
+        let (parts, body) = req.into_parts();
+        let Ok(body) = body.collect().await.map(|body| body.to_bytes());
+        let mut stream = sender
+            .send_request(Request::from_parts(parts, ()))
+            .await
+            .map_err(stream_error)?;
+        if !body.is_empty() {
+            stream.send_data(body).await.map_err(stream_error)?;
+        }
+        stream.finish().await.map_err(stream_error)?;
+        let mut resp = StreamUtils::within(
+            read_timeout,
+            async { stream.recv_response().await.map_err(stream_error) },
+            timed_out,
+        )
+        .await?;
+        resp.extensions_mut().insert(HttpRemoteAddr(remote_addr));
+        let mut out = Vec::new();
+        while let Some(mut chunk) = StreamUtils::within(
+            read_timeout,
+            async { stream.recv_data().await.map_err(stream_error) },
+            timed_out,
+        )
+        .await?
+        {
+            while chunk.has_remaining() {
+                let data = chunk.chunk();
+                out.extend_from_slice(data);
+                let read = data.len();
+                chunk.advance(read);
+            }
+        }
+        let trailers = StreamUtils::within(
+            read_timeout,
+            async { stream.recv_trailers().await.map_err(stream_error) },
+            timed_out,
+        )
+        .await?
+        .map(|trailers| FallbackClient::header_list(&trailers))
+        .unwrap_or_default();
+        Ok((resp, out, trailers))
+    }
+
+    /// Follows the `Alt-Svc` of a response: HTTP/3 on another port of the host, or back on
+    /// the url's port once cleared. A new port is tried at once even while QUIC is paused
+    fn note_alt_svc(&self, headers: &NetHttpHeaders) {
+        let Some(alt_svc) = headers
+            .get_ref("alt-svc")
+            .and_then(Http3Client::parse_alt_svc)
+        else {
+            return;
+        };
+        let mut alt_port = self.alt_port.lock().unwrap_or_else(|e| e.into_inner());
+        match alt_svc {
+            AltSvc::Clear => *alt_port = None,
+            AltSvc::Port(port, max_age) => {
+                let known = alt_port.is_some_and(|(known, _)| known == port)
+                    || (alt_port.is_none() && port == self.config.addr.port);
+                if !known {
+                    *self.paused_until.lock().unwrap_or_else(|e| e.into_inner()) = None;
+                }
+                *alt_port = Some((port, Instant::now() + max_age));
+            }
+        }
+    }
+
+    /// The first `h3` alternative of an `Alt-Svc` value on the same host, e.g.
+    /// `h3=":443"; ma=86400`; alternatives on other hosts are not followed
+    fn parse_alt_svc(value: &str) -> Option<AltSvc> {
+        if value.trim().eq_ignore_ascii_case("clear") {
+            return Some(AltSvc::Clear);
+        }
+        value.split(',').find_map(|alternative| {
+            let mut params = alternative.split(';');
+            let (protocol, authority) = params.next()?.split_once('=')?;
+            if protocol.trim() != "h3" {
+                return None;
+            }
+            let port = authority
+                .trim()
+                .trim_matches('"')
+                .strip_prefix(':')?
+                .parse()
+                .ok()?;
+            let max_age = params
+                .filter_map(|param| param.trim().strip_prefix("ma="))
+                .find_map(|seconds| seconds.parse().ok())
+                .map_or(ALT_SVC_MAX_AGE, Duration::from_secs);
+            Some(AltSvc::Port(port, max_age))
+        })
+    }
+}
+
+#[async_trait]
+impl IClient for Http3Client {
+    /// Prepares the fallback; QUIC is dialed by the first request
+    async fn connect(&self) -> Result<(), NetError> {
+        self.fallback.connect().await
+    }
+
+    fn get_config(&self) -> &NetConfig {
+        &self.config
+    }
+}
+
+#[async_trait]
+impl IHttpClient for Http3Client {
+    async fn send<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        encoding: StreamEncoding,
+        retry_config: &NetHttpRetryConfig<'a>,
+    ) -> Result<NetResponseHttp, NetError> {
+        let http_method = FallbackClient::method(method)?;
+        let (req, clean_url) =
+            self.fallback
+                .build_request(http_method, url, body.clone(), headers, &[])?;
+        let read_timeout = self.config.timeouts.read();
+        let retry_delay = Duration::from_millis(retry_config.retry_delay as u64);
+        for attempt in 0..=retry_config.max_retries {
+            let Some((sender, remote_addr)) = self.sender().await else {
+                break;
+            };
+            match Http3Client::exchange(sender, remote_addr, req.clone(), read_timeout).await {
+                Ok((resp, body, trailers)) => {
+                    let status = resp.status().as_u16();
+                    if retry_config.retry_status.contains(&status)
+                        && attempt < retry_config.max_retries
+                    {
+                        let delay = FallbackClient::retry_after(&resp, retry_config);
+                        sleep(delay.unwrap_or(retry_delay)).await;
+                        continue;
+                    }
+                    let response = FallbackClient::decode_response(
+                        resp,
+                        body,
+                        trailers,
+                        encoding,
+                        self.config.http.decode_charset,
+                    );
+                    self.note_alt_svc(response.headers_ref());
+                    return Ok(response);
+                }
+                Err(e) if attempt < retry_config.max_retries => {
+                    debug!("Retrying HTTP/3 request after {:?}", e);
+                    // the next attempt dials again if the connection is what failed
+                    sleep(retry_delay).await;
+                }
+                Err(e) => return Err(e.with_url(clean_url)),
+            }
+        }
+        // QUIC is not reachable, the request goes the way it would without HTTP/3
+        let response = self
+            .fallback
+            .send(url, method, body, headers, encoding, retry_config)
+            .await?;
+        self.note_alt_svc(response.headers_ref());
+        Ok(response)
+    }
+
+    async fn events<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        last_event_id: Option<&'a str>,
+    ) -> Result<Incoming, NetError> {
+        self.fallback
+            .events(url, method, body, headers, last_event_id)
+            .await
+    }
+
+    async fn download<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        retry_config: &NetHttpRetryConfig<'a>,
+        sink: &HttpDownloadSink<'a>,
+    ) -> Result<NetResponseHttp, NetError> {
+        self.fallback
+            .download(url, method, body, headers, retry_config, sink)
+            .await
+    }
+
+    async fn close(&self) {
+        if let Some(open) = self.connection.lock().await.take() {
+            open.quic.close(0u32.into(), b"");
+        }
+        self.fallback.close().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(value: &str) -> Option<(u16, u64)> {
+        match Http3Client::parse_alt_svc(value)? {
+            AltSvc::Clear => None,
+            AltSvc::Port(port, max_age) => Some((port, max_age.as_secs())),
+        }
+    }
+
+    #[test]
+    fn alt_svc_names_the_h3_port_of_the_same_host() {
+        assert_eq!(port(r#"h3=":8443"; ma=3600"#), Some((8443, 3600)));
+        assert_eq!(port(r#"h2=":443", h3=":443""#), Some((443, 86400)));
+        assert_eq!(port(r#"h3="other.example:443""#), None);
+        assert_eq!(port(r#"h3-29=":443""#), None);
+        assert!(matches!(
+            Http3Client::parse_alt_svc("clear"),
+            Some(AltSvc::Clear)
+        ));
+    }
+}
@@ -1,6 +1,12 @@
 #[cfg(not(target_arch = "wasm32"))]
+pub mod connector;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod executor;
+#[cfg(all(feature = "http3", not(target_arch = "wasm32")))]
+pub mod http3;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod native;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod signing;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
@@ -1,28 +1,62 @@
+#[cfg(feature = "otel")]
+use crate::utils::otel::OtelSpan;
 use crate::{
     client::{
-        http::executor::TokioExecutor,
-        native::{IClient, IHttpClient},
+        http::{
+            connector::{HttpConnector, HttpRemoteAddr},
+            executor::TokioExecutor,
+            signing::RequestSigner,
+        },
+        native::{HttpDownloadSink, IClient, IHttpClient},
     },
-    stream::ConnectStream,
+    stream::{ConnectStream, NetworkWatcher},
     types::{
-        config::{NetConfig, NetHttpHeader, NetHttpProtocol},
-        error::NetResultStatus,
+        AddressTransport,
+        config::{
+            NetConfig, NetHttpAuth, NetHttpHeader, NetHttpHeaderMerge, NetHttpHeaders,
+            NetHttpProtocol,
+        },
+        error::{NetError, NetResultStatus},
         native::request::{NetHttpHeaderRef, NetHttpRetryConfig},
         response::NetResponseHttp,
     },
+    utils::{
+        Utils,
+        buffer::{StreamBuffer, StreamEncoding},
+        charset::TextCharset,
+    },
 };
 use async_trait::async_trait;
 use bytes::Bytes;
 use http_body_util::{BodyExt, Full};
 use hyper::{
-    Error, Method, Request, Response, Uri,
-    body::Incoming,
+    Error, HeaderMap, Method, Request, Response, StatusCode, Uri,
+    body::{Frame, Incoming},
     client::conn::{http1, http2},
+    header::{HeaderName, RETRY_AFTER},
+};
+use hyper_util::{
+    client::legacy::Client,
+    rt::{TokioIo, TokioTimer},
 };
-use hyper_util::rt::TokioIo;
 use log::debug;
-use std::{marker::PhantomData, str::FromStr, sync::Arc, time::Duration};
-use tokio::{sync::Mutex, time::sleep};
+use once_cell::sync::Lazy;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Seek, SeekFrom, Write},
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::{sleep, timeout};
+
+/// Least time between two progress reports of a download
+const DOWNLOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
 #[async_trait]
 pub trait SendRequestExt: Send + Sync {
@@ -30,6 +64,10 @@ pub trait SendRequestExt: Send + Sync {
         &mut self,
         req: Request<Full<Bytes>>,
     ) -> Result<Response<hyper::body::Incoming>, Error>;
+    /// Waits until the connection takes another request, e.g. after the previous body was read
+    async fn ready(&mut self) -> Result<(), Error>;
+    /// The connection is gone, e.g. the server closed it while idle; it takes no more requests
+    fn is_closed(&self) -> bool;
     fn protocol(&self) -> NetHttpProtocol;
 }
 
@@ -42,6 +80,12 @@ impl SendRequestExt for http1::SendRequest<Full<Bytes>> {
     ) -> Result<Response<hyper::body::Incoming>, Error> {
         self.send_request(req).await
     }
+    async fn ready(&mut self) -> Result<(), Error> {
+        self.ready().await
+    }
+    fn is_closed(&self) -> bool {
+        self.is_closed()
+    }
     fn protocol(&self) -> NetHttpProtocol {
         NetHttpProtocol::Http1
     }
@@ -55,17 +99,23 @@ impl SendRequestExt for http2::SendRequest<Full<Bytes>> {
     ) -> Result<Response<hyper::body::Incoming>, Error> {
         self.send_request(req).await
     }
+    async fn ready(&mut self) -> Result<(), Error> {
+        self.ready().await
+    }
+    fn is_closed(&self) -> bool {
+        self.is_closed()
+    }
     fn protocol(&self) -> NetHttpProtocol {
         NetHttpProtocol::Http2
     }
 }
 #[async_trait]
 pub trait Connect: Sized {
-    async fn connect<T: ConnectStream>(addr: &NetConfig) -> Result<Self, NetResultStatus>;
+    async fn connect<T: ConnectStream>(addr: &NetConfig) -> Result<Self, NetError>;
 }
 #[async_trait]
 impl Connect for http1::SendRequest<Full<Bytes>> {
-    async fn connect<T: ConnectStream>(addr: &NetConfig) -> Result<Self, NetResultStatus> {
+    async fn connect<T: ConnectStream>(addr: &NetConfig) -> Result<Self, NetError> {
         let stream = T::connect(addr).await?;
         let tokio = TokioIo::new(stream);
         let (sender, connection) =
@@ -73,7 +123,10 @@ impl Connect for http1::SendRequest<Full<Bytes>> {
                 .await
                 .map_err(|e| {
                     debug!("HTTP/1 handshake error: {:?}", e);
-                    NetResultStatus::ConnectionError
+                    NetError::new(NetResultStatus::ConnectionError)
+                        .with_message("http/1 handshake failed")
+                        .with_url(addr.addr.host.clone())
+                        .with_source(e)
                 })?;
         tokio::spawn(async move {
             if let Err(e) = connection.await {
@@ -85,15 +138,29 @@ impl Connect for http1::SendRequest<Full<Bytes>> {
 }
 #[async_trait]
 impl Connect for http2::SendRequest<Full<Bytes>> {
-    async fn connect<T: ConnectStream>(addr: &NetConfig) -> Result<Self, NetResultStatus> {
+    async fn connect<T: ConnectStream>(addr: &NetConfig) -> Result<Self, NetError> {
         let stream = T::connect(addr).await?;
         let tokio = TokioIo::new(stream);
-        // Builder::new(TokioExecutor).serve_connection(tokio, service_fn(f));
-        let (sender, connection) = hyper::client::conn::http2::handshake(TokioExecutor, tokio)
+        let settings = addr.http.http2_settings;
+        let (sender, connection) = http2::Builder::new(TokioExecutor)
+            .timer(TokioTimer::new())
+            // a connection that stopped answering ends, so the next request reconnects
+            .keep_alive_interval(settings.ping_interval())
+            .keep_alive_timeout(settings.ping_timeout())
+            .keep_alive_while_idle(settings.ping_while_idle())
+            .initial_stream_window_size(settings.initial_stream_window_size())
+            .initial_connection_window_size(settings.initial_connection_window_size())
+            .initial_max_send_streams(settings.max_concurrent_streams())
+            .max_frame_size(settings.max_frame_size())
+            .adaptive_window(settings.adaptive_window)
+            .handshake(tokio)
             .await
             .map_err(|e| {
                 debug!("HTTP/2 handshake error: {:?}", e);
-                NetResultStatus::ConnectionError
+                NetError::new(NetResultStatus::ConnectionError)
+                    .with_message("http/2 handshake failed")
+                    .with_url(addr.addr.host.clone())
+                    .with_source(e)
             })?;
         tokio::spawn(async move {
             if let Err(e) = connection.await {
@@ -110,7 +177,7 @@ pub struct AutoSendRequest {
 
 #[async_trait]
 impl Connect for AutoSendRequest {
-    async fn connect<T: ConnectStream>(config: &NetConfig) -> Result<Self, NetResultStatus> {
+    async fn connect<T: ConnectStream>(config: &NetConfig) -> Result<Self, NetError> {
         let stream = T::connect(config).await?;
         let alpn = stream.alpn_protocol();
         let protocol_pref = config.http.protocol.clone();
@@ -118,12 +185,23 @@ impl Connect for AutoSendRequest {
         match protocol_pref {
             Some(NetHttpProtocol::Http2) => {
                 if alpn != Some(b"h2") {
-                    return Err(NetResultStatus::Http2ConctionFailed);
+                    return Err(NetError::new(NetResultStatus::Http2ConctionFailed)
+                        .with_message("server did not negotiate h2 via ALPN")
+                        .with_url(config.addr.host.clone()));
                 }
 
-                let sender = http2::SendRequest::<Full<Bytes>>::connect::<T>(config)
-                    .await
-                    .map_err(|_| NetResultStatus::ConnectionError)?;
+                let sender = http2::SendRequest::<Full<Bytes>>::connect::<T>(config).await?;
+
+                Ok(Self {
+                    inner: Box::new(sender),
+                })
+            }
+
+            // ===============================
+            // HTTP/2 prior knowledge, ALPN or not
+            // ===============================
+            Some(NetHttpProtocol::Http2PriorKnowledge) => {
+                let sender = http2::SendRequest::<Full<Bytes>>::connect::<T>(config).await?;
 
                 Ok(Self {
                     inner: Box::new(sender),
@@ -134,9 +212,7 @@ impl Connect for AutoSendRequest {
             // Explicit HTTP/1
             // ===============================
             Some(NetHttpProtocol::Http1) => {
-                let sender = http1::SendRequest::<Full<Bytes>>::connect::<T>(config)
-                    .await
-                    .map_err(|_| NetResultStatus::ConnectionError)?;
+                let sender = http1::SendRequest::<Full<Bytes>>::connect::<T>(config).await?;
 
                 Ok(Self {
                     inner: Box::new(sender),
@@ -144,9 +220,9 @@ impl Connect for AutoSendRequest {
             }
 
             // ===============================
-            // Auto (try both)
+            // Auto (try both), also where HTTP/3 falls back to
             // ===============================
-            None => {
+            None | Some(NetHttpProtocol::Http3) => {
                 // Try HTTP/2 first if ALPN allows
                 if alpn == Some(b"h2") {
                     if let Ok(sender) =
@@ -159,9 +235,7 @@ impl Connect for AutoSendRequest {
                 }
 
                 // Fallback to HTTP/1
-                let sender = http1::SendRequest::<Full<Bytes>>::connect::<T>(config)
-                    .await
-                    .map_err(|_| NetResultStatus::ConnectionError)?;
+                let sender = http1::SendRequest::<Full<Bytes>>::connect::<T>(config).await?;
 
                 Ok(Self {
                     inner: Box::new(sender),
@@ -178,64 +252,142 @@ impl SendRequestExt for AutoSendRequest {
     ) -> Result<Response<hyper::body::Incoming>, Error> {
         self.inner.send(req).await
     }
+    async fn ready(&mut self) -> Result<(), Error> {
+        self.inner.ready().await
+    }
+    fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
     fn protocol(&self) -> NetHttpProtocol {
         self.inner.protocol()
     }
 }
-pub struct HttpClient<T, E> {
-    sender: Arc<Mutex<Option<Arc<Mutex<Box<dyn SendRequestExt>>>>>>,
-    _stream_marker: PhantomData<T>,
-    _protocol_marker: PhantomData<E>,
+type PooledClient<T> = Client<HttpConnector<T>, Full<Bytes>>;
+
+/// Pools of `NetConfigHttp::global_client` transports by stream type and connection
+/// settings, with the `NetworkWatcher::generation` each was built for
+static GLOBAL_POOLS: Lazy<Mutex<HashMap<GlobalPoolKey, GlobalPool>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+type GlobalPoolKey = (TypeId, String);
+
+type GlobalPool = (u64, Box<dyn Any + Send>);
+
+/// Requests through a connection pool per client: idle connections are reused, and an h2
+/// connection carries concurrent requests instead of queueing them.
+pub struct HttpClient<T> {
+    client: Mutex<Option<PooledClient<T>>>,
+    /// `NetworkWatcher::generation` when `client` was built
+    network_generation: AtomicU64,
     config: NetConfig,
 }
-impl<T, E> HttpClient<T, E>
+impl<T> HttpClient<T>
 where
     T: ConnectStream,
-    E: SendRequestExt + Connect + 'static,
 {
-    pub fn default(config: NetConfig) -> Result<Self, NetResultStatus> {
+    pub fn default(config: NetConfig) -> Result<Self, NetError> {
         Ok(Self {
-            sender: Arc::new(Mutex::new(None)),
-            _stream_marker: PhantomData,
-            _protocol_marker: PhantomData,
+            client: Mutex::new(None),
+            network_generation: AtomicU64::new(NetworkWatcher::generation()),
             config: config,
         })
     }
-    async fn conneect_inner<'a>(&self) -> Result<(), NetResultStatus> {
-        let mut guard = self.sender.lock().await;
-        let reconnect_needed = match guard.as_mut() {
-            Some(_) => false, // sender exists, assume ready
-            None => true,
-        };
 
-        if reconnect_needed {
-            let sender: E = E::connect::<T>(&self.config).await?;
-            *guard = Some(Arc::new(Mutex::new(Box::new(sender))));
+    /// The pool, replaced after a network change since its connections may use an address
+    /// that is gone
+    fn client(&self) -> PooledClient<T> {
+        let mut guard = self.client.lock().unwrap_or_else(|e| e.into_inner());
+        let generation = NetworkWatcher::generation();
+        match guard.as_ref() {
+            Some(client) if self.network_generation.load(Ordering::Acquire) == generation => {
+                client.clone()
+            }
+            _ => {
+                let client = match self.config.http.global_client {
+                    true => self.global_pool(generation),
+                    false => self.build_pool(),
+                };
+                *guard = Some(client.clone());
+                self.network_generation.store(generation, Ordering::Release);
+                client
+            }
         }
+    }
 
-        Ok(())
+    fn build_pool(&self) -> PooledClient<T> {
+        let http2 = self.config.http.http2_settings;
+        let mut builder = Client::builder(TokioExecutor);
+        builder
+            .pool_timer(TokioTimer::new())
+            .pool_idle_timeout(self.config.http.pool.idle_timeout())
+            .pool_max_idle_per_host(self.config.http.pool.max_idle_per_host())
+            .timer(TokioTimer::new())
+            // idle connections too, so a dead one leaves the pool before a request picks it
+            .http2_keep_alive_interval(http2.ping_interval())
+            .http2_keep_alive_timeout(http2.ping_timeout())
+            .http2_keep_alive_while_idle(http2.ping_while_idle())
+            .http2_initial_stream_window_size(http2.initial_stream_window_size())
+            .http2_initial_connection_window_size(http2.initial_connection_window_size())
+            .http2_initial_max_send_streams(http2.max_concurrent_streams())
+            .http2_max_frame_size(http2.max_frame_size())
+            .http2_adaptive_window(http2.adaptive_window)
+            .http2_only(self.config.http.protocol.is_some_and(|p| p.is_http2()));
+        builder.build(HttpConnector::new(&self.config))
+    }
+
+    /// The pool of `NetConfigHttp::global_client` transports that connect the way this one
+    /// does; closing a transport only lets go of it, the others keep its connections
+    fn global_pool(&self, generation: u64) -> PooledClient<T> {
+        let config = &self.config;
+        // all the connector and the pool builder read, the host is taken from each request
+        let settings = format!(
+            "{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}",
+            config.mode,
+            config.tls_mode,
+            config.http.protocol,
+            config.http.pool,
+            config.http.http2_settings,
+            config.timeouts,
+            config.host_policy,
+            config.tls_client_config.as_ref().map(Arc::as_ptr),
+        );
+        let key = (TypeId::of::<T>(), settings);
+        let mut pools = GLOBAL_POOLS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((built, pool)) = pools.get(&key)
+            && *built == generation
+            && let Some(pool) = pool.downcast_ref::<PooledClient<T>>()
+        {
+            return pool.clone();
+        }
+        let pool = self.build_pool();
+        pools.insert(key, (generation, Box::new(pool.clone())));
+        pool
+    }
+
+    /// The connector's own error for a failed connect
+    fn request_error(e: hyper_util::client::legacy::Error, url: &str) -> NetError {
+        let mut source = std::error::Error::source(&e);
+        while let Some(error) = source {
+            if let Some(error) = error.downcast_ref::<NetError>() {
+                return error.clone();
+            }
+            source = error.source();
+        }
+        NetError::new(NetResultStatus::ConnectionError)
+            .with_message("http request failed")
+            .with_url(url)
+            .with_source(e)
     }
 }
 
 #[async_trait::async_trait]
-impl<'a, T, E> IClient for HttpClient<T, E>
+impl<T> IClient for HttpClient<T>
 where
     T: ConnectStream,
-    E: SendRequestExt + Connect + 'static,
 {
-    async fn connect(&self) -> Result<(), NetResultStatus> {
-        let mut guard = self.sender.lock().await;
-
-        let reconnect_needed = match guard.as_mut() {
-            Some(_) => false, // sender exists, assume ready
-            None => true,
-        };
-
-        if reconnect_needed {
-            let sender: E = E::connect::<T>(&self.config).await?;
-            *guard = Some(Arc::new(Mutex::new(Box::new(sender))));
-        }
-
+    /// Prepares the pool; connections are opened by the requests that need them
+    async fn connect(&self) -> Result<(), NetError> {
+        self.client();
         Ok(())
     }
 
@@ -245,61 +397,183 @@ where
 }
 
 #[async_trait::async_trait]
-impl<T, E> IHttpClient for HttpClient<T, E>
+impl<T> IHttpClient for HttpClient<T>
 where
     T: ConnectStream,
-    E: SendRequestExt + Connect + 'static,
 {
     async fn send<'a>(
         &self,
         url: &'a str,
         method: &'a str,
-        body: Option<&'a [u8]>,
+        body: Option<Bytes>,
         headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        encoding: StreamEncoding,
         retry_config: &NetHttpRetryConfig<'a>,
-    ) -> Result<NetResponseHttp, NetResultStatus> {
-        let method = Method::from_bytes(method.as_bytes()).map_err(|e| {
-            debug!("Http invalid method name: {:?}", e);
-            NetResultStatus::InvalidRequestParameters
-        })?;
-        let result = self.request(method, url, body, headers, retry_config).await;
+    ) -> Result<NetResponseHttp, NetError> {
+        let method = HttpClient::<T>::method(method)?;
+        let result = self
+            .request(method, url, body, headers, encoding, retry_config)
+            .await;
         result
     }
+    async fn events<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        last_event_id: Option<&'a str>,
+    ) -> Result<Incoming, NetError> {
+        let mut stream_headers = vec![
+            (http::header::ACCEPT, "text/event-stream"),
+            (http::header::CACHE_CONTROL, "no-cache"),
+        ];
+        if let Some(id) = last_event_id {
+            stream_headers.push((HeaderName::from_static("last-event-id"), id));
+        }
+        let method = HttpClient::<T>::method(method)?;
+        let (req, url) = self.build_request(method, url, body, headers, &stream_headers)?;
+        let resp = self
+            .client()
+            .request(req)
+            .await
+            .map_err(|e| HttpClient::<T>::request_error(e, &url))?;
+        let content_type = resp
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        let event_stream = content_type
+            .split(';')
+            .next()
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("text/event-stream"));
+        // 204 is how a server tells the client to stop reconnecting
+        if !resp.status().is_success() || resp.status().as_u16() == 204 || !event_stream {
+            return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message(format!(
+                    "not an event stream: status {}, content type {:?}",
+                    resp.status().as_u16(),
+                    content_type
+                ))
+                .with_url(url));
+        }
+        Ok(resp.into_body())
+    }
+    async fn download<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        retry_config: &NetHttpRetryConfig<'a>,
+        sink: &HttpDownloadSink<'a>,
+    ) -> Result<NetResponseHttp, NetError> {
+        let method = HttpClient::<T>::method(method)?;
+        let range = sink
+            .resume
+            .map(|resume| format!("bytes={}-", resume.offset));
+        let mut resume_headers = Vec::new();
+        if let Some(range) = &range {
+            resume_headers.push((http::header::RANGE, range.as_str()));
+        }
+        if let Some(validator) = sink.resume.and_then(|resume| resume.if_range) {
+            resume_headers.push((http::header::IF_RANGE, validator));
+        }
+        let (req, url) = self.build_request(method, url, body, headers, &resume_headers)?;
+        let resp = self.respond(req, &url, retry_config).await?;
+        if !resp.status().is_success() {
+            let read_timeout = self.config.timeouts.read();
+            return HttpClient::<T>::read_response(resp, StreamEncoding::Raw, false, read_timeout)
+                .await;
+        }
+        let status_code = resp.status().as_u16();
+        let headers = HttpClient::<T>::header_list(resp.headers());
+        let (version, remote_addr) = HttpClient::<T>::connection_info(&resp);
+        let start = HttpClient::<T>::resumed_at(&resp, sink, &url)?;
+        // what a range request can pick up again is kept when the body is cut off
+        let resumable = start > 0
+            || resp
+                .headers()
+                .get(http::header::ACCEPT_RANGES)
+                .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"bytes"));
+        let read_timeout = self.config.timeouts.read();
+        let trailers = HttpClient::<T>::write_body(
+            resp.into_body(),
+            sink,
+            &url,
+            read_timeout,
+            start,
+            resumable,
+        )
+        .await?;
+        Ok(
+            NetResponseHttp::new(status_code, Bytes::new(), headers, StreamEncoding::Raw)
+                .with_trailers(trailers)
+                .with_connection(version, remote_addr),
+        )
+    }
     async fn close(&self) {
-        let old_sender = self.sender.lock().await.take();
-        drop(old_sender);
+        let old_client = self.client.lock().unwrap_or_else(|e| e.into_inner()).take();
+        drop(old_client);
     }
 }
 
-impl<T, E> HttpClient<T, E>
+impl<T> HttpClient<T>
 where
     T: ConnectStream,
-    E: SendRequestExt + Connect + 'static,
 {
-    async fn request<'a>(
+    pub(crate) fn method(method: &str) -> Result<Method, NetError> {
+        Method::from_bytes(method.as_bytes()).map_err(|e| {
+            debug!("Http invalid method name: {:?}", e);
+            NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("invalid http method")
+                .with_source(e)
+        })
+    }
+
+    /// The request with the config's headers, url credentials, trace context and signature.
+    /// `defaults` are added unless the headers already have them. Returns the url without
+    /// credentials too
+    pub(crate) fn build_request<'a>(
         &self,
         method: Method,
         url: &'a str,
-        body: Option<&'a [u8]>,
+        body: Option<Bytes>,
         headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
-        retry_config: &NetHttpRetryConfig<'a>,
-    ) -> Result<NetResponseHttp, NetResultStatus> {
-        self.conneect_inner().await?;
+        defaults: &[(HeaderName, &str)],
+    ) -> Result<(Request<Full<Bytes>>, String), NetError> {
         let config = &self.config.http.headers;
-        let uri = Uri::from_str(url).unwrap();
-        let host = uri
-            .host()
-            .ok_or(NetResultStatus::ConnectionError)?
-            .to_string();
+        let addr = Utils::parse_http_url(url).map_err(|e| NetError::new(e).with_url(url))?;
+        self.config.host_policy.check_address(&addr)?;
+        let url = addr.url.as_str();
+        let wire = Utils::unix_http_uri(&addr);
+        let uri = Uri::from_str(wire.as_deref().unwrap_or(url)).map_err(|e| {
+            NetError::new(NetResultStatus::InvalidUrl)
+                .with_url(url)
+                .with_source(e)
+        })?;
+        // the client adds `Host` itself on http/1
         let mut builder = Request::builder().method(method).uri(uri);
-        builder = match headers {
-            Some(headers) => {
+        builder = match (headers, self.config.http.header_merge) {
+            (Some(headers), NetHttpHeaderMerge::Replace) => {
                 for h in headers {
                     builder = builder.header(h.key.to_string(), h.value.to_string());
                 }
                 builder
             }
-            None => {
+            (Some(headers), NetHttpHeaderMerge::Merge) => {
+                let overridden =
+                    |key: &str| headers.iter().any(|h| h.key.eq_ignore_ascii_case(key));
+                for h in config.iter().filter(|h| !overridden(h.key_ref())) {
+                    builder = builder.header(h.key(), h.value());
+                }
+                // an empty value only takes the config header away
+                for h in headers.iter().filter(|h| !h.value.is_empty()) {
+                    builder = builder.header(h.key.to_string(), h.value.to_string());
+                }
+                builder
+            }
+            (None, _) => {
                 for h in config {
                     builder = builder.header(h.key(), h.value());
                 }
@@ -307,35 +581,94 @@ where
             }
         };
 
-        let body = match body {
-            Some(b) => Full::new(Bytes::from(b.to_vec())),
-            None => Full::new(Bytes::new()),
-        };
-        if let Some(sender_arc) = self.sender.lock().await.as_ref() {
-            let sender = sender_arc.lock().await;
-            if sender.protocol() == NetHttpProtocol::Http1 {
-                builder = builder.header(http::header::HOST, host.clone());
+        let body = body.unwrap_or_default();
+
+        // the socket file stands in for the host, which is of no use to the server
+        if addr.transport == AddressTransport::Unix
+            && !builder
+                .headers_ref()
+                .is_some_and(|h| h.contains_key(http::header::HOST))
+        {
+            builder = builder.header(http::header::HOST, "localhost");
+        }
+        for (name, value) in defaults {
+            if !builder.headers_ref().is_some_and(|h| h.contains_key(name)) {
+                builder = builder.header(name, *value);
             }
         }
 
-        let req = builder.body(body).map_err(|_| {
+        let auth = self.config.http.auth.as_ref();
+        let authorization = addr
+            .authorization
+            .clone()
+            .or_else(|| auth.map(NetHttpAuth::header_value));
+        if let Some(authorization) = authorization
+            && !builder
+                .headers_ref()
+                .is_some_and(|h| h.contains_key(http::header::AUTHORIZATION))
+        {
+            builder = builder.header(http::header::AUTHORIZATION, authorization.as_str());
+        }
+
+        let mut req = builder.body(Full::new(body.clone())).map_err(|e| {
             debug!("Create http body error.",);
-            NetResultStatus::InvalidRequestParameters
+            NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("invalid http request")
+                .with_url(url)
+                .with_source(e)
         })?;
+        #[cfg(feature = "otel")]
+        if let Some(traceparent) = OtelSpan::traceparent()
+            && !req.headers().contains_key("traceparent")
+            && let Ok(value) = http::HeaderValue::from_str(&traceparent)
+        {
+            req.headers_mut().insert("traceparent", value);
+        }
+        // signed once, retries go out within the signature's validity
+        if let Some(signing) = &self.config.http.signing {
+            RequestSigner::sign(signing, &mut req, &body)?;
+        }
+        Ok((req, addr.url))
+    }
+
+    async fn request<'a>(
+        &self,
+        method: Method,
+        url: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        encoding: StreamEncoding,
+        retry_config: &NetHttpRetryConfig<'a>,
+    ) -> Result<NetResponseHttp, NetError> {
+        let (req, url) = self.build_request(method, url, body, headers, &[])?;
+        let resp = self.respond(req, &url, retry_config).await?;
+        let decode_charset = self.config.http.decode_charset;
+        let read_timeout = self.config.timeouts.read();
+        HttpClient::<T>::read_response(resp, encoding, decode_charset, read_timeout).await
+    }
+
+    /// Sends `req` until an answer comes that `retry_config` does not retry
+    async fn respond(
+        &self,
+        req: Request<Full<Bytes>>,
+        url: &str,
+        retry_config: &NetHttpRetryConfig<'_>,
+    ) -> Result<Response<Incoming>, NetError> {
         let retry_delay = Duration::from_millis(retry_config.retry_delay as u64);
         for attempt in 0..=retry_config.max_retries {
-            let sender_arc = {
-                let guard = self.sender.lock().await;
-                guard
-                    .as_ref()
-                    .ok_or(NetResultStatus::ConnectionError)?
-                    .clone()
+            // a broken connection has left the pool, the retry gets another one
+            let request = self.client().request(req.clone());
+            let result = match self.config.timeouts.read() {
+                Some(limit) => match timeout(limit, request).await {
+                    Ok(result) => result,
+                    Err(_) if attempt < retry_config.max_retries => {
+                        sleep(retry_delay).await;
+                        continue;
+                    }
+                    Err(_) => return Err(HttpClient::<T>::read_timeout(limit).with_url(url)),
+                },
+                None => request.await,
             };
-            // lock inner sender for this request
-            let mut sender = sender_arc.lock().await;
-
-            // send request
-            let result = sender.send(req.clone()).await;
             match result {
                 Ok(resp) => {
                     let status = resp.status().as_u16();
@@ -348,55 +681,302 @@ where
                             attempt + 1
                         );
 
-                        sleep(retry_delay).await;
+                        let delay = HttpClient::<T>::retry_after(&resp, retry_config);
+                        sleep(delay.unwrap_or(retry_delay)).await;
                         continue;
                     }
 
-                    return HttpClient::<T, E>::read_response(resp).await;
+                    return Ok(resp);
                 }
 
-                Err(_) => {
+                // no connection could be made, the next attempt would not fare better
+                Err(e) if e.is_connect() => return Err(HttpClient::<T>::request_error(e, url)),
+
+                Err(e) => {
                     if attempt >= retry_config.max_retries {
-                        return Err(NetResultStatus::ConnectionError);
+                        return Err(HttpClient::<T>::request_error(e, url));
                     }
-
-                    // Reconnect: acquire lock only while replacing sender
-                    let new_sender: Arc<Mutex<Box<dyn SendRequestExt>>> =
-                        Arc::new(Mutex::new(Box::new(E::connect::<T>(&self.config).await?)
-                            as Box<dyn SendRequestExt>));
-                    let mut guard = self.sender.lock().await;
-                    *guard = Some(new_sender);
-
                     sleep(retry_delay).await;
                     continue;
                 }
             }
         }
 
-        Err(NetResultStatus::ConnectionError)
+        Err(NetError::new(NetResultStatus::ConnectionError).with_url(url))
     }
 
-    async fn read_response(resp: Response<Incoming>) -> Result<NetResponseHttp, NetResultStatus> {
+    async fn read_response(
+        resp: Response<Incoming>,
+        encoding: StreamEncoding,
+        decode_charset: bool,
+        read_timeout: Option<Duration>,
+    ) -> Result<NetResponseHttp, NetError> {
+        let (parts, body) = resp.into_parts();
+        let (body, trailers) = HttpClient::<T>::read_body(body, read_timeout).await?;
+        Ok(HttpClient::<T>::decode_response(
+            Response::from_parts(parts, ()),
+            body,
+            trailers,
+            encoding,
+            decode_charset,
+        ))
+    }
+    /// The answer `resp` heads with its `body` read: in `encoding` when it is a success,
+    /// transcoded to UTF-8 before by `decode_charset`
+    pub(crate) fn decode_response(
+        resp: Response<()>,
+        mut body: Vec<u8>,
+        trailers: NetHttpHeaders,
+        encoding: StreamEncoding,
+        decode_charset: bool,
+    ) -> NetResponseHttp {
         let status_code = resp.status().as_u16();
-        let headers: Vec<NetHttpHeader> = resp
+        let headers = HttpClient::<T>::header_list(resp.headers());
+        let (version, remote_addr) = HttpClient::<T>::connection_info(&resp);
+        let content_type = resp
             .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok());
+        let encoding = encoding.resolve(content_type);
+        let charset = match decode_charset {
+            true => TextCharset::from_content_type(content_type),
+            false => None,
+        };
+        let is_success = resp.status().is_success();
+        if let Some(charset) = charset {
+            body = charset.to_utf8(body);
+        }
+        let (body, encoding) = if is_success {
+            StreamBuffer::try_current_buffer(body, encoding)
+        } else {
+            (body, StreamEncoding::Raw)
+        };
+        NetResponseHttp::new(status_code, body.into(), headers, encoding)
+            .with_trailers(trailers)
+            .with_connection(version, remote_addr)
+    }
+    /// HTTP version of `resp` and the peer of the connection it came over
+    fn connection_info<B>(resp: &Response<B>) -> (String, Option<String>) {
+        let remote_addr = resp
+            .extensions()
+            .get::<HttpRemoteAddr>()
+            .map(|addr| addr.0.to_string());
+        (format!("{:?}", resp.version()), remote_addr)
+    }
+    pub(crate) fn header_list(headers: &HeaderMap) -> NetHttpHeaders {
+        headers
             .iter()
-            .map(|(k, v)| NetHttpHeader::new(k.to_string(), v.to_str().unwrap().to_string()))
-            .collect();
-        let body = HttpClient::<T, E>::read_body(resp.into_body()).await?;
-        Ok(NetResponseHttp::new(status_code, body, headers))
+            .map(|(k, v)| {
+                NetHttpHeader::new(
+                    k.to_string(),
+                    String::from_utf8_lossy(v.as_bytes()).into_owned(),
+                )
+            })
+            .collect()
+    }
+    /// Offset a `206` answer to a resumed download starts at, 0 when the body comes whole
+    fn resumed_at(
+        resp: &Response<Incoming>,
+        sink: &HttpDownloadSink<'_>,
+        url: &str,
+    ) -> Result<u64, NetError> {
+        let Some(resume) = sink.resume else {
+            return Ok(0);
+        };
+        if resp.status() != StatusCode::PARTIAL_CONTENT {
+            return Ok(0);
+        }
+        // `bytes <first>-<last>/<length>`
+        let first = resp
+            .headers()
+            .get(http::header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().strip_prefix("bytes "))
+            .and_then(|v| v.split_once('-'))
+            .and_then(|(first, _)| first.trim().parse::<u64>().ok());
+        match first {
+            Some(first) if first == resume.offset => Ok(first),
+            _ => Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!(
+                    "server answered with a range not starting at {}",
+                    resume.offset
+                ))
+                .with_url(url)),
+        }
+    }
+    /// Streams `body` into a file next to the one of `sink`, renamed to it once complete so
+    /// a failed download leaves nothing half written behind; a `resumable` one leaves the
+    /// file for a later `NetHttpResume`. `start` bytes of that file are kept and the body
+    /// goes after them. Answers the trailers of `body`
+    async fn write_body(
+        mut body: Incoming,
+        sink: &HttpDownloadSink<'_>,
+        url: &str,
+        read_timeout: Option<Duration>,
+        start: u64,
+        resumable: bool,
+    ) -> Result<NetHttpHeaders, NetError> {
+        let file_error = |e: std::io::Error| {
+            NetError::new(NetResultStatus::InternalError)
+                .with_message(format!("failed to write download file {}", sink.path))
+                .with_url(url)
+                .with_source(e)
+        };
+        let total = hyper::body::Body::size_hint(&body)
+            .exact()
+            .map(|len| start + len);
+        let temporary = format!("{}.part", sink.path);
+        let file = match start {
+            0 => File::create(&temporary).map_err(file_error)?,
+            _ => {
+                let mut file = OpenOptions::new()
+                    .write(true)
+                    .open(&temporary)
+                    .map_err(file_error)?;
+                if file.metadata().map_err(file_error)?.len() < start {
+                    return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                        .with_message(format!("{} holds less than the resume offset", temporary))
+                        .with_url(url));
+                }
+                file.set_len(start).map_err(file_error)?;
+                file.seek(SeekFrom::End(0)).map_err(file_error)?;
+                file
+            }
+        };
+        let mut file = BufWriter::new(file);
+        let mut received = start;
+        let mut trailers = NetHttpHeaders::new();
+        let mut reported = Instant::now();
+        (sink.progress)(received, total);
+        let written = async {
+            while let Some(frame) = HttpClient::<T>::next_frame(&mut body, read_timeout)
+                .await
+                .map_err(|e| e.with_url(url))?
+            {
+                if let Some(data) = frame.data_ref() {
+                    file.write_all(data).map_err(file_error)?;
+                    received += data.len() as u64;
+                    if reported.elapsed() >= DOWNLOAD_PROGRESS_INTERVAL {
+                        (sink.progress)(received, total);
+                        reported = Instant::now();
+                    }
+                } else if let Some(map) = frame.trailers_ref() {
+                    trailers = HttpClient::<T>::header_list(map);
+                }
+            }
+            file.flush().map_err(file_error)?;
+            drop(file);
+            std::fs::rename(&temporary, sink.path).map_err(file_error)
+        }
+        .await;
+        match written {
+            Ok(()) => (sink.progress)(received, total),
+            Err(_) if resumable => {}
+            Err(_) => {
+                let _ = std::fs::remove_file(&temporary);
+            }
+        }
+        written.map(|_| trailers)
     }
-    async fn read_body(mut body: Incoming) -> Result<Vec<u8>, NetResultStatus> {
+    /// The data of `body` and the trailers that followed it
+    async fn read_body(
+        mut body: Incoming,
+        read_timeout: Option<Duration>,
+    ) -> Result<(Vec<u8>, NetHttpHeaders), NetError> {
         let mut out = Vec::new();
-        while let Some(frame) = body.frame().await {
-            let frame = frame.map_err(|e| {
-                debug!("Http read body error: {:?}", e);
-                NetResultStatus::InternalError
-            })?;
+        let mut trailers = NetHttpHeaders::new();
+        while let Some(frame) = HttpClient::<T>::next_frame(&mut body, read_timeout).await? {
             if let Some(data) = frame.data_ref() {
                 out.extend_from_slice(data);
+            } else if let Some(map) = frame.trailers_ref() {
+                trailers = HttpClient::<T>::header_list(map);
             }
         }
-        Ok(out)
+        Ok((out, trailers))
+    }
+    /// `None` at the end of `body`; fails when nothing came for `read_timeout`
+    async fn next_frame(
+        body: &mut Incoming,
+        read_timeout: Option<Duration>,
+    ) -> Result<Option<Frame<Bytes>>, NetError> {
+        let frame = match read_timeout {
+            Some(limit) => timeout(limit, body.frame())
+                .await
+                .map_err(|_| HttpClient::<T>::read_timeout(limit))?,
+            None => body.frame().await,
+        };
+        frame.transpose().map_err(|e| {
+            debug!("Http read body error: {:?}", e);
+            NetError::new(NetResultStatus::InternalError)
+                .with_message("failed to read http body")
+                .with_source(e)
+        })
+    }
+    pub(crate) fn read_timeout(limit: Duration) -> NetError {
+        NetError::new(NetResultStatus::RequestTimeout)
+            .with_message(format!("http read timed out after {:?}", limit))
+    }
+
+    /// Wait a 429 or 503 asks for with `Retry-After`, in seconds or as an HTTP date, capped
+    /// by `NetHttpRetryConfig::max_retry_after`
+    pub(crate) fn retry_after<B>(
+        resp: &Response<B>,
+        retry_config: &NetHttpRetryConfig,
+    ) -> Option<Duration> {
+        if !matches!(resp.status().as_u16(), 429 | 503) {
+            return None;
+        }
+        let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+        let delay = match value.parse::<u64>() {
+            Ok(seconds) => Duration::from_secs(seconds),
+            Err(_) => {
+                let date = HttpClient::<T>::parse_http_date(value)?;
+                // a date already past asks for no wait at all
+                date.duration_since(SystemTime::now()).unwrap_or_default()
+            }
+        };
+        Some(delay.min(retry_config.max_retry_after()))
+    }
+
+    /// Parses an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+    fn parse_http_date(value: &str) -> Option<SystemTime> {
+        let mut parts = value.split_ascii_whitespace();
+        let (_weekday, day, month, year, time, zone) = (
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+        );
+        if zone != "GMT" || parts.next().is_some() {
+            return None;
+        }
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+        let day: i64 = day.parse().ok()?;
+        let year: i64 = year.parse().ok()?;
+        let mut time = time.split(':').map(|part| part.parse::<i64>().ok());
+        let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+        if time.next().is_some()
+            || !(1..=31).contains(&day)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return None;
+        }
+        // days from civil (Hinnant), the inverse of the conversion used for request signing
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let mp = (month + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second;
+        Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
     }
 }
@@ -0,0 +1,382 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use http::{HeaderName, HeaderValue, Request};
+use http_body_util::Full;
+use percent_encoding::{
+    AsciiSet, NON_ALPHANUMERIC, percent_decode_str, percent_encode, utf8_percent_encode,
+};
+use sha2::{Digest, Sha256};
+
+use crate::types::{
+    config::NetHttpSigning,
+    error::{NetError, NetResultStatus},
+};
+
+/// Everything but the RFC 3986 unreserved characters, as both schemes encode
+const URI_ENCODE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Signs requests by `NetConfigHttp::signing` right before they are sent.
+///
+/// The canonical request is the one of AWS SigV4: method, encoded path, sorted query,
+/// the lowercased headers with their list, and the hex SHA-256 of the body, joined by
+/// newlines. Every header of the request is signed, plus `host`.
+pub struct RequestSigner;
+
+impl RequestSigner {
+    pub fn sign(
+        signing: &NetHttpSigning,
+        request: &mut Request<Full<Bytes>>,
+        body: &[u8],
+    ) -> Result<(), NetError> {
+        RequestSigner::sign_at(signing, request, body, SystemTime::now())
+    }
+
+    fn sign_at(
+        signing: &NetHttpSigning,
+        request: &mut Request<Full<Bytes>>,
+        body: &[u8],
+        now: SystemTime,
+    ) -> Result<(), NetError> {
+        let (date, time) = RequestSigner::timestamp(now);
+        let payload_hash = RequestSigner::hex(&Sha256::digest(body));
+        let authorization = match signing {
+            NetHttpSigning::Hmac { key_id, secret } => {
+                RequestSigner::set_header(request, "x-date", &time)?;
+                let path = request.uri().path().to_string();
+                let (canonical, signed_headers) =
+                    RequestSigner::canonical_request(request, &path, &payload_hash);
+                let string_to_sign = format!(
+                    "HMAC-SHA256\n{}\n{}",
+                    time,
+                    RequestSigner::hex(&Sha256::digest(canonical.as_bytes()))
+                );
+                let signature = RequestSigner::hmac(secret.as_bytes(), string_to_sign.as_bytes());
+                format!(
+                    "HMAC-SHA256 Credential={}, SignedHeaders={}, Signature={}",
+                    key_id,
+                    signed_headers,
+                    RequestSigner::hex(&signature)
+                )
+            }
+            NetHttpSigning::AwsV4 {
+                access_key,
+                secret_key,
+                region,
+                service,
+                session_token,
+            } => {
+                RequestSigner::set_header(request, "x-amz-date", &time)?;
+                RequestSigner::set_header(request, "x-amz-content-sha256", &payload_hash)?;
+                if let Some(token) = session_token {
+                    RequestSigner::set_header(request, "x-amz-security-token", token)?;
+                }
+                // S3 signs each segment URI-encoded once, the other services encode the path
+                // as sent once more
+                let path = request
+                    .uri()
+                    .path()
+                    .split('/')
+                    .map(|segment| match service == "s3" {
+                        true => RequestSigner::reencode(segment),
+                        false => utf8_percent_encode(segment, URI_ENCODE).to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let (canonical, signed_headers) =
+                    RequestSigner::canonical_request(request, &path, &payload_hash);
+                let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+                let signature =
+                    RequestSigner::aws_v4_signature(secret_key, &scope, &time, &canonical);
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    access_key,
+                    scope,
+                    signed_headers,
+                    RequestSigner::hex(&signature)
+                )
+            }
+        };
+        RequestSigner::set_header(request, "authorization", &authorization)
+    }
+
+    /// Signature of `canonical` under `scope`, `date/region/service/aws4_request`
+    fn aws_v4_signature(secret_key: &str, scope: &str, time: &str, canonical: &str) -> Vec<u8> {
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            time,
+            scope,
+            RequestSigner::hex(&Sha256::digest(canonical.as_bytes()))
+        );
+        let key = scope
+            .split('/')
+            .fold(format!("AWS4{}", secret_key).into_bytes(), |key, part| {
+                RequestSigner::hmac(&key, part.as_bytes())
+            });
+        RequestSigner::hmac(&key, string_to_sign.as_bytes())
+    }
+
+    /// The canonical request over the encoded `path` and the `;` joined names of the
+    /// headers it signs
+    fn canonical_request(
+        request: &Request<Full<Bytes>>,
+        path: &str,
+        payload_hash: &str,
+    ) -> (String, String) {
+        let uri = request.uri();
+        let path = match path {
+            "" => "/",
+            path => path,
+        };
+        let mut query: Vec<(String, String)> = uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (RequestSigner::reencode(key), RequestSigner::reencode(value))
+            })
+            .collect();
+        query.sort();
+        let query = query
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        // the client adds `Host` from the authority itself, so it is signed from there
+        let mut headers: Vec<(String, String)> = request
+            .headers()
+            .iter()
+            .filter(|(name, _)| *name != http::header::AUTHORIZATION)
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                (
+                    name.as_str().to_string(),
+                    value.split_whitespace().collect::<Vec<_>>().join(" "),
+                )
+            })
+            .collect();
+        if !request.headers().contains_key(http::header::HOST)
+            && let Some(authority) = uri.authority()
+        {
+            headers.push(("host".to_string(), authority.as_str().to_string()));
+        }
+        headers.sort_by(|a, b| a.0.cmp(&b.0));
+        // repeated headers are signed as one, their values joined by commas
+        headers.dedup_by(|next, first| {
+            let same = next.0 == first.0;
+            if same {
+                first.1 = format!("{},{}", first.1, next.1);
+            }
+            same
+        });
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            request.method(),
+            path,
+            query,
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+        (canonical, signed_headers)
+    }
+
+    fn reencode(component: &str) -> String {
+        let decoded: Vec<u8> = percent_decode_str(component).collect();
+        percent_encode(&decoded, URI_ENCODE).to_string()
+    }
+
+    fn set_header(
+        request: &mut Request<Full<Bytes>>,
+        name: &'static str,
+        value: &str,
+    ) -> Result<(), NetError> {
+        let value = HeaderValue::from_str(value).map_err(|e| {
+            NetError::new(NetResultStatus::InvalidConfigParameters)
+                .with_message(format!("signing produced an invalid {} header", name))
+                .with_source(e)
+        })?;
+        request
+            .headers_mut()
+            .insert(HeaderName::from_static(name), value);
+        Ok(())
+    }
+
+    fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// `YYYYMMDD` and `YYYYMMDDTHHMMSSZ` in UTC
+    fn timestamp(now: SystemTime) -> (String, String) {
+        let seconds = now
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        let (days, rest) = (seconds / 86_400, seconds % 86_400);
+        // civil date from days since the epoch, after Howard Hinnant's algorithm
+        let z = days as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        let date = format!("{:04}{:02}{:02}", year, month, day);
+        let time = format!(
+            "{}T{:02}{:02}{:02}Z",
+            date,
+            rest / 3_600,
+            rest % 3_600 / 60,
+            rest % 60
+        );
+        (date, time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    // vectors of the AWS SigV4 test suite, signed at 20150830T123600Z
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+    const SCOPE: &str = "20150830/us-east-1/service/aws4_request";
+    const TIME: &str = "20150830T123600Z";
+    const EMPTY_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn request(method: &str, uri: &str, headers: &[(&str, &str)]) -> Request<Full<Bytes>> {
+        let mut builder = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(Full::default()).unwrap()
+    }
+
+    /// Canonical request and hex signature of a test-suite request, which signs only the
+    /// headers it carries and encodes the path as every service but S3
+    fn suite(request: &Request<Full<Bytes>>, body: &[u8]) -> (String, String) {
+        let payload_hash = RequestSigner::hex(&Sha256::digest(body));
+        let (canonical, _) = RequestSigner::canonical_request(request, "/", &payload_hash);
+        let signature = RequestSigner::aws_v4_signature(SECRET_KEY, SCOPE, TIME, &canonical);
+        (canonical, RequestSigner::hex(&signature))
+    }
+
+    #[test]
+    fn get_vanilla() {
+        let request = request(
+            "GET",
+            "https://example.amazonaws.com/",
+            &[("x-amz-date", TIME)],
+        );
+        let (canonical, signature) = suite(&request, b"");
+        assert_eq!(
+            canonical,
+            format!(
+                "GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:{}\n\nhost;x-amz-date\n{}",
+                TIME, EMPTY_HASH
+            )
+        );
+        assert_eq!(
+            signature,
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn get_vanilla_query_order_key() {
+        let request = request(
+            "GET",
+            "https://example.amazonaws.com/?Param2=value2&Param1=value1",
+            &[("x-amz-date", TIME)],
+        );
+        let (canonical, signature) = suite(&request, b"");
+        assert_eq!(
+            canonical,
+            format!(
+                "GET\n/\nParam1=value1&Param2=value2\nhost:example.amazonaws.com\n\
+                 x-amz-date:{}\n\nhost;x-amz-date\n{}",
+                TIME, EMPTY_HASH
+            )
+        );
+        assert_eq!(
+            signature,
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        );
+    }
+
+    #[test]
+    fn post_x_www_form_urlencoded() {
+        let request = request(
+            "POST",
+            "https://example.amazonaws.com/",
+            &[
+                ("content-type", "application/x-www-form-urlencoded"),
+                ("x-amz-date", TIME),
+            ],
+        );
+        let (canonical, signature) = suite(&request, b"Param1=value1");
+        assert_eq!(
+            canonical,
+            format!(
+                "POST\n/\n\ncontent-type:application/x-www-form-urlencoded\n\
+                 host:example.amazonaws.com\nx-amz-date:{}\n\ncontent-type;host;x-amz-date\n\
+                 9095672bbd1f56dfc5b65f3e153adc8731a4a654192329106275f4c7b24d0b6e",
+                TIME
+            )
+        );
+        assert_eq!(
+            signature,
+            "ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a"
+        );
+    }
+
+    #[test]
+    fn s3_path_is_uri_encoded_once() {
+        let signing = NetHttpSigning::AwsV4 {
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: SECRET_KEY.to_string(),
+            region: "us-east-1".to_string(),
+            service: "s3".to_string(),
+            session_token: None,
+        };
+        let mut request = request(
+            "GET",
+            "https://examplebucket.s3.amazonaws.com/my%20photos/a+b(1)*%24.jpg",
+            &[],
+        );
+        let now = UNIX_EPOCH + Duration::from_secs(1_440_938_160);
+        RequestSigner::sign_at(&signing, &mut request, b"", now).unwrap();
+        assert_eq!(
+            request.headers()["authorization"],
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+             Signature=60c2bcb113ab0debb2f4b9ec381d28dbc1d2d9c47b3415a10d5d116f3483c90b"
+        );
+    }
+}
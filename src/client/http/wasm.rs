@@ -1,12 +1,16 @@
 use crate::{
     client::wasm::{IClient, IHttpClient},
     types::{
-        config::{NetConfig, NetHttpHeader},
+        config::{NetConfig, NetHttpHeader, NetHttpHeaders},
         error::NetResultStatus,
         request::NetHttpRetryConfig,
         response::NetResponseHttp,
     },
-    utils::buffer::{StreamBuffer, StreamEncoding},
+    utils::{
+        Utils,
+        buffer::{StreamBuffer, StreamEncoding},
+        charset::TextCharset,
+    },
 };
 use reqwest::{Client, RequestBuilder};
 use std::sync::Arc;
@@ -64,8 +68,17 @@ impl IHttpClient for HttpClient {
                     }
 
                     let is_success = (200..300).contains(&status_code);
+                    let content_type = resp
+                        .headers()
+                        .get(reqwest::header::CONTENT_TYPE)
+                        .and_then(|v| v.to_str().ok());
+                    let encoding = encoding.resolve(content_type);
+                    let charset = match self.config.http.decode_charset {
+                        true => TextCharset::from_content_type(content_type),
+                        false => None,
+                    };
 
-                    let headers: Vec<NetHttpHeader> = resp
+                    let headers: NetHttpHeaders = resp
                         .headers()
                         .iter()
                         .map(|(k, v)| {
@@ -80,14 +93,18 @@ impl IHttpClient for HttpClient {
                         .bytes()
                         .await
                         .map_err(|_| NetResultStatus::ConnectionError)?;
+                    let bytes = match charset {
+                        Some(charset) => charset.to_utf8(bytes.to_vec()),
+                        None => bytes.to_vec(),
+                    };
 
                     let (body, encoding) = if is_success {
-                        StreamBuffer::try_current_buffer(bytes.to_vec(), encoding)
+                        StreamBuffer::try_current_buffer(bytes, encoding)
                     } else {
-                        (bytes.to_vec(), StreamEncoding::Raw)
+                        (bytes, StreamEncoding::Raw)
                     };
 
-                    return Ok(NetResponseHttp::new(status_code, body, headers, encoding));
+                    return Ok(NetResponseHttp::new(status_code, body.into(), headers, encoding));
                 }
             }
 
@@ -128,13 +145,29 @@ impl HttpClient {
     ) -> Result<RequestBuilder, NetResultStatus> {
         let method = reqwest::Method::from_bytes(method.as_bytes())
             .map_err(|_| NetResultStatus::InvalidRequestParameters)?;
-        let mut req = self.sender.request(method.clone(), url.to_string());
+        // fetch rejects URLs with embedded credentials, send them as a header instead
+        let addr = Utils::parse_http_url(url)?;
+        self.config
+            .host_policy
+            .check_address(&addr)
+            .map_err(|e| e.status())?;
+        let mut req = self.sender.request(method.clone(), addr.url);
 
         if let Some(headers) = headers {
             for h in headers {
                 req = req.header(h.key(), h.value());
             }
         }
+        let has_authorization = headers.is_some_and(|headers| {
+            headers
+                .iter()
+                .any(|h| h.key_ref().eq_ignore_ascii_case("authorization"))
+        });
+        if let Some(authorization) = addr.authorization
+            && !has_authorization
+        {
+            req = req.header(reqwest::header::AUTHORIZATION, authorization);
+        }
         if let Some(b) = body {
             req = req.body(b.to_vec());
         }
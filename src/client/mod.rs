@@ -1,3 +1,6 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod failover;
+#[cfg(feature = "grpc")]
 pub mod grpc;
 pub mod http;
 #[cfg(not(target_arch = "wasm32"))]
@@ -5,4 +8,5 @@ pub mod native;
 pub mod raw;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
+#[cfg(feature = "websocket")]
 pub mod websocket;
@@ -1,63 +1,178 @@
-use tokio::sync::{broadcast, oneshot};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::{Stream, stream};
+use hyper::body::Incoming;
+use tokio::sync::{broadcast::error::RecvError, oneshot};
 
-use crate::types::{
-    config::NetConfig,
-    error::NetResultStatus,
-    native::request::{NetHttpHeaderRef, NetHttpRetryConfig},
-    response::NetResponseHttp,
+#[cfg(feature = "grpc")]
+use std::sync::Arc;
+
+#[cfg(feature = "grpc")]
+use crate::client::grpc::interceptor::NetGrpcInterceptor;
+use crate::{
+    types::{
+        config::NetConfig,
+        error::NetError,
+        native::request::{NetHttpHeaderRef, NetHttpResume, NetHttpRetryConfig},
+        response::{NetGrpcConnectivity, NetResponseGrpcUnary, NetResponseHttp},
+    },
+    utils::{buffer::StreamEncoding, subscribers::NetSubscription},
 };
 
 #[async_trait::async_trait]
 pub trait IClient {
-    async fn connect(&self) -> Result<(), NetResultStatus>;
+    async fn connect(&self) -> Result<(), NetError>;
     fn get_config(&self) -> &NetConfig;
 }
 
 #[async_trait::async_trait]
 pub trait IStreamClient: IClient + Send + Sync + 'static {
-    async fn send<'a>(&self, data: &'a [u8]) -> Result<(), NetResultStatus>;
+    async fn send<'a>(&self, data: &'a [u8]) -> Result<(), NetError>;
     async fn subscribe(
         &self,
-    ) -> Result<broadcast::Receiver<Result<Option<Vec<u8>>, NetResultStatus>>, NetResultStatus>;
+    ) -> Result<NetSubscription<Result<Option<Vec<u8>>, NetError>>, NetError>;
 
     async fn close(&self);
 }
 
 pub struct GrpcStreamHandle {
-    pub rx: broadcast::Receiver<Result<Option<Vec<u8>>, NetResultStatus>>,
+    pub rx: NetSubscription<Result<Option<Vec<u8>>, NetError>>,
     pub cancel: oneshot::Sender<()>,
 }
 impl GrpcStreamHandle {
     pub fn cancel(self) {
         let _ = self.cancel.send(());
     }
+
+    /// Server messages as a `Stream`; dropping it cancels the call
+    pub fn into_stream(self) -> NetMessageStream {
+        let mut stream = NetMessageStream::new(self.rx);
+        stream.cancel = Some(self.cancel);
+        stream
+    }
+}
+
+/// `Stream` over a subscription.
+///
+/// Ends after `Ok(None)`, the first connection error, or when the subscription closes.
+/// Dropped frames show up as a `StreamOverflow` error that does not end it.
+pub struct NetMessageStream {
+    inner: Pin<Box<dyn Stream<Item = Result<Vec<u8>, NetError>> + Send>>,
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+impl NetMessageStream {
+    pub fn new(rx: NetSubscription<Result<Option<Vec<u8>>, NetError>>) -> Self {
+        let inner = stream::unfold(Some(rx), |rx| async move {
+            let mut rx = rx?;
+            match rx.recv().await {
+                Ok(Ok(Some(data))) => Some((Ok(data), Some(rx))),
+                Ok(Err(e)) => Some((Err(e), None)),
+                Ok(Ok(None)) | Err(RecvError::Closed) => None,
+                Err(RecvError::Lagged(lost)) => {
+                    Some((Err(NetError::stream_overflow(lost)), Some(rx)))
+                }
+            }
+        });
+        Self {
+            inner: Box::pin(inner),
+            cancel: None,
+        }
+    }
+}
+
+impl From<NetSubscription<Result<Option<Vec<u8>>, NetError>>> for NetMessageStream {
+    fn from(rx: NetSubscription<Result<Option<Vec<u8>>, NetError>>) -> Self {
+        NetMessageStream::new(rx)
+    }
+}
+
+impl Stream for NetMessageStream {
+    type Item = Result<Vec<u8>, NetError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
 }
 #[async_trait::async_trait]
 pub trait IGrpcClient: IClient + Send + Sync {
+    /// `deadline` goes to the server as `grpc-timeout`, which cancels the call once it passed;
+    /// `authority` is the call's `:authority` in place of `NetConfig::grpc_authority`
     async fn unary<'a>(
         &self,
-        buffer: &'a [u8],
+        buffer: Bytes,
         method_name: &'a str,
-    ) -> Result<Vec<u8>, NetResultStatus>;
+        metadata: &[NetHttpHeaderRef<'a>],
+        deadline: Option<Duration>,
+        authority: Option<&'a str>,
+    ) -> Result<NetResponseGrpcUnary, NetError>;
 
     async fn stream<'a>(
         &self,
-        buffer: &'a [u8],
+        buffer: Bytes,
         method_name: &'a str,
-    ) -> Result<GrpcStreamHandle, NetResultStatus>;
+        metadata: &[NetHttpHeaderRef<'a>],
+        deadline: Option<Duration>,
+        authority: Option<&'a str>,
+    ) -> Result<GrpcStreamHandle, NetError>;
+
+    /// Runs `interceptor` around the calls made from now on, after those added before
+    #[cfg(feature = "grpc")]
+    fn add_interceptor(&self, interceptor: Arc<dyn NetGrpcInterceptor>);
+
+    /// State of the channel as of the last call or connect, without dialing
+    fn connectivity(&self) -> NetGrpcConnectivity;
 
     async fn close(&self);
 }
+/// Where `IHttpClient::download` writes a body, and who hears of its progress
+pub struct HttpDownloadSink<'a> {
+    pub path: &'a str,
+    /// Bytes written so far and the length the server announced, if any; a resumed download
+    /// counts the bytes it started from
+    pub progress: &'a (dyn Fn(u64, Option<u64>) + Send + Sync),
+    pub resume: Option<NetHttpResume<'a>>,
+}
+
 #[async_trait::async_trait]
 pub trait IHttpClient: IClient + Send + Sync {
     async fn send<'a>(
         &self,
         url: &'a str,
         method: &'a str,
-        body: Option<&'a [u8]>,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        encoding: StreamEncoding,
+        retry_config: &NetHttpRetryConfig<'a>,
+    ) -> Result<NetResponseHttp, NetError>;
+
+    /// Opens a server-sent event stream, sending `last_event_id` when it resumes one. The
+    /// body is returned unread once the server answered with `text/event-stream`
+    async fn events<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
+        headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
+        last_event_id: Option<&'a str>,
+    ) -> Result<Incoming, NetError>;
+
+    /// Like `send`, but a successful body is written to the file of `sink` as it arrives
+    /// instead of being returned. Other answers keep their body
+    async fn download<'a>(
+        &self,
+        url: &'a str,
+        method: &'a str,
+        body: Option<Bytes>,
         headers: Option<&Vec<NetHttpHeaderRef<'a>>>,
         retry_config: &NetHttpRetryConfig<'a>,
-    ) -> Result<NetResponseHttp, NetResultStatus>;
+        sink: &HttpDownloadSink<'a>,
+    ) -> Result<NetResponseHttp, NetError>;
 
     async fn close(&self);
 }
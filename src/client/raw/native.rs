@@ -3,40 +3,46 @@ use std::sync::Arc;
 use log::debug;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
-    sync::{Mutex, broadcast},
+    sync::Mutex,
+    task::JoinHandle,
 };
 
 use crate::{
     client::native::{IClient, IStreamClient},
     stream::ConnectStream,
-    types::{config::NetConfig, error::NetResultStatus},
+    types::{config::NetConfig, error::{NetError, NetResultStatus}},
+    utils::subscribers::{NetSubscription, StreamSubscribers},
 };
 
 struct WriterWithHandler<T> {
     pub writer: tokio::io::WriteHalf<T>,
+    /// Reader task, stopped on close since a dead peer may never end it
+    pub reader: JoinHandle<()>,
 }
 impl<T> WriterWithHandler<T>
 where
     T: ConnectStream,
 {
-    async fn send(&mut self, data: &[u8]) -> Result<(), NetResultStatus> {
-        let _ = self
-            .writer
-            .write_all(&data)
-            .await
-            .map_err(|_| NetResultStatus::ConnectionError)?;
-        self.writer
-            .flush()
-            .await
-            .map_err(|_| NetResultStatus::ConnectionError)
+    async fn send(&mut self, data: &[u8]) -> Result<(), NetError> {
+        self.writer.write_all(data).await.map_err(|e| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("socket write failed")
+                .with_source(e)
+        })?;
+        self.writer.flush().await.map_err(|e| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("socket flush failed")
+                .with_source(e)
+        })
     }
     async fn close(&mut self) {
         let _ = self.writer.shutdown().await;
+        self.reader.abort();
     }
 }
 
 pub struct RawStreamClient<T> {
-    incoming: broadcast::Sender<Result<Option<Vec<u8>>, NetResultStatus>>,
+    incoming: Arc<StreamSubscribers<Result<Option<Vec<u8>>, NetError>>>,
     writer: Arc<Mutex<Option<WriterWithHandler<T>>>>,
     config: NetConfig,
 }
@@ -45,10 +51,9 @@ impl<'a, T> RawStreamClient<T>
 where
     T: ConnectStream,
 {
-    pub fn default(config: NetConfig) -> Result<Self, NetResultStatus> {
-        let (tx, _) = broadcast::channel(128);
+    pub fn default(config: NetConfig) -> Result<Self, NetError> {
         Ok(Self {
-            incoming: tx,
+            incoming: Arc::new(StreamSubscribers::new(&config.stream_queue)),
             writer: Arc::new(Mutex::new(None)),
             config: config,
         })
@@ -59,7 +64,7 @@ impl<'a, T> IClient for RawStreamClient<T>
 where
     T: ConnectStream,
 {
-    async fn connect(&self) -> Result<(), NetResultStatus> {
+    async fn connect(&self) -> Result<(), NetError> {
         let mut guard = self.writer.lock().await;
 
         // Already connected
@@ -72,38 +77,34 @@ where
 
         // Clone for background task
         let writer_mutex = Arc::clone(&self.writer);
-        let tx_clone = self.incoming.clone();
+        let incoming = Arc::clone(&self.incoming);
 
-        tokio::spawn(async move {
+        let reader = tokio::spawn(async move {
             let mut buf = [0u8; 4096];
-            loop {
+            let end = loop {
                 match reader.read(&mut buf).await {
-                    Ok(0) => {
-                        let _ = tx_clone.send(Ok(None));
-                        break;
-                    } // EOF
+                    Ok(0) => break Ok(None), // EOF
                     Ok(n) => {
-                        let _ = tx_clone.send(Ok(Some(buf[..n].to_vec())));
+                        incoming.send(Ok(Some(buf[..n].to_vec()))).await;
                     }
                     Err(e)
                         if e.kind() == std::io::ErrorKind::ConnectionReset
                             || e.kind() == std::io::ErrorKind::BrokenPipe =>
                     {
-                        let _ = tx_clone.send(Ok(None));
-                        break;
+                        break Ok(None);
                     }
                     Err(e) => {
-                        let _ = tx_clone.send(Err(NetResultStatus::SocketError));
                         debug!("Socket stream error: {:?}", e);
-                        break;
+                        break Err(NetError::new(NetResultStatus::SocketError).with_source(e));
                     }
                 }
-            }
-            // Connection closed → set writer to None
-            let mut guard = writer_mutex.lock().await;
-            *guard = None;
+            };
+            // Connection closed → set writer to None, before the end is posted so a
+            // subscribe reacting to it reconnects
+            *writer_mutex.lock().await = None;
+            incoming.send(end).await;
         });
-        *guard = Some(WriterWithHandler { writer });
+        *guard = Some(WriterWithHandler { writer, reader });
 
         Ok(())
     }
@@ -117,7 +118,7 @@ impl<T> IStreamClient for RawStreamClient<T>
 where
     T: ConnectStream,
 {
-    async fn send<'a>(&self, data: &'a [u8]) -> Result<(), NetResultStatus> {
+    async fn send<'a>(&self, data: &'a [u8]) -> Result<(), NetError> {
         self.connect().await?; // ensure connection exists
 
         let mut guard = self.writer.lock().await;
@@ -125,22 +126,25 @@ where
         if let Some(writer) = guard.as_mut() {
             writer.send(&data).await
         } else {
-            Err(NetResultStatus::InternalError)
+            Err(NetResultStatus::InternalError.into())
         }
     }
 
     async fn subscribe(
         &self,
-    ) -> Result<broadcast::Receiver<Result<Option<Vec<u8>>, NetResultStatus>>, NetResultStatus>
+    ) -> Result<NetSubscription<Result<Option<Vec<u8>>, NetError>>, NetError>
     {
+        // subscribed first, so nothing read right after connecting is missed
+        let subscription = self.incoming.subscribe();
         self.connect().await?;
-        Ok(self.incoming.subscribe())
+        Ok(subscription)
     }
+    /// Ends the subscriptions itself, the stopped reader can not
     async fn close(&self) {
-        let mut guard = self.writer.lock().await;
-        if let Some(writer) = guard.as_mut() {
-            let _ = writer.close().await;
+        let writer = self.writer.lock().await.take();
+        if let Some(mut writer) = writer {
+            writer.close().await;
+            self.incoming.send(Ok(None)).await;
         }
-        *guard = None
     }
 }
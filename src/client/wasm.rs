@@ -1,13 +1,20 @@
-use tokio::sync::{broadcast, oneshot};
+use std::time::Duration;
 
+use tokio::sync::oneshot;
+
+#[cfg(feature = "grpc")]
+use std::sync::Arc;
+
+#[cfg(feature = "grpc")]
+use crate::client::grpc::interceptor::NetGrpcInterceptor;
 use crate::{
     types::{
         config::{NetConfig, NetHttpHeader},
-        error::NetResultStatus,
+        error::{NetError, NetResultStatus},
         request::NetHttpRetryConfig,
-        response::NetResponseHttp,
+        response::{NetGrpcConnectivity, NetResponseGrpcUnary, NetResponseHttp},
     },
-    utils::buffer::StreamEncoding,
+    utils::{buffer::StreamEncoding, subscribers::NetSubscription},
 };
 
 #[async_trait::async_trait(?Send)]
@@ -21,13 +28,13 @@ pub trait IStreamClient: IClient + Send + Sync + 'static {
     async fn send(&self, data: &[u8]) -> Result<(), NetResultStatus>;
     async fn subscribe(
         &self,
-    ) -> Result<broadcast::Receiver<Result<Option<Vec<u8>>, NetResultStatus>>, NetResultStatus>;
+    ) -> Result<NetSubscription<Result<Option<Vec<u8>>, NetResultStatus>>, NetResultStatus>;
 
     async fn close(&self);
 }
 
 pub struct GrpcStreamHandle {
-    pub rx: broadcast::Receiver<Result<Option<Vec<u8>>, NetResultStatus>>,
+    pub rx: NetSubscription<Result<Option<Vec<u8>>, NetError>>,
     pub cancel: oneshot::Sender<()>,
 }
 impl GrpcStreamHandle {
@@ -37,15 +44,30 @@ impl GrpcStreamHandle {
 }
 #[async_trait::async_trait(?Send)]
 pub trait IGrpcClient: IClient + Send + Sync {
-    /// Send raw bytes
-    async fn unary(&self, buffer: &[u8], method_name: &str) -> Result<Vec<u8>, NetResultStatus>;
+    /// Send raw bytes; `deadline` goes to the server as `grpc-timeout`
+    async fn unary(
+        &self,
+        buffer: &[u8],
+        method_name: &str,
+        metadata: &[NetHttpHeader],
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseGrpcUnary, NetError>;
 
-    /// Send a streaming RPC and receive a broadcast channel for multiple messages
+    /// Send a streaming RPC and receive a subscription for multiple messages
     async fn stream(
         &self,
         buffer: &[u8],
         method_name: &str,
-    ) -> Result<GrpcStreamHandle, NetResultStatus>;
+        metadata: &[NetHttpHeader],
+        deadline: Option<Duration>,
+    ) -> Result<GrpcStreamHandle, NetError>;
+
+    /// Runs `interceptor` around the calls made from now on, after those added before
+    #[cfg(feature = "grpc")]
+    fn add_interceptor(&self, interceptor: Arc<dyn NetGrpcInterceptor>);
+
+    /// State of the client as of the last call, without dialing
+    fn connectivity(&self) -> NetGrpcConnectivity;
 
     async fn close(&self);
 }
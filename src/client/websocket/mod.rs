@@ -1,4 +1,6 @@
 #[cfg(not(target_arch = "wasm32"))]
 pub mod native;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shared;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
@@ -3,45 +3,53 @@ use std::{str::FromStr, sync::Arc};
 use crate::{
     client::native::{IClient, IStreamClient},
     stream::ConnectStream,
-    types::{config::NetConfig, error::NetResultStatus},
+    types::{config::NetConfig, error::{NetError, NetResultStatus}},
+    utils::subscribers::{NetSubscription, StreamSubscribers},
 };
 use bytes::Bytes;
 use futures::{SinkExt, StreamExt, stream::SplitSink};
-use http::{HeaderName, HeaderValue};
+use http::{HeaderName, HeaderValue, header::AUTHORIZATION};
 use log::debug;
-use tokio::sync::{Mutex, broadcast};
+use tokio::{sync::Mutex, task::JoinHandle};
 use tokio_tungstenite::{
     WebSocketStream, client_async,
     tungstenite::{Message, client::IntoClientRequest},
 };
 struct WriterWithHandler<T> {
     pub writer: SplitSink<WebSocketStream<Box<T>>, Message>,
+    /// Reader task, stopped on close since a dead peer may never answer the close frame
+    pub reader: JoinHandle<()>,
 }
 impl<T> WriterWithHandler<T>
 where
     T: ConnectStream,
 {
-    async fn send(&mut self, data: &[u8]) -> Result<(), NetResultStatus> {
+    async fn send(&mut self, data: &[u8]) -> Result<(), NetError> {
         self.writer
             .send(Message::Binary(Bytes::copy_from_slice(data)))
             .await
             .map_err(|e| {
                 debug!("Socket write error: {:?}", e);
-                NetResultStatus::ConnectionError
+                NetError::new(NetResultStatus::ConnectionError)
+                    .with_message("websocket write failed")
+                    .with_source(e)
             })?;
         self.writer.flush().await.map_err(|e| {
             debug!("Socket write error: {:?}", e);
-            NetResultStatus::ConnectionError
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("websocket flush failed")
+                .with_source(e)
         })
     }
     async fn close(&mut self) {
         let _ = self.writer.send(Message::Close(None)).await;
         let _ = self.writer.close().await;
+        self.reader.abort();
     }
 }
 pub struct WsStreamClient<T> {
     writer: Arc<Mutex<Option<WriterWithHandler<T>>>>,
-    incoming: broadcast::Sender<Result<Option<Vec<u8>>, NetResultStatus>>,
+    incoming: Arc<StreamSubscribers<Result<Option<Vec<u8>>, NetError>>>,
     config: NetConfig,
 }
 
@@ -49,10 +57,9 @@ impl<T> WsStreamClient<T>
 where
     T: ConnectStream,
 {
-    pub fn default(config: NetConfig) -> Result<Self, NetResultStatus> {
-        let (tx, _) = broadcast::channel(128);
+    pub fn default(config: NetConfig) -> Result<Self, NetError> {
         Ok(Self {
-            incoming: tx,
+            incoming: Arc::new(StreamSubscribers::new(&config.stream_queue)),
             writer: Arc::new(Mutex::new(None)),
             config: config,
         })
@@ -63,7 +70,7 @@ impl<'a, T> IClient for WsStreamClient<T>
 where
     T: ConnectStream,
 {
-    async fn connect(&self) -> Result<(), NetResultStatus> {
+    async fn connect(&self) -> Result<(), NetError> {
         let mut guard = self.writer.lock().await;
 
         if guard.is_some() {
@@ -80,7 +87,9 @@ where
             .into_client_request()
             .map_err(|e| {
                 debug!("Invalid socket url: {:?}, {:#?}", e, self.config.addr.url);
-                NetResultStatus::InvalidUrl
+                NetError::new(NetResultStatus::InvalidUrl)
+                    .with_url(self.config.addr.url.clone())
+                    .with_source(e)
             })?;
 
         // Override headers from config if present
@@ -89,46 +98,77 @@ where
             request.headers_mut().insert(
                 HeaderName::from_str(&header.key()).map_err(|e| {
                     debug!("Socket config headers error: {:?}", e);
-                    NetResultStatus::InvalidRequestParameters
+                    NetError::new(NetResultStatus::InvalidRequestParameters)
+                        .with_message(format!("invalid header name: {}", header.key_ref()))
+                        .with_source(e)
                 })?,
                 HeaderValue::from_str(&header.value()).unwrap(),
             );
         }
 
+        if let Some(authorization) = &self.config.addr.authorization
+            && !request.headers().contains_key(AUTHORIZATION)
+        {
+            request.headers_mut().insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(authorization).map_err(|e| {
+                    NetError::new(NetResultStatus::InvalidUrl)
+                        .with_message("invalid url credentials")
+                        .with_source(e)
+                })?,
+            );
+        }
+        // rebuilt on every connect, so reconnects authenticate too
+        if let Some(auth) = &self.config.http.auth
+            && !request.headers().contains_key(AUTHORIZATION)
+        {
+            request.headers_mut().insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&auth.header_value()).map_err(|e| {
+                    NetError::new(NetResultStatus::InvalidConfigParameters)
+                        .with_message("invalid auth credentials")
+                        .with_source(e)
+                })?,
+            );
+        }
+
         // Connect WebSocket
         let (ws_stream, _response) = client_async(request, boxed_stream).await.map_err(|e| {
             debug!("Socket connection error: {:?}", e);
-            NetResultStatus::ConnectionError
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("websocket handshake failed")
+                .with_url(self.config.addr.url.clone())
+                .with_source(e)
         })?;
         let (write, mut read) = ws_stream.split();
 
         // Spawn background reader
-        let tx_clone = self.incoming.clone();
+        let incoming = Arc::clone(&self.incoming);
         let writer_mutex = Arc::clone(&self.writer);
 
-        tokio::spawn(async move {
+        let reader = tokio::spawn(async move {
             loop {
                 let msg = read.next().await;
                 match msg {
                     Some(Ok(Message::Binary(data))) => {
-                        let _ = tx_clone.send(Ok(Some(data.to_vec())));
+                        incoming.send(Ok(Some(data.to_vec()))).await;
                     }
                     Some(Ok(Message::Text(utf8))) => {
-                        let _ = tx_clone.send(Ok(Some(utf8.as_bytes().to_vec())));
+                        incoming.send(Ok(Some(utf8.as_bytes().to_vec()))).await;
                     }
                     Some(Ok(_)) => {}
+                    // the writer is cleared first, so a subscribe after the end reconnects
                     None => {
-                        let _ = tx_clone.send(Ok(None));
-                        let mut guard = writer_mutex.lock().await;
-                        *guard = None;
+                        *writer_mutex.lock().await = None;
+                        incoming.send(Ok(None)).await;
                         break;
                     }
                     Some(Err(e)) => {
                         debug!("Socket stream error: {:?}", e);
-                        let _ = tx_clone.send(Err(NetResultStatus::SocketError));
-                        // On disconnect, set writer to None
-                        let mut guard = writer_mutex.lock().await;
-                        *guard = None;
+                        *writer_mutex.lock().await = None;
+                        incoming
+                            .send(Err(NetError::new(NetResultStatus::SocketError).with_source(e)))
+                            .await;
                         break;
                     }
                 }
@@ -136,7 +176,10 @@ where
         });
 
         // Save writer in mutex
-        *guard = Some(WriterWithHandler { writer: write });
+        *guard = Some(WriterWithHandler {
+            writer: write,
+            reader,
+        });
 
         Ok(())
     }
@@ -150,29 +193,32 @@ impl<T> IStreamClient for WsStreamClient<T>
 where
     T: ConnectStream,
 {
-    async fn send<'a>(&self, data: &'a [u8]) -> Result<(), NetResultStatus> {
+    async fn send<'a>(&self, data: &'a [u8]) -> Result<(), NetError> {
         self.connect().await?;
         let mut guard = self.writer.lock().await;
 
         if let Some(writer) = guard.as_mut() {
             writer.send(&data).await
         } else {
-            Err(NetResultStatus::InternalError)
+            Err(NetResultStatus::InternalError.into())
         }
     }
 
     async fn subscribe(
         &self,
-    ) -> Result<broadcast::Receiver<Result<Option<Vec<u8>>, NetResultStatus>>, NetResultStatus>
+    ) -> Result<NetSubscription<Result<Option<Vec<u8>>, NetError>>, NetError>
     {
+        // subscribed first, so nothing read right after the handshake is missed
+        let subscription = self.incoming.subscribe();
         self.connect().await?;
-        Ok(self.incoming.subscribe())
+        Ok(subscription)
     }
+    /// Ends the subscriptions itself, the stopped reader can not
     async fn close(&self) {
-        let mut guard = self.writer.lock().await;
-        if let Some(writer) = guard.as_mut() {
-            let _ = writer.close().await;
+        let writer = self.writer.lock().await.take();
+        if let Some(mut writer) = writer {
+            writer.close().await;
+            self.incoming.send(Ok(None)).await;
         }
-        *guard = None
     }
 }
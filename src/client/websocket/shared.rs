@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use log::debug;
+use once_cell::sync::Lazy;
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+
+use crate::{
+    client::native::{IClient, IStreamClient},
+    types::{config::NetConfig, error::NetError},
+    utils::subscribers::{NetSubscription, StreamSubscribers},
+};
+
+type Frame = Result<Option<Vec<u8>>, NetError>;
+
+/// Url, mode, TLS mode, headers, host policy and custom TLS config of a shared connection
+type SharedKey = String;
+
+struct SharedConnection {
+    client: Arc<dyn IStreamClient>,
+    /// Transports that joined and did not close yet
+    users: usize,
+}
+
+static SHARED_CONNECTIONS: Lazy<Mutex<HashMap<SharedKey, SharedConnection>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// One WebSocket connection used by every transport created for the same endpoint with
+/// `NetConfig::share_connection`.
+///
+/// Every transport receives each message through subscribers of its own, fed from the
+/// shared connection, and their sends go out over it in turn. Closing a transport ends its
+/// subscribers as if the connection closed but only leaves the share; the connection is
+/// closed with the last one. A transport used again after closing joins again.
+pub struct SharedStreamClient {
+    key: SharedKey,
+    client: Arc<dyn IStreamClient>,
+    /// This transport's own config; only the connection is shared
+    config: NetConfig,
+    joined: Mutex<bool>,
+    incoming: Arc<StreamSubscribers<Frame>>,
+    /// Task copying the shared connection's messages into `incoming`
+    forward: tokio::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SharedStreamClient {
+    /// Joins the connection for `config`, creating it with `create` when there is none
+    pub fn join(
+        config: NetConfig,
+        create: fn(NetConfig) -> Result<Box<dyn IStreamClient>, NetError>,
+    ) -> Result<Self, NetError> {
+        let key = SharedStreamClient::key(&config);
+        let mut shared = SharedStreamClient::lock();
+        let client = match shared.get_mut(&key) {
+            Some(connection) => {
+                connection.users += 1;
+                Arc::clone(&connection.client)
+            }
+            None => {
+                let client: Arc<dyn IStreamClient> = Arc::from(create(config.clone())?);
+                shared.insert(
+                    key.clone(),
+                    SharedConnection {
+                        client: Arc::clone(&client),
+                        users: 1,
+                    },
+                );
+                client
+            }
+        };
+        Ok(Self {
+            key,
+            client,
+            incoming: Arc::new(StreamSubscribers::new(&config.stream_queue)),
+            config,
+            joined: Mutex::new(true),
+            forward: tokio::sync::Mutex::new(None),
+        })
+    }
+
+    fn key(config: &NetConfig) -> SharedKey {
+        let headers: Vec<(&str, &str)> = config
+            .http
+            .headers
+            .iter()
+            .map(|header| (header.key_ref(), header.value_ref()))
+            .collect();
+        format!(
+            "{}|{:?}|{:?}|{:?}|{:?}|{:?}",
+            config.addr.url,
+            config.mode,
+            config.tls_mode,
+            headers,
+            config.host_policy,
+            config.tls_client_config.as_ref().map(Arc::as_ptr),
+        )
+    }
+
+    fn lock() -> std::sync::MutexGuard<'static, HashMap<SharedKey, SharedConnection>> {
+        SHARED_CONNECTIONS.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Subscribes to the shared connection unless a forward task is running
+    async fn start_forward(&self) -> Result<(), NetError> {
+        let mut forward = self.forward.lock().await;
+        if forward.as_ref().is_some_and(|task| !task.is_finished()) {
+            return Ok(());
+        }
+        let mut rx = self.client.subscribe().await?;
+        let incoming = Arc::clone(&self.incoming);
+        *forward = Some(tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(Ok(Some(data))) => incoming.send(Ok(Some(data))).await,
+                    // the connection ended; the next subscribe reconnects it
+                    Ok(end) => {
+                        incoming.send(end).await;
+                        return;
+                    }
+                    Err(RecvError::Closed) => {
+                        incoming.send(Ok(None)).await;
+                        return;
+                    }
+                    Err(RecvError::Lagged(lost)) => {
+                        debug!("shared connection dropped {} messages", lost);
+                        incoming.send(Err(NetError::stream_overflow(lost))).await;
+                        return;
+                    }
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    /// Counts this transport as a user again after it closed
+    fn rejoin(&self) {
+        let mut joined = self.joined.lock().unwrap_or_else(|e| e.into_inner());
+        if *joined {
+            return;
+        }
+        let mut shared = SharedStreamClient::lock();
+        match shared.get_mut(&self.key) {
+            Some(connection) if Arc::ptr_eq(&connection.client, &self.client) => {
+                connection.users += 1
+            }
+            // a new connection took the endpoint meanwhile; this one stays private
+            Some(_) => {}
+            None => {
+                shared.insert(
+                    self.key.clone(),
+                    SharedConnection {
+                        client: Arc::clone(&self.client),
+                        users: 1,
+                    },
+                );
+            }
+        }
+        *joined = true;
+    }
+
+    /// Whether this transport was the last user, so the connection has to be closed
+    fn leave(&self) -> bool {
+        let mut joined = self.joined.lock().unwrap_or_else(|e| e.into_inner());
+        if !*joined {
+            return false;
+        }
+        *joined = false;
+        let mut shared = SharedStreamClient::lock();
+        match shared.get_mut(&self.key) {
+            Some(connection) if Arc::ptr_eq(&connection.client, &self.client) => {
+                connection.users -= 1;
+                if connection.users > 0 {
+                    return false;
+                }
+                shared.remove(&self.key);
+                true
+            }
+            // the private connection of `rejoin`
+            _ => true,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IClient for SharedStreamClient {
+    async fn connect(&self) -> Result<(), NetError> {
+        self.rejoin();
+        self.client.connect().await
+    }
+
+    fn get_config(&self) -> &NetConfig {
+        &self.config
+    }
+}
+
+#[async_trait::async_trait]
+impl IStreamClient for SharedStreamClient {
+    async fn send<'a>(&self, data: &'a [u8]) -> Result<(), NetError> {
+        self.rejoin();
+        self.client.send(data).await
+    }
+
+    async fn subscribe(&self) -> Result<NetSubscription<Frame>, NetError> {
+        self.rejoin();
+        // subscribed first, so nothing forwarded right after connecting is missed
+        let subscription = self.incoming.subscribe();
+        self.start_forward().await?;
+        Ok(subscription)
+    }
+
+    async fn close(&self) {
+        if let Some(task) = self.forward.lock().await.take() {
+            task.abort();
+            self.incoming.send(Ok(None)).await;
+        }
+        if self.leave() {
+            self.client.close().await;
+        }
+    }
+}
@@ -2,10 +2,11 @@ use std::{str::FromStr, sync::Arc};
 
 use crate::client::wasm::{IClient, IStreamClient};
 use crate::types::{config::NetConfig, error::NetResultStatus};
+use crate::utils::subscribers::{NetSubscription, StreamSubscribers};
 use bytes::Bytes;
 use futures::{SinkExt, StreamExt};
 use http::{HeaderName, HeaderValue};
-use tokio::sync::{Mutex, broadcast};
+use tokio::sync::Mutex;
 use wasm_bindgen_futures::spawn_local;
 use ws_stream_wasm::{WsMessage, WsMeta};
 
@@ -29,16 +30,14 @@ impl WriterWithHandler {
 
 pub struct WsStreamClient {
     writer: Arc<Mutex<Option<WriterWithHandler>>>,
-    incoming: broadcast::Sender<Result<Option<Vec<u8>>, NetResultStatus>>,
+    incoming: Arc<StreamSubscribers<Result<Option<Vec<u8>>, NetResultStatus>>>,
     config: NetConfig,
 }
 
 impl WsStreamClient {
     pub fn default(config: NetConfig) -> Result<Self, NetResultStatus> {
-        let (tx, _) = broadcast::channel(128);
-
         Ok(Self {
-            incoming: tx,
+            incoming: Arc::new(StreamSubscribers::new(&config.stream_queue)),
             writer: Arc::new(Mutex::new(None)),
             config,
         })
@@ -54,6 +53,10 @@ impl IClient for WsStreamClient {
             return Ok(());
         }
 
+        self.config
+            .host_policy
+            .check_address(&self.config.addr)
+            .map_err(|e| e.status())?;
         let url = self.config.addr.url.clone();
 
         // Headers (browser only allows limited custom headers!)
@@ -77,21 +80,21 @@ impl IClient for WsStreamClient {
 
         let (write, mut read) = ws_stream.split();
 
-        let tx_clone = self.incoming.clone();
+        let incoming = Arc::clone(&self.incoming);
         let writer_mutex = Arc::clone(&self.writer);
         // ws_stream.
         spawn_local(async move {
             while let Some(msg) = read.next().await {
                 match msg {
                     WsMessage::Binary(data) => {
-                        let _ = tx_clone.send(Ok(Some(data)));
+                        incoming.send(Ok(Some(data))).await;
                     }
                     WsMessage::Text(text) => {
-                        let _ = tx_clone.send(Ok(Some(text.into_bytes())));
+                        incoming.send(Ok(Some(text.into_bytes()))).await;
                     }
                 }
             }
-            let _ = tx_clone.send(Ok(None));
+            incoming.send(Ok(None)).await;
 
             let mut guard = writer_mutex.lock().await;
             *guard = None;
@@ -123,10 +126,11 @@ impl IStreamClient for WsStreamClient {
 
     async fn subscribe(
         &self,
-    ) -> Result<broadcast::Receiver<Result<Option<Vec<u8>>, NetResultStatus>>, NetResultStatus>
+    ) -> Result<NetSubscription<Result<Option<Vec<u8>>, NetResultStatus>>, NetResultStatus>
     {
+        let subscription = self.incoming.subscribe();
         self.connect().await?;
-        Ok(self.incoming.subscribe())
+        Ok(subscription)
     }
 
     async fn close(&self) {
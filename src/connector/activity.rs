@@ -0,0 +1,205 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{
+    types::{
+        config::{NetConfig, NetProtocol},
+        error::NetResultStatus,
+        native::request::{
+            NetRequest, NetRequestGrpc, NetRequestHttpEvents, NetRequestKind, NetRequestSocket,
+        },
+        response::{NetResponseGrpc, NetResponseKind},
+    },
+    utils::Utils,
+};
+
+/// Entries kept by instances created with `debug`
+pub(crate) const DEBUG_CAPACITY: u32 = 100;
+
+/// One finished transport request, as `dart_transporter_activity` lists it
+#[derive(Serialize)]
+pub(crate) struct ActivityEntry {
+    request_id: u32,
+    transport_id: u32,
+    protocol: &'static str,
+    /// e.g. `http`, `grpc_unary` or `socket_send`
+    kind: &'static str,
+    /// Http method or gRPC method path
+    method: Option<String>,
+    url: String,
+    /// Unix time in milliseconds
+    started_at: u64,
+    duration_ms: u64,
+    request_bytes: Option<usize>,
+    response_bytes: Option<usize>,
+    /// Http status code
+    status: Option<u16>,
+    error: Option<String>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+/// Bounded log of the latest requests of a `DartTransporter`, for an in-app network
+/// inspector that reads it on demand instead of receiving every event.
+///
+/// Only metadata is kept, never bodies; the oldest entry goes once `capacity` is reached.
+/// A capacity of 0 keeps nothing.
+pub(crate) struct ActivityLog {
+    entries: Mutex<VecDeque<ActivityEntry>>,
+    capacity: AtomicUsize,
+}
+
+impl ActivityLog {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            capacity: AtomicUsize::new(capacity as usize),
+        }
+    }
+
+    /// Drops the oldest entries beyond the new capacity
+    pub fn set_capacity(&self, capacity: u32) {
+        let mut entries = self.lock();
+        self.capacity.store(capacity as usize, Ordering::Relaxed);
+        while entries.len() > capacity as usize {
+            entries.pop_front();
+        }
+    }
+
+    /// The entry of `request` while it runs, `None` when nothing is kept
+    pub fn start(&self, request: &NetRequest<'_>, config: &NetConfig) -> Option<ActivityEntry> {
+        if self.capacity() == 0 {
+            return None;
+        }
+        let (kind, method, url, request_bytes) = match &request.kind {
+            NetRequestKind::Http(http) => (
+                match http.download_path {
+                    Some(_) => "http_download",
+                    None => "http",
+                },
+                Some(http.method.to_string()),
+                // credentials in the url are not logged, like bodies
+                Utils::strip_userinfo(http.url),
+                http.body.as_ref().map(|body| body.len()),
+            ),
+            NetRequestKind::Grpc(grpc) => {
+                let (kind, method, data) = match grpc {
+                    NetRequestGrpc::Unary(unary) => {
+                        ("grpc_unary", Some(unary.method), Some(&unary.data))
+                    }
+                    NetRequestGrpc::Stream(stream) => {
+                        ("grpc_stream", Some(stream.method), Some(&stream.data))
+                    }
+                    NetRequestGrpc::Unsubscribe(_) => ("grpc_unsubscribe", None, None),
+                    NetRequestGrpc::Health(health) => ("grpc_health", Some(health.method()), None),
+                    NetRequestGrpc::Credit(_) => ("grpc_credit", None, None),
+                    NetRequestGrpc::State => ("grpc_state", None, None),
+                    NetRequestGrpc::Stats => ("grpc_stats", None, None),
+                    NetRequestGrpc::Rebuild(rebuild) => ("grpc_rebuild", None, Some(&rebuild.data)),
+                };
+                (
+                    kind,
+                    method.map(str::to_string),
+                    config.addr.url.clone(),
+                    data.map(|data| data.len()),
+                )
+            }
+            NetRequestKind::Socket(socket) => {
+                let (kind, request_bytes) = match socket {
+                    NetRequestSocket::Subscribe(_) => ("socket_subscribe", None),
+                    NetRequestSocket::Unsubscribe(_) => ("socket_unsubscribe", None),
+                    NetRequestSocket::Send(send) => ("socket_send", Some(send.data.len())),
+                    NetRequestSocket::BufferStatus(_) => ("socket_buffer_status", None),
+                    NetRequestSocket::FlushBuffer(_) => ("socket_flush_buffer", None),
+                };
+                (kind, None, config.addr.url.clone(), request_bytes)
+            }
+            NetRequestKind::HttpEvents(NetRequestHttpEvents::Subscribe(http)) => (
+                "http_events_subscribe",
+                Some(http.method.to_string()),
+                Utils::strip_userinfo(http.url),
+                http.body.as_ref().map(|body| body.len()),
+            ),
+            NetRequestKind::HttpEvents(NetRequestHttpEvents::Unsubscribe(_)) => (
+                "http_events_unsubscribe",
+                None,
+                config.addr.url.clone(),
+                None,
+            ),
+            _ => ("other", None, config.addr.url.clone(), None),
+        };
+        Some(ActivityEntry {
+            request_id: request.id,
+            transport_id: request.transport_id,
+            protocol: match config.protocol {
+                NetProtocol::Http => "http",
+                NetProtocol::Grpc => "grpc",
+                NetProtocol::WebSocket => "websocket",
+                NetProtocol::Socket => "socket",
+            },
+            kind,
+            method,
+            url,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_millis() as u64),
+            duration_ms: 0,
+            request_bytes,
+            response_bytes: None,
+            status: None,
+            error: None,
+            started: Instant::now(),
+        })
+    }
+
+    /// Records how `entry`'s request ended
+    pub fn finish(&self, mut entry: ActivityEntry, response: &NetResponseKind) {
+        entry.duration_ms = entry.started.elapsed().as_millis() as u64;
+        match response {
+            NetResponseKind::Http(http) => {
+                entry.status = Some(http.status_code());
+                entry.response_bytes = Some(http.body_ref().len());
+            }
+            NetResponseKind::Grpc(NetResponseGrpc::Unary(unary)) => {
+                entry.response_bytes = Some(unary.data_ref().len());
+            }
+            NetResponseKind::ResponseError(e) => entry.error = Some(e.to_string()),
+            _ => {}
+        }
+        let mut entries = self.lock();
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return;
+        }
+        while entries.len() >= capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// The entries as a JSON array, oldest first; `clear` empties the log
+    pub fn to_json(&self, clear: bool) -> Result<String, NetResultStatus> {
+        let mut entries = self.lock();
+        let json = serde_json::to_string(&*entries).map_err(|_| NetResultStatus::InternalError)?;
+        if clear {
+            entries.clear();
+        }
+        Ok(json)
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<ActivityEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
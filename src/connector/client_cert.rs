@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+        mpsc::{self, SyncSender},
+    },
+    time::Duration,
+};
+
+use log::debug;
+use once_cell::sync::Lazy;
+use rustls::sign::CertifiedKey;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::{stream::ClientCertSelector, types::native::c_tyes::NetClientCertRequestC};
+
+pub type DartClientCertCallbackC = extern "C" fn(request: *const NetClientCertRequestC);
+
+/// Wait for Dart's answer before the handshake goes on without a certificate, when the
+/// callback was set with none of its own
+const DEFAULT_SELECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where the handshake waiting on a request takes its identity from
+type Answer = SyncSender<Option<Arc<CertifiedKey>>>;
+
+/// Handshakes waiting for `dart_client_cert_select`, by request id
+static PENDING: Lazy<Mutex<HashMap<u32, Answer>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static NEXT_REQUEST_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Selector set by `dart_set_client_cert_callback`. The callback only posts the request,
+/// so the handshake blocks its thread until Dart answers or `timeout` passes, off the
+/// runtime's workers where it can be moved. On a current-thread runtime nothing else runs
+/// meanwhile.
+pub(crate) struct DartClientCertSelector {
+    callback: DartClientCertCallbackC,
+    timeout: Duration,
+}
+
+impl DartClientCertSelector {
+    /// `timeout_ms` 0 waits `DEFAULT_SELECT_TIMEOUT`
+    pub fn new(callback: DartClientCertCallbackC, timeout_ms: u32) -> Self {
+        let timeout = match timeout_ms {
+            0 => DEFAULT_SELECT_TIMEOUT,
+            ms => Duration::from_millis(ms.into()),
+        };
+        Self { callback, timeout }
+    }
+
+    /// Hands `identity` to the handshake of `request_id`; false once it stopped waiting
+    pub fn answer(request_id: u32, identity: Option<Arc<CertifiedKey>>) -> bool {
+        let sender = DartClientCertSelector::pending().remove(&request_id);
+        sender.is_some_and(|sender| sender.send(identity).is_ok())
+    }
+
+    fn pending() -> std::sync::MutexGuard<'static, HashMap<u32, Answer>> {
+        PENDING.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ClientCertSelector for DartClientCertSelector {
+    fn select(&self, host: &str, issuers: &[&[u8]]) -> Option<Arc<CertifiedKey>> {
+        let request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::sync_channel(1);
+        DartClientCertSelector::pending().insert(request_id, sender);
+        // Dart owns the request until it answers
+        let request = Box::new(NetClientCertRequestC::new(request_id, host, issuers));
+        (self.callback)(Box::into_raw(request));
+        let wait = || receiver.recv_timeout(self.timeout);
+        let answer = match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(wait)
+            }
+            _ => wait(),
+        };
+        DartClientCertSelector::pending().remove(&request_id);
+        match answer {
+            Ok(identity) => identity,
+            Err(_) => {
+                debug!(
+                    "No client certificate chosen for {} within {:?}, presenting none",
+                    host, self.timeout
+                );
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, MutexGuard, RwLock,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Sender},
+    },
+};
+
+use log::{debug, error};
+
+use crate::{
+    connector::native::DartCallbackC,
+    types::{
+        error::{NetError, NetResultStatus},
+        native::c_tyes::{NetCallbackStatsC, NetResponseC},
+        response::{NetResponse, NetResponseKind, NetResponseStream, NetResponseStreamError},
+    },
+};
+
+/// Stream data and download progress waiting for the callback before more is dropped
+const DATA_CAPACITY: usize = 1024;
+
+/// Transport and subscription id of a stream
+type Subscription = (u32, Option<i32>);
+
+/// Data dropped per subscription since it last got any
+type Lost = Mutex<HashMap<Subscription, u64>>;
+
+#[derive(Default)]
+struct Counters {
+    /// Queued `NetResponseStream::Data` and `NetResponseKind::HttpDownload`
+    data: AtomicUsize,
+    pending: AtomicUsize,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Calls the Dart callback of a `DartTransporter` from a thread of its own, in the order
+/// responses were posted, so a slow callback never holds up a runtime worker.
+///
+/// Stream data is dropped while `DATA_CAPACITY` messages wait, it is the one response a
+/// server can flood; the subscription is told how many it missed, as when its own queue
+/// overflows. Download progress is dropped the same way, the next one carries the totals.
+/// Everything else always queues, since Dart needs each answer and event: at most one
+/// answer per request, plus the lifecycle events of the streams and transports in flight,
+/// so the queue only outgrows `DATA_CAPACITY` by what Dart itself has asked for.
+pub(crate) struct CallbackQueue {
+    sender: Sender<NetResponse>,
+    counters: Arc<Counters>,
+    /// Posted as a `StreamOverflow` error ahead of the next event of the subscription, or
+    /// by the callback thread once the queue is empty
+    lost: Arc<Lost>,
+}
+
+impl CallbackQueue {
+    pub fn new(callback: Arc<RwLock<Option<DartCallbackC>>>, instance_id: u32) -> Self {
+        let (sender, receiver) = mpsc::channel::<NetResponse>();
+        let counters = Arc::new(Counters::default());
+        let lost: Arc<Lost> = Arc::default();
+        let delivery = Arc::clone(&counters);
+        let missed = Arc::clone(&lost);
+        // ends once the transporter and every task holding a sender are gone
+        let spawned = std::thread::Builder::new()
+            .name(format!("net_sdk-callback-{}", instance_id))
+            .spawn(move || {
+                for response in receiver {
+                    let limited = CallbackQueue::is_limited(&response);
+                    CallbackQueue::call(&callback, response);
+                    if limited {
+                        delivery.data.fetch_sub(1, Ordering::AcqRel);
+                    }
+                    delivery.delivered.fetch_add(1, Ordering::Relaxed);
+                    if delivery.pending.fetch_sub(1, Ordering::AcqRel) > 1 {
+                        continue;
+                    }
+                    // a quiet subscription would otherwise never hear of its gap
+                    let flushed: Vec<_> = missed
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .drain()
+                        .collect();
+                    for (subscription, lost) in flushed {
+                        CallbackQueue::call(&callback, CallbackQueue::overflow(subscription, lost));
+                        delivery.delivered.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            error!("callback thread not started: {}", e);
+        }
+        Self {
+            sender,
+            counters,
+            lost,
+        }
+    }
+
+    fn call(callback: &RwLock<Option<DartCallbackC>>, response: NetResponse) {
+        let response_c = response.into_c();
+        let ptr: *const NetResponseC = Box::into_raw(Box::new(response_c));
+        match callback.read() {
+            Ok(callback) => match *callback {
+                Some(cb) => cb(ptr),
+                None => debug!("called callback after remove."),
+            },
+            Err(_) => error!("read lock failed."),
+        }
+    }
+
+    pub fn post(&self, response: NetResponse) -> Result<(), NetResultStatus> {
+        if !CallbackQueue::is_limited(&response) {
+            // the subscriber learns about the gap before the event that follows it
+            if let Some(subscription) = CallbackQueue::subscription(&response) {
+                let lost = self.lost.lock().unwrap_or_else(|e| e.into_inner());
+                return self.send_after_gap(lost, subscription, response);
+            }
+            return self.send(response);
+        }
+        let queued = self.counters.data.fetch_add(1, Ordering::AcqRel);
+        let mut lost = self.lost.lock().unwrap_or_else(|e| e.into_inner());
+        let subscription = CallbackQueue::subscription(&response);
+        if queued >= DATA_CAPACITY {
+            self.counters.data.fetch_sub(1, Ordering::AcqRel);
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+            if let Some(subscription) = subscription {
+                *lost.entry(subscription).or_default() += 1;
+            }
+            return Err(NetResultStatus::StreamOverflow);
+        }
+        let sent = match subscription {
+            Some(subscription) => self.send_after_gap(lost, subscription, response),
+            None => {
+                drop(lost);
+                self.send(response)
+            }
+        };
+        sent.inspect_err(|_| {
+            self.counters.data.fetch_sub(1, Ordering::AcqRel);
+        })
+    }
+
+    /// Sends `response` of `subscription`, preceded by the data it lost if any
+    fn send_after_gap(
+        &self,
+        mut lost: MutexGuard<'_, HashMap<Subscription, u64>>,
+        subscription: Subscription,
+        response: NetResponse,
+    ) -> Result<(), NetResultStatus> {
+        if let Some(lost) = lost.remove(&subscription) {
+            self.send(CallbackQueue::overflow(subscription, lost))?;
+        }
+        drop(lost);
+        self.send(response)
+    }
+
+    fn overflow((transport_id, id): Subscription, lost: u64) -> NetResponse {
+        NetResponse {
+            transport_id,
+            request_id: 0,
+            response: NetResponseKind::Stream(NetResponseStream::Error(
+                NetResponseStreamError::new(id, NetError::stream_overflow(lost)),
+            )),
+        }
+    }
+
+    fn send(&self, response: NetResponse) -> Result<(), NetResultStatus> {
+        self.counters.pending.fetch_add(1, Ordering::AcqRel);
+        self.sender.send(response).map_err(|_| {
+            self.counters.pending.fetch_sub(1, Ordering::AcqRel);
+            NetResultStatus::InternalError
+        })
+    }
+
+    /// Stream data and download progress, dropped past `DATA_CAPACITY`
+    fn is_limited(response: &NetResponse) -> bool {
+        matches!(
+            response.response,
+            NetResponseKind::Stream(NetResponseStream::Data(_)) | NetResponseKind::HttpDownload(_)
+        )
+    }
+
+    /// Transport and subscription id of a stream event, `None` for every other response
+    fn subscription(response: &NetResponse) -> Option<Subscription> {
+        let NetResponseKind::Stream(stream) = &response.response else {
+            return None;
+        };
+        let id = match stream {
+            NetResponseStream::Data(data) => data.id(),
+            NetResponseStream::Error(error) => error.id(),
+            NetResponseStream::Reconnected(reconnected) => reconnected.id(),
+            NetResponseStream::Rebuild(rebuild) => rebuild.id(),
+            NetResponseStream::Close(id)
+            | NetResponseStream::Idle(id)
+            | NetResponseStream::Cancelled(id) => *id,
+        };
+        Some((response.transport_id, id))
+    }
+
+    pub fn stats(&self) -> NetCallbackStatsC {
+        NetCallbackStatsC {
+            pending: self.counters.pending.load(Ordering::Acquire) as u32,
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
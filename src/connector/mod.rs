@@ -1,4 +1,15 @@
 #[cfg(not(target_arch = "wasm32"))]
+mod activity;
+#[cfg(not(target_arch = "wasm32"))]
+mod client_cert;
+#[cfg(not(target_arch = "wasm32"))]
+mod delivery;
+#[cfg(not(target_arch = "wasm32"))]
 mod native;
 #[cfg(target_arch = "wasm32")]
 mod wasm;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::{TransportFactory, register_transport, unregister_transport};
+#[cfg(target_arch = "wasm32")]
+pub use wasm::{TransportFactory, register_transport, unregister_transport};
@@ -1,30 +1,55 @@
+use libc::c_char;
 use log::{Level, LevelFilter, Metadata, Record, debug, error};
 use once_cell::sync::Lazy;
 use std::{
     collections::HashMap,
+    path::Path,
     sync::{
         Arc, Mutex, Once, RwLock,
         atomic::{AtomicU32, Ordering},
     },
     time::Duration,
 };
-use tokio::{runtime::Runtime, time::timeout};
+use tokio::{
+    runtime::Runtime,
+    sync::{broadcast::error::RecvError, oneshot},
+    task::JoinHandle,
+    time::timeout,
+};
 
+#[cfg(feature = "grpc")]
+use crate::transport::native::grpc::GrpcTransport;
+#[cfg(feature = "otel")]
 use crate::{
-    stream,
-    transport::native::{
-        Transport, grpc::GrpcTransport, http::HttpTransport, socket::SocketTransport,
+    types::config::NetConfigOtel,
+    utils::otel::{OtelExporter, OtelSpan},
+};
+
+use crate::{
+    connector::{
+        activity::{ActivityLog, DEBUG_CAPACITY},
+        client_cert::{DartClientCertCallbackC, DartClientCertSelector},
+        delivery::CallbackQueue,
     },
+    stream,
+    transport::native::{Transport, http::HttpTransport, socket::SocketTransport},
     types::{
         DartCallback,
-        config::NetConfigRequest,
-        error::NetResultStatus,
+        config::{NetConfigRequest, NetMode, NetProtocol},
+        error::{NetError, NetErrorCategory, NetResultStatus},
         native::{
-            c_tyes::{NetConfigRequestC, NetRequestC, NetResponseC},
+            c_tyes::{
+                BytesRefC, NetAddressInfoC, NetCallbackStatsC, NetClientCertRequestC,
+                NetClientIdentityC, NetConfigOtelC, NetConfigRequestC, NetHttpHeaderC, NetRequestC,
+                NetResponseC, NetUrlQueryParamC, free_c_string, string_to_c_ptr,
+            },
             request::NetRequest,
         },
-        response::{NetResponse, NetResponseKind},
+        response::{
+            NetResponse, NetResponseDns, NetResponseKind, NetResponseMdns, NetResponseSpeedTest,
+        },
     },
+    utils::Utils,
 };
 
 struct SimpleLogger;
@@ -33,6 +58,26 @@ static LOGGER: SimpleLogger = SimpleLogger;
 
 static TRANSPORTER_INSTANCES: Lazy<RwLock<HashMap<u32, Arc<DartTransporter>>>> =
     Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Builds a transport for a protocol id registered with `register_transport`
+pub type TransportFactory = Arc<
+    dyn Fn(
+            NetConfigRequest,
+            DartCallback,
+            u32,
+        ) -> Result<Box<dyn Transport + Send + Sync>, NetError>
+        + Send
+        + Sync,
+>;
+
+struct CustomTransport {
+    /// built-in protocol whose url parsing and request kinds the transport accepts
+    protocol: NetProtocol,
+    factory: TransportFactory,
+}
+
+static CUSTOM_TRANSPORTS: Lazy<RwLock<HashMap<u8, CustomTransport>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
 // Atomic counter for unique IDs
 static NEXT_INSTANCE_ID: AtomicU32 = AtomicU32::new(257);
 impl log::Log for SimpleLogger {
@@ -74,21 +119,51 @@ static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 pub type DartCallbackC = extern "C" fn(response: *const NetResponseC);
 struct TransporterEntry {
     transport: Box<dyn Transport + Send + Sync>,
+    /// `CustomStreamRegistry::register_fd` connector the transport dials, unregistered with
+    /// the entry so a socket it never connected is closed too
+    fd_connector: Option<String>,
+}
+
+impl Drop for TransporterEntry {
+    fn drop(&mut self) {
+        if let Some(name) = &self.fd_connector {
+            stream::CustomStreamRegistry::unregister(name);
+        }
+    }
+}
+/// Running `Splice` request; dropping `stop` ends it
+struct SpliceEntry {
+    request_id: u32,
+    transports: [u32; 2],
+    _stop: oneshot::Sender<()>,
 }
 pub struct DartTransporter {
     callback: Arc<RwLock<Option<DartCallbackC>>>,
+    /// calls `callback` off the runtime
+    delivery: Arc<CallbackQueue>,
     transports: Mutex<HashMap<u32, Arc<TransporterEntry>>>,
     next_id: Mutex<u32>,
     instance_id: u32,
+    /// running `WatchInterfaces` request, at most one per instance
+    network_watch: Mutex<Option<JoinHandle<()>>>,
+    /// ended when Dart closes one of their transports
+    splices: Arc<Mutex<Vec<SpliceEntry>>>,
+    /// latest requests, read by `dart_transporter_activity`
+    activity: Arc<ActivityLog>,
 }
 
 impl DartTransporter {
     pub fn new(callback: DartCallbackC, instance_id: u32) -> Self {
+        let callback = Arc::new(RwLock::new(Some(callback)));
         Self {
-            callback: Arc::new(RwLock::new(Some(callback))),
+            delivery: Arc::new(CallbackQueue::new(Arc::clone(&callback), instance_id)),
+            callback,
             transports: Mutex::new(HashMap::new()),
             next_id: Mutex::new(258),
             instance_id,
+            network_watch: Mutex::new(None),
+            splices: Arc::new(Mutex::new(Vec::new())),
+            activity: Arc::new(ActivityLog::new(0)),
         }
     }
 
@@ -101,7 +176,14 @@ impl DartTransporter {
             &*request
         };
         let request = unsafe { NetRequest::from_c(request) }?;
-        let callback = Arc::clone(&self.callback);
+        if let crate::types::native::request::NetRequestKind::WatchInterfaces(watch) = request.kind
+        {
+            return self.watch_interfaces(watch, &request);
+        }
+        if let crate::types::native::request::NetRequestKind::Splice(splice) = &request.kind {
+            return self.splice(splice.source, splice.target, &request);
+        }
+        let callback = Arc::clone(&self.delivery);
         RUNTIME.spawn(async move {
             let response = match request.kind {
                 crate::types::native::request::NetRequestKind::InitTor(net_config_tor) => {
@@ -115,6 +197,151 @@ impl DartTransporter {
                     let inited = stream::StreamUtils::tor_inited();
                     NetResponseKind::TorInited(inited)
                 }
+                crate::types::native::request::NetRequestKind::DnsLookup(dns) => {
+                    let lookup = timeout(
+                        Duration::from_secs(request.timeout as u64),
+                        stream::DnsResolver::lookup(&dns),
+                    )
+                    .await;
+                    match lookup {
+                        Ok(Ok(records)) => NetResponseKind::Dns(NetResponseDns::new(records)),
+                        Ok(Err(e)) => NetResponseKind::ResponseError(e),
+                        Err(_) => NetResponseKind::ResponseError(DartTransporter::timeout_error(
+                            request.timeout,
+                        )),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::Ping(ping) => {
+                    let ping = timeout(
+                        Duration::from_secs(request.timeout as u64),
+                        stream::Pinger::ping(&ping),
+                    )
+                    .await;
+                    match ping {
+                        Ok(Ok(ping)) => NetResponseKind::Ping(ping),
+                        Ok(Err(e)) => NetResponseKind::ResponseError(e),
+                        Err(_) => NetResponseKind::ResponseError(DartTransporter::timeout_error(
+                            request.timeout,
+                        )),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::Ntp(ntp) => {
+                    let query = timeout(
+                        Duration::from_secs(request.timeout as u64),
+                        stream::NtpClient::query(&ntp),
+                    )
+                    .await;
+                    match query {
+                        Ok(Ok(ntp)) => NetResponseKind::Ntp(ntp),
+                        Ok(Err(e)) => NetResponseKind::ResponseError(e),
+                        Err(_) => NetResponseKind::ResponseError(DartTransporter::timeout_error(
+                            request.timeout,
+                        )),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::Stun(stun) => {
+                    let bind = timeout(
+                        Duration::from_secs(request.timeout as u64),
+                        stream::StunClient::bind(&stun),
+                    )
+                    .await;
+                    match bind {
+                        Ok(Ok(stun)) => NetResponseKind::Stun(stun),
+                        Ok(Err(e)) => NetResponseKind::ResponseError(e),
+                        Err(_) => NetResponseKind::ResponseError(DartTransporter::timeout_error(
+                            request.timeout,
+                        )),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::PortCheck(check) => {
+                    match stream::PortCheck::check(&check).await {
+                        Ok(check) => NetResponseKind::PortCheck(check),
+                        Err(e) => NetResponseKind::ResponseError(e),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::PortMapping(mapping) => {
+                    let map = timeout(
+                        Duration::from_secs(request.timeout as u64),
+                        stream::PortMapper::map(&mapping),
+                    )
+                    .await;
+                    match map {
+                        Ok(Ok(mapping)) => NetResponseKind::PortMapping(mapping),
+                        Ok(Err(e)) => NetResponseKind::ResponseError(e),
+                        Err(_) => NetResponseKind::ResponseError(DartTransporter::timeout_error(
+                            request.timeout,
+                        )),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::SpeedTest(test) => {
+                    let run = timeout(
+                        Duration::from_secs(request.timeout as u64),
+                        stream::SpeedTest::run(&test, |progress| {
+                            let progress = NetResponse {
+                                request_id: request.id,
+                                response: NetResponseKind::SpeedTest(
+                                    NetResponseSpeedTest::Progress(progress),
+                                ),
+                                transport_id: request.transport_id,
+                            };
+                            let _ = DartTransporter::post_response(&callback, progress);
+                        }),
+                    )
+                    .await;
+                    match run {
+                        Ok(Ok(result)) => {
+                            NetResponseKind::SpeedTest(NetResponseSpeedTest::Finished(result))
+                        }
+                        Ok(Err(e)) => NetResponseKind::ResponseError(e),
+                        Err(_) => NetResponseKind::ResponseError(DartTransporter::timeout_error(
+                            request.timeout,
+                        )),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::CaptivePortal(probe) => {
+                    match stream::CaptivePortal::probe(&probe).await {
+                        Ok(probe) => NetResponseKind::CaptivePortal(probe),
+                        Err(e) => NetResponseKind::ResponseError(e),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::DnsPrefetch(prefetch) => {
+                    let run = timeout(
+                        Duration::from_secs(request.timeout as u64),
+                        stream::DnsCache::prefetch(&prefetch),
+                    )
+                    .await;
+                    match run {
+                        Ok(prefetch) => NetResponseKind::DnsPrefetch(prefetch),
+                        Err(_) => NetResponseKind::ResponseError(DartTransporter::timeout_error(
+                            request.timeout,
+                        )),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::Interfaces => {
+                    match stream::NetworkWatcher::snapshot() {
+                        Ok(interfaces) => NetResponseKind::Interfaces(interfaces),
+                        Err(e) => NetResponseKind::ResponseError(e),
+                    }
+                }
+                crate::types::native::request::NetRequestKind::Mdns(mdns) => {
+                    let run = stream::MdnsDiscovery::run(
+                        &mdns,
+                        Duration::from_secs(request.timeout as u64),
+                        |service| {
+                            let found = NetResponse {
+                                request_id: request.id,
+                                response: NetResponseKind::Mdns(NetResponseMdns::Found(service)),
+                                transport_id: request.transport_id,
+                            };
+                            let _ = DartTransporter::post_response(&callback, found);
+                        },
+                    )
+                    .await;
+                    match run {
+                        Ok(()) => NetResponseKind::Mdns(NetResponseMdns::Finished),
+                        Err(e) => NetResponseKind::ResponseError(e),
+                    }
+                }
                 _ => return Err(NetResultStatus::InvalidRequestParameters),
             };
 
@@ -123,24 +350,164 @@ impl DartTransporter {
                 response,
                 transport_id: request.transport_id,
             };
-            let response_c = response.to_c();
-            let boxed = Box::new(response_c);
-            let ptr: *const NetResponseC = Box::into_raw(boxed);
-
-            if let Some(cb) = *callback
-                .read()
-                .map_err(|_| NetResultStatus::InternalError)?
-            {
-                cb(ptr);
+            DartTransporter::post_response(&callback, response)
+        });
+        Ok(())
+    }
+
+    /// Stops the running watch; a new one answers with the current interfaces, then posts
+    /// `NetworkChanged` under `request.id` on every change. Stopping answers with a snapshot too
+    fn watch_interfaces(&self, watch: bool, request: &NetRequest) -> Result<(), NetResultStatus> {
+        let mut running = self
+            .network_watch
+            .lock()
+            .map_err(|_| NetResultStatus::InternalError)?;
+        if let Some(task) = running.take() {
+            task.abort();
+        }
+        let callback = Arc::clone(&self.delivery);
+        let (request_id, transport_id) = (request.id, request.transport_id);
+        let task = RUNTIME.spawn(async move {
+            let post = |response| {
+                let response = NetResponse {
+                    request_id,
+                    response,
+                    transport_id,
+                };
+                let _ = DartTransporter::post_response(&callback, response);
+            };
+            // subscribed first, so no change between the snapshot and the feed is lost
+            let mut changes = watch.then(stream::NetworkWatcher::subscribe);
+            post(match stream::NetworkWatcher::snapshot() {
+                Ok(interfaces) => NetResponseKind::Interfaces(interfaces),
+                Err(e) => NetResponseKind::ResponseError(e),
+            });
+            let Some(changes) = changes.as_mut() else {
+                return;
+            };
+            loop {
+                match changes.recv().await {
+                    Ok(interfaces) => post(NetResponseKind::NetworkChanged(interfaces)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        });
+        if watch {
+            *running = Some(task);
+        }
+        Ok(())
+    }
+
+    /// Joins two stream transports in a background task that answers `request` once either
+    /// side closed. Both stay registered, closed, until Dart closes them
+    fn splice(
+        &self,
+        source_id: u32,
+        target_id: u32,
+        request: &NetRequest,
+    ) -> Result<(), NetResultStatus> {
+        if source_id == target_id {
+            return Err(NetResultStatus::InvalidRequestParameters);
+        }
+        let (source, target) = {
+            let guard = self
+                .transports
+                .lock()
+                .map_err(|_| NetResultStatus::InternalError)?;
+            match (guard.get(&source_id), guard.get(&target_id)) {
+                (Some(source), Some(target)) => (Arc::clone(source), Arc::clone(target)),
+                _ => return Err(NetResultStatus::TransportNotFound),
+            }
+        };
+        if source.transport.stream_client().is_none() || target.transport.stream_client().is_none()
+        {
+            return Err(NetResultStatus::InvalidRequestParameters);
+        }
+        let (stop, stopped) = oneshot::channel();
+        let (request_id, transport_id) = (request.id, request.transport_id);
+        self.splices
+            .lock()
+            .map_err(|_| NetResultStatus::InternalError)?
+            .push(SpliceEntry {
+                request_id,
+                transports: [source_id, target_id],
+                _stop: stop,
+            });
+        let callback = Arc::clone(&self.delivery);
+        let splices = Arc::clone(&self.splices);
+        RUNTIME.spawn(async move {
+            let stopped = async {
+                let _ = stopped.await;
+            };
+            let run = match (
+                source.transport.stream_client(),
+                target.transport.stream_client(),
+            ) {
+                (Some(source), Some(target)) => {
+                    stream::StreamSplice::run(source, target, stopped).await
+                }
+                _ => Err(NetResultStatus::InvalidRequestParameters.into()),
+            };
+            if let Ok(mut splices) = splices.lock() {
+                splices.retain(|splice| splice.request_id != request_id);
             }
-            Ok(())
+            let response = NetResponse {
+                request_id,
+                response: match run {
+                    Ok(splice) => NetResponseKind::Splice(splice),
+                    Err(e) => NetResponseKind::ResponseError(e),
+                },
+                transport_id,
+            };
+            let _ = DartTransporter::post_response(&callback, response);
         });
         Ok(())
     }
 
+    fn timeout_error(seconds: u32) -> NetError {
+        NetError::new(NetResultStatus::RequestTimeout)
+            .with_message(format!("no response after {}s", seconds))
+    }
+
+    fn post_response(
+        callback: &CallbackQueue,
+        response: NetResponse,
+    ) -> Result<(), NetResultStatus> {
+        callback.post(response)
+    }
+
     pub fn create_transporter(
         &self,
         config: *const NetConfigRequestC,
+    ) -> Result<u32, NetResultStatus> {
+        self.create_transporter_with_mode(config, None)
+    }
+
+    /// Creates a transport over `fd` instead of dialing `config.url`; `config.mode` is ignored
+    #[cfg(unix)]
+    pub fn create_transporter_with_fd(
+        &self,
+        config: *const NetConfigRequestC,
+        fd: i32,
+    ) -> Result<u32, NetResultStatus> {
+        let name = {
+            // tokio needs its reactor to adopt the socket
+            let _guard = RUNTIME.enter();
+            unsafe { stream::CustomStreamRegistry::register_fd(fd) }.map_err(|e| e.status())?
+        };
+        let result = self.create_transporter_with_mode(config, Some(name.clone()));
+        if result.is_err() {
+            stream::CustomStreamRegistry::unregister(&name);
+        }
+        result
+    }
+
+    /// `fd_connector` replaces the mode of `config`, and is unregistered with the transport
+    fn create_transporter_with_mode(
+        &self,
+        config: *const NetConfigRequestC,
+        fd_connector: Option<String>,
     ) -> Result<u32, NetResultStatus> {
         if config.is_null() {
             return Err(NetResultStatus::InvalidConfigParameters);
@@ -149,7 +516,25 @@ impl DartTransporter {
             assert!(!config.is_null());
             &*config
         };
-        let config = NetConfigRequest::try_from(cfg)?;
+        let factory = CUSTOM_TRANSPORTS
+            .read()
+            .map_err(|_| NetResultStatus::InternalError)?
+            .get(&cfg.protocol)
+            .map(|custom| (custom.protocol, Arc::clone(&custom.factory)));
+        let config = match &factory {
+            Some((protocol, _)) => NetConfigRequest::try_from(&NetConfigRequestC {
+                protocol: *protocol as u8,
+                ..*cfg
+            })?,
+            None => NetConfigRequest::try_from(cfg)?,
+        };
+        let config = match &fd_connector {
+            Some(name) => NetConfigRequest {
+                mode: NetMode::Custom(name.clone()),
+                ..config
+            },
+            None => config,
+        };
 
         let transport_id = {
             let mut id_guard = self
@@ -168,44 +553,61 @@ impl DartTransporter {
             transport_id,
             config.mode,
         );
-        let callback = Arc::clone(&self.callback);
+        let callback = Arc::clone(&self.delivery);
         let rust_callback: DartCallback = Arc::new(move |response: NetResponseKind| {
             let response = NetResponse {
                 request_id: 0,
                 response,
                 transport_id: transport_id,
             };
-            let response_c = response.to_c();
-            let boxed = Box::new(response_c);
-            let ptr: *const NetResponseC = Box::into_raw(boxed);
-            let g = callback.read();
-            match g {
-                Ok(e) => match *e {
-                    Some(cb) => cb(ptr),
-                    None => {}
-                },
-                Err(_) => {}
-            };
+            let _ = DartTransporter::post_response(&callback, response);
         });
+        // transports may start background work, e.g. replaying an offline queue
+        let _guard = RUNTIME.enter();
+        let transport = match factory {
+            Some((_, factory)) => factory(config, rust_callback, transport_id),
+            None => Self::build_transport(config, rust_callback, transport_id),
+        }
+        .map_err(|e| {
+            error!("Transport create error: {}", e);
+            e.status()
+        })?;
+        self.transports
+            .lock()
+            .map_err(|_| NetResultStatus::InternalError)?
+            .insert(
+                transport_id,
+                Arc::new(TransporterEntry {
+                    transport,
+                    fd_connector,
+                }),
+            );
+        Ok(transport_id)
+    }
+
+    fn build_transport(
+        config: NetConfigRequest,
+        callback: DartCallback,
+        transport_id: u32,
+    ) -> Result<Box<dyn Transport + Send + Sync>, NetError> {
         let transport: Box<dyn Transport + Send + Sync> = match config.protocol {
             crate::types::config::NetProtocol::Http => {
-                Box::new(HttpTransport::create(config, rust_callback, transport_id)?)
+                Box::new(HttpTransport::create(config, callback, transport_id)?)
+            }
+            #[cfg(feature = "grpc")]
+            crate::types::config::NetProtocol::Grpc => {
+                Box::new(GrpcTransport::create(config, callback, transport_id)?)
             }
+            #[cfg(not(feature = "grpc"))]
             crate::types::config::NetProtocol::Grpc => {
-                Box::new(GrpcTransport::create(config, rust_callback, transport_id)?)
+                return Err(NetError::feature_disabled("grpc"));
             }
             crate::types::config::NetProtocol::WebSocket
-            | crate::types::config::NetProtocol::Socket => Box::new(SocketTransport::create(
-                config,
-                rust_callback,
-                transport_id,
-            )?),
+            | crate::types::config::NetProtocol::Socket => {
+                Box::new(SocketTransport::create(config, callback, transport_id)?)
+            }
         };
-        self.transports
-            .lock()
-            .map_err(|_| NetResultStatus::InternalError)?
-            .insert(transport_id, Arc::new(TransporterEntry { transport }));
-        Ok(transport_id)
+        Ok(transport)
     }
 
     pub fn send_request(&self, request: *const NetRequestC) -> Result<(), NetResultStatus> {
@@ -233,54 +635,125 @@ impl DartTransporter {
 
         let id = request.transport_id;
         let request_id = request.id;
-        let callback = Arc::clone(&self.callback);
+        let callback = Arc::clone(&self.delivery);
+        let activity = Arc::clone(&self.activity);
         println!(
             "new request intance: {:#?} id: {:#?} transport: {:#?}",
             self.instance_id, request_id, id
         );
         // spawn async task on your static runtime
         RUNTIME.spawn(async move {
+            let request_timeout = request.timeout;
+            let entry = activity.start(&request, transport_arc.transport.get_config());
+            #[cfg(feature = "otel")]
+            let mut span = OtelSpan::request(&request, transport_arc.transport.get_config());
             let result = timeout(
-                Duration::from_secs(request.timeout as u64),
+                Duration::from_secs(request_timeout as u64),
                 transport_arc.transport.do_request(request),
-            )
-            .await;
-            let response = match result {
+            );
+            #[cfg(feature = "otel")]
+            let result = span.scope(result);
+            let response = match result.await {
                 Ok(inner) => inner.map_or_else(|e| NetResponseKind::ResponseError(e), |e| e),
-                Err(_) => NetResponseKind::ResponseError(NetResultStatus::RequestTimeout),
+                Err(_) => NetResponseKind::ResponseError(
+                    NetError::new(NetResultStatus::RequestTimeout)
+                        .with_message(format!("no response after {}s", request_timeout)),
+                ),
             };
+            #[cfg(feature = "otel")]
+            span.response(&response);
+            #[cfg(feature = "otel")]
+            drop(span);
+            if let Some(entry) = entry {
+                activity.finish(entry, &response);
+            }
 
             let response = NetResponse {
                 transport_id: id,
                 response,
                 request_id,
             };
+            if DartTransporter::post_response(&callback, response).is_err() {
+                error!("callback thread is gone.")
+            }
+        });
 
-            let response_c = response.to_c();
-            let boxed = Box::new(response_c);
-            let ptr: *const NetResponseC = Box::into_raw(boxed);
-            let g = callback.read();
-
-            match g {
-                Ok(e) => match *e {
-                    Some(cb) => {
-                        debug!("cb called send");
-                        cb(ptr);
-                        println!("response post to dart");
-                    }
-                    None => {
-                        debug!("called callback after remove.")
-                    }
-                },
-                Err(_) => {
-                    error!("read lock failed.")
-                }
+        Ok(())
+    }
+
+    /// Connects `transport_id` in the background and posts `TransportConnected`, or the
+    /// connect error, under request id 0
+    pub fn connect(&self, transport_id: u32) -> Result<(), NetResultStatus> {
+        let entry = self
+            .transports
+            .lock()
+            .map_err(|_| NetResultStatus::InternalError)?
+            .get(&transport_id)
+            .cloned()
+            .ok_or(NetResultStatus::TransportNotFound)?;
+        let callback = Arc::clone(&self.delivery);
+        RUNTIME.spawn(async move {
+            let response = match entry.transport.connect().await {
+                Ok(()) => NetResponseKind::TransportConnected,
+                Err(e) => NetResponseKind::ResponseError(e),
+            };
+            let response = NetResponse {
+                transport_id,
+                response,
+                request_id: 0,
             };
+            if DartTransporter::post_response(&callback, response).is_err() {
+                error!("callback thread is gone.")
+            }
         });
-
         Ok(())
     }
 
+    /// Resolves `reference` against the url this transport was created with
+    pub fn resolve_url(
+        &self,
+        transport_id: u32,
+        reference: &str,
+    ) -> Result<String, NetResultStatus> {
+        let entry = self
+            .transports
+            .lock()
+            .map_err(|_| NetResultStatus::InternalError)?
+            .get(&transport_id)
+            .cloned()
+            .ok_or(NetResultStatus::TransportNotFound)?;
+        Utils::resolve_url(&entry.transport.get_config().addr.url, reference)
+    }
+
+    /// `Transport::save_state` of `transport_id`, or its `restore_state`
+    pub fn transport_state(
+        &self,
+        transport_id: u32,
+        dir: &str,
+        restore: bool,
+    ) -> Result<(), NetResultStatus> {
+        let entry = self
+            .transports
+            .lock()
+            .map_err(|_| NetResultStatus::InternalError)?
+            .get(&transport_id)
+            .cloned()
+            .ok_or(NetResultStatus::TransportNotFound)?;
+        let dir = Path::new(dir);
+        if !dir.is_dir() {
+            return Err(NetResultStatus::InvalidRequestParameters);
+        }
+        let result = if restore {
+            entry.transport.restore_state(dir)
+        } else {
+            entry.transport.save_state(dir)
+        };
+        result.map_err(|e| {
+            debug!("transport {} state: {:?}", transport_id, e);
+            e.status()
+        })
+    }
+
     pub fn close(&self, transport_id: u32) -> Result<(), NetResultStatus> {
         // Step 1: Remove the transport from the global map
         let transport_arc = {
@@ -294,7 +767,12 @@ impl DartTransporter {
                 return Err(NetResultStatus::TransportNotFound);
             }
         };
-        let callback = Arc::clone(&self.callback);
+        // dropping their senders ends the splices through this transport
+        self.splices
+            .lock()
+            .map_err(|_| NetResultStatus::InternalError)?
+            .retain(|splice| !splice.transports.contains(&transport_id));
+        let callback = Arc::clone(&self.delivery);
         // Step 2: Spawn async task to close transport
         RUNTIME.spawn(async move {
             let _ = transport_arc.transport.close().await;
@@ -304,26 +782,27 @@ impl DartTransporter {
                 response: NetResponseKind::TransportClosed,
                 request_id: 0,
             };
-            let response_c = response.to_c();
-            let boxed = Box::new(response_c);
-            let ptr: *const NetResponseC = Box::into_raw(boxed);
-            let g = callback.read();
-            match g {
-                Ok(e) => match *e {
-                    Some(cb) => cb(ptr),
-                    None => {
-                        debug!("called callback after remove.")
-                    }
-                },
-                Err(_) => {
-                    error!("read lock failed.")
-                }
-            };
+            if DartTransporter::post_response(&callback, response).is_err() {
+                error!("callback thread is gone.")
+            }
         });
 
         Ok(())
     }
 
+    pub fn callback_stats(&self) -> NetCallbackStatsC {
+        self.delivery.stats()
+    }
+
+    /// Keeps the latest `capacity` requests in the activity log, none with 0
+    pub fn set_activity_capacity(&self, capacity: u32) {
+        self.activity.set_capacity(capacity);
+    }
+
+    pub fn activity(&self, clear: bool) -> Result<String, NetResultStatus> {
+        self.activity.to_json(clear)
+    }
+
     /// Close all transports, ignoring callback results
     pub fn close_all(&self) -> Result<(), NetResultStatus> {
         // Step 1: Set callback to None
@@ -331,6 +810,18 @@ impl DartTransporter {
             .write()
             .map_err(|_| NetResultStatus::InternalError)?
             .take();
+        if let Some(task) = self
+            .network_watch
+            .lock()
+            .map_err(|_| NetResultStatus::InternalError)?
+            .take()
+        {
+            task.abort();
+        }
+        self.splices
+            .lock()
+            .map_err(|_| NetResultStatus::InternalError)?
+            .clear();
 
         // Step 2: Take all transports
         let transports: Vec<Arc<TransporterEntry>> = {
@@ -375,6 +866,23 @@ pub extern "C" fn dart_transporter_create(id: u32, config: *const NetConfigReque
         Err(status) => status as u32,
     }
 }
+/// # Safety
+/// `fd` must be an open, connected stream socket; the SDK takes ownership and closes it.
+#[cfg(unix)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_transporter_create_with_fd(
+    id: u32,
+    config: *const NetConfigRequestC,
+    fd: i32,
+) -> u32 {
+    match get_transporter_by_id(id) {
+        Ok(transporter) => match transporter.create_transporter_with_fd(config, fd) {
+            Ok(transport_id) => transport_id,
+            Err(e) => e as u32,
+        },
+        Err(status) => status as u32,
+    }
+}
 #[unsafe(no_mangle)]
 pub extern "C" fn dart_transporter_send(id: u32, request: *const NetRequestC) -> u8 {
     match get_transporter_by_id(id) {
@@ -396,6 +904,17 @@ pub extern "C" fn dart_update_config(id: u32, request: *const NetRequestC) -> u8
     }
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn dart_transporter_connect(id: u32, transport_id: u32) -> u8 {
+    match get_transporter_by_id(id) {
+        Ok(transporter) => match transporter.connect(transport_id) {
+            Ok(_) => NetResultStatus::OK as u8,
+            Err(e) => e as u8,
+        },
+        Err(status) => status,
+    }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn dart_transporter_close(id: u32, transport_id: u32) -> u8 {
     match get_transporter_by_id(id) {
@@ -435,6 +954,332 @@ pub unsafe extern "C" fn dart_transporter_free_response(response: *const NetResp
     NetResultStatus::OK as u8
 }
 
+/// Writes `result` to `out` as a C string that must be released with `dart_free_string`.
+unsafe fn write_c_string(result: Result<String, NetResultStatus>, out: *mut *const c_char) -> u8 {
+    if out.is_null() {
+        return NetResultStatus::InvalidRequestParameters as u8;
+    }
+    match result {
+        Ok(value) => {
+            unsafe { *out = string_to_c_ptr(value) as *const c_char };
+            NetResultStatus::OK as u8
+        }
+        Err(e) => e as u8,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_free_string(ptr: *const c_char) {
+    unsafe { free_c_string(ptr as *mut u8) };
+}
+
+/// Buffer for a request body that Rust takes over through `BytesRefC::owned`, so it is not
+/// copied again. One that is never sent is released with `dart_free_bytes`.
+#[unsafe(no_mangle)]
+pub extern "C" fn dart_alloc_bytes(len: u32) -> *mut u8 {
+    if len == 0 {
+        return std::ptr::null_mut();
+    }
+    Box::into_raw(vec![0u8; len as usize].into_boxed_slice()) as *mut u8
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_free_bytes(ptr: *mut u8, len: u32) {
+    let bytes = BytesRefC { ptr, len, owned: 1 };
+    unsafe { bytes.free_memory() };
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_url_merge_query(
+    url: *const c_char,
+    params: *const NetUrlQueryParamC,
+    params_len: u32,
+    out: *mut *const c_char,
+) -> u8 {
+    let url = unsafe { Utils::cstr_to_str(url as *const u8) };
+    let params = unsafe { NetUrlQueryParamC::to_pairs(params, params_len) };
+    unsafe { write_c_string(Utils::merge_query_params(url, &params), out) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_url_encode_path_segment(
+    segment: *const c_char,
+    out: *mut *const c_char,
+) -> u8 {
+    let segment = unsafe { Utils::cstr_to_str(segment as *const u8) };
+    unsafe { write_c_string(Ok(Utils::encode_path_segment(segment)), out) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_url_resolve(
+    base: *const c_char,
+    reference: *const c_char,
+    out: *mut *const c_char,
+) -> u8 {
+    let base = unsafe { Utils::cstr_to_str(base as *const u8) };
+    let reference = unsafe { Utils::cstr_to_str(reference as *const u8) };
+    unsafe { write_c_string(Utils::resolve_url(base, reference), out) }
+}
+
+/// Writes the state of `transport_id` worth keeping across app restarts into the existing
+/// directory `dir`, for `dart_transporter_restore_state`. That is the responses of
+/// `NetConfigHttp::cache` with their validators, in `http_cache.json`, so the first requests
+/// after a restart revalidate instead of downloading again; responses kept for requests with
+/// credentials are left out. Everything else starts over: TLS session tickets, which rustls
+/// can not export, connections and Tor circuits, gRPC channels and streams. Transports
+/// without a cache write nothing. Give each transport a directory of its own.
+///
+/// # Safety
+/// `dir` must be a valid null-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_transporter_save_state(
+    id: u32,
+    transport_id: u32,
+    dir: *const c_char,
+) -> u8 {
+    let dir = unsafe { Utils::cstr_to_str(dir as *const u8) };
+    match get_transporter_by_id(id) {
+        Ok(transporter) => match transporter.transport_state(transport_id, dir, false) {
+            Ok(()) => NetResultStatus::OK as u8,
+            Err(e) => e as u8,
+        },
+        Err(status) => status,
+    }
+}
+
+/// Takes up what `dart_transporter_save_state` left in `dir` in place of what the transport
+/// keeps, best called right after the transport is created. A directory the transport saved
+/// nothing to restores nothing; a file it can not read fails with `InvalidRequestParameters`.
+///
+/// # Safety
+/// `dir` must be a valid null-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_transporter_restore_state(
+    id: u32,
+    transport_id: u32,
+    dir: *const c_char,
+) -> u8 {
+    let dir = unsafe { Utils::cstr_to_str(dir as *const u8) };
+    match get_transporter_by_id(id) {
+        Ok(transporter) => match transporter.transport_state(transport_id, dir, true) {
+            Ok(()) => NetResultStatus::OK as u8,
+            Err(e) => e as u8,
+        },
+        Err(status) => status,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_transporter_resolve_url(
+    id: u32,
+    transport_id: u32,
+    reference: *const c_char,
+    out: *mut *const c_char,
+) -> u8 {
+    let reference = unsafe { Utils::cstr_to_str(reference as *const u8) };
+    match get_transporter_by_id(id) {
+        Ok(transporter) => unsafe {
+            write_c_string(transporter.resolve_url(transport_id, reference), out)
+        },
+        Err(e) => e,
+    }
+}
+
+/// Validates `url` with the parser used by transports of `protocol` and fills `out`.
+/// release `out` with `dart_url_free_address` when OK is returned.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_url_parse(
+    url: *const c_char,
+    protocol: u8,
+    out: *mut NetAddressInfoC,
+) -> u8 {
+    if url.is_null() || out.is_null() {
+        return NetResultStatus::InvalidRequestParameters as u8;
+    }
+    let protocol = match protocol {
+        1 => NetProtocol::Http,
+        2 => NetProtocol::Grpc,
+        3 => NetProtocol::WebSocket,
+        4 => NetProtocol::Socket,
+        _ => return NetResultStatus::InvalidRequestParameters as u8,
+    };
+    let url = unsafe { Utils::cstr_to_str(url as *const u8) };
+    match Utils::parse_protocol_url(url, protocol) {
+        Ok(addr) => {
+            unsafe { out.write(addr.to_c()) };
+            NetResultStatus::OK as u8
+        }
+        Err(e) => e as u8,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_url_free_address(addr: *const NetAddressInfoC) {
+    if !addr.is_null() {
+        unsafe { (*addr).free_memory() };
+    }
+}
+
+/// Case-insensitive lookup over `NetResponseHttpC` headers or trailers. call again with `index + 1`
+/// to walk repeated headers; returns -1 when there are no more matches.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_http_headers_find(
+    headers: *const NetHttpHeaderC,
+    len: u32,
+    name: *const c_char,
+    start: u32,
+) -> i32 {
+    unsafe { NetHttpHeaderC::find(headers, len, name, start) }.map_or(-1, |i| i as i32)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn dart_status_is_transient(status: u8) -> bool {
+    NetResultStatus::from_u8(status).is_some_and(|status| status.is_transient())
+}
+
+/// Returns the `NetErrorCategory` of a status code. unknown codes are reported as internal.
+#[unsafe(no_mangle)]
+pub extern "C" fn dart_status_category(status: u8) -> u8 {
+    NetResultStatus::from_u8(status)
+        .map(|status| status.category())
+        .unwrap_or(NetErrorCategory::Internal) as u8
+}
+
+/// Fills `out` with the callback delivery counters of the instance.
+///
+/// # Safety
+/// `out` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_transporter_callback_stats(
+    id: u32,
+    out: *mut NetCallbackStatsC,
+) -> u8 {
+    if out.is_null() {
+        return NetResultStatus::InvalidRequestParameters as u8;
+    }
+    match get_transporter_by_id(id) {
+        Ok(transporter) => {
+            unsafe { out.write(transporter.callback_stats()) };
+            NetResultStatus::OK as u8
+        }
+        Err(status) => status,
+    }
+}
+
+/// Keeps the latest `capacity` requests of the instance for `dart_transporter_activity`;
+/// 0 stops recording. Instances created with `debug` keep 100.
+#[unsafe(no_mangle)]
+pub extern "C" fn dart_transporter_set_activity_capacity(id: u32, capacity: u32) -> u8 {
+    match get_transporter_by_id(id) {
+        Ok(transporter) => {
+            transporter.set_activity_capacity(capacity);
+            NetResultStatus::OK as u8
+        }
+        Err(status) => status,
+    }
+}
+
+/// Writes the activity log of the instance to `out` as a JSON array, oldest first, and
+/// empties it when `clear` is set. Entries hold the method, url, status, byte counts,
+/// timings and error of each request, never its body.
+///
+/// # Safety
+/// `out` must be valid for writes; release the string with `dart_free_string`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_transporter_activity(
+    id: u32,
+    clear: bool,
+    out: *mut *const c_char,
+) -> u8 {
+    match get_transporter_by_id(id) {
+        Ok(transporter) => unsafe { write_c_string(transporter.activity(clear), out) },
+        Err(e) => e,
+    }
+}
+
+/// Exports spans of every instance to `config`, or stops exporting when it is null.
+/// fails with `InvalidConfigParameters` when built without the `otel` feature.
+///
+/// # Safety
+/// `config` must be null or point to a valid `NetConfigOtelC`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_otel_configure(config: *const NetConfigOtelC) -> u8 {
+    #[cfg(feature = "otel")]
+    {
+        let config = match unsafe { config.as_ref() }
+            .map(NetConfigOtel::try_from)
+            .transpose()
+        {
+            Ok(config) => config,
+            Err(e) => return e as u8,
+        };
+        // the exporter runs its batches on the runtime
+        let _guard = RUNTIME.enter();
+        match OtelExporter::configure(config) {
+            Ok(()) => NetResultStatus::OK as u8,
+            Err(e) => e.status() as u8,
+        }
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = config;
+        NetError::feature_disabled("otel").status() as u8
+    }
+}
+
+/// Asks `callback` for the certificate to present whenever a server requests one during
+/// a TLS handshake, or presents none again when it is null. The callback is invoked on the
+/// connecting thread and must only post the request on, e.g. a `NativeCallable.listener`;
+/// every request is answered with `dart_client_cert_select`.
+///
+/// The handshake blocks a runtime thread until the answer, so Dart must answer promptly
+/// and without waiting on a request of its own through the SDK. After `timeout_ms`, 0 for
+/// 5 seconds, the handshake goes on without a certificate, which the server usually
+/// rejects, and a later answer returns `InvalidRequestParameters`.
+#[unsafe(no_mangle)]
+pub extern "C" fn dart_set_client_cert_callback(
+    callback: Option<DartClientCertCallbackC>,
+    timeout_ms: u32,
+) -> u8 {
+    let selector = callback.map(|callback| {
+        Arc::new(DartClientCertSelector::new(callback, timeout_ms))
+            as Arc<dyn stream::ClientCertSelector>
+    });
+    match stream::ClientCertRegistry::set(selector) {
+        Ok(()) => NetResultStatus::OK as u8,
+        Err(e) => e.status() as u8,
+    }
+}
+
+/// Answers `request` with `identity`, or with no certificate when it is null, and frees
+/// the request. An identity that does not parse answers with none and returns its error;
+/// an answer after the handshake gave up waiting returns `InvalidRequestParameters`.
+///
+/// # Safety
+/// `request` must come from the client certificate callback and be answered once;
+/// `identity` must be null or point to a valid `NetClientIdentityC`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dart_client_cert_select(
+    request: *const NetClientCertRequestC,
+    identity: *const NetClientIdentityC,
+) -> u8 {
+    if request.is_null() {
+        return NetResultStatus::InvalidRequestParameters as u8;
+    }
+    let request = unsafe { Box::from_raw(request as *mut NetClientCertRequestC) };
+    unsafe { request.free_memory() };
+    let identity = unsafe { identity.as_ref() }.map(|identity| unsafe { identity.to_identity() });
+    let (identity, status) = match identity {
+        Some(Ok(identity)) => (Some(identity), NetResultStatus::OK),
+        Some(Err(e)) => (None, e.status()),
+        None => (None, NetResultStatus::OK),
+    };
+    match DartClientCertSelector::answer(request.request_id, identity) {
+        true => status as u8,
+        false => NetResultStatus::InvalidRequestParameters as u8,
+    }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn dart_transporter_create_instance(callback: DartCallbackC, debug: bool) -> u32 {
     // Initialize logger if debug is true
@@ -445,6 +1290,9 @@ pub extern "C" fn dart_transporter_create_instance(callback: DartCallbackC, debu
     let instance_id = NEXT_INSTANCE_ID.fetch_add(1, Ordering::Relaxed);
     // Create new DartTransporter instance
     let transporter = Arc::new(DartTransporter::new(callback, instance_id));
+    if debug {
+        transporter.set_activity_capacity(DEBUG_CAPACITY);
+    }
 
     // Store instance in global map
     let mut guard = match TRANSPORTER_INSTANCES.write() {
@@ -455,3 +1303,50 @@ pub extern "C" fn dart_transporter_create_instance(callback: DartCallbackC, debu
     // Return the new transport ID
     instance_id
 }
+
+/// Registers a handler for `protocol_id`, the `protocol` byte of `NetConfigRequestC`,
+/// shared by every `DartTransporter` instance.
+///
+/// `protocol` selects the built-in protocol used to parse the url and validate requests.
+/// ids of the built-in protocols cannot be replaced.
+pub fn register_transport<F>(
+    protocol_id: u8,
+    protocol: NetProtocol,
+    factory: F,
+) -> Result<(), NetError>
+where
+    F: Fn(
+            NetConfigRequest,
+            DartCallback,
+            u32,
+        ) -> Result<Box<dyn Transport + Send + Sync>, NetError>
+        + Send
+        + Sync
+        + 'static,
+{
+    if protocol_id == 0
+        || (NetProtocol::Http as u8..=NetProtocol::Socket as u8).contains(&protocol_id)
+    {
+        return Err(NetError::new(NetResultStatus::InvalidConfigParameters)
+            .with_message(format!("protocol id {} is reserved", protocol_id)));
+    }
+    CUSTOM_TRANSPORTS
+        .write()
+        .map_err(|_| NetError::new(NetResultStatus::InternalError))?
+        .insert(
+            protocol_id,
+            CustomTransport {
+                protocol,
+                factory: Arc::new(factory),
+            },
+        );
+    Ok(())
+}
+
+/// Removes a registered handler. Transports already created keep running
+pub fn unregister_transport(protocol_id: u8) -> bool {
+    CUSTOM_TRANSPORTS
+        .write()
+        .map(|mut registry| registry.remove(&protocol_id).is_some())
+        .unwrap_or(false)
+}
@@ -5,23 +5,27 @@ use futures::FutureExt;
 use futures::future::{Either, select};
 use gloo_timers::future::TimeoutFuture;
 use log::{Level, LevelFilter, Metadata, Record, debug};
+use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use wasm_bindgen::prelude::*;
 
 use crate::types::config::NetConfigRequestWasm;
 use crate::types::request::NetRequestWasm;
 use crate::types::response::NetResponseWasm;
+#[cfg(feature = "grpc")]
+use crate::transport::wasm::grpc::GrpcTransport;
+#[cfg(feature = "websocket")]
+use crate::transport::wasm::socket::SocketTransport;
 use crate::{
-    transport::wasm::{
-        Transport, grpc::GrpcTransport, http::HttpTransport, socket::SocketTransport,
-    },
+    transport::wasm::{Transport, http::HttpTransport},
     types::{
-        DartCallback,
-        config::NetConfigRequest,
-        error::NetResultStatus,
+        DartCallback, NetAddressInfoWasm,
+        config::{NetConfigRequest, NetProtocol},
+        error::{NetErrorCategory, NetResultStatus},
         request::NetRequest,
         response::{NetResponse, NetResponseKind},
     },
+    utils::Utils,
 };
 
 struct SimpleLogger;
@@ -60,6 +64,22 @@ struct TransporterEntry {
     transport: Box<dyn Transport>,
 }
 
+/// Builds a transport for a protocol id registered with `register_transport`
+pub type TransportFactory = Arc<
+    dyn Fn(NetConfigRequest, DartCallback, u32) -> Result<Box<dyn Transport>, NetResultStatus>
+        + Send
+        + Sync,
+>;
+
+struct CustomTransport {
+    /// built-in protocol whose url parsing and request kinds the transport accepts
+    protocol: NetProtocol,
+    factory: TransportFactory,
+}
+
+static CUSTOM_TRANSPORTS: Lazy<Mutex<HashMap<u8, CustomTransport>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 // static GLOBAL_MUX: Lazy<Mutex<Arc<js_sys::Function>>> = Lazy::new(|| Mutex::new(None));
 
 #[wasm_bindgen]
@@ -89,7 +109,31 @@ impl DartTransporter {
         }
     }
     pub fn create_transporter(&self, config: NetConfigRequestWasm) -> Result<u32, NetResultStatus> {
-        let config: NetConfigRequest = config.to_config()?;
+        self.insert_transporter(config.to_config()?, None)
+    }
+
+    /// Creates a transport with a handler registered through `register_transport`.
+    /// `config.protocol` is replaced by the protocol the handler was registered with.
+    pub fn create_custom_transporter(
+        &self,
+        protocol_id: u8,
+        config: NetConfigRequestWasm,
+    ) -> Result<u32, NetResultStatus> {
+        let (protocol, factory) = CUSTOM_TRANSPORTS
+            .lock()
+            .get(&protocol_id)
+            .map(|custom| (custom.protocol, Arc::clone(&custom.factory)))
+            .ok_or(NetResultStatus::InvalidConfigParameters)?;
+        let mut config = config.to_config()?;
+        config.protocol = protocol;
+        self.insert_transporter(config, Some(factory))
+    }
+
+    fn insert_transporter(
+        &self,
+        config: NetConfigRequest,
+        factory: Option<TransportFactory>,
+    ) -> Result<u32, NetResultStatus> {
         let mut id_guard = self.next_id.lock();
         let transport_id = *id_guard;
         *id_guard += 1;
@@ -109,19 +153,30 @@ impl DartTransporter {
             let _ = callback.call1(&this, &arg);
         });
 
+        if let Some(factory) = factory {
+            let transport = factory(config, rust_callback, transport_id)?;
+            self.transports
+                .lock()
+                .insert(transport_id, Arc::new(TransporterEntry { transport }));
+            return Ok(transport_id);
+        }
         let transport: Box<dyn Transport> = match config.protocol {
             crate::types::config::NetProtocol::Http => {
                 Box::new(HttpTransport::create(config, rust_callback, transport_id)?)
             }
+            #[cfg(feature = "grpc")]
             crate::types::config::NetProtocol::Grpc => {
                 Box::new(GrpcTransport::create(config, rust_callback, transport_id)?)
             }
+            #[cfg(feature = "websocket")]
             crate::types::config::NetProtocol::WebSocket
             | crate::types::config::NetProtocol::Socket => Box::new(SocketTransport::create(
                 config,
                 rust_callback,
                 transport_id,
             )?),
+            #[cfg(not(all(feature = "grpc", feature = "websocket")))]
+            _ => return Err(NetResultStatus::InvalidConfigParameters),
         };
 
         self.transports
@@ -164,10 +219,10 @@ impl DartTransporter {
         let response = match select(request_future, timeout_future).await {
             Either::Left((res, _)) => match res {
                 Ok(res) => res,
-                Err(err) => NetResponseKind::ResponseError(err),
+                Err(err) => NetResponseKind::ResponseError(err.into()),
             }, // request completed first
             Either::Right((_, _)) => {
-                NetResponseKind::ResponseError(NetResultStatus::RequestTimeout)
+                NetResponseKind::ResponseError(NetResultStatus::RequestTimeout.into())
             }
         };
 
@@ -181,6 +236,22 @@ impl DartTransporter {
         Ok(js_response)
     }
 
+    /// Resolves `reference` against the url this transport was created with
+    #[wasm_bindgen]
+    pub fn resolve_url(
+        &self,
+        transport_id: u32,
+        reference: String,
+    ) -> Result<String, NetResultStatus> {
+        let entry = self
+            .transports
+            .lock()
+            .get(&transport_id)
+            .cloned()
+            .ok_or(NetResultStatus::TransportNotFound)?;
+        Utils::resolve_url(&entry.transport.get_config().addr.url, &reference)
+    }
+
     #[wasm_bindgen]
     pub async fn close_transport(&self, transport_id: u32) -> NetResultStatus {
         debug!("close transport: {:#?}", transport_id);
@@ -193,3 +264,80 @@ impl DartTransporter {
         NetResultStatus::OK
     }
 }
+
+#[wasm_bindgen]
+pub fn status_is_transient(status: NetResultStatus) -> bool {
+    status.is_transient()
+}
+
+#[wasm_bindgen]
+pub fn status_category(status: NetResultStatus) -> NetErrorCategory {
+    status.category()
+}
+
+/// `keys` and `values` are parallel lists of query parameters
+#[wasm_bindgen]
+pub fn url_merge_query(
+    url: String,
+    keys: Vec<String>,
+    values: Vec<String>,
+) -> Result<String, NetResultStatus> {
+    if keys.len() != values.len() {
+        return Err(NetResultStatus::InvalidRequestParameters);
+    }
+    let params: Vec<(&str, &str)> = keys
+        .iter()
+        .zip(values.iter())
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    Utils::merge_query_params(&url, &params)
+}
+
+#[wasm_bindgen]
+pub fn url_encode_path_segment(segment: String) -> String {
+    Utils::encode_path_segment(&segment)
+}
+
+#[wasm_bindgen]
+pub fn url_resolve(base: String, reference: String) -> Result<String, NetResultStatus> {
+    Utils::resolve_url(&base, &reference)
+}
+
+#[wasm_bindgen]
+pub fn url_parse(url: String, protocol: NetProtocol) -> Result<NetAddressInfoWasm, NetResultStatus> {
+    Utils::parse_protocol_url(&url, protocol).map(NetAddressInfoWasm::from)
+}
+
+/// Registers a handler used by `DartTransporter::create_custom_transporter` for `protocol_id`.
+///
+/// `protocol` selects the built-in protocol used to parse the url and validate requests.
+pub fn register_transport<F>(
+    protocol_id: u8,
+    protocol: NetProtocol,
+    factory: F,
+) -> Result<(), NetResultStatus>
+where
+    F: Fn(NetConfigRequest, DartCallback, u32) -> Result<Box<dyn Transport>, NetResultStatus>
+        + Send
+        + Sync
+        + 'static,
+{
+    if protocol_id == 0
+        || (NetProtocol::Http as u8..=NetProtocol::Socket as u8).contains(&protocol_id)
+    {
+        return Err(NetResultStatus::InvalidConfigParameters);
+    }
+    CUSTOM_TRANSPORTS.lock().insert(
+        protocol_id,
+        CustomTransport {
+            protocol,
+            factory: Arc::new(factory),
+        },
+    );
+    Ok(())
+}
+
+/// Removes a registered handler. Transports already created keep running
+pub fn unregister_transport(protocol_id: u8) -> bool {
+    CUSTOM_TRANSPORTS.lock().remove(&protocol_id).is_some()
+}
@@ -1,5 +1,6 @@
 pub mod client;
 pub mod connector;
+pub mod sdk;
 pub mod stream;
 /// spub mod tmp;
 pub mod transport;
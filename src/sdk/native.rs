@@ -0,0 +1,533 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::sync::broadcast;
+
+use crate::{
+    client::native::{
+        GrpcStreamHandle, HttpDownloadSink, IGrpcClient, IHttpClient, IStreamClient,
+        NetMessageStream,
+    },
+    stream::{
+        CaptivePortal, DnsCache, DnsResolver, MdnsDiscovery, NetworkWatcher, NtpClient, Pinger,
+        PortCheck, PortMapper, SpeedTest, StreamSplice, StreamUtils, StunClient,
+    },
+    transport::native::{http::HttpTransport, socket::SocketTransport},
+    types::{
+        config::{NetConfig, NetConfigRequest, NetConfigTor, NetMode, NetProtocol},
+        error::NetError,
+        grpc_health::{NetGrpcHealth, NetGrpcServingStatus},
+        native::request::{
+            NetDnsResolver, NetHttpHeaderRef, NetHttpResume, NetHttpRetryConfig,
+            NetRequestCaptivePortal, NetRequestDns, NetRequestDnsPrefetch, NetRequestMdns,
+            NetRequestNtp, NetRequestPing, NetRequestPortCheck, NetRequestPortMapping,
+            NetRequestSpeedTest, NetRequestStun,
+        },
+        response::{
+            NetDnsRecord, NetDnsRecordType, NetMdnsService, NetPortMappingProtocol,
+            NetResponseCaptivePortal, NetResponseDnsPrefetch, NetResponseGrpcUnary,
+            NetResponseHttp, NetResponseInterfaces, NetResponseNtp, NetResponsePing,
+            NetResponsePortCheck, NetResponsePortMapping, NetResponseSplice, NetResponseStun,
+            NetSpeedTestProgress, NetSpeedTestResult,
+        },
+    },
+    utils::subscribers::NetSubscription,
+};
+
+#[cfg(feature = "grpc")]
+use crate::{
+    client::grpc::interceptor::NetGrpcInterceptor, transport::native::grpc::GrpcTransport,
+};
+#[cfg(feature = "grpc")]
+use std::sync::Arc;
+
+/// Rust entry point to the same clients the Dart transports use, without a callback
+pub struct NetSdk;
+
+impl NetSdk {
+    pub async fn init_tor(config: &NetConfigTor) -> Result<(), NetError> {
+        StreamUtils::init_tor_config(config).await
+    }
+
+    pub async fn dns_lookup(
+        name: &str,
+        record_type: NetDnsRecordType,
+        resolver: NetDnsResolver,
+    ) -> Result<Vec<NetDnsRecord>, NetError> {
+        DnsResolver::lookup(&NetRequestDns {
+            name: name.to_string(),
+            record_type,
+            resolver,
+        })
+        .await
+    }
+
+    /// Resolves `names` ahead of their first connect and caches the addresses
+    pub async fn dns_prefetch(names: &[&str], resolver: NetDnsResolver) -> NetResponseDnsPrefetch {
+        DnsCache::prefetch(&NetRequestDnsPrefetch {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            resolver,
+        })
+        .await
+    }
+
+    /// Instances of `service`, e.g. `_http._tcp.local`, found within `duration`
+    pub async fn mdns_browse(
+        service: &str,
+        duration: Duration,
+    ) -> Result<Vec<NetMdnsService>, NetError> {
+        let mut found = Vec::new();
+        MdnsDiscovery::run(
+            &NetRequestMdns::Browse(service.to_string()),
+            duration,
+            |service| found.push(service),
+        )
+        .await?;
+        Ok(found)
+    }
+
+    /// `None` when `instance` did not answer within `duration`
+    pub async fn mdns_resolve(
+        instance: &str,
+        duration: Duration,
+    ) -> Result<Option<NetMdnsService>, NetError> {
+        let mut found = None;
+        MdnsDiscovery::run(
+            &NetRequestMdns::Resolve(instance.to_string()),
+            duration,
+            |service| found = Some(service),
+        )
+        .await?;
+        Ok(found)
+    }
+
+    /// `count` probes to `host`, one every `interval`
+    pub async fn ping(
+        host: &str,
+        count: u16,
+        interval: Duration,
+    ) -> Result<NetResponsePing, NetError> {
+        Pinger::ping(&NetRequestPing {
+            host: host.to_string(),
+            count,
+            interval_ms: interval.as_millis().min(u32::MAX as u128) as u32,
+        })
+        .await
+    }
+
+    /// Clock offset from the first of `servers` that answers; the default pool when empty
+    pub async fn ntp_query(servers: &[&str]) -> Result<NetResponseNtp, NetError> {
+        NtpClient::query(&NetRequestNtp {
+            servers: servers.iter().map(|server| server.to_string()).collect(),
+        })
+        .await
+    }
+
+    /// Reflexive address of one local socket as seen by each of `servers`; the default
+    /// servers when empty
+    pub async fn stun_binding(servers: &[&str]) -> Result<NetResponseStun, NetError> {
+        StunClient::bind(&NetRequestStun {
+            servers: servers.iter().map(|server| server.to_string()).collect(),
+        })
+        .await
+    }
+
+    /// Whether `host:port` accepts a TCP connection within `timeout`
+    pub async fn port_check(
+        host: &str,
+        port: u16,
+        mode: NetMode,
+        timeout: Duration,
+    ) -> Result<NetResponsePortCheck, NetError> {
+        PortCheck::check(&NetRequestPortCheck {
+            host: host.to_string(),
+            port,
+            mode,
+            timeout_ms: timeout.as_millis().clamp(1, u32::MAX as u128) as u32,
+        })
+        .await
+    }
+
+    /// Maps or renews `external_port` (0 for the internal port) for `lifetime`; a zero
+    /// `lifetime` removes the mapping
+    pub async fn port_mapping(
+        protocol: NetPortMappingProtocol,
+        internal_port: u16,
+        external_port: u16,
+        lifetime: Duration,
+        gateway: Option<&str>,
+    ) -> Result<NetResponsePortMapping, NetError> {
+        PortMapper::map(&NetRequestPortMapping {
+            protocol,
+            internal_port,
+            external_port,
+            lifetime: lifetime.as_secs().min(u32::MAX.into()) as u32,
+            gateway: gateway.map(str::to_string),
+        })
+        .await
+    }
+
+    /// `duration` per direction; `on_progress` is called while a direction is measured
+    pub async fn speed_test(
+        download_url: Option<&str>,
+        upload_url: Option<&str>,
+        duration: Duration,
+        rtt_samples: u8,
+        mode: NetMode,
+        on_progress: impl FnMut(NetSpeedTestProgress),
+    ) -> Result<NetSpeedTestResult, NetError> {
+        SpeedTest::run(
+            &NetRequestSpeedTest {
+                download_url: download_url.map(str::to_string),
+                upload_url: upload_url.map(str::to_string),
+                duration_ms: duration.as_millis().clamp(1, u32::MAX as u128) as u32,
+                rtt_samples,
+                mode,
+            },
+            on_progress,
+        )
+        .await
+    }
+
+    /// Whether the network is open, behind a captive portal or blocked, probing `url` or
+    /// the default generate_204 url
+    pub async fn captive_portal(
+        url: Option<&str>,
+        timeout: Duration,
+    ) -> Result<NetResponseCaptivePortal, NetError> {
+        CaptivePortal::probe(&NetRequestCaptivePortal {
+            url: url.map(str::to_string),
+            timeout_ms: timeout.as_millis().clamp(1, u32::MAX as u128) as u32,
+        })
+        .await
+    }
+
+    pub fn interfaces() -> Result<NetResponseInterfaces, NetError> {
+        NetworkWatcher::snapshot()
+    }
+
+    /// Interfaces after every change of the default route or an address, until the receiver
+    /// is dropped. Must be called within a tokio runtime
+    pub fn watch_interfaces() -> broadcast::Receiver<NetResponseInterfaces> {
+        NetworkWatcher::subscribe()
+    }
+
+    pub fn http(config: NetConfigRequest) -> Result<NetHttp, NetError> {
+        let config = config.to_protocol_config(NetProtocol::Http)?;
+        Ok(NetHttp {
+            client: HttpTransport::create_client(config)?,
+        })
+    }
+
+    /// WebSocket client from a `ws://`/`wss://` config
+    pub fn websocket(config: NetConfigRequest) -> Result<NetStream, NetError> {
+        NetSdk::stream(config.to_protocol_config(NetProtocol::WebSocket)?)
+    }
+
+    /// Raw TCP/TLS client from a `tcp://`/`tls://` config
+    pub fn socket(config: NetConfigRequest) -> Result<NetStream, NetError> {
+        NetSdk::stream(config.to_protocol_config(NetProtocol::Socket)?)
+    }
+
+    #[cfg(feature = "grpc")]
+    pub fn grpc(config: NetConfigRequest) -> Result<NetGrpc, NetError> {
+        let config = config.to_protocol_config(NetProtocol::Grpc)?;
+        Ok(NetGrpc {
+            client: GrpcTransport::create_client(config)?,
+        })
+    }
+
+    fn stream(config: NetConfig) -> Result<NetStream, NetError> {
+        Ok(NetStream {
+            client: SocketTransport::create_client(config)?,
+        })
+    }
+}
+
+pub struct NetHttp {
+    client: Box<dyn IHttpClient>,
+}
+
+impl NetHttp {
+    pub async fn get(&self, url: &str) -> Result<NetResponseHttp, NetError> {
+        self.request("GET", url, None, &[]).await
+    }
+
+    pub async fn post(&self, url: &str, body: &[u8]) -> Result<NetResponseHttp, NetError> {
+        self.request("POST", url, Some(body), &[]).await
+    }
+
+    /// `headers` replace the config headers for this request when not empty
+    pub async fn request(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&[u8]>,
+        headers: &[(&str, &str)],
+    ) -> Result<NetResponseHttp, NetError> {
+        let headers: Option<Vec<NetHttpHeaderRef>> = match headers.is_empty() {
+            true => None,
+            false => Some(
+                headers
+                    .iter()
+                    .map(|(key, value)| NetHttpHeaderRef { key, value })
+                    .collect(),
+            ),
+        };
+        self.client
+            .send(
+                url,
+                method,
+                body.map(Bytes::copy_from_slice),
+                headers.as_ref(),
+                self.client.get_config().encoding,
+                &NetHttpRetryConfig::default(),
+            )
+            .await
+    }
+
+    /// GETs `url` into the file at `path`; `on_progress` hears the bytes written so far and
+    /// the length the server announced. A failed answer keeps its body and writes nothing
+    pub async fn download(
+        &self,
+        url: &str,
+        path: &str,
+        on_progress: impl Fn(u64, Option<u64>) + Send + Sync,
+    ) -> Result<NetResponseHttp, NetError> {
+        self.client
+            .download(
+                url,
+                "GET",
+                None,
+                None,
+                &NetHttpRetryConfig::default(),
+                &HttpDownloadSink {
+                    path,
+                    progress: &on_progress,
+                    resume: None,
+                },
+            )
+            .await
+    }
+
+    /// `download` carrying on from the `offset` bytes already in `<path>.part`, which stay
+    /// when the server sends the rest; `if_range` (an etag or date) restarts the download
+    /// instead when the resource changed since
+    pub async fn resume_download(
+        &self,
+        url: &str,
+        path: &str,
+        offset: u64,
+        if_range: Option<&str>,
+        on_progress: impl Fn(u64, Option<u64>) + Send + Sync,
+    ) -> Result<NetResponseHttp, NetError> {
+        self.client
+            .download(
+                url,
+                "GET",
+                None,
+                None,
+                &NetHttpRetryConfig::default(),
+                &HttpDownloadSink {
+                    path,
+                    progress: &on_progress,
+                    resume: (offset > 0).then_some(NetHttpResume { offset, if_range }),
+                },
+            )
+            .await
+    }
+
+    pub fn config(&self) -> &NetConfig {
+        self.client.get_config()
+    }
+
+    pub async fn close(&self) {
+        self.client.close().await;
+    }
+}
+
+pub struct NetStream {
+    client: Box<dyn IStreamClient>,
+}
+
+impl NetStream {
+    pub async fn connect(&self) -> Result<(), NetError> {
+        self.client.connect().await
+    }
+
+    pub async fn send(&self, data: &[u8]) -> Result<(), NetError> {
+        self.client.send(data).await
+    }
+
+    /// Incoming frames; `Ok(None)` marks the end of the stream
+    pub async fn subscribe(
+        &self,
+    ) -> Result<NetSubscription<Result<Option<Vec<u8>>, NetError>>, NetError> {
+        self.client.subscribe().await
+    }
+
+    /// `subscribe` as a `Stream` of frames
+    pub async fn messages(&self) -> Result<NetMessageStream, NetError> {
+        Ok(NetMessageStream::new(self.client.subscribe().await?))
+    }
+
+    pub fn config(&self) -> &NetConfig {
+        self.client.get_config()
+    }
+
+    pub async fn close(&self) {
+        self.client.close().await;
+    }
+
+    /// Pipes this stream and `other` into each other until either closes, then closes both.
+    /// Dropping the future stops forwarding and leaves them open
+    pub async fn splice(&self, other: &NetStream) -> Result<NetResponseSplice, NetError> {
+        StreamSplice::run(
+            self.client.as_ref(),
+            other.client.as_ref(),
+            std::future::pending(),
+        )
+        .await
+    }
+}
+
+pub struct NetGrpc {
+    client: Box<dyn IGrpcClient>,
+}
+
+impl NetGrpc {
+    pub async fn unary(&self, method: &str, data: &[u8]) -> Result<Vec<u8>, NetError> {
+        Ok(self
+            .unary_with_metadata(method, data, &[])
+            .await?
+            .into_data()
+            .into())
+    }
+
+    /// `unary` sending the call `metadata`, e.g. `("authorization", "Bearer ..")`; the
+    /// response carries the metadata and trailers of the server too
+    pub async fn unary_with_metadata(
+        &self,
+        method: &str,
+        data: &[u8],
+        metadata: &[(&str, &str)],
+    ) -> Result<NetResponseGrpcUnary, NetError> {
+        self.client
+            .unary(
+                Bytes::copy_from_slice(data),
+                method,
+                &NetGrpc::metadata(metadata),
+                None,
+                None,
+            )
+            .await
+    }
+
+    /// `unary_with_metadata` the server is told to give up on after `deadline`, as
+    /// `grpc-timeout`; the call fails with `DEADLINE_EXCEEDED` then
+    pub async fn unary_with_deadline(
+        &self,
+        method: &str,
+        data: &[u8],
+        metadata: &[(&str, &str)],
+        deadline: Duration,
+    ) -> Result<NetResponseGrpcUnary, NetError> {
+        self.client
+            .unary(
+                Bytes::copy_from_slice(data),
+                method,
+                &NetGrpc::metadata(metadata),
+                Some(deadline),
+                None,
+            )
+            .await
+    }
+
+    /// `grpc.health.v1.Health/Check` of `service`, empty for the server as a whole
+    pub async fn health(&self, service: &str) -> Result<NetGrpcServingStatus, NetError> {
+        let response = self
+            .client
+            .unary(
+                NetGrpcHealth::request(service).into(),
+                NetGrpcHealth::CHECK,
+                &[],
+                None,
+                None,
+            )
+            .await?;
+        NetGrpcHealth::check_status(response.data_ref())
+    }
+
+    /// Server stream; drop or `cancel` the handle to stop it
+    pub async fn stream(&self, method: &str, data: &[u8]) -> Result<GrpcStreamHandle, NetError> {
+        self.stream_with_metadata(method, data, &[]).await
+    }
+
+    /// `stream` sending the call `metadata`
+    pub async fn stream_with_metadata(
+        &self,
+        method: &str,
+        data: &[u8],
+        metadata: &[(&str, &str)],
+    ) -> Result<GrpcStreamHandle, NetError> {
+        self.client
+            .stream(
+                Bytes::copy_from_slice(data),
+                method,
+                &NetGrpc::metadata(metadata),
+                None,
+                None,
+            )
+            .await
+    }
+
+    /// `stream_with_metadata` ended by the server after `deadline`
+    pub async fn stream_with_deadline(
+        &self,
+        method: &str,
+        data: &[u8],
+        metadata: &[(&str, &str)],
+        deadline: Duration,
+    ) -> Result<GrpcStreamHandle, NetError> {
+        self.client
+            .stream(
+                Bytes::copy_from_slice(data),
+                method,
+                &NetGrpc::metadata(metadata),
+                Some(deadline),
+                None,
+            )
+            .await
+    }
+
+    /// Server stream as a `Stream` of messages; dropping it cancels the call
+    pub async fn stream_messages(
+        &self,
+        method: &str,
+        data: &[u8],
+    ) -> Result<NetMessageStream, NetError> {
+        Ok(self.stream(method, data).await?.into_stream())
+    }
+
+    fn metadata<'a>(metadata: &[(&'a str, &'a str)]) -> Vec<NetHttpHeaderRef<'a>> {
+        metadata
+            .iter()
+            .map(|(key, value)| NetHttpHeaderRef { key, value })
+            .collect()
+    }
+
+    /// Runs `interceptor` around every call made from now on, e.g. to put a fresh token in
+    /// the metadata or to time calls
+    #[cfg(feature = "grpc")]
+    pub fn add_interceptor(&self, interceptor: Arc<dyn NetGrpcInterceptor>) {
+        self.client.add_interceptor(interceptor);
+    }
+
+    pub fn config(&self) -> &NetConfig {
+        self.client.get_config()
+    }
+
+    pub async fn close(&self) {
+        self.client.close().await;
+    }
+}
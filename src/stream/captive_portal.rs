@@ -0,0 +1,137 @@
+use std::{str::FromStr, time::Duration};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request, StatusCode, Uri};
+use log::debug;
+use tokio::{net::TcpStream, time::timeout};
+use tokio_rustls::client::TlsStream;
+use url::Url;
+
+use crate::{
+    client::http::native::{AutoSendRequest, Connect, SendRequestExt},
+    types::{
+        builder::NetConfigBuilder,
+        config::{NetConfig, NetHttpProtocol, NetMode, NetProtocol},
+        error::{NetError, NetResultStatus},
+        native::request::NetRequestCaptivePortal,
+        response::{NetCaptivePortalState, NetResponseCaptivePortal},
+    },
+};
+
+/// Plain http, portals can only intercept what they can read
+const DEFAULT_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Captive portal probe behind `NetRequestKind::CaptivePortal`.
+///
+/// The probe url answers 204 with an empty body; anything else that still answers is a
+/// portal intercepting the request, and no answer at all means the network is blocked.
+/// Redirects are not followed, their target is the portal.
+pub struct CaptivePortal;
+
+impl CaptivePortal {
+    /// Errors only for a probe url that can not be requested
+    pub async fn probe(
+        request: &NetRequestCaptivePortal,
+    ) -> Result<NetResponseCaptivePortal, NetError> {
+        let url = request.url.as_deref().unwrap_or(DEFAULT_URL);
+        let config = NetConfigBuilder::new(url)
+            .protocol(NetProtocol::Http)
+            .mode(NetMode::Clearnet)
+            .build_config()?;
+        let wait = match request.timeout_ms {
+            0 => DEFAULT_TIMEOUT,
+            ms => Duration::from_millis(ms.into()),
+        };
+        let blocked = || NetResponseCaptivePortal::new(NetCaptivePortalState::Blocked, None, 0);
+        let (status, location, empty) = match timeout(wait, CaptivePortal::fetch(&config)).await {
+            Ok(Ok(answer)) => answer,
+            Ok(Err(e)) => {
+                debug!("captive portal probe of {} failed: {:?}", url, e);
+                return Ok(blocked());
+            }
+            Err(_) => return Ok(blocked()),
+        };
+        let (state, portal_url) = match status {
+            StatusCode::NO_CONTENT => (NetCaptivePortalState::Open, None),
+            // some proxies turn the 204 into an empty 200
+            StatusCode::OK if empty => (NetCaptivePortalState::Open, None),
+            status if status.is_redirection() => {
+                let portal = location.and_then(|location| {
+                    Url::parse(config.addr.url.as_str())
+                        .and_then(|base| base.join(&location))
+                        .ok()
+                        .map(|portal| portal.to_string())
+                });
+                (NetCaptivePortalState::Portal, portal.or(Some(url.into())))
+            }
+            // RFC 6585
+            StatusCode::NETWORK_AUTHENTICATION_REQUIRED => {
+                (NetCaptivePortalState::Portal, Some(url.into()))
+            }
+            status if status.is_success() => (NetCaptivePortalState::Portal, Some(url.into())),
+            _ => (NetCaptivePortalState::Blocked, None),
+        };
+        Ok(NetResponseCaptivePortal::new(
+            state,
+            portal_url,
+            status.as_u16(),
+        ))
+    }
+
+    /// Status, `Location` header and whether the body is empty
+    async fn fetch(config: &NetConfig) -> Result<(StatusCode, Option<String>, bool), NetError> {
+        let mut sender: Box<dyn SendRequestExt> = match config.addr.is_tls {
+            true => Box::new(AutoSendRequest::connect::<TlsStream<TcpStream>>(config).await?),
+            false => Box::new(AutoSendRequest::connect::<TcpStream>(config).await?),
+        };
+        let url = config.addr.url.as_str();
+        let uri = Uri::from_str(url).map_err(|e| {
+            NetError::new(NetResultStatus::InvalidUrl)
+                .with_url(url)
+                .with_source(e)
+        })?;
+        let mut builder = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .header(http::header::CACHE_CONTROL, "no-cache");
+        if sender.protocol() == NetHttpProtocol::Http1 {
+            builder = builder.header(http::header::HOST, config.addr.host.as_str());
+        }
+        let request = builder.body(Full::new(Bytes::new())).map_err(|e| {
+            NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("invalid http request")
+                .with_url(url)
+                .with_source(e)
+        })?;
+        let failed = |e: hyper::Error| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("captive portal probe failed")
+                .with_url(url)
+                .with_source(e)
+        };
+        let response = sender.send(request).await.map_err(failed)?;
+        let status = response.status();
+        let location = response
+            .headers()
+            .get(http::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(str::to_string);
+        // the first data frame is enough to tell a portal page from an empty answer
+        let mut body = response.into_body();
+        let mut empty = true;
+        while let Some(frame) = body.frame().await {
+            if frame
+                .map_err(failed)?
+                .data_ref()
+                .is_some_and(|data| !data.is_empty())
+            {
+                empty = false;
+                break;
+            }
+        }
+        Ok((status, location, empty))
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    future::Future,
+    sync::{Arc, RwLock},
+};
+
+use once_cell::sync::Lazy;
+use rustls::{
+    SignatureScheme,
+    client::ResolvesClientCert,
+    pki_types::{CertificateDer, PrivateKeyDer},
+    sign::CertifiedKey,
+};
+
+use crate::types::error::{NetError, NetResultStatus};
+
+/// Picks the identity a TLS connection presents when the server asks for one, e.g. from a
+/// hardware or OS keystore. Runs during the handshake, on the thread making the connection.
+pub trait ClientCertSelector: Send + Sync {
+    /// `issuers` are the DER distinguished names of the CAs the server accepts, empty when
+    /// it named none. `None` goes on without a certificate.
+    fn select(&self, host: &str, issuers: &[&[u8]]) -> Option<Arc<CertifiedKey>>;
+}
+
+static SELECTOR: Lazy<RwLock<Option<Arc<dyn ClientCertSelector>>>> =
+    Lazy::new(|| RwLock::new(None));
+
+tokio::task_local! {
+    /// Host of the handshake running on the task
+    static HANDSHAKE_HOST: String;
+}
+
+pub struct ClientCertRegistry;
+
+impl ClientCertRegistry {
+    /// Replaces the selector of every later handshake of the built-in TLS configs; `None`
+    /// presents no certificate again. A `tls_client_config` keeps its own client auth.
+    pub fn set(selector: Option<Arc<dyn ClientCertSelector>>) -> Result<(), NetError> {
+        *SELECTOR
+            .write()
+            .map_err(|_| NetError::new(NetResultStatus::InternalError))? = selector;
+        Ok(())
+    }
+
+    /// Identity of a DER chain, leaf first, and its PKCS#8, SEC1 or PKCS#1 DER key
+    pub fn identity(chain: Vec<Vec<u8>>, key: Vec<u8>) -> Result<Arc<CertifiedKey>, NetError> {
+        let invalid = |message: &str| {
+            NetError::new(NetResultStatus::InvalidConfigParameters).with_message(message)
+        };
+        if chain.is_empty() {
+            return Err(invalid("client certificate chain is empty"));
+        }
+        let key = PrivateKeyDer::try_from(key).map_err(invalid)?;
+        let chain = chain.into_iter().map(CertificateDer::from).collect();
+        let identity =
+            CertifiedKey::from_der(chain, key, &rustls::crypto::ring::default_provider())
+                .map_err(|e| invalid("invalid client certificate or key").with_source(e))?;
+        Ok(Arc::new(identity))
+    }
+
+    /// Runs the handshake `future` with `host` known to the selector
+    pub(crate) async fn scope<F: Future>(host: &str, future: F) -> F::Output {
+        HANDSHAKE_HOST.scope(host.to_string(), future).await
+    }
+}
+
+/// Client auth of the built-in TLS configs, asking the selector set at handshake time
+#[derive(Debug)]
+pub(crate) struct ClientCertResolver;
+
+impl ResolvesClientCert for ClientCertResolver {
+    fn resolve(
+        &self,
+        root_hint_subjects: &[&[u8]],
+        _sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        let selector = SELECTOR.read().ok()?.clone()?;
+        let host = HANDSHAKE_HOST.try_with(String::clone).unwrap_or_default();
+        selector.select(&host, root_hint_subjects)
+    }
+
+    fn has_certs(&self) -> bool {
+        SELECTOR
+            .read()
+            .map(|selector| selector.is_some())
+            .unwrap_or(false)
+    }
+}
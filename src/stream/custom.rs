@@ -0,0 +1,197 @@
+use std::{
+    collections::HashMap,
+    io,
+    pin::Pin,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicU32, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use once_cell::sync::Lazy;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+
+use crate::{
+    stream::{AsyncReadWrite, ConnectStream, StreamUtils},
+    types::{
+        config::{NetConfig, NetMode},
+        error::{NetError, NetResultStatus},
+    },
+};
+
+/// Opens the byte stream used by `NetMode::Custom(name)` connections, e.g. a VPN/TUN socket
+/// or an obfuscation layer. TLS is still negotiated on top when the url asks for it.
+#[async_trait::async_trait]
+pub trait CustomConnector: Send + Sync {
+    async fn connect(&self, config: &NetConfig) -> Result<Box<dyn AsyncReadWrite>, NetError>;
+}
+
+static CUSTOM_CONNECTORS: Lazy<RwLock<HashMap<String, Arc<dyn CustomConnector>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+static NEXT_FD_CONNECTOR: AtomicU32 = AtomicU32::new(1);
+
+pub struct CustomStreamRegistry;
+
+impl CustomStreamRegistry {
+    /// Replaces any connector already registered under `name`
+    pub fn register(
+        name: impl Into<String>,
+        connector: Arc<dyn CustomConnector>,
+    ) -> Result<(), NetError> {
+        let name = name.into();
+        if name.is_empty() {
+            return Err(NetError::new(NetResultStatus::InvalidConfigParameters)
+                .with_message("empty custom mode name"));
+        }
+        CUSTOM_CONNECTORS
+            .write()
+            .map_err(|_| NetError::new(NetResultStatus::InternalError))?
+            .insert(name, connector);
+        Ok(())
+    }
+
+    pub fn unregister(name: &str) -> bool {
+        CUSTOM_CONNECTORS
+            .write()
+            .map(|mut connectors| connectors.remove(name).is_some())
+            .unwrap_or(false)
+    }
+
+    pub fn is_registered(name: &str) -> bool {
+        CUSTOM_CONNECTORS
+            .read()
+            .map(|connectors| connectors.contains_key(name))
+            .unwrap_or(false)
+    }
+
+    /// Registers an already-connected socket and returns the custom mode name to connect with.
+    /// The stream is handed out once, so a transport using it cannot redial.
+    /// Must be called from within the tokio runtime.
+    ///
+    /// # Safety
+    /// `fd` must be an open, connected stream socket. Ownership moves to the SDK, which closes
+    /// it when registration fails, or when the name is unregistered before it connected; a
+    /// transport created with `dart_transporter_create_with_fd` unregisters it once closed.
+    #[cfg(unix)]
+    pub unsafe fn register_fd(fd: i32) -> Result<String, NetError> {
+        use std::os::fd::FromRawFd;
+        if fd < 0 {
+            return Err(NetError::new(NetResultStatus::InvalidConfigParameters)
+                .with_message(format!("invalid file descriptor: {}", fd)));
+        }
+        let socket = unsafe { std::net::TcpStream::from_raw_fd(fd) };
+        let socket_error =
+            |e: io::Error| NetError::new(NetResultStatus::SocketError).with_source(e);
+        socket.set_nonblocking(true).map_err(socket_error)?;
+        let stream = TcpStream::from_std(socket).map_err(socket_error)?;
+        let name = format!(
+            "fd:{}:{}",
+            fd,
+            NEXT_FD_CONNECTOR.fetch_add(1, Ordering::Relaxed)
+        );
+        Self::register(
+            name.clone(),
+            Arc::new(FdConnector {
+                name: name.clone(),
+                stream: Mutex::new(Some(stream)),
+            }),
+        )?;
+        Ok(name)
+    }
+
+    fn get(name: &str) -> Result<Arc<dyn CustomConnector>, NetError> {
+        CUSTOM_CONNECTORS
+            .read()
+            .map_err(|_| NetError::new(NetResultStatus::InternalError))?
+            .get(name)
+            .cloned()
+            .ok_or_else(|| {
+                NetError::new(NetResultStatus::InvalidConfigParameters)
+                    .with_message(format!("no connector registered for mode `{}`", name))
+            })
+    }
+}
+
+/// One-shot connector around a socket dialed by the caller
+struct FdConnector {
+    name: String,
+    stream: Mutex<Option<TcpStream>>,
+}
+
+#[async_trait::async_trait]
+impl CustomConnector for FdConnector {
+    async fn connect(&self, _config: &NetConfig) -> Result<Box<dyn AsyncReadWrite>, NetError> {
+        let stream = self
+            .stream
+            .lock()
+            .map_err(|_| NetError::new(NetResultStatus::InternalError))?
+            .take();
+        CustomStreamRegistry::unregister(&self.name);
+        match stream {
+            Some(stream) => Ok(Box::new(stream)),
+            None => Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message("file descriptor stream was already used")),
+        }
+    }
+}
+
+/// Stream returned by a registered `CustomConnector`
+#[derive(Debug)]
+pub struct CustomStream(Box<dyn AsyncReadWrite>);
+
+#[async_trait::async_trait]
+impl ConnectStream for CustomStream {
+    async fn connect(config: &NetConfig) -> Result<Self, NetError> {
+        let NetMode::Custom(name) = &config.mode else {
+            return Err(NetError::new(NetResultStatus::InvalidConfigParameters)
+                .with_message("custom stream requires a custom mode"));
+        };
+        config.host_policy.check_address(&config.addr)?;
+        let connector = CustomStreamRegistry::get(name)?;
+        let connect = connector.connect(config);
+        let stream = StreamUtils::within(config.timeouts.connect(), connect, |limit| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("custom connect timed out after {:?}", limit))
+                .with_url(config.addr.authority())
+        })
+        .await?;
+        Ok(CustomStream(stream))
+    }
+
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl AsyncRead for CustomStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for CustomStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,721 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use once_cell::sync::Lazy;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UdpSocket},
+    task::JoinSet,
+    time::timeout,
+};
+
+use crate::{
+    stream::{NetworkWatcher, StreamUtils},
+    transport::native::http::HttpTransport,
+    types::{
+        builder::NetConfigBuilder,
+        config::{NetConnectResolver, NetMode, NetProtocol},
+        error::{NetError, NetResultStatus},
+        native::request::{
+            NetDnsResolver, NetHttpHeaderRef, NetHttpRetryConfig, NetRequestDns,
+            NetRequestDnsPrefetch,
+        },
+        response::{NetDnsRecord, NetDnsRecordType, NetResponseDnsPrefetch},
+    },
+    utils::buffer::StreamEncoding,
+};
+
+/// Time given to each nameserver before the next one is asked
+const NAMESERVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+const DNS_MESSAGE_TYPE: &str = "application/dns-message";
+
+/// Lifetime of a prefetched answer without a TTL, which is all the OS resolver gives
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Longest a prefetched answer is kept, whatever its TTL
+const MAX_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+struct CachedAddresses {
+    addresses: Vec<IpAddr>,
+    expires_at: Instant,
+    /// `NetworkWatcher::generation` when resolved; another network may answer differently
+    generation: u64,
+}
+
+static DNS_CACHE: Lazy<Mutex<HashMap<String, CachedAddresses>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Explicit DNS queries behind `NetRequestKind::DnsLookup`
+pub struct DnsResolver;
+
+impl DnsResolver {
+    pub async fn lookup(request: &NetRequestDns) -> Result<Vec<NetDnsRecord>, NetError> {
+        let name = DnsResolver::ascii_name(&request.name)?;
+        let record_type = request.record_type;
+        match &request.resolver {
+            NetDnsResolver::System => match record_type {
+                NetDnsRecordType::A | NetDnsRecordType::Aaaa => {
+                    DnsResolver::lookup_system(&name, record_type).await
+                }
+                _ => DnsResolver::lookup_nameservers(&name, record_type).await,
+            },
+            NetDnsResolver::DoH(url, mode) => {
+                DnsResolver::lookup_doh(url, mode, &name, record_type).await
+            }
+            NetDnsResolver::Tor => DnsResolver::lookup_tor(&name, record_type).await,
+            NetDnsResolver::Nameservers(nameservers) => {
+                DnsResolver::query_nameservers(nameservers, &name, record_type).await
+            }
+        }
+    }
+
+    /// Addresses TCP connects to `host` go to with `resolver`, cached for their TTL like
+    /// prefetched ones
+    pub(crate) async fn connect_addresses(
+        host: &str,
+        port: u16,
+        resolver: &NetConnectResolver,
+    ) -> Result<Vec<SocketAddr>, NetError> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        if let Some(socket_addrs) = DnsCache::get(host, port) {
+            return Ok(socket_addrs);
+        }
+        let resolver = match resolver {
+            NetConnectResolver::System => {
+                let resolved = tokio::net::lookup_host((host, port)).await.map_err(|e| {
+                    NetError::new(NetResultStatus::ConnectionError)
+                        .with_message("dns lookup failed")
+                        .with_url(host)
+                        .with_source(e)
+                })?;
+                return Ok(resolved.collect());
+            }
+            NetConnectResolver::Nameservers { servers } => {
+                let mut nameservers = Vec::with_capacity(servers.len());
+                for server in servers {
+                    nameservers.extend(StreamUtils::resolve_server(server, 53).await?);
+                }
+                NetDnsResolver::Nameservers(nameservers)
+            }
+            NetConnectResolver::Doh { url } => NetDnsResolver::DoH(url.clone(), NetMode::Clearnet),
+        };
+        // boxed, the lookup of a DoH server connects through here again
+        Box::pin(DnsCache::resolve(host, resolver)).await?;
+        DnsCache::get(host, port).ok_or_else(|| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("host has no address")
+                .with_url(host)
+        })
+    }
+
+    fn ascii_name(name: &str) -> Result<String, NetError> {
+        let invalid = || {
+            NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message(format!("invalid dns name: {}", name))
+        };
+        let ascii = idna::domain_to_ascii(name.trim_end_matches('.')).map_err(|_| invalid())?;
+        if ascii.is_empty()
+            || ascii.len() > 253
+            || ascii
+                .split('.')
+                .any(|label| label.is_empty() || label.len() > 63)
+        {
+            return Err(invalid());
+        }
+        Ok(ascii)
+    }
+
+    fn address_records(
+        name: &str,
+        record_type: NetDnsRecordType,
+        addresses: impl IntoIterator<Item = IpAddr>,
+    ) -> Vec<NetDnsRecord> {
+        let mut seen = HashSet::new();
+        addresses
+            .into_iter()
+            .filter(|ip| match record_type {
+                NetDnsRecordType::A => ip.is_ipv4(),
+                _ => ip.is_ipv6(),
+            })
+            .filter(|ip| seen.insert(*ip))
+            .map(|ip| NetDnsRecord::new(record_type, name.to_string(), 0, ip.to_string()))
+            .collect()
+    }
+
+    async fn lookup_system(
+        name: &str,
+        record_type: NetDnsRecordType,
+    ) -> Result<Vec<NetDnsRecord>, NetError> {
+        let addresses = tokio::net::lookup_host((name, 0)).await.map_err(|e| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("dns lookup failed")
+                .with_url(name)
+                .with_source(e)
+        })?;
+        Ok(DnsResolver::address_records(
+            name,
+            record_type,
+            addresses.map(|addr| addr.ip()),
+        ))
+    }
+
+    #[cfg(feature = "tor")]
+    async fn lookup_tor(
+        name: &str,
+        record_type: NetDnsRecordType,
+    ) -> Result<Vec<NetDnsRecord>, NetError> {
+        if !matches!(record_type, NetDnsRecordType::A | NetDnsRecordType::Aaaa) {
+            return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("tor exits only resolve A/AAAA; use DoH over tor instead"));
+        }
+        let client = crate::stream::StreamUtils::get_tor_client().await?;
+        let addresses = client.resolve(name).await.map_err(|e| {
+            NetError::new(NetResultStatus::TorNetError)
+                .with_message("dns lookup failed")
+                .with_url(name)
+                .with_source(e)
+        })?;
+        Ok(DnsResolver::address_records(name, record_type, addresses))
+    }
+
+    #[cfg(not(feature = "tor"))]
+    async fn lookup_tor(
+        _name: &str,
+        _record_type: NetDnsRecordType,
+    ) -> Result<Vec<NetDnsRecord>, NetError> {
+        Err(NetError::feature_disabled("tor"))
+    }
+
+    async fn lookup_doh(
+        url: &str,
+        mode: &NetMode,
+        name: &str,
+        record_type: NetDnsRecordType,
+    ) -> Result<Vec<NetDnsRecord>, NetError> {
+        let config = NetConfigBuilder::new(url)
+            .protocol(NetProtocol::Http)
+            .mode(mode.clone())
+            .build_config()?;
+        let client = HttpTransport::create_client(config)?;
+        // RFC 8484 asks for id 0 so responses stay cacheable
+        let query = DnsMessage::query(0, name, record_type);
+        let headers = vec![
+            NetHttpHeaderRef {
+                key: "content-type",
+                value: DNS_MESSAGE_TYPE,
+            },
+            NetHttpHeaderRef {
+                key: "accept",
+                value: DNS_MESSAGE_TYPE,
+            },
+        ];
+        let response = client
+            .send(
+                url,
+                "POST",
+                Some(query.into()),
+                Some(&headers),
+                StreamEncoding::Raw,
+                &NetHttpRetryConfig::default(),
+            )
+            .await;
+        client.close().await;
+        let response = response?;
+        if response.status_code() != 200 {
+            return Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("DoH server answered {}", response.status_code()))
+                .with_url(url));
+        }
+        Ok(DnsMessage::parse(0, response.body_ref())?.1)
+    }
+
+    /// Nameservers listed in `/etc/resolv.conf`, port 53
+    fn system_nameservers() -> Vec<SocketAddr> {
+        if !cfg!(unix) {
+            return Vec::new();
+        }
+        std::fs::read_to_string("/etc/resolv.conf")
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("nameserver"))
+            .filter_map(|server| server.trim().split('%').next()?.parse::<IpAddr>().ok())
+            .map(|ip| SocketAddr::new(ip, 53))
+            .collect()
+    }
+
+    async fn lookup_nameservers(
+        name: &str,
+        record_type: NetDnsRecordType,
+    ) -> Result<Vec<NetDnsRecord>, NetError> {
+        let nameservers = DnsResolver::system_nameservers();
+        if nameservers.is_empty() {
+            return Err(
+                NetError::new(NetResultStatus::InvalidRequestParameters).with_message(format!(
+                    "no system nameserver for {:?} lookups; use DoH",
+                    record_type
+                )),
+            );
+        }
+        DnsResolver::query_nameservers(&nameservers, name, record_type).await
+    }
+
+    /// Asks each of `nameservers` in turn until one answers
+    async fn query_nameservers(
+        nameservers: &[SocketAddr],
+        name: &str,
+        record_type: NetDnsRecordType,
+    ) -> Result<Vec<NetDnsRecord>, NetError> {
+        let mut last_error = None;
+        for &nameserver in nameservers {
+            let id = DnsMessage::random_id();
+            let query = DnsMessage::query(id, name, record_type);
+            match timeout(
+                NAMESERVER_TIMEOUT,
+                DnsResolver::exchange(nameserver, id, &query),
+            )
+            .await
+            {
+                Ok(Ok(records)) => return Ok(records),
+                Ok(Err(e)) => last_error = Some(e),
+                Err(_) => {
+                    last_error = Some(
+                        NetError::new(NetResultStatus::RequestTimeout)
+                            .with_message("nameserver did not answer")
+                            .with_url(nameserver.to_string()),
+                    )
+                }
+            }
+            debug!("dns lookup via {} failed: {:?}", nameserver, last_error);
+        }
+        Err(last_error.unwrap_or_else(|| NetError::new(NetResultStatus::InternalError)))
+    }
+
+    /// UDP first, TCP when the answer was truncated
+    async fn exchange(
+        nameserver: SocketAddr,
+        id: u16,
+        query: &[u8],
+    ) -> Result<Vec<NetDnsRecord>, NetError> {
+        let socket_error = |e: std::io::Error| {
+            NetError::new(NetResultStatus::SocketError)
+                .with_url(nameserver.to_string())
+                .with_source(e)
+        };
+        let local: SocketAddr = match nameserver {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+        let socket = UdpSocket::bind(local).await.map_err(socket_error)?;
+        socket.connect(nameserver).await.map_err(socket_error)?;
+        socket.send(query).await.map_err(socket_error)?;
+        let mut buffer = vec![0u8; 4096];
+        let (truncated, records) = loop {
+            let len = socket.recv(&mut buffer).await.map_err(socket_error)?;
+            // ignore stray datagrams that do not answer this query
+            match DnsMessage::parse(id, &buffer[..len]) {
+                Err(e) if matches!(e.status(), NetResultStatus::InvalidRequestParameters) => {
+                    continue;
+                }
+                result => break result?,
+            }
+        };
+        if !truncated {
+            return Ok(records);
+        }
+        let mut stream = TcpStream::connect(nameserver).await.map_err(socket_error)?;
+        let mut framed = (query.len() as u16).to_be_bytes().to_vec();
+        framed.extend_from_slice(query);
+        stream.write_all(&framed).await.map_err(socket_error)?;
+        let len = stream.read_u16().await.map_err(socket_error)?;
+        let mut answer = vec![0u8; len as usize];
+        stream.read_exact(&mut answer).await.map_err(socket_error)?;
+        Ok(DnsMessage::parse(id, &answer)?.1)
+    }
+}
+
+/// Addresses warmed by `NetRequestKind::DnsPrefetch`.
+///
+/// TCP connects look a host up here before asking `NetConfig::resolver`, so the first request
+/// to a prefetched name skips the lookup. Names the configured nameservers or DoH server
+/// answered are kept here too; the host policy still applies to cached addresses, and Tor
+/// connections never use them.
+pub struct DnsCache;
+
+impl DnsCache {
+    /// Resolves every name concurrently with `request.resolver` and caches the A and AAAA
+    /// answers; a name fails when neither family resolved
+    pub async fn prefetch(request: &NetRequestDnsPrefetch) -> NetResponseDnsPrefetch {
+        let mut lookups = JoinSet::new();
+        for name in &request.names {
+            let name = name.clone();
+            let resolver = request.resolver.clone();
+            lookups.spawn(async move {
+                let resolved = DnsCache::resolve(&name, resolver).await;
+                (name, resolved)
+            });
+        }
+        let mut resolved = 0;
+        let mut failed = Vec::new();
+        while let Some(lookup) = lookups.join_next().await {
+            match lookup {
+                Ok((_, Ok(()))) => resolved += 1,
+                Ok((name, Err(e))) => {
+                    debug!("dns prefetch of {} failed: {:?}", name, e);
+                    failed.push(name);
+                }
+                Err(e) => debug!("dns prefetch task failed: {:?}", e),
+            }
+        }
+        NetResponseDnsPrefetch::new(resolved, failed)
+    }
+
+    /// Cached addresses of `host` with `port`; `None` when it was not prefetched, expired,
+    /// or resolved on another network
+    pub(crate) fn get(host: &str, port: u16) -> Option<Vec<SocketAddr>> {
+        let key = host.trim_end_matches('.').to_ascii_lowercase();
+        let mut cache = DNS_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = cache.get(&key)?;
+        if entry.expires_at > Instant::now() && entry.generation == NetworkWatcher::generation() {
+            return Some(
+                entry
+                    .addresses
+                    .iter()
+                    .map(|ip| SocketAddr::new(*ip, port))
+                    .collect(),
+            );
+        }
+        cache.remove(&key);
+        None
+    }
+
+    async fn resolve(name: &str, resolver: NetDnsResolver) -> Result<(), NetError> {
+        let ascii = DnsResolver::ascii_name(name)?;
+        let generation = NetworkWatcher::generation();
+        let (mut addresses, ttl): (Vec<IpAddr>, Option<u32>) = match resolver {
+            // the OS answers both families in one lookup, without a TTL
+            NetDnsResolver::System => {
+                let addresses = tokio::net::lookup_host((ascii.as_str(), 0))
+                    .await
+                    .map_err(|e| {
+                        NetError::new(NetResultStatus::ConnectionError)
+                            .with_message("dns lookup failed")
+                            .with_url(name)
+                            .with_source(e)
+                    })?
+                    .map(|addr| addr.ip())
+                    .collect();
+                (addresses, None)
+            }
+            resolver => {
+                let lookup = |record_type| {
+                    let request = NetRequestDns {
+                        name: ascii.clone(),
+                        record_type,
+                        resolver: resolver.clone(),
+                    };
+                    async move { DnsResolver::lookup(&request).await }
+                };
+                let (a, aaaa) =
+                    tokio::join!(lookup(NetDnsRecordType::A), lookup(NetDnsRecordType::Aaaa));
+                let records: Vec<NetDnsRecord> = match (a, aaaa) {
+                    (Err(e), Err(_)) => return Err(e),
+                    (a, aaaa) => a
+                        .unwrap_or_default()
+                        .into_iter()
+                        .chain(aaaa.unwrap_or_default())
+                        .collect(),
+                };
+                let ttl = records
+                    .iter()
+                    .map(|record| record.ttl())
+                    .filter(|ttl| *ttl > 0)
+                    .min();
+                let addresses = records
+                    .iter()
+                    .filter_map(|record| record.value().parse().ok())
+                    .collect();
+                (addresses, ttl)
+            }
+        };
+        let mut seen = HashSet::new();
+        addresses.retain(|ip| seen.insert(*ip));
+        if addresses.is_empty() {
+            return Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message("host has no address")
+                .with_url(name));
+        }
+        let ttl = ttl
+            .map_or(DEFAULT_CACHE_TTL, |ttl| Duration::from_secs(ttl.into()))
+            .min(MAX_CACHE_TTL);
+        DNS_CACHE.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            ascii,
+            CachedAddresses {
+                addresses,
+                expires_at: Instant::now() + ttl,
+                generation,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Minimal DNS wire format (RFC 1035) for one question and its answers
+pub(super) struct DnsMessage;
+
+impl DnsMessage {
+    fn random_id() -> u16 {
+        let mut id = [0u8; 2];
+        let provider = rustls::crypto::ring::default_provider();
+        if provider.secure_random.fill(&mut id).is_err() {
+            id = (std::process::id() as u16).to_be_bytes();
+        }
+        u16::from_be_bytes(id)
+    }
+
+    fn query(id: u16, name: &str, record_type: NetDnsRecordType) -> Vec<u8> {
+        // recursion desired
+        DnsMessage::question(id, 0x0100, name, record_type as u16)
+    }
+
+    /// Message with a single `IN` question
+    pub(super) fn question(id: u16, flags: u16, name: &str, qtype: u16) -> Vec<u8> {
+        let mut message = Vec::with_capacity(18 + name.len());
+        message.extend_from_slice(&id.to_be_bytes());
+        message.extend_from_slice(&flags.to_be_bytes());
+        message.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+        for label in name.split('.') {
+            message.push(label.len() as u8);
+            message.extend_from_slice(label.as_bytes());
+        }
+        message.push(0);
+        message.extend_from_slice(&qtype.to_be_bytes());
+        message.extend_from_slice(&[0, 1]);
+        message
+    }
+
+    /// Header and every resource record of a response; `None` when it is not a
+    /// well-formed response
+    pub(super) fn read(message: &[u8]) -> Option<DnsResponse<'_>> {
+        let mut reader = DnsReader::at(message, 0);
+        let id = reader.u16()?;
+        let flags = reader.u16()?;
+        if flags & 0x8000 == 0 {
+            return None;
+        }
+        let questions = reader.u16()?;
+        let answers = reader.u16()?;
+        let authority = reader.u16()?;
+        let additional = reader.u16()?;
+        for _ in 0..questions {
+            reader.name()?;
+            reader.skip(4)?;
+        }
+        let total = answers as usize + authority as usize + additional as usize;
+        let records = (0..total)
+            .map(|_| reader.record())
+            .collect::<Option<Vec<_>>>()?;
+        Some(DnsResponse {
+            id,
+            flags,
+            answers: answers as usize,
+            records,
+        })
+    }
+
+    /// Answers of a response to query `id`, and whether it was truncated.
+    /// A mismatched or malformed message is `InvalidRequestParameters`.
+    fn parse(id: u16, message: &[u8]) -> Result<(bool, Vec<NetDnsRecord>), NetError> {
+        let malformed = || {
+            NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("malformed dns response")
+        };
+        let response = DnsMessage::read(message)
+            .filter(|response| response.id == id)
+            .ok_or_else(malformed)?;
+        let rcode = response.flags & 0x000f;
+        if rcode != 0 {
+            let reason = match rcode {
+                2 => "SERVFAIL".to_string(),
+                3 => "NXDOMAIN".to_string(),
+                5 => "REFUSED".to_string(),
+                code => format!("rcode {}", code),
+            };
+            return Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("dns server answered {}", reason)));
+        }
+        let mut records = Vec::with_capacity(response.answers);
+        for answer in &response.records[..response.answers] {
+            let name = answer.name.clone();
+            let ttl = answer.ttl;
+            let record = match u8::try_from(answer.record_type)
+                .ok()
+                .and_then(NetDnsRecordType::from_u8)
+            {
+                Some(record_type @ (NetDnsRecordType::A | NetDnsRecordType::Aaaa)) => {
+                    let ip = answer.ip().ok_or_else(malformed)?;
+                    NetDnsRecord::new(record_type, name, ttl, ip.to_string())
+                }
+                Some(NetDnsRecordType::Cname) => {
+                    let target = answer.target().ok_or_else(malformed)?;
+                    NetDnsRecord::new(NetDnsRecordType::Cname, name, ttl, target)
+                }
+                Some(NetDnsRecordType::Txt) => {
+                    let text = answer.texts().ok_or_else(malformed)?.concat();
+                    NetDnsRecord::new(
+                        NetDnsRecordType::Txt,
+                        name,
+                        ttl,
+                        String::from_utf8_lossy(&text).into_owned(),
+                    )
+                }
+                Some(NetDnsRecordType::Srv) => {
+                    let (priority, weight, port, target) = answer.srv().ok_or_else(malformed)?;
+                    NetDnsRecord::srv(name, ttl, priority, weight, port, target)
+                }
+                None => continue,
+            };
+            records.push(record);
+        }
+        Ok((response.flags & 0x0200 != 0, records))
+    }
+}
+
+pub(super) struct DnsResponse<'a> {
+    pub id: u16,
+    pub flags: u16,
+    /// `records[..answers]` is the answer section, the rest authority and additional
+    pub answers: usize,
+    pub records: Vec<DnsRecordRef<'a>>,
+}
+
+/// Resource record whose data still points into the message, for compressed names
+pub(super) struct DnsRecordRef<'a> {
+    pub name: String,
+    pub record_type: u16,
+    pub ttl: u32,
+    message: &'a [u8],
+    start: usize,
+    pub data: &'a [u8],
+}
+
+impl DnsRecordRef<'_> {
+    /// A/AAAA address
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self.data.len() {
+            4 => Some(IpAddr::from(<[u8; 4]>::try_from(self.data).ok()?)),
+            16 => Some(IpAddr::from(<[u8; 16]>::try_from(self.data).ok()?)),
+            _ => None,
+        }
+    }
+
+    /// CNAME/PTR target name
+    pub fn target(&self) -> Option<String> {
+        DnsReader::at(self.message, self.start).name()
+    }
+
+    /// TXT character strings
+    pub fn texts(&self) -> Option<Vec<&[u8]>> {
+        let mut reader = DnsReader::at(self.data, 0);
+        let mut texts = Vec::new();
+        while reader.offset < self.data.len() {
+            let len = reader.u8()? as usize;
+            texts.push(reader.bytes(len)?);
+        }
+        Some(texts)
+    }
+
+    /// SRV priority, weight, port and target
+    pub fn srv(&self) -> Option<(u16, u16, u16, String)> {
+        let mut reader = DnsReader::at(self.message, self.start);
+        Some((reader.u16()?, reader.u16()?, reader.u16()?, reader.name()?))
+    }
+}
+
+struct DnsReader<'a> {
+    message: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> DnsReader<'a> {
+    fn at(message: &'a [u8], offset: usize) -> Self {
+        Self { message, offset }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self
+            .message
+            .get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn record(&mut self) -> Option<DnsRecordRef<'a>> {
+        let name = self.name()?;
+        let record_type = self.u16()?;
+        self.skip(2)?;
+        let ttl = self.u32()?;
+        let len = self.u16()? as usize;
+        let start = self.offset;
+        Some(DnsRecordRef {
+            name,
+            record_type,
+            ttl,
+            message: self.message,
+            start,
+            data: self.bytes(len)?,
+        })
+    }
+
+    /// Dotted name at the current offset, following compression pointers
+    fn name(&mut self) -> Option<String> {
+        let mut labels: Vec<String> = Vec::new();
+        let mut offset = self.offset;
+        let mut resume = None;
+        // bounds pointer loops
+        for _ in 0..128 {
+            let len = *self.message.get(offset)? as usize;
+            match len {
+                0 => {
+                    self.offset = resume.unwrap_or(offset + 1);
+                    return Some(labels.join("."));
+                }
+                len if len & 0xc0 == 0xc0 => {
+                    let low = *self.message.get(offset + 1)? as usize;
+                    resume.get_or_insert(offset + 2);
+                    offset = ((len & 0x3f) << 8) | low;
+                }
+                len if len <= 63 => {
+                    let label = self.message.get(offset + 1..offset + 1 + len)?;
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    offset += 1 + len;
+                }
+                _ => return None,
+            }
+        }
+        None
+    }
+}
@@ -9,14 +9,13 @@ use tower::Service;
 
 use crate::{
     stream::ConnectStream,
-    types::{
-        config::{NetConfig, NetMode, NetProtocol, NetTlsMode},
-        error::NetResultStatus,
-    },
-    utils::{Utils, buffer::StreamEncoding},
+    types::{config::NetConfig, error::NetError},
+    utils::Utils,
 };
+/// Dials the connections of a gRPC channel with the client's config, only the address taken
+/// from the uri tonic connects to
 pub struct GrpcConnector<T> {
-    pub tls_mode: NetTlsMode,
+    pub config: NetConfig,
     pub _marker: std::marker::PhantomData<T>,
 }
 
@@ -24,7 +23,7 @@ impl<T> GrpcConnector<T> {
     pub fn default(config: &NetConfig) -> Self {
         Self {
             _marker: std::marker::PhantomData,
-            tls_mode: config.tls_mode,
+            config: config.clone(),
         }
     }
 }
@@ -34,7 +33,7 @@ where
     T: ConnectStream,
 {
     type Response = TokioIo<T>;
-    type Error = NetResultStatus;
+    type Error = NetError;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
     fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
@@ -42,17 +41,10 @@ where
     }
 
     fn call(&mut self, req: Uri) -> Self::Future {
-        let tls_mode = self.tls_mode.clone();
+        let config = self.config.clone();
         Box::pin(async move {
             let addr = Utils::parse_http_url(&req.to_string())?;
-            let config = NetConfig {
-                addr,
-                mode: NetMode::Clearnet,
-                protocol: NetProtocol::Grpc,
-                tls_mode: tls_mode,
-                http: Default::default(),
-                encoding: StreamEncoding::Raw,
-            };
+            let config = config.change_addr(addr);
             let stream = T::connect(&config).await?;
             Ok(TokioIo::new(stream))
         })
@@ -0,0 +1,262 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use log::debug;
+use once_cell::sync::Lazy;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::types::{
+    error::NetError,
+    response::{NetInterface, NetResponseInterfaces},
+};
+
+/// How often a watched network is compared against its last snapshot
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Targets of the default route lookups, documentation ranges that only a default route covers
+const ROUTE_PROBE_V4: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), 9);
+const ROUTE_PROBE_V6: SocketAddr = SocketAddr::new(
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+    9,
+);
+
+/// Bumped on every change seen by a running watch
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+static WATCHER: Lazy<Mutex<Option<broadcast::Sender<NetResponseInterfaces>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// Interface listing and the change feed behind `NetRequestKind::Interfaces` and
+/// `NetRequestKind::WatchInterfaces`.
+///
+/// Changes are found by polling, since neither netlink nor the Apple route socket is
+/// available everywhere the SDK runs; one poll task serves all subscribers and stops with
+/// the last of them.
+pub struct NetworkWatcher;
+
+impl NetworkWatcher {
+    pub fn snapshot() -> Result<NetResponseInterfaces, NetError> {
+        let interfaces = NetworkWatcher::interfaces()?;
+        let default_ipv4 = NetworkWatcher::route_source(ROUTE_PROBE_V4);
+        let default_ipv6 = NetworkWatcher::route_source(ROUTE_PROBE_V6);
+        let default_interface = default_ipv4.or(default_ipv6).and_then(|ip| {
+            interfaces
+                .iter()
+                .find(|interface| {
+                    interface
+                        .addresses_ref()
+                        .iter()
+                        .any(|address| address.split('/').next() == Some(ip.to_string().as_str()))
+                })
+                .map(|interface| interface.name())
+        });
+        Ok(NetResponseInterfaces::new(
+            interfaces,
+            default_interface,
+            default_ipv4.map(|ip| ip.to_string()),
+            default_ipv6.map(|ip| ip.to_string()),
+        ))
+    }
+
+    /// Changed snapshots, starting the poll task when nobody was subscribed.
+    /// Must be called within the tokio runtime.
+    pub fn subscribe() -> broadcast::Receiver<NetResponseInterfaces> {
+        let mut watcher = WATCHER.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = watcher.as_ref()
+            && sender.receiver_count() > 0
+        {
+            return sender.subscribe();
+        }
+        let (sender, receiver) = broadcast::channel(16);
+        tokio::spawn(NetworkWatcher::poll(sender.clone()));
+        *watcher = Some(sender);
+        receiver
+    }
+
+    /// Counter of network changes, for clients that reconnect when it moved since they
+    /// connected. It only moves while a watch is running.
+    pub fn generation() -> u64 {
+        GENERATION.load(Ordering::Acquire)
+    }
+
+    async fn poll(sender: broadcast::Sender<NetResponseInterfaces>) {
+        let mut last = NetworkWatcher::snapshot().ok();
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            if sender.receiver_count() == 0 {
+                return;
+            }
+            let current = match NetworkWatcher::snapshot() {
+                Ok(current) => current,
+                Err(e) => {
+                    debug!("network snapshot failed: {:?}", e);
+                    continue;
+                }
+            };
+            if last.as_ref() == Some(&current) {
+                continue;
+            }
+            GENERATION.fetch_add(1, Ordering::AcqRel);
+            let _ = sender.send(current.clone());
+            last = Some(current);
+        }
+    }
+
+    /// Source address the kernel picks for `target`; connecting a UDP socket sends nothing
+    pub(crate) fn route_source(target: SocketAddr) -> Option<IpAddr> {
+        let local: SocketAddr = match target {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(local).ok()?;
+        socket.connect(target).ok()?;
+        Some(socket.local_addr().ok()?.ip())
+    }
+
+    /// Interfaces in the order the system lists them, including those without an address
+    #[cfg(unix)]
+    fn interfaces() -> Result<Vec<NetInterface>, NetError> {
+        use crate::types::error::NetResultStatus;
+
+        let mut head: *mut libc::ifaddrs = std::ptr::null_mut();
+        if unsafe { libc::getifaddrs(&mut head) } != 0 {
+            return Err(NetError::new(NetResultStatus::SocketError)
+                .with_message("getifaddrs failed")
+                .with_source(std::io::Error::last_os_error()));
+        }
+        let mut interfaces: Vec<NetInterface> = Vec::new();
+        let mut current = head;
+        while let Some(entry) = unsafe { current.as_ref() } {
+            current = entry.ifa_next;
+            if entry.ifa_name.is_null() {
+                continue;
+            }
+            // one entry per address, plus a link level one on most systems
+            let name = unsafe { std::ffi::CStr::from_ptr(entry.ifa_name) }.to_string_lossy();
+            let index = match interfaces.iter().position(|i| i.name_ref() == name) {
+                Some(index) => index,
+                None => {
+                    let flags = entry.ifa_flags as libc::c_int;
+                    interfaces.push(NetInterface::new(
+                        name.into_owned(),
+                        unsafe { libc::if_nametoindex(entry.ifa_name) },
+                        flags & libc::IFF_UP != 0,
+                        flags & libc::IFF_LOOPBACK != 0,
+                        Vec::new(),
+                    ));
+                    interfaces.len() - 1
+                }
+            };
+            let address = unsafe { NetworkWatcher::address(entry.ifa_addr) };
+            if let Some(ip) = address {
+                let prefix = unsafe { NetworkWatcher::prefix(entry.ifa_netmask) };
+                interfaces[index].addresses_mut().push(match prefix {
+                    Some(prefix) => format!("{}/{}", ip, prefix),
+                    None => ip.to_string(),
+                });
+            }
+        }
+        unsafe { libc::freeifaddrs(head) };
+        Ok(interfaces)
+    }
+
+    #[cfg(not(unix))]
+    fn interfaces() -> Result<Vec<NetInterface>, NetError> {
+        use crate::types::error::NetResultStatus;
+
+        Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+            .with_message("interface listing is not supported on this platform"))
+    }
+
+    /// # Safety
+    /// `address` must be null or point to a socket address of its family's size
+    #[cfg(unix)]
+    unsafe fn address(address: *const libc::sockaddr) -> Option<IpAddr> {
+        let family = libc::c_int::from(unsafe { address.as_ref() }?.sa_family);
+        match family {
+            libc::AF_INET => {
+                let address = unsafe { &*(address as *const libc::sockaddr_in) };
+                Some(Ipv4Addr::from(u32::from_be(address.sin_addr.s_addr)).into())
+            }
+            libc::AF_INET6 => {
+                let address = unsafe { &*(address as *const libc::sockaddr_in6) };
+                Some(Ipv6Addr::from(address.sin6_addr.s6_addr).into())
+            }
+            _ => None,
+        }
+    }
+
+    /// # Safety
+    /// Same as `address`
+    #[cfg(unix)]
+    unsafe fn prefix(netmask: *const libc::sockaddr) -> Option<u32> {
+        Some(match unsafe { NetworkWatcher::address(netmask) }? {
+            IpAddr::V4(mask) => mask.to_bits().count_ones(),
+            IpAddr::V6(mask) => mask.to_bits().count_ones(),
+        })
+    }
+}
+
+/// Default interface and the source addresses of the default routes
+type Route = (Option<String>, Option<String>, Option<String>);
+
+/// Waits for the default route to move, for the transports of `NetConfig::network_reconnect`.
+/// Changes that leave it alone, such as an interface coming up beside it, are skipped.
+pub struct RouteWatch {
+    changes: broadcast::Receiver<NetResponseInterfaces>,
+    route: Option<Route>,
+}
+
+impl RouteWatch {
+    /// Must be called within the tokio runtime
+    pub fn start() -> Self {
+        // subscribed first, so a change right after the snapshot is not missed
+        let changes = NetworkWatcher::subscribe();
+        let route = NetworkWatcher::snapshot()
+            .ok()
+            .map(|snapshot| RouteWatch::route(&snapshot));
+        Self { changes, route }
+    }
+
+    /// Returns once the default route differs from the one last seen
+    pub async fn changed(&mut self) {
+        loop {
+            let snapshot = match self.changes.recv().await {
+                Ok(snapshot) => Some(snapshot),
+                // the latest state is all that matters
+                Err(RecvError::Lagged(_)) => NetworkWatcher::snapshot().ok(),
+                Err(RecvError::Closed) => {
+                    self.changes = NetworkWatcher::subscribe();
+                    continue;
+                }
+            };
+            let route = snapshot.map(|snapshot| RouteWatch::route(&snapshot));
+            if route.is_some() && route != self.route {
+                self.route = route;
+                return;
+            }
+        }
+    }
+
+    /// `changed` of `watch`, pending forever without one
+    pub async fn wait(watch: &mut Option<RouteWatch>) {
+        match watch {
+            Some(watch) => watch.changed().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn route(snapshot: &NetResponseInterfaces) -> Route {
+        (
+            snapshot.default_interface(),
+            snapshot.default_ipv4(),
+            snapshot.default_ipv6(),
+        )
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use tokio::{net::UdpSocket, time::Instant};
+
+use crate::{
+    stream::dns::{DnsMessage, DnsResponse},
+    types::{
+        error::{NetError, NetResultStatus},
+        native::request::NetRequestMdns,
+        response::NetMdnsService,
+    },
+};
+
+const MDNS_GROUP: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+
+/// Longest pause between repeated queries
+const MAX_QUERY_INTERVAL: Duration = Duration::from_secs(8);
+
+/// Multicast DNS (RFC 6762) and DNS-SD (RFC 6763) over IPv4.
+///
+/// Queries are sent from an ephemeral port, so responders answer by unicast and no
+/// multicast membership or port 5353 binding is needed.
+pub struct MdnsDiscovery;
+
+impl MdnsDiscovery {
+    /// Calls `on_found` once per instance as soon as its host, port and an address are known,
+    /// until `duration` has passed. A resolve returns after its instance was found.
+    pub async fn run(
+        request: &NetRequestMdns,
+        duration: Duration,
+        mut on_found: impl FnMut(NetMdnsService),
+    ) -> Result<(), NetError> {
+        let (name, resolve) = match request {
+            NetRequestMdns::Browse(service) => (service, false),
+            NetRequestMdns::Resolve(instance) => (instance, true),
+        };
+        let name = name.trim_end_matches('.');
+        if !name.to_ascii_lowercase().ends_with(".local") {
+            return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message(format!("not an mdns name: {}", name)));
+        }
+        let socket_error = |e: std::io::Error| {
+            NetError::new(NetResultStatus::SocketError)
+                .with_url(MDNS_GROUP.to_string())
+                .with_source(e)
+        };
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .await
+            .map_err(socket_error)?;
+        let mut state = MdnsState::new(name, resolve);
+        let deadline = Instant::now() + duration;
+        let mut next_query = Instant::now();
+        let mut interval = Duration::from_secs(1);
+        let mut asked = HashSet::new();
+        let mut buffer = vec![0u8; 9000];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(());
+            }
+            // repeat every pending question on each tick, new ones as soon as they come up
+            if now >= next_query {
+                asked.clear();
+                next_query = now + interval;
+                interval = (interval * 2).min(MAX_QUERY_INTERVAL);
+            }
+            for (qname, qtype) in state.questions() {
+                if asked.insert((qname.clone(), qtype)) {
+                    let query = DnsMessage::question(0, 0, &qname, qtype);
+                    socket
+                        .send_to(&query, MDNS_GROUP)
+                        .await
+                        .map_err(socket_error)?;
+                }
+            }
+            let received =
+                tokio::time::timeout_at(deadline.min(next_query), socket.recv_from(&mut buffer))
+                    .await;
+            let len = match received {
+                Err(_) => continue,
+                Ok(Err(e)) => return Err(socket_error(e)),
+                Ok(Ok((len, _))) => len,
+            };
+            let Some(response) = DnsMessage::read(&buffer[..len]) else {
+                continue;
+            };
+            for service in state.update(&response) {
+                on_found(service);
+                if resolve {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct MdnsInstance {
+    name: String,
+    srv: Option<(String, u16)>,
+    /// every DNS-SD instance has one, even if empty
+    txt: Option<Vec<String>>,
+}
+
+/// What is known so far about the browsed service or resolved instance; keys are lowercase
+struct MdnsState {
+    name: String,
+    resolve: bool,
+    instances: HashMap<String, MdnsInstance>,
+    hosts: HashMap<String, Vec<IpAddr>>,
+    reported: HashSet<String>,
+}
+
+impl MdnsState {
+    fn new(name: &str, resolve: bool) -> Self {
+        let mut instances = HashMap::new();
+        if resolve {
+            instances.insert(
+                name.to_ascii_lowercase(),
+                MdnsInstance {
+                    name: name.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        Self {
+            name: name.to_string(),
+            resolve,
+            instances,
+            hosts: HashMap::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    /// Browse PTR, then SRV/TXT for instances and A/AAAA for hosts still missing
+    fn questions(&self) -> Vec<(String, u16)> {
+        let mut questions = Vec::new();
+        if !self.resolve {
+            questions.push((self.name.clone(), TYPE_PTR));
+        }
+        for (key, instance) in &self.instances {
+            if self.reported.contains(key) {
+                continue;
+            }
+            if instance.srv.is_none() {
+                questions.push((instance.name.clone(), TYPE_SRV));
+            }
+            if instance.txt.is_none() {
+                questions.push((instance.name.clone(), TYPE_TXT));
+            }
+            if let Some((host, _)) = &instance.srv
+                && !self.hosts.contains_key(&host.to_ascii_lowercase())
+            {
+                questions.push((host.clone(), TYPE_A));
+                questions.push((host.clone(), TYPE_AAAA));
+            }
+        }
+        questions
+    }
+
+    /// Applies every record of `response` and returns the instances that became complete
+    fn update(&mut self, response: &DnsResponse) -> Vec<NetMdnsService> {
+        let browsed = self.name.to_ascii_lowercase();
+        // a TTL of 0 announces that the record is gone
+        for record in response.records.iter().filter(|record| record.ttl > 0) {
+            let key = record.name.to_ascii_lowercase();
+            match record.record_type {
+                TYPE_PTR if !self.resolve && key == browsed => {
+                    if let Some(instance) = record.target() {
+                        self.instances
+                            .entry(instance.to_ascii_lowercase())
+                            .or_insert_with(|| MdnsInstance {
+                                name: instance,
+                                ..Default::default()
+                            });
+                    }
+                }
+                TYPE_SRV => {
+                    if let (Some(instance), Some((_, _, port, host))) =
+                        (self.instances.get_mut(&key), record.srv())
+                    {
+                        instance.srv = Some((host, port));
+                    }
+                }
+                TYPE_TXT => {
+                    if let (Some(instance), Some(texts)) =
+                        (self.instances.get_mut(&key), record.texts())
+                    {
+                        instance.txt = Some(
+                            texts
+                                .into_iter()
+                                .filter(|text| !text.is_empty())
+                                .map(|text| String::from_utf8_lossy(text).into_owned())
+                                .collect(),
+                        );
+                    }
+                }
+                TYPE_A | TYPE_AAAA => {
+                    if let Some(ip) = record.ip() {
+                        let addresses = self.hosts.entry(key).or_default();
+                        if !addresses.contains(&ip) {
+                            addresses.push(ip);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        let mut found = Vec::new();
+        for (key, instance) in &self.instances {
+            let (Some((host, port)), Some(txt)) = (&instance.srv, &instance.txt) else {
+                continue;
+            };
+            let Some(addresses) = self.hosts.get(&host.to_ascii_lowercase()) else {
+                continue;
+            };
+            if !self.reported.insert(key.clone()) {
+                continue;
+            }
+            let service = match self.resolve {
+                true => instance
+                    .name
+                    .split_once('.')
+                    .map(|(_, service)| service.to_string())
+                    .unwrap_or_default(),
+                false => self.name.clone(),
+            };
+            found.push(NetMdnsService::new(
+                instance.name.clone(),
+                service,
+                host.clone(),
+                *port,
+                addresses.iter().map(|ip| ip.to_string()).collect(),
+                txt.clone(),
+            ));
+        }
+        found
+    }
+}
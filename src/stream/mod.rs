@@ -1,9 +1,67 @@
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(not(target_arch = "wasm32"), feature = "grpc"))]
 pub mod grpc;
 #[cfg(not(target_arch = "wasm32"))]
+mod captive_portal;
+#[cfg(not(target_arch = "wasm32"))]
+mod client_cert;
+#[cfg(not(target_arch = "wasm32"))]
+mod custom;
+#[cfg(not(target_arch = "wasm32"))]
+mod dns;
+#[cfg(not(target_arch = "wasm32"))]
+mod interfaces;
+#[cfg(not(target_arch = "wasm32"))]
+mod mdns;
+#[cfg(not(target_arch = "wasm32"))]
 mod native;
 #[cfg(not(target_arch = "wasm32"))]
+mod ntp;
+#[cfg(not(target_arch = "wasm32"))]
+mod ping;
+#[cfg(not(target_arch = "wasm32"))]
+mod port_check;
+#[cfg(not(target_arch = "wasm32"))]
+mod port_mapping;
+#[cfg(not(target_arch = "wasm32"))]
+mod socks5;
+#[cfg(not(target_arch = "wasm32"))]
+mod speed_test;
+#[cfg(not(target_arch = "wasm32"))]
+mod splice;
+#[cfg(not(target_arch = "wasm32"))]
+mod stun;
+#[cfg(not(target_arch = "wasm32"))]
 mod tls;
+#[cfg(unix)]
+mod unix;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use captive_portal::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use client_cert::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use custom::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use dns::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use interfaces::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use mdns::*;
 #[cfg(not(target_arch = "wasm32"))]
 pub use native::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use ntp::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use ping::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use port_check::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use port_mapping::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use socks5::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use speed_test::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use splice::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use stun::*;
@@ -1,31 +1,87 @@
 pub struct StreamUtils;
+#[cfg(feature = "tor")]
 use arti_client::{StreamPrefs, TorClient, config::TorClientConfigBuilder};
 use log::debug;
 use once_cell::sync::Lazy;
-use rustls::{ClientConfig, RootCertStore, pki_types::ServerName};
-use std::{fmt::Debug, path::Path, sync::Arc};
+use rustls::{
+    ClientConfig, RootCertStore,
+    client::{ClientSessionMemoryCache, Resumption},
+    pki_types::ServerName,
+};
+use socket2::{SockRef, TcpKeepalive};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fmt::Debug,
+    io,
+    net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpStream,
-    sync::OnceCell,
+    task::JoinSet,
 };
+#[cfg(feature = "tor")]
+use std::path::Path;
+#[cfg(feature = "tor")]
+use tokio::sync::OnceCell;
 use tokio_rustls::{TlsConnector, client::TlsStream};
+#[cfg(feature = "tor")]
 use tor_rtcompat::PreferredRuntime;
 
+#[cfg(feature = "tor")]
+use crate::stream::NetworkWatcher;
+
 use crate::{
-    stream::tls::{CustomTlsVerifier, TofuVerifier},
+    stream::{
+        ClientCertRegistry, ClientCertResolver, DnsResolver,
+        tls::{CustomTlsVerifier, TofuVerifier},
+    },
     types::{
-        AddressInfo,
-        config::{NetConfig, NetConfigTor, NetHttpProtocol, NetProtocol, NetTlsMode},
-        error::NetResultStatus,
+        AddressInfo, AddressTransport,
+        config::{
+            NetConfig, NetConfigTor, NetConnectResolver, NetHttpProtocol, NetMode, NetProtocol,
+            NetTlsMode,
+        },
+        error::{NetError, NetResultStatus},
+        host_policy::NetHostPolicy,
     },
 };
 
+#[cfg(feature = "tor")]
 static TOR_CLIENT: OnceCell<TorClient<PreferredRuntime>> = OnceCell::const_new();
 
+/// Client isolated from the circuits built before the last network change
+#[cfg(feature = "tor")]
+static TOR_NETWORK_CLIENT: Lazy<Mutex<Option<TorNetworkClient>>> = Lazy::new(|| Mutex::new(None));
+/// `NetworkWatcher::generation` the client was made for, and the client
+#[cfg(feature = "tor")]
+type TorNetworkClient = (u64, TorClient<PreferredRuntime>);
+
 pub trait AsyncReadWrite: AsyncRead + AsyncWrite + Unpin + Send + Sync + Debug {}
 impl<T> AsyncReadWrite for T where T: AsyncRead + AsyncWrite + Unpin + Send + Sync + Debug + 'static {}
 
+/// Head start of a connection attempt before the next address is tried alongside it, the
+/// RFC 8305 default
+const HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+
+/// TLS session caches shared across connections so reconnects resume, also between configs
+/// that only differ in ALPN. Split by tls mode, since resumption skips verification, and by
+/// net mode so Tor sessions can't be linked to clearnet ones.
+static TLS_SESSION_STORES: Lazy<Mutex<HashMap<TlsSessionKey, Arc<ClientSessionMemoryCache>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// tls mode and `NetMode` debug name
+type TlsSessionKey = (u8, String);
+
+/// Built `ClientConfig`s, so a reconnect reuses one instead of wiring up the verifier again.
+/// A custom `tls_client_config` is not cached, the caller already holds it.
+static TLS_CLIENT_CONFIGS: Lazy<Mutex<HashMap<TlsConfigKey, Arc<ClientConfig>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// `TlsSessionKey` and the ALPN protocols
+type TlsConfigKey = (u8, String, Vec<Vec<u8>>);
+
 static TLS_VERIFIER: Lazy<Arc<rustls::client::WebPkiServerVerifier>> = Lazy::new(|| {
     let mut root_store: RootCertStore = RootCertStore::empty();
     root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
@@ -36,13 +92,32 @@ static TLS_VERIFIER: Lazy<Arc<rustls::client::WebPkiServerVerifier>> = Lazy::new
 });
 
 impl StreamUtils {
-    pub fn get_server_name(host: &str) -> Result<ServerName<'static>, NetResultStatus> {
-        ServerName::try_from(host.to_owned()).map_err(|_| NetResultStatus::InvalidUrl)
+    pub fn get_server_name(host: &str) -> Result<ServerName<'static>, NetError> {
+        // IP literals are verified against the certificate's IP SAN and sent without SNI
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(ServerName::IpAddress(ip.into()));
+        }
+        ServerName::try_from(host.to_owned()).map_err(|e| {
+            NetError::new(NetResultStatus::InvalidUrl)
+                .with_message("invalid TLS server name")
+                .with_url(host)
+                .with_source(e)
+        })
     }
+    #[cfg(not(feature = "tor"))]
+    pub fn tor_inited() -> bool {
+        false
+    }
+    #[cfg(not(feature = "tor"))]
+    pub async fn init_tor_config(_config: &NetConfigTor) -> Result<(), NetError> {
+        Err(NetError::feature_disabled("tor"))
+    }
+    #[cfg(feature = "tor")]
     pub fn tor_inited() -> bool {
         TOR_CLIENT.initialized()
     }
-    pub async fn init_tor_config(config: &NetConfigTor) -> Result<(), NetResultStatus> {
+    #[cfg(feature = "tor")]
+    pub async fn init_tor_config(config: &NetConfigTor) -> Result<(), NetError> {
         if TOR_CLIENT.initialized() {
             return Ok(());
         }
@@ -55,27 +130,41 @@ impl StreamUtils {
                 .build()
                 .map_err(|e| {
                     debug!("Tor client error: {:#?} ", e);
-                    NetResultStatus::InvalidTorConfig
+                    NetError::new(NetResultStatus::InvalidTorConfig).with_source(e)
                 })?;
 
                 TorClient::create_bootstrapped(config).await.map_err(|e| {
                     debug!("create_bootstrapped error: {:#?} ", e);
-                    NetResultStatus::TorInitializationFailed
+                    NetError::new(NetResultStatus::TorInitializationFailed).with_source(e)
                 })
             })
             .await;
         Ok(())
     }
 
-    pub async fn get_tor_client() -> Result<TorClient<PreferredRuntime>, NetResultStatus> {
-        let client = TOR_CLIENT.get();
-        match client {
-            Some(client) => Ok(client.clone()),
-            None => Err(NetResultStatus::TorClientNotInitialized),
+    #[cfg(feature = "tor")]
+    /// Circuits opened over a network that is gone are not reused; after each change the
+    /// streams go through circuits of their own, built over the new network
+    pub async fn get_tor_client() -> Result<TorClient<PreferredRuntime>, NetError> {
+        let Some(client) = TOR_CLIENT.get() else {
+            return Err(NetResultStatus::TorClientNotInitialized.into());
+        };
+        let generation = NetworkWatcher::generation();
+        if generation == 0 {
+            return Ok(client.clone());
+        }
+        let mut isolated = TOR_NETWORK_CLIENT.lock().unwrap_or_else(|e| e.into_inner());
+        match isolated.as_ref() {
+            Some((built_for, client)) if *built_for == generation => Ok(client.clone()),
+            _ => {
+                let client = client.isolated_client();
+                *isolated = Some((generation, client.clone()));
+                Ok(client)
+            }
         }
     }
 
-    pub fn create_tls_config(tls_mode: &NetTlsMode) -> Result<ClientConfig, NetResultStatus> {
+    pub fn create_tls_config(tls_mode: &NetTlsMode) -> Result<ClientConfig, NetError> {
         let tls = TLS_VERIFIER.clone();
         let config = ClientConfig::builder()
             .dangerous()
@@ -83,22 +172,210 @@ impl StreamUtils {
                 tls,
                 tls_mode.clone(),
             )))
-            .with_no_client_auth();
+            .with_client_cert_resolver(Arc::new(ClientCertResolver));
         Ok(config)
     }
-    pub fn create_no_verify_tls_config() -> Result<ClientConfig, NetResultStatus> {
+    fn tls_session_store(tls_mode: &NetTlsMode, mode: &NetMode) -> Arc<ClientSessionMemoryCache> {
+        let key = (*tls_mode as u8, format!("{:?}", mode));
+        let mut stores = TLS_SESSION_STORES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        stores
+            .entry(key)
+            .or_insert_with(|| Arc::new(ClientSessionMemoryCache::new(256)))
+            .clone()
+    }
+    pub fn create_no_verify_tls_config() -> Result<ClientConfig, NetError> {
         Ok(ClientConfig::builder()
             .dangerous()
             .with_custom_certificate_verifier(Arc::new(TofuVerifier))
             .with_no_client_auth())
     }
-    pub async fn create_tcp_stream(addr: &AddressInfo) -> Result<TcpStream, NetResultStatus> {
-        TcpStream::connect((addr.host.to_string(), addr.port))
-            .await
-            .map_err(|e| {
+    pub(crate) fn scoped_socket_addr(addr: &AddressInfo) -> Result<Option<SocketAddr>, NetError> {
+        let Some(scope_id) = &addr.scope_id else {
+            return Ok(None);
+        };
+        let invalid = || {
+            NetError::new(NetResultStatus::InvalidUrl)
+                .with_message(format!("invalid IPv6 zone id: {}", scope_id))
+                .with_url(addr.url.clone())
+        };
+        let ip = addr.host.parse::<Ipv6Addr>().map_err(|_| invalid())?;
+        let index = match scope_id.parse::<u32>() {
+            Ok(index) => index,
+            Err(_) => {
+                let name = CString::new(scope_id.as_str()).map_err(|_| invalid())?;
+                match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+                    0 => return Err(invalid()),
+                    index => index,
+                }
+            }
+        };
+        Ok(Some(SocketAddr::V6(SocketAddrV6::new(ip, addr.port, 0, index))))
+    }
+    pub(crate) fn require_tcp(addr: &AddressInfo) -> Result<(), NetError> {
+        if addr.transport != AddressTransport::Tcp {
+            return Err(NetError::new(NetResultStatus::UnsupportedScheme)
+                .with_message(format!("{:?} sockets are not supported yet", addr.transport))
+                .with_url(addr.url.clone()));
+        }
+        Ok(())
+    }
+    /// `step`, failing with `error` once it took longer than `limit`
+    pub(crate) async fn within<T>(
+        limit: Option<Duration>,
+        step: impl Future<Output = Result<T, NetError>>,
+        error: impl FnOnce(Duration) -> NetError,
+    ) -> Result<T, NetError> {
+        match limit {
+            Some(limit) => tokio::time::timeout(limit, step)
+                .await
+                .unwrap_or_else(|_| Err(error(limit))),
+            None => step.await,
+        }
+    }
+    /// Name resolution included in `connect_timeout`
+    pub async fn create_tcp_stream(
+        addr: &AddressInfo,
+        policy: &NetHostPolicy,
+        resolver: &NetConnectResolver,
+        connect_timeout: Option<Duration>,
+    ) -> Result<TcpStream, NetError> {
+        StreamUtils::require_tcp(addr)?;
+        policy.check_address(addr)?;
+        let connect = async {
+            let stream = match StreamUtils::scoped_socket_addr(addr)? {
+                Some(socket_addr) => TcpStream::connect(socket_addr).await,
+                None => {
+                    let socket_addrs = StreamUtils::socket_addrs(addr, policy, resolver).await?;
+                    StreamUtils::connect_happy_eyeballs(&socket_addrs).await
+                }
+            };
+            stream.map_err(|e| {
                 debug!("create_tcp_stream error: {:#?}, {:#?} ", e, addr.host);
-                NetResultStatus::ConnectionError
+                NetError::new(NetResultStatus::ConnectionError)
+                    .with_message("tcp connect failed")
+                    .with_url(addr.authority())
+                    .with_source(e)
             })
+        };
+        StreamUtils::within(connect_timeout, connect, |limit| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("tcp connect timed out after {:?}", limit))
+                .with_url(addr.authority())
+        })
+        .await
+    }
+    /// RFC 8305 connection attempts: the addresses alternate between IPv6 and IPv4, IPv6
+    /// first, and each attempt gets `HAPPY_EYEBALLS_DELAY` before the next starts alongside
+    /// it, or less when it fails. The first stream up is used, the others are dropped
+    async fn connect_happy_eyeballs(socket_addrs: &[SocketAddr]) -> io::Result<TcpStream> {
+        let (v6, v4): (Vec<SocketAddr>, Vec<SocketAddr>) = socket_addrs
+            .iter()
+            .partition(|socket_addr| socket_addr.is_ipv6());
+        let mut pending = Vec::with_capacity(socket_addrs.len());
+        let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+        loop {
+            let (next_v6, next_v4) = (v6.next(), v4.next());
+            if next_v6.is_none() && next_v4.is_none() {
+                break;
+            }
+            pending.extend(next_v6.into_iter().chain(next_v4));
+        }
+        let mut pending = pending.into_iter();
+        let mut attempts = JoinSet::new();
+        let mut last_error = None;
+        loop {
+            match pending.next() {
+                Some(socket_addr) => {
+                    attempts.spawn(TcpStream::connect(socket_addr));
+                }
+                None if attempts.is_empty() => break,
+                None => {}
+            }
+            let delay = tokio::time::sleep(HAPPY_EYEBALLS_DELAY);
+            // until one attempt is up, fails, or it is time for the next one
+            tokio::select! {
+                joined = attempts.join_next() => match joined {
+                    Some(Ok(Ok(stream))) => return Ok(stream),
+                    Some(Ok(Err(e))) => last_error = Some(e),
+                    Some(Err(e)) => last_error = Some(io::Error::other(e)),
+                    None => {}
+                },
+                _ = delay, if pending.len() > 0 => {}
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host has no address")))
+    }
+    /// Addresses `addr` resolves to, those the policy allows when it filters addresses
+    pub(crate) async fn socket_addrs(
+        addr: &AddressInfo,
+        policy: &NetHostPolicy,
+        resolver: &NetConnectResolver,
+    ) -> Result<Vec<SocketAddr>, NetError> {
+        match policy.filters_addresses() {
+            true => StreamUtils::allowed_socket_addrs(addr, policy, resolver).await,
+            false => DnsResolver::connect_addresses(&addr.host, addr.port, resolver).await,
+        }
+    }
+    /// Resolved addresses of `addr` the policy allows, so a name cannot point past the policy
+    async fn allowed_socket_addrs(
+        addr: &AddressInfo,
+        policy: &NetHostPolicy,
+        resolver: &NetConnectResolver,
+    ) -> Result<Vec<SocketAddr>, NetError> {
+        let resolved = DnsResolver::connect_addresses(&addr.host, addr.port, resolver).await?;
+        let mut denied = None;
+        let allowed: Vec<SocketAddr> = resolved
+            .into_iter()
+            .filter(|socket_addr| match policy.check_ip(socket_addr.ip()) {
+                Ok(()) => true,
+                Err(e) => {
+                    denied = Some(e);
+                    false
+                }
+            })
+            .collect();
+        match (allowed.is_empty(), denied) {
+            (true, Some(e)) => Err(e.with_url(addr.url.clone())),
+            _ => Ok(allowed),
+        }
+    }
+    /// Addresses of a `host`, `host:port`, IP or `[ipv6]:port` server string
+    pub(crate) async fn resolve_server(
+        server: &str,
+        default_port: u16,
+    ) -> Result<Vec<SocketAddr>, NetError> {
+        let ip = server
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>();
+        let host_port = if server.parse::<SocketAddr>().is_ok() {
+            server.to_string()
+        } else if let Ok(ip) = ip {
+            SocketAddr::new(ip, default_port).to_string()
+        } else {
+            match server.rsplit_once(':') {
+                Some((_, port)) if port.parse::<u16>().is_ok() => server.to_string(),
+                _ => format!("{}:{}", server, default_port),
+            }
+        };
+        let resolved: Vec<SocketAddr> = tokio::net::lookup_host(host_port)
+            .await
+            .map_err(|e| {
+                NetError::new(NetResultStatus::ConnectionError)
+                    .with_message("dns lookup failed")
+                    .with_url(server)
+                    .with_source(e)
+            })?
+            .collect();
+        if resolved.is_empty() {
+            return Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message("host has no address")
+                .with_url(server));
+        }
+        Ok(resolved)
     }
     pub async fn create_tls_stream<T: AsyncReadWrite>(
         addr: &AddressInfo,
@@ -106,18 +383,33 @@ impl StreamUtils {
         protocol: &NetProtocol,
         http_protocol: &Option<NetHttpProtocol>,
         tls_mode: &NetTlsMode,
-    ) -> Result<TlsStream<T>, NetResultStatus> {
-        let connector = StreamUtils::create_tls_connector(protocol, http_protocol, tls_mode)?;
+        mode: &NetMode,
+        tls_client_config: Option<&Arc<ClientConfig>>,
+    ) -> Result<TlsStream<T>, NetError> {
+        let connector = StreamUtils::create_tls_connector(
+            protocol,
+            http_protocol,
+            tls_mode,
+            mode,
+            tls_client_config,
+        )?;
         let domain = StreamUtils::get_server_name(&addr.host)?;
-        let stream = connector
-            .connect(domain, stream)
-            .await
-            .map_err(|_| NetResultStatus::TlsError)?;
+        let handshake = ClientCertRegistry::scope(&addr.host, connector.connect(domain, stream));
+        let stream = handshake.await.map_err(|e| {
+            NetError::new(NetResultStatus::TlsError)
+                .with_message("tls handshake failed")
+                .with_url(addr.host.clone())
+                .with_source(e)
+        })?;
         Ok(stream)
     }
+    #[cfg(feature = "tor")]
     pub async fn create_data_stream(
         config: &NetConfig,
-    ) -> Result<arti_client::DataStream, NetResultStatus> {
+    ) -> Result<arti_client::DataStream, NetError> {
+        StreamUtils::require_tcp(&config.addr)?;
+        // names are resolved by the exit relay, only literals can be checked here
+        config.host_policy.check_address(&config.addr)?;
         let client = StreamUtils::get_tor_client().await?;
         let prefs = StreamPrefs::new();
         let stream = client
@@ -125,7 +417,9 @@ impl StreamUtils {
             .await
             .map_err(|e| {
                 debug!("Tor connection error: {:#?} ", e);
-                NetResultStatus::TorNetError
+                NetError::new(NetResultStatus::TorNetError)
+                    .with_url(config.addr.authority())
+                    .with_source(e)
             });
         stream
     }
@@ -134,47 +428,129 @@ impl StreamUtils {
         protocol: &NetProtocol,
         http_protocol: &Option<NetHttpProtocol>,
         tls_mode: &NetTlsMode,
-    ) -> Result<TlsConnector, NetResultStatus> {
-        let mut tls_config = StreamUtils::create_tls_config(tls_mode)?;
-        match protocol {
-            NetProtocol::Http | NetProtocol::Grpc => {
-                tls_config.alpn_protocols = match http_protocol {
-                    Some(protocol) => match protocol {
-                        NetHttpProtocol::Http1 => vec![b"http/1.1".to_vec()],
-                        NetHttpProtocol::Http2 => vec![b"h2".to_vec()],
-                    },
-                    None => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
-                };
-            }
-            _ => (),
+        mode: &NetMode,
+        tls_client_config: Option<&Arc<ClientConfig>>,
+    ) -> Result<TlsConnector, NetError> {
+        let alpn = match protocol {
+            // gRPC needs HTTP/2 whatever the HTTP settings of its config say
+            NetProtocol::Grpc => Some(vec![b"h2".to_vec()]),
+            NetProtocol::Http => Some(match http_protocol {
+                Some(protocol) => match protocol {
+                    NetHttpProtocol::Http1 => vec![b"http/1.1".to_vec()],
+                    NetHttpProtocol::Http2 | NetHttpProtocol::Http2PriorKnowledge => {
+                        vec![b"h2".to_vec()]
+                    }
+                    // TCP only carries what HTTP/3 falls back to
+                    NetHttpProtocol::Http3 => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+                },
+                None => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            }),
+            _ => None,
+        };
+        let config = StreamUtils::tls_client_config(alpn, tls_mode, mode, tls_client_config)?;
+        Ok(TlsConnector::from(config))
+    }
+
+    /// Client config offering `alpn`, shared by the connections of a tls mode, net mode and
+    /// ALPN so their sessions resume. A custom config is kept, ALPN included when it has one
+    pub fn tls_client_config(
+        alpn: Option<Vec<Vec<u8>>>,
+        tls_mode: &NetTlsMode,
+        mode: &NetMode,
+        tls_client_config: Option<&Arc<ClientConfig>>,
+    ) -> Result<Arc<ClientConfig>, NetError> {
+        if let Some(custom) = tls_client_config {
+            // keep the ALPN a custom config asked for
+            let config = match alpn {
+                Some(alpn) if custom.alpn_protocols.is_empty() => {
+                    let mut config = ClientConfig::clone(custom);
+                    config.alpn_protocols = alpn;
+                    Arc::new(config)
+                }
+                _ => Arc::clone(custom),
+            };
+            return Ok(config);
         }
-        Ok(TlsConnector::from(Arc::new(tls_config)))
+        let alpn = alpn.unwrap_or_default();
+        let key = (*tls_mode as u8, format!("{:?}", mode), alpn.clone());
+        let mut configs = TLS_CLIENT_CONFIGS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(config) = configs.get(&key) {
+            return Ok(Arc::clone(config));
+        }
+        let mut config = StreamUtils::create_tls_config(tls_mode)?;
+        config.resumption = Resumption::store(StreamUtils::tls_session_store(tls_mode, mode));
+        config.alpn_protocols = alpn;
+        let config = Arc::new(config);
+        configs.insert(key, Arc::clone(&config));
+        Ok(config)
     }
 }
 
 #[async_trait::async_trait]
 pub trait ConnectStream: AsyncRead + AsyncWrite + Unpin + Send + Sync + Debug + 'static {
-    async fn connect(config: &NetConfig) -> Result<Self, NetResultStatus>
+    async fn connect(config: &NetConfig) -> Result<Self, NetError>
     where
         Self: Sized;
     fn alpn_protocol(&self) -> Option<&[u8]>;
+    /// Turns on TCP keep-alive probes after `idle` without traffic, where the stream has a
+    /// socket of its own
+    fn set_keep_alive(&self, _idle: Duration) -> io::Result<()> {
+        Ok(())
+    }
+    /// Address of the peer the socket is connected to, none where there is no IP peer
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        None
+    }
 }
 
 #[async_trait::async_trait]
 impl ConnectStream for TcpStream {
-    async fn connect(config: &NetConfig) -> Result<Self, NetResultStatus> {
-        StreamUtils::create_tcp_stream(&config.addr).await
+    async fn connect(config: &NetConfig) -> Result<Self, NetError> {
+        StreamUtils::create_tcp_stream(
+            &config.addr,
+            &config.host_policy,
+            &config.resolver,
+            config.timeouts.connect(),
+        )
+        .await
     }
 
     fn alpn_protocol(&self) -> Option<&[u8]> {
         return None;
     }
+
+    fn set_keep_alive(&self, idle: Duration) -> io::Result<()> {
+        let keep_alive = TcpKeepalive::new().with_time(idle);
+        // the system default between probes is a minute or more on most of them
+        #[cfg(any(
+            target_os = "android",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "windows"
+        ))]
+        let keep_alive = keep_alive.with_interval(Duration::from_secs(10));
+        SockRef::from(self).set_tcp_keepalive(&keep_alive)
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        TcpStream::peer_addr(self).ok()
+    }
 }
 
+#[cfg(feature = "tor")]
 #[async_trait::async_trait]
 impl ConnectStream for arti_client::DataStream {
-    async fn connect(config: &NetConfig) -> Result<Self, NetResultStatus> {
-        StreamUtils::create_data_stream(config).await
+    async fn connect(config: &NetConfig) -> Result<Self, NetError> {
+        let connect = StreamUtils::create_data_stream(config);
+        StreamUtils::within(config.timeouts.connect(), connect, |limit| {
+            NetError::new(NetResultStatus::TorNetError)
+                .with_message(format!("tor connect timed out after {:?}", limit))
+                .with_url(config.addr.authority())
+        })
+        .await
     }
     fn alpn_protocol(&self) -> Option<&[u8]> {
         return None;
@@ -186,19 +562,32 @@ impl<T> ConnectStream for TlsStream<T>
 where
     T: ConnectStream + AsyncReadWrite,
 {
-    async fn connect(config: &NetConfig) -> Result<Self, NetResultStatus> {
+    async fn connect(config: &NetConfig) -> Result<Self, NetError> {
         let base_stream = T::connect(config).await?;
-        StreamUtils::create_tls_stream(
+        let handshake = StreamUtils::create_tls_stream(
             &config.addr,
             base_stream,
             &config.protocol,
             &config.http.protocol,
             &config.tls_mode,
-        )
+            &config.mode,
+            config.tls_client_config.as_ref(),
+        );
+        StreamUtils::within(config.timeouts.tls_handshake(), handshake, |limit| {
+            NetError::new(NetResultStatus::TlsError)
+                .with_message(format!("tls handshake timed out after {:?}", limit))
+                .with_url(config.addr.host.clone())
+        })
         .await
     }
     fn alpn_protocol(&self) -> Option<&[u8]> {
         return self.get_ref().1.alpn_protocol();
     }
+    fn set_keep_alive(&self, idle: Duration) -> io::Result<()> {
+        self.get_ref().0.set_keep_alive(idle)
+    }
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        self.get_ref().0.remote_addr()
+    }
 }
 pub type BoxedStream = Box<dyn ConnectStream>;
@@ -0,0 +1,144 @@
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use log::debug;
+use tokio::{net::UdpSocket, time::timeout};
+
+use crate::{
+    stream::StreamUtils,
+    types::{
+        error::{NetError, NetResultStatus},
+        native::request::NetRequestNtp,
+        response::NetResponseNtp,
+    },
+};
+
+const DEFAULT_SERVERS: &[&str] = &["pool.ntp.org", "time.cloudflare.com", "time.google.com"];
+
+const NTP_PORT: u16 = 123;
+
+/// Wait per server before the next one is tried
+const SERVER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Seconds between the NTP era 0 (1900) and the unix epoch
+const UNIX_EPOCH_NTP: u64 = 2_208_988_800;
+
+/// SNTP (RFC 4330) client behind `NetRequestKind::Ntp`
+pub struct NtpClient;
+
+impl NtpClient {
+    /// First server that answers wins
+    pub async fn query(request: &NetRequestNtp) -> Result<NetResponseNtp, NetError> {
+        let servers: Vec<&str> = match request.servers.is_empty() {
+            true => DEFAULT_SERVERS.to_vec(),
+            false => request.servers.iter().map(String::as_str).collect(),
+        };
+        let mut last_error = None;
+        for server in servers {
+            let result = match timeout(SERVER_TIMEOUT, NtpClient::query_server(server)).await {
+                Ok(result) => result,
+                Err(_) => Err(NetError::new(NetResultStatus::RequestTimeout)
+                    .with_message("ntp server did not answer")
+                    .with_url(server)),
+            };
+            match result {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    debug!("ntp query to {} failed: {:?}", server, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| NetError::new(NetResultStatus::InternalError)))
+    }
+
+    async fn query_server(server: &str) -> Result<NetResponseNtp, NetError> {
+        let socket_error = |e: std::io::Error| {
+            NetError::new(NetResultStatus::SocketError)
+                .with_url(server)
+                .with_source(e)
+        };
+        let address = StreamUtils::resolve_server(server, NTP_PORT).await?[0];
+        let local: SocketAddr = match address {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+        let socket = UdpSocket::bind(local).await.map_err(socket_error)?;
+        socket.connect(address).await.map_err(socket_error)?;
+
+        // a random transmit timestamp, echoed back as the originate timestamp, keeps the
+        // local clock private (RFC 9109) and ties the answer to this request
+        let mut nonce = [0u8; 8];
+        let provider = rustls::crypto::ring::default_provider();
+        let originate = NtpClient::now();
+        if provider.secure_random.fill(&mut nonce).is_err() {
+            nonce = originate.to_be_bytes();
+        }
+        let mut request = [0u8; 48];
+        // leap indicator 0, version 4, mode 3 (client)
+        request[0] = 0x23;
+        request[40..].copy_from_slice(&nonce);
+        let sent = Instant::now();
+        socket.send(&request).await.map_err(socket_error)?;
+
+        let mut buffer = [0u8; 512];
+        let packet = loop {
+            let len = socket.recv(&mut buffer).await.map_err(socket_error)?;
+            let packet = &buffer[..len];
+            // ignore stray datagrams that do not answer this request
+            if len >= 48 && packet[0] & 0x07 == 4 && packet[24..32] == nonce {
+                break packet;
+            }
+        };
+        let destination = originate.wrapping_add(NtpClient::to_fixed(sent.elapsed()));
+        let invalid = |message: String| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(message)
+                .with_url(server)
+        };
+        let stratum = packet[1];
+        if stratum == 0 {
+            // kiss-o'-death, the reason is an ASCII code such as RATE or DENY
+            let code = String::from_utf8_lossy(&packet[12..16]).into_owned();
+            return Err(invalid(format!("ntp server refused the request: {}", code)));
+        }
+        if packet[0] >> 6 == 3 {
+            return Err(invalid("ntp server clock is not synchronized".to_string()));
+        }
+        let timestamp = |at: usize| u64::from_be_bytes(packet[at..at + 8].try_into().unwrap());
+        let (receive, transmit) = (timestamp(32), timestamp(40));
+        // differences wrap, so era rollovers cancel out
+        let diff = |a: u64, b: u64| i128::from(a.wrapping_sub(b) as i64);
+        let offset = (diff(receive, originate) + diff(transmit, destination)) / 2;
+        let delay = (diff(destination, originate) - diff(transmit, receive)).max(0);
+        Ok(NetResponseNtp::new(
+            address.to_string(),
+            stratum,
+            NtpClient::to_micros(offset) as i64,
+            NtpClient::to_micros(delay).min(u32::MAX.into()) as u32,
+        ))
+    }
+
+    /// Local wall clock as an NTP 32.32 fixed point timestamp
+    fn now() -> u64 {
+        let since_unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let seconds = since_unix.as_secs() + UNIX_EPOCH_NTP;
+        (seconds << 32).wrapping_add(NtpClient::to_fixed(Duration::new(
+            0,
+            since_unix.subsec_nanos(),
+        )))
+    }
+
+    fn to_fixed(duration: Duration) -> u64 {
+        let fraction = (u64::from(duration.subsec_nanos()) << 32) / 1_000_000_000;
+        (duration.as_secs() << 32).wrapping_add(fraction)
+    }
+
+    fn to_micros(fixed: i128) -> i128 {
+        (fixed * 1_000_000) >> 32
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use log::debug;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{
+    io::Interest,
+    net::UdpSocket,
+    time::{Instant, sleep_until, timeout_at},
+};
+
+use crate::types::{
+    error::{NetError, NetResultStatus},
+    native::request::NetRequestPing,
+    response::{NetPingMethod, NetResponsePing},
+};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// First port of the traceroute range, unlikely to have a listener
+const UDP_PROBE_PORT: u16 = 33434;
+
+const PAYLOAD: &[u8] = b"net_sdk ping probe";
+
+/// Reachability and latency probe behind `NetRequestKind::Ping`
+pub struct Pinger;
+
+impl Pinger {
+    pub async fn ping(request: &NetRequestPing) -> Result<NetResponsePing, NetError> {
+        if request.count == 0 {
+            return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("ping count must be at least 1"));
+        }
+        let interval = match request.interval_ms {
+            0 => DEFAULT_INTERVAL,
+            ms => Duration::from_millis(ms.into()),
+        };
+        let address = Pinger::resolve(&request.host).await?;
+        let socket_error = |e: io::Error| {
+            NetError::new(NetResultStatus::SocketError)
+                .with_url(address.to_string())
+                .with_source(e)
+        };
+        let (method, rtts) = match Pinger::icmp_socket(address) {
+            Ok(socket) => (
+                NetPingMethod::Icmp,
+                Pinger::probe_icmp(&socket, address, request.count, interval)
+                    .await
+                    .map_err(socket_error)?,
+            ),
+            Err(e) => {
+                debug!("icmp socket unavailable, probing with udp: {}", e);
+                (
+                    NetPingMethod::Udp,
+                    Pinger::probe_udp(address, request.count, interval)
+                        .await
+                        .map_err(socket_error)?,
+                )
+            }
+        };
+        Ok(NetResponsePing::new(
+            address.to_string(),
+            method,
+            request.count,
+            &rtts,
+        ))
+    }
+
+    async fn resolve(host: &str) -> Result<IpAddr, NetError> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(ip);
+        }
+        let mut addresses = tokio::net::lookup_host((host, 0)).await.map_err(|e| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("dns lookup failed")
+                .with_url(host)
+                .with_source(e)
+        })?;
+        addresses.next().map(|addr| addr.ip()).ok_or_else(|| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("host has no address")
+                .with_url(host)
+        })
+    }
+
+    /// Unprivileged `SOCK_DGRAM` ICMP socket, allowed on Linux/Android by
+    /// `net.ipv4.ping_group_range` and on Apple platforms
+    fn icmp_socket(address: IpAddr) -> io::Result<UdpSocket> {
+        let (domain, protocol) = match address {
+            IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4),
+            IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6),
+        };
+        let socket = Socket::new(domain, Type::DGRAM, Some(protocol))?;
+        socket.set_nonblocking(true)?;
+        UdpSocket::from_std(socket.into())
+    }
+
+    fn echo_request(address: IpAddr, sequence: u16) -> Vec<u8> {
+        let kind = match address {
+            IpAddr::V4(_) => 8,
+            IpAddr::V6(_) => 128,
+        };
+        // the kernel replaces the identifier of datagram ICMP sockets
+        let mut packet = vec![kind, 0, 0, 0, 0, 0];
+        packet.extend_from_slice(&sequence.to_be_bytes());
+        packet.extend_from_slice(PAYLOAD);
+        // ICMPv6 checksums cover a pseudo header and are filled in by the kernel
+        if address.is_ipv4() {
+            let checksum = Pinger::checksum(&packet);
+            packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+        }
+        packet
+    }
+
+    fn checksum(data: &[u8]) -> u16 {
+        let mut sum: u32 = data
+            .chunks(2)
+            .map(|pair| u32::from(u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])))
+            .sum();
+        while sum > 0xffff {
+            sum = (sum & 0xffff) + (sum >> 16);
+        }
+        !(sum as u16)
+    }
+
+    /// Sequence number of an echo reply; Apple platforms prepend the IPv4 header
+    fn echo_reply_sequence(address: IpAddr, packet: &[u8]) -> Option<u16> {
+        let packet = match packet.first() {
+            Some(first) if address.is_ipv4() && first >> 4 == 4 => {
+                packet.get(usize::from(first & 0x0f) * 4..)?
+            }
+            _ => packet,
+        };
+        let reply = match address {
+            IpAddr::V4(_) => 0,
+            IpAddr::V6(_) => 129,
+        };
+        if *packet.first()? != reply || packet.get(8..)? != PAYLOAD {
+            return None;
+        }
+        Some(u16::from_be_bytes([*packet.get(6)?, *packet.get(7)?]))
+    }
+
+    async fn probe_icmp(
+        socket: &UdpSocket,
+        address: IpAddr,
+        count: u16,
+        interval: Duration,
+    ) -> io::Result<Vec<u32>> {
+        let target = SocketAddr::new(address, 0);
+        let mut rtts = Vec::with_capacity(count.into());
+        let mut buffer = [0u8; 1500];
+        for sequence in 0..count {
+            let sent = Instant::now();
+            let deadline = sent + interval;
+            socket
+                .send_to(&Pinger::echo_request(address, sequence), target)
+                .await?;
+            loop {
+                match timeout_at(deadline, socket.recv_from(&mut buffer)).await {
+                    Err(_) => break,
+                    Ok(received) => {
+                        let (len, _) = received?;
+                        if Pinger::echo_reply_sequence(address, &buffer[..len]) == Some(sequence) {
+                            rtts.push(sent.elapsed().as_micros() as u32);
+                            break;
+                        }
+                    }
+                }
+            }
+            sleep_until(deadline).await;
+        }
+        Ok(rtts)
+    }
+
+    /// A closed port answers with ICMP port unreachable, which a connected UDP socket
+    /// reports as a refused connection
+    async fn probe_udp(address: IpAddr, count: u16, interval: Duration) -> io::Result<Vec<u32>> {
+        let local = match address {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        let socket = UdpSocket::bind((local, 0)).await?;
+        socket.connect((address, UDP_PROBE_PORT)).await?;
+        let mut rtts = Vec::with_capacity(count.into());
+        let mut buffer = [0u8; 64];
+        for _ in 0..count {
+            let sent = Instant::now();
+            let deadline = sent + interval;
+            // a late answer to an earlier probe is reported by the next send or receive
+            let _ = socket.try_recv(&mut buffer);
+            if let Err(e) = socket.send(PAYLOAD).await {
+                if !Pinger::is_unreachable(&e) {
+                    return Err(e);
+                }
+                socket.send(PAYLOAD).await?;
+            }
+            if Pinger::wait_udp_answer(&socket, deadline, &mut buffer).await? {
+                rtts.push(sent.elapsed().as_micros() as u32);
+            }
+            sleep_until(deadline).await;
+        }
+        Ok(rtts)
+    }
+
+    /// The refusal is a pending socket error, which sets error rather than read readiness
+    async fn wait_udp_answer(
+        socket: &UdpSocket,
+        deadline: Instant,
+        buffer: &mut [u8],
+    ) -> io::Result<bool> {
+        loop {
+            let ready = match timeout_at(
+                deadline,
+                socket.ready(Interest::READABLE | Interest::ERROR),
+            )
+            .await
+            {
+                Err(_) => return Ok(false),
+                Ok(ready) => ready?,
+            };
+            if ready.is_error() {
+                // clears the error readiness once nothing is pending
+                let error = socket.try_io(Interest::ERROR, || {
+                    socket
+                        .take_error()?
+                        .ok_or_else(|| io::ErrorKind::WouldBlock.into())
+                });
+                match error {
+                    Ok(e) if Pinger::is_unreachable(&e) => return Ok(true),
+                    Ok(e) => return Err(e),
+                    Err(e) if e.kind() != io::ErrorKind::WouldBlock => return Err(e),
+                    Err(_) => (),
+                }
+            }
+            if ready.is_readable() {
+                match socket.try_recv(buffer) {
+                    Ok(_) => return Ok(true),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => (),
+                    Err(e) if Pinger::is_unreachable(&e) => return Ok(true),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    fn is_unreachable(error: &io::Error) -> bool {
+        matches!(
+            error.kind(),
+            io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset
+        )
+    }
+}
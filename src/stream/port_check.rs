@@ -0,0 +1,121 @@
+use std::{
+    io,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use tokio::{net::TcpStream, time::timeout};
+
+#[cfg(feature = "tor")]
+use crate::stream::StreamUtils;
+use crate::types::{
+    config::NetMode,
+    error::{NetError, NetResultStatus},
+    native::request::NetRequestPortCheck,
+    response::{NetPortCheckFailure, NetResponsePortCheck},
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// TCP reachability probe behind `NetRequestKind::PortCheck`
+pub struct PortCheck;
+
+impl PortCheck {
+    /// A failed dial is a result, not an error; errors are reserved for requests that can not
+    /// be probed, e.g. Tor before `InitTor`
+    pub async fn check(request: &NetRequestPortCheck) -> Result<NetResponsePortCheck, NetError> {
+        let host = request.host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() || request.port == 0 {
+            return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("port check needs a host and a port"));
+        }
+        let wait = match request.timeout_ms {
+            0 => DEFAULT_TIMEOUT,
+            ms => Duration::from_millis(ms.into()),
+        };
+        let dial = match &request.mode {
+            NetMode::Clearnet => timeout(wait, PortCheck::dial(host, request.port)).await,
+            NetMode::Tor => timeout(wait, PortCheck::dial_tor(host, request.port)).await,
+            NetMode::Custom(_) => {
+                return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                    .with_message("custom streams can not be port checked"));
+            }
+            NetMode::Socks5(_) => {
+                return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                    .with_message("socks5 proxies can not be port checked"));
+            }
+        };
+        let (failure, latency) = match dial {
+            Ok(Ok(Ok(latency))) => (NetPortCheckFailure::None, latency),
+            Ok(Ok(Err(failure))) => (failure, Duration::ZERO),
+            Ok(Err(e)) => return Err(e),
+            Err(_) => (NetPortCheckFailure::Timeout, Duration::ZERO),
+        };
+        Ok(NetResponsePortCheck::new(
+            failure,
+            latency.as_micros().min(u32::MAX.into()) as u32,
+        ))
+    }
+
+    /// Connect latency, measured after name resolution; `Ok(Err(_))` is a failed dial
+    async fn dial(
+        host: &str,
+        port: u16,
+    ) -> Result<Result<Duration, NetPortCheckFailure>, NetError> {
+        let resolved: Vec<SocketAddr> = match tokio::net::lookup_host((host, port)).await {
+            Ok(resolved) => resolved.collect(),
+            Err(_) => return Ok(Err(NetPortCheckFailure::Dns)),
+        };
+        if resolved.is_empty() {
+            return Ok(Err(NetPortCheckFailure::Dns));
+        }
+        let started = Instant::now();
+        Ok(match TcpStream::connect(resolved.as_slice()).await {
+            Ok(_) => Ok(started.elapsed()),
+            Err(e) => Err(PortCheck::io_failure(&e)),
+        })
+    }
+
+    fn io_failure(error: &io::Error) -> NetPortCheckFailure {
+        match error.kind() {
+            io::ErrorKind::ConnectionRefused => NetPortCheckFailure::Refused,
+            io::ErrorKind::TimedOut => NetPortCheckFailure::Timeout,
+            io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::NetworkDown
+            | io::ErrorKind::AddrNotAvailable => NetPortCheckFailure::Unreachable,
+            _ => NetPortCheckFailure::Other,
+        }
+    }
+
+    #[cfg(feature = "tor")]
+    async fn dial_tor(
+        host: &str,
+        port: u16,
+    ) -> Result<Result<Duration, NetPortCheckFailure>, NetError> {
+        use arti_client::{ErrorKind, HasKind};
+
+        let client = StreamUtils::get_tor_client().await?;
+        let started = Instant::now();
+        Ok(match client.connect((host, port)).await {
+            Ok(_) => Ok(started.elapsed()),
+            Err(e) => Err(match e.kind() {
+                ErrorKind::RemoteConnectionRefused => NetPortCheckFailure::Refused,
+                ErrorKind::RemoteNetworkTimeout => NetPortCheckFailure::Timeout,
+                ErrorKind::RemoteHostNotFound | ErrorKind::RemoteHostResolutionFailed => {
+                    NetPortCheckFailure::Dns
+                }
+                ErrorKind::RemoteNetworkFailed => NetPortCheckFailure::Unreachable,
+                _ => NetPortCheckFailure::Tor,
+            }),
+        })
+    }
+
+    #[cfg(not(feature = "tor"))]
+    async fn dial_tor(
+        _host: &str,
+        _port: u16,
+    ) -> Result<Result<Duration, NetPortCheckFailure>, NetError> {
+        Err(NetError::feature_disabled("tor"))
+    }
+}
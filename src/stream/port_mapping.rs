@@ -0,0 +1,480 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use log::debug;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UdpSocket},
+    time::{Instant, timeout, timeout_at},
+};
+use url::Url;
+
+use crate::{
+    stream::NetworkWatcher,
+    types::{
+        error::{NetError, NetResultStatus},
+        native::request::NetRequestPortMapping,
+        response::{NetPortMappingMethod, NetPortMappingProtocol, NetResponsePortMapping},
+    },
+};
+
+const NAT_PMP_PORT: u16 = 5351;
+
+/// Attempts of the RFC 6886 retransmission, which starts at 250ms and doubles; the RFC
+/// allows 9, after 3 the gateway is taken to not speak NAT-PMP
+const NAT_PMP_ATTEMPTS: u32 = 3;
+
+const SSDP_GROUP: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250)), 1900);
+
+/// Wait for gateways to answer the search, a little above its `MX`
+const SSDP_WAIT: Duration = Duration::from_secs(3);
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+const IGD_DEVICES: &[&str] = &[
+    "urn:schemas-upnp-org:device:InternetGatewayDevice:2",
+    "urn:schemas-upnp-org:device:InternetGatewayDevice:1",
+];
+
+/// Services that manage mappings, in order of preference
+const IGD_SERVICES: &[&str] = &[
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// UPnP `OnlyPermanentLeasesSupported`
+const UPNP_PERMANENT_ONLY: u16 = 725;
+
+/// UPnP `NoSuchEntryInArray`
+const UPNP_NO_SUCH_ENTRY: u16 = 714;
+
+/// Gateway port mappings behind `NetRequestKind::PortMapping`.
+///
+/// NAT-PMP is asked first when the gateway address is known, UPnP IGD otherwise or when
+/// the gateway does not answer it.
+pub struct PortMapper;
+
+impl PortMapper {
+    pub async fn map(request: &NetRequestPortMapping) -> Result<NetResponsePortMapping, NetError> {
+        if request.internal_port == 0 {
+            return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("port mapping needs an internal port"));
+        }
+        let gateway = match &request.gateway {
+            Some(gateway) => Some(gateway.parse::<Ipv4Addr>().map_err(|e| {
+                NetError::new(NetResultStatus::InvalidRequestParameters)
+                    .with_message("gateway is not an IPv4 address")
+                    .with_url(gateway)
+                    .with_source(e)
+            })?),
+            None => PortMapper::default_gateway(),
+        };
+        if let Some(gateway) = gateway {
+            match PortMapper::nat_pmp(gateway, request).await {
+                Ok(mapping) => return Ok(mapping),
+                Err(e) => debug!("nat-pmp via {} failed: {:?}", gateway, e),
+            }
+        }
+        PortMapper::upnp(gateway, request).await
+    }
+
+    /// Gateway of the IPv4 default route. Only Linux exposes the routing table to apps;
+    /// Android 10+ and Apple platforms rely on UPnP discovery or an explicit gateway
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn default_gateway() -> Option<Ipv4Addr> {
+        let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+        routes.lines().skip(1).find_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let flags = u16::from_str_radix(fields.get(3)?, 16).ok()?;
+            // RTF_UP | RTF_GATEWAY on the 0.0.0.0 destination
+            if *fields.get(1)? != "00000000" || flags & 0x3 != 0x3 {
+                return None;
+            }
+            // the kernel prints the address in host byte order
+            let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+            Some(Ipv4Addr::from(gateway.to_ne_bytes()))
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn default_gateway() -> Option<Ipv4Addr> {
+        None
+    }
+
+    async fn nat_pmp(
+        gateway: Ipv4Addr,
+        request: &NetRequestPortMapping,
+    ) -> Result<NetResponsePortMapping, NetError> {
+        let address = SocketAddr::from((gateway, NAT_PMP_PORT));
+        let socket_error = |e: io::Error| {
+            NetError::new(NetResultStatus::SocketError)
+                .with_url(address.to_string())
+                .with_source(e)
+        };
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .await
+            .map_err(socket_error)?;
+        socket.connect(address).await.map_err(socket_error)?;
+
+        let external = PortMapper::nat_pmp_call(&socket, &[0, 0], 4).await?;
+        let external_address = Ipv4Addr::new(external[0], external[1], external[2], external[3]);
+        let opcode = match request.protocol {
+            NetPortMappingProtocol::Udp => 1,
+            NetPortMappingProtocol::Tcp => 2,
+        };
+        // a removal must not suggest an external port
+        let suggested = match (request.lifetime, request.external_port) {
+            (0, _) => 0,
+            (_, 0) => request.internal_port,
+            (_, port) => port,
+        };
+        let mut message = vec![0, opcode, 0, 0];
+        message.extend_from_slice(&request.internal_port.to_be_bytes());
+        message.extend_from_slice(&suggested.to_be_bytes());
+        message.extend_from_slice(&request.lifetime.to_be_bytes());
+        let mapped = PortMapper::nat_pmp_call(&socket, &message, 8).await?;
+        Ok(NetResponsePortMapping::new(
+            gateway.to_string(),
+            NetPortMappingMethod::NatPmp,
+            request.protocol,
+            request.internal_port,
+            u16::from_be_bytes([mapped[2], mapped[3]]),
+            external_address.to_string(),
+            u32::from_be_bytes([mapped[4], mapped[5], mapped[6], mapped[7]]),
+        ))
+    }
+
+    /// Answer to `message` after its 8 byte header, at least `len` bytes of it
+    async fn nat_pmp_call(
+        socket: &UdpSocket,
+        message: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, NetError> {
+        let socket_error =
+            |e: io::Error| NetError::new(NetResultStatus::SocketError).with_source(e);
+        let mut wait = Duration::from_millis(250);
+        let mut buffer = [0u8; 64];
+        for _ in 0..NAT_PMP_ATTEMPTS {
+            socket.send(message).await.map_err(socket_error)?;
+            let deadline = Instant::now() + wait;
+            wait *= 2;
+            while let Ok(received) = timeout_at(deadline, socket.recv(&mut buffer)).await {
+                let received = received.map_err(socket_error)?;
+                let answer = &buffer[..received];
+                if answer.len() < 8 + len || answer[0] != 0 || answer[1] != message[1] | 0x80 {
+                    continue;
+                }
+                let result = u16::from_be_bytes([answer[2], answer[3]]);
+                if result != 0 {
+                    let reason = match result {
+                        1 => "unsupported version",
+                        2 => "not authorized",
+                        3 => "network failure",
+                        4 => "out of resources",
+                        5 => "unsupported opcode",
+                        _ => "unknown result",
+                    };
+                    return Err(NetError::new(NetResultStatus::ConnectionError)
+                        .with_message(format!("nat-pmp {}: {}", result, reason)));
+                }
+                return Ok(answer[8..].to_vec());
+            }
+        }
+        Err(NetError::new(NetResultStatus::RequestTimeout)
+            .with_message("gateway did not answer nat-pmp"))
+    }
+
+    async fn upnp(
+        gateway: Option<Ipv4Addr>,
+        request: &NetRequestPortMapping,
+    ) -> Result<NetResponsePortMapping, NetError> {
+        let location = PortMapper::ssdp_search(gateway).await?;
+        let (_, description) = PortMapper::http(&location, "GET", "", "").await?;
+        let Some((service, control)) = PortMapper::control_url(&location, &description) else {
+            return Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message("gateway has no WAN connection service")
+                .with_url(location.as_str()));
+        };
+        let external_port = match request.external_port {
+            0 => request.internal_port,
+            port => port,
+        };
+        let protocol = match request.protocol {
+            NetPortMappingProtocol::Tcp => "TCP",
+            NetPortMappingProtocol::Udp => "UDP",
+        };
+        let entry = vec![
+            ("NewRemoteHost", String::new()),
+            ("NewExternalPort", external_port.to_string()),
+            ("NewProtocol", protocol.to_string()),
+        ];
+        let lifetime = match request.lifetime {
+            0 => {
+                let (status, answer) =
+                    PortMapper::soap(&control, service, "DeletePortMapping", &entry).await?;
+                if PortMapper::upnp_error(&answer) != Some(UPNP_NO_SUCH_ENTRY) {
+                    PortMapper::soap_result("DeletePortMapping", status, &answer)?;
+                }
+                0
+            }
+            lifetime => {
+                let Some(host) = control.host_str().and_then(|host| host.parse().ok()) else {
+                    return Err(NetError::new(NetResultStatus::ConnectionError)
+                        .with_message("gateway control url is not an IP address")
+                        .with_url(control.as_str()));
+                };
+                let port = control.port_or_known_default().unwrap_or(80);
+                let Some(internal_client) =
+                    NetworkWatcher::route_source(SocketAddr::new(host, port))
+                else {
+                    return Err(NetError::new(NetResultStatus::ConnectionError)
+                        .with_message("no route to the gateway")
+                        .with_url(control.as_str()));
+                };
+                let add = |lease: u32| {
+                    let mut arguments = entry.clone();
+                    arguments.extend([
+                        ("NewInternalPort", request.internal_port.to_string()),
+                        ("NewInternalClient", internal_client.to_string()),
+                        ("NewEnabled", "1".to_string()),
+                        ("NewPortMappingDescription", "net_sdk".to_string()),
+                        ("NewLeaseDuration", lease.to_string()),
+                    ]);
+                    arguments
+                };
+                let (mut status, mut answer) =
+                    PortMapper::soap(&control, service, "AddPortMapping", &add(lifetime)).await?;
+                let mut lifetime = lifetime;
+                // IGD:1 gateways may only accept permanent mappings
+                if status != 200 && PortMapper::upnp_error(&answer) == Some(UPNP_PERMANENT_ONLY) {
+                    lifetime = u32::MAX;
+                    (status, answer) =
+                        PortMapper::soap(&control, service, "AddPortMapping", &add(0)).await?;
+                }
+                PortMapper::soap_result("AddPortMapping", status, &answer)?;
+                lifetime
+            }
+        };
+        let external_address =
+            match PortMapper::soap(&control, service, "GetExternalIPAddress", &[]).await {
+                Ok((200, answer)) => PortMapper::xml_text(&answer, "NewExternalIPAddress")
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                _ => String::new(),
+            };
+        Ok(NetResponsePortMapping::new(
+            control.host_str().unwrap_or_default().to_string(),
+            NetPortMappingMethod::Upnp,
+            request.protocol,
+            request.internal_port,
+            external_port,
+            external_address,
+            lifetime,
+        ))
+    }
+
+    /// Description url of the first gateway that answers, `gateway` only when set
+    async fn ssdp_search(gateway: Option<Ipv4Addr>) -> Result<Url, NetError> {
+        let socket_error = |e: io::Error| {
+            NetError::new(NetResultStatus::SocketError)
+                .with_url(SSDP_GROUP.to_string())
+                .with_source(e)
+        };
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .await
+            .map_err(socket_error)?;
+        for device in IGD_DEVICES {
+            let search = format!(
+                "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+                SSDP_GROUP, device
+            );
+            socket
+                .send_to(search.as_bytes(), SSDP_GROUP)
+                .await
+                .map_err(socket_error)?;
+        }
+        let deadline = Instant::now() + SSDP_WAIT;
+        let mut buffer = [0u8; 2048];
+        while let Ok(received) = timeout_at(deadline, socket.recv_from(&mut buffer)).await {
+            let (len, from) = received.map_err(socket_error)?;
+            if gateway.is_some_and(|gateway| from.ip() != IpAddr::V4(gateway)) {
+                continue;
+            }
+            let answer = String::from_utf8_lossy(&buffer[..len]);
+            let location = PortMapper::header(&answer, "location")
+                .and_then(|location| Url::parse(location).ok());
+            if let Some(location) = location
+                && location.scheme() == "http"
+            {
+                return Ok(location);
+            }
+        }
+        Err(NetError::new(NetResultStatus::ConnectionError)
+            .with_message("no NAT-PMP or UPnP gateway answered"))
+    }
+
+    /// The first of `IGD_SERVICES` the description lists, with its absolute control url
+    fn control_url(location: &Url, description: &str) -> Option<(&'static str, Url)> {
+        let base = PortMapper::xml_text(description, "URLBase")
+            .and_then(|base| Url::parse(base.trim()).ok())
+            .unwrap_or_else(|| location.clone());
+        IGD_SERVICES.iter().find_map(|service| {
+            let block = description.split("<service>").skip(1).find(|block| {
+                PortMapper::xml_text(block, "serviceType").map(str::trim) == Some(*service)
+            })?;
+            let control = PortMapper::xml_text(block, "controlURL")?;
+            Some((*service, base.join(control.trim()).ok()?))
+        })
+    }
+
+    async fn soap(
+        control: &Url,
+        service: &str,
+        action: &str,
+        arguments: &[(&str, String)],
+    ) -> Result<(u16, String), NetError> {
+        let arguments: String = arguments
+            .iter()
+            .map(|(name, value)| format!("<{0}>{1}</{0}>", name, value))
+            .collect();
+        let body = format!(
+            "<?xml version=\"1.0\"?>\r\n<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body>\
+             <u:{0} xmlns:u=\"{1}\">{2}</u:{0}></s:Body></s:Envelope>",
+            action, service, arguments
+        );
+        let headers = format!(
+            "Content-Type: text/xml; charset=\"utf-8\"\r\nSOAPAction: \"{}#{}\"\r\n",
+            service, action
+        );
+        PortMapper::http(control, "POST", &headers, &body).await
+    }
+
+    fn soap_result(action: &str, status: u16, answer: &str) -> Result<(), NetError> {
+        if status == 200 {
+            return Ok(());
+        }
+        let reason = PortMapper::xml_text(answer, "errorDescription").unwrap_or_default();
+        Err(
+            NetError::new(NetResultStatus::ConnectionError).with_message(format!(
+                "upnp {} failed: {} {}",
+                action,
+                PortMapper::upnp_error(answer).unwrap_or(status),
+                reason.trim()
+            )),
+        )
+    }
+
+    fn upnp_error(answer: &str) -> Option<u16> {
+        PortMapper::xml_text(answer, "errorCode")?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Text of the first `<tag>` element; gateway documents are small and flat enough that
+    /// no XML parser is needed
+    fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+        let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+        let len = xml[start..].find(&format!("</{}>", tag))?;
+        Some(&xml[start..start + len])
+    }
+
+    fn header<'a>(message: &'a str, name: &str) -> Option<&'a str> {
+        message.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    /// One `Connection: close` exchange; gateways serve plain HTTP/1.1 on the local network
+    async fn http(
+        url: &Url,
+        method: &str,
+        headers: &str,
+        body: &str,
+    ) -> Result<(u16, String), NetError> {
+        let invalid = |message: &str| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(message.to_string())
+                .with_url(url.as_str())
+        };
+        let host = url
+            .host_str()
+            .ok_or_else(|| invalid("gateway url has no host"))?;
+        let port = url.port_or_known_default().unwrap_or(80);
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}:{}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            host,
+            port,
+            headers,
+            body.len(),
+            body
+        );
+        let exchange = async {
+            let mut stream = TcpStream::connect((host, port)).await?;
+            stream.write_all(request.as_bytes()).await?;
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await?;
+            Ok::<_, io::Error>(response)
+        };
+        let response = match timeout(HTTP_TIMEOUT, exchange).await {
+            Ok(response) => response.map_err(|e| {
+                NetError::new(NetResultStatus::SocketError)
+                    .with_url(url.as_str())
+                    .with_source(e)
+            })?,
+            Err(_) => {
+                return Err(NetError::new(NetResultStatus::RequestTimeout)
+                    .with_message("gateway did not answer")
+                    .with_url(url.as_str()));
+            }
+        };
+        let end = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(|| invalid("invalid http answer from the gateway"))?;
+        let head = String::from_utf8_lossy(&response[..end]);
+        let status = head
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| invalid("invalid http answer from the gateway"))?;
+        let body = &response[end + 4..];
+        let body = match PortMapper::header(&head, "transfer-encoding") {
+            Some(encoding) if encoding.eq_ignore_ascii_case("chunked") => PortMapper::dechunk(body),
+            _ => body.to_vec(),
+        };
+        Ok((status, String::from_utf8_lossy(&body).into_owned()))
+    }
+
+    fn dechunk(mut body: &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::new();
+        while let Some(end) = body.windows(2).position(|window| window == b"\r\n") {
+            let size = std::str::from_utf8(&body[..end]).ok().and_then(|line| {
+                let size = line.split(';').next()?.trim();
+                usize::from_str_radix(size, 16).ok()
+            });
+            let Some(chunk) = size
+                .filter(|size| *size > 0)
+                .and_then(|size| body.get(end + 2..end + 2 + size))
+            else {
+                break;
+            };
+            decoded.extend_from_slice(chunk);
+            body = body.get(end + 4 + chunk.len()..).unwrap_or_default();
+        }
+        decoded
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::TcpStream,
+};
+
+use crate::{
+    stream::{ConnectStream, StreamUtils},
+    types::{
+        config::{NetConfig, NetMode, NetProxySocks5},
+        error::{NetError, NetResultStatus},
+    },
+};
+
+const VERSION: u8 = 5;
+const NO_AUTH: u8 = 0;
+const USERNAME_PASSWORD: u8 = 2;
+const NO_ACCEPTABLE_METHOD: u8 = 0xff;
+const CONNECT: u8 = 1;
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+/// Connection through the SOCKS5 proxy of `NetMode::Socks5` (RFC 1928), with username and
+/// password auth (RFC 1929) when the proxy has credentials. Host names go to the proxy
+/// unresolved, like Tor it resolves them itself.
+#[derive(Debug)]
+pub struct Socks5Stream(TcpStream);
+
+impl Socks5Stream {
+    /// Negotiates auth and asks the proxy to connect to `host:port`
+    async fn handshake(
+        stream: &mut TcpStream,
+        proxy: &NetProxySocks5,
+        host: &str,
+        port: u16,
+    ) -> io::Result<()> {
+        let credentials = proxy
+            .username
+            .as_deref()
+            .map(|username| (username, proxy.password.as_deref().unwrap_or_default()));
+        let greeting: &[u8] = match credentials {
+            Some(_) => &[VERSION, 2, NO_AUTH, USERNAME_PASSWORD],
+            None => &[VERSION, 1, NO_AUTH],
+        };
+        stream.write_all(greeting).await?;
+        let mut choice = [0u8; 2];
+        stream.read_exact(&mut choice).await?;
+        match (choice, credentials) {
+            ([VERSION, NO_AUTH], _) => {}
+            ([VERSION, USERNAME_PASSWORD], Some((username, password))) => {
+                Socks5Stream::authenticate(stream, username, password).await?
+            }
+            ([VERSION, NO_ACCEPTABLE_METHOD], _) => {
+                return Err(Socks5Stream::error(
+                    "proxy accepts none of the auth methods offered",
+                ));
+            }
+            _ => return Err(Socks5Stream::error("unexpected socks5 method selection")),
+        }
+
+        let mut request = vec![VERSION, CONNECT, 0];
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                request.push(ATYP_IPV4);
+                request.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                request.push(ATYP_IPV6);
+                request.extend_from_slice(&ip.octets());
+            }
+            Err(_) => {
+                let name = u8::try_from(host.len())
+                    .map_err(|_| Socks5Stream::error("host name too long for socks5"))?;
+                request.extend_from_slice(&[ATYP_DOMAIN, name]);
+                request.extend_from_slice(host.as_bytes());
+            }
+        }
+        request.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&request).await?;
+
+        let mut reply = [0u8; 4];
+        stream.read_exact(&mut reply).await?;
+        if reply[0] != VERSION {
+            return Err(Socks5Stream::error("unexpected socks5 reply"));
+        }
+        if reply[1] != 0 {
+            return Err(Socks5Stream::error(Socks5Stream::reply_message(reply[1])));
+        }
+        // the address the proxy bound is of no use to the caller
+        let bound = match reply[3] {
+            ATYP_IPV4 => 4,
+            ATYP_IPV6 => 16,
+            ATYP_DOMAIN => stream.read_u8().await? as usize,
+            _ => return Err(Socks5Stream::error("unexpected socks5 address type")),
+        };
+        let mut bound_addr = vec![0u8; bound + 2];
+        stream.read_exact(&mut bound_addr).await?;
+        Ok(())
+    }
+
+    async fn authenticate(
+        stream: &mut TcpStream,
+        username: &str,
+        password: &str,
+    ) -> io::Result<()> {
+        let (Ok(username_len), Ok(password_len)) =
+            (u8::try_from(username.len()), u8::try_from(password.len()))
+        else {
+            return Err(Socks5Stream::error(
+                "socks5 credentials are limited to 255 bytes",
+            ));
+        };
+        let mut request = vec![1, username_len];
+        request.extend_from_slice(username.as_bytes());
+        request.push(password_len);
+        request.extend_from_slice(password.as_bytes());
+        stream.write_all(&request).await?;
+        let mut status = [0u8; 2];
+        stream.read_exact(&mut status).await?;
+        match status[1] {
+            0 => Ok(()),
+            _ => Err(Socks5Stream::error("proxy rejected the socks5 credentials")),
+        }
+    }
+
+    fn reply_message(reply: u8) -> &'static str {
+        match reply {
+            1 => "socks5 proxy failure",
+            2 => "connection not allowed by the socks5 proxy",
+            3 => "network unreachable from the socks5 proxy",
+            4 => "host unreachable from the socks5 proxy",
+            5 => "connection refused through the socks5 proxy",
+            6 => "ttl expired through the socks5 proxy",
+            7 => "command not supported by the socks5 proxy",
+            8 => "address type not supported by the socks5 proxy",
+            _ => "socks5 connect failed",
+        }
+    }
+
+    fn error(message: &'static str) -> io::Error {
+        io::Error::other(message)
+    }
+}
+
+#[async_trait::async_trait]
+impl ConnectStream for Socks5Stream {
+    async fn connect(config: &NetConfig) -> Result<Self, NetError> {
+        let NetMode::Socks5(proxy) = &config.mode else {
+            return Err(NetError::new(NetResultStatus::InvalidConfigParameters)
+                .with_message("socks5 stream requires a socks5 mode"));
+        };
+        StreamUtils::require_tcp(&config.addr)?;
+        // names are resolved by the proxy, only literals can be checked here
+        config.host_policy.check_address(&config.addr)?;
+        let connect = async {
+            let mut stream = TcpStream::connect((proxy.host.as_str(), proxy.port))
+                .await
+                .map_err(|e| {
+                    NetError::new(NetResultStatus::ConnectionError)
+                        .with_message("socks5 proxy connect failed")
+                        .with_url(format!("{}:{}", proxy.host, proxy.port))
+                        .with_source(e)
+                })?;
+            Socks5Stream::handshake(&mut stream, proxy, &config.addr.host, config.addr.port)
+                .await
+                .map_err(|e| {
+                    NetError::new(NetResultStatus::ConnectionError)
+                        .with_message("socks5 handshake failed")
+                        .with_url(config.addr.authority())
+                        .with_source(e)
+                })?;
+            Ok(Socks5Stream(stream))
+        };
+        // the proxy's connect to the host is part of the handshake
+        StreamUtils::within(config.timeouts.connect(), connect, |limit| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("socks5 connect timed out after {:?}", limit))
+                .with_url(config.addr.authority())
+        })
+        .await
+    }
+
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn set_keep_alive(&self, idle: Duration) -> io::Result<()> {
+        // probes the connection to the proxy, the only one this end has
+        self.0.set_keep_alive(idle)
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        // the proxy, the host behind it is never seen
+        self.0.peer_addr().ok()
+    }
+}
+
+impl AsyncRead for Socks5Stream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Socks5Stream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
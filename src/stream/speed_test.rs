@@ -0,0 +1,303 @@
+use std::{str::FromStr, time::Duration};
+
+#[cfg(feature = "tor")]
+use arti_client::DataStream;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request, Response, Uri, body::Incoming};
+use tokio::{
+    net::TcpStream,
+    time::{Instant, timeout_at},
+};
+use tokio_rustls::client::TlsStream;
+
+use crate::{
+    client::http::native::{AutoSendRequest, Connect, SendRequestExt},
+    stream::{CustomStream, Socks5Stream},
+    types::{
+        builder::NetConfigBuilder,
+        config::{NetConfig, NetHttpProtocol, NetMode, NetProtocol},
+        error::{NetError, NetResultStatus},
+        native::request::NetRequestSpeedTest,
+        response::{NetSpeedTestPhase, NetSpeedTestProgress, NetSpeedTestResult},
+    },
+};
+
+const DEFAULT_DURATION: Duration = Duration::from_secs(10);
+
+/// Least time between two progress events of a phase
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Body of one upload request. Only completed requests are counted, so it is kept small
+/// enough for slow links to finish several within a test
+const UPLOAD_CHUNK: usize = 256 * 1024;
+
+/// Throughput and latency measurement behind `NetRequestKind::SpeedTest`.
+///
+/// Each direction runs on its own connection, made before the timing starts, so the
+/// handshakes are not part of the measurement.
+pub struct SpeedTest;
+
+impl SpeedTest {
+    /// Calls `on_progress` while a phase runs and once when it ends
+    pub async fn run(
+        request: &NetRequestSpeedTest,
+        mut on_progress: impl FnMut(NetSpeedTestProgress),
+    ) -> Result<NetSpeedTestResult, NetError> {
+        if request.download_url.is_none() && request.upload_url.is_none() {
+            return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("speed test needs a download or an upload url"));
+        }
+        let duration = match request.duration_ms {
+            0 => DEFAULT_DURATION,
+            ms => Duration::from_millis(ms.into()),
+        };
+        let mut download = match &request.download_url {
+            Some(url) => Some(SpeedTestConnection::connect(url, &request.mode).await?),
+            None => None,
+        };
+        let mut upload = match &request.upload_url {
+            Some(url) => Some(SpeedTestConnection::connect(url, &request.mode).await?),
+            None => None,
+        };
+        let mut result = NetSpeedTestResult::default();
+        if let Some(connection) = download.as_mut().or(upload.as_mut()) {
+            let rtts = connection.sample_rtts(request.rtt_samples).await?;
+            result.set_rtts(&rtts);
+        }
+        if let Some(connection) = download.as_mut() {
+            connection.reopen_if_closed().await?;
+            let progress = connection.download(duration, &mut on_progress).await?;
+            result.set_phase(&progress);
+        }
+        if let Some(connection) = upload.as_mut() {
+            connection.reopen_if_closed().await?;
+            let progress = connection.upload(duration, &mut on_progress).await?;
+            result.set_phase(&progress);
+        }
+        Ok(result)
+    }
+
+    fn elapsed_ms(started: Instant, at: Instant) -> u32 {
+        (at - started).as_millis().min(u32::MAX.into()) as u32
+    }
+}
+
+struct SpeedTestConnection {
+    sender: Box<dyn SendRequestExt>,
+    config: NetConfig,
+}
+
+impl SpeedTestConnection {
+    async fn connect(url: &str, mode: &NetMode) -> Result<Self, NetError> {
+        let config = NetConfigBuilder::new(url)
+            .protocol(NetProtocol::Http)
+            .mode(mode.clone())
+            .build_config()?;
+        let sender = match (config.addr.is_tls, &config.mode) {
+            #[cfg(feature = "tor")]
+            (true, NetMode::Tor) => {
+                AutoSendRequest::connect::<TlsStream<DataStream>>(&config).await?
+            }
+            (true, NetMode::Clearnet) => {
+                AutoSendRequest::connect::<TlsStream<TcpStream>>(&config).await?
+            }
+            #[cfg(feature = "tor")]
+            (false, NetMode::Tor) => AutoSendRequest::connect::<DataStream>(&config).await?,
+            (false, NetMode::Clearnet) => AutoSendRequest::connect::<TcpStream>(&config).await?,
+            (true, NetMode::Custom(_)) => {
+                AutoSendRequest::connect::<TlsStream<CustomStream>>(&config).await?
+            }
+            (false, NetMode::Custom(_)) => {
+                AutoSendRequest::connect::<CustomStream>(&config).await?
+            }
+            (true, NetMode::Socks5(_)) => {
+                AutoSendRequest::connect::<TlsStream<Socks5Stream>>(&config).await?
+            }
+            (false, NetMode::Socks5(_)) => {
+                AutoSendRequest::connect::<Socks5Stream>(&config).await?
+            }
+            #[cfg(not(feature = "tor"))]
+            (_, NetMode::Tor) => return Err(NetError::feature_disabled("tor")),
+        };
+        Ok(Self {
+            sender: Box::new(sender),
+            config,
+        })
+    }
+
+    /// Connects again when the server closed the connection while it waited for its phase,
+    /// as servers do with idle ones after a few seconds
+    async fn reopen_if_closed(&mut self) -> Result<(), NetError> {
+        if self.sender.is_closed() {
+            let reopened =
+                SpeedTestConnection::connect(&self.config.addr.url, &self.config.mode).await?;
+            *self = reopened;
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, method: Method, body: Bytes) -> Result<Response<Incoming>, NetError> {
+        let url = self.config.addr.url.as_str();
+        let uri = Uri::from_str(url).map_err(|e| {
+            NetError::new(NetResultStatus::InvalidUrl)
+                .with_url(url)
+                .with_source(e)
+        })?;
+        // caches in between would measure themselves instead of the server
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(http::header::CACHE_CONTROL, "no-store");
+        if self.sender.protocol() == NetHttpProtocol::Http1 {
+            builder = builder.header(http::header::HOST, self.config.addr.host.as_str());
+        }
+        if let Some(authorization) = &self.config.addr.authorization {
+            builder = builder.header(http::header::AUTHORIZATION, authorization.as_str());
+        }
+        if !body.is_empty() {
+            builder = builder.header(http::header::CONTENT_TYPE, "application/octet-stream");
+        }
+        let request = builder.body(Full::new(body)).map_err(|e| {
+            NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("invalid http request")
+                .with_url(url)
+                .with_source(e)
+        })?;
+        let failed = |e: hyper::Error| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("speed test request failed")
+                .with_url(url)
+                .with_source(e)
+        };
+        self.sender.ready().await.map_err(failed)?;
+        let response = self.sender.send(request).await.map_err(failed)?;
+        if !response.status().is_success() {
+            return Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("speed test server answered {}", response.status()))
+                .with_url(url));
+        }
+        Ok(response)
+    }
+
+    fn body_error(&self, e: hyper::Error) -> NetError {
+        NetError::new(NetResultStatus::ConnectionError)
+            .with_message("speed test transfer failed")
+            .with_url(self.config.addr.url.as_str())
+            .with_source(e)
+    }
+
+    /// Time to the response headers of `HEAD` requests, in microseconds
+    async fn sample_rtts(&mut self, samples: u8) -> Result<Vec<u32>, NetError> {
+        let mut rtts = Vec::with_capacity(samples.into());
+        for _ in 0..samples {
+            let started = Instant::now();
+            let response = self.send(Method::HEAD, Bytes::new()).await?;
+            rtts.push(started.elapsed().as_micros().min(u32::MAX.into()) as u32);
+            response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|e| self.body_error(e))?;
+        }
+        Ok(rtts)
+    }
+
+    /// Repeats the download until `duration` has passed; a response cut at the end counts
+    /// with the bytes received so far
+    async fn download(
+        &mut self,
+        duration: Duration,
+        on_progress: &mut impl FnMut(NetSpeedTestProgress),
+    ) -> Result<NetSpeedTestProgress, NetError> {
+        let started = Instant::now();
+        let deadline = started + duration;
+        let mut next_progress = started + PROGRESS_INTERVAL;
+        let mut bytes = 0u64;
+        'requests: while Instant::now() < deadline {
+            let response = match timeout_at(deadline, self.send(Method::GET, Bytes::new())).await {
+                Ok(response) => response?,
+                Err(_) => break,
+            };
+            let mut body = response.into_body();
+            let mut received = 0u64;
+            loop {
+                let frame = match timeout_at(deadline, body.frame()).await {
+                    Err(_) => break 'requests,
+                    Ok(None) => break,
+                    Ok(Some(frame)) => frame.map_err(|e| self.body_error(e))?,
+                };
+                if let Some(data) = frame.data_ref() {
+                    received += data.len() as u64;
+                }
+                let now = Instant::now();
+                if now >= next_progress {
+                    next_progress = now + PROGRESS_INTERVAL;
+                    on_progress(NetSpeedTestProgress::new(
+                        NetSpeedTestPhase::Download,
+                        bytes + received,
+                        SpeedTest::elapsed_ms(started, now),
+                    ));
+                }
+            }
+            bytes += received;
+            // nothing more to measure on an empty resource
+            if received == 0 {
+                break;
+            }
+        }
+        let progress = NetSpeedTestProgress::new(
+            NetSpeedTestPhase::Download,
+            bytes,
+            SpeedTest::elapsed_ms(started, Instant::now().min(deadline)),
+        );
+        on_progress(progress.clone());
+        Ok(progress)
+    }
+
+    /// Posts `UPLOAD_CHUNK` sized bodies until `duration` has passed; the rate is taken over
+    /// the completed requests, the one cut at the end is not counted
+    async fn upload(
+        &mut self,
+        duration: Duration,
+        on_progress: &mut impl FnMut(NetSpeedTestProgress),
+    ) -> Result<NetSpeedTestProgress, NetError> {
+        let chunk = Bytes::from(vec![0u8; UPLOAD_CHUNK]);
+        let started = Instant::now();
+        let deadline = started + duration;
+        let mut next_progress = started + PROGRESS_INTERVAL;
+        let mut completed = started;
+        let mut bytes = 0u64;
+        while Instant::now() < deadline {
+            let exchange = async {
+                let response = self.send(Method::POST, chunk.clone()).await?;
+                response
+                    .into_body()
+                    .collect()
+                    .await
+                    .map_err(|e| self.body_error(e))
+            };
+            match timeout_at(deadline, exchange).await {
+                Ok(answer) => answer?,
+                Err(_) => break,
+            };
+            completed = Instant::now();
+            bytes += UPLOAD_CHUNK as u64;
+            if completed >= next_progress {
+                next_progress = completed + PROGRESS_INTERVAL;
+                on_progress(NetSpeedTestProgress::new(
+                    NetSpeedTestPhase::Upload,
+                    bytes,
+                    SpeedTest::elapsed_ms(started, completed),
+                ));
+            }
+        }
+        let progress = NetSpeedTestProgress::new(
+            NetSpeedTestPhase::Upload,
+            bytes,
+            SpeedTest::elapsed_ms(started, completed),
+        );
+        on_progress(progress.clone());
+        Ok(progress)
+    }
+}
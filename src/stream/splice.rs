@@ -0,0 +1,64 @@
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    client::native::IStreamClient,
+    types::{error::NetError, response::NetResponseSplice},
+    utils::subscribers::NetSubscription,
+};
+
+type Incoming = NetSubscription<Result<Option<Vec<u8>>, NetError>>;
+
+/// Byte pipe between two stream clients behind `NetRequestKind::Splice`.
+///
+/// Data is read from each side's incoming feed and written to the other as it arrives, so
+/// it never leaves Rust. Message framing is not applied; the bytes pass as they were read.
+pub struct StreamSplice;
+
+impl StreamSplice {
+    /// Runs until either side closes or fails, or `stop` completes, then closes both
+    pub async fn run(
+        source: &dyn IStreamClient,
+        target: &dyn IStreamClient,
+        stop: impl Future<Output = ()>,
+    ) -> Result<NetResponseSplice, NetError> {
+        // both feeds are open before anything is forwarded, so no early data is missed
+        let from_source = source.subscribe().await?;
+        let from_target = match target.subscribe().await {
+            Ok(incoming) => incoming,
+            Err(e) => {
+                source.close().await;
+                return Err(e);
+            }
+        };
+        let mut source_bytes = 0u64;
+        let mut target_bytes = 0u64;
+        let result = tokio::select! {
+            result = StreamSplice::forward(from_source, target, &mut source_bytes) => result,
+            result = StreamSplice::forward(from_target, source, &mut target_bytes) => result,
+            _ = stop => Ok(()),
+        };
+        source.close().await;
+        target.close().await;
+        result.map(|_| NetResponseSplice::new(source_bytes, target_bytes))
+    }
+
+    /// Ends with `Ok` when the reading side closes
+    async fn forward(
+        mut incoming: Incoming,
+        to: &dyn IStreamClient,
+        bytes: &mut u64,
+    ) -> Result<(), NetError> {
+        loop {
+            match incoming.recv().await {
+                Ok(Ok(Some(data))) => {
+                    to.send(&data).await?;
+                    *bytes += data.len() as u64;
+                }
+                Ok(Ok(None)) | Err(RecvError::Closed) => return Ok(()),
+                Ok(Err(e)) => return Err(e),
+                // skipping would corrupt the stream passing through
+                Err(RecvError::Lagged(lost)) => return Err(NetError::stream_overflow(lost)),
+            }
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use log::debug;
+use tokio::{
+    net::UdpSocket,
+    time::{Instant, timeout_at},
+};
+
+use crate::{
+    stream::StreamUtils,
+    types::{
+        error::{NetError, NetResultStatus},
+        native::request::NetRequestStun,
+        response::{NetResponseStun, NetStunMapping},
+    },
+};
+
+const DEFAULT_SERVERS: &[&str] = &["stun.l.google.com:19302", "stun.cloudflare.com:3478"];
+
+const STUN_PORT: u16 = 3478;
+
+const MAGIC_COOKIE: u32 = 0x2112_a442;
+
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const BINDING_ERROR: u16 = 0x0111;
+
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_ERROR_CODE: u16 = 0x0009;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+/// Wait per server, requests are retransmitted within it
+const SERVER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Initial retransmission timeout, doubled after every attempt (RFC 5389 section 7.2.1)
+const INITIAL_RTO: Duration = Duration::from_millis(500);
+
+/// STUN (RFC 5389) binding client behind `NetRequestKind::Stun`.
+///
+/// Every server is asked from the same local socket, so the mappings show how the NAT
+/// translates it.
+pub struct StunClient;
+
+impl StunClient {
+    /// Fails only when no server answered
+    pub async fn bind(request: &NetRequestStun) -> Result<NetResponseStun, NetError> {
+        let servers: Vec<&str> = match request.servers.is_empty() {
+            true => DEFAULT_SERVERS.to_vec(),
+            false => request.servers.iter().map(String::as_str).collect(),
+        };
+        let mut socket: Option<(UdpSocket, SocketAddr)> = None;
+        let mut mappings = Vec::new();
+        let mut last_error = None;
+        for server in servers {
+            match StunClient::bind_server(&mut socket, server).await {
+                Ok(mapping) => mappings.push(mapping),
+                Err(e) => {
+                    debug!("stun binding via {} failed: {:?}", server, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match socket {
+            Some((_, local)) if !mappings.is_empty() => Ok(NetResponseStun::new(
+                local.ip().to_string(),
+                local.port(),
+                mappings,
+            )),
+            _ => Err(last_error.unwrap_or_else(|| NetError::new(NetResultStatus::InternalError))),
+        }
+    }
+
+    /// The socket is bound on the first server, in the address family that server resolved to.
+    /// Its local address is the interface address the route to that server uses.
+    async fn bind_server(
+        socket: &mut Option<(UdpSocket, SocketAddr)>,
+        server: &str,
+    ) -> Result<NetStunMapping, NetError> {
+        let socket_error = |e: std::io::Error| {
+            NetError::new(NetResultStatus::SocketError)
+                .with_url(server)
+                .with_source(e)
+        };
+        let resolved = StreamUtils::resolve_server(server, STUN_PORT).await?;
+        let (socket, local) = match socket {
+            Some((socket, local)) => (&*socket, *local),
+            None => {
+                let unspecified: IpAddr = match resolved[0] {
+                    SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+                    SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+                };
+                let bound = UdpSocket::bind((unspecified, 0))
+                    .await
+                    .map_err(socket_error)?;
+                // connecting a UDP socket sends nothing, it only picks the source address
+                let route = UdpSocket::bind((unspecified, 0))
+                    .await
+                    .map_err(socket_error)?;
+                route.connect(resolved[0]).await.map_err(socket_error)?;
+                let ip = route.local_addr().map_err(socket_error)?.ip();
+                let port = bound.local_addr().map_err(socket_error)?.port();
+                let (socket, local) = socket.insert((bound, SocketAddr::new(ip, port)));
+                (&*socket, *local)
+            }
+        };
+        let Some(address) = resolved
+            .into_iter()
+            .find(|address| address.is_ipv4() == local.is_ipv4())
+        else {
+            return Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!(
+                    "no {} address",
+                    if local.is_ipv4() { "IPv4" } else { "IPv6" }
+                ))
+                .with_url(server));
+        };
+
+        let mut transaction = [0u8; 12];
+        let provider = rustls::crypto::ring::default_provider();
+        if provider.secure_random.fill(&mut transaction).is_err() {
+            return Err(NetError::new(NetResultStatus::InternalError)
+                .with_message("no random source for the transaction id"));
+        }
+        let mut request = Vec::with_capacity(20);
+        request.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+        request.extend_from_slice(&0u16.to_be_bytes());
+        request.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        request.extend_from_slice(&transaction);
+
+        let deadline = Instant::now() + SERVER_TIMEOUT;
+        let mut rto = INITIAL_RTO;
+        let mut buffer = [0u8; 1500];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(NetError::new(NetResultStatus::RequestTimeout)
+                    .with_message("stun server did not answer")
+                    .with_url(server));
+            }
+            socket
+                .send_to(&request, address)
+                .await
+                .map_err(socket_error)?;
+            let retransmit = deadline.min(now + rto);
+            rto *= 2;
+            // stray datagrams, e.g. late answers to an earlier server, are skipped
+            while let Ok(received) = timeout_at(retransmit, socket.recv_from(&mut buffer)).await {
+                let (len, from) = received.map_err(socket_error)?;
+                if from != address {
+                    continue;
+                }
+                match StunClient::parse(&buffer[..len], &transaction) {
+                    None => continue,
+                    Some(Ok(mapped)) => {
+                        return Ok(NetStunMapping::new(
+                            address.to_string(),
+                            mapped.ip().to_string(),
+                            mapped.port(),
+                        ));
+                    }
+                    Some(Err(message)) => {
+                        return Err(NetError::new(NetResultStatus::ConnectionError)
+                            .with_message(message)
+                            .with_url(server));
+                    }
+                }
+            }
+        }
+    }
+
+    /// `None` unless `message` answers `transaction`; an error for a rejected request
+    fn parse(message: &[u8], transaction: &[u8; 12]) -> Option<Result<SocketAddr, String>> {
+        if message.len() < 20
+            || message[4..8] != MAGIC_COOKIE.to_be_bytes()
+            || message[8..20] != *transaction
+        {
+            return None;
+        }
+        let kind = u16::from_be_bytes([message[0], message[1]]);
+        let len = usize::from(u16::from_be_bytes([message[2], message[3]]));
+        let mut attributes = message.get(20..20 + len)?;
+        let mut mapped = None;
+        while attributes.len() >= 4 {
+            let attribute = u16::from_be_bytes([attributes[0], attributes[1]]);
+            let len = usize::from(u16::from_be_bytes([attributes[2], attributes[3]]));
+            let value = attributes.get(4..4 + len)?;
+            match (kind, attribute) {
+                (BINDING_SUCCESS, ATTR_XOR_MAPPED_ADDRESS) => {
+                    mapped = StunClient::address(value, Some(transaction)).or(mapped);
+                }
+                (BINDING_SUCCESS, ATTR_MAPPED_ADDRESS) if mapped.is_none() => {
+                    mapped = StunClient::address(value, None);
+                }
+                (BINDING_ERROR, ATTR_ERROR_CODE) if value.len() >= 4 => {
+                    let code = u16::from(value[2] & 0x07) * 100 + u16::from(value[3]);
+                    let reason = String::from_utf8_lossy(&value[4..]);
+                    return Some(Err(format!("stun error {} {}", code, reason.trim())));
+                }
+                _ => (),
+            }
+            // attributes are padded to 4 bytes
+            attributes = attributes
+                .get(4 + len.div_ceil(4) * 4..)
+                .unwrap_or_default();
+        }
+        match kind {
+            BINDING_SUCCESS => {
+                Some(mapped.ok_or_else(|| "stun answer has no mapped address".to_string()))
+            }
+            BINDING_ERROR => Some(Err("stun binding was rejected".to_string())),
+            _ => None,
+        }
+    }
+
+    /// (XOR-)MAPPED-ADDRESS value; XOR applies when `transaction` is set
+    fn address(value: &[u8], transaction: Option<&[u8; 12]>) -> Option<SocketAddr> {
+        let cookie = MAGIC_COOKIE.to_be_bytes();
+        let mask: Vec<u8> = match transaction {
+            Some(transaction) => cookie.iter().chain(transaction.iter()).copied().collect(),
+            None => vec![0; 16],
+        };
+        let port = u16::from_be_bytes([*value.get(2)? ^ mask[0], *value.get(3)? ^ mask[1]]);
+        let ip: IpAddr = match *value.get(1)? {
+            1 => {
+                let mut octets: [u8; 4] = value.get(4..8)?.try_into().ok()?;
+                octets.iter_mut().zip(&mask).for_each(|(b, m)| *b ^= m);
+                Ipv4Addr::from(octets).into()
+            }
+            2 => {
+                let mut octets: [u8; 16] = value.get(4..20)?.try_into().ok()?;
+                octets.iter_mut().zip(&mask).for_each(|(b, m)| *b ^= m);
+                Ipv6Addr::from(octets).into()
+            }
+            _ => return None,
+        };
+        Some(SocketAddr::new(ip, port))
+    }
+}
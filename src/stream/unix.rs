@@ -0,0 +1,50 @@
+use tokio::net::UnixStream;
+
+use crate::{
+    stream::{ConnectStream, StreamUtils},
+    types::{
+        AddressTransport,
+        config::{NetConfig, NetMode},
+        error::{NetError, NetResultStatus},
+    },
+};
+
+/// Connection to the socket file of a `unix://` address, for daemons listening on one
+/// instead of a port. There is nothing to route, so only `NetMode::Clearnet` reaches it.
+#[async_trait::async_trait]
+impl ConnectStream for UnixStream {
+    async fn connect(config: &NetConfig) -> Result<Self, NetError> {
+        let path = match (&config.addr.transport, &config.addr.path) {
+            (AddressTransport::Unix, Some(path)) => path,
+            _ => {
+                return Err(NetError::new(NetResultStatus::InvalidConfigParameters)
+                    .with_message("unix stream requires a unix:// address")
+                    .with_url(config.addr.url.clone()));
+            }
+        };
+        if !matches!(config.mode, NetMode::Clearnet) {
+            return Err(NetError::new(NetResultStatus::InvalidConfigParameters)
+                .with_message("unix sockets are only reached without a proxy")
+                .with_url(config.addr.url.clone()));
+        }
+        config.host_policy.check_address(&config.addr)?;
+        let connect = async {
+            UnixStream::connect(path).await.map_err(|e| {
+                NetError::new(NetResultStatus::ConnectionError)
+                    .with_message("unix socket connect failed")
+                    .with_url(path.as_str())
+                    .with_source(e)
+            })
+        };
+        StreamUtils::within(config.timeouts.connect(), connect, |limit| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("unix socket connect timed out after {:?}", limit))
+                .with_url(path.as_str())
+        })
+        .await
+    }
+
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        None
+    }
+}
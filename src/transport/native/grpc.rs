@@ -4,58 +4,520 @@ use std::{
         Arc,
         atomic::{AtomicI32, Ordering},
     },
+    time::{Duration, Instant},
 };
 
+#[cfg(feature = "tor")]
 use arti_client::DataStream;
+use bytes::Bytes;
 use log::debug;
 use tokio::{
     net::TcpStream,
-    sync::{
-        Mutex,
-        broadcast::{self},
-    },
+    sync::{Mutex, Semaphore, broadcast, oneshot},
 };
 use tokio_rustls::client::TlsStream;
 
+#[cfg(feature = "otel")]
+use crate::utils::otel::OtelSpan;
 use crate::{
     client::{
+        failover::FailoverGrpcClient,
         grpc::native::GrpcClient,
-        native::{GrpcStreamHandle, IGrpcClient},
+        native::IGrpcClient,
     },
-    transport::native::{IGrpcTransport, Transport},
+    stream::{CustomStream, RouteWatch, Socks5Stream},
+    transport::native::{IGrpcTransport, Transport, socket::Backoff},
     types::{
         DartCallback,
-        config::{NetConfig, NetConfigRequest, NetMode, NetProtocol},
-        error::NetResultStatus,
+        config::{NetConfig, NetConfigRequest, NetMode, NetProtocol, NetStreamReconnect},
+        error::{NetError, NetResultStatus},
+        grpc_health::{NetGrpcHealth, NetGrpcServingStatus},
+        grpc_json::NetGrpcJson,
+        grpc_stats::GrpcStats,
         native::request::{
-            NetRequest, NetRequestGrpc, NetRequestGrpcStream, NetRequestGrpcUnary,
+            NetHttpHeaderRef, NetRequest, NetRequestGrpc, NetRequestGrpcCredit,
+            NetRequestGrpcHealth, NetRequestGrpcRebuild, NetRequestGrpcStream, NetRequestGrpcUnary,
             NetRequestGrpcUnsubscribe,
         },
         response::{
-            NetResponseGrpc, NetResponseGrpcSubscribe, NetResponseGrpcUnary,
+            NetResponseGrpc, NetResponseGrpcHealth, NetResponseGrpcState, NetResponseGrpcSubscribe,
             NetResponseGrpcUnsubscribe, NetResponseKind, NetResponseStream, NetResponseStreamData,
-            NetResponseStreamError,
+            NetResponseStreamError, NetResponseStreamRebuild, NetResponseStreamReconnected,
         },
     },
+    utils::subscribers::NetSubscription,
 };
 
+type Incoming = NetSubscription<Result<Option<Vec<u8>>, NetError>>;
+
+type Listeners = Arc<Mutex<HashMap<i32, oneshot::Sender<()>>>>;
+
+type Credits = Arc<Mutex<HashMap<i32, Arc<Semaphore>>>>;
+
+/// Where the streams waiting on a `Rebuild` take the request they resubscribe with
+type Rebuilds = Arc<Mutex<HashMap<i32, oneshot::Sender<Bytes>>>>;
+
+/// gRPC `UNKNOWN`, `ABORTED`, `INTERNAL` and `UNAVAILABLE`, what a stream ends with when its
+/// connection drops or the server restarts; any other status answers the call itself
+const RESUBSCRIBE_CODES: [i32; 4] = [2, 10, 13, 14];
+
+/// What a stream's call is made with, kept to make it again on restart
+struct GrpcStreamCall {
+    request: Bytes,
+    method: String,
+    metadata: Vec<(String, String)>,
+    authority: Option<String>,
+    /// When the stream's deadline passes
+    ends: Option<Instant>,
+    /// Asks Dart for the request before resubscribing, `NetRequestGrpcStream::rebuild`
+    rebuild: bool,
+}
+
 pub struct GrpcTransport {
-    stream: Box<dyn IGrpcClient>,
+    stream: Arc<dyn IGrpcClient>,
     callback: DartCallback,
-    listeners: Arc<Mutex<HashMap<i32, GrpcStreamHandle>>>,
+    /// Cancel senders of the running streams
+    listeners: Listeners,
+    /// Messages the streams opened with `NetRequestGrpcStream::credits` may still post
+    credits: Credits,
+    rebuilds: Rebuilds,
+    /// Transcodes the calls from and to JSON when the config has a descriptor set
+    json: Option<NetGrpcJson>,
+    stats: Arc<GrpcStats>,
     _transport_id: u32,
     next_stream_id: AtomicI32,
 }
-#[async_trait::async_trait]
-impl Transport for GrpcTransport {
-    fn create(
-        config: NetConfigRequest,
-        callback: DartCallback,
-        transport_id: u32,
-    ) -> Result<GrpcTransport, NetResultStatus> {
-        let config: NetConfig = config.to_protocol_config(NetProtocol::Grpc)?;
+impl GrpcTransport {
+    /// What Dart is posted of message `b`: its serving status alone for a health watch, its
+    /// JSON when the calls are transcoded
+    fn message(
+        b: Vec<u8>,
+        health: bool,
+        json: &Option<NetGrpcJson>,
+        method: &str,
+    ) -> Result<Vec<u8>, NetError> {
+        if health {
+            let status = NetGrpcHealth::status(&b).unwrap_or(NetGrpcServingStatus::Unknown);
+            return Ok(vec![status as u8]);
+        }
+        match json {
+            Some(json) => json.decode(method, &b),
+            None => Ok(b),
+        }
+    }
+
+    /// First wait after a failed dial of a `wait_for_ready` call, doubled up to `MAX_REDIAL`
+    const REDIAL: Duration = Duration::from_millis(250);
+    const MAX_REDIAL: Duration = Duration::from_secs(5);
+
+    /// Dials until the channel is ready, for calls with `wait_for_ready`; the request timeout
+    /// ends the wait. Errors a later dial cannot fix, such as a bad endpoint, fail at once
+    async fn wait_for_ready(client: &dyn IGrpcClient) -> Result<(), NetError> {
+        let mut redial = GrpcTransport::REDIAL;
+        loop {
+            match client.connect().await {
+                Ok(()) => return Ok(()),
+                Err(e) if e.status().is_transient() => {
+                    debug!("Grpc waiting for ready: {:?}", e);
+                    tokio::time::sleep(redial).await;
+                    redial = (redial * 2).min(GrpcTransport::MAX_REDIAL);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Wait for Dart's answer to a `Rebuild` before the stream keeps the request it had
+    const REBUILD_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Makes the call of stream `id` again in place of its idle one, or of one left on an old
+    /// default route; `None` once the stream was unsubscribed meanwhile
+    async fn restart(
+        client: &dyn IGrpcClient,
+        listeners: &Listeners,
+        id: i32,
+        call: &GrpcStreamCall,
+    ) -> Option<Result<Incoming, NetError>> {
+        let mut listeners = listeners.lock().await;
+        // the stream stays subscribed when the new call fails, for another attempt
+        let (placeholder, _) = oneshot::channel();
+        let _ = std::mem::replace(listeners.get_mut(&id)?, placeholder).send(());
+        let metadata: Vec<NetHttpHeaderRef> = call
+            .metadata
+            .iter()
+            .map(|(key, value)| NetHttpHeaderRef { key, value })
+            .collect();
+        // the new call gets what is left of the stream's deadline
+        let deadline = call
+            .ends
+            .map(|ends| ends.saturating_duration_since(Instant::now()));
+        match client
+            .stream(
+                call.request.clone(),
+                &call.method,
+                &metadata,
+                deadline,
+                call.authority.as_deref(),
+            )
+            .await
+        {
+            Ok(handle) => {
+                listeners.insert(id, handle.cancel);
+                Some(Ok(handle.rx))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// Whether a stream that failed with `error` is made again by `stream_reconnect`
+    fn resubscribes(error: &NetError) -> bool {
+        error.is_transient()
+            && error
+                .grpc_status()
+                .is_none_or(|status| RESUBSCRIBE_CODES.contains(&status.code()))
+    }
+
+    /// Makes the call of stream `id` again after it failed with `error`, waiting longer after
+    /// each failed attempt; the last error once the attempts ran out or one can not be fixed
+    /// by trying again, `None` once the stream was unsubscribed meanwhile
+    async fn resubscribe(
+        client: &dyn IGrpcClient,
+        listeners: &Listeners,
+        id: i32,
+        call: &GrpcStreamCall,
+        policy: NetStreamReconnect,
+        error: NetError,
+    ) -> Option<Result<Incoming, NetError>> {
+        let mut backoff = Backoff::new(&policy);
+        let mut last_error = error;
+        while let Some(delay) = backoff.next() {
+            // unsubscribing ends the wait as it would end the call
+            let (cancel, cancelled) = oneshot::channel::<()>();
+            *listeners.lock().await.get_mut(&id)? = cancel;
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = cancelled => return None,
+            }
+            match GrpcTransport::restart(client, listeners, id, call).await? {
+                Ok(incoming) => return Some(Ok(incoming)),
+                Err(e) if !GrpcTransport::resubscribes(&e) => return Some(Err(e)),
+                Err(e) => {
+                    debug!(
+                        "Grpc stream {} resubscribe attempt {} failed: {:?}",
+                        id, backoff.attempts, e
+                    );
+                    last_error = e;
+                }
+            }
+        }
+        Some(Err(last_error))
+    }
+
+    /// Posts stream `id`'s `Rebuild` with the `last` message it posted and waits for the
+    /// request Dart answers with; `None` when none came in time or it was unsubscribed
+    async fn ask_rebuild(
+        rebuilds: &Rebuilds,
+        callback: &DartCallback,
+        id: i32,
+        last: Option<Bytes>,
+    ) -> Option<Bytes> {
+        let (answer, answered) = oneshot::channel();
+        rebuilds.lock().await.insert(id, answer);
+        callback(NetResponseKind::Stream(NetResponseStream::Rebuild(
+            NetResponseStreamRebuild::new(Some(id), last),
+        )));
+        let request = tokio::time::timeout(GrpcTransport::REBUILD_TIMEOUT, answered)
+            .await
+            .ok()
+            .and_then(Result::ok);
+        rebuilds.lock().await.remove(&id);
+        request
+    }
+
+    /// Opens the stream and forwards its messages by a new stream id, restarting it when it
+    /// idles or the route changes and, by `stream_reconnect`, after it failed; `health`
+    /// streams forward the status of each message
+    async fn subscribe(
+        &self,
+        data: &NetRequestGrpcStream<'_>,
+        health: bool,
+    ) -> Result<NetResponseKind, NetError> {
+        let failed = |e: NetError| {
+            self.stats.failed(&e);
+            e
+        };
+        if data.wait_for_ready {
+            GrpcTransport::wait_for_ready(self.stream.as_ref())
+                .await
+                .map_err(failed)?;
+        }
+        // a health watch encodes its own request
+        let json = self.json.clone().filter(|_| !health);
+        let request = match &json {
+            Some(json) => json.encode(data.method, &data.data).map_err(failed)?,
+            None => data.data.clone(),
+        };
+        let deadline = (data.deadline != 0).then(|| Duration::from_secs(data.deadline.into()));
+        let ends = deadline.map(|deadline| Instant::now() + deadline);
+        let handle = self
+            .stream
+            .stream(
+                request.clone(),
+                data.method,
+                &data.metadata,
+                deadline,
+                data.authority,
+            )
+            .await
+            .map_err(failed)?;
+        self.stats.stream_opened();
+        self.stats.sent(request.len());
+        let id = self.next_stream_id.fetch_add(1, Ordering::Relaxed);
+        let callback = self.callback.clone();
+        let mut rx = handle.rx;
+        let listeners = Arc::clone(&self.listeners);
+        let credits = Arc::clone(&self.credits);
+        let rebuilds = Arc::clone(&self.rebuilds);
+        let credit = (data.credits != 0).then(|| Arc::new(Semaphore::new(data.credits as usize)));
+        if let Some(credit) = &credit {
+            credits.lock().await.insert(id, Arc::clone(credit));
+        }
+        let client = Arc::clone(&self.stream);
+        let stats = Arc::clone(&self.stats);
+        let idle_timeout = self.stream.get_config().stream_idle_timeout;
+        let reconnect = self.stream.get_config().stream_reconnect;
+        // the client connects again by itself, since the route change moved the generation
+        let mut route = self
+            .stream
+            .get_config()
+            .network_reconnect
+            .then(RouteWatch::start);
+        let mut call = GrpcStreamCall {
+            request,
+            method: data.method.to_string(),
+            metadata: data
+                .metadata
+                .iter()
+                .map(|m| (m.key.to_string(), m.value.to_string()))
+                .collect(),
+            authority: data.authority.map(str::to_string),
+            ends,
+            rebuild: data.rebuild,
+        };
+        #[cfg(feature = "otel")]
+        let mut span = OtelSpan::stream(Some(&call.method), self.stream.get_config(), Some(id));
+        tokio::spawn(async move {
+            let method = call.method.as_str();
+            // what the stream posted last, for a `Rebuild`
+            let mut last: Option<Bytes> = None;
+            loop {
+                let (received, rerouted) = tokio::select! {
+                    received = rx.recv_within(idle_timeout.map(|idle| idle.duration())) => {
+                        (received, false)
+                    }
+                    _ = RouteWatch::wait(&mut route) => (None, true),
+                };
+                let Some(received) = received else {
+                    if !rerouted {
+                        #[cfg(feature = "otel")]
+                        span.count("net_sdk.stream.idle");
+                        callback(NetResponseKind::Stream(NetResponseStream::Idle(Some(id))));
+                        if !idle_timeout.is_some_and(|idle| idle.reconnect) {
+                            continue;
+                        }
+                    }
+                    let restarted =
+                        GrpcTransport::restart(client.as_ref(), &listeners, id, &call).await;
+                    match restarted {
+                        Some(Ok(incoming)) => {
+                            #[cfg(feature = "otel")]
+                            span.count("net_sdk.stream.reconnects");
+                            stats.sent(call.request.len());
+                            rx = incoming;
+                            // what the old call had in flight is lost, so Dart is told
+                            if rerouted {
+                                callback(NetResponseKind::Stream(NetResponseStream::Reconnected(
+                                    NetResponseStreamReconnected::new(Some(id), 0),
+                                )));
+                            }
+                        }
+                        Some(Err(err)) => {
+                            #[cfg(feature = "otel")]
+                            span.fail(&err);
+                            stats.failed(&err);
+                            callback(NetResponseKind::Stream(NetResponseStream::Error(
+                                NetResponseStreamError::new(Some(id), err),
+                            )));
+                            break;
+                        }
+                        None => {
+                            callback(NetResponseKind::Stream(NetResponseStream::Cancelled(Some(
+                                id,
+                            ))));
+                            break;
+                        }
+                    }
+                    continue;
+                };
+                match received {
+                    Ok(msg) => match msg {
+                        Ok(data) => match data {
+                            Some(b) => {
+                                stats.received(b.len());
+                                // waits for Dart to grant more, the stream queue fills meanwhile
+                                if let Some(credit) = &credit {
+                                    match credit.acquire().await {
+                                        Ok(permit) => permit.forget(),
+                                        // unsubscribed while waiting, the call goes away next
+                                        Err(_) => continue,
+                                    }
+                                }
+                                #[cfg(feature = "otel")]
+                                span.count("net_sdk.stream.messages");
+                                let b = match GrpcTransport::message(b, health, &json, method) {
+                                    Ok(b) => Bytes::from(b),
+                                    Err(err) => {
+                                        #[cfg(feature = "otel")]
+                                        span.fail(&err);
+                                        stats.failed(&err);
+                                        callback(NetResponseKind::Stream(
+                                            NetResponseStream::Error(NetResponseStreamError::new(
+                                                Some(id),
+                                                err,
+                                            )),
+                                        ));
+                                        break;
+                                    }
+                                };
+                                if call.rebuild {
+                                    last = Some(b.clone());
+                                }
+                                callback(NetResponseKind::Stream(NetResponseStream::Data(
+                                    NetResponseStreamData::new(Some(id), b),
+                                )));
+                            }
+                            None => {
+                                callback(NetResponseKind::Stream(NetResponseStream::Close(Some(
+                                    id,
+                                ))));
+                                break;
+                            }
+                        },
+                        Err(err) => {
+                            stats.failed(&err);
+                            let policy = reconnect.filter(|_| GrpcTransport::resubscribes(&err));
+                            let Some(policy) = policy else {
+                                #[cfg(feature = "otel")]
+                                span.fail(&err);
+                                callback(NetResponseKind::Stream(NetResponseStream::Error(
+                                    NetResponseStreamError::new(Some(id), err),
+                                )));
+                                break;
+                            };
+                            if call.rebuild
+                                && let Some(request) = GrpcTransport::ask_rebuild(
+                                    &rebuilds,
+                                    &callback,
+                                    id,
+                                    last.clone(),
+                                )
+                                .await
+                            {
+                                let request = match &json {
+                                    Some(json) => json.encode(method, &request),
+                                    None => Ok(request),
+                                };
+                                match request {
+                                    Ok(request) => call.request = request,
+                                    Err(err) => {
+                                        #[cfg(feature = "otel")]
+                                        span.fail(&err);
+                                        callback(NetResponseKind::Stream(
+                                            NetResponseStream::Error(NetResponseStreamError::new(
+                                                Some(id),
+                                                err,
+                                            )),
+                                        ));
+                                        break;
+                                    }
+                                }
+                            }
+                            let resubscribed = GrpcTransport::resubscribe(
+                                client.as_ref(),
+                                &listeners,
+                                id,
+                                &call,
+                                policy,
+                                err,
+                            )
+                            .await;
+                            match resubscribed {
+                                Some(Ok(incoming)) => {
+                                    #[cfg(feature = "otel")]
+                                    span.count("net_sdk.stream.reconnects");
+                                    stats.sent(call.request.len());
+                                    rx = incoming;
+                                    callback(NetResponseKind::Stream(
+                                        NetResponseStream::Reconnected(
+                                            NetResponseStreamReconnected::new(Some(id), 0),
+                                        ),
+                                    ));
+                                }
+                                Some(Err(err)) => {
+                                    #[cfg(feature = "otel")]
+                                    span.fail(&err);
+                                    stats.failed(&err);
+                                    callback(NetResponseKind::Stream(NetResponseStream::Error(
+                                        NetResponseStreamError::new(Some(id), err),
+                                    )));
+                                    break;
+                                }
+                                None => {
+                                    callback(NetResponseKind::Stream(
+                                        NetResponseStream::Cancelled(Some(id)),
+                                    ));
+                                    break;
+                                }
+                            }
+                        }
+                    },
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // the call ends without a last message when it was cancelled
+                        let event = match listeners.lock().await.contains_key(&id) {
+                            true => NetResponseStream::Close(Some(id)),
+                            false => NetResponseStream::Cancelled(Some(id)),
+                        };
+                        callback(NetResponseKind::Stream(event));
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(lost)) => {
+                        callback(NetResponseKind::Stream(NetResponseStream::Error(
+                            NetResponseStreamError::new(Some(id), NetError::stream_overflow(lost)),
+                        )));
+                    }
+                }
+            }
+            stats.stream_closed();
+            let mut guard = listeners.lock().await;
+            guard.remove(&id);
+            credits.lock().await.remove(&id);
+        });
+        // 3. Store it
+        {
+            let mut listeners = self.listeners.lock().await;
+            listeners.insert(id, handle.cancel);
+        }
+
+        // 4. Return ID to caller
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::StreamId(
+            NetResponseGrpcSubscribe::new(id),
+        )))
+    }
+
+    pub(crate) fn create_client(config: NetConfig) -> Result<Box<dyn IGrpcClient>, NetError> {
         let stream: Box<dyn IGrpcClient> = match config.protocol {
             NetProtocol::Grpc => match (config.addr.is_tls, &config.mode) {
+                #[cfg(feature = "tor")]
                 (true, NetMode::Tor) => {
                     Box::new(GrpcClient::<TlsStream<DataStream>>::default(config)?)
                 }
@@ -64,16 +526,57 @@ impl Transport for GrpcTransport {
                     Box::new(GrpcClient::<TlsStream<TcpStream>>::default(config)?)
                 }
 
+                #[cfg(feature = "tor")]
                 (false, NetMode::Tor) => Box::new(GrpcClient::<DataStream>::default(config)?),
 
                 (false, NetMode::Clearnet) => Box::new(GrpcClient::<TcpStream>::default(config)?),
+                (true, NetMode::Custom(_)) => {
+                    Box::new(GrpcClient::<TlsStream<CustomStream>>::default(config)?)
+                }
+                (false, NetMode::Custom(_)) => {
+                    Box::new(GrpcClient::<CustomStream>::default(config)?)
+                }
+                (true, NetMode::Socks5(_)) => {
+                    Box::new(GrpcClient::<TlsStream<Socks5Stream>>::default(config)?)
+                }
+                (false, NetMode::Socks5(_)) => {
+                    Box::new(GrpcClient::<Socks5Stream>::default(config)?)
+                }
+                #[cfg(not(feature = "tor"))]
+                (_, NetMode::Tor) => return Err(NetError::feature_disabled("tor")),
             },
-            _ => return Err(NetResultStatus::InvalidConfigParameters),
+            _ => return Err(NetResultStatus::InvalidConfigParameters.into()),
         };
+        Ok(stream)
+    }
+}
+#[async_trait::async_trait]
+impl Transport for GrpcTransport {
+    fn create(
+        config: NetConfigRequest,
+        callback: DartCallback,
+        transport_id: u32,
+    ) -> Result<GrpcTransport, NetError> {
+        let fallbacks = config.to_fallback_configs()?;
+        let config: NetConfig = config.to_protocol_config(NetProtocol::Grpc)?;
+        let json = NetGrpcJson::from_config(&config)?;
+        let mut stream = GrpcTransport::create_client(config)?;
+        if !fallbacks.is_empty() {
+            stream = Box::new(FailoverGrpcClient::new(
+                stream,
+                fallbacks,
+                GrpcTransport::create_client,
+                callback.clone(),
+            )?);
+        }
         Ok(Self {
-            stream,
+            stream: Arc::from(stream),
             callback,
             listeners: Arc::new(Mutex::new(HashMap::new())),
+            credits: Arc::new(Mutex::new(HashMap::new())),
+            rebuilds: Arc::new(Mutex::new(HashMap::new())),
+            stats: Arc::new(GrpcStats::default()),
+            json,
             _transport_id: transport_id,
             next_stream_id: AtomicI32::new(1),
         })
@@ -82,27 +585,42 @@ impl Transport for GrpcTransport {
     async fn do_request<'a>(
         &self,
         request: NetRequest<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus> {
+    ) -> Result<NetResponseKind, NetError> {
+        let deadline = (request.timeout != 0).then(|| Duration::from_secs(request.timeout.into()));
         let socket_requset = request.to_grpc_request()?;
         let kind = match socket_requset {
             NetRequestGrpc::Stream(e) => self.stream(e).await?,
-            NetRequestGrpc::Unary(e) => self.unary(e).await?,
+            NetRequestGrpc::Unary(e) => self.unary(e, deadline).await?,
             NetRequestGrpc::Unsubscribe(e) => self.unsubscribe(e).await?,
+            NetRequestGrpc::Health(e) => self.health(e, deadline).await?,
+            NetRequestGrpc::Credit(e) => self.credit(e).await?,
+            NetRequestGrpc::State => self.state().await?,
+            NetRequestGrpc::Stats => self.stats().await?,
+            NetRequestGrpc::Rebuild(e) => self.rebuild(e).await?,
         };
         Ok(kind)
     }
 
+    async fn connect(&self) -> Result<(), NetError> {
+        self.stream.connect().await
+    }
+
     async fn close(&self) {
         // Lock the mutex
         let mut listeners = self.listeners.lock().await;
 
         // Collect all handles into a Vec to drop the lock while closing
-        let handles: Vec<GrpcStreamHandle> = listeners.drain().map(|(_, handle)| handle).collect();
+        let handles: Vec<oneshot::Sender<()>> =
+            listeners.drain().map(|(_, handle)| handle).collect();
         // Close each handle
         for handle in handles {
-            handle.cancel();
+            let _ = handle.send(());
         }
         drop(listeners);
+        for (_, credit) in self.credits.lock().await.drain() {
+            credit.close();
+        }
+        self.rebuilds.lock().await.clear();
         self.stream.close().await;
     }
 
@@ -115,83 +633,139 @@ impl<'a> IGrpcTransport<'a> for GrpcTransport {
     async fn unary(
         &self,
         data: &NetRequestGrpcUnary<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus> {
-        let data = self.stream.unary(&data.data, &data.method).await?;
-        Ok(NetResponseKind::Grpc(NetResponseGrpc::Unary(
-            NetResponseGrpcUnary::new(data),
-        )))
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseKind, NetError> {
+        let failed = |e: NetError| {
+            self.stats.failed(&e);
+            e
+        };
+        self.stats.unary();
+        if data.wait_for_ready {
+            GrpcTransport::wait_for_ready(self.stream.as_ref())
+                .await
+                .map_err(failed)?;
+        }
+        let request = match &self.json {
+            Some(json) => json.encode(data.method, &data.data).map_err(failed)?,
+            None => data.data.clone(),
+        };
+        self.stats.sent(request.len());
+        let response = self
+            .stream
+            .unary(
+                request,
+                data.method,
+                &data.metadata,
+                deadline,
+                data.authority,
+            )
+            .await
+            .map_err(failed)?;
+        self.stats.received(response.data_ref().len());
+        let response = match &self.json {
+            Some(json) => {
+                let data = json
+                    .decode(data.method, response.data_ref())
+                    .map_err(failed)?;
+                response.with_data(Bytes::from(data))
+            }
+            None => response,
+        };
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::Unary(response)))
     }
 
     async fn stream(
         &self,
         data: &NetRequestGrpcStream<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus> {
-        let handle = self.stream.stream(&data.data, &data.method).await?;
-        let id = self.next_stream_id.fetch_add(1, Ordering::Relaxed);
-        let callback = self.callback.clone();
-        let mut rx = handle.rx.resubscribe();
-        let listeners = Arc::clone(&self.listeners);
-        tokio::spawn(async move {
-            loop {
-                match rx.recv().await {
-                    Ok(msg) => match msg {
-                        Ok(data) => match data {
-                            Some(b) => {
-                                callback(NetResponseKind::Stream(NetResponseStream::Data(
-                                    NetResponseStreamData::new(Some(id), b),
-                                )));
-                            }
-                            None => {
-                                callback(NetResponseKind::Stream(NetResponseStream::Close(Some(
-                                    id,
-                                ))));
-                                break;
-                            }
-                        },
-                        Err(err) => {
-                            callback(NetResponseKind::Stream(NetResponseStream::Error(
-                                NetResponseStreamError::new(Some(id), err),
-                            )));
-                            break;
-                        }
-                    },
-                    Err(broadcast::error::RecvError::Closed) => {
-                        callback(NetResponseKind::Stream(NetResponseStream::Close(Some(id))));
-                        break;
-                    }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {
-                        // optional: report backpressure
-                    }
-                }
-            }
-            let mut guard = listeners.lock().await;
-            guard.remove(&id);
-        });
-        // 3. Store it
-        {
-            let mut listeners = self.listeners.lock().await;
-            listeners.insert(id, handle);
-        }
+    ) -> Result<NetResponseKind, NetError> {
+        self.subscribe(data, false).await
+    }
 
-        // 4. Return ID to caller
-        Ok(NetResponseKind::Grpc(NetResponseGrpc::StreamId(
-            NetResponseGrpcSubscribe::new(id),
+    async fn health(
+        &self,
+        data: &NetRequestGrpcHealth<'a>,
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseKind, NetError> {
+        let request = Bytes::from(NetGrpcHealth::request(data.service));
+        if data.watch {
+            let watch = NetRequestGrpcStream {
+                method: NetGrpcHealth::WATCH,
+                data: request,
+                metadata: Vec::new(),
+                deadline: 0,
+                credits: 0,
+                wait_for_ready: false,
+                authority: None,
+                rebuild: false,
+            };
+            return self.subscribe(&watch, true).await;
+        }
+        self.stats.unary();
+        self.stats.sent(request.len());
+        let response = self
+            .stream
+            .unary(request, NetGrpcHealth::CHECK, &[], deadline, None)
+            .await
+            .inspect_err(|e| self.stats.failed(e))?;
+        self.stats.received(response.data_ref().len());
+        let status = NetGrpcHealth::check_status(response.data_ref())
+            .map_err(|e| e.with_url(self.stream.get_config().addr.url.clone()))?;
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::Health(
+            NetResponseGrpcHealth::new(status),
         )))
     }
+
     async fn unsubscribe(
         &self,
         data: &NetRequestGrpcUnsubscribe,
-    ) -> Result<NetResponseKind, NetResultStatus> {
+    ) -> Result<NetResponseKind, NetError> {
         debug!("Grpc unsubscribe.");
         let mut listeners = self.listeners.lock().await;
-        let handler = listeners.remove(&data.id);
-        let _ = match handler {
-            Some(e) => e.cancel(),
-            None => (),
-        };
+        if let Some(cancel) = listeners.remove(&data.id) {
+            let _ = cancel.send(());
+        }
+        // a stream waiting for credit would not see the cancel
+        if let Some(credit) = self.credits.lock().await.remove(&data.id) {
+            credit.close();
+        }
+        // nor would one waiting for a rebuild
+        self.rebuilds.lock().await.remove(&data.id);
         // 4. Return ID to caller
         Ok(NetResponseKind::Grpc(NetResponseGrpc::Unsubscribe(
             NetResponseGrpcUnsubscribe::new(data.id),
         )))
     }
+
+    async fn credit(&self, data: &NetRequestGrpcCredit) -> Result<NetResponseKind, NetError> {
+        if let Some(credit) = self.credits.lock().await.get(&data.id) {
+            let room = Semaphore::MAX_PERMITS - credit.available_permits();
+            credit.add_permits((data.credits as usize).min(room));
+        }
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::StreamId(
+            NetResponseGrpcSubscribe::new(data.id),
+        )))
+    }
+
+    async fn state(&self) -> Result<NetResponseKind, NetError> {
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::State(
+            NetResponseGrpcState::new(self.stream.connectivity()),
+        )))
+    }
+
+    async fn stats(&self) -> Result<NetResponseKind, NetError> {
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::Stats(
+            self.stats.snapshot(),
+        )))
+    }
+
+    async fn rebuild(&self, data: &NetRequestGrpcRebuild) -> Result<NetResponseKind, NetError> {
+        let answer = self.rebuilds.lock().await.remove(&data.id);
+        let Some(Ok(())) = answer.map(|answer| answer.send(data.data.clone())) else {
+            return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message(format!("grpc stream {} waits for no rebuild", data.id)));
+        };
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::StreamId(
+            NetResponseGrpcSubscribe::new(data.id),
+        )))
+    }
 }
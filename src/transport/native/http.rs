@@ -1,52 +1,297 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+#[cfg(feature = "tor")]
 use arti_client::DataStream;
-use tokio::net::TcpStream;
+use log::debug;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::{net::TcpStream, sync::broadcast::error::RecvError, task::JoinHandle, time::timeout};
 use tokio_rustls::client::TlsStream;
 
+#[cfg(feature = "http3")]
+use crate::client::http::http3::Http3Client;
 use crate::{
     client::{
-        http::native::{AutoSendRequest, HttpClient},
-        native::IHttpClient,
+        failover::FailoverHttpClient,
+        http::native::HttpClient,
+        native::{HttpDownloadSink, IHttpClient},
+    },
+    stream::{CustomStream, NetworkWatcher, RouteWatch, Socks5Stream},
+    transport::native::{
+        IHttpTransport, Transport, http_cache::HttpResponseCache,
+        http_coalesce::HttpRequestCoalescer, http_events::HttpEventStreams,
+        http_pool::HttpHostPool, http_queue::HttpOfflineQueue,
     },
-    transport::native::{IHttpTransport, Transport},
     types::{
-        DartCallback,
-        config::{NetConfig, NetConfigRequest, NetMode, NetProtocol},
-        error::NetResultStatus,
-        native::request::{NetRequest, NetRequestHttp},
-        response::NetResponseKind,
+        AddressTransport, DartCallback,
+        config::{NetConfig, NetConfigRequest, NetHttpProtocol, NetMode, NetProtocol},
+        error::{NetError, NetResultStatus},
+        native::request::{
+            NetHttpHeaderRef, NetHttpRetryConfig, NetRequest, NetRequestHttp, NetRequestHttpEvents,
+            NetRequestKind,
+        },
+        response::{
+            NetHttpQueueState, NetResponseHttp, NetResponseHttpDownload, NetResponseHttpQueue,
+            NetResponseKind,
+        },
     },
     utils::Utils,
 };
 
+/// Retry period of a waiting offline queue, for outages that leave the interfaces alone
+const REPLAY_INTERVAL: Duration = Duration::from_secs(30);
+
 pub struct HttpTransport {
-    client: Box<dyn IHttpClient>,
-    _callback: DartCallback,
+    clients: Arc<HttpHostPool>,
+    queue: Option<Arc<HttpOfflineQueue>>,
+    cache: Option<HttpResponseCache>,
+    coalescer: Option<HttpRequestCoalescer>,
+    events: HttpEventStreams,
+    callback: DartCallback,
+    /// Drops the pools on default route changes, by `NetConfig::network_reconnect`
+    route_watch: Option<JoinHandle<()>>,
     _transport_id: u32,
 }
 impl HttpTransport {
-    fn create_client(config: NetConfig) -> Result<Box<dyn IHttpClient>, NetResultStatus> {
+    pub(crate) fn create_client(config: NetConfig) -> Result<Box<dyn IHttpClient>, NetError> {
+        #[cfg(not(feature = "http3"))]
+        if config.http.protocol == Some(NetHttpProtocol::Http3) {
+            return Err(NetError::feature_disabled("http3"));
+        }
         let client: Box<dyn IHttpClient> = match config.protocol {
+            #[cfg(unix)]
+            NetProtocol::Http if config.addr.transport == AddressTransport::Unix => {
+                Box::new(HttpClient::<UnixStream>::default(config)?)
+            }
             NetProtocol::Http => match (config.addr.is_tls, &config.mode) {
+                #[cfg(feature = "tor")]
                 (true, NetMode::Tor) => {
-                    Box::new(HttpClient::<TlsStream<DataStream>, AutoSendRequest>::default(config)?)
+                    Box::new(HttpClient::<TlsStream<DataStream>>::default(config)?)
+                }
+                // QUIC goes over UDP, which only the clearnet mode reaches
+                #[cfg(feature = "http3")]
+                (true, NetMode::Clearnet)
+                    if config.http.protocol == Some(NetHttpProtocol::Http3) =>
+                {
+                    Box::new(Http3Client::new(config)?)
                 }
-
                 (true, NetMode::Clearnet) => {
-                    Box::new(HttpClient::<TlsStream<TcpStream>, AutoSendRequest>::default(config)?)
+                    Box::new(HttpClient::<TlsStream<TcpStream>>::default(config)?)
                 }
-
-                (false, NetMode::Tor) => {
-                    Box::new(HttpClient::<DataStream, AutoSendRequest>::default(config)?)
+                #[cfg(feature = "tor")]
+                (false, NetMode::Tor) => Box::new(HttpClient::<DataStream>::default(config)?),
+                (false, NetMode::Clearnet) => Box::new(HttpClient::<TcpStream>::default(config)?),
+                (true, NetMode::Custom(_)) => {
+                    Box::new(HttpClient::<TlsStream<CustomStream>>::default(config)?)
                 }
-
-                (false, NetMode::Clearnet) => {
-                    Box::new(HttpClient::<TcpStream, AutoSendRequest>::default(config)?)
+                (false, NetMode::Custom(_)) => {
+                    Box::new(HttpClient::<CustomStream>::default(config)?)
+                }
+                (true, NetMode::Socks5(_)) => {
+                    Box::new(HttpClient::<TlsStream<Socks5Stream>>::default(config)?)
                 }
+                (false, NetMode::Socks5(_)) => {
+                    Box::new(HttpClient::<Socks5Stream>::default(config)?)
+                }
+                #[cfg(not(feature = "tor"))]
+                (_, NetMode::Tor) => return Err(NetError::feature_disabled("tor")),
             },
-            _ => return Err(NetResultStatus::InvalidConfigParameters),
+            _ => return Err(NetResultStatus::InvalidConfigParameters.into()),
         };
         Ok(client)
     }
+
+    /// Sends through the pooled client of the host `request` is for
+    async fn send_to(
+        clients: &HttpHostPool,
+        request: &NetRequestHttp<'_>,
+    ) -> Result<NetResponseHttp, NetError> {
+        let addr = Utils::parse_http_url(request.url)?;
+        let client = clients.client(addr)?;
+        client
+            .send(
+                request.url,
+                request.method,
+                request.body.clone(),
+                request.headers.as_ref(),
+                request.encoding,
+                &request.retry_config,
+            )
+            .await
+    }
+
+    /// `send_to` through the response cache, revalidating what it keeps for the url
+    async fn send_cached(&self, request: &NetRequestHttp<'_>) -> Result<NetResponseHttp, NetError> {
+        let cache = match &self.cache {
+            Some(cache) if HttpResponseCache::can_cache(request) => cache,
+            _ => return HttpTransport::send_to(&self.clients, request).await,
+        };
+        let validators = cache.validators(request);
+        if validators.is_empty() {
+            let response = HttpTransport::send_to(&self.clients, request).await?;
+            return Ok(cache.update(request, response));
+        }
+        // the validators join the headers the request would have been sent with
+        let mut headers: Vec<NetHttpHeaderRef> = match &request.headers {
+            Some(headers) => headers
+                .iter()
+                .map(|h| NetHttpHeaderRef {
+                    key: h.key,
+                    value: h.value,
+                })
+                .collect(),
+            None => self
+                .get_config()
+                .http
+                .headers
+                .iter()
+                .map(|h| NetHttpHeaderRef {
+                    key: h.key_ref(),
+                    value: h.value_ref(),
+                })
+                .collect(),
+        };
+        headers.extend(
+            validators
+                .iter()
+                .map(|(key, value)| NetHttpHeaderRef { key, value }),
+        );
+        let conditional = NetRequestHttp {
+            method: request.method,
+            url: request.url,
+            body: request.body.clone(),
+            headers: Some(headers),
+            encoding: request.encoding,
+            retry_config: NetHttpRetryConfig {
+                max_retries: request.retry_config.max_retries,
+                retry_status: request.retry_config.retry_status,
+                retry_delay: request.retry_config.retry_delay,
+                max_retry_after: request.retry_config.max_retry_after,
+            },
+            queue_offline: false,
+            download_path: None,
+            resume: None,
+        };
+        let response = HttpTransport::send_to(&self.clients, &conditional).await?;
+        Ok(cache.update(request, response))
+    }
+
+    /// `send_cached`, shared with the identical GETs in flight by `NetConfigHttp::coalesce`
+    async fn send_coalesced(
+        &self,
+        request: &NetRequestHttp<'_>,
+    ) -> Result<NetResponseHttp, NetError> {
+        match &self.coalescer {
+            Some(coalescer) if HttpRequestCoalescer::can_coalesce(request) => {
+                coalescer.send(request, self.send_cached(request)).await
+            }
+            _ => self.send_cached(request).await,
+        }
+    }
+
+    /// Downloads `request` to its `download_path`, posting progress under `request_id`
+    async fn download_to(
+        clients: &HttpHostPool,
+        request: &NetRequestHttp<'_>,
+        path: &str,
+        request_id: u32,
+        callback: &DartCallback,
+    ) -> Result<NetResponseHttp, NetError> {
+        let addr = Utils::parse_http_url(request.url)?;
+        let client = clients.client(addr)?;
+        let progress = |received, total| {
+            callback(NetResponseKind::HttpDownload(NetResponseHttpDownload::new(
+                request_id, received, total,
+            )))
+        };
+        let sink = HttpDownloadSink {
+            path,
+            progress: &progress,
+            resume: request.resume,
+        };
+        client
+            .download(
+                request.url,
+                request.method,
+                request.body.clone(),
+                request.headers.as_ref(),
+                &request.retry_config,
+                &sink,
+            )
+            .await
+    }
+
+    /// Drops the pooled connections whenever the default route moves, so the next request
+    /// connects over the new one instead of writing to a connection that is gone
+    fn watch_route(mut route: RouteWatch, clients: Arc<HttpHostPool>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                route.changed().await;
+                debug!("default route changed, dropping http connections");
+                clients.close().await;
+            }
+        })
+    }
+
+    fn start_replay(
+        &self,
+        queue: &Arc<HttpOfflineQueue>,
+    ) -> impl FnOnce() -> JoinHandle<()> + use<> {
+        let (clients, callback) = (Arc::clone(&self.clients), Arc::clone(&self.callback));
+        let queue = Arc::clone(queue);
+        move || tokio::spawn(HttpTransport::replay(queue, clients, callback))
+    }
+
+    /// Replays the queue in order after every network change and every `REPLAY_INTERVAL`,
+    /// until it is empty. A request that still can not connect ends the round.
+    async fn replay(
+        queue: Arc<HttpOfflineQueue>,
+        clients: Arc<HttpHostPool>,
+        callback: DartCallback,
+    ) {
+        let post = |request_id, state, pending, http, error| {
+            callback(NetResponseKind::HttpQueue(NetResponseHttpQueue::new(
+                request_id, state, pending, http, error,
+            )))
+        };
+        let mut changes = NetworkWatcher::subscribe();
+        loop {
+            if let Ok(Err(RecvError::Closed)) = timeout(REPLAY_INTERVAL, changes.recv()).await {
+                changes = NetworkWatcher::subscribe();
+            }
+            // `front` clears the replay slot when it finds the queue empty
+            loop {
+                let Some(entry) = queue.front() else {
+                    return;
+                };
+                if entry.expired() {
+                    let pending = queue.pop(entry.request_id);
+                    post(
+                        entry.request_id,
+                        NetHttpQueueState::Expired,
+                        pending,
+                        None,
+                        None,
+                    );
+                    continue;
+                }
+                let result = match entry.body() {
+                    Ok(body) => {
+                        let request = entry.request(body);
+                        HttpTransport::send_to(&clients, &request).await
+                    }
+                    Err(e) => Err(e),
+                };
+                let (state, http, error) = match result {
+                    Err(e) if HttpOfflineQueue::is_offline(&e) => break,
+                    Ok(http) => (NetHttpQueueState::Delivered, Some(http), None),
+                    Err(e) => (NetHttpQueueState::Failed, None, Some(e.to_string())),
+                };
+                let pending = queue.pop(entry.request_id);
+                post(entry.request_id, state, pending, http, error);
+            }
+        }
+    }
 }
 #[async_trait::async_trait]
 impl Transport for HttpTransport {
@@ -54,47 +299,143 @@ impl Transport for HttpTransport {
         config: NetConfigRequest,
         callback: DartCallback,
         transport_id: u32,
-    ) -> Result<Self, NetResultStatus> {
+    ) -> Result<Self, NetError> {
+        let fallbacks = config.to_fallback_configs()?;
         let config = config.to_protocol_config(NetProtocol::Http)?;
-        let client = HttpTransport::create_client(config)?;
-        Ok(Self {
-            client: client,
-            _callback: callback,
+        let queue = config
+            .http
+            .offline_queue
+            .as_ref()
+            .map(|queue| Arc::new(HttpOfflineQueue::open(queue)));
+        let cache = config
+            .http
+            .cache
+            .as_ref()
+            .map(|cache| HttpResponseCache::new(cache, &config.http));
+        let coalescer = config.http.coalesce.then(HttpRequestCoalescer::new);
+        let network_reconnect = config.network_reconnect;
+        let mut client = HttpTransport::create_client(config)?;
+        if !fallbacks.is_empty() {
+            client = Box::new(FailoverHttpClient::new(
+                client,
+                fallbacks,
+                HttpTransport::create_client,
+                callback.clone(),
+            )?);
+        }
+        let clients = Arc::new(HttpHostPool::new(
+            Arc::from(client),
+            HttpTransport::create_client,
+        ));
+        let in_runtime = tokio::runtime::Handle::try_current().is_ok();
+        let route_watch = (network_reconnect && in_runtime)
+            .then(|| HttpTransport::watch_route(RouteWatch::start(), Arc::clone(&clients)));
+        let transport = Self {
+            clients,
+            queue,
+            cache,
+            coalescer,
+            events: HttpEventStreams::new(),
+            callback,
+            route_watch,
             _transport_id: transport_id,
-        })
+        };
+        // requests left by an earlier run wait for the network like new ones
+        if let Some(queue) = &transport.queue
+            && in_runtime
+        {
+            queue.resume(transport.start_replay(queue));
+        }
+        Ok(transport)
     }
 
     async fn do_request<'a>(
         &self,
         request: NetRequest<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus> {
+    ) -> Result<NetResponseKind, NetError> {
+        if let NetRequestKind::HttpEvents(events) = &request.kind {
+            let reconnect = self.get_config().stream_reconnect;
+            return match events {
+                NetRequestHttpEvents::Subscribe(http) => {
+                    self.events
+                        .subscribe(http, &self.clients, &self.callback, reconnect)
+                        .await
+                }
+                NetRequestHttpEvents::Unsubscribe(id) => Ok(self.events.unsubscribe(*id)),
+            };
+        }
         let http_request = request.to_http_request()?;
-        let addr = Utils::parse_http_url(http_request.url)?;
-        let config = self.client.get_config();
-        if addr.host != config.addr.host {
-            let new_config = config.change_addr(addr);
-            let client = HttpTransport::create_client(new_config)?;
-            let result = client
-                .send(
-                    &http_request.url,
-                    &http_request.method,
-                    http_request.body,
-                    http_request.headers.as_ref(),
-                    &http_request.retry_config,
-                )
-                .await;
-
-            let _ = client.close();
-            return result.map(|e| NetResponseKind::Http(e));
-        }
-        // if(http_request.)
-        self.send(http_request).await
+        if let Some(path) = http_request.download_path {
+            return HttpTransport::download_to(
+                &self.clients,
+                http_request,
+                path,
+                request.id,
+                &self.callback,
+            )
+            .await
+            .map(NetResponseKind::Http);
+        }
+        let queue = match &self.queue {
+            Some(queue) if http_request.queue_offline => queue,
+            _ => {
+                return self
+                    .send_coalesced(http_request)
+                    .await
+                    .map(NetResponseKind::Http);
+            }
+        };
+        if !HttpOfflineQueue::can_queue(http_request) {
+            return Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message("only idempotent requests can be queued offline")
+                .with_url(http_request.url));
+        }
+        // while requests wait, new ones queue behind them to keep the order
+        if queue.len() == 0 {
+            match self.send_coalesced(http_request).await {
+                Err(e) if HttpOfflineQueue::is_offline(&e) => {}
+                result => return result.map(NetResponseKind::Http),
+            }
+        }
+        let pending = queue.push(request.id, http_request, self.start_replay(queue))?;
+        Ok(NetResponseKind::HttpQueue(NetResponseHttpQueue::new(
+            request.id,
+            NetHttpQueueState::Queued,
+            pending,
+            None,
+            None,
+        )))
+    }
+    async fn connect(&self) -> Result<(), NetError> {
+        self.clients.primary().connect().await
     }
     async fn close(&self) {
-        self.client.close().await;
+        if let Some(watch) = &self.route_watch {
+            watch.abort();
+        }
+        if let Some(queue) = &self.queue {
+            queue.close();
+        }
+        self.events.close();
+        self.clients.close().await;
     }
     fn get_config(&self) -> &NetConfig {
-        self.client.get_config()
+        self.clients.primary().get_config()
+    }
+
+    /// The responses of `NetConfigHttp::cache`; nothing without one
+    fn save_state(&self, dir: &Path) -> Result<(), NetError> {
+        match &self.cache {
+            Some(cache) => cache.save(dir),
+            None => Ok(()),
+        }
+    }
+
+    fn restore_state(&self, dir: &Path) -> Result<(), NetError> {
+        match &self.cache {
+            Some(cache) => cache.restore(dir),
+            None => Ok(()),
+        }
     }
 }
 
@@ -103,18 +444,96 @@ impl IHttpTransport for HttpTransport {
     async fn send<'a>(
         &self,
         request: &NetRequestHttp<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus> {
-        let result = self
-            .client
-            .send(
-                &request.url,
-                &request.method,
-                request.body,
-                request.headers.as_ref(),
-                &request.retry_config,
-            )
-            .await?;
+    ) -> Result<NetResponseKind, NetError> {
+        let result = HttpTransport::send_to(&self.clients, request).await?;
         println!("net response!");
         Ok(NetResponseKind::Http(result))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{types::builder::NetConfigBuilder, utils::buffer::StreamEncoding};
+
+    /// HTTP/1.1 server answering each request after `delay`, on as many connections as the
+    /// client opens
+    async fn slow_server(delay: Duration) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    loop {
+                        let Ok(n) = socket.read(&mut buf).await else {
+                            return;
+                        };
+                        if n == 0 {
+                            return;
+                        }
+                        request.extend_from_slice(&buf[..n]);
+                        while let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                            request.drain(..end + 4);
+                            tokio::time::sleep(delay).await;
+                            let response = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                            if socket.write_all(response).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    fn get(url: &str, id: u32) -> NetRequest<'_> {
+        NetRequest {
+            transport_id: 1,
+            id,
+            timeout: 10,
+            kind: NetRequestKind::Http(NetRequestHttp {
+                method: "GET",
+                url,
+                body: None,
+                headers: None,
+                encoding: StreamEncoding::default(),
+                retry_config: NetHttpRetryConfig::default(),
+                queue_offline: false,
+                download_path: None,
+                resume: None,
+            }),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn requests_on_one_transport_run_concurrently() {
+        let delay = Duration::from_millis(300);
+        let url = format!("http://127.0.0.1:{}/", slow_server(delay).await);
+        let config = NetConfigBuilder::new(url.as_str()).http1().build().unwrap();
+        let transport = HttpTransport::create(config, Arc::new(|_| {}), 1).unwrap();
+        let started = Instant::now();
+        let (a, b, c, d) = tokio::join!(
+            transport.do_request(get(&url, 1)),
+            transport.do_request(get(&url, 2)),
+            transport.do_request(get(&url, 3)),
+            transport.do_request(get(&url, 4)),
+        );
+        for response in [a, b, c, d] {
+            let response = response.unwrap().http().unwrap();
+            assert_eq!(response.status_code(), 200);
+        }
+        // one after the other they would take four delays
+        let elapsed = started.elapsed();
+        assert!(elapsed < delay * 2, "took {:?}", elapsed);
+    }
+}
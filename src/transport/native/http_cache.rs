@@ -0,0 +1,516 @@
+use std::{collections::HashMap, path::Path, sync::Mutex, time::Instant};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    types::{
+        config::{
+            NetConfigHttp, NetHttpAuth, NetHttpCache, NetHttpHeader, NetHttpHeaderMerge,
+            NetHttpHeaders,
+        },
+        error::{NetError, NetResultStatus},
+        native::request::NetRequestHttp,
+        response::NetResponseHttp,
+    },
+    utils::{Utils, buffer::StreamEncoding},
+};
+
+const DEFAULT_MAX_ENTRIES: usize = 64;
+
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// What `HttpResponseCache::save` writes in the directory it is given
+const SAVE_FILE: &str = "http_cache.json";
+
+/// Request headers that make a GET conditional already, or ask past a cache
+const CONDITIONAL_HEADERS: &[&str] = &[
+    "if-none-match",
+    "if-modified-since",
+    "if-match",
+    "if-unmodified-since",
+    "if-range",
+    "range",
+];
+
+/// A kept response and the validators it is revalidated with
+struct CachedResponse {
+    response: NetResponseHttp,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// What the body was decoded for; a request asking another encoding is a miss
+    encoding: StreamEncoding,
+    /// Lowercase names of the response's `Vary` and the values the request had for them
+    vary: Vec<(String, Option<String>)>,
+    /// `Authorization` of the request, only ever kept for a `Cache-Control: public` response
+    authorization: Option<String>,
+    last_used: Instant,
+}
+
+/// A kept response in the form it is saved in. Responses kept for credentials never are,
+/// so no secret reaches the disk
+#[derive(Serialize, Deserialize)]
+struct SavedResponse {
+    url: String,
+    status_code: u16,
+    /// base64
+    body: String,
+    headers: Vec<NetHttpHeader>,
+    trailers: Vec<NetHttpHeader>,
+    version: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    encoding: StreamEncoding,
+    vary: Vec<(String, Option<String>)>,
+}
+
+/// Responses of `NetConfigHttp::cache`, by url and the request headers their `Vary` names.
+/// Only what a server can revalidate is kept, freshness headers such as `max-age` are not
+/// followed, so every GET still asks the server.
+pub(crate) struct HttpResponseCache {
+    entries: Mutex<HashMap<String, Vec<CachedResponse>>>,
+    max_entries: usize,
+    max_body_size: usize,
+    /// What requests are sent with besides their own headers
+    headers: Vec<NetHttpHeader>,
+    header_merge: NetHttpHeaderMerge,
+    authorization: Option<String>,
+}
+
+impl HttpResponseCache {
+    pub fn new(config: &NetHttpCache, http: &NetConfigHttp) -> Self {
+        // a signature is the credentials of the config, whatever it comes out as per request
+        let authorization = match &http.signing {
+            Some(_) => Some("signed".to_string()),
+            None => http.auth.as_ref().map(NetHttpAuth::header_value),
+        };
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            max_entries: match config.max_entries {
+                0 => DEFAULT_MAX_ENTRIES,
+                max => max as usize,
+            },
+            max_body_size: match config.max_body_size {
+                0 => DEFAULT_MAX_BODY_SIZE,
+                max => max as usize,
+            },
+            headers: http.headers.clone(),
+            header_merge: http.header_merge,
+            authorization,
+        }
+    }
+
+    /// GETs that revalidate with nothing of their own
+    pub fn can_cache(request: &NetRequestHttp<'_>) -> bool {
+        request.method.eq_ignore_ascii_case("GET")
+            && !request.headers.as_ref().is_some_and(|headers| {
+                headers.iter().any(|h| {
+                    CONDITIONAL_HEADERS
+                        .iter()
+                        .any(|name| h.key.eq_ignore_ascii_case(name))
+                })
+            })
+    }
+
+    /// `If-None-Match`/`If-Modified-Since` for the response kept for `request`, none when
+    /// there is nothing to revalidate
+    pub fn validators(&self, request: &NetRequestHttp<'_>) -> Vec<(&'static str, String)> {
+        let entries = self.lock();
+        let Some(entry) = entries
+            .get(request.url)
+            .and_then(|variants| variants.iter().find(|entry| self.matches(entry, request)))
+        else {
+            return Vec::new();
+        };
+        let mut validators = Vec::new();
+        if let Some(etag) = &entry.etag {
+            validators.push(("if-none-match", etag.clone()));
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            validators.push(("if-modified-since", last_modified.clone()));
+        }
+        validators
+    }
+
+    /// The answer to give for `response`: the kept response on a `304`, which renews it,
+    /// otherwise `response` itself, kept when it can be revalidated
+    pub fn update(
+        &self,
+        request: &NetRequestHttp<'_>,
+        response: NetResponseHttp,
+    ) -> NetResponseHttp {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        if response.status_code() == 304 {
+            let Some(entry) = entries.get_mut(request.url).and_then(|variants| {
+                variants
+                    .iter_mut()
+                    .find(|entry| self.matches(entry, request))
+            }) else {
+                return response;
+            };
+            // a 304 carries the validators of the representation it confirms
+            let headers = response.headers_ref();
+            if let Some(etag) = headers.get_ref("etag") {
+                entry.etag = Some(etag.to_string());
+            }
+            if let Some(last_modified) = headers.get_ref("last-modified") {
+                entry.last_modified = Some(last_modified.to_string());
+            }
+            entry.last_used = now;
+            return entry.response.clone();
+        }
+        if !(200..300).contains(&response.status_code()) {
+            return response;
+        }
+        let headers = response.headers_ref();
+        let etag = headers.get_ref("etag").map(str::to_string);
+        let last_modified = headers.get_ref("last-modified").map(str::to_string);
+        let cache_control = |name: &str| {
+            headers.get_ref("cache-control").is_some_and(|value| {
+                value
+                    .split(',')
+                    .any(|directive| directive.trim().eq_ignore_ascii_case(name))
+            })
+        };
+        let vary: Vec<String> = headers
+            .get_ref("vary")
+            .map(|vary| {
+                vary.split(',')
+                    .map(|name| name.trim().to_ascii_lowercase())
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let authorization = self.authorization(request);
+        // what is kept no longer is the current representation
+        if let Some(variants) = entries.get_mut(request.url) {
+            variants.retain(|entry| !self.matches(entry, request));
+            if variants.is_empty() {
+                entries.remove(request.url);
+            }
+        }
+        if response.status_code() != 200
+            || (etag.is_none() && last_modified.is_none())
+            || cache_control("no-store")
+            || (authorization.is_some() && !cache_control("public"))
+            || vary.iter().any(|name| name == "*")
+            || response.body_ref().len() > self.max_body_size
+        {
+            return response;
+        }
+        if entries.values().map(Vec::len).sum::<usize>() >= self.max_entries {
+            let oldest = entries
+                .iter()
+                .flat_map(|(url, variants)| {
+                    variants
+                        .iter()
+                        .enumerate()
+                        .map(move |(index, entry)| (url, index, entry.last_used))
+                })
+                .min_by_key(|(_, _, last_used)| *last_used)
+                .map(|(url, index, _)| (url.clone(), index));
+            if let Some((url, index)) = oldest
+                && let Some(variants) = entries.get_mut(&url)
+            {
+                variants.remove(index);
+                if variants.is_empty() {
+                    entries.remove(&url);
+                }
+            }
+        }
+        let vary = vary
+            .into_iter()
+            .map(|name| {
+                let value = self.sent_header(request, &name);
+                (name, value)
+            })
+            .collect();
+        entries
+            .entry(request.url.to_string())
+            .or_default()
+            .push(CachedResponse {
+                response: response.clone(),
+                etag,
+                last_modified,
+                encoding: request.encoding,
+                vary,
+                authorization,
+                last_used: now,
+            });
+        response
+    }
+
+    /// Writes the kept responses to `dir`, least recently used first, through a temporary
+    /// file so a crash never leaves half of them
+    pub fn save(&self, dir: &Path) -> Result<(), NetError> {
+        let saved: Vec<SavedResponse> = {
+            let entries = self.lock();
+            let mut kept: Vec<(&String, &CachedResponse)> = entries
+                .iter()
+                .flat_map(|(url, variants)| variants.iter().map(move |entry| (url, entry)))
+                .filter(|(_, entry)| entry.authorization.is_none())
+                .collect();
+            kept.sort_by_key(|(_, entry)| entry.last_used);
+            kept.into_iter()
+                .map(|(url, entry)| SavedResponse {
+                    url: url.clone(),
+                    status_code: entry.response.status_code(),
+                    body: STANDARD.encode(entry.response.body_ref()),
+                    headers: entry.response.headers_ref().iter().cloned().collect(),
+                    trailers: entry.response.trailers_ref().iter().cloned().collect(),
+                    version: entry.response.version(),
+                    etag: entry.etag.clone(),
+                    last_modified: entry.last_modified.clone(),
+                    encoding: entry.encoding,
+                    vary: entry.vary.clone(),
+                })
+                .collect()
+        };
+        let path = dir.join(SAVE_FILE);
+        let temporary = dir.join(format!("{}.tmp", SAVE_FILE));
+        serde_json::to_vec(&saved)
+            .map_err(std::io::Error::other)
+            .and_then(|data| std::fs::write(&temporary, data))
+            .and_then(|_| std::fs::rename(&temporary, &path))
+            .map_err(|e| {
+                NetError::new(NetResultStatus::InternalError)
+                    .with_message(format!(
+                        "failed to save the http cache to {}",
+                        path.display()
+                    ))
+                    .with_source(e)
+            })
+    }
+
+    /// Takes the responses `save` left in `dir` in place of the kept ones, up to
+    /// `max_entries` of the most recently used. A directory without them keeps nothing
+    pub fn restore(&self, dir: &Path) -> Result<(), NetError> {
+        let path = dir.join(SAVE_FILE);
+        let unreadable = |message: String| {
+            NetError::new(NetResultStatus::InvalidRequestParameters).with_message(message)
+        };
+        let saved: Vec<SavedResponse> = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| {
+                unreadable(format!("{} is not a saved http cache", path.display())).with_source(e)
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(unreadable(format!("failed to read {}", path.display())).with_source(e));
+            }
+        };
+        let skip = saved.len().saturating_sub(self.max_entries);
+        let mut restored: HashMap<String, Vec<CachedResponse>> = HashMap::new();
+        for saved in saved.into_iter().skip(skip) {
+            let body = STANDARD
+                .decode(&saved.body)
+                .map_err(|e| unreadable(format!("corrupt body in {}: {}", path.display(), e)))?;
+            if body.len() > self.max_body_size {
+                continue;
+            }
+            let headers = |saved: Vec<NetHttpHeader>| {
+                let mut headers = NetHttpHeaders::new();
+                for header in saved {
+                    headers.push(header);
+                }
+                headers
+            };
+            let response = NetResponseHttp::new(
+                saved.status_code,
+                Bytes::from(body),
+                headers(saved.headers),
+                saved.encoding,
+            )
+            .with_trailers(headers(saved.trailers))
+            .with_connection(saved.version, None);
+            restored.entry(saved.url).or_default().push(CachedResponse {
+                response,
+                etag: saved.etag,
+                last_modified: saved.last_modified,
+                encoding: saved.encoding,
+                vary: saved.vary,
+                authorization: None,
+                // in the order they were saved, so the least recently used still goes first
+                last_used: Instant::now(),
+            });
+        }
+        *self.lock() = restored;
+        Ok(())
+    }
+
+    /// Whether `entry` was kept for a request with what `request` is sent with
+    fn matches(&self, entry: &CachedResponse, request: &NetRequestHttp<'_>) -> bool {
+        entry.encoding as u8 == request.encoding as u8
+            && entry.authorization == self.authorization(request)
+            && entry
+                .vary
+                .iter()
+                .all(|(name, value)| self.sent_header(request, name) == *value)
+    }
+
+    /// The value `request` goes out with for the lowercase header `name`, config headers
+    /// included, repeated headers joined by commas
+    fn sent_header(&self, request: &NetRequestHttp<'_>, name: &str) -> Option<String> {
+        let is_name = |key: &str| key.eq_ignore_ascii_case(name);
+        let values: Vec<&str> = match (&request.headers, self.header_merge) {
+            (Some(headers), NetHttpHeaderMerge::Replace) => headers
+                .iter()
+                .filter(|h| is_name(h.key))
+                .map(|h| h.value)
+                .collect(),
+            // an empty value only takes the config header away
+            (Some(headers), NetHttpHeaderMerge::Merge)
+                if headers.iter().any(|h| is_name(h.key)) =>
+            {
+                headers
+                    .iter()
+                    .filter(|h| is_name(h.key) && !h.value.is_empty())
+                    .map(|h| h.value)
+                    .collect()
+            }
+            _ => self
+                .headers
+                .iter()
+                .filter(|h| is_name(h.key_ref()))
+                .map(NetHttpHeader::value_ref)
+                .collect(),
+        };
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+
+    /// The `Authorization` of `request`: its own, from url credentials or from the config
+    fn authorization(&self, request: &NetRequestHttp<'_>) -> Option<String> {
+        self.sent_header(request, "authorization")
+            .or_else(|| {
+                Utils::parse_http_url(request.url)
+                    .ok()
+                    .and_then(|addr| addr.authorization)
+            })
+            .or_else(|| self.authorization.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<CachedResponse>>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::types::{
+        config::NetHttpHeaders,
+        native::request::{NetHttpHeaderRef, NetHttpRetryConfig},
+    };
+
+    const URL: &str = "https://example.com/a";
+
+    fn get<'a>(headers: Vec<NetHttpHeaderRef<'a>>) -> NetRequestHttp<'a> {
+        NetRequestHttp {
+            method: "GET",
+            url: URL,
+            body: None,
+            headers: Some(headers),
+            encoding: StreamEncoding::default(),
+            retry_config: NetHttpRetryConfig::default(),
+            queue_offline: false,
+            download_path: None,
+            resume: None,
+        }
+    }
+
+    fn ok(headers: &[(&str, &str)]) -> NetResponseHttp {
+        let mut kept = NetHttpHeaders::new();
+        for (key, value) in headers {
+            kept.push(NetHttpHeader::new(key.to_string(), value.to_string()));
+        }
+        NetResponseHttp::new(
+            200,
+            Bytes::from_static(b"ok"),
+            kept,
+            StreamEncoding::default(),
+        )
+    }
+
+    fn header<'a>(key: &'a str, value: &'a str) -> NetHttpHeaderRef<'a> {
+        NetHttpHeaderRef { key, value }
+    }
+
+    #[test]
+    fn vary_keeps_variants_apart() {
+        let cache = HttpResponseCache::new(&NetHttpCache::default(), &NetConfigHttp::default());
+        let en = get(vec![header("Accept-Language", "en")]);
+        let de = get(vec![header("Accept-Language", "de")]);
+        cache.update(&en, ok(&[("etag", "\"en\""), ("vary", "Accept-Language")]));
+        assert_eq!(
+            cache.validators(&en),
+            vec![("if-none-match", "\"en\"".to_string())]
+        );
+        assert!(cache.validators(&de).is_empty());
+        cache.update(&de, ok(&[("etag", "\"de\""), ("vary", "accept-language")]));
+        assert_eq!(
+            cache.validators(&de),
+            vec![("if-none-match", "\"de\"".to_string())]
+        );
+        assert_eq!(
+            cache.validators(&en),
+            vec![("if-none-match", "\"en\"".to_string())]
+        );
+    }
+
+    #[test]
+    fn authorized_requests_need_public_responses() {
+        let cache = HttpResponseCache::new(&NetHttpCache::default(), &NetConfigHttp::default());
+        let alice = get(vec![header("Authorization", "Bearer alice")]);
+        let bob = get(vec![header("Authorization", "Bearer bob")]);
+        cache.update(&alice, ok(&[("etag", "\"1\"")]));
+        assert!(cache.validators(&alice).is_empty());
+        cache.update(
+            &alice,
+            ok(&[("etag", "\"1\""), ("cache-control", "public")]),
+        );
+        assert!(!cache.validators(&alice).is_empty());
+        assert!(cache.validators(&bob).is_empty());
+        assert!(cache.validators(&get(Vec::new())).is_empty());
+    }
+
+    #[test]
+    fn saved_responses_are_restored_without_credentials() {
+        let dir = std::env::temp_dir().join(format!("net_sdk_cache_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache = HttpResponseCache::new(&NetHttpCache::default(), &NetConfigHttp::default());
+        let plain = get(vec![header("Accept-Language", "en")]);
+        let alice = get(vec![header("Authorization", "Bearer alice")]);
+        cache.update(
+            &plain,
+            ok(&[("etag", "\"en\""), ("vary", "Accept-Language")]),
+        );
+        cache.update(
+            &alice,
+            ok(&[("etag", "\"1\""), ("cache-control", "public")]),
+        );
+        cache.save(&dir).unwrap();
+
+        let restored = HttpResponseCache::new(&NetHttpCache::default(), &NetConfigHttp::default());
+        restored.restore(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            restored.validators(&plain),
+            vec![("if-none-match", "\"en\"".to_string())]
+        );
+        assert!(restored.validators(&get(Vec::new())).is_empty());
+        assert!(restored.validators(&alice).is_empty());
+        let confirmed = restored.update(
+            &plain,
+            NetResponseHttp::new(
+                304,
+                Bytes::new(),
+                NetHttpHeaders::new(),
+                StreamEncoding::default(),
+            ),
+        );
+        assert_eq!(confirmed.body_ref(), b"ok");
+    }
+}
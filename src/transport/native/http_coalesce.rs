@@ -0,0 +1,116 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::oneshot;
+
+use crate::types::{error::NetError, native::request::NetRequestHttp, response::NetResponseHttp};
+
+/// Url, decoding and headers a GET is told apart by
+type RequestKey = (String, u8, Vec<(String, String)>);
+
+type Waiters = Vec<oneshot::Sender<Result<NetResponseHttp, NetError>>>;
+
+type InFlight = Arc<Mutex<HashMap<RequestKey, Waiters>>>;
+
+/// GETs of `NetConfigHttp::coalesce`, by what they ask for. The first of identical requests
+/// in flight at once goes to the server and those sent while it is answered wait for a copy
+/// of its response, error included, so a fan out of the same poll costs one request.
+pub(crate) struct HttpRequestCoalescer {
+    in_flight: InFlight,
+}
+
+/// The request that goes to the server for the key it holds; dropped, it lets the waiters
+/// go, who send their own when it was cancelled before it answered them
+struct Leader {
+    key: Option<RequestKey>,
+    in_flight: InFlight,
+}
+
+impl Leader {
+    fn finish(mut self, result: &Result<NetResponseHttp, NetError>) {
+        let waiters = self
+            .key
+            .take()
+            .and_then(|key| HttpRequestCoalescer::lock(&self.in_flight).remove(&key));
+        for waiter in waiters.into_iter().flatten() {
+            let _ = waiter.send(result.clone());
+        }
+    }
+}
+
+impl Drop for Leader {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            HttpRequestCoalescer::lock(&self.in_flight).remove(&key);
+        }
+    }
+}
+
+impl HttpRequestCoalescer {
+    pub fn new() -> Self {
+        Self {
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// GETs without a body
+    pub fn can_coalesce(request: &NetRequestHttp<'_>) -> bool {
+        request.method.eq_ignore_ascii_case("GET") && request.body.is_none()
+    }
+
+    /// The response of `send`, or of the identical request already sending it
+    pub async fn send<F>(
+        &self,
+        request: &NetRequestHttp<'_>,
+        send: F,
+    ) -> Result<NetResponseHttp, NetError>
+    where
+        F: Future<Output = Result<NetResponseHttp, NetError>>,
+    {
+        let key = HttpRequestCoalescer::key(request);
+        let waiting = {
+            let mut in_flight = HttpRequestCoalescer::lock(&self.in_flight);
+            match in_flight.get_mut(&key) {
+                Some(waiters) => {
+                    let (sender, receiver) = oneshot::channel();
+                    waiters.push(sender);
+                    Some(receiver)
+                }
+                None => {
+                    in_flight.insert(key.clone(), Vec::new());
+                    None
+                }
+            }
+        };
+        if let Some(receiver) = waiting {
+            match receiver.await {
+                Ok(result) => return result,
+                // the leader was cancelled, this request goes out on its own
+                Err(_) => return send.await,
+            }
+        }
+        let leader = Leader {
+            key: Some(key),
+            in_flight: Arc::clone(&self.in_flight),
+        };
+        let result = send.await;
+        leader.finish(&result);
+        result
+    }
+
+    fn key(request: &NetRequestHttp<'_>) -> RequestKey {
+        let headers = request
+            .headers
+            .iter()
+            .flatten()
+            .map(|h| (h.key.to_ascii_lowercase(), h.value.to_string()))
+            .collect();
+        (request.url.to_string(), request.encoding as u8, headers)
+    }
+
+    fn lock(in_flight: &InFlight) -> std::sync::MutexGuard<'_, HashMap<RequestKey, Waiters>> {
+        in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
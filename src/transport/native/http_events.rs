@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI32, Ordering},
+    },
+    time::Duration,
+};
+
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use hyper::body::Incoming;
+use log::debug;
+use tokio::task::JoinHandle;
+
+use crate::{
+    transport::native::{http_pool::HttpHostPool, socket::Backoff},
+    types::{
+        DartCallback,
+        config::NetStreamReconnect,
+        error::{NetError, NetResultStatus},
+        native::request::{NetHttpHeaderRef, NetRequestHttp},
+        response::{
+            NetResponseHttpEvents, NetResponseKind, NetResponseStream, NetResponseStreamData,
+            NetResponseStreamError, NetResponseStreamReconnected,
+        },
+    },
+    utils::{Utils, event_stream::EventStreamParser},
+};
+
+type Streams = Arc<Mutex<HashMap<i32, JoinHandle<()>>>>;
+
+/// Subscribe request kept for reconnects, since the Dart one is gone once it is answered
+struct EventRequest {
+    url: String,
+    method: String,
+    body: Option<Bytes>,
+    headers: Option<Vec<(String, String)>>,
+}
+
+impl EventRequest {
+    fn new(request: &NetRequestHttp) -> Self {
+        Self {
+            url: request.url.to_string(),
+            method: request.method.to_string(),
+            body: request.body.clone(),
+            headers: request.headers.as_ref().map(|headers| {
+                headers
+                    .iter()
+                    .map(|h| (h.key.to_string(), h.value.to_string()))
+                    .collect()
+            }),
+        }
+    }
+
+    async fn open(
+        &self,
+        clients: &HttpHostPool,
+        last_event_id: Option<&str>,
+    ) -> Result<Incoming, NetError> {
+        let client = clients.client(Utils::parse_http_url(&self.url)?)?;
+        let headers: Option<Vec<NetHttpHeaderRef>> = self.headers.as_ref().map(|headers| {
+            headers
+                .iter()
+                .map(|(key, value)| NetHttpHeaderRef { key, value })
+                .collect()
+        });
+        client
+            .events(
+                &self.url,
+                &self.method,
+                self.body.clone(),
+                headers.as_ref(),
+                last_event_id,
+            )
+            .await
+    }
+}
+
+/// Server-sent event streams of an HTTP transport. Each runs in a task of its own posting
+/// the parsed events as JSON stream data under its id. A dropped stream is opened again
+/// from its last event id by `NetConfig::stream_reconnect`, waiting what the server asked
+/// for with `retry:` in place of the policy's delay; without a policy it ends with the
+/// connection.
+pub(crate) struct HttpEventStreams {
+    streams: Streams,
+    next_id: AtomicI32,
+}
+
+impl HttpEventStreams {
+    pub fn new() -> Self {
+        Self {
+            streams: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicI32::new(1),
+        }
+    }
+
+    /// Answers once the server accepted the stream; a non event stream answer fails it
+    pub async fn subscribe(
+        &self,
+        request: &NetRequestHttp<'_>,
+        clients: &Arc<HttpHostPool>,
+        callback: &DartCallback,
+        reconnect: Option<NetStreamReconnect>,
+    ) -> Result<NetResponseKind, NetError> {
+        let request = EventRequest::new(request);
+        let body = request.open(clients, None).await?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let listen = HttpEventStreams::listen(
+            id,
+            request,
+            body,
+            Arc::clone(clients),
+            callback.clone(),
+            reconnect,
+        );
+        let streams = Arc::clone(&self.streams);
+        // held while spawning, so a stream that ends at once still finds itself to remove
+        let mut running = HttpEventStreams::lock(&self.streams);
+        running.insert(
+            id,
+            tokio::spawn(async move {
+                listen.await;
+                HttpEventStreams::lock(&streams).remove(&id);
+            }),
+        );
+        Ok(NetResponseKind::HttpEvents(NetResponseHttpEvents::new(
+            id, true,
+        )))
+    }
+
+    /// Stops stream `id`, nothing more is posted for it
+    pub fn unsubscribe(&self, id: i32) -> NetResponseKind {
+        if let Some(task) = HttpEventStreams::lock(&self.streams).remove(&id) {
+            task.abort();
+        }
+        NetResponseKind::HttpEvents(NetResponseHttpEvents::new(id, false))
+    }
+
+    pub fn close(&self) {
+        for (_, task) in HttpEventStreams::lock(&self.streams).drain() {
+            task.abort();
+        }
+    }
+
+    async fn listen(
+        id: i32,
+        request: EventRequest,
+        mut body: Incoming,
+        clients: Arc<HttpHostPool>,
+        callback: DartCallback,
+        reconnect: Option<NetStreamReconnect>,
+    ) {
+        let mut parser = EventStreamParser::default();
+        let end = loop {
+            let dropped = match body.frame().await {
+                Some(Ok(frame)) => {
+                    let Some(data) = frame.data_ref() else {
+                        continue;
+                    };
+                    for event in parser.feed(data) {
+                        match serde_json::to_vec(&event) {
+                            Ok(json) => callback(NetResponseKind::Stream(NetResponseStream::Data(
+                                NetResponseStreamData::new(Some(id), json.into()),
+                            ))),
+                            Err(e) => debug!("event encode failed: {:?}", e),
+                        }
+                    }
+                    continue;
+                }
+                Some(Err(e)) => NetResponseStream::Error(NetResponseStreamError::new(
+                    Some(id),
+                    NetError::new(NetResultStatus::ConnectionError)
+                        .with_message("event stream read failed")
+                        .with_url(request.url.as_str())
+                        .with_source(e),
+                )),
+                None => NetResponseStream::Close(Some(id)),
+            };
+            let Some(policy) = reconnect else {
+                break dropped;
+            };
+            match HttpEventStreams::reopen(&request, &clients, &mut parser, policy).await {
+                Ok(reopened) => {
+                    body = reopened;
+                    callback(NetResponseKind::Stream(NetResponseStream::Reconnected(
+                        NetResponseStreamReconnected::new(Some(id), 0),
+                    )));
+                }
+                Err(e) => break NetResponseStream::Error(NetResponseStreamError::new(Some(id), e)),
+            }
+        };
+        callback(NetResponseKind::Stream(end));
+    }
+
+    /// Opens the stream again from the last event id, until the attempts ran out or the
+    /// server stopped answering with an event stream
+    async fn reopen(
+        request: &EventRequest,
+        clients: &HttpHostPool,
+        parser: &mut EventStreamParser,
+        policy: NetStreamReconnect,
+    ) -> Result<Incoming, NetError> {
+        // an event cut off by the drop is not dispatched
+        parser.reset();
+        let mut backoff = Backoff::new(&policy);
+        let mut last_error = None;
+        while let Some(delay) = backoff.next() {
+            let delay = parser
+                .retry()
+                .map(|ms| Duration::from_millis(ms.into()))
+                .unwrap_or(delay);
+            tokio::time::sleep(delay).await;
+            let last_event_id = parser.last_event_id().map(str::to_string);
+            match request.open(clients, last_event_id.as_deref()).await {
+                Ok(body) => return Ok(body),
+                Err(e) if !e.is_transient() => return Err(e),
+                Err(e) => {
+                    debug!(
+                        "event stream reconnect attempt {} failed: {:?}",
+                        backoff.attempts, e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| NetError::new(NetResultStatus::ConnectionError)))
+    }
+
+    fn lock(streams: &Streams) -> std::sync::MutexGuard<'_, HashMap<i32, JoinHandle<()>>> {
+        streams.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
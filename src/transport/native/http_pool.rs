@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::{
+    client::native::IHttpClient,
+    types::{AddressInfo, config::NetConfig, error::NetError},
+};
+
+/// Host (socket file for `unix://`), port and TLS of a pooled client
+type HostKey = (String, u16, bool);
+
+struct PooledHost {
+    client: Arc<dyn IHttpClient>,
+    last_used: Instant,
+}
+
+/// The client of the configured url, plus one per other host requests of the transport went
+/// to, kept so their connections are reused instead of opened for every request.
+///
+/// Hosts unused for `NetHttpPool::idle_timeout` are let go, as their connections already
+/// are; beyond `NetHttpPool::max_hosts` the one unused the longest goes. A dropped client
+/// closes its connections once the requests still holding it are done.
+pub(crate) struct HttpHostPool {
+    primary: Arc<dyn IHttpClient>,
+    hosts: Mutex<HashMap<HostKey, PooledHost>>,
+    create: fn(NetConfig) -> Result<Box<dyn IHttpClient>, NetError>,
+}
+
+impl HttpHostPool {
+    pub fn new(
+        primary: Arc<dyn IHttpClient>,
+        create: fn(NetConfig) -> Result<Box<dyn IHttpClient>, NetError>,
+    ) -> Self {
+        Self {
+            primary,
+            hosts: Mutex::new(HashMap::new()),
+            create,
+        }
+    }
+
+    pub fn primary(&self) -> &dyn IHttpClient {
+        self.primary.as_ref()
+    }
+
+    /// Client for requests to `addr`, created with the transport's config when none is kept
+    pub fn client(&self, addr: AddressInfo) -> Result<Arc<dyn IHttpClient>, NetError> {
+        let config = self.primary.get_config();
+        let key = HttpHostPool::key(&addr);
+        if key == HttpHostPool::key(&config.addr) {
+            return Ok(Arc::clone(&self.primary));
+        }
+        let now = Instant::now();
+        let mut hosts = self.lock();
+        if let Some(host) = hosts.get_mut(&key) {
+            host.last_used = now;
+            return Ok(Arc::clone(&host.client));
+        }
+        let pool = config.http.pool;
+        hosts.retain(|_, host| now.duration_since(host.last_used) < pool.idle_timeout());
+        while hosts.len() >= pool.max_hosts() {
+            let oldest = hosts
+                .iter()
+                .min_by_key(|(_, host)| host.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => hosts.remove(&oldest),
+                None => break,
+            };
+        }
+        let host_override = config.http.host_override(&addr.host).cloned();
+        let mut config = config.change_addr(addr);
+        // credentials of the config are for its own host only
+        config.http.auth = None;
+        if let Some(host_override) = host_override {
+            if let Some(tls_mode) = host_override.tls_mode {
+                config.tls_mode = tls_mode;
+            }
+            if let Some(protocol) = host_override.protocol {
+                config.http.protocol = Some(protocol);
+            }
+        }
+        let client: Arc<dyn IHttpClient> = Arc::from((self.create)(config)?);
+        hosts.insert(
+            key,
+            PooledHost {
+                client: Arc::clone(&client),
+                last_used: now,
+            },
+        );
+        Ok(client)
+    }
+
+    /// Closes every connection, later requests open new ones
+    pub async fn close(&self) {
+        let hosts: Vec<Arc<dyn IHttpClient>> =
+            self.lock().drain().map(|(_, host)| host.client).collect();
+        for client in hosts {
+            client.close().await;
+        }
+        self.primary.close().await;
+    }
+
+    fn key(addr: &AddressInfo) -> HostKey {
+        // `unix://` addresses have no host, their socket file tells them apart
+        match &addr.path {
+            Some(path) => (path.clone(), addr.port, addr.is_tls),
+            None => (addr.host.to_ascii_lowercase(), addr.port, addr.is_tls),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<HostKey, PooledHost>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
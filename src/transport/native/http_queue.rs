@@ -0,0 +1,244 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::{
+    types::{
+        config::{NetHttpHeader, NetHttpOfflineQueue},
+        error::{NetError, NetResultStatus},
+        native::request::{NetHttpHeaderRef, NetHttpRetryConfig, NetRequestHttp},
+    },
+    utils::buffer::StreamEncoding,
+};
+
+const DEFAULT_MAX_REQUESTS: usize = 64;
+
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A request waiting in the offline queue, in the form it is persisted in
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct QueuedRequest {
+    pub request_id: u32,
+    method: String,
+    url: String,
+    /// base64
+    body: Option<String>,
+    headers: Vec<NetHttpHeader>,
+    encoding: StreamEncoding,
+    /// Unix time in seconds
+    expires_at: u64,
+}
+
+impl QueuedRequest {
+    pub fn expired(&self) -> bool {
+        HttpOfflineQueue::now() >= self.expires_at
+    }
+
+    pub fn body(&self) -> Result<Option<Bytes>, NetError> {
+        self.body
+            .as_ref()
+            .map(|body| {
+                STANDARD.decode(body).map(Bytes::from).map_err(|e| {
+                    NetError::new(NetResultStatus::InternalError)
+                        .with_message(format!("corrupt body in the offline queue: {}", e))
+                })
+            })
+            .transpose()
+    }
+
+    /// The request as it was first sent, with `body` from `body()`
+    pub fn request(&self, body: Option<Bytes>) -> NetRequestHttp<'_> {
+        let headers = self
+            .headers
+            .iter()
+            .map(|header| NetHttpHeaderRef {
+                key: header.key_ref(),
+                value: header.value_ref(),
+            })
+            .collect();
+        NetRequestHttp {
+            method: &self.method,
+            url: &self.url,
+            body,
+            headers: Some(headers),
+            encoding: self.encoding,
+            retry_config: NetHttpRetryConfig::default(),
+            queue_offline: false,
+            download_path: None,
+            resume: None,
+        }
+    }
+}
+
+/// Ordered store behind `NetConfigHttp::offline_queue`, written through to its file after
+/// every change. Replaying is left to the transport, which owns the client.
+pub(crate) struct HttpOfflineQueue {
+    entries: Mutex<VecDeque<QueuedRequest>>,
+    max_requests: usize,
+    ttl: Duration,
+    path: Option<String>,
+    /// Running replay task, cleared by the task itself once the queue is empty
+    replay: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl HttpOfflineQueue {
+    /// Loads what an earlier run left in `config.path`; a missing or unreadable file starts
+    /// an empty queue
+    pub fn open(config: &NetHttpOfflineQueue) -> Self {
+        let entries = config
+            .path
+            .as_ref()
+            .and_then(|path| match std::fs::read(path) {
+                Ok(data) => serde_json::from_slice(&data)
+                    .inspect_err(|e| debug!("offline queue {} is unreadable: {:?}", path, e))
+                    .ok(),
+                Err(_) => None,
+            })
+            .unwrap_or_default();
+        Self {
+            entries: Mutex::new(entries),
+            max_requests: match config.max_requests {
+                0 => DEFAULT_MAX_REQUESTS,
+                max => max as usize,
+            },
+            ttl: match config.ttl {
+                0 => DEFAULT_TTL,
+                ttl => Duration::from_secs(ttl.into()),
+            },
+            path: config.path.clone(),
+            replay: Mutex::new(None),
+        }
+    }
+
+    /// Failures that mean the server could not be reached, as opposed to a rejected request
+    pub fn is_offline(error: &NetError) -> bool {
+        matches!(
+            error.status(),
+            NetResultStatus::ConnectionError
+                | NetResultStatus::SocketError
+                | NetResultStatus::TorNetError
+                | NetResultStatus::RequestTimeout
+        )
+    }
+
+    /// Only requests that can be replayed after a lost answer are queued
+    pub fn can_queue(request: &NetRequestHttp<'_>) -> bool {
+        NetRequestHttp::is_idempotent(request.method, request.headers.as_ref())
+    }
+
+    pub fn len(&self) -> u32 {
+        self.lock().len() as u32
+    }
+
+    /// Appends `request` and returns the number waiting; `start_replay` is called when no
+    /// replay is running
+    pub fn push(
+        &self,
+        request_id: u32,
+        request: &NetRequestHttp<'_>,
+        start_replay: impl FnOnce() -> JoinHandle<()>,
+    ) -> Result<u32, NetError> {
+        let mut entries = self.lock();
+        if entries.len() >= self.max_requests {
+            return Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message("offline queue is full")
+                .with_url(request.url));
+        }
+        entries.push_back(QueuedRequest {
+            request_id,
+            method: request.method.to_string(),
+            url: request.url.to_string(),
+            body: request.body.as_ref().map(|body| STANDARD.encode(body)),
+            headers: request
+                .headers
+                .iter()
+                .flatten()
+                .map(|header| NetHttpHeader::new(header.key.to_string(), header.value.to_string()))
+                .collect(),
+            encoding: request.encoding,
+            expires_at: HttpOfflineQueue::now() + self.ttl.as_secs(),
+        });
+        self.save(&entries);
+        self.resume_locked(&entries, start_replay);
+        Ok(entries.len() as u32)
+    }
+
+    /// Starts a replay for requests loaded from the file, unless one is running
+    pub fn resume(&self, start_replay: impl FnOnce() -> JoinHandle<()>) {
+        let entries = self.lock();
+        self.resume_locked(&entries, start_replay);
+    }
+
+    /// The oldest request, or `None` after clearing the replay slot when there is none; the
+    /// replay task ends on `None`
+    pub fn front(&self) -> Option<QueuedRequest> {
+        let entries = self.lock();
+        let front = entries.front().cloned();
+        if front.is_none() {
+            self.replay.lock().unwrap_or_else(|e| e.into_inner()).take();
+        }
+        front
+    }
+
+    /// Drops the oldest request once it was replayed or expired; returns the number left
+    pub fn pop(&self, request_id: u32) -> u32 {
+        let mut entries = self.lock();
+        if entries.front().map(|entry| entry.request_id) == Some(request_id) {
+            entries.pop_front();
+            self.save(&entries);
+        }
+        entries.len() as u32
+    }
+
+    /// Stops replaying; the queued requests stay in the file for the next transport
+    pub fn close(&self) {
+        if let Some(task) = self.replay.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            task.abort();
+        }
+    }
+
+    /// Both locks are held, so a replay ending on an empty queue can not miss a push
+    fn resume_locked(
+        &self,
+        entries: &VecDeque<QueuedRequest>,
+        start_replay: impl FnOnce() -> JoinHandle<()>,
+    ) {
+        let mut replay = self.replay.lock().unwrap_or_else(|e| e.into_inner());
+        if replay.is_none() && !entries.is_empty() {
+            *replay = Some(start_replay());
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<QueuedRequest>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Writes a temporary file and renames it, so a crash never leaves half a queue
+    fn save(&self, entries: &VecDeque<QueuedRequest>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let temporary = format!("{}.tmp", path);
+        let written = serde_json::to_vec(entries)
+            .map_err(std::io::Error::other)
+            .and_then(|data| std::fs::write(&temporary, data))
+            .and_then(|_| std::fs::rename(&temporary, path));
+        if let Err(e) = written {
+            debug!("offline queue {} not saved: {:?}", path, e);
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs())
+    }
+}
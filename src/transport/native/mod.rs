@@ -1,46 +1,86 @@
+#[cfg(feature = "grpc")]
 pub mod grpc;
 pub mod http;
+mod http_cache;
+mod http_coalesce;
+mod http_events;
+mod http_pool;
+mod http_queue;
+mod send_buffer;
 pub mod socket;
 
-use crate::types::{
-    DartCallback,
-    config::{NetConfig, NetConfigRequest},
-    error::NetResultStatus,
-    native::request::{
-        NetRequest, NetRequestGrpcStream, NetRequestGrpcUnary, NetRequestGrpcUnsubscribe,
-        NetRequestHttp, NetRequestSocketSend,
+use std::{path::Path, time::Duration};
+
+use crate::{
+    client::native::IStreamClient,
+    types::{
+        DartCallback,
+        config::{NetConfig, NetConfigRequest},
+        error::NetError,
+        native::request::{
+            NetRequest, NetRequestGrpcCredit, NetRequestGrpcHealth, NetRequestGrpcRebuild,
+            NetRequestGrpcStream, NetRequestGrpcUnary, NetRequestGrpcUnsubscribe, NetRequestHttp,
+            NetRequestSocketSend,
+        },
+        response::NetResponseKind,
     },
-    response::NetResponseKind,
 };
 
 #[async_trait::async_trait]
 pub trait ISocketTransport {
     /// Send raw bytes
-    async fn send<'a>(&self, data: &NetRequestSocketSend<'a>) -> Result<(), NetResultStatus>;
+    async fn send<'a>(&self, data: &NetRequestSocketSend<'a>) -> Result<(), NetError>;
 
-    /// Subscribe to incoming messages (Dart-style stream)
-    async fn subscribe(&self) -> Result<(), NetResultStatus>;
+    /// Subscribe to incoming messages (Dart-style stream); `id` names the subscription,
+    /// `None` is the default one
+    async fn subscribe(&self, id: Option<i32>) -> Result<(), NetError>;
+
+    async fn unsubscribe(&self, id: Option<i32>) -> Result<(), NetError>;
+
+    /// Bytes held by the subscription's buffer waiting for a complete message
+    async fn buffered_len(&self, id: Option<i32>) -> Result<u32, NetError>;
 
-    async fn unsubscribe(&self) -> Result<(), NetResultStatus>;
+    /// Discard a partially buffered message. Returns the number of dropped bytes
+    async fn flush_buffer(&self, id: Option<i32>) -> Result<u32, NetError>;
 }
 #[async_trait::async_trait]
 pub trait IGrpcTransport<'a> {
-    /// Send raw bytes
+    /// Send raw bytes; `deadline` is the request timeout, which the server is told of
     async fn unary(
         &self,
         data: &NetRequestGrpcUnary<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus>;
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseKind, NetError>;
 
     /// Subscribe to incoming messages (Dart-style stream)
     async fn stream(
         &self,
         data: &NetRequestGrpcStream<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus>;
+    ) -> Result<NetResponseKind, NetError>;
 
     async fn unsubscribe(
         &self,
         data: &NetRequestGrpcUnsubscribe,
-    ) -> Result<NetResponseKind, NetResultStatus>;
+    ) -> Result<NetResponseKind, NetError>;
+
+    /// `grpc.health.v1.Health/Check`, or `Watch` as a stream of serving statuses
+    async fn health(
+        &self,
+        data: &NetRequestGrpcHealth<'a>,
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseKind, NetError>;
+
+    /// Lets a stream opened with credits post more messages
+    async fn credit(&self, data: &NetRequestGrpcCredit) -> Result<NetResponseKind, NetError>;
+
+    /// The channel's `NetGrpcConnectivity`, answered without dialing
+    async fn state(&self) -> Result<NetResponseKind, NetError>;
+
+    /// The transport's `NetGrpcStats` so far
+    async fn stats(&self) -> Result<NetResponseKind, NetError>;
+
+    /// Answers a stream's `NetResponseStream::Rebuild` with the request it resubscribes with
+    async fn rebuild(&self, data: &NetRequestGrpcRebuild) -> Result<NetResponseKind, NetError>;
 }
 #[async_trait::async_trait]
 pub trait IHttpTransport {
@@ -48,7 +88,7 @@ pub trait IHttpTransport {
     async fn send<'a>(
         &self,
         request: &NetRequestHttp<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus>;
+    ) -> Result<NetResponseKind, NetError>;
 }
 #[async_trait::async_trait]
 pub trait Transport {
@@ -56,14 +96,32 @@ pub trait Transport {
         config: NetConfigRequest,
         callback: DartCallback,
         transport_id: u32,
-    ) -> Result<Self, NetResultStatus>
+    ) -> Result<Self, NetError>
     where
         Self: Sized;
     async fn do_request<'a>(
         &self,
         request: NetRequest<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus>;
+    ) -> Result<NetResponseKind, NetError>;
 
+    /// Opens the connection ahead of the first request; HTTP only prepares its pool
+    async fn connect(&self) -> Result<(), NetError>;
     async fn close(&self);
     fn get_config(&self) -> &NetConfig;
+
+    /// Underlying byte stream, for transports a splice can join
+    fn stream_client(&self) -> Option<&dyn IStreamClient> {
+        None
+    }
+
+    /// Writes what the transport keeps between requests to `dir`, for `restore_state` after
+    /// a restart; transports with nothing worth keeping write nothing
+    fn save_state(&self, _dir: &Path) -> Result<(), NetError> {
+        Ok(())
+    }
+
+    /// Takes up what `save_state` left in `dir`
+    fn restore_state(&self, _dir: &Path) -> Result<(), NetError> {
+        Ok(())
+    }
 }
@@ -0,0 +1,144 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    sync::{Notify, futures::Notified},
+    task::JoinHandle,
+};
+
+use crate::types::{
+    config::NetStreamSendBuffer,
+    error::{NetError, NetResultStatus},
+};
+
+const DEFAULT_MAX_MESSAGES: usize = 64;
+
+const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// A message waiting in the send buffer
+#[derive(Clone)]
+pub(crate) struct BufferedMessage {
+    pub request_id: u32,
+    pub data: Vec<u8>,
+    expires_at: Instant,
+}
+
+impl BufferedMessage {
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+}
+
+/// Ordered store behind `NetStreamReconnect::send_buffer`, kept in memory only. Writing the
+/// messages out is left to the transport, which owns the client.
+pub(crate) struct SocketSendBuffer {
+    messages: Mutex<VecDeque<BufferedMessage>>,
+    max_messages: usize,
+    ttl: Duration,
+    /// Running flush task, cleared by the task itself once the buffer is empty
+    flush: Mutex<Option<JoinHandle<()>>>,
+    /// Set by a subscription that reconnected, so the flush does not wait out its delay
+    reconnected: Notify,
+}
+
+impl SocketSendBuffer {
+    pub fn new(config: &NetStreamSendBuffer) -> Self {
+        Self {
+            messages: Mutex::new(VecDeque::new()),
+            max_messages: match config.max_messages {
+                0 => DEFAULT_MAX_MESSAGES,
+                max => max as usize,
+            },
+            ttl: match config.ttl {
+                0 => DEFAULT_TTL,
+                ttl => Duration::from_secs(ttl.into()),
+            },
+            flush: Mutex::new(None),
+            reconnected: Notify::new(),
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.lock().len() as u32
+    }
+
+    /// Appends `data` and returns the number waiting; `start_flush` is called when no flush
+    /// is running
+    pub fn push(
+        &self,
+        request_id: u32,
+        data: &[u8],
+        start_flush: impl FnOnce() -> JoinHandle<()>,
+    ) -> Result<u32, NetError> {
+        let mut messages = self.lock();
+        if messages.len() >= self.max_messages {
+            return Err(NetError::new(NetResultStatus::ConnectionError)
+                .with_message("socket send buffer is full"));
+        }
+        messages.push_back(BufferedMessage {
+            request_id,
+            data: data.to_vec(),
+            expires_at: Instant::now() + self.ttl,
+        });
+        // both locks are held, so a flush ending on an empty buffer can not miss a push
+        let mut flush = self.flush.lock().unwrap_or_else(|e| e.into_inner());
+        if flush.is_none() {
+            *flush = Some(start_flush());
+        }
+        Ok(messages.len() as u32)
+    }
+
+    /// The oldest message, or `None` after clearing the flush slot when there is none; the
+    /// flush task ends on `None`
+    pub fn front(&self) -> Option<BufferedMessage> {
+        let messages = self.lock();
+        let front = messages.front().cloned();
+        if front.is_none() {
+            self.flush.lock().unwrap_or_else(|e| e.into_inner()).take();
+        }
+        front
+    }
+
+    /// Drops the oldest message once it was written, failed or expired; returns the number
+    /// left
+    pub fn pop(&self, request_id: u32) -> u32 {
+        let mut messages = self.lock();
+        if messages.front().map(|message| message.request_id) == Some(request_id) {
+            messages.pop_front();
+        }
+        messages.len() as u32
+    }
+
+    /// Empties the buffer and clears the flush slot, returning the ids of what was waiting
+    pub fn drain(&self) -> Vec<u32> {
+        let mut messages = self.lock();
+        self.flush.lock().unwrap_or_else(|e| e.into_inner()).take();
+        messages
+            .drain(..)
+            .map(|message| message.request_id)
+            .collect()
+    }
+
+    /// Stops flushing and returns the ids of the messages left unsent
+    pub fn close(&self) -> Vec<u32> {
+        if let Some(task) = self.flush.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            task.abort();
+        }
+        self.drain()
+    }
+
+    pub fn wake(&self) {
+        self.reconnected.notify_one();
+    }
+
+    pub fn reconnected(&self) -> Notified<'_> {
+        self.reconnected.notified()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<BufferedMessage>> {
+        self.messages.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -1,50 +1,285 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+#[cfg(feature = "tor")]
 use arti_client::DataStream;
 use tokio::{
     net::TcpStream,
-    sync::{
-        Mutex,
-        broadcast::{self, Receiver},
-    },
+    sync::{Mutex, broadcast},
+    task::JoinHandle,
 };
+use log::debug;
 use tokio_rustls::client::TlsStream;
 
+#[cfg(feature = "websocket")]
+use crate::client::websocket::{native::WsStreamClient, shared::SharedStreamClient};
+#[cfg(feature = "otel")]
+use crate::utils::otel::OtelSpan;
 use crate::{
     client::{
-        native::IStreamClient, raw::native::RawStreamClient, websocket::native::WsStreamClient,
+        failover::FailoverStreamClient, native::IStreamClient, raw::native::RawStreamClient,
+    },
+    stream::{CustomStream, RouteWatch, Socks5Stream},
+    transport::native::{
+        ISocketTransport, Transport, http_queue::HttpOfflineQueue, send_buffer::SocketSendBuffer,
     },
-    transport::native::{ISocketTransport, Transport},
     types::{
         DartCallback,
-        config::{NetConfig, NetConfigRequest, NetMode, NetProtocol},
-        error::NetResultStatus,
+        config::{NetConfig, NetConfigRequest, NetMode, NetProtocol, NetStreamReconnect},
+        error::{NetError, NetResultStatus},
         native::request::{NetRequest, NetRequestSocket, NetRequestSocketSend},
         response::{
-            NetResponseKind, NetResponseSocketOk, NetResponseStream, NetResponseStreamData,
-            NetResponseStreamError,
+            NetResponseKind, NetResponseSocketBuffer, NetResponseSocketOk, NetResponseSocketSend,
+            NetResponseStream, NetResponseStreamData, NetResponseStreamError,
+            NetResponseStreamReconnected, NetSocketSendState,
         },
     },
-    utils::buffer::StreamBuffer,
+    utils::{buffer::StreamBuffer, subscribers::NetSubscription},
 };
 
+const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+const DEFAULT_MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+type Incoming = NetSubscription<Result<Option<Vec<u8>>, NetError>>;
+
+/// Waits between the attempts of a `NetStreamReconnect`
+pub(crate) struct Backoff {
+    delay: Duration,
+    max_delay: Duration,
+    pub attempts: u32,
+    max_attempts: u32,
+}
+
+impl Backoff {
+    pub fn new(policy: &NetStreamReconnect) -> Self {
+        Self {
+            delay: match policy.delay_ms {
+                0 => DEFAULT_RECONNECT_DELAY,
+                ms => Duration::from_millis(ms.into()),
+            },
+            max_delay: match policy.max_delay_ms {
+                0 => DEFAULT_MAX_RECONNECT_DELAY,
+                ms => Duration::from_millis(ms.into()),
+            },
+            attempts: 0,
+            max_attempts: policy.max_attempts,
+        }
+    }
+
+    /// Wait before the next attempt, `None` once they ran out
+    pub fn next(&mut self) -> Option<Duration> {
+        if self.max_attempts != 0 && self.attempts >= self.max_attempts {
+            return None;
+        }
+        self.attempts += 1;
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(self.max_delay);
+        Some(delay)
+    }
+}
+
+/// Task posting one subscription to Dart, with the buffer its messages are framed in
+struct SocketListener {
+    task: JoinHandle<()>,
+    buffer: Arc<Mutex<StreamBuffer>>,
+    /// Cleared before the connection is closed on purpose, so the listener does not
+    /// reconnect it
+    active: Arc<AtomicBool>,
+}
+
 pub struct SocketTransport {
-    stream: Box<dyn IStreamClient>,
+    stream: Arc<dyn IStreamClient>,
     callback: DartCallback,
-    rx: Mutex<Option<Receiver<Result<Option<Vec<u8>>, NetResultStatus>>>>,
+    /// Subscriptions by the id Dart gave them, `None` for the default one
+    listeners: Mutex<HashMap<Option<i32>, SocketListener>>,
+    /// Buffer of the default subscription, kept between its runs
+    buffer: Arc<Mutex<StreamBuffer>>,
+    send_buffer: Option<Arc<SocketSendBuffer>>,
+    /// Default route changes that closed the connection, by `NetConfig::network_reconnect`
+    route_changes: Arc<AtomicU64>,
+    route_watch: Option<JoinHandle<()>>,
     _transport_id: u32,
 }
-#[async_trait::async_trait]
-impl Transport for SocketTransport {
-    fn create(
-        config: NetConfigRequest,
+impl SocketTransport {
+    /// Buffer of subscription `id`; the default one has its buffer before it subscribed
+    async fn subscription_buffer(
+        &self,
+        id: Option<i32>,
+    ) -> Result<Arc<Mutex<StreamBuffer>>, NetError> {
+        match (id, self.listeners.lock().await.get(&id)) {
+            (_, Some(listener)) => Ok(Arc::clone(&listener.buffer)),
+            (None, None) => Ok(Arc::clone(&self.buffer)),
+            (Some(id), None) => Err(NetError::new(NetResultStatus::InvalidRequestParameters)
+                .with_message(format!("no socket subscription {}", id))),
+        }
+    }
+
+    /// Subscribes again after the connection dropped, waiting longer after each failed
+    /// attempt; `None` once the attempts ran out or the listener was stopped meanwhile
+    async fn resubscribe(
+        stream: &dyn IStreamClient,
+        policy: NetStreamReconnect,
+        active: &AtomicBool,
+    ) -> Option<Incoming> {
+        let mut backoff = Backoff::new(&policy);
+        while let Some(delay) = backoff.next() {
+            tokio::time::sleep(delay).await;
+            if !active.load(Ordering::Acquire) {
+                return None;
+            }
+            match stream.subscribe().await {
+                // a close that raced the attempt wins
+                Ok(_) if !active.load(Ordering::Acquire) => {
+                    stream.close().await;
+                    return None;
+                }
+                Ok(incoming) => return Some(incoming),
+                Err(e) => debug!(
+                    "socket reconnect attempt {} failed: {:?}",
+                    backoff.attempts, e
+                ),
+            }
+        }
+        None
+    }
+
+    /// Closes the connection whenever the default route moves; the listeners take it for
+    /// a drop and subscribe again over the new route
+    fn watch_route(
+        mut route: RouteWatch,
+        stream: Arc<dyn IStreamClient>,
+        route_changes: Arc<AtomicU64>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                route.changed().await;
+                debug!("default route changed, reconnecting socket");
+                route_changes.fetch_add(1, Ordering::AcqRel);
+                stream.close().await;
+            }
+        })
+    }
+
+    /// Writes the send buffer in order, reconnecting by the transport's policy while the
+    /// connection is down. Once the attempts ran out, what is left fails.
+    async fn flush(
+        buffer: Arc<SocketSendBuffer>,
+        stream: Arc<dyn IStreamClient>,
+        policy: NetStreamReconnect,
         callback: DartCallback,
-        transport_id: u32,
-    ) -> Result<Self, NetResultStatus> {
-        let config = config
-            .to_protocol_config(NetProtocol::Socket)
-            .or_else(|_| config.to_protocol_config(NetProtocol::WebSocket))?;
+    ) {
+        let post = |request_id, state, pending, error| {
+            callback(NetResponseKind::SocketSend(NetResponseSocketSend::new(
+                request_id, state, pending, error,
+            )))
+        };
+        let mut backoff = Backoff::new(&policy);
+        loop {
+            // `front` clears the flush slot when it finds the buffer empty
+            loop {
+                let Some(message) = buffer.front() else {
+                    return;
+                };
+                if message.expired() {
+                    let pending = buffer.pop(message.request_id);
+                    post(
+                        message.request_id,
+                        NetSocketSendState::Expired,
+                        pending,
+                        None,
+                    );
+                    continue;
+                }
+                let (state, error) = match stream.send(&message.data).await {
+                    Err(e) if HttpOfflineQueue::is_offline(&e) => break,
+                    Ok(()) => (NetSocketSendState::Delivered, None),
+                    Err(e) => (NetSocketSendState::Failed, Some(e.to_string())),
+                };
+                let pending = buffer.pop(message.request_id);
+                post(message.request_id, state, pending, error);
+                backoff = Backoff::new(&policy);
+            }
+            let Some(delay) = backoff.next() else {
+                let error = "socket did not reconnect".to_string();
+                for request_id in buffer.drain() {
+                    post(
+                        request_id,
+                        NetSocketSendState::Failed,
+                        0,
+                        Some(error.clone()),
+                    );
+                }
+                return;
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = buffer.reconnected() => {}
+            }
+        }
+    }
+
+    fn start_flush(
+        &self,
+        buffer: &Arc<SocketSendBuffer>,
+    ) -> impl FnOnce() -> JoinHandle<()> + use<> {
+        let (stream, callback) = (Arc::clone(&self.stream), Arc::clone(&self.callback));
+        let buffer = Arc::clone(buffer);
+        let policy = self
+            .stream
+            .get_config()
+            .stream_reconnect
+            .unwrap_or_default();
+        move || tokio::spawn(SocketTransport::flush(buffer, stream, policy, callback))
+    }
+
+    /// Writes `data` right away unless the connection is down or older messages still wait,
+    /// in which case it joins the send buffer
+    async fn send_buffered(
+        &self,
+        buffer: &Arc<SocketSendBuffer>,
+        request_id: u32,
+        data: &[u8],
+    ) -> Result<NetResponseKind, NetError> {
+        // while messages wait, new ones queue behind them to keep the order
+        if buffer.len() == 0 {
+            match self.stream.send(data).await {
+                Err(e) if HttpOfflineQueue::is_offline(&e) => {}
+                result => return result.map(|_| NetResponseKind::Socket(NetResponseSocketOk)),
+            }
+        }
+        let pending = buffer.push(request_id, data, self.start_flush(buffer))?;
+        Ok(NetResponseKind::SocketSend(NetResponseSocketSend::new(
+            request_id,
+            NetSocketSendState::Queued,
+            pending,
+            None,
+        )))
+    }
+
+    pub(crate) fn create_client(config: NetConfig) -> Result<Box<dyn IStreamClient>, NetError> {
+        #[cfg(feature = "websocket")]
+        if config.protocol == NetProtocol::WebSocket && config.share_connection {
+            return Ok(Box::new(SharedStreamClient::join(
+                config,
+                SocketTransport::create_connection,
+            )?));
+        }
+        SocketTransport::create_connection(config)
+    }
 
+    /// Client with a connection of its own
+    fn create_connection(config: NetConfig) -> Result<Box<dyn IStreamClient>, NetError> {
         let stream: Box<dyn IStreamClient> = match config.protocol {
+            #[cfg(feature = "websocket")]
             NetProtocol::WebSocket => match (config.addr.is_tls, &config.mode) {
+                #[cfg(feature = "tor")]
                 (true, NetMode::Tor) => {
                     Box::new(WsStreamClient::<TlsStream<DataStream>>::default(config)?)
                 }
@@ -53,13 +288,29 @@ impl Transport for SocketTransport {
                     Box::new(WsStreamClient::<TlsStream<TcpStream>>::default(config)?)
                 }
 
+                #[cfg(feature = "tor")]
                 (false, NetMode::Tor) => Box::new(WsStreamClient::<DataStream>::default(config)?),
 
                 (false, NetMode::Clearnet) => {
                     Box::new(WsStreamClient::<TcpStream>::default(config)?)
                 }
+                (true, NetMode::Custom(_)) => {
+                    Box::new(WsStreamClient::<TlsStream<CustomStream>>::default(config)?)
+                }
+                (false, NetMode::Custom(_)) => {
+                    Box::new(WsStreamClient::<CustomStream>::default(config)?)
+                }
+                (true, NetMode::Socks5(_)) => {
+                    Box::new(WsStreamClient::<TlsStream<Socks5Stream>>::default(config)?)
+                }
+                (false, NetMode::Socks5(_)) => {
+                    Box::new(WsStreamClient::<Socks5Stream>::default(config)?)
+                }
+                #[cfg(not(feature = "tor"))]
+                (_, NetMode::Tor) => return Err(NetError::feature_disabled("tor")),
             },
             NetProtocol::Socket => match (config.addr.is_tls, &config.mode) {
+                #[cfg(feature = "tor")]
                 (true, NetMode::Tor) => {
                     Box::new(RawStreamClient::<TlsStream<DataStream>>::default(config)?)
                 }
@@ -68,113 +319,296 @@ impl Transport for SocketTransport {
                     Box::new(RawStreamClient::<TlsStream<TcpStream>>::default(config)?)
                 }
 
+                #[cfg(feature = "tor")]
                 (false, NetMode::Tor) => Box::new(RawStreamClient::<DataStream>::default(config)?),
 
                 (false, NetMode::Clearnet) => {
                     Box::new(RawStreamClient::<TcpStream>::default(config)?)
                 }
+                (true, NetMode::Custom(_)) => {
+                    Box::new(RawStreamClient::<TlsStream<CustomStream>>::default(config)?)
+                }
+                (false, NetMode::Custom(_)) => {
+                    Box::new(RawStreamClient::<CustomStream>::default(config)?)
+                }
+                (true, NetMode::Socks5(_)) => {
+                    Box::new(RawStreamClient::<TlsStream<Socks5Stream>>::default(config)?)
+                }
+                (false, NetMode::Socks5(_)) => {
+                    Box::new(RawStreamClient::<Socks5Stream>::default(config)?)
+                }
+                #[cfg(not(feature = "tor"))]
+                (_, NetMode::Tor) => return Err(NetError::feature_disabled("tor")),
             },
-            _ => return Err(NetResultStatus::InvalidConfigParameters),
+            #[cfg(not(feature = "websocket"))]
+            NetProtocol::WebSocket => return Err(NetError::feature_disabled("websocket")),
+            _ => return Err(NetResultStatus::InvalidConfigParameters.into()),
         };
+        Ok(stream)
+    }
+}
+#[async_trait::async_trait]
+impl Transport for SocketTransport {
+    fn create(
+        config: NetConfigRequest,
+        callback: DartCallback,
+        transport_id: u32,
+    ) -> Result<Self, NetError> {
+        let fallbacks = config.to_fallback_configs()?;
+        let config = config
+            .to_protocol_config(NetProtocol::Socket)
+            .or_else(|_| config.to_protocol_config(NetProtocol::WebSocket))?;
+        let buffer = Arc::new(Mutex::new(StreamBuffer::new(config.encoding)));
+        let send_buffer = config
+            .stream_reconnect
+            .and_then(|reconnect| reconnect.send_buffer)
+            .map(|buffer| Arc::new(SocketSendBuffer::new(&buffer)));
+        let network_reconnect = config.network_reconnect;
+
+        let mut stream = SocketTransport::create_client(config)?;
+        if !fallbacks.is_empty() {
+            stream = Box::new(FailoverStreamClient::new(
+                stream,
+                fallbacks,
+                SocketTransport::create_client,
+                callback.clone(),
+            )?);
+        }
+
+        let stream: Arc<dyn IStreamClient> = Arc::from(stream);
+        let route_changes = Arc::new(AtomicU64::new(0));
+        let route_watch = (network_reconnect && tokio::runtime::Handle::try_current().is_ok())
+            .then(|| {
+                SocketTransport::watch_route(
+                    RouteWatch::start(),
+                    Arc::clone(&stream),
+                    Arc::clone(&route_changes),
+                )
+            });
 
         Ok(Self {
-            stream: stream,
+            stream,
             callback,
-            rx: Mutex::new(None),
+            listeners: Mutex::new(HashMap::new()),
+            buffer,
+            send_buffer,
+            route_changes,
+            route_watch,
             _transport_id: transport_id,
         })
     }
     async fn do_request<'a>(
         &self,
         request: NetRequest<'a>,
-    ) -> Result<NetResponseKind, NetResultStatus> {
+    ) -> Result<NetResponseKind, NetError> {
         let socket_requset = request.to_socket_request()?;
         let _ = match socket_requset {
-            NetRequestSocket::Subscribe => self.subscribe().await?,
-            NetRequestSocket::Unsubscribe => self.unsubscribe().await?,
-            NetRequestSocket::Send(socket_request_send) => self.send(socket_request_send).await?,
+            NetRequestSocket::Subscribe(id) => self.subscribe(*id).await?,
+            NetRequestSocket::Unsubscribe(id) => self.unsubscribe(*id).await?,
+            NetRequestSocket::Send(socket_request_send) => match &self.send_buffer {
+                Some(buffer) => {
+                    return self
+                        .send_buffered(buffer, request.id, socket_request_send.data)
+                        .await;
+                }
+                None => self.send(socket_request_send).await?,
+            },
+            NetRequestSocket::BufferStatus(id) => {
+                let buffered = self.buffered_len(*id).await?;
+                return Ok(NetResponseKind::SocketBuffer(NetResponseSocketBuffer::new(
+                    buffered,
+                )));
+            }
+            NetRequestSocket::FlushBuffer(id) => {
+                let flushed = self.flush_buffer(*id).await?;
+                return Ok(NetResponseKind::SocketBuffer(NetResponseSocketBuffer::new(
+                    flushed,
+                )));
+            }
         };
         Ok(NetResponseKind::Socket(NetResponseSocketOk))
     }
 
+    async fn connect(&self) -> Result<(), NetError> {
+        self.stream.connect().await
+    }
+
     async fn close(&self) {
-        self.stream.close().await;
-        let mut guard = self.rx.lock().await;
-        if let Some(rx) = guard.take() {
-            drop(rx);
+        if let Some(watch) = &self.route_watch {
+            watch.abort();
+        }
+        if let Some(buffer) = &self.send_buffer {
+            for request_id in buffer.close() {
+                (self.callback)(NetResponseKind::SocketSend(NetResponseSocketSend::new(
+                    request_id,
+                    NetSocketSendState::Failed,
+                    0,
+                    Some("transport closed".to_string()),
+                )));
+            }
+        }
+        let mut listeners = self.listeners.lock().await;
+        for listener in listeners.values() {
+            listener.active.store(false, Ordering::Release);
         }
-        *guard = None;
+        self.stream.close().await;
+        listeners.clear();
     }
 
     fn get_config(&self) -> &NetConfig {
         self.stream.get_config()
     }
+
+    fn stream_client(&self) -> Option<&dyn IStreamClient> {
+        Some(self.stream.as_ref())
+    }
 }
 #[async_trait::async_trait]
 impl ISocketTransport for SocketTransport {
-    async fn send<'a>(&self, data: &NetRequestSocketSend<'a>) -> Result<(), NetResultStatus> {
+    async fn send<'a>(&self, data: &NetRequestSocketSend<'a>) -> Result<(), NetError> {
         self.stream.send(&data.data).await
     }
 
-    async fn subscribe(&self) -> Result<(), NetResultStatus> {
-        let mut rx = self.stream.subscribe().await?;
+    async fn subscribe(&self, id: Option<i32>) -> Result<(), NetError> {
+        let mut listeners = self.listeners.lock().await;
+        if listeners
+            .get(&id)
+            .is_some_and(|listener| !listener.task.is_finished())
         {
-            let mut guard = self.rx.lock().await;
-            if guard.is_some() {
-                return Ok(());
-            }
-            *guard = Some(rx.resubscribe()); // store a clone in the struct
+            return Ok(());
         }
+        let mut rx = self.stream.subscribe().await?;
         let callback = self.callback.clone();
-        let encoding = self.get_config().encoding;
-        tokio::spawn(async move {
-            let mut buffer = StreamBuffer::new(encoding);
-            println!("craete buffer {:#?}", encoding);
+        let buffer = match id {
+            Some(_) => Arc::new(Mutex::new(StreamBuffer::new(
+                self.stream.get_config().encoding,
+            ))),
+            None => Arc::clone(&self.buffer),
+        };
+        buffer.lock().await.flush();
+        let listener_buffer = Arc::clone(&buffer);
+        let active = Arc::new(AtomicBool::new(true));
+        let listener_active = Arc::clone(&active);
+        let stream = Arc::clone(&self.stream);
+        let reconnect = self.stream.get_config().stream_reconnect;
+        let idle_timeout = self.stream.get_config().stream_idle_timeout;
+        let send_buffer = self.send_buffer.clone();
+        let route_changes = Arc::clone(&self.route_changes);
+        #[cfg(feature = "otel")]
+        let mut span = OtelSpan::stream(None, self.stream.get_config(), id);
+        let task = tokio::spawn(async move {
+            // set once an idle connection was dropped, which reconnects even without a policy,
+            // as does one closed for a new default route
+            let mut idle_dropped = false;
+            let mut route = route_changes.load(Ordering::Acquire);
             loop {
-                match rx.recv().await {
-                    Ok(msg) => match msg {
-                        Ok(data) => match data {
-                            Some(data) => {
-                                println!("data send {:#?}", data.len());
-                                if let Some(parsed) = buffer.add(data) {
-                                    println!("buffer success ${:#?}", parsed.len());
-                                    callback(NetResponseKind::Stream(NetResponseStream::Data(
-                                        NetResponseStreamData::new(None, parsed),
-                                    )));
-                                }
-                            }
-                            None => {
-                                callback(NetResponseKind::Stream(NetResponseStream::Close(None)));
-                                break;
-                            }
-                        },
-                        Err(err) => {
-                            callback(NetResponseKind::Stream(NetResponseStream::Error(
-                                NetResponseStreamError::new(None, err),
+                let received = rx
+                    .recv_within(idle_timeout.map(|idle| idle.duration()))
+                    .await;
+                let Some(received) = received else {
+                    #[cfg(feature = "otel")]
+                    span.count("net_sdk.stream.idle");
+                    callback(NetResponseKind::Stream(NetResponseStream::Idle(id)));
+                    if idle_timeout.is_some_and(|idle| idle.reconnect) {
+                        // the end this causes takes the reconnect path below
+                        idle_dropped = true;
+                        stream.close().await;
+                    }
+                    continue;
+                };
+                let end = match received {
+                    Ok(Ok(Some(data))) => {
+                        println!("data send {:#?}", data.len());
+                        let parsed = buffer.lock().await.add(data);
+                        if let Some(parsed) = parsed {
+                            println!("buffer success ${:#?}", parsed.len());
+                            #[cfg(feature = "otel")]
+                            span.count("net_sdk.stream.messages");
+                            callback(NetResponseKind::Stream(NetResponseStream::Data(
+                                NetResponseStreamData::new(id, parsed.into()),
                             )));
-                            break;
                         }
-                    },
-                    Err(broadcast::error::RecvError::Closed) => {
-                        callback(NetResponseKind::Stream(NetResponseStream::Close(None)));
-
-                        break;
+                        continue;
+                    }
+                    Ok(Ok(None)) | Err(broadcast::error::RecvError::Closed) => {
+                        NetResponseStream::Close(id)
                     }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Ok(Err(err)) => NetResponseStream::Error(NetResponseStreamError::new(id, err)),
+                    Err(broadcast::error::RecvError::Lagged(lost)) => {
+                        callback(NetResponseKind::Stream(NetResponseStream::Error(
+                            NetResponseStreamError::new(id, NetError::stream_overflow(lost)),
+                        )));
+                        continue;
+                    }
+                };
+                let rerouted = route_changes.load(Ordering::Acquire) != route;
+                let policy =
+                    reconnect.or((idle_dropped || rerouted).then(NetStreamReconnect::default));
+                idle_dropped = false;
+                route = route_changes.load(Ordering::Acquire);
+                let resubscribed = match policy {
+                    Some(policy) if active.load(Ordering::Acquire) => {
+                        SocketTransport::resubscribe(stream.as_ref(), policy, &active).await
+                    }
+                    _ => None,
+                };
+                let Some(incoming) = resubscribed else {
+                    let end = NetResponseKind::Stream(end);
+                    #[cfg(feature = "otel")]
+                    span.response(&end);
+                    callback(end);
+                    break;
+                };
+                rx = incoming;
+                // what was left of a message cut off by the drop can not be completed
+                let flushed = buffer.lock().await.flush();
+                #[cfg(feature = "otel")]
+                span.count("net_sdk.stream.reconnects");
+                callback(NetResponseKind::Stream(NetResponseStream::Reconnected(
+                    NetResponseStreamReconnected::new(id, flushed as u32),
+                )));
+                if let Some(send_buffer) = &send_buffer {
+                    send_buffer.wake();
                 }
             }
         });
+        listeners.insert(
+            id,
+            SocketListener {
+                task,
+                buffer: listener_buffer,
+                active: listener_active,
+            },
+        );
         Ok(())
     }
 
-    async fn unsubscribe(&self) -> Result<(), NetResultStatus> {
-        self.stream.close().await;
-        let mut guard = self.rx.lock().await;
-        if let Some(rx) = guard.take() {
-            drop(rx);
+    async fn unsubscribe(&self, id: Option<i32>) -> Result<(), NetError> {
+        let mut listeners = self.listeners.lock().await;
+        match (id, listeners.remove(&id)) {
+            // the listener posts the close once the connection is down; closing it ends
+            // every subscription, none of them reconnects
+            (None, default) => {
+                for listener in default.iter().chain(listeners.values()) {
+                    listener.active.store(false, Ordering::Release);
+                }
+                self.stream.close().await;
+            }
+            (Some(_), Some(listener)) => listener.task.abort(),
+            (Some(_), None) => {}
         }
-        *guard = None;
-
         Ok(())
     }
+
+    async fn buffered_len(&self, id: Option<i32>) -> Result<u32, NetError> {
+        let buffer = self.subscription_buffer(id).await?;
+        Ok(buffer.lock().await.buffered_len() as u32)
+    }
+
+    async fn flush_buffer(&self, id: Option<i32>) -> Result<u32, NetError> {
+        let buffer = self.subscription_buffer(id).await?;
+        let flushed = buffer.lock().await.flush();
+        debug!("Socket buffer flushed: {:#?} bytes", flushed);
+        Ok(flushed as u32)
+    }
 }
@@ -4,26 +4,32 @@ use std::{
         Arc,
         atomic::{AtomicI32, Ordering},
     },
+    time::Duration,
 };
 
-use tokio::sync::{Mutex, broadcast};
+use bytes::Bytes;
+use tokio::sync::{Mutex, Semaphore, broadcast, oneshot};
 use wasm_bindgen_futures::spawn_local;
 
 use crate::{
     client::{
         grpc::wasm::GrpcClient,
-        wasm::{GrpcStreamHandle, IGrpcClient},
+        wasm::IGrpcClient,
     },
     transport::wasm::{IGrpcTransport, Transport},
     types::{
         DartCallback,
         config::{NetConfig, NetConfigRequest, NetProtocol},
-        error::NetResultStatus,
+        error::{NetError, NetResultStatus},
+        grpc_health::{NetGrpcHealth, NetGrpcServingStatus},
+        grpc_json::NetGrpcJson,
+        grpc_stats::GrpcStats,
         request::{
-            NetRequest, NetRequestGrpcStream, NetRequestGrpcUnary, NetRequestGrpcUnsubscribe,
+            NetRequest, NetRequestGrpcCredit, NetRequestGrpcHealth, NetRequestGrpcStream,
+            NetRequestGrpcUnary, NetRequestGrpcUnsubscribe,
         },
         response::{
-            NetResponseGrpc, NetResponseGrpcSubscribe, NetResponseGrpcUnary,
+            NetResponseGrpc, NetResponseGrpcHealth, NetResponseGrpcState, NetResponseGrpcSubscribe,
             NetResponseGrpcUnsubscribe, NetResponseKind, NetResponseStream, NetResponseStreamData,
             NetResponseStreamError,
         },
@@ -33,10 +39,164 @@ use crate::{
 pub struct GrpcTransport {
     stream: Box<dyn IGrpcClient>,
     callback: DartCallback,
-    listeners: Arc<Mutex<HashMap<i32, GrpcStreamHandle>>>,
+    /// Cancel senders of the running streams
+    listeners: Arc<Mutex<HashMap<i32, oneshot::Sender<()>>>>,
+    /// Messages the streams opened with `NetRequestGrpcStream::credits` may still post
+    credits: Arc<Mutex<HashMap<i32, Arc<Semaphore>>>>,
+    /// Transcodes the calls from and to JSON when the config has a descriptor set
+    json: Option<NetGrpcJson>,
+    stats: Arc<GrpcStats>,
     _transport_id: u32,
     next_stream_id: AtomicI32,
 }
+impl GrpcTransport {
+    /// What Dart is posted of message `b`: its serving status alone for a health watch, its
+    /// JSON when the calls are transcoded
+    fn message(
+        b: Vec<u8>,
+        health: bool,
+        json: &Option<NetGrpcJson>,
+        method: &str,
+    ) -> Result<Vec<u8>, NetError> {
+        if health {
+            let status = NetGrpcHealth::status(&b).unwrap_or(NetGrpcServingStatus::Unknown);
+            return Ok(vec![status as u8]);
+        }
+        match json {
+            Some(json) => json.decode(method, &b),
+            None => Ok(b),
+        }
+    }
+
+    /// Opens the stream and forwards its messages by a new stream id; `health` streams
+    /// forward the status of each message
+    async fn subscribe(
+        &self,
+        data: &NetRequestGrpcStream,
+        health: bool,
+    ) -> Result<NetResponseKind, NetResultStatus> {
+        // a health watch encodes its own request
+        let json = self.json.clone().filter(|_| !health);
+        let request = match &json {
+            Some(json) => match json.encode(data.method(), data.data()) {
+                Ok(request) => request,
+                Err(e) => {
+                    self.stats.failed(&e);
+                    return Ok(NetResponseKind::ResponseError(e));
+                }
+            },
+            None => Bytes::copy_from_slice(data.data()),
+        };
+        let method = data.method().to_string();
+        let handle = match self
+            .stream
+            .stream(
+                &request,
+                data.method(),
+                data.metadata(),
+                (data.deadline() != 0).then(|| Duration::from_secs(data.deadline().into())),
+            )
+            .await
+        {
+            Ok(handle) => handle,
+            Err(e) => {
+                self.stats.failed(&e);
+                return Ok(NetResponseKind::ResponseError(e));
+            }
+        };
+        self.stats.stream_opened();
+        self.stats.sent(request.len());
+        let stats = Arc::clone(&self.stats);
+        let id = self.next_stream_id.fetch_add(1, Ordering::Relaxed);
+        let callback = self.callback.clone();
+        let mut rx = handle.rx;
+        let listeners = Arc::clone(&self.listeners);
+        let credits = Arc::clone(&self.credits);
+        let credit =
+            (data.credits() != 0).then(|| Arc::new(Semaphore::new(data.credits() as usize)));
+        if let Some(credit) = &credit {
+            credits.lock().await.insert(id, Arc::clone(credit));
+        }
+        spawn_local(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(msg) => match msg {
+                        Ok(data) => match data {
+                            Some(b) => {
+                                stats.received(b.len());
+                                // waits for Dart to grant more, the stream queue fills meanwhile
+                                if let Some(credit) = &credit {
+                                    match credit.acquire().await {
+                                        Ok(permit) => permit.forget(),
+                                        // unsubscribed while waiting, the call goes away next
+                                        Err(_) => continue,
+                                    }
+                                }
+                                let b = match GrpcTransport::message(b, health, &json, &method) {
+                                    Ok(b) => b,
+                                    Err(err) => {
+                                        stats.failed(&err);
+                                        callback(NetResponseKind::Stream(
+                                            NetResponseStream::Error(NetResponseStreamError::new(
+                                                Some(id),
+                                                err,
+                                            )),
+                                        ));
+                                        break;
+                                    }
+                                };
+                                callback(NetResponseKind::Stream(NetResponseStream::Data(
+                                    NetResponseStreamData::new(Some(id), b.into()),
+                                )));
+                            }
+                            None => {
+                                callback(NetResponseKind::Stream(NetResponseStream::Close(Some(
+                                    id,
+                                ))));
+                                break;
+                            }
+                        },
+                        Err(err) => {
+                            stats.failed(&err);
+                            callback(NetResponseKind::Stream(NetResponseStream::Error(
+                                NetResponseStreamError::new(Some(id), err),
+                            )));
+                            break;
+                        }
+                    },
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // the call ends without a last message when it was cancelled
+                        let event = match listeners.lock().await.contains_key(&id) {
+                            true => NetResponseStream::Close(Some(id)),
+                            false => NetResponseStream::Cancelled(Some(id)),
+                        };
+                        callback(NetResponseKind::Stream(event));
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(lost)) => {
+                        callback(NetResponseKind::Stream(NetResponseStream::Error(
+                            NetResponseStreamError::new(Some(id), NetError::stream_overflow(lost)),
+                        )));
+                    }
+                }
+            }
+            stats.stream_closed();
+            let mut guard = listeners.lock().await;
+            guard.remove(&id);
+            credits.lock().await.remove(&id);
+        });
+        // 3. Store it
+        {
+            let mut listeners = self.listeners.lock().await;
+            listeners.insert(id, handle.cancel);
+        }
+
+        // 4. Return ID to caller
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::StreamId(
+            NetResponseGrpcSubscribe::new(id),
+        )))
+    }
+}
 #[async_trait::async_trait(?Send)]
 impl Transport for GrpcTransport {
     fn create(
@@ -45,22 +205,32 @@ impl Transport for GrpcTransport {
         transport_id: u32,
     ) -> Result<GrpcTransport, NetResultStatus> {
         let config: NetConfig = config.to_protocol_config(NetProtocol::Grpc)?;
+        let json = NetGrpcJson::from_config(&config).map_err(|e| e.status())?;
         let client = GrpcClient::default(config)?;
         Ok(Self {
             stream: Box::new(client),
             callback,
             listeners: Arc::new(Mutex::new(HashMap::new())),
+            credits: Arc::new(Mutex::new(HashMap::new())),
+            json,
+            stats: Arc::new(GrpcStats::default()),
             _transport_id: transport_id,
             next_stream_id: AtomicI32::new(1),
         })
     }
 
     async fn do_request(&self, request: NetRequest) -> Result<NetResponseKind, NetResultStatus> {
+        let deadline =
+            (request.timeout() != 0).then(|| Duration::from_secs(request.timeout().into()));
         let socket_requset = request.to_grpc_request()?;
         let kind = match socket_requset {
             crate::types::request::NetRequestGrpc::Stream(e) => self.stream(e).await?,
-            crate::types::request::NetRequestGrpc::Unary(e) => self.unary(e).await?,
+            crate::types::request::NetRequestGrpc::Unary(e) => self.unary(e, deadline).await?,
             crate::types::request::NetRequestGrpc::Unsubscribe(e) => self.unsubscribe(e).await?,
+            crate::types::request::NetRequestGrpc::Health(e) => self.health(e, deadline).await?,
+            crate::types::request::NetRequestGrpc::Credit(e) => self.credit(e).await?,
+            crate::types::request::NetRequestGrpc::State => self.state().await?,
+            crate::types::request::NetRequestGrpc::Stats => self.stats().await?,
         };
         Ok(kind)
     }
@@ -70,12 +240,16 @@ impl Transport for GrpcTransport {
         let mut listeners = self.listeners.lock().await;
 
         // Collect all handles into a Vec to drop the lock while closing
-        let handles: Vec<GrpcStreamHandle> = listeners.drain().map(|(_, handle)| handle).collect();
+        let handles: Vec<oneshot::Sender<()>> =
+            listeners.drain().map(|(_, handle)| handle).collect();
         // Close each handle
         for handle in handles {
-            handle.cancel();
+            let _ = handle.send(());
         }
         drop(listeners);
+        for (_, credit) in self.credits.lock().await.drain() {
+            credit.close();
+        }
         self.stream.close().await;
     }
 
@@ -85,82 +259,130 @@ impl Transport for GrpcTransport {
 }
 #[async_trait::async_trait(?Send)]
 impl IGrpcTransport for GrpcTransport {
-    async fn unary(&self, data: &NetRequestGrpcUnary) -> Result<NetResponseKind, NetResultStatus> {
-        let data = self.stream.unary(data.data(), data.method()).await?;
-        Ok(NetResponseKind::Grpc(NetResponseGrpc::Unary(
-            NetResponseGrpcUnary::new(data),
-        )))
+    async fn unary(
+        &self,
+        data: &NetRequestGrpcUnary,
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseKind, NetResultStatus> {
+        self.stats.unary();
+        let request = match &self.json {
+            Some(json) => match json.encode(data.method(), data.data()) {
+                Ok(request) => request,
+                Err(e) => {
+                    self.stats.failed(&e);
+                    return Ok(NetResponseKind::ResponseError(e));
+                }
+            },
+            None => Bytes::copy_from_slice(data.data()),
+        };
+        self.stats.sent(request.len());
+        // answered as an error response, a bare `NetResultStatus` would lose the grpc status
+        let response = match self
+            .stream
+            .unary(&request, data.method(), data.metadata(), deadline)
+            .await
+            .inspect(|response| self.stats.received(response.data_ref().len()))
+            .and_then(|response| match &self.json {
+                Some(json) => {
+                    let data = json.decode(data.method(), response.data_ref())?;
+                    Ok(response.with_data(Bytes::from(data)))
+                }
+                None => Ok(response),
+            }) {
+            Ok(response) => response,
+            Err(e) => {
+                self.stats.failed(&e);
+                return Ok(NetResponseKind::ResponseError(e));
+            }
+        };
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::Unary(response)))
     }
 
     async fn stream(
         &self,
         data: &NetRequestGrpcStream,
     ) -> Result<NetResponseKind, NetResultStatus> {
-        let handle = self.stream.stream(data.data(), data.method()).await?;
-        let id = self.next_stream_id.fetch_add(1, Ordering::Relaxed);
-        let callback = self.callback.clone();
-        let mut rx = handle.rx.resubscribe();
-        let listeners = Arc::clone(&self.listeners);
-        spawn_local(async move {
-            loop {
-                match rx.recv().await {
-                    Ok(msg) => match msg {
-                        Ok(data) => match data {
-                            Some(b) => {
-                                callback(NetResponseKind::Stream(NetResponseStream::Data(
-                                    NetResponseStreamData::new(Some(id), b),
-                                )));
-                            }
-                            None => {
-                                callback(NetResponseKind::Stream(NetResponseStream::Close(Some(
-                                    id,
-                                ))));
-                                break;
-                            }
-                        },
-                        Err(err) => {
-                            callback(NetResponseKind::Stream(NetResponseStream::Error(
-                                NetResponseStreamError::new(Some(id), err),
-                            )));
-                            break;
-                        }
-                    },
-                    Err(broadcast::error::RecvError::Closed) => {
-                        callback(NetResponseKind::Stream(NetResponseStream::Close(Some(id))));
-                        break;
-                    }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {
-                        // optional: report backpressure
-                    }
-                }
-            }
-            let mut guard = listeners.lock().await;
-            guard.remove(&id);
-        });
-        // 3. Store it
+        self.subscribe(data, false).await
+    }
+
+    async fn health(
+        &self,
+        data: &NetRequestGrpcHealth,
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseKind, NetResultStatus> {
+        let request = NetGrpcHealth::request(data.service());
+        if data.watch() {
+            let watch = NetRequestGrpcStream::create(
+                NetGrpcHealth::WATCH.to_string(),
+                request,
+                None,
+                None,
+                None,
+            );
+            return self.subscribe(&watch, true).await;
+        }
+        self.stats.unary();
+        self.stats.sent(request.len());
+        let response = match self
+            .stream
+            .unary(&request, NetGrpcHealth::CHECK, &[], deadline)
+            .await
         {
-            let mut listeners = self.listeners.lock().await;
-            listeners.insert(id, handle);
+            Ok(response) => response,
+            Err(e) => {
+                self.stats.failed(&e);
+                return Ok(NetResponseKind::ResponseError(e));
+            }
+        };
+        self.stats.received(response.data_ref().len());
+        match NetGrpcHealth::check_status(response.data_ref()) {
+            Ok(status) => Ok(NetResponseKind::Grpc(NetResponseGrpc::Health(
+                NetResponseGrpcHealth::new(status),
+            ))),
+            Err(e) => Ok(NetResponseKind::ResponseError(e)),
         }
-
-        // 4. Return ID to caller
-        Ok(NetResponseKind::Grpc(NetResponseGrpc::StreamId(
-            NetResponseGrpcSubscribe::new(id),
-        )))
     }
+
     async fn unsubscribe(
         &self,
         data: &NetRequestGrpcUnsubscribe,
     ) -> Result<NetResponseKind, NetResultStatus> {
         let mut listeners = self.listeners.lock().await;
-        let handler = listeners.remove(&data.id());
-        let _ = match handler {
-            Some(e) => e.cancel(),
-            None => (),
-        };
+        if let Some(cancel) = listeners.remove(&data.id()) {
+            let _ = cancel.send(());
+        }
+        // a stream waiting for credit would not see the cancel
+        if let Some(credit) = self.credits.lock().await.remove(&data.id()) {
+            credit.close();
+        }
         // 4. Return ID to caller
         Ok(NetResponseKind::Grpc(NetResponseGrpc::Unsubscribe(
             NetResponseGrpcUnsubscribe::new(data.id()),
         )))
     }
+
+    async fn credit(
+        &self,
+        data: &NetRequestGrpcCredit,
+    ) -> Result<NetResponseKind, NetResultStatus> {
+        if let Some(credit) = self.credits.lock().await.get(&data.id()) {
+            let room = Semaphore::MAX_PERMITS - credit.available_permits();
+            credit.add_permits((data.credits() as usize).min(room));
+        }
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::StreamId(
+            NetResponseGrpcSubscribe::new(data.id()),
+        )))
+    }
+
+    async fn state(&self) -> Result<NetResponseKind, NetResultStatus> {
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::State(
+            NetResponseGrpcState::new(self.stream.connectivity()),
+        )))
+    }
+
+    async fn stats(&self) -> Result<NetResponseKind, NetResultStatus> {
+        Ok(NetResponseKind::Grpc(NetResponseGrpc::Stats(
+            self.stats.snapshot(),
+        )))
+    }
 }
@@ -1,14 +1,19 @@
+#[cfg(feature = "grpc")]
 pub mod grpc;
 pub mod http;
+// browsers only expose sockets through WebSocket
+#[cfg(feature = "websocket")]
 pub mod socket;
+use std::time::Duration;
+
 use crate::types::response::NetResponseKind;
 use crate::types::{
     DartCallback,
     config::{NetConfig, NetConfigRequest},
     error::NetResultStatus,
     request::{
-        NetRequest, NetRequestGrpcStream, NetRequestGrpcUnary, NetRequestGrpcUnsubscribe,
-        NetRequestHttp, NetRequestSocketSend,
+        NetRequest, NetRequestGrpcCredit, NetRequestGrpcHealth, NetRequestGrpcStream,
+        NetRequestGrpcUnary, NetRequestGrpcUnsubscribe, NetRequestHttp, NetRequestSocketSend,
     },
 };
 #[async_trait::async_trait(?Send)]
@@ -20,11 +25,21 @@ pub trait ISocketTransport {
     async fn subscribe(&self) -> Result<(), NetResultStatus>;
 
     async fn unsubscribe(&self) -> Result<(), NetResultStatus>;
+
+    /// Bytes held by the stream buffer waiting for a complete message
+    async fn buffered_len(&self) -> Result<u32, NetResultStatus>;
+
+    /// Discard a partially buffered message. Returns the number of dropped bytes
+    async fn flush_buffer(&self) -> Result<u32, NetResultStatus>;
 }
 #[async_trait::async_trait(?Send)]
 pub trait IGrpcTransport {
-    /// Send raw bytes
-    async fn unary(&self, data: &NetRequestGrpcUnary) -> Result<NetResponseKind, NetResultStatus>;
+    /// Send raw bytes; `deadline` is the request timeout, which the server is told of
+    async fn unary(
+        &self,
+        data: &NetRequestGrpcUnary,
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseKind, NetResultStatus>;
 
     /// Subscribe to incoming messages (Dart-style stream)
     async fn stream(&self, data: &NetRequestGrpcStream)
@@ -34,6 +49,23 @@ pub trait IGrpcTransport {
         &self,
         data: &NetRequestGrpcUnsubscribe,
     ) -> Result<NetResponseKind, NetResultStatus>;
+
+    /// `grpc.health.v1.Health/Check`, or `Watch` as a stream of serving statuses
+    async fn health(
+        &self,
+        data: &NetRequestGrpcHealth,
+        deadline: Option<Duration>,
+    ) -> Result<NetResponseKind, NetResultStatus>;
+
+    /// Lets a stream opened with credits post more messages
+    async fn credit(&self, data: &NetRequestGrpcCredit)
+    -> Result<NetResponseKind, NetResultStatus>;
+
+    /// The client's `NetGrpcConnectivity`
+    async fn state(&self) -> Result<NetResponseKind, NetResultStatus>;
+
+    /// The transport's `NetGrpcStats` so far
+    async fn stats(&self) -> Result<NetResponseKind, NetResultStatus>;
 }
 #[async_trait::async_trait(?Send)]
 pub trait IHttpTransport {
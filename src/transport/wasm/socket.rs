@@ -1,7 +1,6 @@
-use tokio::sync::{
-    Mutex,
-    broadcast::{self, Receiver},
-};
+use std::sync::Arc;
+
+use tokio::sync::{Mutex, broadcast};
 use wasm_bindgen_futures::spawn_local;
 
 use crate::{
@@ -10,11 +9,11 @@ use crate::{
     types::{
         DartCallback,
         config::{NetConfig, NetConfigRequest, NetProtocol},
-        error::NetResultStatus,
+        error::{NetError, NetResultStatus},
         request::{NetRequest, NetRequestSocketSend},
         response::{
-            NetResponseKind, NetResponseSocketOk, NetResponseStream, NetResponseStreamData,
-            NetResponseStreamError,
+            NetResponseKind, NetResponseSocketBuffer, NetResponseSocketOk, NetResponseStream,
+            NetResponseStreamData, NetResponseStreamError,
         },
     },
     utils::buffer::StreamBuffer,
@@ -23,7 +22,9 @@ use crate::{
 pub struct SocketTransport {
     stream: Box<dyn IStreamClient>,
     callback: DartCallback,
-    rx: Mutex<Option<Receiver<Result<Option<Vec<u8>>, NetResultStatus>>>>,
+    /// Whether a task posts the subscription to Dart
+    subscribed: Mutex<bool>,
+    buffer: Arc<Mutex<StreamBuffer>>,
     _transport_id: u32,
 }
 #[async_trait::async_trait(?Send)]
@@ -36,12 +37,14 @@ impl Transport for SocketTransport {
         let config = config
             .to_protocol_config(NetProtocol::Socket)
             .or_else(|_| config.to_protocol_config(NetProtocol::WebSocket))?;
+        let buffer = Arc::new(Mutex::new(StreamBuffer::new(config.encoding)));
         let client = WsStreamClient::default(config)?;
 
         Ok(Self {
             stream: Box::new(client),
             callback,
-            rx: Mutex::new(None),
+            subscribed: Mutex::new(false),
+            buffer,
             _transport_id: transport_id,
         })
     }
@@ -53,17 +56,25 @@ impl Transport for SocketTransport {
             crate::types::request::NetRequestSocket::Send(socket_request_send) => {
                 self.send(socket_request_send).await?
             }
+            crate::types::request::NetRequestSocket::BufferStatus => {
+                let buffered = self.buffered_len().await?;
+                return Ok(NetResponseKind::SocketBuffer(NetResponseSocketBuffer::new(
+                    buffered,
+                )));
+            }
+            crate::types::request::NetRequestSocket::FlushBuffer => {
+                let flushed = self.flush_buffer().await?;
+                return Ok(NetResponseKind::SocketBuffer(NetResponseSocketBuffer::new(
+                    flushed,
+                )));
+            }
         };
         Ok(NetResponseKind::Socket(NetResponseSocketOk))
     }
 
     async fn close(&self) {
         self.stream.close().await;
-        let mut guard = self.rx.lock().await;
-        if let Some(rx) = guard.take() {
-            drop(rx);
-        }
-        *guard = None;
+        *self.subscribed.lock().await = false;
     }
 
     fn get_config(&self) -> &NetConfig {
@@ -77,31 +88,28 @@ impl ISocketTransport for SocketTransport {
     }
 
     async fn subscribe(&self) -> Result<(), NetResultStatus> {
-        // Create a new receiver from the inner RawStreamClient
-        let mut rx = self.stream.subscribe().await?;
-
-        // Store it in self.rx
-        {
-            let mut guard = self.rx.lock().await;
-            if guard.is_some() {
-                return Ok(());
-            }
-            *guard = Some(rx.resubscribe()); // store a clone in the struct
+        let mut subscribed = self.subscribed.lock().await;
+        if *subscribed {
+            return Ok(());
         }
+        let mut rx = self.stream.subscribe().await?;
+        *subscribed = true;
+        drop(subscribed);
         let callback = self.callback.clone();
-        let encoding = self.get_config().encoding;
+        let buffer = Arc::clone(&self.buffer);
+        buffer.lock().await.flush();
         spawn_local(async move {
-            let mut buffer = StreamBuffer::new(encoding);
             loop {
                 match rx.recv().await {
                     Ok(msg) => match msg {
                         Ok(data) => match data {
                             Some(data) => {
                                 // Try to parse/convert the incoming data
-                                if let Some(parsed) = buffer.add(data) {
+                                let parsed = buffer.lock().await.add(data);
+                                if let Some(parsed) = parsed {
                                     // Send the processed data to callback
                                     callback(NetResponseKind::Stream(NetResponseStream::Data(
-                                        NetResponseStreamData::new(None, parsed),
+                                        NetResponseStreamData::new(None, parsed.into()),
                                     )));
                                 }
                             }
@@ -112,7 +120,7 @@ impl ISocketTransport for SocketTransport {
                         },
                         Err(err) => {
                             callback(NetResponseKind::Stream(NetResponseStream::Error(
-                                NetResponseStreamError::new(None, err),
+                                NetResponseStreamError::new(None, err.into()),
                             )));
                             break;
                         }
@@ -121,7 +129,11 @@ impl ISocketTransport for SocketTransport {
                         callback(NetResponseKind::Stream(NetResponseStream::Close(None)));
                         break;
                     }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Lagged(lost)) => {
+                        callback(NetResponseKind::Stream(NetResponseStream::Error(
+                            NetResponseStreamError::new(None, NetError::stream_overflow(lost)),
+                        )));
+                    }
                 }
             }
         });
@@ -130,12 +142,16 @@ impl ISocketTransport for SocketTransport {
 
     async fn unsubscribe(&self) -> Result<(), NetResultStatus> {
         self.stream.close().await;
-        let mut guard = self.rx.lock().await;
-        if let Some(rx) = guard.take() {
-            drop(rx);
-        }
-        *guard = None;
+        *self.subscribed.lock().await = false;
 
         Ok(())
     }
+
+    async fn buffered_len(&self) -> Result<u32, NetResultStatus> {
+        Ok(self.buffer.lock().await.buffered_len() as u32)
+    }
+
+    async fn flush_buffer(&self) -> Result<u32, NetResultStatus> {
+        Ok(self.buffer.lock().await.flush() as u32)
+    }
 }
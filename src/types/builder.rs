@@ -0,0 +1,355 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::{
+    types::{
+        config::{
+            NetConfig, NetConfigHttp, NetConfigRequest, NetConnectResolver, NetHttp2Settings,
+            NetHttpAuth, NetHttpCache, NetHttpHeader, NetHttpHeaderMerge, NetHttpHostOverride,
+            NetHttpOfflineQueue, NetHttpPool, NetHttpProtocol, NetHttpSigning, NetMode,
+            NetProtocol, NetProxySocks5, NetStreamIdleTimeout, NetStreamQueue, NetStreamReconnect,
+            NetTimeouts, NetTlsMode,
+        },
+        error::{NetError, NetResultStatus},
+        host_policy::NetHostPolicy,
+    },
+    utils::buffer::StreamEncoding,
+};
+
+/// Fluent construction of `NetConfigRequest` for Rust callers.
+///
+/// The protocol is taken from the url scheme unless set explicitly; everything is
+/// validated in `build`.
+#[derive(Clone, Debug)]
+pub struct NetConfigBuilder {
+    url: String,
+    protocol: Option<NetProtocol>,
+    mode: NetMode,
+    tls_mode: NetTlsMode,
+    http: NetConfigHttp,
+    encoding: StreamEncoding,
+    fallback_urls: Vec<String>,
+    host_policy: NetHostPolicy,
+    stream_queue: NetStreamQueue,
+    share_connection: bool,
+    stream_reconnect: Option<NetStreamReconnect>,
+    stream_idle_timeout: Option<NetStreamIdleTimeout>,
+    network_reconnect: bool,
+    timeouts: NetTimeouts,
+    resolver: NetConnectResolver,
+    grpc_descriptor_set: Option<Bytes>,
+    grpc_authority: Option<String>,
+    grpc_idle_timeout: u32,
+    #[cfg(not(target_arch = "wasm32"))]
+    tls_client_config: Option<Arc<rustls::ClientConfig>>,
+}
+
+impl NetConfigBuilder {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            protocol: None,
+            mode: NetMode::default(),
+            tls_mode: NetTlsMode::default(),
+            http: NetConfigHttp::default(),
+            encoding: StreamEncoding::default(),
+            fallback_urls: Vec::new(),
+            host_policy: NetHostPolicy::default(),
+            stream_queue: NetStreamQueue::default(),
+            share_connection: false,
+            stream_reconnect: None,
+            stream_idle_timeout: None,
+            network_reconnect: false,
+            timeouts: NetTimeouts::default(),
+            resolver: NetConnectResolver::System,
+            grpc_descriptor_set: None,
+            grpc_authority: None,
+            grpc_idle_timeout: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            tls_client_config: None,
+        }
+    }
+
+    pub fn protocol(mut self, protocol: NetProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    pub fn grpc(self) -> Self {
+        self.protocol(NetProtocol::Grpc)
+    }
+
+    pub fn mode(mut self, mode: NetMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Route the connection through Tor when `enabled`
+    pub fn tor(mut self, enabled: bool) -> Self {
+        self.mode = match enabled {
+            true => NetMode::Tor,
+            false => NetMode::Clearnet,
+        };
+        self
+    }
+
+    /// Connect through the stream registered under `name` with `CustomStreamRegistry`
+    pub fn custom_mode(mut self, name: impl Into<String>) -> Self {
+        self.mode = NetMode::Custom(name.into());
+        self
+    }
+
+    /// Connect through the SOCKS5 `proxy`
+    pub fn socks5(mut self, proxy: NetProxySocks5) -> Self {
+        self.mode = NetMode::Socks5(proxy);
+        self
+    }
+
+    pub fn tls_mode(mut self, tls_mode: NetTlsMode) -> Self {
+        self.tls_mode = tls_mode;
+        self
+    }
+
+    /// Sends the config headers with every request too, see `NetHttpHeaderMerge::Merge`
+    pub fn header_merge(mut self, merge: NetHttpHeaderMerge) -> Self {
+        self.http.header_merge = merge;
+        self
+    }
+
+    pub fn http1(mut self) -> Self {
+        self.http.protocol = Some(NetHttpProtocol::Http1);
+        self
+    }
+
+    pub fn http2(mut self) -> Self {
+        self.http.protocol = Some(NetHttpProtocol::Http2);
+        self
+    }
+
+    /// HTTP/2 without ALPN, so plaintext `http://` servers are spoken to in h2 (h2c)
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http.protocol = Some(NetHttpProtocol::Http2PriorKnowledge);
+        self
+    }
+
+    /// HTTP/3 over QUIC, see `NetHttpProtocol::Http3`
+    pub fn http3(mut self) -> Self {
+        self.http.protocol = Some(NetHttpProtocol::Http3);
+        self
+    }
+
+    pub fn header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.http
+            .headers
+            .push(NetHttpHeader::new(key.into(), value.into()));
+        self
+    }
+
+    pub fn decode_charset(mut self, decode_charset: bool) -> Self {
+        self.http.decode_charset = decode_charset;
+        self
+    }
+
+    /// Lets requests sent with `queue_offline` wait for the network instead of failing
+    pub fn offline_queue(mut self, queue: NetHttpOfflineQueue) -> Self {
+        self.http.offline_queue = Some(queue);
+        self
+    }
+
+    /// Revalidates GET responses with their `ETag`/`Last-Modified` instead of fetching them again
+    pub fn cache(mut self, cache: NetHttpCache) -> Self {
+        self.http.cache = Some(cache);
+        self
+    }
+
+    /// Signs every HTTP request with the given keys
+    pub fn signing(mut self, signing: NetHttpSigning) -> Self {
+        self.http.signing = Some(signing);
+        self
+    }
+
+    /// Sends basic or bearer credentials with every HTTP request and WebSocket handshake
+    pub fn auth(mut self, auth: NetHttpAuth) -> Self {
+        self.http.auth = Some(auth);
+        self
+    }
+
+    /// Idle timeout and size of the HTTP connection pools
+    pub fn pool(mut self, pool: NetHttpPool) -> Self {
+        self.http.pool = pool;
+        self
+    }
+
+    /// Window sizes, stream limit and frame size of HTTP/2 connections
+    pub fn http2_settings(mut self, settings: NetHttp2Settings) -> Self {
+        self.http.http2_settings = settings;
+        self
+    }
+
+    /// Sends identical GETs in flight at once as one request, each getting its response
+    pub fn coalesce(mut self, coalesce: bool) -> Self {
+        self.http.coalesce = coalesce;
+        self
+    }
+
+    /// Connects requests to the hosts of `host_override` with its TLS mode and protocol
+    pub fn host_override(mut self, host_override: NetHttpHostOverride) -> Self {
+        self.http.host_overrides.push(host_override);
+        self
+    }
+
+    /// Takes HTTP connections from the pool shared by the whole process
+    pub fn global_client(mut self, global: bool) -> Self {
+        self.http.global_client = global;
+        self
+    }
+
+    pub fn encoding(mut self, encoding: StreamEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Endpoint tried, in the order added, when the previous ones are unreachable
+    pub fn fallback_url(mut self, url: impl Into<String>) -> Self {
+        self.fallback_urls.push(url.into());
+        self
+    }
+
+    /// Restricts the hosts, address ranges and schemes the transport may dial
+    pub fn host_policy(mut self, policy: NetHostPolicy) -> Self {
+        self.host_policy = policy;
+        self
+    }
+
+    /// Size and overflow behaviour of each subscriber's queue on stream transports
+    pub fn stream_queue(mut self, queue: NetStreamQueue) -> Self {
+        self.stream_queue = queue;
+        self
+    }
+
+    /// Lets WebSocket transports to the same endpoint share one connection
+    pub fn share_connection(mut self, share: bool) -> Self {
+        self.share_connection = share;
+        self
+    }
+
+    /// Reconnects stream transports after a drop and resubscribes their subscriptions
+    pub fn stream_reconnect(mut self, reconnect: NetStreamReconnect) -> Self {
+        self.stream_reconnect = Some(reconnect);
+        self
+    }
+
+    /// Reports socket and gRPC subscriptions that stay silent for too long
+    pub fn stream_idle_timeout(mut self, idle: NetStreamIdleTimeout) -> Self {
+        self.stream_idle_timeout = Some(idle);
+        self
+    }
+
+    /// Reopens connections when the default route changes instead of letting them time out
+    pub fn network_reconnect(mut self, reconnect: bool) -> Self {
+        self.network_reconnect = reconnect;
+        self
+    }
+
+    /// Limits connecting, the TLS handshake and HTTP reads on their own
+    pub fn timeouts(mut self, timeouts: NetTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Looks host names up with nameservers or DoH instead of the OS resolver
+    pub fn resolver(mut self, resolver: NetConnectResolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Serialized `FileDescriptorSet` the JSON of gRPC calls is transcoded with
+    pub fn grpc_descriptor_set(mut self, descriptor_set: impl Into<Bytes>) -> Self {
+        self.grpc_descriptor_set = Some(descriptor_set.into());
+        self
+    }
+
+    /// `:authority` of the gRPC calls, e.g. `api.example.com` when dialing its load balancer
+    pub fn grpc_authority(mut self, authority: impl Into<String>) -> Self {
+        self.grpc_authority = Some(authority.into());
+        self
+    }
+
+    /// Closes the gRPC channel after `seconds` without calls, dialing again on the next
+    pub fn grpc_idle_timeout(mut self, seconds: u32) -> Self {
+        self.grpc_idle_timeout = seconds;
+        self
+    }
+
+    /// Replaces the built-in verifiers with a caller-provided rustls config
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tls_client_config(mut self, config: Arc<rustls::ClientConfig>) -> Self {
+        self.tls_client_config = Some(config);
+        self
+    }
+
+    fn protocol_from_scheme(url: &str) -> Option<NetProtocol> {
+        let scheme = url.split_once("://").map(|(scheme, _)| scheme)?;
+        match scheme.to_ascii_lowercase().as_str() {
+            "http" | "https" => Some(NetProtocol::Http),
+            "ws" | "wss" => Some(NetProtocol::WebSocket),
+            "tcp" | "tls" | "tcp+tls" | "udp" | "unix" => Some(NetProtocol::Socket),
+            _ => None,
+        }
+    }
+
+    pub fn build(self) -> Result<NetConfigRequest, NetError> {
+        let protocol = match self.protocol {
+            Some(protocol) => protocol,
+            None => Self::protocol_from_scheme(&self.url).ok_or_else(|| {
+                NetError::new(NetResultStatus::UnsupportedScheme)
+                    .with_message("cannot infer protocol from url")
+                    .with_url(self.url.clone())
+            })?,
+        };
+        if self.http.headers.iter().any(|h| h.key_ref().is_empty()) {
+            return Err(NetError::new(NetResultStatus::InvalidConfigParameters)
+                .with_message("empty header name"));
+        }
+        let request = NetConfigRequest {
+            url: self.url,
+            mode: self.mode,
+            protocol,
+            http: self.http,
+            tls_mode: self.tls_mode,
+            encoding: self.encoding,
+            fallback_urls: self.fallback_urls,
+            host_policy: self.host_policy,
+            stream_queue: self.stream_queue,
+            share_connection: self.share_connection,
+            stream_reconnect: self.stream_reconnect,
+            stream_idle_timeout: self.stream_idle_timeout,
+            network_reconnect: self.network_reconnect,
+            timeouts: self.timeouts,
+            resolver: self.resolver,
+            grpc_descriptor_set: self.grpc_descriptor_set,
+            grpc_authority: self.grpc_authority,
+            grpc_idle_timeout: self.grpc_idle_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            tls_client_config: self.tls_client_config,
+        };
+        // validate the urls with the parser the transport will use
+        request
+            .to_config()
+            .map_err(|e| NetError::new(e).with_url(request.url.clone()))?;
+        request
+            .to_fallback_configs()
+            .map_err(|e| NetError::new(e).with_message("invalid fallback url"))?;
+        Ok(request)
+    }
+
+    /// Same as `build` but returns the resolved `NetConfig`
+    pub fn build_config(self) -> Result<NetConfig, NetError> {
+        let request = self.build()?;
+        request
+            .to_config()
+            .map_err(|e| NetError::new(e).with_url(request.url.clone()))
+    }
+}
@@ -1,41 +1,103 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
 use crate::{
-    types::{AddressInfo, error::NetResultStatus},
+    types::{
+        AddressInfo,
+        error::{NetError, NetResultStatus},
+        host_policy::NetHostPolicy,
+    },
     utils::{Utils, buffer::StreamEncoding},
 };
 #[repr(u8)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum NetMode {
     Tor = 1,
+    #[default]
     Clearnet = 2,
+    /// Stream from the connector registered under this name with `CustomStreamRegistry`
+    Custom(String) = 3,
+    /// Through a SOCKS5 proxy, which resolves host names itself
+    Socks5(NetProxySocks5) = 4,
+}
+
+/// SOCKS5 proxy of `NetMode::Socks5`, username and password auth is offered when
+/// `username` is set
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NetProxySocks5 {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+// the mode ends up in logs and session keys, the password must not
+impl std::fmt::Debug for NetProxySocks5 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NetProxySocks5")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .finish()
+    }
 }
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum NetHttpProtocol {
     Http1 = 1,
     Http2 = 2,
+    /// HTTP/2 from the first byte without waiting for ALPN, for cleartext (h2c) servers
+    Http2PriorKnowledge = 3,
+    /// HTTP/3 over QUIC for clearnet `https://` urls (`http3` feature, native). When QUIC
+    /// can not be dialed, e.g. UDP is blocked, requests go over HTTP/2 or HTTP/1.1 instead
+    /// and QUIC is tried again after a while; an `Alt-Svc` of the server moves it to the
+    /// port it names. Event streams, downloads, and other urls and modes always take HTTP/2
+    /// or HTTP/1.1
+    Http3 = 4,
+}
+impl NetHttpProtocol {
+    /// Connections speak HTTP/2 only
+    pub fn is_http2(&self) -> bool {
+        matches!(
+            self,
+            NetHttpProtocol::Http2 | NetHttpProtocol::Http2PriorKnowledge
+        )
+    }
 }
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum NetProtocol {
     Http = 1,
     Grpc = 2,
+    #[serde(rename = "websocket")]
     WebSocket = 3,
     Socket = 4,
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum NetTlsMode {
+    #[default]
     Safe = 1,
     Dangerous = 2,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NetHttpHeader {
     key: String,
     value: String,
@@ -69,25 +131,546 @@ impl NetHttpHeader {
         &self.value
     }
 }
-#[derive(Clone, Debug)]
+
+/// Ordered header collection with case-insensitive lookup; repeated names are kept as separate entries
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug, Default)]
+pub struct NetHttpHeaders {
+    entries: Vec<NetHttpHeader>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetHttpHeaders {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn len(&self) -> u32 {
+        self.entries.len() as u32
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// First value of `name`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.get_ref(name).map(|v| v.to_string())
+    }
+    /// Every value of `name`, in the order received
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_all(&self, name: &str) -> Vec<String> {
+        self.iter_named(name).map(|h| h.value()).collect()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn contains(&self, name: &str) -> bool {
+        self.iter_named(name).next().is_some()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn entries(&self) -> Vec<NetHttpHeader> {
+        self.entries.clone()
+    }
+}
+
+impl NetHttpHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, header: NetHttpHeader) {
+        self.entries.push(header);
+    }
+    pub fn get_ref(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|h| h.key.eq_ignore_ascii_case(name))
+            .map(|h| h.value_ref())
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, NetHttpHeader> {
+        self.entries.iter()
+    }
+    pub fn as_slice(&self) -> &[NetHttpHeader] {
+        &self.entries
+    }
+    fn iter_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a NetHttpHeader> + 'a {
+        self.entries
+            .iter()
+            .filter(move |h| h.key.eq_ignore_ascii_case(name))
+    }
+}
+
+impl FromIterator<NetHttpHeader> for NetHttpHeaders {
+    fn from_iter<I: IntoIterator<Item = NetHttpHeader>>(iter: I) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a NetHttpHeaders {
+    type Item = &'a NetHttpHeader;
+    type IntoIter = std::slice::Iter<'a, NetHttpHeader>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+/// How the headers of a request combine with `NetConfigHttp::headers`
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetHttpHeaderMerge {
+    /// A request with headers of its own sends only those
+    #[default]
+    Replace = 1,
+    /// The config headers go with every request. A request header takes the place of the
+    /// config ones of its name, and one with an empty value removes them without being sent
+    Merge = 2,
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NetConfigHttp {
     pub headers: Vec<NetHttpHeader>,
+    /// What request headers do to `headers`; native only
+    pub header_merge: NetHttpHeaderMerge,
     pub protocol: Option<NetHttpProtocol>,
+    /// Transcode ISO-8859-1/UTF-16 response bodies to UTF-8 using the `Content-Type` charset
+    pub decode_charset: bool,
+    /// Holds requests sent with `queue_offline` while the network is down; native only
+    pub offline_queue: Option<NetHttpOfflineQueue>,
+    /// Keeps GET responses by url and revalidates them; native only
+    pub cache: Option<NetHttpCache>,
+    /// Signs every request before it is sent; native only
+    pub signing: Option<NetHttpSigning>,
+    /// `Authorization` of every request and WebSocket handshake to the configured host;
+    /// native only
+    pub auth: Option<NetHttpAuth>,
+    /// Connections kept open for later requests, to the configured host and to others
+    pub pool: NetHttpPool,
+    /// Connections come from one pool for the whole process, shared by every transport with
+    /// the flag and the same mode, TLS and pool settings, so short lived transports reuse them
+    /// instead of opening their own; native only
+    pub global_client: bool,
+    /// Flow control and frame size of HTTP/2 connections; native only
+    pub http2_settings: NetHttp2Settings,
+    /// Identical GETs in flight at once share one request and its response; native only
+    pub coalesce: bool,
+    /// TLS mode and protocol of the connections to hosts other than the configured one,
+    /// the first that matches applies; native only
+    pub host_overrides: Vec<NetHttpHostOverride>,
 }
-#[derive(Clone, Debug)]
+impl NetConfigHttp {
+    /// The entry of `host_overrides` for requests to `host`
+    pub fn host_override(&self, host: &str) -> Option<&NetHttpHostOverride> {
+        self.host_overrides
+            .iter()
+            .find(|o| NetHostPolicy::host_matches(&o.host, host))
+    }
+}
+/// How requests to a host are connected in place of the config of the transport. `host`
+/// matches like the host rules of `NetHostPolicy`, `*.` for any subdomain; what is `None`
+/// stays as configured. A `tls_client_config` still takes the place of `tls_mode`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetHttpHostOverride {
+    pub host: String,
+    pub tls_mode: Option<NetTlsMode>,
+    pub protocol: Option<NetHttpProtocol>,
+}
+/// Credentials sent as the `Authorization` header, reconnects included. An `Authorization`
+/// header of the request, credentials in the url or a `signing` take precedence, and hosts
+/// other than the configured one never get them
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetHttpAuth {
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+impl NetHttpAuth {
+    /// Value of the `Authorization` header
+    pub fn header_value(&self) -> String {
+        match self {
+            NetHttpAuth::Basic { username, password } => {
+                format!(
+                    "Basic {}",
+                    STANDARD.encode(format!("{}:{}", username, password))
+                )
+            }
+            NetHttpAuth::Bearer { token } => format!("Bearer {}", token),
+        }
+    }
+}
+/// Keeps the secrets out of logs
+impl std::fmt::Debug for NetHttpAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetHttpAuth::Basic { username, .. } => {
+                write!(f, "NetHttpAuth::Basic {{ username: {:?} }}", username)
+            }
+            NetHttpAuth::Bearer { .. } => write!(f, "NetHttpAuth::Bearer"),
+        }
+    }
+}
+/// Request signature added as the `Authorization` header. Both schemes sign a canonical
+/// form of the method, path, query, headers and body hash, so a request altered on the way
+/// no longer verifies
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetHttpSigning {
+    /// `HMAC-SHA256 Credential=<key_id>, SignedHeaders=.., Signature=..`, dated by `x-date`
+    Hmac { key_id: String, secret: String },
+    /// AWS Signature Version 4
+    AwsV4 {
+        access_key: String,
+        secret_key: String,
+        region: String,
+        service: String,
+        /// Sent as `x-amz-security-token` for temporary credentials
+        session_token: Option<String>,
+    },
+}
+/// Keeps the secrets out of logs
+impl std::fmt::Debug for NetHttpSigning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetHttpSigning::Hmac { key_id, .. } => {
+                write!(f, "NetHttpSigning::Hmac {{ key_id: {:?} }}", key_id)
+            }
+            NetHttpSigning::AwsV4 {
+                access_key,
+                region,
+                service,
+                ..
+            } => write!(
+                f,
+                "NetHttpSigning::AwsV4 {{ access_key: {:?}, region: {:?}, service: {:?} }}",
+                access_key, region, service
+            ),
+        }
+    }
+}
+/// In-memory cache of GET responses that carry an `ETag` or `Last-Modified`. Later GETs of
+/// the url send them back as `If-None-Match`/`If-Modified-Since` and a `304 Not Modified` is
+/// answered with the kept response, so polling an unchanged resource costs no body.
+/// Responses are kept apart by the request headers their `Vary` names, config headers
+/// included. Requests with validators of their own and `no-store` responses are left alone,
+/// and so are requests with an `Authorization` of their own, in the url or from the config
+/// unless the response is `Cache-Control: public`
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetHttpCache {
+    /// 0 for 64; the response unused the longest makes room
+    pub max_entries: u32,
+    /// Larger bodies are not kept, 0 for 1 MiB
+    pub max_body_size: u32,
+}
+/// Store-and-forward of idempotent HTTP requests. Requests that fail to connect are kept in
+/// order and replayed when the network returns; requests sent while some are waiting join
+/// the end of the queue
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetHttpOfflineQueue {
+    /// 0 for 64; requests beyond it fail as if there was no queue
+    pub max_requests: u32,
+    /// Seconds a request may wait before it expires, 0 for a day
+    pub ttl: u32,
+    /// File the queue is kept in across restarts, memory only when `None`
+    pub path: Option<String>,
+}
+/// Connection pools of an HTTP transport, one per host, port and TLS its requests went to
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetHttpPool {
+    /// Seconds an unused connection stays open, 0 for 90
+    pub idle_timeout: u32,
+    /// Unused connections kept per host, 0 for no limit
+    pub max_idle_per_host: u32,
+    /// Pools kept for hosts other than the configured one, 0 for 16; the one unused the
+    /// longest is closed to make room
+    pub max_hosts: u32,
+    /// Seconds a connection goes without traffic before TCP keep-alive probes check the
+    /// peer is still there, 0 for 30. One that is gone is closed and leaves the pool, so
+    /// the next request opens a new one instead of failing on it
+    pub keep_alive: u32,
+}
+impl NetHttpPool {
+    pub fn idle_timeout(&self) -> std::time::Duration {
+        match self.idle_timeout {
+            0 => std::time::Duration::from_secs(90),
+            timeout => std::time::Duration::from_secs(timeout.into()),
+        }
+    }
+    pub fn max_idle_per_host(&self) -> usize {
+        match self.max_idle_per_host {
+            0 => usize::MAX,
+            max => max as usize,
+        }
+    }
+    pub fn max_hosts(&self) -> usize {
+        match self.max_hosts {
+            0 => 16,
+            max => max as usize,
+        }
+    }
+    pub fn keep_alive(&self) -> std::time::Duration {
+        match self.keep_alive {
+            0 => std::time::Duration::from_secs(30),
+            idle => std::time::Duration::from_secs(idle.into()),
+        }
+    }
+}
+/// HTTP/2 tuning of an HTTP transport's connections. The defaults suit low latency links;
+/// over Tor, where a round trip takes hundreds of milliseconds, larger windows or
+/// `adaptive_window` keep a download from stalling on window updates
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetHttp2Settings {
+    /// Bytes the server may send on a stream before waiting for a window update, 0 for 2 MiB
+    pub initial_stream_window_size: u32,
+    /// Bytes the server may send on the connection as a whole, 0 for 5 MiB
+    pub initial_connection_window_size: u32,
+    /// Streams opened on a connection before the server announced its limit, 0 for 100
+    pub max_concurrent_streams: u32,
+    /// Largest frame payload the server may send, 0 for 16 KiB; kept within 16 KiB and 16 MiB
+    pub max_frame_size: u32,
+    /// Sizes the windows by the measured bandwidth-delay product, the windows above are ignored
+    pub adaptive_window: bool,
+    /// Seconds between PINGs on a connection, idle ones included unless `no_idle_pings`, 0 for
+    /// 30. A connection a NAT or middlebox silently dropped is then closed before a request is
+    /// sent on it
+    pub ping_interval: u32,
+    /// Seconds a PING may go unanswered before the connection counts as dead, 0 for 10
+    pub ping_timeout: u32,
+    /// PINGs only while a request or stream is open, idle connections go unchecked and a NAT
+    /// may drop them unnoticed until the next request
+    pub no_idle_pings: bool,
+}
+impl NetHttp2Settings {
+    /// The largest window HTTP/2 allows
+    const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+
+    pub fn initial_stream_window_size(&self) -> Option<u32> {
+        match self.initial_stream_window_size {
+            0 => None,
+            size => Some(size.min(Self::MAX_WINDOW_SIZE)),
+        }
+    }
+    pub fn initial_connection_window_size(&self) -> Option<u32> {
+        match self.initial_connection_window_size {
+            0 => None,
+            size => Some(size.min(Self::MAX_WINDOW_SIZE)),
+        }
+    }
+    pub fn max_concurrent_streams(&self) -> Option<usize> {
+        match self.max_concurrent_streams {
+            0 => None,
+            max => Some(max as usize),
+        }
+    }
+    pub fn max_frame_size(&self) -> Option<u32> {
+        match self.max_frame_size {
+            0 => None,
+            size => Some(size.clamp(16_384, 16_777_215)),
+        }
+    }
+    pub fn ping_interval(&self) -> std::time::Duration {
+        match self.ping_interval {
+            0 => std::time::Duration::from_secs(30),
+            interval => std::time::Duration::from_secs(interval.into()),
+        }
+    }
+    pub fn ping_timeout(&self) -> std::time::Duration {
+        match self.ping_timeout {
+            0 => std::time::Duration::from_secs(10),
+            timeout => std::time::Duration::from_secs(timeout.into()),
+        }
+    }
+    pub fn ping_while_idle(&self) -> bool {
+        !self.no_idle_pings
+    }
+}
+/// What a subscriber's queue does with a frame that does not fit
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetStreamOverflow {
+    /// Stop reading the connection until every subscriber has room again
+    #[default]
+    Block = 1,
+    /// End the subscription after what it has queued, reporting the overflow
+    Error = 2,
+    /// Drop the frame and report how many were lost before the next one
+    Drop = 3,
+}
+/// Bounded queue each subscriber of a socket, WebSocket or gRPC stream reads from
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetStreamQueue {
+    /// Frames per subscriber, 0 for 128
+    pub capacity: u32,
+    pub overflow: NetStreamOverflow,
+}
+/// Reconnects a socket or WebSocket transport whose connection dropped and resubscribes its
+/// subscriptions, each posting `Reconnected` once it receives again. HTTP event streams
+/// are opened again the same way, from their last event id, and gRPC streams that failed
+/// on a dropped connection or an unavailable server, keeping their stream id (native)
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetStreamReconnect {
+    /// Attempts after each drop, 0 for no limit
+    pub max_attempts: u32,
+    /// Wait before the first attempt, doubled after each failed one; 0 for a second
+    pub delay_ms: u32,
+    /// Longest wait between attempts, 0 for 30 seconds
+    pub max_delay_ms: u32,
+    /// Holds sends that fail while the connection is down, sends fail as before when `None`
+    pub send_buffer: Option<NetStreamSendBuffer>,
+}
+/// Outgoing messages kept in order while a stream transport reconnects and written after it
+/// did; sends made while some wait join the end
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetStreamSendBuffer {
+    /// 0 for 64; sends beyond it fail as if there was no buffer
+    pub max_messages: u32,
+    /// Seconds a message may wait before it expires, 0 for a minute
+    pub ttl: u32,
+}
+/// Posts `Idle` for a subscription that received nothing for `timeout` seconds, since a
+/// half-dead connection otherwise only shows once a send fails
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetStreamIdleTimeout {
+    /// 0 for a minute
+    pub timeout: u32,
+    /// Also reconnect: sockets drop the connection and reconnect it by `stream_reconnect`,
+    /// or its defaults when that is `None`; gRPC streams make their call again
+    pub reconnect: bool,
+}
+impl NetStreamIdleTimeout {
+    pub fn duration(&self) -> std::time::Duration {
+        match self.timeout {
+            0 => std::time::Duration::from_secs(60),
+            timeout => std::time::Duration::from_secs(timeout.into()),
+        }
+    }
+}
+/// Limits of the steps of a connection, in seconds; 0 leaves a step to the request timeout
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetTimeouts {
+    /// Dialing the host, through the proxy or Tor circuit of the mode
+    pub connect: u32,
+    pub tls_handshake: u32,
+    /// Longest wait for the answer to an HTTP request, its connection included, and then for
+    /// each part of the body; event streams are not limited
+    pub read: u32,
+}
+impl NetTimeouts {
+    pub fn connect(&self) -> Option<std::time::Duration> {
+        NetTimeouts::limit(self.connect)
+    }
+    pub fn tls_handshake(&self) -> Option<std::time::Duration> {
+        NetTimeouts::limit(self.tls_handshake)
+    }
+    pub fn read(&self) -> Option<std::time::Duration> {
+        NetTimeouts::limit(self.read)
+    }
+    fn limit(seconds: u32) -> Option<std::time::Duration> {
+        (seconds != 0).then(|| std::time::Duration::from_secs(seconds.into()))
+    }
+}
+/// Where TCP connects of the clearnet mode look host names up; Tor, SOCKS5 and custom
+/// modes leave names to the other end
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum NetConnectResolver {
+    #[default]
+    System,
+    /// These nameservers, `ip` or `ip:port` with port 53 by default, over UDP and over TCP
+    /// for truncated answers
+    Nameservers { servers: Vec<String> },
+    /// DNS-over-HTTPS (RFC 8484) endpoint; only its own name is looked up by the OS, none
+    /// at all when the url has an IP
+    Doh { url: String },
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NetConfigTor {
     pub cache_dir: String,
     pub state_dir: String,
 }
-#[derive()]
+/// Where the `otel` feature exports spans of requests and streams, shared by every instance
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetConfigOtel {
+    /// OTLP/HTTP traces url, e.g. `http://localhost:4318/v1/traces`; spans are posted as JSON
+    pub endpoint: String,
+    /// Sent with every export, e.g. the collector's API key
+    pub headers: Vec<NetHttpHeader>,
+    /// `service.name` of the exported resource, `net_sdk` when `None`
+    pub service_name: Option<String>,
+}
+/// Only `url` and `protocol` are required when loading from JSON; the rest use their defaults
+#[derive(Serialize, Deserialize)]
 pub struct NetConfigRequest {
     pub url: String,
+    #[serde(default)]
     pub mode: NetMode,
     pub protocol: NetProtocol,
+    #[serde(default)]
     pub http: NetConfigHttp,
+    #[serde(default)]
     pub tls_mode: NetTlsMode,
     // pub tor_config: Option<NetConfigTor>,
+    #[serde(default)]
     pub encoding: StreamEncoding,
+    /// Tried in order after `url` when it cannot be reached; HTTP requests only when they
+    /// are idempotent or carry an `Idempotency-Key` header
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_urls: Vec<String>,
+    /// Checked before every connection, including per-request urls
+    #[serde(default, skip_serializing_if = "NetHostPolicy::is_empty")]
+    pub host_policy: NetHostPolicy,
+    #[serde(default)]
+    pub stream_queue: NetStreamQueue,
+    /// WebSocket only: transports with the same url, mode, TLS mode, headers and host
+    /// policy use one connection, each receiving every message
+    #[serde(default)]
+    pub share_connection: bool,
+    /// Stream transports only; a dropped connection ends the subscriptions when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_reconnect: Option<NetStreamReconnect>,
+    /// Socket and gRPC subscriptions only; a subscription may wait forever when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_idle_timeout: Option<NetStreamIdleTimeout>,
+    /// Drops and reopens the connections when the default route moves, as between Wi-Fi
+    /// and cellular or when a VPN comes up, instead of waiting for them to time out
+    #[serde(default)]
+    pub network_reconnect: bool,
+    #[serde(default)]
+    pub timeouts: NetTimeouts,
+    #[serde(default)]
+    pub resolver: NetConnectResolver,
+    /// gRPC only: serialized `FileDescriptorSet` of the services called, base64 in JSON.
+    /// Calls of the transport then send and answer JSON, transcoded with it; needs the
+    /// `grpc-json` feature
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_base64",
+        deserialize_with = "deserialize_base64"
+    )]
+    pub grpc_descriptor_set: Option<Bytes>,
+    /// gRPC only: `:authority` of the calls in place of the url's host and port, for a server
+    /// that virtual-hosts by name dialed by IP or through a TCP load balancer. TLS still
+    /// verifies the host dialed; a request may override it for its own call. Not on wasm,
+    /// where the browser sets it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_authority: Option<String>,
+    /// gRPC only: seconds without a call in flight after which the channel is closed, its
+    /// socket and Tor circuit let go until the next call dials again; 0 keeps it open. Not
+    /// on wasm, where the browser owns the connections
+    #[serde(default)]
+    pub grpc_idle_timeout: u32,
+    /// Used as-is for TLS instead of the config derived from `tls_mode`; ALPN is only filled in when empty
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub tls_client_config: Option<Arc<rustls::ClientConfig>>,
 }
 
 #[derive(Clone, Debug)]
@@ -99,16 +682,42 @@ pub struct NetConfig {
     pub tls_mode: NetTlsMode,
     // pub tor_config: Option<NetConfigTor>,
     pub encoding: StreamEncoding,
+    pub host_policy: NetHostPolicy,
+    pub stream_queue: NetStreamQueue,
+    pub share_connection: bool,
+    pub stream_reconnect: Option<NetStreamReconnect>,
+    pub stream_idle_timeout: Option<NetStreamIdleTimeout>,
+    pub network_reconnect: bool,
+    pub timeouts: NetTimeouts,
+    pub resolver: NetConnectResolver,
+    pub grpc_descriptor_set: Option<Bytes>,
+    pub grpc_authority: Option<String>,
+    pub grpc_idle_timeout: u32,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub tls_client_config: Option<Arc<rustls::ClientConfig>>,
 }
 impl NetConfig {
     pub fn change_addr(&self, new_addr: AddressInfo) -> NetConfig {
         Self {
             addr: new_addr,
-            mode: self.mode,
+            mode: self.mode.clone(),
             http: self.http.clone(),
             protocol: self.protocol,
             tls_mode: self.tls_mode,
             encoding: self.encoding.clone(),
+            host_policy: self.host_policy.clone(),
+            stream_queue: self.stream_queue,
+            share_connection: self.share_connection,
+            stream_reconnect: self.stream_reconnect,
+            stream_idle_timeout: self.stream_idle_timeout,
+            network_reconnect: self.network_reconnect,
+            timeouts: self.timeouts,
+            resolver: self.resolver.clone(),
+            grpc_descriptor_set: self.grpc_descriptor_set.clone(),
+            grpc_authority: self.grpc_authority.clone(),
+            grpc_idle_timeout: self.grpc_idle_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            tls_client_config: self.tls_client_config.clone(),
         }
     }
 }
@@ -117,29 +726,92 @@ impl Default for NetConfigHttp {
     fn default() -> NetConfigHttp {
         Self {
             headers: Vec::new(),
+            header_merge: NetHttpHeaderMerge::default(),
             protocol: None,
+            decode_charset: false,
+            offline_queue: None,
+            cache: None,
+            signing: None,
+            auth: None,
+            pool: NetHttpPool::default(),
+            global_client: false,
+            http2_settings: NetHttp2Settings::default(),
+            coalesce: false,
+            host_overrides: Vec::new(),
         }
     }
 }
 
+fn serialize_base64<S: Serializer>(
+    bytes: &Option<Bytes>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match bytes {
+        Some(bytes) => serializer.serialize_some(&STANDARD.encode(bytes)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_base64<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Bytes>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|encoded| {
+            STANDARD
+                .decode(encoded)
+                .map(Bytes::from)
+                .map_err(serde::de::Error::custom)
+        })
+        .transpose()
+}
+
+fn from_json<'a, T: Deserialize<'a>>(json: &'a str) -> Result<T, NetError> {
+    serde_json::from_str(json).map_err(|e| {
+        NetError::new(NetResultStatus::InvalidConfigParameters)
+            .with_message("invalid config json")
+            .with_source(e)
+    })
+}
+
+impl NetConfigTor {
+    pub fn from_json(json: &str) -> Result<Self, NetError> {
+        from_json(json)
+    }
+}
+
 impl NetConfigRequest {
+    pub fn from_json(json: &str) -> Result<Self, NetError> {
+        from_json(json)
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
     fn to_protocol_address(&self) -> Result<AddressInfo, NetResultStatus> {
-        match self.protocol {
-            NetProtocol::Http => Utils::parse_http_url(&self.url),
-            NetProtocol::Grpc => Utils::parse_http_url(&self.url),
-            NetProtocol::WebSocket => Utils::parse_ws_url(&self.url),
-            NetProtocol::Socket => Utils::parse_tcp_url(&self.url),
-        }
+        Utils::parse_protocol_url(&self.url, self.protocol)
     }
     pub fn to_config(&self) -> Result<NetConfig, NetResultStatus> {
         let addr: AddressInfo = self.to_protocol_address()?;
+        self.host_policy.validate()?;
         Ok(NetConfig {
             addr,
             http: self.http.clone(),
             protocol: self.protocol,
-            mode: self.mode,
+            mode: self.mode.clone(),
             tls_mode: self.tls_mode,
             encoding: self.encoding,
+            host_policy: self.host_policy.clone(),
+            stream_queue: self.stream_queue,
+            share_connection: self.share_connection,
+            stream_reconnect: self.stream_reconnect,
+            stream_idle_timeout: self.stream_idle_timeout,
+            network_reconnect: self.network_reconnect,
+            timeouts: self.timeouts,
+            resolver: self.resolver.clone(),
+            grpc_descriptor_set: self.grpc_descriptor_set.clone(),
+            grpc_authority: self.grpc_authority.clone(),
+            grpc_idle_timeout: self.grpc_idle_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            tls_client_config: self.tls_client_config.clone(),
         })
     }
     pub fn to_protocol_config(&self, protocol: NetProtocol) -> Result<NetConfig, NetResultStatus> {
@@ -148,19 +820,31 @@ impl NetConfigRequest {
         }
         self.to_config()
     }
+    /// `to_config` for each of `fallback_urls`, in order
+    pub fn to_fallback_configs(&self) -> Result<Vec<NetConfig>, NetResultStatus> {
+        let config = self.to_config()?;
+        self.fallback_urls
+            .iter()
+            .map(|url| Ok(config.change_addr(Utils::parse_protocol_url(url, self.protocol)?)))
+            .collect()
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Clone, Debug)]
 pub struct NetConfigHttpWasm {
     headers: Vec<NetHttpHeader>,
+    decode_charset: bool,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 impl NetConfigHttpWasm {
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-    pub fn create(headers: Vec<NetHttpHeader>) -> Self {
-        Self { headers }
+    pub fn create(headers: Vec<NetHttpHeader>, decode_charset: bool) -> Self {
+        Self {
+            headers,
+            decode_charset,
+        }
     }
 }
 
@@ -171,6 +855,8 @@ pub struct NetConfigRequestWasm {
     protocol: NetProtocol,
     http: NetConfigHttpWasm,
     encoding: StreamEncoding,
+    /// Serialized `FileDescriptorSet` for JSON gRPC calls
+    grpc_descriptor_set: Option<Vec<u8>>,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
@@ -181,12 +867,14 @@ impl NetConfigRequestWasm {
         protocol: NetProtocol,
         http: NetConfigHttpWasm,
         encoding: StreamEncoding,
+        grpc_descriptor_set: Option<Vec<u8>>,
     ) -> Self {
         Self {
             url,
             protocol,
             http,
             encoding,
+            grpc_descriptor_set,
         }
     }
 }
@@ -196,7 +884,18 @@ impl NetConfigRequestWasm {
         // convert NetConfigHttpWasm → NetConfigHttp
         let http = NetConfigHttp {
             headers: self.http.headers.clone(),
+            header_merge: NetHttpHeaderMerge::default(),
             protocol: None, // map if needed
+            decode_charset: self.http.decode_charset,
+            offline_queue: None,
+            cache: None,
+            signing: None,
+            auth: None,
+            pool: NetHttpPool::default(),
+            global_client: false,
+            http2_settings: NetHttp2Settings::default(),
+            coalesce: false,
+            host_overrides: Vec::new(),
         };
 
         Ok(NetConfigRequest {
@@ -206,6 +905,20 @@ impl NetConfigRequestWasm {
             tls_mode: NetTlsMode::Safe,
             http,
             encoding: self.encoding,
+            fallback_urls: Vec::new(),
+            host_policy: NetHostPolicy::default(),
+            stream_queue: NetStreamQueue::default(),
+            share_connection: false,
+            stream_reconnect: None,
+            stream_idle_timeout: None,
+            network_reconnect: false,
+            timeouts: NetTimeouts::default(),
+            resolver: NetConnectResolver::System,
+            grpc_descriptor_set: self.grpc_descriptor_set.clone().map(Bytes::from),
+            grpc_authority: None,
+            grpc_idle_timeout: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            tls_client_config: None,
         })
     }
 }
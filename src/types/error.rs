@@ -1,4 +1,6 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
+
+use crate::types::grpc_status::NetGrpcStatus;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -24,6 +26,90 @@ pub enum NetResultStatus {
     TorClientNotInitialized = 26,
     InternalError = 27,
     InstanceDoesNotExist = 28,
+    UnsupportedScheme = 29,
+    /// Refused by the config's `NetHostPolicy` before dialing
+    HostNotAllowed = 30,
+    /// A subscriber fell behind its `NetStreamQueue` and frames were dropped
+    StreamOverflow = 31,
+}
+
+/// Broad grouping of a status so callers can decide how to react without matching every code
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetErrorCategory {
+    None = 0,
+    Network = 1,
+    Configuration = 2,
+    Protocol = 3,
+    Internal = 4,
+}
+
+impl NetResultStatus {
+    pub const ALL: [NetResultStatus; 19] = [
+        NetResultStatus::OK,
+        NetResultStatus::InvalidUrl,
+        NetResultStatus::TlsError,
+        NetResultStatus::ConnectionError,
+        NetResultStatus::TorNetError,
+        NetResultStatus::SocketError,
+        NetResultStatus::Http2ConctionFailed,
+        NetResultStatus::InvalidRequestParameters,
+        NetResultStatus::InvalidConfigParameters,
+        NetResultStatus::TransportNotFound,
+        NetResultStatus::RequestTimeout,
+        NetResultStatus::InvalidTorConfig,
+        NetResultStatus::TorInitializationFailed,
+        NetResultStatus::TorClientNotInitialized,
+        NetResultStatus::InternalError,
+        NetResultStatus::InstanceDoesNotExist,
+        NetResultStatus::UnsupportedScheme,
+        NetResultStatus::HostNotAllowed,
+        NetResultStatus::StreamOverflow,
+    ];
+
+    pub fn from_u8(code: u8) -> Option<NetResultStatus> {
+        NetResultStatus::ALL
+            .iter()
+            .find(|status| **status as u8 == code)
+            .copied()
+    }
+
+    /// Whether repeating the same operation later may succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            NetResultStatus::ConnectionError
+                | NetResultStatus::TorNetError
+                | NetResultStatus::SocketError
+                | NetResultStatus::RequestTimeout
+                | NetResultStatus::TorInitializationFailed
+        )
+    }
+
+    pub fn category(&self) -> NetErrorCategory {
+        match self {
+            NetResultStatus::OK => NetErrorCategory::None,
+            NetResultStatus::TlsError
+            | NetResultStatus::ConnectionError
+            | NetResultStatus::TorNetError
+            | NetResultStatus::SocketError
+            | NetResultStatus::RequestTimeout
+            | NetResultStatus::TorInitializationFailed
+            | NetResultStatus::StreamOverflow => NetErrorCategory::Network,
+            NetResultStatus::InvalidUrl
+            | NetResultStatus::InvalidRequestParameters
+            | NetResultStatus::InvalidConfigParameters
+            | NetResultStatus::TransportNotFound
+            | NetResultStatus::InvalidTorConfig
+            | NetResultStatus::TorClientNotInitialized
+            | NetResultStatus::InstanceDoesNotExist
+            | NetResultStatus::UnsupportedScheme
+            | NetResultStatus::HostNotAllowed => NetErrorCategory::Configuration,
+            NetResultStatus::Http2ConctionFailed => NetErrorCategory::Protocol,
+            NetResultStatus::InternalError => NetErrorCategory::Internal,
+        }
+    }
 }
 
 impl fmt::Display for NetResultStatus {
@@ -33,3 +119,129 @@ impl fmt::Display for NetResultStatus {
 }
 
 impl std::error::Error for NetResultStatus {}
+
+type NetErrorSource = Arc<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Error carrying the status reported to Dart together with the context needed to diagnose it
+#[derive(Clone)]
+pub struct NetError {
+    status: NetResultStatus,
+    message: Option<String>,
+    url: Option<String>,
+    source: Option<NetErrorSource>,
+    grpc_status: Option<Box<NetGrpcStatus>>,
+}
+
+impl NetError {
+    pub fn new(status: NetResultStatus) -> Self {
+        Self {
+            status,
+            message: None,
+            url: None,
+            source: None,
+            grpc_status: None,
+        }
+    }
+
+    /// A subsystem that was left out of this build by its cargo feature
+    pub fn feature_disabled(feature: &str) -> Self {
+        NetError::new(NetResultStatus::InvalidConfigParameters)
+            .with_message(format!("net_sdk was built without the `{}` feature", feature))
+    }
+
+    /// `lost` frames were dropped for a subscriber that fell behind
+    pub fn stream_overflow(lost: u64) -> Self {
+        NetError::new(NetResultStatus::StreamOverflow)
+            .with_message(format!("{} frames dropped for a slow subscriber", lost))
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Host, `host:port` or URL of the endpoint that failed
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn with_source<E>(mut self, source: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    /// Status returned by a gRPC server, including any `google.rpc.Status` details
+    pub fn with_grpc_status(mut self, grpc_status: NetGrpcStatus) -> Self {
+        self.grpc_status = Some(Box::new(grpc_status));
+        self
+    }
+
+    pub fn status(&self) -> NetResultStatus {
+        self.status
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    pub fn grpc_status(&self) -> Option<&NetGrpcStatus> {
+        self.grpc_status.as_deref()
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.status.is_transient()
+    }
+
+    pub fn category(&self) -> NetErrorCategory {
+        self.status.category()
+    }
+}
+
+impl From<NetResultStatus> for NetError {
+    fn from(status: NetResultStatus) -> Self {
+        NetError::new(status)
+    }
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.status)?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        if let Some(url) = &self.url {
+            write!(f, " ({})", url)?;
+        }
+        let mut source = self
+            .source
+            .as_deref()
+            .map(|e| e as &(dyn std::error::Error + 'static));
+        while let Some(err) = source {
+            write!(f, "; caused by: {}", err)?;
+            source = err.source();
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NetError({})", self)
+    }
+}
+
+impl std::error::Error for NetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
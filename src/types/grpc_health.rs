@@ -0,0 +1,72 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::types::{
+    error::{NetError, NetResultStatus},
+    grpc_status::{ProtoReader, WIRE_VARINT},
+};
+
+/// `ServingStatus` of `grpc.health.v1.HealthCheckResponse`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetGrpcServingStatus {
+    Unknown = 0,
+    Serving = 1,
+    NotServing = 2,
+    /// Only sent to `Watch` calls, for a service the server does not know (yet)
+    ServiceUnknown = 3,
+}
+
+/// The `grpc.health.v1.Health` service, encoded here so callers need no protobuf of their own
+pub(crate) struct NetGrpcHealth;
+
+impl NetGrpcHealth {
+    pub const CHECK: &'static str = "/grpc.health.v1.Health/Check";
+    pub const WATCH: &'static str = "/grpc.health.v1.Health/Watch";
+
+    /// `HealthCheckRequest` for `service`, empty for the server as a whole
+    pub fn request(service: &str) -> Vec<u8> {
+        if service.is_empty() {
+            return Vec::new();
+        }
+        let mut request = vec![0x0a];
+        let mut len = service.len();
+        while len >= 0x80 {
+            request.push((len as u8 & 0x7f) | 0x80);
+            len >>= 7;
+        }
+        request.push(len as u8);
+        request.extend_from_slice(service.as_bytes());
+        request
+    }
+
+    /// Status of a `HealthCheckResponse`; statuses added after `ServiceUnknown` read as
+    /// `Unknown`, malformed input returns `None`
+    pub fn status(response: &[u8]) -> Option<NetGrpcServingStatus> {
+        let mut reader = ProtoReader::new(response);
+        let mut status = NetGrpcServingStatus::Unknown;
+        while let Some((field, wire)) = reader.key()? {
+            match (field, wire) {
+                (1, WIRE_VARINT) => {
+                    status = match reader.varint()? {
+                        1 => NetGrpcServingStatus::Serving,
+                        2 => NetGrpcServingStatus::NotServing,
+                        3 => NetGrpcServingStatus::ServiceUnknown,
+                        _ => NetGrpcServingStatus::Unknown,
+                    }
+                }
+                _ => reader.skip(wire)?,
+            }
+        }
+        Some(status)
+    }
+
+    /// Status of the answer to a `Check`
+    pub fn check_status(response: &[u8]) -> Result<NetGrpcServingStatus, NetError> {
+        NetGrpcHealth::status(response).ok_or_else(|| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message("malformed grpc health response")
+        })
+    }
+}
@@ -0,0 +1,105 @@
+use bytes::Bytes;
+#[cfg(feature = "grpc-json")]
+use prost::Message;
+#[cfg(feature = "grpc-json")]
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor};
+
+#[cfg(feature = "grpc-json")]
+use crate::types::error::NetResultStatus;
+use crate::types::{config::NetConfig, error::NetError};
+
+/// Transcoder of a transport's gRPC calls between JSON and protobuf, with the descriptor set
+/// of `NetConfigRequest::grpc_descriptor_set`, so Dart can call simple services without
+/// generated code. JSON follows the proto3 mapping: camelCase field names out, either form
+/// in, 64 bit integers as strings
+#[cfg(feature = "grpc-json")]
+#[derive(Clone)]
+pub(crate) struct NetGrpcJson {
+    pool: DescriptorPool,
+}
+
+/// Never made without the `grpc-json` feature, a descriptor set is refused instead
+#[cfg(not(feature = "grpc-json"))]
+#[derive(Clone)]
+pub(crate) enum NetGrpcJson {}
+
+impl NetGrpcJson {
+    /// Transcoder of `config`'s descriptor set; `None` when it has none, leaving calls raw
+    pub fn from_config(config: &NetConfig) -> Result<Option<NetGrpcJson>, NetError> {
+        let Some(descriptor_set) = &config.grpc_descriptor_set else {
+            return Ok(None);
+        };
+        #[cfg(feature = "grpc-json")]
+        {
+            let pool = DescriptorPool::decode(descriptor_set.clone()).map_err(|e| {
+                NetError::new(NetResultStatus::InvalidConfigParameters)
+                    .with_message("invalid grpc descriptor set")
+                    .with_source(e)
+            })?;
+            Ok(Some(NetGrpcJson { pool }))
+        }
+        #[cfg(not(feature = "grpc-json"))]
+        {
+            let _ = descriptor_set;
+            Err(NetError::feature_disabled("grpc-json"))
+        }
+    }
+}
+
+#[cfg(feature = "grpc-json")]
+impl NetGrpcJson {
+    /// The request message of `method` for its JSON
+    pub fn encode(&self, method: &str, json: &[u8]) -> Result<Bytes, NetError> {
+        let input = self.method(method)?.input();
+        let mut deserializer = serde_json::Deserializer::from_slice(json);
+        let message = DynamicMessage::deserialize(input, &mut deserializer)
+            .and_then(|message| deserializer.end().map(|_| message))
+            .map_err(|e| {
+                NetError::new(NetResultStatus::InvalidRequestParameters)
+                    .with_message(format!("request is not json of {}", method))
+                    .with_source(e)
+            })?;
+        Ok(Bytes::from(message.encode_to_vec()))
+    }
+
+    /// JSON of a response message of `method`
+    pub fn decode(&self, method: &str, message: &[u8]) -> Result<Vec<u8>, NetError> {
+        let output = self.method(method)?.output();
+        let message = DynamicMessage::decode(output, message).map_err(|e| {
+            NetError::new(NetResultStatus::ConnectionError)
+                .with_message(format!("malformed response of {}", method))
+                .with_source(e)
+        })?;
+        serde_json::to_vec(&message).map_err(|e| {
+            NetError::new(NetResultStatus::InternalError)
+                .with_message(format!("response of {} has no json form", method))
+                .with_source(e)
+        })
+    }
+
+    /// `method` as called, `/package.Service/Method`
+    fn method(&self, method: &str) -> Result<MethodDescriptor, NetError> {
+        let (service, name) = method
+            .trim_start_matches('/')
+            .split_once('/')
+            .unwrap_or_default();
+        self.pool
+            .get_service_by_name(service)
+            .and_then(|service| service.methods().find(|m| m.name() == name))
+            .ok_or_else(|| {
+                NetError::new(NetResultStatus::InvalidRequestParameters)
+                    .with_message(format!("{} is not in the grpc descriptor set", method))
+            })
+    }
+}
+
+#[cfg(not(feature = "grpc-json"))]
+impl NetGrpcJson {
+    pub fn encode(&self, _method: &str, _json: &[u8]) -> Result<Bytes, NetError> {
+        match *self {}
+    }
+
+    pub fn decode(&self, _method: &str, _message: &[u8]) -> Result<Vec<u8>, NetError> {
+        match *self {}
+    }
+}
@@ -0,0 +1,147 @@
+#[cfg(feature = "grpc")]
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "grpc")]
+use crate::types::error::NetError;
+
+/// Status codes of gRPC, `OK` through `UNAUTHENTICATED`
+const CODES: usize = 17;
+
+/// Counters of a gRPC transport since it was created, answer of a `Stats` request. Health
+/// checks and watches are calls like any other; the bytes are those of the protobuf
+/// messages, JSON transcoding and framing left out
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetGrpcStats {
+    unary_calls: u64,
+    streams: u64,
+    active_streams: u32,
+    messages_sent: u64,
+    messages_received: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+    errors: [u64; CODES],
+    failures: u64,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetGrpcStats {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn unary_calls(&self) -> u64 {
+        self.unary_calls
+    }
+    /// Streams opened, a restart of one counted once
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn streams(&self) -> u64 {
+        self.streams
+    }
+    /// Streams still forwarding messages
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn active_streams(&self) -> u32 {
+        self.active_streams
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn messages_sent(&self) -> u64 {
+        self.messages_sent
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn messages_received(&self) -> u64 {
+        self.messages_received
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+    /// Failed calls by the gRPC status code they ended with, indexed by code
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn errors(&self) -> Vec<u64> {
+        self.errors.to_vec()
+    }
+    /// Failed calls that got no gRPC status, e.g. the server could not be reached or the
+    /// request was invalid
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+}
+
+impl NetGrpcStats {
+    pub fn errors_ref(&self) -> &[u64; CODES] {
+        &self.errors
+    }
+}
+
+/// What `NetGrpcStats` is read from, counted by the transport as its calls go
+#[cfg(feature = "grpc")]
+#[derive(Default)]
+pub(crate) struct GrpcStats {
+    unary_calls: AtomicU64,
+    streams: AtomicU64,
+    active_streams: AtomicU32,
+    messages_sent: AtomicU64,
+    messages_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    errors: [AtomicU64; CODES],
+    failures: AtomicU64,
+}
+
+#[cfg(feature = "grpc")]
+impl GrpcStats {
+    pub fn unary(&self) {
+        self.unary_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stream_opened(&self) {
+        self.streams.fetch_add(1, Ordering::Relaxed);
+        self.active_streams.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stream_closed(&self) {
+        self.active_streams.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// A message of `len` bytes went out
+    pub fn sent(&self, len: usize) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// A message of `len` bytes came in
+    pub fn received(&self, len: usize) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    pub fn failed(&self, error: &NetError) {
+        let code = error
+            .grpc_status()
+            .and_then(|status| usize::try_from(status.code()).ok())
+            .filter(|code| *code < CODES);
+        match code {
+            Some(code) => self.errors[code].fetch_add(1, Ordering::Relaxed),
+            None => self.failures.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn snapshot(&self) -> NetGrpcStats {
+        NetGrpcStats {
+            unary_calls: self.unary_calls.load(Ordering::Relaxed),
+            streams: self.streams.load(Ordering::Relaxed),
+            active_streams: self.active_streams.load(Ordering::Relaxed),
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            errors: std::array::from_fn(|code| self.errors[code].load(Ordering::Relaxed)),
+            failures: self.failures.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+/// One `google.protobuf.Any` entry of a `google.rpc.Status`, e.g. RetryInfo or BadRequest
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct NetGrpcStatusDetail {
+    type_url: String,
+    value: Vec<u8>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetGrpcStatusDetail {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn type_url(&self) -> String {
+        self.type_url.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn value(&self) -> Vec<u8> {
+        self.value.clone()
+    }
+}
+
+/// gRPC status returned by the server, including the decoded `grpc-status-details-bin` envelope
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct NetGrpcStatus {
+    code: i32,
+    message: String,
+    details: Vec<NetGrpcStatusDetail>,
+    /// `grpc-status-details-bin` as sent, for servers whose envelope is not a `google.rpc.Status`
+    details_bin: Vec<u8>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetGrpcStatus {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn details(&self) -> Vec<NetGrpcStatusDetail> {
+        self.details.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn details_bin(&self) -> Vec<u8> {
+        self.details_bin.clone()
+    }
+}
+
+impl NetGrpcStatus {
+    pub fn new(code: i32, message: String, details: Vec<NetGrpcStatusDetail>) -> Self {
+        Self {
+            code,
+            message,
+            details,
+            details_bin: vec![],
+        }
+    }
+
+    pub fn details_bin_ref(&self) -> &[u8] {
+        &self.details_bin
+    }
+
+    /// Decodes a serialized `google.rpc.Status` message.
+    /// unknown fields are skipped; malformed input returns `None`.
+    pub fn decode(bytes: &[u8]) -> Option<NetGrpcStatus> {
+        let mut reader = ProtoReader::new(bytes);
+        let mut status = NetGrpcStatus::new(0, String::new(), vec![]);
+        while let Some((field, wire)) = reader.key()? {
+            match (field, wire) {
+                (1, WIRE_VARINT) => status.code = reader.varint()? as i32,
+                (2, WIRE_LEN) => {
+                    status.message = String::from_utf8(reader.bytes()?.to_vec()).ok()?
+                }
+                (3, WIRE_LEN) => status.details.push(Self::decode_any(reader.bytes()?)?),
+                _ => reader.skip(wire)?,
+            }
+        }
+        Some(status)
+    }
+
+    fn decode_any(bytes: &[u8]) -> Option<NetGrpcStatusDetail> {
+        let mut reader = ProtoReader::new(bytes);
+        let mut detail = NetGrpcStatusDetail {
+            type_url: String::new(),
+            value: vec![],
+        };
+        while let Some((field, wire)) = reader.key()? {
+            match (field, wire) {
+                (1, WIRE_LEN) => {
+                    detail.type_url = String::from_utf8(reader.bytes()?.to_vec()).ok()?
+                }
+                (2, WIRE_LEN) => detail.value = reader.bytes()?.to_vec(),
+                _ => reader.skip(wire)?,
+            }
+        }
+        Some(detail)
+    }
+}
+
+#[cfg(feature = "grpc")]
+impl From<&tonic::Status> for NetGrpcStatus {
+    fn from(status: &tonic::Status) -> Self {
+        let details = status.details();
+        let mut decoded = match NetGrpcStatus::decode(details) {
+            Some(decoded) if !details.is_empty() => decoded,
+            _ => NetGrpcStatus::new(status.code() as i32, status.message().to_string(), vec![]),
+        };
+        // the envelope may leave out code or message, `grpc-status` is what the call ended with
+        decoded.code = status.code() as i32;
+        if decoded.message.is_empty() {
+            decoded.message = status.message().to_string();
+        }
+        decoded.details_bin = details.to_vec();
+        decoded
+    }
+}
+
+pub(crate) const WIRE_VARINT: u8 = 0;
+const WIRE_I64: u8 = 1;
+pub(crate) const WIRE_LEN: u8 = 2;
+const WIRE_I32: u8 = 5;
+
+pub(crate) struct ProtoReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ProtoReader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// `Some(None)` at end of input, `None` on malformed data.
+    pub(crate) fn key(&mut self) -> Option<Option<(u64, u8)>> {
+        if self.pos >= self.buf.len() {
+            return Some(None);
+        }
+        let key = self.varint()?;
+        Some(Some((key >> 3, (key & 0x7) as u8)))
+    }
+
+    pub(crate) fn varint(&mut self) -> Option<u64> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.buf.get(self.pos)?;
+            self.pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let slice = self.buf.get(self.pos..end)?;
+        self.pos = end;
+        Some(slice)
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.varint()? as usize;
+        self.take(len)
+    }
+
+    pub(crate) fn skip(&mut self, wire: u8) -> Option<()> {
+        match wire {
+            WIRE_VARINT => self.varint().map(|_| ()),
+            WIRE_I64 => self.take(8).map(|_| ()),
+            WIRE_LEN => self.bytes().map(|_| ()),
+            WIRE_I32 => self.take(4).map(|_| ()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{
+    AddressInfo,
+    error::{NetError, NetResultStatus},
+};
+
+/// Hosts, address ranges and schemes a transport may dial, checked before every connection.
+///
+/// The default policy allows everything. Host rules match case-insensitively; a leading
+/// `*.` matches any subdomain, e.g. `*.example.com`. Address rules apply to IP literals and,
+/// on clearnet, to every address a host name resolves to.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetHostPolicy {
+    /// When not empty only these hosts are dialed
+    pub allow_hosts: Vec<String>,
+    pub deny_hosts: Vec<String>,
+    /// CIDR ranges such as `10.0.0.0/8` or `fd00::/8`; a bare address blocks just that address
+    pub deny_cidrs: Vec<String>,
+    /// Block loopback, private (RFC 1918), shared (RFC 6598), link-local, unique-local and
+    /// unspecified addresses
+    pub deny_private: bool,
+    /// When not empty only urls with these schemes are dialed, e.g. `https`, `wss`
+    pub allow_schemes: Vec<String>,
+}
+
+impl NetHostPolicy {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether resolved addresses must be checked before connecting
+    pub fn filters_addresses(&self) -> bool {
+        self.deny_private || !self.deny_cidrs.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<(), NetResultStatus> {
+        for cidr in &self.deny_cidrs {
+            Cidr::parse(cidr).ok_or(NetResultStatus::InvalidConfigParameters)?;
+        }
+        Ok(())
+    }
+
+    /// Scheme, host and IP literal checks for `addr`. host rules do not apply to `unix://`
+    pub fn check_address(&self, addr: &AddressInfo) -> Result<(), NetError> {
+        let denied = |message: String| {
+            NetError::new(NetResultStatus::HostNotAllowed)
+                .with_message(message)
+                .with_url(addr.url.clone())
+        };
+        if !self.allow_schemes.is_empty() {
+            let scheme = addr.url.split_once("://").map(|(scheme, _)| scheme);
+            if !scheme.is_some_and(|scheme| {
+                self.allow_schemes
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+            }) {
+                return Err(denied("scheme is not allowed".to_string()));
+            }
+        }
+        if addr.host.is_empty() {
+            return Ok(());
+        }
+        if self
+            .deny_hosts
+            .iter()
+            .any(|p| Self::host_matches(p, &addr.host))
+        {
+            return Err(denied(format!("host {} is denied", addr.host)));
+        }
+        if !self.allow_hosts.is_empty()
+            && !self
+                .allow_hosts
+                .iter()
+                .any(|p| Self::host_matches(p, &addr.host))
+        {
+            return Err(denied(format!("host {} is not allowed", addr.host)));
+        }
+        match addr.host.parse::<IpAddr>() {
+            Ok(ip) => self.check_ip(ip).map_err(|e| e.with_url(addr.url.clone())),
+            Err(_) => Ok(()),
+        }
+    }
+
+    pub fn check_ip(&self, ip: IpAddr) -> Result<(), NetError> {
+        let ip = ip.to_canonical();
+        if self.deny_private && Self::is_private(ip) {
+            return Err(NetError::new(NetResultStatus::HostNotAllowed)
+                .with_message(format!("address {} is private", ip)));
+        }
+        if self
+            .deny_cidrs
+            .iter()
+            .filter_map(|cidr| Cidr::parse(cidr))
+            .any(|cidr| cidr.contains(ip))
+        {
+            return Err(NetError::new(NetResultStatus::HostNotAllowed)
+                .with_message(format!("address {} is in a denied range", ip)));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn host_matches(pattern: &str, host: &str) -> bool {
+        let pattern = pattern.trim_end_matches('.');
+        let host = host.trim_end_matches('.');
+        match pattern.strip_prefix("*.") {
+            Some(domain) => host.len().checked_sub(domain.len() + 1).is_some_and(|dot| {
+                host.as_bytes()[dot] == b'.' && host[dot + 1..].eq_ignore_ascii_case(domain)
+            }),
+            None => pattern.eq_ignore_ascii_case(host),
+        }
+    }
+
+    fn is_private(ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => {
+                let [a, b, ..] = ip.octets();
+                ip.is_private()
+                    || ip.is_loopback()
+                    || ip.is_link_local()
+                    || ip.is_unspecified()
+                    || ip.is_broadcast()
+                    || (a == 100 && (64..128).contains(&b))
+            }
+            IpAddr::V6(ip) => {
+                let first = ip.segments()[0];
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80
+            }
+        }
+    }
+}
+
+struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn parse(value: &str) -> Option<Self> {
+        let (network, prefix) = match value.split_once('/') {
+            Some((network, prefix)) => (network.parse::<IpAddr>().ok()?, prefix.parse().ok()?),
+            None => {
+                let network = value.parse::<IpAddr>().ok()?;
+                (network, if network.is_ipv4() { 32 } else { 128 })
+            }
+        };
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return None;
+        }
+        // `::ffff:a.b.c.d/n` ranges are matched as IPv4, like the addresses `check_ip` sees
+        match network.to_canonical() {
+            IpAddr::V4(v4) if network.is_ipv6() && prefix >= 96 => Some(Self {
+                network: IpAddr::V4(v4),
+                prefix: prefix - 96,
+            }),
+            _ => Some(Self { network, prefix }),
+        }
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let mask = |bits: u32| match self.prefix {
+            0 => 0,
+            prefix => u128::MAX << (bits - prefix as u32),
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = mask(32) as u32;
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = mask(128);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
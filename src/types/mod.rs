@@ -1,21 +1,108 @@
 use std::sync::Arc;
 
 use crate::types::response::NetResponseKind;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
 
+pub mod builder;
 pub mod config;
 pub mod error;
+pub mod grpc_health;
+#[cfg(feature = "grpc")]
+pub mod grpc_json;
+pub mod grpc_stats;
+pub mod grpc_status;
+pub mod host_policy;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod native;
 #[cfg(target_arch = "wasm32")]
 pub mod request;
 pub mod response;
 
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressTransport {
+    Tcp = 1,
+    Udp = 2,
+    Unix = 3,
+}
+
 #[derive(Debug, Clone)]
 pub struct AddressInfo {
+    pub transport: AddressTransport,
+    /// Domain or IP literal; IPv6 addresses are stored without brackets. empty for `unix://`
     pub host: String,
     pub url: String,
     pub port: u16,
     pub is_tls: bool,
+    /// IPv6 zone id of a link-local address, e.g. `eth0` or `3`
+    pub scope_id: Option<String>,
+    /// `Basic` credentials taken from `user:pass@` in the URL; they are removed from `url`
+    pub authorization: Option<String>,
+    /// Socket file of a `unix://` address; `port` is 0 in that case
+    pub path: Option<String>,
+}
+
+/// Parsed endpoint returned to Dart so user input can be validated before creating a transport
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct NetAddressInfoWasm {
+    addr: AddressInfo,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetAddressInfoWasm {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn transport(&self) -> AddressTransport {
+        self.addr.transport
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn host(&self) -> String {
+        self.addr.host.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn port(&self) -> u16 {
+        self.addr.port
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn is_tls(&self) -> bool {
+        self.addr.is_tls
+    }
+    /// The url without credentials
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn url(&self) -> String {
+        self.addr.url.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn scope_id(&self) -> Option<String> {
+        self.addr.scope_id.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn path(&self) -> Option<String> {
+        self.addr.path.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn has_credentials(&self) -> bool {
+        self.addr.authorization.is_some()
+    }
+}
+
+impl From<AddressInfo> for NetAddressInfoWasm {
+    fn from(addr: AddressInfo) -> Self {
+        Self { addr }
+    }
+}
+
+impl AddressInfo {
+    /// `host:port`, bracketing IPv6 literals
+    pub fn authority(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -1,23 +1,139 @@
-use std::{mem::ManuallyDrop, slice};
+use std::{mem::ManuallyDrop, slice, sync::Arc};
+
+use bytes::Bytes;
+use rustls::sign::CertifiedKey;
 
 use crate::{
+    stream::ClientCertRegistry,
     types::{
+        AddressInfo,
         config::{
-            NetConfigHttp, NetConfigRequest, NetConfigTor, NetHttpHeader, NetHttpProtocol, NetMode,
-            NetProtocol, NetTlsMode,
+            NetConfigHttp, NetConfigOtel, NetConfigRequest, NetConfigTor, NetConnectResolver,
+            NetHttp2Settings, NetHttpAuth, NetHttpCache, NetHttpHeader, NetHttpHeaderMerge,
+            NetHttpHostOverride, NetHttpOfflineQueue, NetHttpPool, NetHttpProtocol, NetHttpSigning,
+            NetMode, NetProtocol, NetProxySocks5, NetStreamIdleTimeout, NetStreamOverflow,
+            NetStreamQueue, NetStreamReconnect, NetStreamSendBuffer, NetTimeouts, NetTlsMode,
         },
-        error::NetResultStatus,
+        error::{NetError, NetResultStatus},
+        grpc_status::NetGrpcStatus,
+        host_policy::NetHostPolicy,
         native::request::{
-            NetHttpHeaderRef, NetHttpRetryConfig, NetRequest, NetRequestGrpc, NetRequestGrpcStream,
-            NetRequestGrpcUnary, NetRequestGrpcUnsubscribe, NetRequestHttp, NetRequestKind,
-            NetRequestSocket, NetRequestSocketSend,
+            NetDnsResolver, NetHttpHeaderRef, NetHttpResume, NetHttpRetryConfig, NetRequest,
+            NetRequestCaptivePortal, NetRequestDns, NetRequestDnsPrefetch, NetRequestGrpc,
+            NetRequestGrpcCredit, NetRequestGrpcHealth, NetRequestGrpcRebuild,
+            NetRequestGrpcStream, NetRequestGrpcUnary, NetRequestGrpcUnsubscribe, NetRequestHttp,
+            NetRequestHttpEvents, NetRequestKind, NetRequestMdns, NetRequestNtp, NetRequestPing,
+            NetRequestPortCheck, NetRequestPortMapping, NetRequestSocket, NetRequestSocketSend,
+            NetRequestSpeedTest, NetRequestSplice, NetRequestStun,
+        },
+        response::{
+            NetDnsRecord, NetDnsRecordType, NetInterface, NetMdnsService, NetPortMappingProtocol,
+            NetResponse, NetResponseDns, NetResponseGrpc, NetResponseHttp, NetResponseInterfaces,
+            NetResponseKind, NetResponseMdns, NetResponsePing, NetResponsePortMapping,
+            NetResponseSpeedTest, NetResponseStream, NetResponseStun, NetStunMapping,
         },
-        response::{NetResponse, NetResponseGrpc, NetResponseKind, NetResponseStream},
     },
     utils::{Utils, buffer::StreamEncoding},
 };
 use libc::c_char;
 
+/// url utils
+#[repr(C)]
+pub struct NetUrlQueryParamC {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+impl NetUrlQueryParamC {
+    /// # Safety
+    /// `params` must be null or point to `len` entries holding valid C strings.
+    pub unsafe fn to_pairs<'a>(params: *const NetUrlQueryParamC, len: u32) -> Vec<(&'a str, &'a str)> {
+        if params.is_null() {
+            return vec![];
+        }
+        unsafe { slice::from_raw_parts(params, len as usize) }
+            .iter()
+            .map(|p| unsafe {
+                (
+                    Utils::cstr_to_str(p.key as *const u8),
+                    Utils::cstr_to_str(p.value as *const u8),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Callback delivery of an instance, filled by `dart_transporter_callback_stats`
+#[repr(C)]
+pub struct NetCallbackStatsC {
+    /// Responses waiting for the callback
+    pub pending: u32,
+    pub delivered: u64,
+    /// Stream data dropped because too much was waiting
+    pub dropped: u64,
+}
+
+/// Passed to the callback of `dart_set_client_cert_callback` when a server asks for a
+/// client certificate; `dart_client_cert_select` answers and frees it
+#[repr(C)]
+pub struct NetClientCertRequestC {
+    pub request_id: u32,
+    pub host: *const c_char,
+    /// DER distinguished names of the CAs the server accepts; none when it named none
+    pub issuers: *const BytesRefC,
+    pub issuers_len: u32,
+}
+
+/// DER certificate chain, leaf first, and its PKCS#8, SEC1 or PKCS#1 DER key
+#[repr(C)]
+pub struct NetClientIdentityC {
+    pub certs: *const BytesRefC,
+    pub certs_len: u8,
+    pub key: BytesRefC,
+}
+
+/// `transport` is `AddressTransport`; `scope_id` and `path` are null when absent
+#[repr(C)]
+pub struct NetAddressInfoC {
+    pub transport: u8,
+    pub host: *const c_char,
+    pub port: u16,
+    pub is_tls: bool,
+    pub url: *const c_char,
+    pub scope_id: *const c_char,
+    pub path: *const c_char,
+    pub has_credentials: bool,
+}
+impl AddressInfo {
+    pub fn to_c(&self) -> NetAddressInfoC {
+        let optional = |value: &Option<String>| match value {
+            Some(v) => (unsafe { string_to_c_ptr(v.clone()) }) as *const c_char,
+            None => std::ptr::null(),
+        };
+        NetAddressInfoC {
+            transport: self.transport as u8,
+            host: unsafe { string_to_c_ptr(self.host.clone()) } as *const _,
+            port: self.port,
+            is_tls: self.is_tls,
+            url: unsafe { string_to_c_ptr(self.url.clone()) } as *const _,
+            scope_id: optional(&self.scope_id),
+            path: optional(&self.path),
+            has_credentials: self.authorization.is_some(),
+        }
+    }
+}
+impl NetAddressInfoC {
+    /// # Safety
+    /// must only be called once on a value filled by `AddressInfo::to_c`.
+    pub unsafe fn free_memory(&self) {
+        unsafe {
+            free_c_string(self.host as *mut u8);
+            free_c_string(self.url as *mut u8);
+            free_c_string(self.scope_id as *mut u8);
+            free_c_string(self.path as *mut u8);
+        }
+    }
+}
+
 /// configs
 #[repr(C)]
 pub struct NetHttpHeaderC {
@@ -29,9 +145,160 @@ pub struct NetHttpHeaderC {
 pub struct NetConfigHttpC {
     pub headers: *const NetHttpHeaderC,
     pub headers_len: u8,
+    /// `NetHttpHeaderMerge`, 0 for replace
+    pub header_merge: u8,
+
+    /// `NetHttpProtocol`, 0 to negotiate HTTP/2 or HTTP/1.1
+    pub protocol: u8,
+    pub decode_charset: bool,
+    /// Null without an offline queue
+    pub offline_queue: *const NetHttpOfflineQueueC,
+    /// Null without a response cache
+    pub cache: *const NetHttpCacheC,
+    /// Null to send requests unsigned
+    pub signing: *const NetHttpSigningC,
+    /// Null for the default pools
+    pub pool: *const NetHttpPoolC,
+    /// Null to send no credentials of the config
+    pub auth: *const NetHttpAuthC,
+    /// See `NetConfigHttp::global_client`
+    pub global_client: bool,
+    /// Null for the default HTTP/2 settings
+    pub http2_settings: *const NetHttp2SettingsC,
+    /// See `NetConfigHttp::coalesce`
+    pub coalesce: bool,
+    pub host_overrides: *const NetHttpHostOverrideC,
+    pub host_overrides_len: u8,
+}
+/// `kind` 1 is basic with `username` and `password`, 2 is bearer with `token`
+#[repr(C)]
+pub struct NetHttpAuthC {
+    pub kind: u8,
+    pub username: *const c_char,
+    pub password: *const c_char,
+    pub token: *const c_char,
+}
 
+impl TryFrom<&NetHttpAuthC> for NetHttpAuth {
+    type Error = NetResultStatus;
+    fn try_from(c: &NetHttpAuthC) -> Result<Self, NetResultStatus> {
+        let string = |ptr: *const c_char| match ptr.is_null() {
+            true => Err(NetResultStatus::InvalidConfigParameters),
+            false => Ok(unsafe { Utils::cstr_to_string(ptr as *const u8) }),
+        };
+        match c.kind {
+            1 => Ok(NetHttpAuth::Basic {
+                username: string(c.username)?,
+                password: string(c.password)?,
+            }),
+            2 => Ok(NetHttpAuth::Bearer {
+                token: string(c.token)?,
+            }),
+            _ => Err(NetResultStatus::InvalidConfigParameters),
+        }
+    }
+}
+/// `tls_mode` and `protocol` are those of `NetConfigRequestC` and `NetConfigHttpC`, 0
+/// keeps the configured one
+#[repr(C)]
+pub struct NetHttpHostOverrideC {
+    pub host: *const c_char,
+    pub tls_mode: u8,
     pub protocol: u8,
 }
+impl TryFrom<&NetHttpHostOverrideC> for NetHttpHostOverride {
+    type Error = NetResultStatus;
+    fn try_from(c: &NetHttpHostOverrideC) -> Result<Self, NetResultStatus> {
+        if c.host.is_null() {
+            return Err(NetResultStatus::InvalidConfigParameters);
+        }
+        Ok(Self {
+            host: unsafe { Utils::cstr_to_string(c.host as *const u8) },
+            tls_mode: match c.tls_mode {
+                0 => None,
+                1 => Some(NetTlsMode::Safe),
+                2 => Some(NetTlsMode::Dangerous),
+                _ => return Err(NetResultStatus::InvalidConfigParameters),
+            },
+            protocol: match c.protocol {
+                0 => None,
+                1 => Some(NetHttpProtocol::Http1),
+                2 => Some(NetHttpProtocol::Http2),
+                3 => Some(NetHttpProtocol::Http2PriorKnowledge),
+                4 => Some(NetHttpProtocol::Http3),
+                _ => return Err(NetResultStatus::InvalidConfigParameters),
+            },
+        })
+    }
+}
+/// Zero fields take their defaults, as in `NetHttpCache`
+#[repr(C)]
+pub struct NetHttpCacheC {
+    pub max_entries: u32,
+    pub max_body_size: u32,
+}
+/// Zero fields take their defaults, as in `NetHttpPool`
+#[repr(C)]
+pub struct NetHttpPoolC {
+    pub idle_timeout: u32,
+    pub max_idle_per_host: u32,
+    pub max_hosts: u32,
+    pub keep_alive: u32,
+}
+/// Zero fields take their defaults, as in `NetHttp2Settings`
+#[repr(C)]
+pub struct NetHttp2SettingsC {
+    pub initial_stream_window_size: u32,
+    pub initial_connection_window_size: u32,
+    pub max_concurrent_streams: u32,
+    pub max_frame_size: u32,
+    pub adaptive_window: bool,
+    pub ping_interval: u32,
+    pub ping_timeout: u32,
+    pub no_idle_pings: bool,
+}
+/// `kind` 1 is HMAC with `key_id` and `secret`, 2 is AWS SigV4 with all fields but
+/// `session_token`, which may be null
+#[repr(C)]
+pub struct NetHttpSigningC {
+    pub kind: u8,
+    pub key_id: *const c_char,
+    pub secret: *const c_char,
+    pub region: *const c_char,
+    pub service: *const c_char,
+    pub session_token: *const c_char,
+}
+
+impl TryFrom<&NetHttpSigningC> for NetHttpSigning {
+    type Error = NetResultStatus;
+    fn try_from(c: &NetHttpSigningC) -> Result<Self, NetResultStatus> {
+        let string = |ptr: *const c_char| match ptr.is_null() {
+            true => Err(NetResultStatus::InvalidConfigParameters),
+            false => Ok(unsafe { Utils::cstr_to_string(ptr as *const u8) }),
+        };
+        match c.kind {
+            1 => Ok(NetHttpSigning::Hmac {
+                key_id: string(c.key_id)?,
+                secret: string(c.secret)?,
+            }),
+            2 => Ok(NetHttpSigning::AwsV4 {
+                access_key: string(c.key_id)?,
+                secret_key: string(c.secret)?,
+                region: string(c.region)?,
+                service: string(c.service)?,
+                session_token: string(c.session_token).ok(),
+            }),
+            _ => Err(NetResultStatus::InvalidConfigParameters),
+        }
+    }
+}
+/// `path` may be null to keep the queue in memory
+#[repr(C)]
+pub struct NetHttpOfflineQueueC {
+    pub max_requests: u32,
+    pub ttl: u32,
+    pub path: *const c_char,
+}
 
 #[repr(C)]
 pub struct NetConfigTorC {
@@ -39,6 +306,29 @@ pub struct NetConfigTorC {
     pub state_dir: *const c_char,
 }
 
+/// `service_name` may be null, `headers` too when `headers_len` is 0
+#[repr(C)]
+pub struct NetConfigOtelC {
+    pub endpoint: *const c_char,
+    pub headers: *const NetHttpHeaderC,
+    pub headers_len: u8,
+    pub service_name: *const c_char,
+}
+
+/// String lists may be null when their length is 0
+#[repr(C)]
+pub struct NetHostPolicyC {
+    pub allow_hosts: *const *const c_char,
+    pub allow_hosts_len: u8,
+    pub deny_hosts: *const *const c_char,
+    pub deny_hosts_len: u8,
+    pub deny_cidrs: *const *const c_char,
+    pub deny_cidrs_len: u8,
+    pub deny_private: bool,
+    pub allow_schemes: *const *const c_char,
+    pub allow_schemes_len: u8,
+}
+
 #[repr(C)]
 pub struct NetConfigRequestC {
     pub url: *const c_char,
@@ -47,7 +337,149 @@ pub struct NetConfigRequestC {
     pub http: *const NetConfigHttpC,
     pub tls_mode: u8,
     pub stream_encoding: u8,
+    /// Connector name when `mode` is 3 (custom); ignored otherwise
+    pub custom_mode: *const c_char,
+    /// Endpoints tried in order after `url`; may be null when `fallback_urls_len` is 0
+    pub fallback_urls: *const *const c_char,
+    pub fallback_urls_len: u8,
+    /// Null for no restrictions
+    pub host_policy: *const NetHostPolicyC,
+    /// Null for the default queue
+    pub stream_queue: *const NetStreamQueueC,
+    /// See `NetConfigRequest::share_connection`
+    pub share_connection: bool,
+    /// Null to end the subscriptions when the connection drops
+    pub stream_reconnect: *const NetStreamReconnectC,
+    /// Null for no idle timeout
+    pub stream_idle_timeout: *const NetStreamIdleTimeoutC,
+    /// See `NetConfigRequest::network_reconnect`
+    pub network_reconnect: bool,
+    /// Proxy when `mode` is 4 (socks5); ignored otherwise
+    pub socks5: *const NetProxySocks5C,
+    /// Null to leave every step to the request timeout
+    pub timeouts: *const NetTimeoutsC,
+    /// Null for the system resolver
+    pub resolver: *const NetConnectResolverC,
+    /// Serialized `FileDescriptorSet` for JSON gRPC calls, copied; null for none
+    pub grpc_descriptor_set: *const u8,
+    pub grpc_descriptor_set_len: u32,
+    /// `:authority` of the gRPC calls; null for the url's
+    pub grpc_authority: *const c_char,
+    /// See `NetConfigRequest::grpc_idle_timeout`
+    pub grpc_idle_timeout: u32,
+}
+/// `kind` 1 is the system resolver, 2 the `servers_len` `servers`, 3 DoH at `doh_url`
+#[repr(C)]
+pub struct NetConnectResolverC {
+    pub kind: u8,
+    pub servers: *const *const c_char,
+    pub servers_len: u8,
+    pub doh_url: *const c_char,
+}
+impl TryFrom<&NetConnectResolverC> for NetConnectResolver {
+    type Error = NetResultStatus;
+    fn try_from(c: &NetConnectResolverC) -> Result<Self, NetResultStatus> {
+        match c.kind {
+            1 => Ok(NetConnectResolver::System),
+            2 => match unsafe { c_string_list(c.servers, c.servers_len)? } {
+                servers if servers.is_empty() => Err(NetResultStatus::InvalidConfigParameters),
+                servers => Ok(NetConnectResolver::Nameservers { servers }),
+            },
+            3 if !c.doh_url.is_null() => Ok(NetConnectResolver::Doh {
+                url: unsafe { Utils::cstr_to_string(c.doh_url as *const u8) },
+            }),
+            _ => Err(NetResultStatus::InvalidConfigParameters),
+        }
+    }
+}
+/// Seconds, see `NetTimeouts`
+#[repr(C)]
+pub struct NetTimeoutsC {
+    pub connect: u32,
+    pub tls_handshake: u32,
+    pub read: u32,
+}
+/// `username` and `password` may be null for a proxy without auth
+#[repr(C)]
+pub struct NetProxySocks5C {
+    pub host: *const c_char,
+    pub port: u16,
+    pub username: *const c_char,
+    pub password: *const c_char,
+}
+impl TryFrom<&NetProxySocks5C> for NetProxySocks5 {
+    type Error = NetResultStatus;
+    fn try_from(c: &NetProxySocks5C) -> Result<Self, NetResultStatus> {
+        let string = |ptr: *const c_char| match ptr.is_null() {
+            true => None,
+            false => Some(unsafe { Utils::cstr_to_string(ptr as *const u8) }),
+        };
+        Ok(NetProxySocks5 {
+            host: string(c.host).ok_or(NetResultStatus::InvalidConfigParameters)?,
+            port: c.port,
+            username: string(c.username),
+            password: string(c.password),
+        })
+    }
+}
+/// `overflow` is `NetStreamOverflow`
+#[repr(C)]
+pub struct NetStreamQueueC {
+    pub capacity: u32,
+    pub overflow: u8,
+}
+/// Zero fields take their defaults, as in `NetStreamReconnect`; `send_buffer` may be null
+#[repr(C)]
+pub struct NetStreamReconnectC {
+    pub max_attempts: u32,
+    pub delay_ms: u32,
+    pub max_delay_ms: u32,
+    pub send_buffer: *const NetStreamSendBufferC,
+}
+#[repr(C)]
+pub struct NetStreamIdleTimeoutC {
+    pub timeout: u32,
+    pub reconnect: bool,
+}
+#[repr(C)]
+pub struct NetStreamSendBufferC {
+    pub max_messages: u32,
+    pub ttl: u32,
+}
+/// Copies `len` non-null C strings; a null `ptr` is an empty list
+unsafe fn c_string_list(
+    ptr: *const *const c_char,
+    len: u8,
+) -> Result<Vec<String>, NetResultStatus> {
+    if ptr.is_null() {
+        return Ok(Vec::new());
+    }
+    unsafe {
+        std::slice::from_raw_parts(ptr, len.into())
+            .iter()
+            .map(|value| match value.is_null() {
+                true => Err(NetResultStatus::InvalidConfigParameters),
+                false => Ok(Utils::cstr_to_string(*value as *const u8)),
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<&NetHostPolicyC> for NetHostPolicy {
+    type Error = NetResultStatus;
+    fn try_from(c: &NetHostPolicyC) -> Result<Self, NetResultStatus> {
+        unsafe {
+            Ok(Self {
+                allow_hosts: c_string_list(c.allow_hosts, c.allow_hosts_len)?,
+                deny_hosts: c_string_list(c.deny_hosts, c.deny_hosts_len)?,
+                deny_cidrs: c_string_list(c.deny_cidrs, c.deny_cidrs_len)?,
+                deny_private: c.deny_private,
+                allow_schemes: c_string_list(c.allow_schemes, c.allow_schemes_len)?,
+            })
+        }
+    }
 }
+
 impl TryFrom<&NetHttpHeaderC> for NetHttpHeader {
     type Error = NetResultStatus;
     fn try_from(c: &NetHttpHeaderC) -> Result<Self, NetResultStatus> {
@@ -73,6 +505,29 @@ impl TryFrom<&NetConfigTorC> for NetConfigTor {
         })
     }
 }
+impl TryFrom<&NetConfigOtelC> for NetConfigOtel {
+    type Error = NetResultStatus;
+    fn try_from(c: &NetConfigOtelC) -> Result<Self, NetResultStatus> {
+        if c.endpoint.is_null() {
+            return Err(NetResultStatus::InvalidConfigParameters);
+        }
+        let headers = match c.headers.is_null() {
+            true => Vec::new(),
+            false => unsafe { std::slice::from_raw_parts(c.headers, c.headers_len.into()) }
+                .iter()
+                .map(NetHttpHeader::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        Ok(Self {
+            endpoint: unsafe { Utils::cstr_to_string(c.endpoint as *const u8) },
+            headers,
+            service_name: match c.service_name.is_null() {
+                true => None,
+                false => Some(unsafe { Utils::cstr_to_string(c.service_name as *const u8) }),
+            },
+        })
+    }
+}
 impl TryFrom<&NetConfigHttpC> for NetConfigHttp {
     type Error = NetResultStatus;
     fn try_from(c: &NetConfigHttpC) -> Result<Self, NetResultStatus> {
@@ -87,13 +542,85 @@ impl TryFrom<&NetConfigHttpC> for NetConfigHttp {
             }
         };
 
+        let header_merge = match c.header_merge {
+            0 | 1 => NetHttpHeaderMerge::Replace,
+            2 => NetHttpHeaderMerge::Merge,
+            _ => return Err(NetResultStatus::InvalidConfigParameters),
+        };
         let protocol = match c.protocol {
             0 => None,
             1 => Some(NetHttpProtocol::Http1),
             2 => Some(NetHttpProtocol::Http2),
+            3 => Some(NetHttpProtocol::Http2PriorKnowledge),
+            4 => Some(NetHttpProtocol::Http3),
             _ => return Err(NetResultStatus::InvalidConfigParameters),
         };
-        Ok(Self { headers, protocol })
+        let offline_queue = unsafe { c.offline_queue.as_ref() }.map(|queue| NetHttpOfflineQueue {
+            max_requests: queue.max_requests,
+            ttl: queue.ttl,
+            path: match queue.path.is_null() {
+                true => None,
+                false => Some(unsafe { Utils::cstr_to_string(queue.path as *const u8) }),
+            },
+        });
+        let cache = unsafe { c.cache.as_ref() }.map(|cache| NetHttpCache {
+            max_entries: cache.max_entries,
+            max_body_size: cache.max_body_size,
+        });
+        let signing = match unsafe { c.signing.as_ref() } {
+            Some(signing) => Some(NetHttpSigning::try_from(signing)?),
+            None => None,
+        };
+        let auth = match unsafe { c.auth.as_ref() } {
+            Some(auth) => Some(NetHttpAuth::try_from(auth)?),
+            None => None,
+        };
+        let pool = match unsafe { c.pool.as_ref() } {
+            Some(pool) => NetHttpPool {
+                idle_timeout: pool.idle_timeout,
+                max_idle_per_host: pool.max_idle_per_host,
+                max_hosts: pool.max_hosts,
+                keep_alive: pool.keep_alive,
+            },
+            None => NetHttpPool::default(),
+        };
+        let http2_settings = match unsafe { c.http2_settings.as_ref() } {
+            Some(settings) => NetHttp2Settings {
+                initial_stream_window_size: settings.initial_stream_window_size,
+                initial_connection_window_size: settings.initial_connection_window_size,
+                max_concurrent_streams: settings.max_concurrent_streams,
+                max_frame_size: settings.max_frame_size,
+                adaptive_window: settings.adaptive_window,
+                ping_interval: settings.ping_interval,
+                ping_timeout: settings.ping_timeout,
+                no_idle_pings: settings.no_idle_pings,
+            },
+            None => NetHttp2Settings::default(),
+        };
+        let host_overrides = match c.host_overrides.is_null() {
+            true => Vec::new(),
+            false => {
+                unsafe { std::slice::from_raw_parts(c.host_overrides, c.host_overrides_len.into()) }
+                    .iter()
+                    .map(NetHttpHostOverride::try_from)
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+        Ok(Self {
+            headers,
+            header_merge,
+            protocol,
+            decode_charset: c.decode_charset,
+            offline_queue,
+            cache,
+            signing,
+            auth,
+            pool,
+            global_client: c.global_client,
+            http2_settings,
+            coalesce: c.coalesce,
+            host_overrides,
+        })
     }
 }
 impl TryFrom<&NetConfigRequestC> for NetConfigRequest {
@@ -114,6 +641,12 @@ impl TryFrom<&NetConfigRequestC> for NetConfigRequest {
             mode: match c.mode {
                 1 => NetMode::Tor,
                 2 => NetMode::Clearnet,
+                3 if !c.custom_mode.is_null() => NetMode::Custom(unsafe {
+                    Utils::cstr_to_string(c.custom_mode as *const u8)
+                }),
+                4 if !c.socks5.is_null() => {
+                    NetMode::Socks5(NetProxySocks5::try_from(unsafe { &*c.socks5 })?)
+                }
                 _ => return Err(NetResultStatus::InvalidConfigParameters),
             },
             protocol: match c.protocol {
@@ -131,35 +664,145 @@ impl TryFrom<&NetConfigRequestC> for NetConfigRequest {
             encoding: match c.stream_encoding {
                 1 => StreamEncoding::Json,
                 2 => StreamEncoding::Raw,
+                3 => StreamEncoding::CborJson,
+                4 => StreamEncoding::Auto,
                 _ => return Err(NetResultStatus::InvalidConfigParameters),
             },
             http,
+            fallback_urls: unsafe { c_string_list(c.fallback_urls, c.fallback_urls_len)? },
+            host_policy: unsafe {
+                c.host_policy
+                    .as_ref()
+                    .map(NetHostPolicy::try_from)
+                    .transpose()?
+                    .unwrap_or_default()
+            },
+            stream_queue: match unsafe { c.stream_queue.as_ref() } {
+                Some(queue) => NetStreamQueue {
+                    capacity: queue.capacity,
+                    overflow: match queue.overflow {
+                        1 => NetStreamOverflow::Block,
+                        2 => NetStreamOverflow::Error,
+                        3 => NetStreamOverflow::Drop,
+                        _ => return Err(NetResultStatus::InvalidConfigParameters),
+                    },
+                },
+                None => NetStreamQueue::default(),
+            },
+            share_connection: c.share_connection,
+            stream_reconnect: unsafe { c.stream_reconnect.as_ref() }.map(|reconnect| {
+                NetStreamReconnect {
+                    max_attempts: reconnect.max_attempts,
+                    delay_ms: reconnect.delay_ms,
+                    max_delay_ms: reconnect.max_delay_ms,
+                    send_buffer: unsafe { reconnect.send_buffer.as_ref() }.map(|buffer| {
+                        NetStreamSendBuffer {
+                            max_messages: buffer.max_messages,
+                            ttl: buffer.ttl,
+                        }
+                    }),
+                }
+            }),
+            stream_idle_timeout: unsafe { c.stream_idle_timeout.as_ref() }.map(|idle| {
+                NetStreamIdleTimeout {
+                    timeout: idle.timeout,
+                    reconnect: idle.reconnect,
+                }
+            }),
+            network_reconnect: c.network_reconnect,
+            timeouts: match unsafe { c.timeouts.as_ref() } {
+                Some(timeouts) => NetTimeouts {
+                    connect: timeouts.connect,
+                    tls_handshake: timeouts.tls_handshake,
+                    read: timeouts.read,
+                },
+                None => NetTimeouts::default(),
+            },
+            resolver: match unsafe { c.resolver.as_ref() } {
+                Some(resolver) => NetConnectResolver::try_from(resolver)?,
+                None => NetConnectResolver::System,
+            },
+            grpc_descriptor_set: match c.grpc_descriptor_set.is_null() {
+                true => None,
+                false => Some(Bytes::copy_from_slice(unsafe {
+                    std::slice::from_raw_parts(
+                        c.grpc_descriptor_set,
+                        c.grpc_descriptor_set_len as usize,
+                    )
+                })),
+            },
+            grpc_authority: match c.grpc_authority.is_null() {
+                true => None,
+                false => Some(unsafe { Utils::cstr_to_string(c.grpc_authority as *const u8) }),
+            },
+            grpc_idle_timeout: c.grpc_idle_timeout,
+            tls_client_config: None,
         })
     }
 }
 
 /// request
 
+/// In a request, `owned` = 1 hands a buffer from `dart_alloc_bytes` over to Rust, which
+/// frees it; the caller must not touch it after the call. Any other buffer is copied and
+/// stays the caller's. Response buffers belong to the response.
 #[repr(C)]
 pub struct BytesRefC {
     pub ptr: *const u8,
     pub len: u32,
+    pub owned: u8,
 }
+/// `metadata` may be null when `metadata_len` is 0
 #[repr(C)]
 pub struct NetRequestGrpcUnaryC {
     pub method: *const c_char,
     pub data: BytesRefC,
+    pub metadata: *const NetHttpHeaderC,
+    pub metadata_len: u8,
+    /// Keeps dialing until the request times out while the server cannot be reached
+    pub wait_for_ready: bool,
+    /// `:authority` of this call; null for the config's
+    pub authority: *const c_char,
 }
+/// `metadata` may be null when `metadata_len` is 0
 #[repr(C)]
 pub struct NetRequestGrpcStreamC {
     pub method: *const c_char,
     pub data: BytesRefC,
+    pub metadata: *const NetHttpHeaderC,
+    pub metadata_len: u8,
+    /// Seconds the stream may run, 0 for no deadline
+    pub deadline: u32,
+    /// Messages posted before more are granted, 0 for no limit
+    pub credits: u32,
+    /// Keeps dialing until the request times out while the server cannot be reached
+    pub wait_for_ready: bool,
+    /// `:authority` of this call; null for the config's
+    pub authority: *const c_char,
+    /// See `NetRequestGrpcStream::rebuild`
+    pub rebuild: bool,
 }
 #[repr(C)]
 pub struct NetRequestGrpcUnsubscribeC {
     pub id: i32,
 }
 #[repr(C)]
+pub struct NetRequestGrpcCreditC {
+    pub id: i32,
+    pub credits: u32,
+}
+#[repr(C)]
+pub struct NetRequestGrpcRebuildC {
+    pub id: i32,
+    pub data: BytesRefC,
+}
+/// `service` null or empty asks about the server as a whole
+#[repr(C)]
+pub struct NetRequestGrpcHealthC {
+    pub service: *const c_char,
+    pub watch: bool,
+}
+#[repr(C)]
 pub struct NetRequestHttpC {
     pub method: *const c_char,
     pub url: *const c_char,
@@ -168,6 +811,20 @@ pub struct NetRequestHttpC {
     pub headers_len: u8,
     pub encoding: u8,
     pub retry_config: *const NetHttpRetryConfigC,
+    pub queue_offline: bool,
+    /// Null to return the body
+    pub download_path: *const c_char,
+    /// Bytes of `download_path` already received, 0 to download from the start
+    pub resume_offset: u64,
+    /// `If-Range` of a resumed download, null to send none
+    pub if_range: *const c_char,
+}
+
+/// Subscribes with `http`, or unsubscribes the events of stream `id` when `http` is null
+#[repr(C)]
+pub struct NetRequestHttpEventsC {
+    pub http: *const NetRequestHttpC,
+    pub id: i32,
 }
 
 pub struct NetHttpRetryConfigC {
@@ -175,22 +832,112 @@ pub struct NetHttpRetryConfigC {
     pub len: u8,
     pub max_retries: u8,
     pub retry_delay: u32,
+    pub max_retry_after: u32,
+}
+/// `resolver` is 1 system, 2 DoH, 3 Tor, 4 DoH over Tor; `doh_url` is required for 2 and 4
+#[repr(C)]
+pub struct NetRequestDnsC {
+    pub name: *const c_char,
+    pub record_type: u8,
+    pub resolver: u8,
+    pub doh_url: *const c_char,
+}
+/// `name` is a service type to browse, or an instance when `resolve` is set
+#[repr(C)]
+pub struct NetRequestMdnsC {
+    pub name: *const c_char,
+    pub resolve: bool,
+}
+/// `interval_ms` of 0 probes once per second
+#[repr(C)]
+pub struct NetRequestPingC {
+    pub host: *const c_char,
+    pub count: u16,
+    pub interval_ms: u32,
+}
+/// `servers` is an array of C strings; null for the default pool
+#[repr(C)]
+pub struct NetRequestNtpC {
+    pub servers: *const *const c_char,
+    pub servers_len: u8,
+}
+/// `servers` is an array of C strings; null for the default servers
+#[repr(C)]
+pub struct NetRequestStunC {
+    pub servers: *const *const c_char,
+    pub servers_len: u8,
+}
+/// `mode` is 1 Tor, 2 clearnet
+#[repr(C)]
+pub struct NetRequestPortCheckC {
+    pub host: *const c_char,
+    pub port: u16,
+    pub mode: u8,
+    pub timeout_ms: u32,
+}
+/// `protocol` is 1 TCP, 2 UDP; `gateway` may be null
+#[repr(C)]
+pub struct NetRequestPortMappingC {
+    pub gateway: *const c_char,
+    pub protocol: u8,
+    pub internal_port: u16,
+    pub external_port: u16,
+    pub lifetime: u32,
+}
+/// `download_url` and `upload_url` may be null; `mode` is 1 Tor, 2 clearnet
+#[repr(C)]
+pub struct NetRequestSpeedTestC {
+    pub download_url: *const c_char,
+    pub upload_url: *const c_char,
+    pub duration_ms: u32,
+    pub rtt_samples: u8,
+    pub mode: u8,
+}
+/// `url` may be null for the default probe
+#[repr(C)]
+pub struct NetRequestCaptivePortalC {
+    pub url: *const c_char,
+    pub timeout_ms: u32,
+}
+/// `names` is an array of C strings; `resolver` and `doh_url` as in `NetRequestDnsC`
+#[repr(C)]
+pub struct NetRequestDnsPrefetchC {
+    pub names: *const *const c_char,
+    pub names_len: u8,
+    pub resolver: u8,
+    pub doh_url: *const c_char,
+}
+/// Transport ids of the two stream transports to join
+#[repr(C)]
+pub struct NetRequestSpliceC {
+    pub source: u32,
+    pub target: u32,
 }
 #[repr(C)]
 pub struct NetRequestSocketSendC {
     pub data: BytesRefC,
 }
+/// Id of a named socket subscription
+#[repr(C)]
+pub struct NetRequestSocketSubscriptionC {
+    pub id: i32,
+}
 
 #[repr(C)]
 pub union NetRequestGrpcUnionC {
     pub unary: ManuallyDrop<*const NetRequestGrpcUnaryC>,
     pub stream: ManuallyDrop<*const NetRequestGrpcStreamC>,
     pub unsubscribe: ManuallyDrop<*const NetRequestGrpcUnsubscribeC>,
+    pub health: ManuallyDrop<*const NetRequestGrpcHealthC>,
+    pub credit: ManuallyDrop<*const NetRequestGrpcCreditC>,
+    pub rebuild: ManuallyDrop<*const NetRequestGrpcRebuildC>,
 }
 
 #[repr(C)]
 pub union NetRequestSocketUnionC {
     pub send: ManuallyDrop<*const NetRequestSocketSendC>,
+    /// Null for the default subscription
+    pub subscription: ManuallyDrop<*const NetRequestSocketSubscriptionC>,
 }
 #[repr(C)]
 pub struct NetRequestGrpcC {
@@ -209,6 +956,18 @@ pub union NetRequestKindUnionC {
     pub grpc: ManuallyDrop<*const NetRequestGrpcC>,
     pub http: ManuallyDrop<*const NetRequestHttpC>,
     pub init_tor: ManuallyDrop<*const NetConfigTorC>,
+    pub dns: ManuallyDrop<*const NetRequestDnsC>,
+    pub mdns: ManuallyDrop<*const NetRequestMdnsC>,
+    pub ping: ManuallyDrop<*const NetRequestPingC>,
+    pub ntp: ManuallyDrop<*const NetRequestNtpC>,
+    pub stun: ManuallyDrop<*const NetRequestStunC>,
+    pub port_check: ManuallyDrop<*const NetRequestPortCheckC>,
+    pub port_mapping: ManuallyDrop<*const NetRequestPortMappingC>,
+    pub speed_test: ManuallyDrop<*const NetRequestSpeedTestC>,
+    pub captive_portal: ManuallyDrop<*const NetRequestCaptivePortalC>,
+    pub splice: ManuallyDrop<*const NetRequestSpliceC>,
+    pub dns_prefetch: ManuallyDrop<*const NetRequestDnsPrefetchC>,
+    pub http_events: ManuallyDrop<*const NetRequestHttpEventsC>,
 }
 #[repr(C)]
 pub struct NetRequestKindC {
@@ -239,6 +998,18 @@ impl BytesRefC {
 unsafe fn bytes_from_ref<'a>(b: &BytesRefC) -> &'a [u8] {
     unsafe { slice::from_raw_parts(b.ptr, b.len as usize) }
 }
+/// Takes over an owned buffer without copying; a borrowed one is copied
+unsafe fn bytes_from_c(b: &BytesRefC) -> Bytes {
+    if b.ptr.is_null() || b.len == 0 {
+        return Bytes::new();
+    }
+    match b.owned {
+        0 => Bytes::copy_from_slice(unsafe { bytes_from_ref(b) }),
+        _ => Bytes::from(unsafe {
+            Vec::from_raw_parts(b.ptr as *mut u8, b.len as usize, b.len as usize)
+        }),
+    }
+}
 unsafe fn u16_from_ref<'a>(v: *const u16, len: u8) -> &'a [u16] {
     unsafe { slice::from_raw_parts(v, len as usize) }
 }
@@ -278,59 +1049,312 @@ impl<'a> NetRequest<'a> {
                     }
                 }
                 5 => NetRequestKind::TorInited,
-                _ => return Err(NetResultStatus::InvalidRequestParameters),
-            },
-        })
-    }
-}
-impl<'a> NetRequestGrpc<'a> {
-    pub unsafe fn from_c(c: &NetRequestGrpcC) -> Result<Self, NetResultStatus> {
-        Ok(match c.tag {
-            1 => {
-                let pointer = unsafe { c.payload.unary.as_ref() };
-                match pointer {
-                    Some(u) => {
-                        if u.method.is_null() {
-                            return Err(NetResultStatus::InvalidRequestParameters);
-                        }
-                        NetRequestGrpc::Unary(NetRequestGrpcUnary {
-                            method: unsafe { Utils::cstr_to_str(u.method as *const u8) },
-                            data: unsafe { bytes_from_ref(&u.data) },
-                        })
+                6 => {
+                    let pointer = unsafe { c.kind.payload.dns.as_ref() };
+                    match pointer {
+                        Some(u) => NetRequestKind::DnsLookup(unsafe { NetRequestDns::from_c(u) }?),
+                        None => return Err(NetResultStatus::InvalidRequestParameters),
                     }
-                    None => return Err(NetResultStatus::InvalidRequestParameters),
                 }
-            }
-            2 => {
-                let pointer = unsafe { c.payload.stream.as_ref() };
-                match pointer {
-                    Some(s) => {
-                        if s.method.is_null() {
-                            return Err(NetResultStatus::InvalidRequestParameters);
+                7 => {
+                    let pointer = unsafe { c.kind.payload.mdns.as_ref() };
+                    match pointer {
+                        Some(u) if !u.name.is_null() => {
+                            let name = unsafe { Utils::cstr_to_string(u.name as *const u8) };
+                            NetRequestKind::Mdns(match u.resolve {
+                                true => NetRequestMdns::Resolve(name),
+                                false => NetRequestMdns::Browse(name),
+                            })
                         }
-                        NetRequestGrpc::Stream(NetRequestGrpcStream {
-                            method: unsafe { Utils::cstr_to_str(s.method as *const u8) },
-                            data: unsafe { bytes_from_ref(&s.data) },
-                        })
+                        _ => return Err(NetResultStatus::InvalidRequestParameters),
                     }
-                    None => return Err(NetResultStatus::InvalidRequestParameters),
                 }
-            }
-
-            3 => {
-                let pointer = unsafe { c.payload.unsubscribe.as_ref() };
-                match pointer {
-                    Some(u) => NetRequestGrpc::Unsubscribe(NetRequestGrpcUnsubscribe { id: u.id }),
-                    None => return Err(NetResultStatus::InvalidRequestParameters),
+                8 => {
+                    let pointer = unsafe { c.kind.payload.ping.as_ref() };
+                    match pointer {
+                        Some(u) if !u.host.is_null() => NetRequestKind::Ping(NetRequestPing {
+                            host: unsafe { Utils::cstr_to_string(u.host as *const u8) },
+                            count: u.count,
+                            interval_ms: u.interval_ms,
+                        }),
+                        _ => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
                 }
-            }
-
-            _ => return Err(NetResultStatus::InvalidRequestParameters),
-        })
-    }
-}
-impl<'a> NetRequestSocket<'a> {
-    pub unsafe fn from_c(c: &NetRequestSocketC) -> Result<Self, NetResultStatus> {
+                9 => {
+                    let pointer = unsafe { c.kind.payload.ntp.as_ref() };
+                    match pointer {
+                        Some(u) => NetRequestKind::Ntp(NetRequestNtp {
+                            servers: unsafe { c_string_list(u.servers, u.servers_len) }
+                                .map_err(|_| NetResultStatus::InvalidRequestParameters)?,
+                        }),
+                        None => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
+                }
+                10 => {
+                    let pointer = unsafe { c.kind.payload.stun.as_ref() };
+                    match pointer {
+                        Some(u) => NetRequestKind::Stun(NetRequestStun {
+                            servers: unsafe { c_string_list(u.servers, u.servers_len) }
+                                .map_err(|_| NetResultStatus::InvalidRequestParameters)?,
+                        }),
+                        None => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
+                }
+                11 => {
+                    let pointer = unsafe { c.kind.payload.port_check.as_ref() };
+                    match pointer {
+                        Some(u) if !u.host.is_null() => {
+                            NetRequestKind::PortCheck(NetRequestPortCheck {
+                                host: unsafe { Utils::cstr_to_string(u.host as *const u8) },
+                                port: u.port,
+                                mode: match u.mode {
+                                    1 => NetMode::Tor,
+                                    2 => NetMode::Clearnet,
+                                    _ => return Err(NetResultStatus::InvalidRequestParameters),
+                                },
+                                timeout_ms: u.timeout_ms,
+                            })
+                        }
+                        _ => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
+                }
+                12 => NetRequestKind::Interfaces,
+                13 => NetRequestKind::WatchInterfaces(true),
+                14 => NetRequestKind::WatchInterfaces(false),
+                15 => {
+                    let pointer = unsafe { c.kind.payload.port_mapping.as_ref() };
+                    match pointer {
+                        Some(u) if u.internal_port != 0 => {
+                            NetRequestKind::PortMapping(NetRequestPortMapping {
+                                protocol: match u.protocol {
+                                    1 => NetPortMappingProtocol::Tcp,
+                                    2 => NetPortMappingProtocol::Udp,
+                                    _ => return Err(NetResultStatus::InvalidRequestParameters),
+                                },
+                                internal_port: u.internal_port,
+                                external_port: u.external_port,
+                                lifetime: u.lifetime,
+                                gateway: match u.gateway.is_null() {
+                                    true => None,
+                                    false => Some(unsafe {
+                                        Utils::cstr_to_string(u.gateway as *const u8)
+                                    }),
+                                },
+                            })
+                        }
+                        _ => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
+                }
+                16 => {
+                    let pointer = unsafe { c.kind.payload.speed_test.as_ref() };
+                    let url = |url: *const c_char| match url.is_null() {
+                        true => None,
+                        false => Some(unsafe { Utils::cstr_to_string(url as *const u8) }),
+                    };
+                    match pointer {
+                        Some(u) => NetRequestKind::SpeedTest(NetRequestSpeedTest {
+                            download_url: url(u.download_url),
+                            upload_url: url(u.upload_url),
+                            duration_ms: u.duration_ms,
+                            rtt_samples: u.rtt_samples,
+                            mode: match u.mode {
+                                1 => NetMode::Tor,
+                                2 => NetMode::Clearnet,
+                                _ => return Err(NetResultStatus::InvalidRequestParameters),
+                            },
+                        }),
+                        None => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
+                }
+                17 => {
+                    let pointer = unsafe { c.kind.payload.captive_portal.as_ref() };
+                    match pointer {
+                        Some(u) => NetRequestKind::CaptivePortal(NetRequestCaptivePortal {
+                            url: match u.url.is_null() {
+                                true => None,
+                                false => Some(unsafe { Utils::cstr_to_string(u.url as *const u8) }),
+                            },
+                            timeout_ms: u.timeout_ms,
+                        }),
+                        None => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
+                }
+                18 => {
+                    let pointer = unsafe { c.kind.payload.splice.as_ref() };
+                    match pointer {
+                        Some(u) => NetRequestKind::Splice(NetRequestSplice {
+                            source: u.source,
+                            target: u.target,
+                        }),
+                        None => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
+                }
+                19 => {
+                    let pointer = unsafe { c.kind.payload.dns_prefetch.as_ref() };
+                    match pointer {
+                        Some(u) => NetRequestKind::DnsPrefetch(NetRequestDnsPrefetch {
+                            names: unsafe { c_string_list(u.names, u.names_len) }
+                                .map_err(|_| NetResultStatus::InvalidRequestParameters)?,
+                            resolver: unsafe { NetDnsResolver::from_c(u.resolver, u.doh_url)? },
+                        }),
+                        None => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
+                }
+                20 => {
+                    let pointer = unsafe { c.kind.payload.http_events.as_ref() };
+                    match pointer {
+                        Some(u) => NetRequestKind::HttpEvents(match unsafe { u.http.as_ref() } {
+                            Some(http) => NetRequestHttpEvents::Subscribe(unsafe {
+                                NetRequestHttp::from_c(http)
+                            }?),
+                            None => NetRequestHttpEvents::Unsubscribe(u.id),
+                        }),
+                        None => return Err(NetResultStatus::InvalidRequestParameters),
+                    }
+                }
+                _ => return Err(NetResultStatus::InvalidRequestParameters),
+            },
+        })
+    }
+}
+impl NetRequestDns {
+    /// # Safety
+    /// `name` and `doh_url` must be null or valid C strings.
+    pub unsafe fn from_c(c: &NetRequestDnsC) -> Result<Self, NetResultStatus> {
+        if c.name.is_null() {
+            return Err(NetResultStatus::InvalidRequestParameters);
+        }
+        Ok(Self {
+            name: unsafe { Utils::cstr_to_string(c.name as *const u8) },
+            record_type: NetDnsRecordType::from_u8(c.record_type)
+                .ok_or(NetResultStatus::InvalidRequestParameters)?,
+            resolver: unsafe { NetDnsResolver::from_c(c.resolver, c.doh_url)? },
+        })
+    }
+}
+impl NetDnsResolver {
+    /// # Safety
+    /// `doh_url` must be null or a valid C string.
+    pub unsafe fn from_c(resolver: u8, doh_url: *const c_char) -> Result<Self, NetResultStatus> {
+        let doh_url = || match doh_url.is_null() {
+            true => Err(NetResultStatus::InvalidRequestParameters),
+            false => Ok(unsafe { Utils::cstr_to_string(doh_url as *const u8) }),
+        };
+        Ok(match resolver {
+            1 => NetDnsResolver::System,
+            2 => NetDnsResolver::DoH(doh_url()?, NetMode::Clearnet),
+            3 => NetDnsResolver::Tor,
+            4 => NetDnsResolver::DoH(doh_url()?, NetMode::Tor),
+            _ => return Err(NetResultStatus::InvalidRequestParameters),
+        })
+    }
+}
+impl<'a> NetRequestGrpc<'a> {
+    pub unsafe fn from_c(c: &NetRequestGrpcC) -> Result<Self, NetResultStatus> {
+        Ok(match c.tag {
+            1 => {
+                let pointer = unsafe { c.payload.unary.as_ref() };
+                match pointer {
+                    Some(u) => {
+                        if u.method.is_null() {
+                            return Err(NetResultStatus::InvalidRequestParameters);
+                        }
+                        NetRequestGrpc::Unary(NetRequestGrpcUnary {
+                            method: unsafe { Utils::cstr_to_str(u.method as *const u8) },
+                            data: unsafe { bytes_from_c(&u.data) },
+                            metadata: unsafe {
+                                NetHttpHeaderRef::list_from_c(u.metadata, u.metadata_len)
+                            }?,
+                            wait_for_ready: u.wait_for_ready,
+                            authority: match u.authority.is_null() {
+                                true => None,
+                                false => {
+                                    Some(unsafe { Utils::cstr_to_str(u.authority as *const u8) })
+                                }
+                            },
+                        })
+                    }
+                    None => return Err(NetResultStatus::InvalidRequestParameters),
+                }
+            }
+            2 => {
+                let pointer = unsafe { c.payload.stream.as_ref() };
+                match pointer {
+                    Some(s) => {
+                        if s.method.is_null() {
+                            return Err(NetResultStatus::InvalidRequestParameters);
+                        }
+                        NetRequestGrpc::Stream(NetRequestGrpcStream {
+                            method: unsafe { Utils::cstr_to_str(s.method as *const u8) },
+                            data: unsafe { bytes_from_c(&s.data) },
+                            metadata: unsafe {
+                                NetHttpHeaderRef::list_from_c(s.metadata, s.metadata_len)
+                            }?,
+                            deadline: s.deadline,
+                            credits: s.credits,
+                            wait_for_ready: s.wait_for_ready,
+                            authority: match s.authority.is_null() {
+                                true => None,
+                                false => {
+                                    Some(unsafe { Utils::cstr_to_str(s.authority as *const u8) })
+                                }
+                            },
+                            rebuild: s.rebuild,
+                        })
+                    }
+                    None => return Err(NetResultStatus::InvalidRequestParameters),
+                }
+            }
+
+            3 => {
+                let pointer = unsafe { c.payload.unsubscribe.as_ref() };
+                match pointer {
+                    Some(u) => NetRequestGrpc::Unsubscribe(NetRequestGrpcUnsubscribe { id: u.id }),
+                    None => return Err(NetResultStatus::InvalidRequestParameters),
+                }
+            }
+            4 => {
+                let pointer = unsafe { c.payload.health.as_ref() };
+                match pointer {
+                    Some(h) => NetRequestGrpc::Health(NetRequestGrpcHealth {
+                        service: match h.service.is_null() {
+                            true => "",
+                            false => unsafe { Utils::cstr_to_str(h.service as *const u8) },
+                        },
+                        watch: h.watch,
+                    }),
+                    None => return Err(NetResultStatus::InvalidRequestParameters),
+                }
+            }
+            5 => {
+                let pointer = unsafe { c.payload.credit.as_ref() };
+                match pointer {
+                    Some(c) => NetRequestGrpc::Credit(NetRequestGrpcCredit {
+                        id: c.id,
+                        credits: c.credits,
+                    }),
+                    None => return Err(NetResultStatus::InvalidRequestParameters),
+                }
+            }
+            6 => NetRequestGrpc::State,
+            7 => NetRequestGrpc::Stats,
+            8 => {
+                let pointer = unsafe { c.payload.rebuild.as_ref() };
+                match pointer {
+                    Some(r) => NetRequestGrpc::Rebuild(NetRequestGrpcRebuild {
+                        id: r.id,
+                        data: unsafe { bytes_from_c(&r.data) },
+                    }),
+                    None => return Err(NetResultStatus::InvalidRequestParameters),
+                }
+            }
+
+            _ => return Err(NetResultStatus::InvalidRequestParameters),
+        })
+    }
+}
+impl<'a> NetRequestSocket<'a> {
+    pub unsafe fn from_c(c: &NetRequestSocketC) -> Result<Self, NetResultStatus> {
         Ok(match c.tag {
             1 => {
                 let pointer = unsafe { c.payload.send.as_ref() };
@@ -341,8 +1365,15 @@ impl<'a> NetRequestSocket<'a> {
                     None => return Err(NetResultStatus::InvalidRequestParameters),
                 }
             }
-            2 => NetRequestSocket::Subscribe,
-            3 => NetRequestSocket::Unsubscribe,
+            tag @ 2..=5 => {
+                let id = unsafe { c.payload.subscription.as_ref() }.map(|u| u.id);
+                match tag {
+                    2 => NetRequestSocket::Subscribe(id),
+                    3 => NetRequestSocket::Unsubscribe(id),
+                    4 => NetRequestSocket::BufferStatus(id),
+                    _ => NetRequestSocket::FlushBuffer(id),
+                }
+            }
             _ => return Err(NetResultStatus::InvalidRequestParameters),
         })
     }
@@ -357,6 +1388,23 @@ impl<'a> NetHttpHeaderRef<'a> {
             value: unsafe { Utils::cstr_to_str(c.value as *const u8) },
         })
     }
+    /// # Safety
+    /// `list` must be null or point to `len` entries holding valid C strings.
+    unsafe fn list_from_c(
+        list: *const NetHttpHeaderC,
+        len: u8,
+    ) -> Result<Vec<Self>, NetResultStatus> {
+        if list.is_null() {
+            return match len {
+                0 => Ok(Vec::new()),
+                _ => Err(NetResultStatus::InvalidRequestParameters),
+            };
+        }
+        unsafe { std::slice::from_raw_parts(list, len.into()) }
+            .iter()
+            .map(NetHttpHeaderRef::from_c)
+            .collect()
+    }
 }
 impl<'a> NetHttpRetryConfig<'a> {
     pub unsafe fn from_c(c: &NetHttpRetryConfigC) -> Result<Self, NetResultStatus> {
@@ -365,6 +1413,7 @@ impl<'a> NetHttpRetryConfig<'a> {
                 retry_status: u16_from_ref(c.retry_status, c.len),
                 max_retries: c.max_retries,
                 retry_delay: c.retry_delay,
+                max_retry_after: c.max_retry_after,
             }
         });
     }
@@ -397,14 +1446,28 @@ impl<'a> NetRequestHttp<'a> {
             retry_config: retry,
             body: match c.body.len {
                 0 => None,
-                _ => Some(unsafe { bytes_from_ref(&c.body) }),
+                _ => Some(unsafe { bytes_from_c(&c.body) }),
             },
             encoding: match c.encoding {
                 1 => StreamEncoding::Json,
                 2 => StreamEncoding::Raw,
+                3 => StreamEncoding::CborJson,
+                4 => StreamEncoding::Auto,
                 _ => return Err(NetResultStatus::InvalidRequestParameters),
             },
             headers: headers,
+            queue_offline: c.queue_offline,
+            download_path: match c.download_path.is_null() {
+                true => None,
+                false => Some(unsafe { Utils::cstr_to_str(c.download_path as *const u8) }),
+            },
+            resume: (c.resume_offset > 0).then(|| NetHttpResume {
+                offset: c.resume_offset,
+                if_range: match c.if_range.is_null() {
+                    true => None,
+                    false => Some(unsafe { Utils::cstr_to_str(c.if_range as *const u8) }),
+                },
+            }),
         })
     }
 }
@@ -419,17 +1482,57 @@ pub struct NetResponseStreamDataC {
 pub struct NetResponseStreamCloseC {
     pub id: i32,
 }
+#[repr(C)]
+pub struct NetResponseStreamIdleC {
+    pub id: i32,
+}
+#[repr(C)]
+pub struct NetResponseStreamCancelledC {
+    pub id: i32,
+}
+#[repr(C)]
+pub struct NetResponseStreamReconnectedC {
+    pub id: i32,
+    pub flushed: u32,
+}
+/// `last.ptr` is null before the stream received a message
+#[repr(C)]
+pub struct NetResponseStreamRebuildC {
+    pub id: i32,
+    pub last: BytesRefC,
+}
 
+#[repr(C)]
+pub struct NetGrpcStatusDetailC {
+    pub type_url: *const c_char,
+    pub value: BytesRefC,
+}
+#[repr(C)]
+pub struct NetGrpcStatusC {
+    pub code: i32,
+    pub message: *const c_char,
+    pub details: *const NetGrpcStatusDetailC,
+    pub details_len: u32,
+    /// `grpc-status-details-bin` as sent, empty when the server sent none
+    pub details_bin: BytesRefC,
+}
 #[repr(C)]
 pub struct NetResponseStreamErrorC {
     pub id: i32,
     pub error: u8,
+    pub message: *const c_char,
+    /// null unless the error came from a gRPC server status
+    pub grpc_status: *const NetGrpcStatusC,
 }
 #[repr(C)]
 pub union NetResponseStreamUnionC {
     pub data: ManuallyDrop<NetResponseStreamDataC>,
     pub close: ManuallyDrop<NetResponseStreamCloseC>,
     pub error: ManuallyDrop<NetResponseStreamErrorC>,
+    pub reconnected: ManuallyDrop<NetResponseStreamReconnectedC>,
+    pub idle: ManuallyDrop<NetResponseStreamIdleC>,
+    pub cancelled: ManuallyDrop<NetResponseStreamCancelledC>,
+    pub rebuild: ManuallyDrop<NetResponseStreamRebuildC>,
 }
 #[repr(C)]
 pub struct NetResponseStreamC {
@@ -439,15 +1542,30 @@ pub struct NetResponseStreamC {
 #[repr(C)]
 pub struct NetSocketStreamResponseOkC;
 #[repr(C)]
+pub struct NetResponseSocketBufferC {
+    pub buffered: u32,
+}
+/// `trailers` is null when the server sent none, `remote_addr` when the peer is not known
+#[repr(C)]
 pub struct NetResponseHttpC {
     pub status_code: u16,
     pub body: BytesRefC,
     pub headers: *const NetHttpHeaderC,
     pub headers_len: u32,
+    pub encoding: u8,
+    pub trailers: *const NetHttpHeaderC,
+    pub trailers_len: u32,
+    pub version: *const c_char,
+    pub remote_addr: *const c_char,
 }
+/// `metadata` and `trailers` are null when the server sent none
 #[repr(C)]
 pub struct NetResponseGrpcUnaryC {
     pub data: BytesRefC,
+    pub metadata: *const NetHttpHeaderC,
+    pub metadata_len: u32,
+    pub trailers: *const NetHttpHeaderC,
+    pub trailers_len: u32,
 }
 #[repr(C)]
 pub struct NetResponseGrpcSubscribeC {
@@ -458,12 +1576,38 @@ pub struct NetResponseGrpcSubscribeC {
 pub struct NetResponseGrpcUnsubscribeC {
     pub id: i32,
 }
+/// `status` is a `NetGrpcServingStatus`
+#[repr(C)]
+pub struct NetResponseGrpcHealthC {
+    pub status: u8,
+}
+/// `state` is a `NetGrpcConnectivity`
+#[repr(C)]
+pub struct NetResponseGrpcStateC {
+    pub state: u8,
+}
+/// See `NetGrpcStats`; `errors` is indexed by gRPC status code
+#[repr(C)]
+pub struct NetResponseGrpcStatsC {
+    pub unary_calls: u64,
+    pub streams: u64,
+    pub active_streams: u32,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub errors: [u64; 17],
+    pub failures: u64,
+}
 
 #[repr(C)]
 pub union NetResponseGrpcUnionC {
     pub unary: ManuallyDrop<NetResponseGrpcUnaryC>,
     pub stream_id: ManuallyDrop<NetResponseGrpcSubscribeC>,
     pub unsubscribe: ManuallyDrop<NetResponseGrpcUnsubscribeC>,
+    pub health: ManuallyDrop<NetResponseGrpcHealthC>,
+    pub state: ManuallyDrop<NetResponseGrpcStateC>,
+    pub stats: ManuallyDrop<NetResponseGrpcStatsC>,
 }
 
 #[repr(C)]
@@ -474,12 +1618,211 @@ pub struct NetResponseGrpcC {
 #[repr(C)]
 pub struct NetResponseTransportClosedC;
 #[repr(C)]
+pub struct NetResponseTransportConnectedC;
+#[repr(C)]
 pub struct NetResponseTorInited {
     pub inited: bool,
 }
 #[repr(C)]
 pub struct NetResponseErrorC {
     pub error: u8,
+    pub message: *const c_char,
+    /// null unless the error came from a gRPC server status
+    pub grpc_status: *const NetGrpcStatusC,
+}
+#[repr(C)]
+pub struct NetResponseEndpointC {
+    pub index: u32,
+    pub url: *const c_char,
+}
+/// `value` is an address, a target name or TXT data depending on `record_type`
+#[repr(C)]
+pub struct NetDnsRecordC {
+    pub record_type: u8,
+    pub name: *const c_char,
+    pub ttl: u32,
+    pub value: *const c_char,
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+}
+#[repr(C)]
+pub struct NetResponseDnsC {
+    pub records: *const NetDnsRecordC,
+    pub records_len: u32,
+}
+/// `addresses` and `txt` are arrays of C strings; null when empty
+#[repr(C)]
+pub struct NetMdnsServiceC {
+    pub instance: *const c_char,
+    pub service: *const c_char,
+    pub host: *const c_char,
+    pub port: u16,
+    pub addresses: *const *const c_char,
+    pub addresses_len: u32,
+    pub txt: *const *const c_char,
+    pub txt_len: u32,
+}
+#[repr(C)]
+pub struct NetResponseMdnsFinishedC;
+#[repr(C)]
+pub union NetResponseMdnsUnionC {
+    pub found: ManuallyDrop<NetMdnsServiceC>,
+    pub finished: ManuallyDrop<NetResponseMdnsFinishedC>,
+}
+#[repr(C)]
+pub struct NetResponseMdnsC {
+    pub tag: u8,
+    pub payload: NetResponseMdnsUnionC,
+}
+/// `method` is 1 ICMP, 2 UDP; RTTs are microseconds
+#[repr(C)]
+pub struct NetResponsePingC {
+    pub address: *const c_char,
+    pub method: u8,
+    pub sent: u16,
+    pub received: u16,
+    pub rtt_min: u32,
+    pub rtt_avg: u32,
+    pub rtt_max: u32,
+}
+/// `offset` and `delay` are microseconds
+#[repr(C)]
+pub struct NetResponseNtpC {
+    pub server: *const c_char,
+    pub stratum: u8,
+    pub offset: i64,
+    pub delay: u32,
+}
+/// `failure` is `NetPortCheckFailure`, 0 when reachable
+#[repr(C)]
+pub struct NetResponsePortCheckC {
+    pub reachable: bool,
+    pub latency: u32,
+    pub failure: u8,
+}
+#[repr(C)]
+pub struct NetStunMappingC {
+    pub server: *const c_char,
+    pub address: *const c_char,
+    pub port: u16,
+}
+#[repr(C)]
+pub struct NetResponseStunC {
+    pub local_address: *const c_char,
+    pub local_port: u16,
+    pub mappings: *const NetStunMappingC,
+    pub mappings_len: u32,
+}
+/// `phase` is 1 download, 2 upload; `bitrate` is bits per second
+#[repr(C)]
+pub struct NetSpeedTestProgressC {
+    pub phase: u8,
+    pub bytes: u64,
+    pub elapsed_ms: u32,
+    pub bitrate: u64,
+}
+/// RTTs are microseconds, bitrates bits per second
+#[repr(C)]
+pub struct NetSpeedTestResultC {
+    pub rtt_min: u32,
+    pub rtt_avg: u32,
+    pub rtt_max: u32,
+    pub download_bytes: u64,
+    pub download_bitrate: u64,
+    pub upload_bytes: u64,
+    pub upload_bitrate: u64,
+}
+#[repr(C)]
+pub union NetResponseSpeedTestUnionC {
+    pub progress: ManuallyDrop<NetSpeedTestProgressC>,
+    pub finished: ManuallyDrop<NetSpeedTestResultC>,
+}
+/// `tag` is 1 progress, 2 finished
+#[repr(C)]
+pub struct NetResponseSpeedTestC {
+    pub tag: u8,
+    pub payload: NetResponseSpeedTestUnionC,
+}
+/// `state` is `NetCaptivePortalState`; `portal_url` is null unless it is a portal
+#[repr(C)]
+pub struct NetResponseCaptivePortalC {
+    pub state: u8,
+    pub status: u16,
+    pub portal_url: *const c_char,
+}
+/// `state` is `NetHttpQueueState`. `http` is zeroed unless delivered and `error` is null
+/// unless failed
+#[repr(C)]
+pub struct NetResponseHttpQueueC {
+    pub request_id: u32,
+    pub state: u8,
+    pub pending: u32,
+    pub http: NetResponseHttpC,
+    pub error: *const c_char,
+}
+/// `state` is `NetSocketSendState`; `error` is null unless failed
+#[repr(C)]
+pub struct NetResponseSocketSendC {
+    pub request_id: u32,
+    pub state: u8,
+    pub pending: u32,
+    pub error: *const c_char,
+}
+/// See `NetResponseHttpEvents`
+#[repr(C)]
+pub struct NetResponseHttpEventsC {
+    pub id: i32,
+    pub subscribed: bool,
+}
+/// `total` is 0 when the server announced no length
+#[repr(C)]
+pub struct NetResponseHttpDownloadC {
+    pub request_id: u32,
+    pub received: u64,
+    pub total: u64,
+}
+#[repr(C)]
+pub struct NetResponseSpliceC {
+    pub source_bytes: u64,
+    pub target_bytes: u64,
+}
+/// `failed` is an array of C strings; null when every name resolved
+#[repr(C)]
+pub struct NetResponseDnsPrefetchC {
+    pub resolved: u32,
+    pub failed: *const *const c_char,
+    pub failed_len: u32,
+}
+/// `method` is `NetPortMappingMethod`, `protocol` is `NetPortMappingProtocol`
+#[repr(C)]
+pub struct NetResponsePortMappingC {
+    pub gateway: *const c_char,
+    pub method: u8,
+    pub protocol: u8,
+    pub internal_port: u16,
+    pub external_port: u16,
+    pub external_address: *const c_char,
+    pub lifetime: u32,
+}
+/// `addresses` are in CIDR notation
+#[repr(C)]
+pub struct NetInterfaceC {
+    pub name: *const c_char,
+    pub index: u32,
+    pub up: bool,
+    pub loopback: bool,
+    pub addresses: *const *const c_char,
+    pub addresses_len: u32,
+}
+/// `default_interface`, `default_ipv4` and `default_ipv6` are null without a default route
+#[repr(C)]
+pub struct NetResponseInterfacesC {
+    pub interfaces: *const NetInterfaceC,
+    pub interfaces_len: u32,
+    pub default_interface: *const c_char,
+    pub default_ipv4: *const c_char,
+    pub default_ipv6: *const c_char,
 }
 #[repr(C)]
 pub union NetResponseKindUnionC {
@@ -489,7 +1832,26 @@ pub union NetResponseKindUnionC {
     pub stream: ManuallyDrop<NetResponseStreamC>,
     pub error: ManuallyDrop<NetResponseErrorC>,
     pub closed: ManuallyDrop<NetResponseTransportClosedC>,
+    pub connected: ManuallyDrop<NetResponseTransportConnectedC>,
     pub tor_inited: ManuallyDrop<NetResponseTorInited>,
+    pub socket_buffer: ManuallyDrop<NetResponseSocketBufferC>,
+    pub endpoint: ManuallyDrop<NetResponseEndpointC>,
+    pub dns: ManuallyDrop<NetResponseDnsC>,
+    pub mdns: ManuallyDrop<NetResponseMdnsC>,
+    pub ping: ManuallyDrop<NetResponsePingC>,
+    pub ntp: ManuallyDrop<NetResponseNtpC>,
+    pub stun: ManuallyDrop<NetResponseStunC>,
+    pub port_check: ManuallyDrop<NetResponsePortCheckC>,
+    pub interfaces: ManuallyDrop<NetResponseInterfacesC>,
+    pub port_mapping: ManuallyDrop<NetResponsePortMappingC>,
+    pub speed_test: ManuallyDrop<NetResponseSpeedTestC>,
+    pub captive_portal: ManuallyDrop<NetResponseCaptivePortalC>,
+    pub http_queue: ManuallyDrop<NetResponseHttpQueueC>,
+    pub splice: ManuallyDrop<NetResponseSpliceC>,
+    pub dns_prefetch: ManuallyDrop<NetResponseDnsPrefetchC>,
+    pub socket_send: ManuallyDrop<NetResponseSocketSendC>,
+    pub http_events: ManuallyDrop<NetResponseHttpEventsC>,
+    pub http_download: ManuallyDrop<NetResponseHttpDownloadC>,
 }
 
 #[repr(C)]
@@ -504,28 +1866,62 @@ pub struct NetResponseC {
     pub response: NetResponseKindC,
 }
 
+impl NetResponseHttp {
+    pub fn into_c(self) -> NetResponseHttpC {
+        let (headers, headers_len) = NetHttpHeader::headers_to_c(self.headers_ref().as_slice());
+        let (trailers, trailers_len) = NetHttpHeader::headers_to_c(self.trailers_ref().as_slice());
+        let version = unsafe { string_to_c_ptr(self.version()) } as *const c_char;
+        let remote_addr = match self.remote_addr() {
+            Some(addr) => (unsafe { string_to_c_ptr(addr) }) as *const c_char,
+            None => std::ptr::null(),
+        };
+        NetResponseHttpC {
+            status_code: self.status_code(),
+            encoding: self.encoding() as u8,
+            headers,
+            headers_len,
+            trailers,
+            trailers_len,
+            version,
+            remote_addr,
+            body: bytes_to_ref(self.into_body()),
+        }
+    }
+}
+/// Moves `bytes` to C; the buffer is only copied when it is shared or not a whole allocation
 #[inline]
-fn bytes_to_ref(bytes: Vec<u8>) -> BytesRefC {
-    let boxed = bytes.clone().into_boxed_slice();
-    let ptr = boxed.as_ptr();
+fn bytes_to_ref(bytes: Bytes) -> BytesRefC {
+    // `free_memory` rebuilds the allocation from `len`, so it has to be exactly that long
+    let boxed = Vec::from(bytes).into_boxed_slice();
     let len = boxed.len();
-    std::mem::forget(boxed);
+    let ptr = Box::into_raw(boxed) as *const u8;
     BytesRefC {
         ptr: ptr,
         len: len as u32,
+        owned: 1,
     }
 }
 impl NetResponseGrpc {
-    pub fn to_c(&self) -> NetResponseGrpcC {
+    pub fn into_c(self) -> NetResponseGrpcC {
         match self {
-            NetResponseGrpc::Unary(u) => NetResponseGrpcC {
-                tag: 1,
-                payload: NetResponseGrpcUnionC {
-                    unary: ManuallyDrop::new(NetResponseGrpcUnaryC {
-                        data: bytes_to_ref(u.data()),
-                    }),
-                },
-            },
+            NetResponseGrpc::Unary(u) => {
+                let (metadata, metadata_len) =
+                    NetHttpHeader::headers_to_c(u.metadata_ref().as_slice());
+                let (trailers, trailers_len) =
+                    NetHttpHeader::headers_to_c(u.trailers_ref().as_slice());
+                NetResponseGrpcC {
+                    tag: 1,
+                    payload: NetResponseGrpcUnionC {
+                        unary: ManuallyDrop::new(NetResponseGrpcUnaryC {
+                            data: bytes_to_ref(u.into_data()),
+                            metadata,
+                            metadata_len,
+                            trailers,
+                            trailers_len,
+                        }),
+                    },
+                }
+            }
 
             NetResponseGrpc::StreamId(s) => NetResponseGrpcC {
                 tag: 2,
@@ -540,19 +1936,54 @@ impl NetResponseGrpc {
                     unsubscribe: ManuallyDrop::new(NetResponseGrpcUnsubscribeC { id: s.id() }),
                 },
             },
+
+            NetResponseGrpc::Health(h) => NetResponseGrpcC {
+                tag: 4,
+                payload: NetResponseGrpcUnionC {
+                    health: ManuallyDrop::new(NetResponseGrpcHealthC {
+                        status: h.status() as u8,
+                    }),
+                },
+            },
+
+            NetResponseGrpc::State(s) => NetResponseGrpcC {
+                tag: 5,
+                payload: NetResponseGrpcUnionC {
+                    state: ManuallyDrop::new(NetResponseGrpcStateC {
+                        state: s.state() as u8,
+                    }),
+                },
+            },
+
+            NetResponseGrpc::Stats(s) => NetResponseGrpcC {
+                tag: 6,
+                payload: NetResponseGrpcUnionC {
+                    stats: ManuallyDrop::new(NetResponseGrpcStatsC {
+                        unary_calls: s.unary_calls(),
+                        streams: s.streams(),
+                        active_streams: s.active_streams(),
+                        messages_sent: s.messages_sent(),
+                        messages_received: s.messages_received(),
+                        bytes_sent: s.bytes_sent(),
+                        bytes_received: s.bytes_received(),
+                        errors: *s.errors_ref(),
+                        failures: s.failures(),
+                    }),
+                },
+            },
         }
     }
 }
 
 impl NetResponseStream {
-    pub fn to_c(&self) -> NetResponseStreamC {
+    pub fn into_c(self) -> NetResponseStreamC {
         match self {
             NetResponseStream::Data(u) => NetResponseStreamC {
                 tag: 1,
                 payload: NetResponseStreamUnionC {
                     data: ManuallyDrop::new(NetResponseStreamDataC {
-                        data: bytes_to_ref(u.data()),
                         id: u.id().map_or(-1, |e| e),
+                        data: bytes_to_ref(u.into_data()),
                     }),
                 },
             },
@@ -571,6 +2002,49 @@ impl NetResponseStream {
                     error: ManuallyDrop::new(NetResponseStreamErrorC {
                         error: e.status() as u8,
                         id: e.id().map_or(-1, |e| e),
+                        message: unsafe { string_to_c_ptr(e.message()) } as *const _,
+                        grpc_status: grpc_status_to_c(e.error()),
+                    }),
+                },
+            },
+            NetResponseStream::Reconnected(r) => NetResponseStreamC {
+                tag: 4,
+                payload: NetResponseStreamUnionC {
+                    reconnected: ManuallyDrop::new(NetResponseStreamReconnectedC {
+                        id: r.id().map_or(-1, |e| e),
+                        flushed: r.flushed(),
+                    }),
+                },
+            },
+            NetResponseStream::Idle(id) => NetResponseStreamC {
+                tag: 5,
+                payload: NetResponseStreamUnionC {
+                    idle: ManuallyDrop::new(NetResponseStreamIdleC {
+                        id: id.map_or(-1, |e| e),
+                    }),
+                },
+            },
+            NetResponseStream::Cancelled(id) => NetResponseStreamC {
+                tag: 6,
+                payload: NetResponseStreamUnionC {
+                    cancelled: ManuallyDrop::new(NetResponseStreamCancelledC {
+                        id: id.map_or(-1, |e| e),
+                    }),
+                },
+            },
+            NetResponseStream::Rebuild(r) => NetResponseStreamC {
+                tag: 7,
+                payload: NetResponseStreamUnionC {
+                    rebuild: ManuallyDrop::new(NetResponseStreamRebuildC {
+                        id: r.id().map_or(-1, |e| e),
+                        last: match r.into_last() {
+                            Some(last) => bytes_to_ref(last),
+                            None => BytesRefC {
+                                ptr: std::ptr::null(),
+                                len: 0,
+                                owned: 0,
+                            },
+                        },
                     }),
                 },
             },
@@ -578,16 +2052,17 @@ impl NetResponseStream {
     }
 }
 impl NetResponse {
-    pub fn to_c(&self) -> NetResponseC {
+    /// Payloads move into the C structs instead of being copied
+    pub fn into_c(self) -> NetResponseC {
         NetResponseC {
             transport_id: self.transport_id,
             request_id: self.request_id,
-            response: self.response.to_c(),
+            response: self.response.into_c(),
         }
     }
 }
 impl NetResponseKind {
-    pub fn to_c(&self) -> NetResponseKindC {
+    pub fn into_c(self) -> NetResponseKindC {
         match self {
             NetResponseKind::Socket(_) => NetResponseKindC {
                 tag: 1,
@@ -598,34 +2073,28 @@ impl NetResponseKind {
             NetResponseKind::Grpc(g) => NetResponseKindC {
                 tag: 2,
                 payload: NetResponseKindUnionC {
-                    grpc: ManuallyDrop::new(g.to_c()),
+                    grpc: ManuallyDrop::new(g.into_c()),
+                },
+            },
+            NetResponseKind::Http(h) => NetResponseKindC {
+                tag: 3,
+                payload: NetResponseKindUnionC {
+                    http: ManuallyDrop::new(h.into_c()),
                 },
             },
-            NetResponseKind::Http(h) => {
-                let (headers_ptr, headers_len) = NetHttpHeader::headers_to_c(h.headers());
-                NetResponseKindC {
-                    tag: 3,
-                    payload: NetResponseKindUnionC {
-                        http: ManuallyDrop::new(NetResponseHttpC {
-                            status_code: h.status_code(),
-                            body: bytes_to_ref(h.body()),
-                            headers: headers_ptr,
-                            headers_len,
-                        }),
-                    },
-                }
-            }
             NetResponseKind::Stream(net_stream_response) => NetResponseKindC {
                 tag: 4,
                 payload: NetResponseKindUnionC {
-                    stream: ManuallyDrop::new(net_stream_response.to_c()),
+                    stream: ManuallyDrop::new(net_stream_response.into_c()),
                 },
             },
-            NetResponseKind::ResponseError(net_result_status) => NetResponseKindC {
+            NetResponseKind::ResponseError(net_error) => NetResponseKindC {
                 tag: 5,
                 payload: NetResponseKindUnionC {
                     error: ManuallyDrop::new(NetResponseErrorC {
-                        error: *net_result_status as u8,
+                        error: net_error.status() as u8,
+                        message: unsafe { string_to_c_ptr(net_error.to_string()) } as *const _,
+                        grpc_status: grpc_status_to_c(&net_error),
                     }),
                 },
             },
@@ -635,10 +2104,202 @@ impl NetResponseKind {
                     closed: ManuallyDrop::new(NetResponseTransportClosedC),
                 },
             },
+            NetResponseKind::TransportConnected => NetResponseKindC {
+                tag: 24,
+                payload: NetResponseKindUnionC {
+                    connected: ManuallyDrop::new(NetResponseTransportConnectedC),
+                },
+            },
             NetResponseKind::TorInited(inited) => NetResponseKindC {
                 tag: 7,
                 payload: NetResponseKindUnionC {
-                    tor_inited: ManuallyDrop::new(NetResponseTorInited { inited: *inited }),
+                    tor_inited: ManuallyDrop::new(NetResponseTorInited { inited }),
+                },
+            },
+            NetResponseKind::SocketBuffer(b) => NetResponseKindC {
+                tag: 8,
+                payload: NetResponseKindUnionC {
+                    socket_buffer: ManuallyDrop::new(NetResponseSocketBufferC {
+                        buffered: b.buffered(),
+                    }),
+                },
+            },
+            NetResponseKind::EndpointChanged(e) => NetResponseKindC {
+                tag: 9,
+                payload: NetResponseKindUnionC {
+                    endpoint: ManuallyDrop::new(NetResponseEndpointC {
+                        index: e.index(),
+                        url: unsafe { string_to_c_ptr(e.url()) } as *const _,
+                    }),
+                },
+            },
+            NetResponseKind::Dns(d) => NetResponseKindC {
+                tag: 10,
+                payload: NetResponseKindUnionC {
+                    dns: ManuallyDrop::new(d.to_c()),
+                },
+            },
+            NetResponseKind::Mdns(m) => NetResponseKindC {
+                tag: 11,
+                payload: NetResponseKindUnionC {
+                    mdns: ManuallyDrop::new(m.to_c()),
+                },
+            },
+            NetResponseKind::Ping(p) => NetResponseKindC {
+                tag: 12,
+                payload: NetResponseKindUnionC {
+                    ping: ManuallyDrop::new(p.to_c()),
+                },
+            },
+            NetResponseKind::Ntp(n) => NetResponseKindC {
+                tag: 13,
+                payload: NetResponseKindUnionC {
+                    ntp: ManuallyDrop::new(NetResponseNtpC {
+                        server: unsafe { string_to_c_ptr(n.server()) } as *const _,
+                        stratum: n.stratum(),
+                        offset: n.offset(),
+                        delay: n.delay(),
+                    }),
+                },
+            },
+            NetResponseKind::Stun(st) => NetResponseKindC {
+                tag: 14,
+                payload: NetResponseKindUnionC {
+                    stun: ManuallyDrop::new(st.to_c()),
+                },
+            },
+            NetResponseKind::PortCheck(c) => NetResponseKindC {
+                tag: 15,
+                payload: NetResponseKindUnionC {
+                    port_check: ManuallyDrop::new(NetResponsePortCheckC {
+                        reachable: c.reachable(),
+                        latency: c.latency(),
+                        failure: c.failure() as u8,
+                    }),
+                },
+            },
+            NetResponseKind::Interfaces(i) => NetResponseKindC {
+                tag: 16,
+                payload: NetResponseKindUnionC {
+                    interfaces: ManuallyDrop::new(i.to_c()),
+                },
+            },
+            NetResponseKind::NetworkChanged(i) => NetResponseKindC {
+                tag: 17,
+                payload: NetResponseKindUnionC {
+                    interfaces: ManuallyDrop::new(i.to_c()),
+                },
+            },
+            NetResponseKind::PortMapping(m) => NetResponseKindC {
+                tag: 18,
+                payload: NetResponseKindUnionC {
+                    port_mapping: ManuallyDrop::new(m.to_c()),
+                },
+            },
+            NetResponseKind::SpeedTest(t) => NetResponseKindC {
+                tag: 19,
+                payload: NetResponseKindUnionC {
+                    speed_test: ManuallyDrop::new(t.to_c()),
+                },
+            },
+            NetResponseKind::CaptivePortal(c) => NetResponseKindC {
+                tag: 20,
+                payload: NetResponseKindUnionC {
+                    captive_portal: ManuallyDrop::new(NetResponseCaptivePortalC {
+                        state: c.state() as u8,
+                        status: c.status(),
+                        portal_url: match c.portal_url() {
+                            Some(url) => unsafe { string_to_c_ptr(url) as *const c_char },
+                            None => std::ptr::null(),
+                        },
+                    }),
+                },
+            },
+            NetResponseKind::HttpQueue(q) => NetResponseKindC {
+                tag: 21,
+                payload: NetResponseKindUnionC {
+                    http_queue: ManuallyDrop::new(NetResponseHttpQueueC {
+                        request_id: q.request_id(),
+                        state: q.state() as u8,
+                        pending: q.pending(),
+                        error: match q.error() {
+                            Some(error) => unsafe { string_to_c_ptr(error) as *const c_char },
+                            None => std::ptr::null(),
+                        },
+                        http: match q.into_http() {
+                            Some(http) => http.into_c(),
+                            None => NetResponseHttpC {
+                                status_code: 0,
+                                body: BytesRefC {
+                                    ptr: std::ptr::null(),
+                                    len: 0,
+                                    owned: 0,
+                                },
+                                headers: std::ptr::null(),
+                                headers_len: 0,
+                                encoding: 0,
+                                trailers: std::ptr::null(),
+                                trailers_len: 0,
+                                version: std::ptr::null(),
+                                remote_addr: std::ptr::null(),
+                            },
+                        },
+                    }),
+                },
+            },
+            NetResponseKind::Splice(s) => NetResponseKindC {
+                tag: 22,
+                payload: NetResponseKindUnionC {
+                    splice: ManuallyDrop::new(NetResponseSpliceC {
+                        source_bytes: s.source_bytes(),
+                        target_bytes: s.target_bytes(),
+                    }),
+                },
+            },
+            NetResponseKind::DnsPrefetch(p) => {
+                let (failed, failed_len) = strings_to_c(p.failed_ref());
+                NetResponseKindC {
+                    tag: 23,
+                    payload: NetResponseKindUnionC {
+                        dns_prefetch: ManuallyDrop::new(NetResponseDnsPrefetchC {
+                            resolved: p.resolved(),
+                            failed,
+                            failed_len,
+                        }),
+                    },
+                }
+            }
+            NetResponseKind::SocketSend(s) => NetResponseKindC {
+                tag: 25,
+                payload: NetResponseKindUnionC {
+                    socket_send: ManuallyDrop::new(NetResponseSocketSendC {
+                        request_id: s.request_id(),
+                        state: s.state() as u8,
+                        pending: s.pending(),
+                        error: match s.error() {
+                            Some(error) => unsafe { string_to_c_ptr(error) as *const c_char },
+                            None => std::ptr::null(),
+                        },
+                    }),
+                },
+            },
+            NetResponseKind::HttpEvents(e) => NetResponseKindC {
+                tag: 26,
+                payload: NetResponseKindUnionC {
+                    http_events: ManuallyDrop::new(NetResponseHttpEventsC {
+                        id: e.id(),
+                        subscribed: e.subscribed(),
+                    }),
+                },
+            },
+            NetResponseKind::HttpDownload(d) => NetResponseKindC {
+                tag: 27,
+                payload: NetResponseKindUnionC {
+                    http_download: ManuallyDrop::new(NetResponseHttpDownloadC {
+                        request_id: d.request_id(),
+                        received: d.received(),
+                        total: d.total().unwrap_or_default(),
+                    }),
                 },
             },
         }
@@ -653,9 +2314,14 @@ impl NetResponseC {
 
                 match grpc.tag {
                     1 => {
-                        unsafe { grpc.payload.unary.data.free_memory() };
+                        let unary = unsafe { &grpc.payload.unary };
+                        unsafe {
+                            unary.data.free_memory();
+                            NetResponseHttpC::free_headers(unary.metadata, unary.metadata_len);
+                            NetResponseHttpC::free_headers(unary.trailers, unary.trailers_len);
+                        }
                     }
-                    2 | 3 => {}
+                    2..=6 => {}
                     _ => {
                         debug_assert!(false, "Unknown NetResponseGrpcC tag");
                     }
@@ -672,13 +2338,81 @@ impl NetResponseC {
                     1 => {
                         unsafe { stream.payload.data.data.free_memory() };
                     }
-                    2 | 3 => {}
+                    3 => {
+                        unsafe { free_c_string(stream.payload.error.message as *mut u8) };
+                        unsafe { free_grpc_status(stream.payload.error.grpc_status) };
+                    }
+                    7 => {
+                        unsafe { stream.payload.rebuild.last.free_memory() };
+                    }
+                    2 | 4..=6 => {}
                     _ => {
                         debug_assert!(false, "Unknown NetResponseKindC tag")
                     }
                 }
             }
-            1 | 5 | 6 => {}
+            5 => {
+                let error = unsafe { &self.response.payload.error };
+                unsafe { free_c_string(error.message as *mut u8) };
+                unsafe { free_grpc_status(error.grpc_status) };
+            }
+            9 => {
+                let endpoint = unsafe { &self.response.payload.endpoint };
+                unsafe { free_c_string(endpoint.url as *mut u8) };
+            }
+            10 => {
+                let dns = unsafe { &self.response.payload.dns };
+                unsafe { dns.free_memory() };
+            }
+            11 => {
+                let mdns = unsafe { &self.response.payload.mdns };
+                match mdns.tag {
+                    1 => unsafe { mdns.payload.found.free_memory() },
+                    2 => {}
+                    _ => {
+                        debug_assert!(false, "Unknown NetResponseMdnsC tag");
+                    }
+                }
+            }
+            12 => {
+                let ping = unsafe { &self.response.payload.ping };
+                unsafe { free_c_string(ping.address as *mut u8) };
+            }
+            13 => {
+                let ntp = unsafe { &self.response.payload.ntp };
+                unsafe { free_c_string(ntp.server as *mut u8) };
+            }
+            14 => {
+                let stun = unsafe { &self.response.payload.stun };
+                unsafe { stun.free_memory() };
+            }
+            16 | 17 => {
+                let interfaces = unsafe { &self.response.payload.interfaces };
+                unsafe { interfaces.free_memory() };
+            }
+            18 => {
+                let mapping = unsafe { &self.response.payload.port_mapping };
+                unsafe { free_c_string(mapping.gateway as *mut u8) };
+                unsafe { free_c_string(mapping.external_address as *mut u8) };
+            }
+            20 => {
+                let probe = unsafe { &self.response.payload.captive_portal };
+                unsafe { free_c_string(probe.portal_url as *mut u8) };
+            }
+            21 => {
+                let queue = unsafe { &self.response.payload.http_queue };
+                unsafe { queue.http.free_memory() };
+                unsafe { free_c_string(queue.error as *mut u8) };
+            }
+            23 => {
+                let prefetch = unsafe { &self.response.payload.dns_prefetch };
+                unsafe { free_c_strings(prefetch.failed, prefetch.failed_len) };
+            }
+            25 => {
+                let send = unsafe { &self.response.payload.socket_send };
+                unsafe { free_c_string(send.error as *mut u8) };
+            }
+            1 | 6 | 8 | 15 | 19 | 22 | 24 | 26 | 27 => {}
 
             _ => {
                 debug_assert!(false, "Unknown NetResponseKindC tag");
@@ -686,7 +2420,7 @@ impl NetResponseC {
         }
     }
 }
-unsafe fn string_to_c_ptr(s: String) -> *mut u8 {
+pub(crate) unsafe fn string_to_c_ptr(s: String) -> *mut u8 {
     let len = s.len();
 
     let buf = unsafe { libc::malloc(len + 1) } as *mut u8;
@@ -700,13 +2434,373 @@ unsafe fn string_to_c_ptr(s: String) -> *mut u8 {
     buf
 }
 
-unsafe fn free_c_string(ptr: *mut u8) {
+pub(crate) unsafe fn free_c_string(ptr: *mut u8) {
     if !ptr.is_null() {
         unsafe { libc::free(ptr as *mut libc::c_void) };
     }
 }
+fn grpc_status_to_c(error: &NetError) -> *const NetGrpcStatusC {
+    match error.grpc_status() {
+        Some(status) => status.to_c(),
+        None => std::ptr::null(),
+    }
+}
+impl NetGrpcStatus {
+    pub fn to_c(&self) -> *const NetGrpcStatusC {
+        let ptr =
+            unsafe { libc::malloc(std::mem::size_of::<NetGrpcStatusC>()) as *mut NetGrpcStatusC };
+        if ptr.is_null() {
+            return std::ptr::null();
+        }
+        let details = self.details();
+        let details_ptr = if details.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            unsafe {
+                libc::malloc(std::mem::size_of::<NetGrpcStatusDetailC>() * details.len())
+                    as *mut NetGrpcStatusDetailC
+            }
+        };
+        let details_len = if details_ptr.is_null() {
+            0
+        } else {
+            details.len() as u32
+        };
+        if !details_ptr.is_null() {
+            for (i, d) in details.into_iter().enumerate() {
+                unsafe {
+                    details_ptr.add(i).write(NetGrpcStatusDetailC {
+                        type_url: string_to_c_ptr(d.type_url()) as *const _,
+                        value: bytes_to_ref(d.value().into()),
+                    })
+                };
+            }
+        }
+        unsafe {
+            ptr.write(NetGrpcStatusC {
+                code: self.code(),
+                message: string_to_c_ptr(self.message()) as *const _,
+                details: details_ptr,
+                details_len,
+                details_bin: bytes_to_ref(self.details_bin_ref().to_vec().into()),
+            })
+        };
+        ptr
+    }
+}
+unsafe fn free_grpc_status(ptr: *const NetGrpcStatusC) {
+    if ptr.is_null() {
+        return;
+    }
+    let status = unsafe { &*ptr };
+    unsafe { free_c_string(status.message as *mut u8) };
+    if !status.details.is_null() {
+        for i in 0..status.details_len as usize {
+            let d = unsafe { &*status.details.add(i) };
+            unsafe { free_c_string(d.type_url as *mut u8) };
+            unsafe { d.value.free_memory() };
+        }
+        unsafe { libc::free(status.details as *mut libc::c_void) };
+    }
+    unsafe { status.details_bin.free_memory() };
+    unsafe { libc::free(ptr as *mut libc::c_void) };
+}
+impl NetResponseDns {
+    pub fn to_c(&self) -> NetResponseDnsC {
+        let records = self.records_ref();
+        if records.is_empty() {
+            return NetResponseDnsC {
+                records: std::ptr::null(),
+                records_len: 0,
+            };
+        }
+        let ptr = unsafe {
+            libc::malloc(std::mem::size_of::<NetDnsRecordC>() * records.len()) as *mut NetDnsRecordC
+        };
+        if ptr.is_null() {
+            return NetResponseDnsC {
+                records: std::ptr::null(),
+                records_len: 0,
+            };
+        }
+        for (i, record) in records.iter().enumerate() {
+            unsafe { ptr.add(i).write(record.to_c()) };
+        }
+        NetResponseDnsC {
+            records: ptr,
+            records_len: records.len() as u32,
+        }
+    }
+}
+impl NetDnsRecord {
+    fn to_c(&self) -> NetDnsRecordC {
+        NetDnsRecordC {
+            record_type: self.record_type() as u8,
+            name: unsafe { string_to_c_ptr(self.name()) } as *const _,
+            ttl: self.ttl(),
+            value: unsafe { string_to_c_ptr(self.value()) } as *const _,
+            priority: self.priority(),
+            weight: self.weight(),
+            port: self.port(),
+        }
+    }
+}
+impl NetResponseDnsC {
+    /// # Safety
+    /// Must only be called once, on a value built by `NetResponseDns::to_c`.
+    pub unsafe fn free_memory(&self) {
+        if self.records.is_null() {
+            return;
+        }
+        for i in 0..self.records_len as usize {
+            let record = unsafe { &*self.records.add(i) };
+            unsafe { free_c_string(record.name as *mut u8) };
+            unsafe { free_c_string(record.value as *mut u8) };
+        }
+        unsafe { libc::free(self.records as *mut libc::c_void) };
+    }
+}
+impl NetResponseStun {
+    pub fn to_c(&self) -> NetResponseStunC {
+        let mappings = self.mappings_ref();
+        let ptr = match mappings.is_empty() {
+            true => std::ptr::null_mut(),
+            false => unsafe {
+                libc::malloc(std::mem::size_of::<NetStunMappingC>() * mappings.len())
+                    as *mut NetStunMappingC
+            },
+        };
+        if !ptr.is_null() {
+            for (i, mapping) in mappings.iter().enumerate() {
+                unsafe { ptr.add(i).write(mapping.to_c()) };
+            }
+        }
+        NetResponseStunC {
+            local_address: unsafe { string_to_c_ptr(self.local_address()) } as *const _,
+            local_port: self.local_port(),
+            mappings: ptr,
+            mappings_len: match ptr.is_null() {
+                true => 0,
+                false => mappings.len() as u32,
+            },
+        }
+    }
+}
+impl NetStunMapping {
+    fn to_c(&self) -> NetStunMappingC {
+        NetStunMappingC {
+            server: unsafe { string_to_c_ptr(self.server()) } as *const _,
+            address: unsafe { string_to_c_ptr(self.address()) } as *const _,
+            port: self.port(),
+        }
+    }
+}
+impl NetResponseStunC {
+    /// # Safety
+    /// Must only be called once, on a value built by `NetResponseStun::to_c`.
+    pub unsafe fn free_memory(&self) {
+        unsafe { free_c_string(self.local_address as *mut u8) };
+        if self.mappings.is_null() {
+            return;
+        }
+        for i in 0..self.mappings_len as usize {
+            let mapping = unsafe { &*self.mappings.add(i) };
+            unsafe { free_c_string(mapping.server as *mut u8) };
+            unsafe { free_c_string(mapping.address as *mut u8) };
+        }
+        unsafe { libc::free(self.mappings as *mut libc::c_void) };
+    }
+}
+impl NetResponseSpeedTest {
+    pub fn to_c(&self) -> NetResponseSpeedTestC {
+        match self {
+            NetResponseSpeedTest::Progress(p) => NetResponseSpeedTestC {
+                tag: 1,
+                payload: NetResponseSpeedTestUnionC {
+                    progress: ManuallyDrop::new(NetSpeedTestProgressC {
+                        phase: p.phase() as u8,
+                        bytes: p.bytes(),
+                        elapsed_ms: p.elapsed_ms(),
+                        bitrate: p.bitrate(),
+                    }),
+                },
+            },
+            NetResponseSpeedTest::Finished(r) => NetResponseSpeedTestC {
+                tag: 2,
+                payload: NetResponseSpeedTestUnionC {
+                    finished: ManuallyDrop::new(NetSpeedTestResultC {
+                        rtt_min: r.rtt_min(),
+                        rtt_avg: r.rtt_avg(),
+                        rtt_max: r.rtt_max(),
+                        download_bytes: r.download_bytes(),
+                        download_bitrate: r.download_bitrate(),
+                        upload_bytes: r.upload_bytes(),
+                        upload_bitrate: r.upload_bitrate(),
+                    }),
+                },
+            },
+        }
+    }
+}
+impl NetResponsePortMapping {
+    pub fn to_c(&self) -> NetResponsePortMappingC {
+        NetResponsePortMappingC {
+            gateway: unsafe { string_to_c_ptr(self.gateway()) } as *const _,
+            method: self.method() as u8,
+            protocol: self.protocol() as u8,
+            internal_port: self.internal_port(),
+            external_port: self.external_port(),
+            external_address: unsafe { string_to_c_ptr(self.external_address()) } as *const _,
+            lifetime: self.lifetime(),
+        }
+    }
+}
+impl NetResponseInterfaces {
+    pub fn to_c(&self) -> NetResponseInterfacesC {
+        let interfaces = self.interfaces_ref();
+        let ptr = match interfaces.is_empty() {
+            true => std::ptr::null_mut(),
+            false => unsafe {
+                libc::malloc(std::mem::size_of::<NetInterfaceC>() * interfaces.len())
+                    as *mut NetInterfaceC
+            },
+        };
+        if !ptr.is_null() {
+            for (i, interface) in interfaces.iter().enumerate() {
+                unsafe { ptr.add(i).write(interface.to_c()) };
+            }
+        }
+        let optional = |value: Option<String>| match value {
+            Some(value) => unsafe { string_to_c_ptr(value) as *const c_char },
+            None => std::ptr::null(),
+        };
+        NetResponseInterfacesC {
+            interfaces: ptr,
+            interfaces_len: match ptr.is_null() {
+                true => 0,
+                false => interfaces.len() as u32,
+            },
+            default_interface: optional(self.default_interface()),
+            default_ipv4: optional(self.default_ipv4()),
+            default_ipv6: optional(self.default_ipv6()),
+        }
+    }
+}
+impl NetInterface {
+    fn to_c(&self) -> NetInterfaceC {
+        let (addresses, addresses_len) = strings_to_c(self.addresses_ref());
+        NetInterfaceC {
+            name: unsafe { string_to_c_ptr(self.name()) } as *const _,
+            index: self.index(),
+            up: self.up(),
+            loopback: self.loopback(),
+            addresses,
+            addresses_len,
+        }
+    }
+}
+impl NetResponseInterfacesC {
+    /// # Safety
+    /// Must only be called once, on a value built by `NetResponseInterfaces::to_c`.
+    pub unsafe fn free_memory(&self) {
+        unsafe { free_c_string(self.default_interface as *mut u8) };
+        unsafe { free_c_string(self.default_ipv4 as *mut u8) };
+        unsafe { free_c_string(self.default_ipv6 as *mut u8) };
+        if self.interfaces.is_null() {
+            return;
+        }
+        for i in 0..self.interfaces_len as usize {
+            let interface = unsafe { &*self.interfaces.add(i) };
+            unsafe { free_c_string(interface.name as *mut u8) };
+            unsafe { free_c_strings(interface.addresses, interface.addresses_len) };
+        }
+        unsafe { libc::free(self.interfaces as *mut libc::c_void) };
+    }
+}
+impl NetResponseMdns {
+    pub fn to_c(&self) -> NetResponseMdnsC {
+        match self {
+            NetResponseMdns::Found(service) => NetResponseMdnsC {
+                tag: 1,
+                payload: NetResponseMdnsUnionC {
+                    found: ManuallyDrop::new(service.to_c()),
+                },
+            },
+            NetResponseMdns::Finished => NetResponseMdnsC {
+                tag: 2,
+                payload: NetResponseMdnsUnionC {
+                    finished: ManuallyDrop::new(NetResponseMdnsFinishedC),
+                },
+            },
+        }
+    }
+}
+impl NetResponsePing {
+    pub fn to_c(&self) -> NetResponsePingC {
+        NetResponsePingC {
+            address: unsafe { string_to_c_ptr(self.address()) } as *const _,
+            method: self.method() as u8,
+            sent: self.sent(),
+            received: self.received(),
+            rtt_min: self.rtt_min(),
+            rtt_avg: self.rtt_avg(),
+            rtt_max: self.rtt_max(),
+        }
+    }
+}
+impl NetMdnsService {
+    fn to_c(&self) -> NetMdnsServiceC {
+        let (addresses, addresses_len) = strings_to_c(self.addresses_ref());
+        let (txt, txt_len) = strings_to_c(self.txt_ref());
+        NetMdnsServiceC {
+            instance: unsafe { string_to_c_ptr(self.instance()) } as *const _,
+            service: unsafe { string_to_c_ptr(self.service()) } as *const _,
+            host: unsafe { string_to_c_ptr(self.host()) } as *const _,
+            port: self.port(),
+            addresses,
+            addresses_len,
+            txt,
+            txt_len,
+        }
+    }
+}
+impl NetMdnsServiceC {
+    /// # Safety
+    /// Must only be called once, on a value built by `NetResponseMdns::to_c`.
+    pub unsafe fn free_memory(&self) {
+        unsafe { free_c_string(self.instance as *mut u8) };
+        unsafe { free_c_string(self.service as *mut u8) };
+        unsafe { free_c_string(self.host as *mut u8) };
+        unsafe { free_c_strings(self.addresses, self.addresses_len) };
+        unsafe { free_c_strings(self.txt, self.txt_len) };
+    }
+}
+fn strings_to_c(values: &[String]) -> (*const *const c_char, u32) {
+    if values.is_empty() {
+        return (std::ptr::null(), 0);
+    }
+    let ptr = unsafe {
+        libc::malloc(std::mem::size_of::<*const c_char>() * values.len()) as *mut *const c_char
+    };
+    if ptr.is_null() {
+        return (std::ptr::null(), 0);
+    }
+    for (i, value) in values.iter().enumerate() {
+        unsafe { ptr.add(i).write(string_to_c_ptr(value.clone()) as *const _) };
+    }
+    (ptr, values.len() as u32)
+}
+unsafe fn free_c_strings(ptr: *const *const c_char, len: u32) {
+    if ptr.is_null() {
+        return;
+    }
+    for i in 0..len as usize {
+        unsafe { free_c_string(*ptr.add(i) as *mut u8) };
+    }
+    unsafe { libc::free(ptr as *mut libc::c_void) };
+}
 impl NetHttpHeader {
-    pub fn headers_to_c(headers: Vec<NetHttpHeader>) -> (*const NetHttpHeaderC, u32) {
+    pub fn headers_to_c(headers: &[NetHttpHeader]) -> (*const NetHttpHeaderC, u32) {
         let len = headers.len();
 
         if len == 0 {
@@ -733,20 +2827,105 @@ impl NetHttpHeader {
     }
 }
 
+impl NetHttpHeaderC {
+    /// Index of the first header at or after `start` named `name` (ASCII case-insensitive)
+    ///
+    /// # Safety
+    /// `headers` must point to `len` valid entries and `name` must be null or a C string.
+    pub unsafe fn find(
+        headers: *const NetHttpHeaderC,
+        len: u32,
+        name: *const c_char,
+        start: u32,
+    ) -> Option<u32> {
+        if headers.is_null() {
+            return None;
+        }
+        let name = unsafe { Utils::cstr_to_slice(name as *const u8) };
+        let headers = unsafe { slice::from_raw_parts(headers, len as usize) };
+        (start..len).find(|i| {
+            let key = unsafe { Utils::cstr_to_slice(headers[*i as usize].key as *const u8) };
+            key.eq_ignore_ascii_case(name)
+        })
+    }
+}
+
 impl NetResponseHttpC {
     pub unsafe fn free_memory(&self) {
         unsafe { self.body.free_memory() };
-        if self.headers.is_null() {
+        unsafe { NetResponseHttpC::free_headers(self.headers, self.headers_len) };
+        unsafe { NetResponseHttpC::free_headers(self.trailers, self.trailers_len) };
+        unsafe { free_c_string(self.version as *mut u8) };
+        unsafe { free_c_string(self.remote_addr as *mut u8) };
+    }
+
+    unsafe fn free_headers(headers: *const NetHttpHeaderC, len: u32) {
+        if headers.is_null() {
             return;
         }
 
-        for i in 0..self.headers_len as usize {
-            let h = unsafe { self.headers.add(i) };
+        for i in 0..len as usize {
+            let h = unsafe { headers.add(i) };
 
             unsafe { free_c_string((*h).key as *mut u8) };
             unsafe { free_c_string((*h).value as *mut u8) };
         }
 
-        unsafe { libc::free(self.headers as *mut libc::c_void) };
+        unsafe { libc::free(headers as *mut libc::c_void) };
+    }
+}
+
+impl NetClientCertRequestC {
+    pub fn new(request_id: u32, host: &str, issuers: &[&[u8]]) -> Self {
+        let ptr = match issuers.is_empty() {
+            true => std::ptr::null_mut(),
+            false => unsafe {
+                libc::malloc(std::mem::size_of::<BytesRefC>() * issuers.len()) as *mut BytesRefC
+            },
+        };
+        if !ptr.is_null() {
+            for (i, issuer) in issuers.iter().enumerate() {
+                let issuer = bytes_to_ref(Bytes::copy_from_slice(issuer));
+                unsafe { ptr.add(i).write(issuer) };
+            }
+        }
+        Self {
+            request_id,
+            host: unsafe { string_to_c_ptr(host.to_string()) } as *const _,
+            issuers: ptr,
+            issuers_len: match ptr.is_null() {
+                true => 0,
+                false => issuers.len() as u32,
+            },
+        }
+    }
+
+    /// # Safety
+    /// Must only be called once, on a value built by `NetClientCertRequestC::new`.
+    pub unsafe fn free_memory(&self) {
+        unsafe { free_c_string(self.host as *mut u8) };
+        if self.issuers.is_null() {
+            return;
+        }
+        for i in 0..self.issuers_len as usize {
+            unsafe { (*self.issuers.add(i)).free_memory() };
+        }
+        unsafe { libc::free(self.issuers as *mut libc::c_void) };
+    }
+}
+
+impl NetClientIdentityC {
+    /// # Safety
+    /// `certs` must point to `certs_len` valid buffers, as `key` must be one.
+    pub unsafe fn to_identity(&self) -> Result<Arc<CertifiedKey>, NetError> {
+        let chain = match self.certs.is_null() {
+            true => Vec::new(),
+            false => unsafe { slice::from_raw_parts(self.certs, self.certs_len.into()) }
+                .iter()
+                .map(|cert| Vec::from(unsafe { bytes_from_c(cert) }))
+                .collect(),
+        };
+        let key = Vec::from(unsafe { bytes_from_c(&self.key) });
+        ClientCertRegistry::identity(chain, key)
     }
 }
@@ -1,26 +1,87 @@
-use std::fmt;
+use std::{fmt, net::SocketAddr, time::Duration};
+
+use bytes::Bytes;
 
 use crate::{
     types::{
-        config::{NetConfigTor, NetProtocol},
+        config::{NetConfigTor, NetMode, NetProtocol},
         error::NetResultStatus,
+        grpc_health::NetGrpcHealth,
+        response::{NetDnsRecordType, NetPortMappingProtocol},
     },
     utils::buffer::StreamEncoding,
 };
 
 pub struct NetRequestGrpcUnary<'a> {
     pub method: &'a str,
-    pub data: &'a [u8],
+    pub data: Bytes,
+    /// Call metadata such as `authorization`; `-bin` keys are sent as binary
+    pub metadata: Vec<NetHttpHeaderRef<'a>>,
+    /// Keeps dialing while the server cannot be reached, until the request times out,
+    /// instead of failing on the first connect error
+    pub wait_for_ready: bool,
+    /// `:authority` of this call in place of `NetConfigRequest::grpc_authority`
+    pub authority: Option<&'a str>,
 }
 
 pub struct NetRequestGrpcStream<'a> {
     pub method: &'a str,
-    pub data: &'a [u8],
+    pub data: Bytes,
+    /// Call metadata, sent again when the stream is restarted
+    pub metadata: Vec<NetHttpHeaderRef<'a>>,
+    /// Seconds the stream may run, restarts included, sent as `grpc-timeout` so the server
+    /// ends it too; 0 keeps it open until unsubscribed. The request timeout only covers
+    /// opening it
+    pub deadline: u32,
+    /// Messages posted before more are granted with `NetRequestGrpc::Credit`, 0 for no
+    /// limit. Messages held back fill the stream queue, whose `Block` overflow then stops
+    /// reading the call, so a slow consumer slows the server down instead of losing any
+    pub credits: u32,
+    /// Keeps dialing while the server cannot be reached, until the request times out
+    pub wait_for_ready: bool,
+    /// `:authority` of this call, its restarts included
+    pub authority: Option<&'a str>,
+    /// When the stream resubscribes after an error, by `NetConfigRequest::stream_reconnect`,
+    /// Dart is first posted `NetResponseStream::Rebuild` with the last message and answers
+    /// with the request to make the call again with, e.g. to resume after it
+    pub rebuild: bool,
 }
 
+/// Cancels stream `id`; its call is reset so the server stops sending, and the stream ends
+/// with `NetResponseStream::Cancelled`
 pub struct NetRequestGrpcUnsubscribe {
     pub id: i32,
 }
+
+/// `credits` more messages stream `id` may post; a stream opened without credits ignores it
+pub struct NetRequestGrpcCredit {
+    pub id: i32,
+    pub credits: u32,
+}
+
+/// Request stream `id` resubscribes with, answering its `NetResponseStream::Rebuild`; JSON
+/// when the calls are transcoded
+pub struct NetRequestGrpcRebuild {
+    pub id: i32,
+    pub data: Bytes,
+}
+
+/// `grpc.health.v1.Health` of the transport's server. `Check` answers with the serving
+/// status; `watch` subscribes instead, each message of the stream is one byte, the
+/// `NetGrpcServingStatus` the server changed to
+pub struct NetRequestGrpcHealth<'a> {
+    /// Service to ask about, empty for the server as a whole
+    pub service: &'a str,
+    pub watch: bool,
+}
+impl NetRequestGrpcHealth<'_> {
+    pub fn method(&self) -> &'static str {
+        match self.watch {
+            true => NetGrpcHealth::WATCH,
+            false => NetGrpcHealth::CHECK,
+        }
+    }
+}
 pub struct NetHttpHeaderRef<'a> {
     pub key: &'a str,
     pub value: &'a str,
@@ -30,33 +91,199 @@ pub enum NetRequestGrpc<'a> {
     Unary(NetRequestGrpcUnary<'a>),
     Stream(NetRequestGrpcStream<'a>),
     Unsubscribe(NetRequestGrpcUnsubscribe),
+    Health(NetRequestGrpcHealth<'a>),
+    Credit(NetRequestGrpcCredit),
+    /// The channel's `NetGrpcConnectivity`, answered without dialing
+    State,
+    /// The transport's `NetGrpcStats`
+    Stats,
+    Rebuild(NetRequestGrpcRebuild),
 }
 
+/// Methods RFC 9110 defines as idempotent; others need an `Idempotency-Key` header
+const IDEMPOTENT_METHODS: &[&str] = &["GET", "HEAD", "PUT", "DELETE", "OPTIONS", "TRACE"];
+
 pub struct NetRequestHttp<'a> {
     pub method: &'a str,
     pub url: &'a str,
-    pub body: Option<&'a [u8]>,
+    pub body: Option<Bytes>,
     pub headers: Option<Vec<NetHttpHeaderRef<'a>>>,
     pub encoding: StreamEncoding,
     pub retry_config: NetHttpRetryConfig<'a>,
+    /// Queue the request when it can not connect, if the transport has an offline queue.
+    /// Only idempotent methods, or requests with an `Idempotency-Key` header, are queued
+    pub queue_offline: bool,
+    /// Writes a successful response body to this file instead of returning it, posting
+    /// `HttpDownload` progress while it arrives; the response then has an empty body. Such
+    /// requests are not queued offline
+    pub download_path: Option<&'a str>,
+    /// Continues an interrupted `download_path` download instead of starting it over
+    pub resume: Option<NetHttpResume<'a>>,
+}
+
+impl NetRequestHttp<'_> {
+    /// Whether sending a request twice is safe, so a replay after a lost answer does no harm
+    pub fn is_idempotent(method: &str, headers: Option<&Vec<NetHttpHeaderRef<'_>>>) -> bool {
+        IDEMPOTENT_METHODS
+            .iter()
+            .any(|idempotent| idempotent.eq_ignore_ascii_case(method))
+            || headers.is_some_and(|headers| {
+                headers
+                    .iter()
+                    .any(|header| header.key.eq_ignore_ascii_case("idempotency-key"))
+            })
+    }
+}
+
+/// Continues a download from the `.part` file an interrupted one left behind. `Range` asks
+/// for the body from `offset` on and `If-Range` only while `if_range`, the `ETag` or
+/// `Last-Modified` the server sent before, still matches. A `206` answer is appended after
+/// the first `offset` bytes; a `200` means the server did not honor the range, or the body
+/// changed, and the download starts over
+#[derive(Clone, Copy, Debug)]
+pub struct NetHttpResume<'a> {
+    pub offset: u64,
+    pub if_range: Option<&'a str>,
+}
+
+/// Server-sent events: `Subscribe` answers with the id its events are posted under as
+/// stream data, until the server ends the stream or `Unsubscribe` cancels it. The request's
+/// encoding, retry and offline queueing do not apply
+pub enum NetRequestHttpEvents<'a> {
+    Subscribe(NetRequestHttp<'a>),
+    Unsubscribe(i32),
 }
 
 pub struct NetRequestSocketSend<'a> {
     pub data: &'a [u8],
 }
 
+/// The subscription variants take the id Dart gave the listener, each with a buffer of its
+/// own; `None` is the default subscription, whose unsubscribe also closes the connection
 pub enum NetRequestSocket<'a> {
-    Subscribe,
-    Unsubscribe,
+    Subscribe(Option<i32>),
+    Unsubscribe(Option<i32>),
     Send(NetRequestSocketSend<'a>),
+    BufferStatus(Option<i32>),
+    FlushBuffer(Option<i32>),
+}
+
+/// Where a DNS lookup is answered
+#[derive(Clone, Debug)]
+pub enum NetDnsResolver {
+    /// The OS resolver for A/AAAA, the nameservers of `/etc/resolv.conf` for other types
+    System,
+    /// DNS-over-HTTPS (RFC 8484) endpoint, reached through `NetMode`
+    DoH(String, NetMode),
+    /// Resolution by a Tor exit relay; A/AAAA only
+    Tor,
+    /// These nameservers, asked in order
+    Nameservers(Vec<SocketAddr>),
+}
+
+pub struct NetRequestDns {
+    pub name: String,
+    pub record_type: NetDnsRecordType,
+    pub resolver: NetDnsResolver,
+}
+
+/// `Browse` lists the instances of a service type such as `_http._tcp.local`, `Resolve`
+/// looks up one instance such as `Printer._ipp._tcp.local`
+pub enum NetRequestMdns {
+    Browse(String),
+    Resolve(String),
+}
+
+/// `count` probes to `host`, one every `interval_ms`; a probe is lost when no reply came
+/// before the next one is sent
+pub struct NetRequestPing {
+    pub host: String,
+    pub count: u16,
+    pub interval_ms: u32,
+}
+
+/// Servers are tried in order as `host` or `host:port`; the default pool when empty
+pub struct NetRequestNtp {
+    pub servers: Vec<String>,
+}
+
+/// Binding requests to each server, `host` or `host:port`; the default servers when empty
+pub struct NetRequestStun {
+    pub servers: Vec<String>,
+}
+
+/// Dials `host:port` and closes the connection without sending data. `timeout_ms` of 0
+/// waits 5 seconds; `NetMode::Custom` is not supported
+pub struct NetRequestPortCheck {
+    pub host: String,
+    pub port: u16,
+    pub mode: NetMode,
+    pub timeout_ms: u32,
+}
+
+/// Asks the gateway to forward `external_port` to `internal_port` of this device for
+/// `lifetime` seconds. Sending it again renews the mapping and a `lifetime` of 0 removes it.
+/// `external_port` 0 asks for the internal port; the gateway is discovered when `None`
+pub struct NetRequestPortMapping {
+    pub protocol: NetPortMappingProtocol,
+    pub internal_port: u16,
+    pub external_port: u16,
+    pub lifetime: u32,
+    pub gateway: Option<String>,
+}
+
+/// Samples `rtt_samples` round trips, then downloads from `download_url` and posts to
+/// `upload_url` for `duration_ms` each (0 for 10 seconds). A direction without a url is
+/// skipped; at least one is needed
+pub struct NetRequestSpeedTest {
+    pub download_url: Option<String>,
+    pub upload_url: Option<String>,
+    pub duration_ms: u32,
+    pub rtt_samples: u8,
+    pub mode: NetMode,
+}
+
+/// Fetches `url`, a generate_204 style probe by default, without following redirects.
+/// `timeout_ms` of 0 waits 5 seconds; the probe always goes over clearnet
+pub struct NetRequestCaptivePortal {
+    pub url: Option<String>,
+    pub timeout_ms: u32,
+}
+
+/// Pipes the stream transports `source` and `target` into each other until either closes.
+/// Closing one closes the other; the request timeout does not apply
+pub struct NetRequestSplice {
+    pub source: u32,
+    pub target: u32,
+}
+
+/// Resolves `names` with `resolver` and caches their A/AAAA answers for later connects
+pub struct NetRequestDnsPrefetch {
+    pub names: Vec<String>,
+    pub resolver: NetDnsResolver,
 }
 
 pub enum NetRequestKind<'a> {
     Socket(NetRequestSocket<'a>),
     Grpc(NetRequestGrpc<'a>),
     Http(NetRequestHttp<'a>),
+    HttpEvents(NetRequestHttpEvents<'a>),
     InitTor(NetConfigTor),
     TorInited,
+    DnsLookup(NetRequestDns),
+    Mdns(NetRequestMdns),
+    Ping(NetRequestPing),
+    Ntp(NetRequestNtp),
+    Stun(NetRequestStun),
+    PortCheck(NetRequestPortCheck),
+    Interfaces,
+    /// `true` posts `NetworkChanged` under this request's id until a `false` watch stops it
+    WatchInterfaces(bool),
+    PortMapping(NetRequestPortMapping),
+    SpeedTest(NetRequestSpeedTest),
+    CaptivePortal(NetRequestCaptivePortal),
+    Splice(NetRequestSplice),
+    DnsPrefetch(NetRequestDnsPrefetch),
 }
 
 pub struct NetRequest<'a> {
@@ -70,6 +297,8 @@ pub struct NetHttpRetryConfig<'a> {
     pub max_retries: u8,
     pub retry_status: &'a [u16],
     pub retry_delay: u32,
+    /// Longest a `Retry-After` of a 429 or 503 is waited, in ms; 0 is a minute
+    pub max_retry_after: u32,
 }
 
 impl<'a> NetHttpRetryConfig<'a> {
@@ -78,6 +307,14 @@ impl<'a> NetHttpRetryConfig<'a> {
             max_retries: 1,
             retry_status: &[],
             retry_delay: 0,
+            max_retry_after: 0,
+        }
+    }
+
+    pub fn max_retry_after(&self) -> Duration {
+        match self.max_retry_after {
+            0 => Duration::from_secs(60),
+            max => Duration::from_millis(max.into()),
         }
     }
 }
@@ -104,7 +341,10 @@ impl<'a> NetRequest<'a> {
     }
     pub fn to_protocol_config(&'a self, protocol: NetProtocol) -> Result<(), NetResultStatus> {
         let _ = match protocol {
-            NetProtocol::Http => self.to_http_request().map(|_| ())?,
+            NetProtocol::Http => match &self.kind {
+                NetRequestKind::HttpEvents(_) => (),
+                _ => self.to_http_request().map(|_| ())?,
+            },
             NetProtocol::Grpc => self.to_grpc_request().map(|_| ())?,
             NetProtocol::WebSocket | NetProtocol::Socket => self.to_socket_request().map(|_| ())?,
         };
@@ -121,12 +361,112 @@ impl<'a> fmt::Debug for NetRequestKind<'a> {
                 NetRequestGrpc::Unsubscribe(_) => {
                     write!(f, "NetRequestKind::Grpc::Unsubscribe")
                 }
+                NetRequestGrpc::Health(health) => {
+                    write!(
+                        f,
+                        "NetRequestKind::Grpc::Health {{ watch: {} }}",
+                        health.watch
+                    )
+                }
+                NetRequestGrpc::Credit(credit) => {
+                    write!(
+                        f,
+                        "NetRequestKind::Grpc::Credit {{ id: {}, credits: {} }}",
+                        credit.id, credit.credits
+                    )
+                }
+                NetRequestGrpc::State => write!(f, "NetRequestKind::Grpc::State"),
+                NetRequestGrpc::Stats => write!(f, "NetRequestKind::Grpc::Stats"),
+                NetRequestGrpc::Rebuild(rebuild) => {
+                    write!(
+                        f,
+                        "NetRequestKind::Grpc::Rebuild {{ id: {}, len: {} }}",
+                        rebuild.id,
+                        rebuild.data.len()
+                    )
+                }
             },
             NetRequestKind::Http(http) => {
                 write!(f, "NetRequestKind::Http {{ url: {} }}", http.url)
             }
+            NetRequestKind::HttpEvents(NetRequestHttpEvents::Subscribe(http)) => {
+                write!(
+                    f,
+                    "NetRequestKind::HttpEvents::Subscribe {{ url: {} }}",
+                    http.url
+                )
+            }
+            NetRequestKind::HttpEvents(NetRequestHttpEvents::Unsubscribe(id)) => {
+                write!(
+                    f,
+                    "NetRequestKind::HttpEvents::Unsubscribe {{ id: {} }}",
+                    id
+                )
+            }
             NetRequestKind::InitTor(_) => write!(f, "NetRequestKind::InitTor"),
             NetRequestKind::TorInited => write!(f, "NetRequestKind::TorInited"),
+            NetRequestKind::DnsLookup(dns) => write!(
+                f,
+                "NetRequestKind::DnsLookup {{ name: {}, type: {:?} }}",
+                dns.name, dns.record_type
+            ),
+            NetRequestKind::Mdns(NetRequestMdns::Browse(service)) => {
+                write!(f, "NetRequestKind::Mdns::Browse {{ service: {} }}", service)
+            }
+            NetRequestKind::Mdns(NetRequestMdns::Resolve(instance)) => {
+                write!(
+                    f,
+                    "NetRequestKind::Mdns::Resolve {{ instance: {} }}",
+                    instance
+                )
+            }
+            NetRequestKind::Ping(ping) => write!(
+                f,
+                "NetRequestKind::Ping {{ host: {}, count: {} }}",
+                ping.host, ping.count
+            ),
+            NetRequestKind::Ntp(ntp) => {
+                write!(f, "NetRequestKind::Ntp {{ servers: {:?} }}", ntp.servers)
+            }
+            NetRequestKind::Stun(stun) => {
+                write!(f, "NetRequestKind::Stun {{ servers: {:?} }}", stun.servers)
+            }
+            NetRequestKind::PortCheck(check) => write!(
+                f,
+                "NetRequestKind::PortCheck {{ host: {}, port: {}, mode: {:?} }}",
+                check.host, check.port, check.mode
+            ),
+            NetRequestKind::Interfaces => write!(f, "NetRequestKind::Interfaces"),
+            NetRequestKind::WatchInterfaces(watch) => {
+                write!(f, "NetRequestKind::WatchInterfaces {{ watch: {} }}", watch)
+            }
+            NetRequestKind::PortMapping(mapping) => write!(
+                f,
+                "NetRequestKind::PortMapping {{ protocol: {:?}, internal_port: {}, lifetime: {} }}",
+                mapping.protocol, mapping.internal_port, mapping.lifetime
+            ),
+            NetRequestKind::SpeedTest(test) => write!(
+                f,
+                "NetRequestKind::SpeedTest {{ download_url: {:?}, upload_url: {:?} }}",
+                test.download_url, test.upload_url
+            ),
+            NetRequestKind::CaptivePortal(probe) => {
+                write!(
+                    f,
+                    "NetRequestKind::CaptivePortal {{ url: {:?} }}",
+                    probe.url
+                )
+            }
+            NetRequestKind::Splice(splice) => write!(
+                f,
+                "NetRequestKind::Splice {{ source: {}, target: {} }}",
+                splice.source, splice.target
+            ),
+            NetRequestKind::DnsPrefetch(prefetch) => write!(
+                f,
+                "NetRequestKind::DnsPrefetch {{ names: {:?}, resolver: {:?} }}",
+                prefetch.names, prefetch.resolver
+            ),
         }
     }
 }
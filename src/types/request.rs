@@ -12,12 +12,18 @@ use wasm_bindgen::prelude::*;
 pub struct NetRequestGrpcUnary {
     method: String,
     data: Vec<u8>,
+    /// Call metadata such as `authorization`; `-bin` keys are sent as binary
+    metadata: Vec<NetHttpHeader>,
 }
 #[wasm_bindgen]
 impl NetRequestGrpcUnary {
     #[wasm_bindgen]
-    pub fn create(method: String, data: Vec<u8>) -> Self {
-        Self { method, data }
+    pub fn create(method: String, data: Vec<u8>, metadata: Option<Vec<NetHttpHeader>>) -> Self {
+        Self {
+            method,
+            data,
+            metadata: metadata.unwrap_or_default(),
+        }
     }
 }
 impl NetRequestGrpcUnary {
@@ -28,6 +34,10 @@ impl NetRequestGrpcUnary {
     pub fn data(&self) -> &[u8] {
         &self.data
     }
+
+    pub fn metadata(&self) -> &[NetHttpHeader] {
+        &self.metadata
+    }
 }
 
 #[derive(Clone)]
@@ -35,12 +45,30 @@ impl NetRequestGrpcUnary {
 pub struct NetRequestGrpcStream {
     method: String,
     data: Vec<u8>,
+    /// Call metadata such as `authorization`; `-bin` keys are sent as binary
+    metadata: Vec<NetHttpHeader>,
+    /// Seconds the stream may run, sent as `grpc-timeout`; 0 keeps it open
+    deadline: u32,
+    /// Messages posted before more are granted with `NetRequestGrpcCredit`, 0 for no limit
+    credits: u32,
 }
 #[wasm_bindgen]
 impl NetRequestGrpcStream {
     #[wasm_bindgen]
-    pub fn create(method: String, data: Vec<u8>) -> Self {
-        Self { method, data }
+    pub fn create(
+        method: String,
+        data: Vec<u8>,
+        metadata: Option<Vec<NetHttpHeader>>,
+        deadline: Option<u32>,
+        credits: Option<u32>,
+    ) -> Self {
+        Self {
+            method,
+            data,
+            metadata: metadata.unwrap_or_default(),
+            deadline: deadline.unwrap_or_default(),
+            credits: credits.unwrap_or_default(),
+        }
     }
 }
 impl NetRequestGrpcStream {
@@ -51,6 +79,18 @@ impl NetRequestGrpcStream {
     pub fn data(&self) -> &[u8] {
         &self.data
     }
+
+    pub fn metadata(&self) -> &[NetHttpHeader] {
+        &self.metadata
+    }
+
+    pub fn deadline(&self) -> u32 {
+        self.deadline
+    }
+
+    pub fn credits(&self) -> u32 {
+        self.credits
+    }
 }
 
 #[derive(Clone)]
@@ -72,12 +112,73 @@ impl NetRequestGrpcUnsubscribe {
     }
 }
 
+/// `credits` more messages stream `id` may post
+#[derive(Clone)]
+#[wasm_bindgen]
+pub struct NetRequestGrpcCredit {
+    id: i32,
+    credits: u32,
+}
+#[wasm_bindgen]
+impl NetRequestGrpcCredit {
+    #[wasm_bindgen]
+    pub fn create(id: i32, credits: u32) -> Self {
+        Self { id, credits }
+    }
+}
+
+impl NetRequestGrpcCredit {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn credits(&self) -> u32 {
+        self.credits
+    }
+}
+
+/// `grpc.health.v1.Health/Check` of the transport's server, or `Watch` for a stream whose
+/// messages are one byte, the `NetGrpcServingStatus`
+#[derive(Clone)]
+#[wasm_bindgen]
+pub struct NetRequestGrpcHealth {
+    /// Service to ask about, empty for the server as a whole
+    service: String,
+    watch: bool,
+}
+#[wasm_bindgen]
+impl NetRequestGrpcHealth {
+    #[wasm_bindgen]
+    pub fn create(service: Option<String>, watch: bool) -> Self {
+        Self {
+            service: service.unwrap_or_default(),
+            watch,
+        }
+    }
+}
+
+impl NetRequestGrpcHealth {
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    pub fn watch(&self) -> bool {
+        self.watch
+    }
+}
+
 #[derive(Clone)]
 
 pub enum NetRequestGrpc {
     Unary(NetRequestGrpcUnary),
     Stream(NetRequestGrpcStream),
     Unsubscribe(NetRequestGrpcUnsubscribe),
+    Health(NetRequestGrpcHealth),
+    Credit(NetRequestGrpcCredit),
+    /// The channel's `NetGrpcConnectivity`
+    State,
+    /// The transport's `NetGrpcStats`
+    Stats,
 }
 
 #[derive(Clone)]
@@ -164,6 +265,8 @@ pub enum NetRequestSocket {
     Subscribe,
     Unsubscribe,
     Send(NetRequestSocketSend),
+    BufferStatus,
+    FlushBuffer,
 }
 
 impl NetRequestSocket {
@@ -194,6 +297,8 @@ pub struct NetRequestWasm {
     gprc_stream: Option<NetRequestGrpcStream>,
     grpc_unsubscribe: Option<NetRequestGrpcUnsubscribe>,
     http: Option<NetRequestHttp>,
+    grpc_health: Option<NetRequestGrpcHealth>,
+    grpc_credit: Option<NetRequestGrpcCredit>,
 }
 #[wasm_bindgen]
 impl NetRequestWasm {
@@ -207,6 +312,8 @@ impl NetRequestWasm {
         gprc_stream: Option<NetRequestGrpcStream>,
         grpc_unsubscribe: Option<NetRequestGrpcUnsubscribe>,
         http: Option<NetRequestHttp>,
+        grpc_health: Option<NetRequestGrpcHealth>,
+        grpc_credit: Option<NetRequestGrpcCredit>,
     ) -> NetRequestWasm {
         Self {
             transport_id,
@@ -218,6 +325,8 @@ impl NetRequestWasm {
             gprc_stream,
             grpc_unsubscribe,
             http,
+            grpc_health,
+            grpc_credit,
         }
     }
 }
@@ -268,6 +377,26 @@ impl NetRequestWasm {
                     .ok_or(NetResultStatus::InvalidRequestParameters)?;
                 NetRequestKind::Http(http.clone())
             }
+            8 => NetRequestKind::Socket(NetRequestSocket::BufferStatus),
+            9 => NetRequestKind::Socket(NetRequestSocket::FlushBuffer),
+            10 => {
+                // Grpc Health
+                let grpc_health = self
+                    .grpc_health
+                    .as_ref()
+                    .ok_or(NetResultStatus::InvalidRequestParameters)?;
+                NetRequestKind::Grpc(NetRequestGrpc::Health(grpc_health.clone()))
+            }
+            11 => {
+                // Grpc Credit
+                let grpc_credit = self
+                    .grpc_credit
+                    .as_ref()
+                    .ok_or(NetResultStatus::InvalidRequestParameters)?;
+                NetRequestKind::Grpc(NetRequestGrpc::Credit(grpc_credit.clone()))
+            }
+            12 => NetRequestKind::Grpc(NetRequestGrpc::State),
+            13 => NetRequestKind::Grpc(NetRequestGrpc::Stats),
             _ => return Err(NetResultStatus::InvalidRequestParameters),
         };
 
@@ -1,14 +1,198 @@
-use crate::types::{config::NetHttpHeader, error::NetResultStatus};
+use bytes::Bytes;
+
+use crate::{
+    types::{
+        config::NetHttpHeaders,
+        error::{NetError, NetResultStatus},
+        grpc_health::NetGrpcServingStatus,
+        grpc_stats::NetGrpcStats,
+        grpc_status::NetGrpcStatus,
+    },
+    utils::buffer::StreamEncoding,
+};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 #[derive(Debug)]
 pub struct NetResponseSocketOk;
 
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseSocketBuffer {
+    buffered: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseSocketBuffer {
+    /// Getter for `buffered`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn buffered(&self) -> u32 {
+        self.buffered
+    }
+}
+
+impl NetResponseSocketBuffer {
+    pub fn new(buffered: u32) -> NetResponseSocketBuffer {
+        Self { buffered }
+    }
+}
+
+/// Endpoint a failover transport switched to; `index` 0 is the configured url
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseEndpoint {
+    index: u32,
+    url: String,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseEndpoint {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+impl NetResponseEndpoint {
+    pub fn new(index: u32, url: String) -> NetResponseEndpoint {
+        Self { index, url }
+    }
+}
+
+/// DNS record types a lookup can ask for; values are the wire `QTYPE`s
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetDnsRecordType {
+    A = 1,
+    Cname = 5,
+    Txt = 16,
+    Aaaa = 28,
+    Srv = 33,
+}
+
+impl NetDnsRecordType {
+    pub fn from_u8(code: u8) -> Option<NetDnsRecordType> {
+        [
+            NetDnsRecordType::A,
+            NetDnsRecordType::Cname,
+            NetDnsRecordType::Txt,
+            NetDnsRecordType::Aaaa,
+            NetDnsRecordType::Srv,
+        ]
+        .into_iter()
+        .find(|record_type| *record_type as u8 == code)
+    }
+}
+
+/// One answer of a DNS lookup. `value` is the address for A/AAAA, the target name for
+/// CNAME/SRV and the joined character strings for TXT; `priority`, `weight` and `port`
+/// are only set for SRV. `ttl` is 0 when the resolver does not report one.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetDnsRecord {
+    record_type: NetDnsRecordType,
+    name: String,
+    ttl: u32,
+    value: String,
+    priority: u16,
+    weight: u16,
+    port: u16,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetDnsRecord {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn record_type(&self) -> NetDnsRecordType {
+        self.record_type
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn value(&self) -> String {
+        self.value.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn priority(&self) -> u16 {
+        self.priority
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn weight(&self) -> u16 {
+        self.weight
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl NetDnsRecord {
+    pub fn new(record_type: NetDnsRecordType, name: String, ttl: u32, value: String) -> Self {
+        Self {
+            record_type,
+            name,
+            ttl,
+            value,
+            priority: 0,
+            weight: 0,
+            port: 0,
+        }
+    }
+
+    pub fn srv(
+        name: String,
+        ttl: u32,
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    ) -> Self {
+        Self {
+            priority,
+            weight,
+            port,
+            ..Self::new(NetDnsRecordType::Srv, name, ttl, target)
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseDns {
+    records: Vec<NetDnsRecord>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseDns {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn records(&self) -> Vec<NetDnsRecord> {
+        self.records.clone()
+    }
+}
+
+impl NetResponseDns {
+    pub fn new(records: Vec<NetDnsRecord>) -> NetResponseDns {
+        Self { records }
+    }
+    pub fn records_ref(&self) -> &[NetDnsRecord] {
+        &self.records
+    }
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Clone, Debug)]
 pub struct NetResponseStreamData {
     id: Option<i32>,
-    data: Vec<u8>,
+    data: Bytes,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
@@ -18,27 +202,93 @@ impl NetResponseStreamData {
     pub fn id(&self) -> Option<i32> {
         self.id
     }
+    /// Copy of `data`, so JS owns its own
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
     pub fn data(&self) -> Vec<u8> {
-        self.data.clone() // clone so JS owns its copy
+        self.data.to_vec()
     }
 }
 
 impl NetResponseStreamData {
-    pub fn new(id: Option<i32>, data: Vec<u8>) -> NetResponseStreamData {
+    pub fn new(id: Option<i32>, data: Bytes) -> NetResponseStreamData {
         Self { id, data }
     }
+    pub fn data_ref(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn into_data(self) -> Bytes {
+        self.data
+    }
+}
+/// A subscription receives again after its transport reconnected. `flushed` bytes of a
+/// message cut off by the drop were discarded from its buffer
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseStreamReconnected {
+    id: Option<i32>,
+    flushed: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseStreamReconnected {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn id(&self) -> Option<i32> {
+        self.id
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn flushed(&self) -> u32 {
+        self.flushed
+    }
+}
+
+impl NetResponseStreamReconnected {
+    pub fn new(id: Option<i32>, flushed: u32) -> Self {
+        Self { id, flushed }
+    }
+}
+/// A gRPC stream opened with `rebuild` is about to resubscribe after an error. `last` is
+/// the last message it posted, `None` before the first; the stream waits for the request
+/// to make its call with, and keeps the one it had when none comes within a while
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseStreamRebuild {
+    id: Option<i32>,
+    last: Option<Bytes>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseStreamRebuild {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn id(&self) -> Option<i32> {
+        self.id
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn last(&self) -> Option<Vec<u8>> {
+        self.last.as_ref().map(|last| last.to_vec())
+    }
+}
+
+impl NetResponseStreamRebuild {
+    pub fn new(id: Option<i32>, last: Option<Bytes>) -> Self {
+        Self { id, last }
+    }
+    pub fn into_last(self) -> Option<Bytes> {
+        self.last
+    }
 }
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Clone, Debug)]
 pub struct NetResponseStreamError {
     id: Option<i32>,
-    status: NetResultStatus,
+    error: NetError,
 }
 
 impl NetResponseStreamError {
-    pub fn new(id: Option<i32>, status: NetResultStatus) -> NetResponseStreamError {
-        Self { id, status }
+    pub fn new(id: Option<i32>, error: NetError) -> NetResponseStreamError {
+        Self { id, error }
+    }
+    pub fn error(&self) -> &NetError {
+        &self.error
     }
 }
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
@@ -52,7 +302,19 @@ impl NetResponseStreamError {
     /// Getter for `status`
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
     pub fn status(&self) -> NetResultStatus {
-        self.status.clone()
+        self.error.status()
+    }
+
+    /// Human readable description including the underlying error chain
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn message(&self) -> String {
+        self.error.to_string()
+    }
+
+    /// Getter for `grpc_status`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn grpc_status(&self) -> Option<NetGrpcStatus> {
+        self.error.grpc_status().cloned()
     }
 }
 
@@ -60,13 +322,28 @@ pub enum NetResponseStream {
     Data(NetResponseStreamData),
     Close(Option<i32>),
     Error(NetResponseStreamError),
+    Reconnected(NetResponseStreamReconnected),
+    /// Nothing arrived within `NetConfigRequest::stream_idle_timeout`
+    Idle(Option<i32>),
+    /// Last event of a gRPC stream cancelled by unsubscribing, in place of `Close`; the call
+    /// was dropped by then, which reset it so the server stops sending
+    Cancelled(Option<i32>),
+    Rebuild(NetResponseStreamRebuild),
 }
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Clone, Debug)]
 pub struct NetResponseHttp {
     status_code: u16,
-    body: Vec<u8>,
-    headers: Vec<NetHttpHeader>,
+    body: Bytes,
+    headers: NetHttpHeaders,
+    encoding: StreamEncoding,
+    /// Sent after the body, in the final HEADERS frame of HTTP/2 or a chunked trailer
+    trailers: NetHttpHeaders,
+    /// HTTP version the response came in with, e.g. `HTTP/1.1` or `HTTP/2.0`; empty on wasm
+    version: String,
+    /// `ip:port` the connection went to: the server, or the SOCKS5 proxy in front of it.
+    /// `None` over Tor, a Unix socket or a custom stream, and on wasm
+    remote_addr: Option<String>,
 }
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 impl NetResponseHttp {
@@ -76,26 +353,81 @@ impl NetResponseHttp {
         self.status_code
     }
 
-    /// Getter for `body`
+    /// Copy of `body`; `body_ref` and `into_body` avoid it
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
     pub fn body(&self) -> Vec<u8> {
-        self.body.clone()
+        self.body.to_vec()
     }
 
     /// Getter for `headers`
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-    pub fn headers(&self) -> Vec<NetHttpHeader> {
+    pub fn headers(&self) -> NetHttpHeaders {
         self.headers.clone()
     }
+
+    /// Getter for `encoding`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn encoding(&self) -> StreamEncoding {
+        self.encoding
+    }
+
+    /// Getter for `trailers`, empty when the server sent none
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn trailers(&self) -> NetHttpHeaders {
+        self.trailers.clone()
+    }
+
+    /// Getter for `version`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn version(&self) -> String {
+        self.version.clone()
+    }
+
+    /// Getter for `remote_addr`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn remote_addr(&self) -> Option<String> {
+        self.remote_addr.clone()
+    }
 }
 impl NetResponseHttp {
-    pub fn new(status_code: u16, body: Vec<u8>, headers: Vec<NetHttpHeader>) -> NetResponseHttp {
+    pub fn new(
+        status_code: u16,
+        body: Bytes,
+        headers: NetHttpHeaders,
+        encoding: StreamEncoding,
+    ) -> NetResponseHttp {
         Self {
             status_code,
             body,
             headers,
+            encoding,
+            trailers: NetHttpHeaders::new(),
+            version: String::new(),
+            remote_addr: None,
         }
     }
+    pub fn with_trailers(mut self, trailers: NetHttpHeaders) -> Self {
+        self.trailers = trailers;
+        self
+    }
+    /// Version and peer of the connection the response came over
+    pub fn with_connection(mut self, version: String, remote_addr: Option<String>) -> Self {
+        self.version = version;
+        self.remote_addr = remote_addr;
+        self
+    }
+    pub fn headers_ref(&self) -> &NetHttpHeaders {
+        &self.headers
+    }
+    pub fn trailers_ref(&self) -> &NetHttpHeaders {
+        &self.trailers
+    }
+    pub fn body_ref(&self) -> &[u8] {
+        &self.body
+    }
+    pub fn into_body(self) -> Bytes {
+        self.body
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
@@ -126,38 +458,1093 @@ impl NetResponseGrpcUnsubscribe {
     pub fn new(id: i32) -> NetResponseGrpcUnsubscribe {
         Self { id }
     }
-}
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-impl NetResponseGrpcUnsubscribe {
-    /// Getter for `id`
+}
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseGrpcUnsubscribe {
+    /// Getter for `id`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+}
+/// Connection state of a gRPC transport's channel
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetGrpcConnectivity {
+    /// Not connected yet or closed, the next call connects
+    Idle = 0,
+    /// Dialing the server, over Tor building the circuit included
+    Connecting = 1,
+    Ready = 2,
+    /// The last connect failed, the next call dials again
+    Failed = 3,
+}
+impl NetGrpcConnectivity {
+    #[cfg(feature = "grpc")]
+    pub(crate) fn from_u8(value: u8) -> NetGrpcConnectivity {
+        match value {
+            1 => NetGrpcConnectivity::Connecting,
+            2 => NetGrpcConnectivity::Ready,
+            3 => NetGrpcConnectivity::Failed,
+            _ => NetGrpcConnectivity::Idle,
+        }
+    }
+}
+/// Answer of a `State` request
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseGrpcState {
+    state: NetGrpcConnectivity,
+}
+impl NetResponseGrpcState {
+    pub fn new(state: NetGrpcConnectivity) -> NetResponseGrpcState {
+        Self { state }
+    }
+}
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseGrpcState {
+    /// Getter for `state`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn state(&self) -> NetGrpcConnectivity {
+        self.state
+    }
+}
+/// Answer of a health `Check`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseGrpcHealth {
+    status: NetGrpcServingStatus,
+}
+impl NetResponseGrpcHealth {
+    pub fn new(status: NetGrpcServingStatus) -> NetResponseGrpcHealth {
+        Self { status }
+    }
+}
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseGrpcHealth {
+    /// Getter for `status`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn status(&self) -> NetGrpcServingStatus {
+        self.status
+    }
+}
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseGrpcUnary {
+    data: Bytes,
+    /// Headers the server answered with, e.g. rate limits
+    metadata: NetHttpHeaders,
+    /// Trailing metadata sent after the message, e.g. session tokens
+    trailers: NetHttpHeaders,
+}
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseGrpcUnary {
+    /// Copy of `data`, so JS owns its own
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn data(&self) -> Vec<u8> {
+        self.data.to_vec()
+    }
+    /// Getter for `metadata`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn metadata(&self) -> NetHttpHeaders {
+        self.metadata.clone()
+    }
+    /// Getter for `trailers`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn trailers(&self) -> NetHttpHeaders {
+        self.trailers.clone()
+    }
+}
+impl NetResponseGrpcUnary {
+    pub fn new(data: Bytes) -> NetResponseGrpcUnary {
+        Self {
+            data,
+            metadata: NetHttpHeaders::new(),
+            trailers: NetHttpHeaders::new(),
+        }
+    }
+    pub fn with_metadata(mut self, metadata: NetHttpHeaders, trailers: NetHttpHeaders) -> Self {
+        self.metadata = metadata;
+        self.trailers = trailers;
+        self
+    }
+    pub fn with_data(mut self, data: Bytes) -> Self {
+        self.data = data;
+        self
+    }
+    pub fn data_ref(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn metadata_ref(&self) -> &NetHttpHeaders {
+        &self.metadata
+    }
+    pub fn trailers_ref(&self) -> &NetHttpHeaders {
+        &self.trailers
+    }
+    pub fn into_data(self) -> Bytes {
+        self.data
+    }
+}
+
+/// How a ping probed the host: ICMP echo, or UDP to a closed port when ICMP sockets are
+/// not permitted, counting the port-unreachable answer as the reply
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetPingMethod {
+    Icmp = 1,
+    Udp = 2,
+}
+
+/// Ping statistics; the RTT fields are microseconds and 0 when nothing was received
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponsePing {
+    address: String,
+    method: NetPingMethod,
+    sent: u16,
+    received: u16,
+    rtt_min: u32,
+    rtt_avg: u32,
+    rtt_max: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponsePing {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn address(&self) -> String {
+        self.address.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn method(&self) -> NetPingMethod {
+        self.method
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn sent(&self) -> u16 {
+        self.sent
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn received(&self) -> u16 {
+        self.received
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn rtt_min(&self) -> u32 {
+        self.rtt_min
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn rtt_avg(&self) -> u32 {
+        self.rtt_avg
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn rtt_max(&self) -> u32 {
+        self.rtt_max
+    }
+    /// Lost probes in percent
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn loss(&self) -> f64 {
+        match self.sent {
+            0 => 0.0,
+            sent => 100.0 * f64::from(sent - self.received) / f64::from(sent),
+        }
+    }
+}
+
+impl NetResponsePing {
+    /// `rtts` are the round trips of the received probes, in microseconds
+    pub fn new(address: String, method: NetPingMethod, sent: u16, rtts: &[u32]) -> Self {
+        let total: u64 = rtts.iter().map(|rtt| u64::from(*rtt)).sum();
+        Self {
+            address,
+            method,
+            sent,
+            received: rtts.len() as u16,
+            rtt_min: rtts.iter().copied().min().unwrap_or(0),
+            rtt_avg: total.checked_div(rtts.len() as u64).unwrap_or(0) as u32,
+            rtt_max: rtts.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+/// Clock check against an NTP server; `offset` is how far the server clock is ahead of the
+/// local one and `delay` the round trip, both in microseconds
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseNtp {
+    server: String,
+    stratum: u8,
+    offset: i64,
+    delay: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseNtp {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn server(&self) -> String {
+        self.server.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn stratum(&self) -> u8 {
+        self.stratum
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+}
+
+impl NetResponseNtp {
+    pub fn new(server: String, stratum: u8, offset: i64, delay: u32) -> Self {
+        Self {
+            server,
+            stratum,
+            offset,
+            delay,
+        }
+    }
+}
+
+/// Why a port check did not connect
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetPortCheckFailure {
+    None = 0,
+    /// The host answered but nothing listens on the port
+    Refused = 1,
+    Timeout = 2,
+    /// No route to the host or its network
+    Unreachable = 3,
+    /// The host name did not resolve
+    Dns = 4,
+    /// The Tor circuit or exit relay failed
+    Tor = 5,
+    Other = 6,
+}
+
+/// Result of dialing `host:port`; `latency` is the connect time in microseconds, after name
+/// resolution on clearnet
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponsePortCheck {
+    failure: NetPortCheckFailure,
+    latency: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponsePortCheck {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn reachable(&self) -> bool {
+        self.failure == NetPortCheckFailure::None
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn failure(&self) -> NetPortCheckFailure {
+        self.failure
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn latency(&self) -> u32 {
+        self.latency
+    }
+}
+
+impl NetResponsePortCheck {
+    pub fn new(failure: NetPortCheckFailure, latency: u32) -> Self {
+        Self { failure, latency }
+    }
+}
+
+/// Protocol a gateway port mapping forwards
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetPortMappingProtocol {
+    Tcp = 1,
+    Udp = 2,
+}
+
+/// How the gateway was asked for a mapping
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetPortMappingMethod {
+    /// NAT-PMP (RFC 6886), answered by most Apple and many open source routers
+    NatPmp = 1,
+    /// UPnP Internet Gateway Device control over SOAP
+    Upnp = 2,
+}
+
+/// Mapping granted by the gateway. `external_port` may differ from the requested one and
+/// `lifetime` is in seconds: 0 once the mapping was removed, `u32::MAX` when the gateway
+/// only grants permanent mappings
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponsePortMapping {
+    gateway: String,
+    method: NetPortMappingMethod,
+    protocol: NetPortMappingProtocol,
+    internal_port: u16,
+    external_port: u16,
+    external_address: String,
+    lifetime: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponsePortMapping {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn gateway(&self) -> String {
+        self.gateway.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn method(&self) -> NetPortMappingMethod {
+        self.method
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn protocol(&self) -> NetPortMappingProtocol {
+        self.protocol
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn internal_port(&self) -> u16 {
+        self.internal_port
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn external_port(&self) -> u16 {
+        self.external_port
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn external_address(&self) -> String {
+        self.external_address.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn lifetime(&self) -> u32 {
+        self.lifetime
+    }
+}
+
+impl NetResponsePortMapping {
+    pub fn new(
+        gateway: String,
+        method: NetPortMappingMethod,
+        protocol: NetPortMappingProtocol,
+        internal_port: u16,
+        external_port: u16,
+        external_address: String,
+        lifetime: u32,
+    ) -> Self {
+        Self {
+            gateway,
+            method,
+            protocol,
+            internal_port,
+            external_port,
+            external_address,
+            lifetime,
+        }
+    }
+}
+
+/// What a captive portal probe found
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetCaptivePortalState {
+    /// The probe url answered as expected
+    Open = 1,
+    /// Something else answered, usually a login page the user has to pass first
+    Portal = 2,
+    /// No answer, or an error status
+    Blocked = 3,
+}
+
+/// `portal_url` is set for `NetCaptivePortalState::Portal`: the redirect target, else the
+/// probe url, which shows the portal when opened in a browser. `status` is 0 without an
+/// answer
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseCaptivePortal {
+    state: NetCaptivePortalState,
+    portal_url: Option<String>,
+    status: u16,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseCaptivePortal {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn state(&self) -> NetCaptivePortalState {
+        self.state
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn portal_url(&self) -> Option<String> {
+        self.portal_url.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+}
+
+impl NetResponseCaptivePortal {
+    pub fn new(state: NetCaptivePortalState, portal_url: Option<String>, status: u16) -> Self {
+        Self {
+            state,
+            portal_url,
+            status,
+        }
+    }
+}
+
+/// Where a request held by the offline queue stands
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetHttpQueueState {
+    /// The request could not connect and waits for the network
+    Queued = 1,
+    /// Replayed; the answer is in `http`
+    Delivered = 2,
+    /// Replayed but rejected before reaching the server, e.g. by the host policy
+    Failed = 3,
+    /// Dropped unsent when its time to live ran out
+    Expired = 4,
+}
+
+/// Offline queue event for the request sent with `request_id`. `Queued` answers the request
+/// itself, the later states arrive as transport events. `pending` counts the requests still
+/// waiting
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseHttpQueue {
+    request_id: u32,
+    state: NetHttpQueueState,
+    pending: u32,
+    http: Option<NetResponseHttp>,
+    error: Option<String>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseHttpQueue {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn request_id(&self) -> u32 {
+        self.request_id
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn state(&self) -> NetHttpQueueState {
+        self.state
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn pending(&self) -> u32 {
+        self.pending
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn http(&self) -> Option<NetResponseHttp> {
+        self.http.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+impl NetResponseHttpQueue {
+    pub fn new(
+        request_id: u32,
+        state: NetHttpQueueState,
+        pending: u32,
+        http: Option<NetResponseHttp>,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            request_id,
+            state,
+            pending,
+            http,
+            error,
+        }
+    }
+    pub fn http_ref(&self) -> Option<&NetResponseHttp> {
+        self.http.as_ref()
+    }
+    pub fn into_http(self) -> Option<NetResponseHttp> {
+        self.http
+    }
+}
+
+/// Sent once a splice ended because a side closed. `source_bytes` went from the source
+/// transport to the target, `target_bytes` the other way
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseSplice {
+    source_bytes: u64,
+    target_bytes: u64,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseSplice {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn source_bytes(&self) -> u64 {
+        self.source_bytes
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn target_bytes(&self) -> u64 {
+        self.target_bytes
+    }
+}
+
+impl NetResponseSplice {
+    pub fn new(source_bytes: u64, target_bytes: u64) -> Self {
+        Self {
+            source_bytes,
+            target_bytes,
+        }
+    }
+}
+
+/// `resolved` names are cached now; `failed` lists the names neither family resolved for
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseDnsPrefetch {
+    resolved: u32,
+    failed: Vec<String>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseDnsPrefetch {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn resolved(&self) -> u32 {
+        self.resolved
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn failed(&self) -> Vec<String> {
+        self.failed.clone()
+    }
+}
+
+impl NetResponseDnsPrefetch {
+    pub fn new(resolved: u32, failed: Vec<String>) -> Self {
+        Self { resolved, failed }
+    }
+    pub fn failed_ref(&self) -> &[String] {
+        &self.failed
+    }
+}
+
+/// Where a message held by a stream transport's send buffer stands
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetSocketSendState {
+    /// The connection was down; the message waits for the reconnect
+    Queued = 1,
+    /// Written after the reconnect
+    Delivered = 2,
+    /// Not written; the reconnect gave up or the write failed
+    Failed = 3,
+    /// Dropped unsent when its time to live ran out
+    Expired = 4,
+}
+
+/// Send buffer event for the send made with `request_id`. `Queued` answers the send itself,
+/// the later states arrive as transport events. `pending` counts the messages still waiting
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseSocketSend {
+    request_id: u32,
+    state: NetSocketSendState,
+    pending: u32,
+    error: Option<String>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseSocketSend {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn request_id(&self) -> u32 {
+        self.request_id
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn state(&self) -> NetSocketSendState {
+        self.state
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn pending(&self) -> u32 {
+        self.pending
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+impl NetResponseSocketSend {
+    pub fn new(
+        request_id: u32,
+        state: NetSocketSendState,
+        pending: u32,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            request_id,
+            state,
+            pending,
+            error,
+        }
+    }
+}
+
+/// Answer to an HTTP events request: `subscribed` once the server answered with an event
+/// stream, whose events follow as stream data under `id`, and false for an unsubscribe
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseHttpEvents {
+    id: i32,
+    subscribed: bool,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseHttpEvents {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn subscribed(&self) -> bool {
+        self.subscribed
+    }
+}
+
+impl NetResponseHttpEvents {
+    pub fn new(id: i32, subscribed: bool) -> Self {
+        Self { id, subscribed }
+    }
+}
+
+/// Bytes of the request `request_id` written to its download file so far; `total` is the
+/// length the server announced, if any
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseHttpDownload {
+    request_id: u32,
+    received: u64,
+    total: Option<u64>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseHttpDownload {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn request_id(&self) -> u32 {
+        self.request_id
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+}
+
+impl NetResponseHttpDownload {
+    pub fn new(request_id: u32, received: u64, total: Option<u64>) -> Self {
+        Self {
+            request_id,
+            received,
+            total,
+        }
+    }
+}
+
+/// Direction a speed test is measuring
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetSpeedTestPhase {
+    Download = 1,
+    Upload = 2,
+}
+
+/// Bytes moved so far in `phase`; `bitrate` is the throughput since the phase started, in
+/// bits per second
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetSpeedTestProgress {
+    phase: NetSpeedTestPhase,
+    bytes: u64,
+    elapsed_ms: u32,
+    bitrate: u64,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetSpeedTestProgress {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn phase(&self) -> NetSpeedTestPhase {
+        self.phase
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn elapsed_ms(&self) -> u32 {
+        self.elapsed_ms
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn bitrate(&self) -> u64 {
+        self.bitrate
+    }
+}
+
+impl NetSpeedTestProgress {
+    pub fn new(phase: NetSpeedTestPhase, bytes: u64, elapsed_ms: u32) -> Self {
+        Self {
+            phase,
+            bytes,
+            elapsed_ms,
+            bitrate: (bytes * 8 * 1000)
+                .checked_div(elapsed_ms.into())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Outcome of a speed test. The RTT fields are microseconds to the response headers of
+/// small requests on an open connection; fields of a direction that was not measured are 0
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug, Default)]
+pub struct NetSpeedTestResult {
+    rtt_min: u32,
+    rtt_avg: u32,
+    rtt_max: u32,
+    download_bytes: u64,
+    download_bitrate: u64,
+    upload_bytes: u64,
+    upload_bitrate: u64,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetSpeedTestResult {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn rtt_min(&self) -> u32 {
+        self.rtt_min
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn rtt_avg(&self) -> u32 {
+        self.rtt_avg
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn rtt_max(&self) -> u32 {
+        self.rtt_max
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn download_bytes(&self) -> u64 {
+        self.download_bytes
+    }
+    /// Bits per second
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn download_bitrate(&self) -> u64 {
+        self.download_bitrate
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn upload_bytes(&self) -> u64 {
+        self.upload_bytes
+    }
+    /// Bits per second
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn upload_bitrate(&self) -> u64 {
+        self.upload_bitrate
+    }
+}
+
+impl NetSpeedTestResult {
+    /// `rtts` are microseconds
+    pub fn set_rtts(&mut self, rtts: &[u32]) {
+        let total: u64 = rtts.iter().map(|rtt| u64::from(*rtt)).sum();
+        self.rtt_min = rtts.iter().copied().min().unwrap_or(0);
+        self.rtt_avg = total.checked_div(rtts.len() as u64).unwrap_or(0) as u32;
+        self.rtt_max = rtts.iter().copied().max().unwrap_or(0);
+    }
+    pub fn set_phase(&mut self, progress: &NetSpeedTestProgress) {
+        match progress.phase {
+            NetSpeedTestPhase::Download => {
+                self.download_bytes = progress.bytes;
+                self.download_bitrate = progress.bitrate;
+            }
+            NetSpeedTestPhase::Upload => {
+                self.upload_bytes = progress.bytes;
+                self.upload_bitrate = progress.bitrate;
+            }
+        }
+    }
+}
+
+/// Events of a speed test, ended by `Finished` or an error
+pub enum NetResponseSpeedTest {
+    Progress(NetSpeedTestProgress),
+    Finished(NetSpeedTestResult),
+}
+
+/// Public address and port a STUN server saw the request come from
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetStunMapping {
+    server: String,
+    address: String,
+    port: u16,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetStunMapping {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn server(&self) -> String {
+        self.server.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn address(&self) -> String {
+        self.address.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl NetStunMapping {
+    pub fn new(server: String, address: String, port: u16) -> Self {
+        Self {
+            server,
+            address,
+            port,
+        }
+    }
+}
+
+/// Reflexive addresses of one local socket. A mapping equal to the local address means no
+/// NAT; mappings that differ between servers mean the NAT maps per destination (symmetric)
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct NetResponseStun {
+    local_address: String,
+    local_port: u16,
+    mappings: Vec<NetStunMapping>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseStun {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn local_address(&self) -> String {
+        self.local_address.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn mappings(&self) -> Vec<NetStunMapping> {
+        self.mappings.clone()
+    }
+}
+
+impl NetResponseStun {
+    pub fn new(local_address: String, local_port: u16, mappings: Vec<NetStunMapping>) -> Self {
+        Self {
+            local_address,
+            local_port,
+            mappings,
+        }
+    }
+    pub fn mappings_ref(&self) -> &[NetStunMapping] {
+        &self.mappings
+    }
+}
+
+/// Network interface of the device. `addresses` are in CIDR notation, e.g. `192.168.1.5/24`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetInterface {
+    name: String,
+    index: u32,
+    up: bool,
+    loopback: bool,
+    addresses: Vec<String>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetInterface {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn up(&self) -> bool {
+        self.up
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn loopback(&self) -> bool {
+        self.loopback
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn addresses(&self) -> Vec<String> {
+        self.addresses.clone()
+    }
+}
+
+impl NetInterface {
+    pub fn new(name: String, index: u32, up: bool, loopback: bool, addresses: Vec<String>) -> Self {
+        Self {
+            name,
+            index,
+            up,
+            loopback,
+            addresses,
+        }
+    }
+    pub fn name_ref(&self) -> &str {
+        &self.name
+    }
+    pub fn addresses_ref(&self) -> &[String] {
+        &self.addresses
+    }
+    pub(crate) fn addresses_mut(&mut self) -> &mut Vec<String> {
+        &mut self.addresses
+    }
+}
+
+/// Interfaces of the device and the source addresses of its default IPv4 and IPv6 routes.
+/// `default_interface` is the interface holding the default IPv4 (else IPv6) address
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetResponseInterfaces {
+    interfaces: Vec<NetInterface>,
+    default_interface: Option<String>,
+    default_ipv4: Option<String>,
+    default_ipv6: Option<String>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NetResponseInterfaces {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn interfaces(&self) -> Vec<NetInterface> {
+        self.interfaces.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn default_interface(&self) -> Option<String> {
+        self.default_interface.clone()
+    }
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-    pub fn id(&self) -> i32 {
-        self.id
+    pub fn default_ipv4(&self) -> Option<String> {
+        self.default_ipv4.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn default_ipv6(&self) -> Option<String> {
+        self.default_ipv6.clone()
+    }
+}
+
+impl NetResponseInterfaces {
+    pub fn new(
+        interfaces: Vec<NetInterface>,
+        default_interface: Option<String>,
+        default_ipv4: Option<String>,
+        default_ipv6: Option<String>,
+    ) -> Self {
+        Self {
+            interfaces,
+            default_interface,
+            default_ipv4,
+            default_ipv6,
+        }
+    }
+    pub fn interfaces_ref(&self) -> &[NetInterface] {
+        &self.interfaces
     }
 }
+
+/// Service instance found on the local network. `addresses` are the host's A/AAAA records
+/// and `txt` its TXT entries, usually `key=value`
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Clone, Debug)]
-pub struct NetResponseGrpcUnary {
-    data: Vec<u8>,
+pub struct NetMdnsService {
+    instance: String,
+    service: String,
+    host: String,
+    port: u16,
+    addresses: Vec<String>,
+    txt: Vec<String>,
 }
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-impl NetResponseGrpcUnary {
-    /// Getter for `data`
+impl NetMdnsService {
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-    pub fn data(&self) -> Vec<u8> {
-        self.data.clone() // clone so JS owns its own copy
+    pub fn instance(&self) -> String {
+        self.instance.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn service(&self) -> String {
+        self.service.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn host(&self) -> String {
+        self.host.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn addresses(&self) -> Vec<String> {
+        self.addresses.clone()
+    }
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn txt(&self) -> Vec<String> {
+        self.txt.clone()
     }
 }
-impl NetResponseGrpcUnary {
-    pub fn new(data: Vec<u8>) -> NetResponseGrpcUnary {
-        Self { data }
+
+impl NetMdnsService {
+    pub fn new(
+        instance: String,
+        service: String,
+        host: String,
+        port: u16,
+        addresses: Vec<String>,
+        txt: Vec<String>,
+    ) -> Self {
+        Self {
+            instance,
+            service,
+            host,
+            port,
+            addresses,
+            txt,
+        }
+    }
+    pub fn addresses_ref(&self) -> &[String] {
+        &self.addresses
+    }
+    pub fn txt_ref(&self) -> &[String] {
+        &self.txt
     }
 }
 
+/// Events of an mDNS browse or resolve, ended by `Finished` or an error
+pub enum NetResponseMdns {
+    Found(NetMdnsService),
+    Finished,
+}
+
 pub enum NetResponseGrpc {
     Unary(NetResponseGrpcUnary),
     StreamId(NetResponseGrpcSubscribe),
     Unsubscribe(NetResponseGrpcUnsubscribe),
+    Health(NetResponseGrpcHealth),
+    State(NetResponseGrpcState),
+    Stats(NetGrpcStats),
 }
 
 pub struct NetResponse {
@@ -168,12 +1555,35 @@ pub struct NetResponse {
 
 pub enum NetResponseKind {
     Socket(NetResponseSocketOk),
+    SocketBuffer(NetResponseSocketBuffer),
     Grpc(NetResponseGrpc),
     Http(NetResponseHttp),
     Stream(NetResponseStream),
-    ResponseError(NetResultStatus),
+    ResponseError(NetError),
     TransportClosed,
+    /// Answer to `dart_transporter_connect`, under request id 0
+    TransportConnected,
     TorInited(bool),
+    EndpointChanged(NetResponseEndpoint),
+    Dns(NetResponseDns),
+    Mdns(NetResponseMdns),
+    Ping(NetResponsePing),
+    Ntp(NetResponseNtp),
+    Stun(NetResponseStun),
+    PortCheck(NetResponsePortCheck),
+    Interfaces(NetResponseInterfaces),
+    /// the default route or an address changed, sent to `WatchInterfaces` requests
+    NetworkChanged(NetResponseInterfaces),
+    PortMapping(NetResponsePortMapping),
+    SpeedTest(NetResponseSpeedTest),
+    CaptivePortal(NetResponseCaptivePortal),
+    HttpQueue(NetResponseHttpQueue),
+    Splice(NetResponseSplice),
+    DnsPrefetch(NetResponseDnsPrefetch),
+    SocketSend(NetResponseSocketSend),
+    HttpEvents(NetResponseHttpEvents),
+    /// progress of a request with a `download_path`
+    HttpDownload(NetResponseHttpDownload),
 }
 impl NetResponseKind {
     pub fn grpc_unary(&self) -> Option<NetResponseGrpcUnary> {
@@ -198,6 +1608,24 @@ impl NetResponseKind {
             _ => None,
         }
     }
+    pub fn grpc_health(&self) -> Option<NetResponseGrpcHealth> {
+        match self {
+            NetResponseKind::Grpc(NetResponseGrpc::Health(health)) => Some(health.clone()),
+            _ => None,
+        }
+    }
+    pub fn grpc_state(&self) -> Option<NetResponseGrpcState> {
+        match self {
+            NetResponseKind::Grpc(NetResponseGrpc::State(state)) => Some(state.clone()),
+            _ => None,
+        }
+    }
+    pub fn grpc_stats(&self) -> Option<NetGrpcStats> {
+        match self {
+            NetResponseKind::Grpc(NetResponseGrpc::Stats(stats)) => Some(stats.clone()),
+            _ => None,
+        }
+    }
     pub fn grpc_unsubscribe(&self) -> Option<NetResponseGrpcUnsubscribe> {
         match self {
             NetResponseKind::Grpc(net_grpc_response) => match net_grpc_response {
@@ -240,15 +1668,160 @@ impl NetResponseKind {
             _ => None,
         }
     }
+    pub fn stream_idle(&self) -> Option<i32> {
+        match self {
+            NetResponseKind::Stream(NetResponseStream::Idle(id)) => Some(id.map_or(-1, |f| f)),
+            _ => None,
+        }
+    }
+    pub fn stream_cancelled(&self) -> Option<i32> {
+        match self {
+            NetResponseKind::Stream(NetResponseStream::Cancelled(id)) => Some(id.map_or(-1, |f| f)),
+            _ => None,
+        }
+    }
+    pub fn stream_reconnected(&self) -> Option<NetResponseStreamReconnected> {
+        match self {
+            NetResponseKind::Stream(NetResponseStream::Reconnected(reconnected)) => {
+                Some(reconnected.clone())
+            }
+            _ => None,
+        }
+    }
+    pub fn stream_rebuild(&self) -> Option<NetResponseStreamRebuild> {
+        match self {
+            NetResponseKind::Stream(NetResponseStream::Rebuild(rebuild)) => Some(rebuild.clone()),
+            _ => None,
+        }
+    }
     pub fn http(&self) -> Option<NetResponseHttp> {
         match self {
             NetResponseKind::Http(net_http_response) => Some(net_http_response.clone()),
             _ => None,
         }
     }
-    pub fn error(&self) -> Option<NetResultStatus> {
+    pub fn socket_buffer(&self) -> Option<NetResponseSocketBuffer> {
+        match self {
+            NetResponseKind::SocketBuffer(net_socket_buffer) => Some(net_socket_buffer.clone()),
+            _ => None,
+        }
+    }
+    pub fn error(&self) -> Option<NetError> {
+        match self {
+            NetResponseKind::ResponseError(net_error) => Some(net_error.clone()),
+            _ => None,
+        }
+    }
+    pub fn endpoint(&self) -> Option<NetResponseEndpoint> {
+        match self {
+            NetResponseKind::EndpointChanged(endpoint) => Some(endpoint.clone()),
+            _ => None,
+        }
+    }
+    pub fn dns(&self) -> Option<NetResponseDns> {
+        match self {
+            NetResponseKind::Dns(dns) => Some(dns.clone()),
+            _ => None,
+        }
+    }
+    pub fn mdns_service(&self) -> Option<NetMdnsService> {
+        match self {
+            NetResponseKind::Mdns(NetResponseMdns::Found(service)) => Some(service.clone()),
+            _ => None,
+        }
+    }
+    pub fn ping(&self) -> Option<NetResponsePing> {
+        match self {
+            NetResponseKind::Ping(ping) => Some(ping.clone()),
+            _ => None,
+        }
+    }
+    pub fn ntp(&self) -> Option<NetResponseNtp> {
+        match self {
+            NetResponseKind::Ntp(ntp) => Some(ntp.clone()),
+            _ => None,
+        }
+    }
+    pub fn stun(&self) -> Option<NetResponseStun> {
+        match self {
+            NetResponseKind::Stun(stun) => Some(stun.clone()),
+            _ => None,
+        }
+    }
+    pub fn port_check(&self) -> Option<NetResponsePortCheck> {
+        match self {
+            NetResponseKind::PortCheck(check) => Some(check.clone()),
+            _ => None,
+        }
+    }
+    pub fn speed_test_progress(&self) -> Option<NetSpeedTestProgress> {
+        match self {
+            NetResponseKind::SpeedTest(NetResponseSpeedTest::Progress(progress)) => {
+                Some(progress.clone())
+            }
+            _ => None,
+        }
+    }
+    pub fn speed_test_result(&self) -> Option<NetSpeedTestResult> {
+        match self {
+            NetResponseKind::SpeedTest(NetResponseSpeedTest::Finished(result)) => {
+                Some(result.clone())
+            }
+            _ => None,
+        }
+    }
+    pub fn port_mapping(&self) -> Option<NetResponsePortMapping> {
+        match self {
+            NetResponseKind::PortMapping(mapping) => Some(mapping.clone()),
+            _ => None,
+        }
+    }
+    pub fn interfaces(&self) -> Option<NetResponseInterfaces> {
+        match self {
+            NetResponseKind::Interfaces(interfaces)
+            | NetResponseKind::NetworkChanged(interfaces) => Some(interfaces.clone()),
+            _ => None,
+        }
+    }
+    pub fn captive_portal(&self) -> Option<NetResponseCaptivePortal> {
+        match self {
+            NetResponseKind::CaptivePortal(probe) => Some(probe.clone()),
+            _ => None,
+        }
+    }
+    pub fn http_queue(&self) -> Option<NetResponseHttpQueue> {
+        match self {
+            NetResponseKind::HttpQueue(queue) => Some(queue.clone()),
+            _ => None,
+        }
+    }
+    pub fn splice(&self) -> Option<NetResponseSplice> {
+        match self {
+            NetResponseKind::Splice(splice) => Some(splice.clone()),
+            _ => None,
+        }
+    }
+    pub fn dns_prefetch(&self) -> Option<NetResponseDnsPrefetch> {
+        match self {
+            NetResponseKind::DnsPrefetch(prefetch) => Some(prefetch.clone()),
+            _ => None,
+        }
+    }
+    pub fn socket_send(&self) -> Option<NetResponseSocketSend> {
+        match self {
+            NetResponseKind::SocketSend(send) => Some(send.clone()),
+            _ => None,
+        }
+    }
+    pub fn http_events(&self) -> Option<NetResponseHttpEvents> {
+        match self {
+            NetResponseKind::HttpEvents(events) => Some(events.clone()),
+            _ => None,
+        }
+    }
+    pub fn http_download(&self) -> Option<NetResponseHttpDownload> {
         match self {
-            NetResponseKind::ResponseError(net_result_status) => Some(net_result_status.clone()),
+            NetResponseKind::HttpDownload(download) => Some(download.clone()),
             _ => None,
         }
     }
@@ -262,11 +1835,39 @@ pub struct NetResponseWasm {
     grpc_unary: Option<NetResponseGrpcUnary>,
     grpc_stream: Option<NetResponseGrpcSubscribe>,
     grpc_unsubscribe: Option<NetResponseGrpcUnsubscribe>,
+    grpc_health: Option<NetResponseGrpcHealth>,
+    grpc_state: Option<NetResponseGrpcState>,
+    grpc_stats: Option<NetGrpcStats>,
     http: Option<NetResponseHttp>,
     stream_data: Option<NetResponseStreamData>,
     stream_close: Option<i32>,
     stream_error: Option<NetResponseStreamError>,
+    stream_reconnected: Option<NetResponseStreamReconnected>,
+    stream_idle: Option<i32>,
+    stream_cancelled: Option<i32>,
+    stream_rebuild: Option<NetResponseStreamRebuild>,
     response_error: Option<NetResultStatus>,
+    response_error_message: Option<String>,
+    response_grpc_status: Option<NetGrpcStatus>,
+    socket_buffer: Option<NetResponseSocketBuffer>,
+    endpoint: Option<NetResponseEndpoint>,
+    dns: Option<NetResponseDns>,
+    mdns_service: Option<NetMdnsService>,
+    ping: Option<NetResponsePing>,
+    ntp: Option<NetResponseNtp>,
+    stun: Option<NetResponseStun>,
+    port_check: Option<NetResponsePortCheck>,
+    interfaces: Option<NetResponseInterfaces>,
+    port_mapping: Option<NetResponsePortMapping>,
+    speed_test_progress: Option<NetSpeedTestProgress>,
+    speed_test_result: Option<NetSpeedTestResult>,
+    captive_portal: Option<NetResponseCaptivePortal>,
+    http_queue: Option<NetResponseHttpQueue>,
+    splice: Option<NetResponseSplice>,
+    dns_prefetch: Option<NetResponseDnsPrefetch>,
+    socket_send: Option<NetResponseSocketSend>,
+    http_events: Option<NetResponseHttpEvents>,
+    http_download: Option<NetResponseHttpDownload>,
 }
 impl NetResponseWasm {
     pub fn from_native(reseponse: NetResponse) -> NetResponseWasm {
@@ -279,25 +1880,89 @@ impl NetResponseWasm {
                     NetResponseGrpc::Unary(_) => 2,
                     NetResponseGrpc::StreamId(_) => 3,
                     NetResponseGrpc::Unsubscribe(_) => 4,
+                    NetResponseGrpc::Health(_) => 36,
+                    NetResponseGrpc::State(_) => 38,
+                    NetResponseGrpc::Stats(_) => 39,
                 },
                 NetResponseKind::Http(_) => 5,
                 NetResponseKind::Stream(net_stream_response) => match net_stream_response {
                     NetResponseStream::Data(_) => 6,
                     NetResponseStream::Close(_) => 7,
                     NetResponseStream::Error(_) => 8,
+                    NetResponseStream::Reconnected(_) => 31,
+                    NetResponseStream::Idle(_) => 33,
+                    NetResponseStream::Cancelled(_) => 37,
+                    NetResponseStream::Rebuild(_) => 40,
                 },
                 NetResponseKind::ResponseError(_) => 9,
                 NetResponseKind::TransportClosed => 10,
                 NetResponseKind::TorInited(_) => 11,
+                NetResponseKind::SocketBuffer(_) => 12,
+                NetResponseKind::EndpointChanged(_) => 13,
+                NetResponseKind::Dns(_) => 14,
+                NetResponseKind::Mdns(mdns) => match mdns {
+                    NetResponseMdns::Found(_) => 15,
+                    NetResponseMdns::Finished => 16,
+                },
+                NetResponseKind::Ping(_) => 17,
+                NetResponseKind::Ntp(_) => 18,
+                NetResponseKind::Stun(_) => 19,
+                NetResponseKind::PortCheck(_) => 20,
+                NetResponseKind::Interfaces(_) => 21,
+                NetResponseKind::NetworkChanged(_) => 22,
+                NetResponseKind::PortMapping(_) => 23,
+                NetResponseKind::SpeedTest(speed_test) => match speed_test {
+                    NetResponseSpeedTest::Progress(_) => 24,
+                    NetResponseSpeedTest::Finished(_) => 25,
+                },
+                NetResponseKind::CaptivePortal(_) => 26,
+                NetResponseKind::HttpQueue(_) => 27,
+                NetResponseKind::Splice(_) => 28,
+                NetResponseKind::DnsPrefetch(_) => 29,
+                NetResponseKind::TransportConnected => 30,
+                NetResponseKind::SocketSend(_) => 32,
+                NetResponseKind::HttpEvents(_) => 34,
+                NetResponseKind::HttpDownload(_) => 35,
             },
             grpc_unary: reseponse.response.grpc_unary(),
             grpc_stream: reseponse.response.grpc_stream_id(),
             grpc_unsubscribe: reseponse.response.grpc_unsubscribe(),
+            grpc_health: reseponse.response.grpc_health(),
+            grpc_state: reseponse.response.grpc_state(),
+            grpc_stats: reseponse.response.grpc_stats(),
             http: reseponse.response.http(),
             stream_data: reseponse.response.stream_data(),
             stream_close: reseponse.response.stream_close(),
             stream_error: reseponse.response.stream_error(),
-            response_error: reseponse.response.error(),
+            stream_reconnected: reseponse.response.stream_reconnected(),
+            stream_idle: reseponse.response.stream_idle(),
+            stream_cancelled: reseponse.response.stream_cancelled(),
+            stream_rebuild: reseponse.response.stream_rebuild(),
+            response_error: reseponse.response.error().map(|e| e.status()),
+            response_error_message: reseponse.response.error().map(|e| e.to_string()),
+            response_grpc_status: reseponse
+                .response
+                .error()
+                .and_then(|e| e.grpc_status().cloned()),
+            socket_buffer: reseponse.response.socket_buffer(),
+            endpoint: reseponse.response.endpoint(),
+            dns: reseponse.response.dns(),
+            mdns_service: reseponse.response.mdns_service(),
+            ping: reseponse.response.ping(),
+            ntp: reseponse.response.ntp(),
+            stun: reseponse.response.stun(),
+            port_check: reseponse.response.port_check(),
+            interfaces: reseponse.response.interfaces(),
+            port_mapping: reseponse.response.port_mapping(),
+            speed_test_progress: reseponse.response.speed_test_progress(),
+            speed_test_result: reseponse.response.speed_test_result(),
+            captive_portal: reseponse.response.captive_portal(),
+            http_queue: reseponse.response.http_queue(),
+            splice: reseponse.response.splice(),
+            dns_prefetch: reseponse.response.dns_prefetch(),
+            socket_send: reseponse.response.socket_send(),
+            http_events: reseponse.response.http_events(),
+            http_download: reseponse.response.http_download(),
         }
     }
 }
@@ -333,6 +1998,21 @@ impl NetResponseWasm {
         self.grpc_unsubscribe.clone()
     }
 
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn grpc_health(&self) -> Option<NetResponseGrpcHealth> {
+        self.grpc_health.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn grpc_state(&self) -> Option<NetResponseGrpcState> {
+        self.grpc_state.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn grpc_stats(&self) -> Option<NetGrpcStats> {
+        self.grpc_stats.clone()
+    }
+
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
     pub fn http(&self) -> Option<NetResponseHttp> {
         self.http.clone()
@@ -353,10 +2033,135 @@ impl NetResponseWasm {
         self.stream_error.clone()
     }
 
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn stream_reconnected(&self) -> Option<NetResponseStreamReconnected> {
+        self.stream_reconnected.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn stream_idle(&self) -> Option<i32> {
+        self.stream_idle
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn stream_cancelled(&self) -> Option<i32> {
+        self.stream_cancelled
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn stream_rebuild(&self) -> Option<NetResponseStreamRebuild> {
+        self.stream_rebuild.clone()
+    }
+
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
     pub fn response_error(&self) -> Option<NetResultStatus> {
         self.response_error.clone()
     }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn response_error_message(&self) -> Option<String> {
+        self.response_error_message.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn response_grpc_status(&self) -> Option<NetGrpcStatus> {
+        self.response_grpc_status.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn socket_buffer(&self) -> Option<NetResponseSocketBuffer> {
+        self.socket_buffer.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn endpoint(&self) -> Option<NetResponseEndpoint> {
+        self.endpoint.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn dns(&self) -> Option<NetResponseDns> {
+        self.dns.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn mdns_service(&self) -> Option<NetMdnsService> {
+        self.mdns_service.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn ping(&self) -> Option<NetResponsePing> {
+        self.ping.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn ntp(&self) -> Option<NetResponseNtp> {
+        self.ntp.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn stun(&self) -> Option<NetResponseStun> {
+        self.stun.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn port_check(&self) -> Option<NetResponsePortCheck> {
+        self.port_check.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn interfaces(&self) -> Option<NetResponseInterfaces> {
+        self.interfaces.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn port_mapping(&self) -> Option<NetResponsePortMapping> {
+        self.port_mapping.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn speed_test_progress(&self) -> Option<NetSpeedTestProgress> {
+        self.speed_test_progress.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn speed_test_result(&self) -> Option<NetSpeedTestResult> {
+        self.speed_test_result.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn captive_portal(&self) -> Option<NetResponseCaptivePortal> {
+        self.captive_portal.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn http_queue(&self) -> Option<NetResponseHttpQueue> {
+        self.http_queue.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn splice(&self) -> Option<NetResponseSplice> {
+        self.splice.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn dns_prefetch(&self) -> Option<NetResponseDnsPrefetch> {
+        self.dns_prefetch.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn socket_send(&self) -> Option<NetResponseSocketSend> {
+        self.socket_send.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn http_events(&self) -> Option<NetResponseHttpEvents> {
+        self.http_events.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn http_download(&self) -> Option<NetResponseHttpDownload> {
+        self.http_download.clone()
+    }
 }
 use std::fmt;
 
@@ -368,7 +2173,7 @@ impl fmt::Debug for NetResponseStream {
                     f,
                     "NetResponseStream::Data {{ id: {:?}, len: {} }}",
                     d.id(),
-                    d.data().len()
+                    d.data_ref().len()
                 )
             }
             NetResponseStream::Close(id) => {
@@ -377,6 +2182,22 @@ impl fmt::Debug for NetResponseStream {
             NetResponseStream::Error(e) => {
                 write!(f, "NetResponseStream::Error {{ id: {:?} }}", e.id())
             }
+            NetResponseStream::Reconnected(r) => write!(
+                f,
+                "NetResponseStream::Reconnected {{ id: {:?}, flushed: {} }}",
+                r.id(),
+                r.flushed()
+            ),
+            NetResponseStream::Idle(id) => write!(f, "NetResponseStream::Idle {{ id: {:?} }}", id),
+            NetResponseStream::Cancelled(id) => {
+                write!(f, "NetResponseStream::Cancelled {{ id: {:?} }}", id)
+            }
+            NetResponseStream::Rebuild(r) => write!(
+                f,
+                "NetResponseStream::Rebuild {{ id: {:?}, last: {:?} }}",
+                r.id(),
+                r.last.as_ref().map(|last| last.len())
+            ),
         }
     }
 }
@@ -390,6 +2211,13 @@ impl fmt::Debug for NetResponseGrpc {
             NetResponseGrpc::Unsubscribe(u) => {
                 write!(f, "NetResponseGrpc::Unsubscribe {{ id: {} }}", u.id())
             }
+            NetResponseGrpc::Health(h) => {
+                write!(f, "NetResponseGrpc::Health {{ status: {:?} }}", h.status())
+            }
+            NetResponseGrpc::State(s) => {
+                write!(f, "NetResponseGrpc::State {{ state: {:?} }}", s.state())
+            }
+            NetResponseGrpc::Stats(s) => write!(f, "NetResponseGrpc::Stats {{ {:?} }}", s),
         }
     }
 }
@@ -398,6 +2226,12 @@ impl fmt::Debug for NetResponseKind {
         match self {
             NetResponseKind::Socket(_) => write!(f, "NetResponseKind::Socket"),
 
+            NetResponseKind::SocketBuffer(b) => write!(
+                f,
+                "NetResponseKind::SocketBuffer {{ buffered: {} }}",
+                b.buffered()
+            ),
+
             NetResponseKind::Grpc(g) => write!(f, "NetResponseKind::{:?}", g),
 
             NetResponseKind::Http(h) => {
@@ -406,13 +2240,150 @@ impl fmt::Debug for NetResponseKind {
 
             NetResponseKind::Stream(s) => write!(f, "NetResponseKind::{:?}", s),
 
-            NetResponseKind::ResponseError(_) => write!(f, "NetResponseKind::ResponseError"),
+            NetResponseKind::ResponseError(e) => {
+                write!(f, "NetResponseKind::ResponseError {{ error: {} }}", e)
+            }
 
             NetResponseKind::TransportClosed => write!(f, "NetResponseKind::TransportClosed"),
+            NetResponseKind::TransportConnected => write!(f, "NetResponseKind::TransportConnected"),
 
             NetResponseKind::TorInited(ok) => {
                 write!(f, "NetResponseKind::TorInited {{ success: {} }}", ok)
             }
+
+            NetResponseKind::EndpointChanged(e) => write!(
+                f,
+                "NetResponseKind::EndpointChanged {{ index: {} }}",
+                e.index()
+            ),
+
+            NetResponseKind::Dns(d) => write!(
+                f,
+                "NetResponseKind::Dns {{ records: {} }}",
+                d.records_ref().len()
+            ),
+
+            NetResponseKind::Mdns(NetResponseMdns::Found(service)) => write!(
+                f,
+                "NetResponseKind::Mdns::Found {{ instance: {} }}",
+                service.instance()
+            ),
+
+            NetResponseKind::Mdns(NetResponseMdns::Finished) => {
+                write!(f, "NetResponseKind::Mdns::Finished")
+            }
+
+            NetResponseKind::Ping(p) => write!(
+                f,
+                "NetResponseKind::Ping {{ sent: {}, received: {} }}",
+                p.sent(),
+                p.received()
+            ),
+
+            NetResponseKind::Ntp(n) => write!(
+                f,
+                "NetResponseKind::Ntp {{ server: {}, offset: {} }}",
+                n.server(),
+                n.offset()
+            ),
+
+            NetResponseKind::Stun(s) => write!(
+                f,
+                "NetResponseKind::Stun {{ mappings: {} }}",
+                s.mappings_ref().len()
+            ),
+
+            NetResponseKind::PortCheck(c) => write!(
+                f,
+                "NetResponseKind::PortCheck {{ failure: {:?}, latency: {} }}",
+                c.failure(),
+                c.latency()
+            ),
+
+            NetResponseKind::Interfaces(i) => write!(
+                f,
+                "NetResponseKind::Interfaces {{ interfaces: {} }}",
+                i.interfaces_ref().len()
+            ),
+
+            NetResponseKind::NetworkChanged(i) => write!(
+                f,
+                "NetResponseKind::NetworkChanged {{ default_interface: {:?} }}",
+                i.default_interface
+            ),
+
+            NetResponseKind::PortMapping(m) => write!(
+                f,
+                "NetResponseKind::PortMapping {{ method: {:?}, external_port: {}, lifetime: {} }}",
+                m.method(),
+                m.external_port(),
+                m.lifetime()
+            ),
+
+            NetResponseKind::SpeedTest(NetResponseSpeedTest::Progress(p)) => write!(
+                f,
+                "NetResponseKind::SpeedTest::Progress {{ phase: {:?}, bytes: {} }}",
+                p.phase(),
+                p.bytes()
+            ),
+
+            NetResponseKind::SpeedTest(NetResponseSpeedTest::Finished(r)) => write!(
+                f,
+                "NetResponseKind::SpeedTest::Finished {{ download: {}, upload: {} }}",
+                r.download_bitrate(),
+                r.upload_bitrate()
+            ),
+
+            NetResponseKind::CaptivePortal(c) => write!(
+                f,
+                "NetResponseKind::CaptivePortal {{ state: {:?}, status: {} }}",
+                c.state(),
+                c.status()
+            ),
+
+            NetResponseKind::HttpQueue(q) => write!(
+                f,
+                "NetResponseKind::HttpQueue {{ request_id: {}, state: {:?}, pending: {} }}",
+                q.request_id(),
+                q.state(),
+                q.pending()
+            ),
+
+            NetResponseKind::Splice(s) => write!(
+                f,
+                "NetResponseKind::Splice {{ source_bytes: {}, target_bytes: {} }}",
+                s.source_bytes(),
+                s.target_bytes()
+            ),
+
+            NetResponseKind::DnsPrefetch(p) => write!(
+                f,
+                "NetResponseKind::DnsPrefetch {{ resolved: {}, failed: {:?} }}",
+                p.resolved(),
+                p.failed_ref()
+            ),
+
+            NetResponseKind::SocketSend(s) => write!(
+                f,
+                "NetResponseKind::SocketSend {{ request_id: {}, state: {:?}, pending: {} }}",
+                s.request_id(),
+                s.state(),
+                s.pending()
+            ),
+
+            NetResponseKind::HttpEvents(e) => write!(
+                f,
+                "NetResponseKind::HttpEvents {{ id: {}, subscribed: {} }}",
+                e.id(),
+                e.subscribed()
+            ),
+            NetResponseKind::HttpDownload(d) => write!(
+                f,
+                "NetResponseKind::HttpDownload {{ request_id: {}, received: {}, total: {:?} }}",
+                d.request_id(),
+                d.received(),
+                d.total()
+            ),
         }
     }
 }
@@ -1,12 +1,48 @@
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum StreamEncoding {
     Json = 1,
     Raw = 2,
+    CborJson = 3,
+    /// Pick the encoding from the response `Content-Type` (HTTP only)
+    #[default]
+    Auto = 4,
+}
+
+impl StreamEncoding {
+    /// Map a `Content-Type` value to the matching encoding, falling back to raw bytes
+    pub fn from_content_type(content_type: Option<&str>) -> StreamEncoding {
+        let mime = match content_type {
+            Some(value) => value
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase(),
+            None => return StreamEncoding::Raw,
+        };
+        if mime == "application/json" || mime.ends_with("+json") {
+            StreamEncoding::Json
+        } else if mime == "application/cbor" || mime.ends_with("+cbor") {
+            StreamEncoding::CborJson
+        } else {
+            StreamEncoding::Raw
+        }
+    }
+
+    /// Resolve `Auto` against the response `Content-Type`; other values are returned as-is
+    pub fn resolve(self, content_type: Option<&str>) -> StreamEncoding {
+        match self {
+            StreamEncoding::Auto => StreamEncoding::from_content_type(content_type),
+            encoding => encoding,
+        }
+    }
 }
 
 pub struct StreamBuffer {
@@ -45,10 +81,22 @@ impl StreamBuffer {
         None
     }
 
+    /// Number of bytes held back waiting for the rest of a message
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Drop any partially received bytes. Returns how many bytes were discarded
+    pub fn flush(&mut self) -> usize {
+        let len = self.buffer.len();
+        self.buffer.clear();
+        len
+    }
+
     /// Add bytes according to encoding
     pub fn add(&mut self, buf: Vec<u8>) -> Option<Vec<u8>> {
         match self.encoding {
-            StreamEncoding::Raw => Some(buf),
+            StreamEncoding::Raw | StreamEncoding::CborJson | StreamEncoding::Auto => Some(buf),
 
             StreamEncoding::Json => self.is_json(buf),
         }
@@ -58,7 +106,8 @@ impl StreamBuffer {
     /// Returns (bytes, encoding actually detected)
     pub fn try_current_buffer(buf: Vec<u8>, encoding: StreamEncoding) -> (Vec<u8>, StreamEncoding) {
         match encoding {
-            StreamEncoding::Raw => (buf, StreamEncoding::Raw),
+            StreamEncoding::Raw | StreamEncoding::Auto => (buf, StreamEncoding::Raw),
+            StreamEncoding::CborJson => (buf, StreamEncoding::CborJson),
 
             StreamEncoding::Json => {
                 if let Ok(s) = std::str::from_utf8(&buf) {
@@ -0,0 +1,68 @@
+/// Text charsets that can be transcoded to UTF-8 before a body is handed to Dart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextCharset {
+    Utf8,
+    Latin1,
+    Utf16,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl TextCharset {
+    /// Read the `charset=` parameter of a `Content-Type` value
+    pub fn from_content_type(content_type: Option<&str>) -> Option<TextCharset> {
+        let content_type = content_type?;
+        let charset = content_type.split(';').skip(1).find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            if key.trim().eq_ignore_ascii_case("charset") {
+                Some(value.trim().trim_matches('"').to_ascii_lowercase())
+            } else {
+                None
+            }
+        })?;
+        match charset.as_str() {
+            "utf-8" | "utf8" | "us-ascii" | "ascii" => Some(TextCharset::Utf8),
+            "iso-8859-1" | "iso8859-1" | "latin1" | "l1" => Some(TextCharset::Latin1),
+            "utf-16" | "utf16" => Some(TextCharset::Utf16),
+            "utf-16le" => Some(TextCharset::Utf16Le),
+            "utf-16be" => Some(TextCharset::Utf16Be),
+            _ => None,
+        }
+    }
+
+    /// Transcode `body` to UTF-8. Bodies that are not valid in the declared charset are returned unchanged
+    pub fn to_utf8(self, body: Vec<u8>) -> Vec<u8> {
+        match self {
+            TextCharset::Utf8 => body,
+            TextCharset::Latin1 => body
+                .iter()
+                .map(|b| *b as char)
+                .collect::<String>()
+                .into_bytes(),
+            TextCharset::Utf16 => match body.as_slice() {
+                [0xFF, 0xFE, rest @ ..] => Self::decode_utf16(rest, false).unwrap_or(body),
+                [0xFE, 0xFF, rest @ ..] => Self::decode_utf16(rest, true).unwrap_or(body),
+                // RFC 2781: no BOM means big endian
+                rest => Self::decode_utf16(rest, true).unwrap_or(body),
+            },
+            TextCharset::Utf16Le => Self::decode_utf16(&body, false).unwrap_or(body),
+            TextCharset::Utf16Be => Self::decode_utf16(&body, true).unwrap_or(body),
+        }
+    }
+
+    fn decode_utf16(bytes: &[u8], big_endian: bool) -> Option<Vec<u8>> {
+        if !bytes.len().is_multiple_of(2) {
+            return None;
+        }
+        let units = bytes.chunks_exact(2).map(|c| {
+            if big_endian {
+                u16::from_be_bytes([c[0], c[1]])
+            } else {
+                u16::from_le_bytes([c[0], c[1]])
+            }
+        });
+        String::from_utf16(&units.collect::<Vec<u16>>())
+            .ok()
+            .map(String::into_bytes)
+    }
+}
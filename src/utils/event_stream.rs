@@ -0,0 +1,120 @@
+use serde::Serialize;
+
+/// One server-sent event, posted to Dart as its JSON in stream data
+#[derive(Clone, Debug, Serialize)]
+pub struct NetHttpEvent {
+    /// `message` when the server named none
+    pub event: String,
+    pub data: String,
+    /// Last event id of the stream so far, `None` until the server set one
+    pub id: Option<String>,
+}
+
+/// Incremental `text/event-stream` parser, fed the body as it arrives. Follows the
+/// WHATWG rules: lines end with CR, LF or CRLF, comments and unknown fields are skipped,
+/// and an event without data is not dispatched.
+#[derive(Default)]
+pub struct EventStreamParser {
+    line: Vec<u8>,
+    /// The last line ended with CR, a LF right after belongs to it
+    after_cr: bool,
+    started: bool,
+    event: String,
+    data: String,
+    has_data: bool,
+    last_event_id: Option<String>,
+    retry: Option<u32>,
+}
+
+impl EventStreamParser {
+    /// Events completed by `chunk`; a line cut at the end waits for the next one
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<NetHttpEvent> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            match byte {
+                b'\n' if self.after_cr => self.after_cr = false,
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    events.extend(self.line_done(&line));
+                }
+                _ => {
+                    self.after_cr = false;
+                    self.line.push(byte);
+                }
+            }
+        }
+        events
+    }
+
+    /// Id a reconnect sends as `Last-Event-ID`
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// Reconnect delay the server asked for with `retry:`
+    pub fn retry(&self) -> Option<u32> {
+        self.retry
+    }
+
+    /// Drops a half received event, as a new connection starts over
+    pub fn reset(&mut self) {
+        self.line.clear();
+        self.after_cr = false;
+        self.started = false;
+        self.event.clear();
+        self.data.clear();
+        self.has_data = false;
+    }
+
+    fn line_done(&mut self, line: &[u8]) -> Option<NetHttpEvent> {
+        let mut line = String::from_utf8_lossy(line).into_owned();
+        if !self.started {
+            self.started = true;
+            if let Some(rest) = line.strip_prefix('\u{feff}') {
+                line = rest.to_string();
+            }
+        }
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<NetHttpEvent> {
+        let event = std::mem::take(&mut self.event);
+        if !std::mem::take(&mut self.has_data) {
+            return None;
+        }
+        Some(NetHttpEvent {
+            event: match event.is_empty() {
+                true => "message".to_string(),
+                false => event,
+            },
+            data: std::mem::take(&mut self.data),
+            id: self.last_event_id.clone(),
+        })
+    }
+}
@@ -1,72 +1,342 @@
-use url::Url;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use idna::AsciiDenyList;
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+use url::{Host, Position, Url};
 
-use crate::types::{AddressInfo, error::NetResultStatus};
+use crate::types::{AddressInfo, AddressTransport, config::NetProtocol, error::NetResultStatus};
 
 pub struct Utils;
 pub mod buffer;
+pub mod charset;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod event_stream;
+#[cfg(all(not(target_arch = "wasm32"), feature = "otel"))]
+pub mod otel;
+pub mod subscribers;
+
+/// Characters escaped in a single path segment; includes `/` and `%` unlike the URL path set,
+/// and `\`, which special schemes read as `/`
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'/')
+    .add(b'\\')
+    .add(b'%')
+    .add(b'[')
+    .add(b']')
+    .add(b'^')
+    .add(b'|');
+
+struct ParsedUrl {
+    url: Url,
+    /// `url_str` without userinfo
+    clean: String,
+    scope_id: Option<String>,
+    authorization: Option<String>,
+}
 
 impl Utils {
-    // This is now a "static" method on Utils
-    pub fn parse_ws_url(url_str: &str) -> Result<AddressInfo, NetResultStatus> {
-        let url = Url::parse(url_str).map_err(|_| NetResultStatus::InvalidUrl)?;
-        let is_tls = match url.scheme() {
-            "ws" => false,
-            "wss" => true,
-            _ => return Err(NetResultStatus::InvalidUrl),
+    /// Parses `url_str`, splitting off an IPv6 zone id (`[fe80::1%25eth0]`) which `Url` rejects
+    /// and any `user:pass@` credentials
+    fn parse_url(url_str: &str) -> Result<ParsedUrl, NetResultStatus> {
+        let clean = Utils::strip_userinfo(url_str);
+        let zone = Utils::authority_range(url_str).and_then(|(start, end)| {
+            let authority = &url_str[start..end];
+            let host = start + authority.rfind('@').map_or(0, |at| at + 1);
+            if !url_str[host..end].starts_with('[') {
+                return None;
+            }
+            let close = host + url_str[host..end].find(']')?;
+            let percent = host + url_str[host..close].find('%')?;
+            Some((percent, close))
+        });
+        let (url, scope_id) = match zone {
+            None => (
+                Url::parse(url_str).map_err(|_| NetResultStatus::InvalidUrl)?,
+                None,
+            ),
+            Some((percent, end)) => {
+                let raw_zone = &url_str[percent + 1..end];
+                let zone_id = raw_zone.strip_prefix("25").unwrap_or(raw_zone);
+                if zone_id.is_empty() {
+                    return Err(NetResultStatus::InvalidUrl);
+                }
+                let stripped = format!("{}{}", &url_str[..percent], &url_str[end..]);
+                let url = Url::parse(&stripped).map_err(|_| NetResultStatus::InvalidUrl)?;
+                (url, Some(zone_id.to_string()))
+            }
         };
-        let host = url
-            .host_str()
-            .ok_or_else(|| NetResultStatus::InvalidUrl)?
-            .to_string();
-        let port = url.port().unwrap_or_else(|| if is_tls { 443 } else { 80 });
-        Ok(AddressInfo {
-            host,
-            port,
-            is_tls,
-            url: url_str.to_string(),
+        let authorization = Utils::basic_authorization(&url);
+        // hyper and tungstenite only accept ASCII, so hand them the A-label form
+        let clean = match scope_id.is_none() && !clean.is_ascii() {
+            true => {
+                let mut ascii = url.clone();
+                let _ = ascii.set_username("");
+                let _ = ascii.set_password(None);
+                ascii.to_string()
+            }
+            false => clean,
+        };
+        Ok(ParsedUrl {
+            url,
+            clean,
+            scope_id,
+            authorization,
         })
     }
 
-    pub fn parse_tcp_url(url_str: &str) -> Result<AddressInfo, NetResultStatus> {
-        let url = Url::parse(url_str).map_err(|_| NetResultStatus::InvalidUrl)?;
-        let is_tls = match url.scheme() {
-            "tcp" => false,
-            "tls" | "tcp+tls" => true,
-            _ => return Err(NetResultStatus::InvalidUrl),
+    /// Byte range of the authority of `url_str`, between `://` and the first `/`, `?` or `#`
+    fn authority_range(url_str: &str) -> Option<(usize, usize)> {
+        let start = url_str.find("://")? + 3;
+        let rest = &url_str[start..];
+        let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        Some((start, start + end))
+    }
+
+    /// `url_str` without its `user:pass@` credentials
+    pub(crate) fn strip_userinfo(url_str: &str) -> String {
+        let Some((start, end)) = Utils::authority_range(url_str) else {
+            return url_str.to_string();
         };
-        let port = url.port().unwrap_or_else(|| if is_tls { 443 } else { 80 });
-        let host = url
-            .host_str()
-            .ok_or_else(|| NetResultStatus::InvalidUrl)?
-            .to_string();
+        match url_str[start..end].rfind('@') {
+            Some(at) => format!("{}{}", &url_str[..start], &url_str[start + at + 1..]),
+            None => url_str.to_string(),
+        }
+    }
+
+    /// `Authorization` header value for the credentials embedded in `url`, if any
+    fn basic_authorization(url: &Url) -> Option<String> {
+        if url.username().is_empty() && url.password().is_none() {
+            return None;
+        }
+        let decode = |s: &str| percent_decode_str(s).decode_utf8_lossy().into_owned();
+        let credentials = format!(
+            "{}:{}",
+            decode(url.username()),
+            decode(url.password().unwrap_or_default())
+        );
+        Some(format!("Basic {}", STANDARD.encode(credentials)))
+    }
+
+    /// Host used for DNS and SNI. Special schemes are already IDNA-mapped by `Url`, while
+    /// `tcp://`/`tls://` hosts arrive percent-encoded and are converted to A-labels here.
+    fn url_host(url: &Url) -> Result<String, NetResultStatus> {
+        match url.host() {
+            Some(Host::Domain(domain)) if domain.contains('%') || !domain.is_ascii() => {
+                let decoded: Vec<u8> = percent_decode_str(domain).collect();
+                idna::domain_to_ascii_cow(&decoded, AsciiDenyList::URL)
+                    .map(|ascii| ascii.into_owned())
+                    .map_err(|_| NetResultStatus::InvalidUrl)
+            }
+            Some(Host::Domain(domain)) => Ok(domain.to_string()),
+            Some(Host::Ipv4(ip)) => Ok(ip.to_string()),
+            Some(Host::Ipv6(ip)) => Ok(ip.to_string()),
+            None => Err(NetResultStatus::InvalidUrl),
+        }
+    }
+
+    fn to_address(parsed: ParsedUrl, is_tls: bool) -> Result<AddressInfo, NetResultStatus> {
+        let host = Utils::url_host(&parsed.url)?;
+        let port = parsed
+            .url
+            .port()
+            .unwrap_or(if is_tls { 443 } else { 80 });
         Ok(AddressInfo {
+            transport: AddressTransport::Tcp,
             host,
             port,
             is_tls,
-            url: url_str.to_string(),
+            url: parsed.clean,
+            scope_id: parsed.scope_id,
+            authorization: parsed.authorization,
+            path: None,
         })
     }
 
+    // This is now a "static" method on Utils
+    pub fn parse_ws_url(url_str: &str) -> Result<AddressInfo, NetResultStatus> {
+        let parsed = Utils::parse_url(url_str)?;
+        let is_tls = match parsed.url.scheme() {
+            "ws" => false,
+            "wss" => true,
+            _ => return Err(NetResultStatus::UnsupportedScheme),
+        };
+        Utils::to_address(parsed, is_tls)
+    }
+
+    /// Accepts `tcp://`, `tls://`/`tcp+tls://`, `udp://host:port` and `unix:///path/to.sock`
+    pub fn parse_tcp_url(url_str: &str) -> Result<AddressInfo, NetResultStatus> {
+        let parsed = Utils::parse_url(url_str)?;
+        match parsed.url.scheme() {
+            "tcp" => Utils::to_address(parsed, false),
+            "tls" | "tcp+tls" => Utils::to_address(parsed, true),
+            "udp" => {
+                // there is no sensible default port for datagram endpoints
+                if parsed.url.port().is_none() {
+                    return Err(NetResultStatus::InvalidUrl);
+                }
+                let mut addr = Utils::to_address(parsed, false)?;
+                addr.transport = AddressTransport::Udp;
+                Ok(addr)
+            }
+            "unix" => {
+                if parsed.url.has_host() || parsed.url.port().is_some() {
+                    return Err(NetResultStatus::InvalidUrl);
+                }
+                let path = percent_decode_str(parsed.url.path())
+                    .decode_utf8()
+                    .map_err(|_| NetResultStatus::InvalidUrl)?;
+                if path.is_empty() || path == "/" {
+                    return Err(NetResultStatus::InvalidUrl);
+                }
+                Ok(AddressInfo {
+                    transport: AddressTransport::Unix,
+                    host: String::new(),
+                    port: 0,
+                    is_tls: false,
+                    url: parsed.clean,
+                    scope_id: None,
+                    authorization: None,
+                    path: Some(path.into_owned()),
+                })
+            }
+            _ => Err(NetResultStatus::UnsupportedScheme),
+        }
+    }
+
     pub fn parse_http_url(url_str: &str) -> Result<AddressInfo, NetResultStatus> {
-        let url = Url::parse(url_str).map_err(|_| NetResultStatus::InvalidUrl)?;
-        let is_tls = match url.scheme() {
+        let parsed = Utils::parse_url(url_str)?;
+        let is_tls = match parsed.url.scheme() {
             "http" => false,
             "https" => true,
+            #[cfg(unix)]
+            "unix" => return Utils::unix_http_address(parsed),
+            _ => return Err(NetResultStatus::UnsupportedScheme),
+        };
+        Utils::to_address(parsed, is_tls)
+    }
+
+    /// `unix://` http address, the socket file percent-encoded in place of the host so the
+    /// path is left for the request: `unix://%2Fvar%2Frun%2Fnode.sock/v1/status`. The hex
+    /// form of `unix_http_uri` is read too
+    #[cfg(unix)]
+    fn unix_http_address(parsed: ParsedUrl) -> Result<AddressInfo, NetResultStatus> {
+        let host = match parsed.url.host_str() {
+            Some(host) if parsed.url.port().is_none() => host,
             _ => return Err(NetResultStatus::InvalidUrl),
         };
-        let port = url.port().unwrap_or_else(|| if is_tls { 443 } else { 80 });
-        let host = url
-            .host_str()
-            .ok_or_else(|| NetResultStatus::InvalidUrl)?
-            .to_string();
+        let path = match percent_decode_str(host).decode_utf8() {
+            Ok(path) if path.starts_with('/') => path.into_owned(),
+            _ => Utils::decode_hex(host)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .filter(|path| path.starts_with('/'))
+                .ok_or(NetResultStatus::InvalidUrl)?,
+        };
         Ok(AddressInfo {
-            host,
-            port,
-            is_tls,
-            url: url_str.to_string(),
+            transport: AddressTransport::Unix,
+            host: String::new(),
+            port: 0,
+            is_tls: false,
+            url: parsed.clean,
+            scope_id: None,
+            authorization: parsed.authorization,
+            path: Some(path),
         })
     }
 
+    /// The url hyper is handed for a `unix://` http address. `Uri` takes no `%` in the
+    /// authority, so the socket file goes there hex-encoded instead, as `hyperlocal` does.
+    /// `None` for any other address
+    pub(crate) fn unix_http_uri(addr: &AddressInfo) -> Option<String> {
+        if addr.transport != AddressTransport::Unix {
+            return None;
+        }
+        let socket = addr.path.as_ref()?;
+        let url = Url::parse(&addr.url).ok()?;
+        let hex: String = socket.bytes().map(|b| format!("{:02x}", b)).collect();
+        Some(format!("unix://{}{}", hex, &url[Position::BeforePath..]))
+    }
+
+    #[cfg(unix)]
+    fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+        if !hex.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+
+    /// Runs the parser a transport of `protocol` would use for `url_str`
+    pub fn parse_protocol_url(
+        url_str: &str,
+        protocol: NetProtocol,
+    ) -> Result<AddressInfo, NetResultStatus> {
+        match protocol {
+            NetProtocol::Http => Utils::parse_http_url(url_str),
+            NetProtocol::Grpc => Utils::parse_http_url(url_str),
+            NetProtocol::WebSocket => Utils::parse_ws_url(url_str),
+            NetProtocol::Socket => Utils::parse_tcp_url(url_str),
+        }
+    }
+
+    /// Sets `params` on the query of `url_str`, replacing existing values of the same keys
+    pub fn merge_query_params(
+        url_str: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String, NetResultStatus> {
+        let mut url = Url::parse(url_str).map_err(|_| NetResultStatus::InvalidUrl)?;
+        let kept: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(k, _)| !params.iter().any(|(key, _)| key == k))
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        url.query_pairs_mut()
+            .clear()
+            .extend_pairs(kept)
+            .extend_pairs(params);
+        if url.query() == Some("") {
+            url.set_query(None);
+        }
+        Ok(url.to_string())
+    }
+
+    /// Percent-encodes `segment` so it can be placed between two `/` of a path
+    pub fn encode_path_segment(segment: &str) -> String {
+        utf8_percent_encode(segment, PATH_SEGMENT).to_string()
+    }
+
+    /// Resolves `reference` (absolute, `/path` or relative) against `base`
+    pub fn resolve_url(base: &str, reference: &str) -> Result<String, NetResultStatus> {
+        let base = Url::parse(base).map_err(|_| NetResultStatus::InvalidUrl)?;
+        base.join(reference)
+            .map(|url| url.to_string())
+            .map_err(|_| NetResultStatus::InvalidUrl)
+    }
+
+    /// Moves `url` from the origin of `from` to the origin of `to`; path and query are kept.
+    /// urls on any other origin are returned unchanged
+    pub fn rebase_url(url: &str, from: &str, to: &str) -> Result<String, NetResultStatus> {
+        let parse = |url: &str| Url::parse(url).map_err(|_| NetResultStatus::InvalidUrl);
+        let (mut url, from, to) = (parse(url)?, parse(from)?, parse(to)?);
+        if url.origin() != from.origin() {
+            return Ok(url.to_string());
+        }
+        url.set_scheme(to.scheme())
+            .and_then(|_| url.set_host(to.host_str()).map_err(|_| ()))
+            .and_then(|_| url.set_port(to.port()))
+            .map_err(|_| NetResultStatus::InvalidUrl)?;
+        Ok(url.to_string())
+    }
+
     pub unsafe fn cstr_to_string(ptr: *const u8) -> String {
         if ptr.is_null() {
             return String::new();
@@ -95,3 +365,30 @@ impl Utils {
         std::str::from_utf8(unsafe { Utils::cstr_to_slice(ptr) }).unwrap_or("")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone_id_is_only_read_from_a_bracketed_host() {
+        let url = "https://api.example.com/items?filter[name%20eq]=x";
+        let addr = Utils::parse_http_url(url).unwrap();
+        assert_eq!(addr.scope_id, None);
+        assert_eq!(addr.host, "api.example.com");
+        assert_eq!(addr.url, url);
+
+        let addr = Utils::parse_http_url("http://[fe80::1%25eth0]:8080/a?b[c%20d]=e").unwrap();
+        assert_eq!(addr.scope_id.as_deref(), Some("eth0"));
+        assert_eq!(addr.host, "fe80::1");
+        assert_eq!(addr.port, 8080);
+    }
+
+    #[test]
+    fn encoded_path_segment_stays_one_segment() {
+        let segment = Utils::encode_path_segment("a\\b/c d%[e]^|");
+        assert_eq!(segment, "a%5Cb%2Fc%20d%25%5Be%5D%5E%7C");
+        let url = Url::parse(&format!("https://example.com/{}", segment)).unwrap();
+        assert_eq!(url.path_segments().unwrap().count(), 1);
+    }
+}
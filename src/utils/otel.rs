@@ -0,0 +1,475 @@
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use log::debug;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::{sync::Notify, task::JoinHandle, time::sleep};
+
+use crate::{
+    client::native::IHttpClient,
+    transport::native::http::HttpTransport,
+    types::{
+        builder::NetConfigBuilder,
+        config::{NetConfig, NetConfigOtel, NetMode, NetProtocol},
+        error::NetError,
+        native::request::{NetHttpRetryConfig, NetRequest, NetRequestGrpc, NetRequestKind},
+        response::{NetResponseKind, NetResponseStream},
+    },
+    utils::buffer::StreamEncoding,
+};
+
+const DEFAULT_SERVICE_NAME: &str = "net_sdk";
+
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Spans per export; reaching it wakes the exporter before the interval is up
+const BATCH_SIZE: usize = 256;
+
+/// Spans kept while the collector can not be reached, newer ones are dropped
+const MAX_PENDING: usize = 2048;
+
+/// `SPAN_KIND_CLIENT`
+const KIND_CLIENT: u8 = 3;
+
+const STATUS_UNSET: u8 = 0;
+
+const STATUS_ERROR: u8 = 2;
+
+static EXPORTER: Lazy<RwLock<Option<Arc<OtelExporter>>>> = Lazy::new(|| RwLock::new(None));
+
+tokio::task_local! {
+    /// Span of the request being handled, which the clients propagate as `traceparent`
+    static CURRENT: Option<OtelSpanContext>;
+}
+
+/// Ids of a span, as W3C trace context carries them
+#[derive(Clone, Copy)]
+pub struct OtelSpanContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+}
+
+impl OtelSpanContext {
+    /// The `traceparent` header value, always sampled
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-01",
+            OtelSpan::hex(&self.trace_id),
+            OtelSpan::hex(&self.span_id)
+        )
+    }
+}
+
+/// Span of one request or stream, exported when dropped.
+///
+/// Spans are only recorded while an exporter is configured; otherwise every call is a no-op.
+/// A span started inside `scope` of another is its child.
+pub struct OtelSpan {
+    context: Option<OtelSpanContext>,
+    span: Option<OtlpSpan>,
+}
+
+impl OtelSpan {
+    pub fn start(name: impl Into<String>) -> Self {
+        if EXPORTER.read().map_or(true, |exporter| exporter.is_none()) {
+            return Self {
+                context: None,
+                span: None,
+            };
+        }
+        let parent = CURRENT.try_with(|current| *current).ok().flatten();
+        let mut ids = [0u8; 24];
+        let provider = rustls::crypto::ring::default_provider();
+        if provider.secure_random.fill(&mut ids).is_err() {
+            let now = OtelSpan::now().to_be_bytes();
+            ids[..16].copy_from_slice(&now);
+            ids[16..].copy_from_slice(&now[8..]);
+        }
+        let mut context = OtelSpanContext {
+            trace_id: [0; 16],
+            span_id: [0; 8],
+        };
+        context.trace_id.copy_from_slice(&ids[..16]);
+        context.span_id.copy_from_slice(&ids[16..]);
+        if let Some(parent) = &parent {
+            context.trace_id = parent.trace_id;
+        }
+        Self {
+            context: Some(context),
+            span: Some(OtlpSpan {
+                trace_id: OtelSpan::hex(&context.trace_id),
+                span_id: OtelSpan::hex(&context.span_id),
+                parent_span_id: parent.map(|parent| OtelSpan::hex(&parent.span_id)),
+                name: name.into(),
+                kind: KIND_CLIENT,
+                start_time_unix_nano: OtelSpan::now().to_string(),
+                end_time_unix_nano: String::new(),
+                attributes: Vec::new(),
+                status: OtlpStatus {
+                    code: STATUS_UNSET,
+                    message: None,
+                },
+            }),
+        }
+    }
+
+    /// Span of a request to a transport, named and described after OpenTelemetry's semantic
+    /// conventions. Urls are recorded without their query, which may carry credentials
+    pub fn request(request: &NetRequest<'_>, config: &NetConfig) -> Self {
+        let name = match &request.kind {
+            NetRequestKind::Http(http) => http.method,
+            NetRequestKind::Grpc(NetRequestGrpc::Unary(unary)) => unary.method,
+            NetRequestKind::Grpc(NetRequestGrpc::Stream(stream)) => stream.method,
+            NetRequestKind::Grpc(NetRequestGrpc::Health(health)) => health.method(),
+            _ => OtelSpan::protocol(config),
+        };
+        let mut span = OtelSpan::start(name.trim_start_matches('/'));
+        if span.span.is_none() {
+            return span;
+        }
+        span.server(config);
+        span.int("net_sdk.request_id", request.id.into());
+        let url = match &request.kind {
+            NetRequestKind::Http(http) => {
+                span.string("http.request.method", http.method);
+                http.url
+            }
+            _ => config.addr.url.as_str(),
+        };
+        span.string("url.full", url.split('?').next().unwrap_or_default());
+        if let NetRequestKind::Grpc(grpc) = &request.kind {
+            span.string("rpc.system", "grpc");
+            match grpc {
+                NetRequestGrpc::Unary(unary) => span.string("rpc.method", unary.method),
+                NetRequestGrpc::Stream(stream) => span.string("rpc.method", stream.method),
+                NetRequestGrpc::Health(health) => span.string("rpc.method", health.method()),
+                NetRequestGrpc::Unsubscribe(_)
+                | NetRequestGrpc::Credit(_)
+                | NetRequestGrpc::State
+                | NetRequestGrpc::Stats
+                | NetRequestGrpc::Rebuild(_) => {}
+            }
+        }
+        span
+    }
+
+    /// Span of a socket subscription or the gRPC stream of `method`, from subscribing until
+    /// it ends
+    pub fn stream(method: Option<&str>, config: &NetConfig, id: Option<i32>) -> Self {
+        let name = method.unwrap_or(OtelSpan::protocol(config));
+        let mut span = OtelSpan::start(format!("{} stream", name.trim_start_matches('/')));
+        span.server(config);
+        if let Some(id) = id {
+            span.int("net_sdk.subscription_id", id.into());
+        }
+        span
+    }
+
+    /// Records how the request or stream ended; an http status of 400 or more is an error
+    pub fn response(&mut self, response: &NetResponseKind) {
+        match response {
+            NetResponseKind::Http(http) => {
+                self.int("http.response.status_code", http.status_code().into());
+                if http.status_code() >= 400 {
+                    self.status(None);
+                }
+            }
+            NetResponseKind::ResponseError(e) => self.fail(e),
+            NetResponseKind::Stream(NetResponseStream::Error(e)) => self.fail(e.error()),
+            _ => {}
+        }
+    }
+
+    pub fn fail(&mut self, error: &NetError) {
+        self.string("error.type", error.status().to_string());
+        self.status(Some(error.to_string()));
+    }
+
+    pub fn string(&mut self, key: &str, value: impl Into<String>) {
+        self.attribute(key, OtlpValue::StringValue(value.into()));
+    }
+
+    pub fn int(&mut self, key: &str, value: i64) {
+        self.attribute(key, OtlpValue::IntValue(value.to_string()));
+    }
+
+    /// Adds one to the int attribute `key`
+    pub fn count(&mut self, key: &str) {
+        let Some(span) = &mut self.span else {
+            return;
+        };
+        let count = span
+            .attributes
+            .iter()
+            .find_map(|attribute| match &attribute.value {
+                OtlpValue::IntValue(count) if attribute.key == key => count.parse::<i64>().ok(),
+                _ => None,
+            })
+            .unwrap_or_default();
+        self.int(key, count + 1);
+    }
+
+    pub fn context(&self) -> Option<OtelSpanContext> {
+        self.context
+    }
+
+    /// Runs `future` with this span as the parent of the spans and `traceparent` headers it
+    /// creates
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        CURRENT.scope(self.context(), future).await
+    }
+
+    /// `traceparent` of the span in scope, for the clients to send along
+    pub fn traceparent() -> Option<String> {
+        CURRENT
+            .try_with(|current| current.as_ref().map(OtelSpanContext::traceparent))
+            .ok()
+            .flatten()
+    }
+
+    fn server(&mut self, config: &NetConfig) {
+        self.string("network.protocol.name", OtelSpan::protocol(config));
+        self.string("server.address", config.addr.host.as_str());
+    }
+
+    fn protocol(config: &NetConfig) -> &'static str {
+        match config.protocol {
+            NetProtocol::Http => "http",
+            NetProtocol::Grpc => "grpc",
+            NetProtocol::WebSocket => "websocket",
+            NetProtocol::Socket => "socket",
+        }
+    }
+
+    fn status(&mut self, message: Option<String>) {
+        if let Some(span) = &mut self.span {
+            span.status = OtlpStatus {
+                code: STATUS_ERROR,
+                message,
+            };
+        }
+    }
+
+    fn attribute(&mut self, key: &str, value: OtlpValue) {
+        let Some(span) = &mut self.span else {
+            return;
+        };
+        match span
+            .attributes
+            .iter_mut()
+            .find(|attribute| attribute.key == key)
+        {
+            Some(attribute) => attribute.value = value,
+            None => span.attributes.push(OtlpAttribute {
+                key: key.to_string(),
+                value,
+            }),
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn now() -> u128 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_nanos())
+    }
+}
+
+impl Drop for OtelSpan {
+    fn drop(&mut self) {
+        let Some(mut span) = self.span.take() else {
+            return;
+        };
+        span.end_time_unix_nano = OtelSpan::now().to_string();
+        if let Some(exporter) = OtelExporter::current() {
+            exporter.push(span);
+        }
+    }
+}
+
+/// Batches finished spans and posts them to `NetConfigOtel::endpoint` as OTLP/HTTP JSON.
+///
+/// Exports run every few seconds, or sooner once a batch is full. Failed exports are not
+/// retried, so a collector that is down loses what was sent meanwhile.
+pub struct OtelExporter {
+    client: Box<dyn IHttpClient>,
+    endpoint: String,
+    service_name: String,
+    pending: Mutex<Vec<OtlpSpan>>,
+    wake: Notify,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl OtelExporter {
+    /// Replaces the exporter, or stops exporting with `None`; spans the old one did not
+    /// export yet are dropped. Must be called within the runtime
+    pub fn configure(config: Option<NetConfigOtel>) -> Result<(), NetError> {
+        let exporter = match config {
+            Some(config) => {
+                let client = config
+                    .headers
+                    .iter()
+                    .fold(
+                        NetConfigBuilder::new(config.endpoint.as_str())
+                            .protocol(NetProtocol::Http)
+                            .mode(NetMode::Clearnet)
+                            .header("content-type", "application/json"),
+                        |builder, header| builder.header(header.key(), header.value()),
+                    )
+                    .build_config()
+                    .and_then(HttpTransport::create_client)?;
+                let exporter = Arc::new(Self {
+                    client,
+                    endpoint: config.endpoint,
+                    service_name: config
+                        .service_name
+                        .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string()),
+                    pending: Mutex::new(Vec::new()),
+                    wake: Notify::new(),
+                    task: Mutex::new(None),
+                });
+                let task = tokio::spawn(OtelExporter::run(Arc::clone(&exporter)));
+                *exporter.task.lock().unwrap_or_else(|e| e.into_inner()) = Some(task);
+                Some(exporter)
+            }
+            None => None,
+        };
+        let previous = std::mem::replace(
+            &mut *EXPORTER.write().unwrap_or_else(|e| e.into_inner()),
+            exporter,
+        );
+        if let Some(previous) = previous
+            && let Some(task) = previous
+                .task
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take()
+        {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    fn current() -> Option<Arc<Self>> {
+        EXPORTER.read().ok()?.clone()
+    }
+
+    fn push(&self, span: OtlpSpan) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.len() >= MAX_PENDING {
+            return;
+        }
+        pending.push(span);
+        if pending.len() >= BATCH_SIZE {
+            self.wake.notify_one();
+        }
+    }
+
+    async fn run(self: Arc<Self>) {
+        loop {
+            tokio::select! {
+                _ = sleep(EXPORT_INTERVAL) => {}
+                _ = self.wake.notified() => {}
+            }
+            loop {
+                let batch: Vec<OtlpSpan> = {
+                    let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+                    let len = pending.len().min(BATCH_SIZE);
+                    pending.drain(..len).collect()
+                };
+                if batch.is_empty() {
+                    break;
+                }
+                if let Err(e) = self.export(batch).await {
+                    debug!("otel export to {} failed: {}", self.endpoint, e);
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn export(&self, spans: Vec<OtlpSpan>) -> Result<(), NetError> {
+        let body = serde_json::json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        OtlpAttribute {
+                            key: "service.name".to_string(),
+                            value: OtlpValue::StringValue(self.service_name.clone()),
+                        },
+                        OtlpAttribute {
+                            key: "telemetry.sdk.name".to_string(),
+                            value: OtlpValue::StringValue(DEFAULT_SERVICE_NAME.to_string()),
+                        },
+                    ],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": DEFAULT_SERVICE_NAME, "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }],
+            }],
+        });
+        let response = self
+            .client
+            .send(
+                &self.endpoint,
+                "POST",
+                Some(Bytes::from(body.to_string())),
+                None,
+                StreamEncoding::Raw,
+                &NetHttpRetryConfig::default(),
+            )
+            .await?;
+        if response.status_code() >= 300 {
+            debug!(
+                "otel collector {} answered {}",
+                self.endpoint,
+                response.status_code()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A span as OTLP JSON encodes it: ids in hex, 64 bit integers as strings
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpSpan {
+    trace_id: String,
+    span_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_span_id: Option<String>,
+    name: String,
+    kind: u8,
+    start_time_unix_nano: String,
+    end_time_unix_nano: String,
+    attributes: Vec<OtlpAttribute>,
+    status: OtlpStatus,
+}
+
+#[derive(Serialize)]
+struct OtlpAttribute {
+    key: String,
+    value: OtlpValue,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum OtlpValue {
+    StringValue(String),
+    IntValue(String),
+}
+
+#[derive(Serialize)]
+struct OtlpStatus {
+    code: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
@@ -0,0 +1,133 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+
+use crate::types::config::{NetStreamOverflow, NetStreamQueue};
+
+const DEFAULT_CAPACITY: usize = 128;
+
+enum Queued<T> {
+    Frame(T),
+    /// Frames dropped for this subscriber since the previous marker
+    Lost(u64),
+}
+
+struct SubscriberQueue<T> {
+    sender: mpsc::Sender<Queued<T>>,
+    /// Dropped frames not reported yet, `Drop` only
+    lost: u64,
+}
+
+/// Incoming frames of one connection, copied into a bounded queue per subscriber.
+///
+/// Unlike a broadcast channel a slow subscriber never loses frames silently; what happens
+/// when its queue is full is up to `NetStreamQueue::overflow`. A subscriber leaves when its
+/// `NetSubscription` is dropped.
+pub struct StreamSubscribers<T> {
+    queues: Mutex<Vec<SubscriberQueue<T>>>,
+    capacity: usize,
+    overflow: NetStreamOverflow,
+}
+
+impl<T: Clone + Send> StreamSubscribers<T> {
+    pub fn new(config: &NetStreamQueue) -> Self {
+        Self {
+            queues: Mutex::new(Vec::new()),
+            capacity: match config.capacity {
+                0 => DEFAULT_CAPACITY,
+                capacity => capacity as usize,
+            },
+            overflow: config.overflow,
+        }
+    }
+
+    /// Receives every frame sent from now on
+    pub fn subscribe(&self) -> NetSubscription<T> {
+        // one slot more for the marker reporting an overflow
+        let slots = match self.overflow {
+            NetStreamOverflow::Block => self.capacity,
+            _ => self.capacity + 1,
+        };
+        let (sender, receiver) = mpsc::channel(slots);
+        self.lock().push(SubscriberQueue { sender, lost: 0 });
+        NetSubscription { receiver }
+    }
+
+    /// Waits for room in every queue under `Block`; never waits otherwise
+    pub async fn send(&self, frame: T) {
+        if self.overflow != NetStreamOverflow::Block {
+            let overflow = self.overflow;
+            self.lock()
+                .retain_mut(|queue| queue.offer(overflow, frame.clone()));
+            return;
+        }
+        let senders: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|queue| queue.sender.clone())
+            .collect();
+        for sender in senders {
+            if sender.send(Queued::Frame(frame.clone())).await.is_err() {
+                self.lock()
+                    .retain(|queue| !queue.sender.same_channel(&sender));
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<SubscriberQueue<T>>> {
+        self.queues.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> SubscriberQueue<T> {
+    /// Queues `frame` without waiting; false once the subscriber is gone
+    fn offer(&mut self, overflow: NetStreamOverflow, frame: T) -> bool {
+        if self.sender.is_closed() {
+            return false;
+        }
+        if self.lost > 0 && self.sender.try_send(Queued::Lost(self.lost)).is_ok() {
+            self.lost = 0;
+        }
+        if self.lost == 0 && self.sender.capacity() > 1 {
+            let _ = self.sender.try_send(Queued::Frame(frame));
+            return true;
+        }
+        match overflow {
+            // the marker takes the spare slot, and dropping the sender closes the queue
+            NetStreamOverflow::Error => {
+                let _ = self.sender.try_send(Queued::Lost(1));
+                false
+            }
+            _ => {
+                self.lost += 1;
+                true
+            }
+        }
+    }
+}
+
+/// One subscriber's queue of a `StreamSubscribers`.
+///
+/// `recv` reports like a broadcast receiver: `Lagged` with the number of frames dropped at
+/// that point, `Closed` once the connection went away or an `Error` overflow ended it.
+pub struct NetSubscription<T> {
+    receiver: mpsc::Receiver<Queued<T>>,
+}
+
+impl<T> NetSubscription<T> {
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        match self.receiver.recv().await {
+            Some(Queued::Frame(frame)) => Ok(frame),
+            Some(Queued::Lost(lost)) => Err(RecvError::Lagged(lost)),
+            None => Err(RecvError::Closed),
+        }
+    }
+
+    /// `recv` giving up after `idle`; `None` when nothing arrived in time
+    pub async fn recv_within(&mut self, idle: Option<Duration>) -> Option<Result<T, RecvError>> {
+        match idle {
+            Some(idle) => tokio::time::timeout(idle, self.recv()).await.ok(),
+            None => Some(self.recv().await),
+        }
+    }
+}